@@ -0,0 +1,379 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat};
+use crossbeam_queue::ArrayQueue;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Extension of the chunk timing file saved next to a recording
+pub const ALIGNMENT_EXTENSION: &str = "align.json";
+
+/// When one captured buffer was recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkTiming {
+    /// First sample frame of the buffer, counted from the start of the capture
+    pub frame: u64,
+    /// Monotonic time from the start of the capture to the buffer's first
+    /// sample, in milliseconds
+    pub offset_ms: f64,
+}
+
+/// Capture timing of a recording, for aligning it with external events
+/// such as video or sensor logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureAlignment {
+    /// Wall-clock time of the first captured sample, in Unix milliseconds
+    pub capture_started_at_ms: i64,
+    /// Rate the frame numbers count at
+    pub sample_rate: u32,
+    pub chunks: Vec<ChunkTiming>,
+}
+
+impl CaptureAlignment {
+    pub fn sidecar_path(wav_path: &Path) -> PathBuf {
+        wav_path.with_extension(ALIGNMENT_EXTENSION)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("Failed to serialize capture timing")?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write capture timing: {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path)
+            .with_context(|| format!("Failed to read capture timing: {}", path.display()))?;
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Failed to parse capture timing: {}", path.display()))
+    }
+}
+
+/// Stamps kept on the ring between the audio callback and the recording
+/// loop; at 10 ms per callback, several seconds of a loop not reading them
+const STAMP_RING: usize = 512;
+
+/// Stamps the recording loop keeps once read, enough to cover a pre-roll
+const STAMPS_KEPT: usize = 1024;
+
+/// When an input stream's buffers were captured, as the audio callback saw
+/// them
+///
+/// The callback stamps each buffer with the capture time the host reports
+/// and hands the stamp over on a lock-free ring, so it never waits; the
+/// recording loop reads the stamps back through [`CaptureClock`]. Stamping
+/// in the loop instead would measure how long buffers sat in the queue.
+#[derive(Debug)]
+pub struct CaptureStamps {
+    sample_rate: u32,
+    /// Frames stamped so far
+    frames: AtomicU64,
+    /// First frame of each buffer, counted from the start of the stream,
+    /// and when it was captured
+    ring: ArrayQueue<(u64, Instant)>,
+}
+
+impl CaptureStamps {
+    /// Stamps of a stream delivering `sample_rate` frames a second
+    pub fn new(sample_rate: u32) -> Arc<Self> {
+        Arc::new(Self {
+            sample_rate,
+            frames: AtomicU64::new(0),
+            ring: ArrayQueue::new(STAMP_RING),
+        })
+    }
+
+    /// Note a buffer of `frames` frames whose first frame was captured `age`
+    /// ago; never waits, so it is safe to call from the audio callback
+    pub fn stamp(&self, frames: usize, age: Duration) {
+        let frame = self.frames.fetch_add(frames as u64, Ordering::Relaxed);
+        let now = Instant::now();
+        // With the ring full the stamp is skipped; later frames are timed
+        // from the stamps before it
+        let _ = self.ring.push((frame, now.checked_sub(age).unwrap_or(now)));
+    }
+
+    /// Note a cpal input buffer of `frames` frames, by the capture time in
+    /// its callback info
+    ///
+    /// Hosts that report no capture time before the callback get the
+    /// buffer's length instead: the callback runs once the last frame is in.
+    pub fn stamp_callback(&self, frames: usize, info: &cpal::InputCallbackInfo) {
+        let timestamp = info.timestamp();
+        let age = timestamp
+            .callback
+            .duration_since(&timestamp.capture)
+            .filter(|age| !age.is_zero())
+            .unwrap_or_else(|| frames_duration(frames as u64, self.sample_rate));
+        self.stamp(frames, age);
+    }
+}
+
+fn frames_duration(frames: u64, sample_rate: u32) -> Duration {
+    Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64)
+}
+
+/// The stream a [`CaptureClock`] takes its capture times from
+#[derive(Debug)]
+struct Stream {
+    stamps: Arc<CaptureStamps>,
+    /// Stamps read off the ring, oldest first
+    kept: VecDeque<(u64, Instant)>,
+    /// Frames received from the stream so far
+    received: u64,
+}
+
+impl Stream {
+    /// When frame `frame` of the stream was captured, timed from the last
+    /// stamp at or before it (or the first one, for frames before it)
+    fn captured_at(&mut self, frame: u64) -> Option<Instant> {
+        while let Some(stamp) = self.stamps.ring.pop() {
+            if self.kept.len() == STAMPS_KEPT {
+                self.kept.pop_front();
+            }
+            self.kept.push_back(stamp);
+        }
+        let &(stamped, at) = self
+            .kept
+            .iter()
+            .rev()
+            .find(|(stamped, _)| *stamped <= frame)
+            .or(self.kept.front())?;
+        let rate = self.stamps.sample_rate;
+        Some(if frame >= stamped {
+            at + frames_duration(frame - stamped, rate)
+        } else {
+            at.checked_sub(frames_duration(stamped - frame, rate))
+                .unwrap_or(at)
+        })
+    }
+}
+
+/// Timestamps captured buffers against the monotonic clock, anchored to the
+/// wall clock once at the start so clock adjustments mid-recording do not
+/// skew the offsets
+///
+/// Capture times come from the stream's [`CaptureStamps`]; without a
+/// stream to follow, a buffer counts as captured when it is pushed.
+#[derive(Debug)]
+pub struct CaptureClock {
+    sample_rate: u32,
+    /// Monotonic and wall-clock (Unix ms) time of the first captured sample
+    started: Option<(Instant, i64)>,
+    frames: u64,
+    chunks: Vec<ChunkTiming>,
+    stream: Option<Stream>,
+}
+
+impl CaptureClock {
+    /// Clock of a recording at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            started: None,
+            frames: 0,
+            chunks: Vec::new(),
+            stream: None,
+        }
+    }
+
+    /// Take capture times from `stamps`, e.g. once the input stream is
+    /// opened or reopened
+    pub fn follow(&mut self, stamps: Arc<CaptureStamps>) {
+        self.stream = Some(Stream {
+            stamps,
+            kept: VecDeque::new(),
+            received: 0,
+        });
+    }
+
+    /// Note a buffer of `frames` frames received from the stream, whether
+    /// it is recorded or not (e.g. while paused)
+    pub fn received(&mut self, frames: usize) {
+        if let Some(stream) = &mut self.stream {
+            stream.received += frames as u64;
+        }
+    }
+
+    /// Note the recording's next `frames` sample frames, which end `lag`
+    /// stream frames before the end of what was received (audio still held
+    /// back by a resampler)
+    pub fn push(&mut self, frames: usize, lag: u64) {
+        let length = frames_duration(frames as u64, self.sample_rate);
+        let captured = match &mut self.stream {
+            Some(stream) => {
+                let rate = stream.stamps.sample_rate as f64 / self.sample_rate.max(1) as f64;
+                let stream_frames = (frames as f64 * rate).round() as u64;
+                let first = stream.received.saturating_sub(lag + stream_frames);
+                stream.captured_at(first)
+            }
+            None => None,
+        };
+        // Arrived just now, once its last sample was captured
+        let captured = captured.unwrap_or_else(|| {
+            let now = Instant::now();
+            now.checked_sub(length).unwrap_or(now)
+        });
+        let (started, _) = *self.started.get_or_insert_with(|| {
+            let age = captured.elapsed();
+            let wall_ms = chrono::Utc::now().timestamp_millis() - age.as_millis() as i64;
+            (captured, wall_ms)
+        });
+
+        self.chunks.push(ChunkTiming {
+            frame: self.frames,
+            offset_ms: captured.saturating_duration_since(started).as_secs_f64() * 1000.0,
+        });
+        self.frames += frames as u64;
+    }
+
+    /// Timing of everything captured, or `None` if nothing was
+    pub fn finish(self) -> Option<CaptureAlignment> {
+        let (_, capture_started_at_ms) = self.started?;
+        Some(CaptureAlignment {
+            capture_started_at_ms,
+            sample_rate: self.sample_rate,
+            chunks: self.chunks,
+        })
+    }
+}
+
+/// Unix milliseconds as an RFC 3339 UTC timestamp with millisecond precision
+pub fn format_timestamp_ms(ms: i64) -> Option<String> {
+    DateTime::from_timestamp_millis(ms)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// Capture start (Unix ms) of every recording that has one, with the
+/// seconds since trimmed off the start of its audio
+pub async fn capture_starts(db: &SqlitePool) -> Result<HashMap<String, (i64, f64)>> {
+    let rows: Vec<(String, i64, f64)> = sqlx::query_as(
+        r#"
+        SELECT id, capture_started_at_ms,
+               COALESCE(auto_trim_start_secs, 0.0) + COALESCE(trim_start_secs, 0.0)
+        FROM recordings WHERE capture_started_at_ms IS NOT NULL
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch capture start times")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, started_at_ms, trimmed_secs)| (id, (started_at_ms, trimmed_secs)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stamps of `buffers` buffers of `frames` frames at `sample_rate`,
+    /// captured back to back from `start`
+    fn stamps(sample_rate: u32, frames: u64, buffers: u64, start: Instant) -> Arc<CaptureStamps> {
+        let stamps = CaptureStamps::new(sample_rate);
+        for buffer in 0..buffers {
+            let frame = buffer * frames;
+            let at = start + frames_duration(frame, sample_rate);
+            stamps.ring.push((frame, at)).unwrap();
+        }
+        stamps.frames.store(buffers * frames, Ordering::Relaxed);
+        stamps
+    }
+
+    fn offsets(alignment: &CaptureAlignment) -> Vec<(u64, f64)> {
+        alignment
+            .chunks
+            .iter()
+            .map(|chunk| (chunk.frame, (chunk.offset_ms * 1000.0).round() / 1000.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_clock_times_buffers_by_capture_not_arrival() {
+        // Captured half a second ago, read from the queue only now
+        let start = Instant::now() - Duration::from_millis(500);
+        let mut clock = CaptureClock::new(1000);
+        clock.follow(stamps(1000, 10, 3, start));
+        for _ in 0..3 {
+            clock.received(10);
+            clock.push(10, 0);
+        }
+
+        let alignment = clock.finish().unwrap();
+        assert_eq!(offsets(&alignment), vec![(0, 0.0), (10, 10.0), (20, 20.0)]);
+        let age_ms = chrono::Utc::now().timestamp_millis() - alignment.capture_started_at_ms;
+        assert!((500..1000).contains(&age_ms), "{age_ms}");
+    }
+
+    #[test]
+    fn test_clock_follows_resampling_pauses_and_held_back_audio() {
+        let start = Instant::now();
+        // A 2 kHz stream recorded at 1 kHz
+        let mut clock = CaptureClock::new(1000);
+        clock.follow(stamps(2000, 20, 6, start));
+
+        clock.received(20);
+        clock.push(10, 0);
+        // Dropped while paused
+        clock.received(20);
+        clock.received(20);
+        clock.push(10, 0);
+        // Two buffers in, the second held back by the resampler
+        clock.received(20);
+        clock.received(20);
+        clock.push(10, 20);
+
+        let alignment = clock.finish().unwrap();
+        assert_eq!(offsets(&alignment), vec![(0, 0.0), (10, 20.0), (20, 30.0)]);
+    }
+
+    #[test]
+    fn test_clock_extrapolates_between_and_before_stamps() {
+        let start = Instant::now();
+        let stamps = CaptureStamps::new(1000);
+        // Only every other buffer stamped, as with a full ring
+        stamps
+            .ring
+            .push((10, start + Duration::from_millis(10)))
+            .unwrap();
+        stamps
+            .ring
+            .push((30, start + Duration::from_millis(30)))
+            .unwrap();
+        let mut stream = Stream {
+            stamps,
+            kept: VecDeque::new(),
+            received: 0,
+        };
+
+        assert_eq!(
+            stream.captured_at(20),
+            Some(start + Duration::from_millis(20))
+        );
+        assert_eq!(
+            stream.captured_at(45),
+            Some(start + Duration::from_millis(45))
+        );
+        assert_eq!(stream.captured_at(0), Some(start));
+    }
+
+    #[test]
+    fn test_clock_without_stream_times_buffers_on_arrival() {
+        let mut clock = CaptureClock::new(1000);
+        assert!(CaptureClock::new(1000).finish().is_none());
+        clock.push(10, 0);
+        clock.push(10, 0);
+
+        let alignment = clock.finish().unwrap();
+        assert_eq!(alignment.chunks.len(), 2);
+        assert_eq!(alignment.chunks[1].frame, 10);
+        assert!(alignment.chunks[1].offset_ms >= 0.0);
+        let age_ms = chrono::Utc::now().timestamp_millis() - alignment.capture_started_at_ms;
+        assert!((0..1000).contains(&age_ms));
+    }
+}
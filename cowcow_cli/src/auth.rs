@@ -5,19 +5,22 @@ use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{error, info, warn};
 
-use crate::config::{Config, Credentials};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct LoginRequest {
-    pub username: String,
-    pub password: String,
-}
+use crate::campaigns::Campaign;
+use crate::config::{Config, Credentials, Scope};
+use crate::http;
+use crate::pair::{Enrollment, EnrollmentRequest};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub access_token: String,
     pub token_type: String,
-    pub api_key: String,
+    /// The account's API key; withheld from logins with restricted scopes
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Granted scopes, space separated (OAuth 2.0); absent on servers
+    /// without scoped tokens
+    #[serde(default)]
+    pub scope: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +53,18 @@ pub struct TokenTransaction {
     pub balance: u32,
     pub date: DateTime<Utc>,
     pub notes: String,
+    /// Recording the tokens were awarded for; absent on older servers
+    #[serde(default)]
+    pub recording_id: Option<String>,
+}
+
+/// A recording as the server has it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerRecording {
+    pub id: String,
+    /// `completed` once the server accepted the upload
+    #[serde(default)]
+    pub status: Option<String>,
 }
 
 pub struct AuthClient {
@@ -59,21 +74,27 @@ pub struct AuthClient {
 
 impl AuthClient {
     pub fn new(config: Config) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(config.api.timeout_secs))
-            .build()
-            .unwrap();
-
+        let client = http::shared_client(&config);
         Self { client, config }
     }
 
-    pub async fn login(&self, username: String, password: String) -> Result<Credentials> {
+    pub async fn login(
+        &self,
+        username: String,
+        password: String,
+        scopes: &[Scope],
+    ) -> Result<Credentials> {
         let login_url = format!("{}/auth/token", self.config.api.endpoint);
 
-        let form_data = [("username", username.clone()), ("password", password)];
+        let mut form_data = vec![("username", username.clone()), ("password", password)];
+        if !scopes.is_empty() {
+            let requested: Vec<&str> = scopes.iter().map(|scope| scope.as_str()).collect();
+            form_data.push(("scope", requested.join(" ")));
+        }
 
         info!("Attempting login for user: {}", username);
 
+        http::throttle(&self.config).await;
         let response = self
             .client
             .post(&login_url)
@@ -94,11 +115,19 @@ impl AuthClient {
                 .as_secs()
                 + (24 * 60 * 60); // 24 hours
 
+            let scopes = login_response.scope.map(|granted| {
+                granted
+                    .split_whitespace()
+                    .filter_map(|scope| scope.parse().ok())
+                    .collect()
+            });
+
             let credentials = Credentials {
                 access_token: Some(login_response.access_token),
-                api_key: Some(login_response.api_key),
+                api_key: login_response.api_key,
                 username: Some(username),
                 expires_at: Some(expires_at),
+                scopes,
             };
 
             credentials.save(&self.config)?;
@@ -126,6 +155,7 @@ impl AuthClient {
 
         info!("Attempting registration for user: {}", username);
 
+        http::throttle(&self.config).await;
         let response = self
             .client
             .post(&register_url)
@@ -169,13 +199,58 @@ impl AuthClient {
         ))
     }
 
+    /// Refuse `operation` unless valid credentials allow `scope`; without
+    /// them it is refused as well
+    pub fn require_scope(&self, scope: Scope, operation: &str) -> Result<()> {
+        self.check_scope(scope, operation, false)
+    }
+
+    /// Like [`AuthClient::require_scope`], but also allowing `operation`
+    /// without credentials, so a new install can be set up before logging
+    /// in. Scopes stored by an earlier login still restrict it, even once
+    /// expired or logged out.
+    pub fn require_scope_or_offline(&self, scope: Scope, operation: &str) -> Result<()> {
+        self.check_scope(scope, operation, true)
+    }
+
+    fn check_scope(&self, scope: Scope, operation: &str, offline_allowed: bool) -> Result<()> {
+        match Credentials::load(&self.config)? {
+            Some(credentials) if credentials.is_valid() => {
+                credentials.require_scope(scope, operation)
+            }
+            // An expired credential keeps its restrictions
+            Some(credentials) if !credentials.has_scope(scope) => {
+                credentials.require_scope(scope, operation)
+            }
+            _ if offline_allowed => Ok(()),
+            _ => Err(anyhow::anyhow!(
+                "{} needs the {} scope; log in first: cowcow auth login --scope {}",
+                operation,
+                scope,
+                scope
+            )),
+        }
+    }
+
+    /// Forget the stored tokens; a restricted device keeps its scopes, so
+    /// logging out never lifts them
     pub async fn logout(&self) -> Result<()> {
-        Credentials::clear(&self.config)?;
+        match Credentials::load(&self.config)? {
+            Some(credentials) if credentials.scopes.is_some() => Credentials {
+                access_token: None,
+                api_key: None,
+                expires_at: None,
+                ..credentials
+            }
+            .save(&self.config)?,
+            _ => Credentials::clear(&self.config)?,
+        }
         info!("Logged out successfully");
         Ok(())
     }
 
     pub async fn health_check(&self) -> Result<()> {
+        http::throttle(&self.config).await;
         let response = self
             .client
             .get(format!("{}/health", self.config.api.endpoint))
@@ -195,6 +270,7 @@ impl AuthClient {
     pub async fn get_token_balance(&self) -> Result<TokenBalance> {
         let credentials = self.check_auth().await?;
 
+        http::throttle(&self.config).await;
         let response = self
             .client
             .get(format!("{}/tokens/balance", self.config.api.endpoint))
@@ -218,6 +294,7 @@ impl AuthClient {
     pub async fn get_token_history(&self, days: u32) -> Result<Vec<TokenTransaction>> {
         let credentials = self.check_auth().await?;
 
+        http::throttle(&self.config).await;
         let response = self
             .client
             .get(format!("{}/tokens/history", self.config.api.endpoint))
@@ -238,6 +315,133 @@ impl AuthClient {
             Err(anyhow::anyhow!("Failed to get token history"))
         }
     }
+
+    /// Recordings the server holds for the account
+    pub async fn get_recordings(&self) -> Result<Vec<ServerRecording>> {
+        let credentials = self.check_auth().await?;
+
+        http::throttle(&self.config).await;
+        let response = self
+            .client
+            .get(format!("{}/recordings", self.config.api.endpoint))
+            .bearer_auth(credentials.access_token.context("No access token")?)
+            .send()
+            .await
+            .context("Failed to get recordings")?;
+
+        if response.status().is_success() {
+            let recordings = response
+                .json::<Vec<ServerRecording>>()
+                .await
+                .context("Failed to parse recordings response")?;
+            Ok(recordings)
+        } else {
+            error!("Failed to get recordings: {}", response.status());
+            Err(anyhow::anyhow!("Failed to get recordings"))
+        }
+    }
+
+    pub async fn get_campaigns(&self) -> Result<Vec<Campaign>> {
+        let credentials = self.check_auth().await?;
+
+        http::throttle(&self.config).await;
+        let response = self
+            .client
+            .get(format!("{}/campaigns", self.config.api.endpoint))
+            .bearer_auth(credentials.access_token.context("No access token")?)
+            .send()
+            .await
+            .context("Failed to get campaigns")?;
+
+        if response.status().is_success() {
+            let campaigns = response
+                .json::<Vec<Campaign>>()
+                .await
+                .context("Failed to parse campaigns response")?;
+            Ok(campaigns)
+        } else {
+            error!("Failed to get campaigns: {}", response.status());
+            Err(anyhow::anyhow!("Failed to get campaigns"))
+        }
+    }
+
+    /// Issue a single-use token a companion app can redeem for credentials
+    pub async fn create_enrollment(&self, request: &EnrollmentRequest) -> Result<Enrollment> {
+        let credentials = self.check_auth().await?;
+
+        http::throttle(&self.config).await;
+        let response = self
+            .client
+            .post(format!("{}/auth/enrollments", self.config.api.endpoint))
+            .bearer_auth(credentials.access_token.context("No access token")?)
+            .json(request)
+            .send()
+            .await
+            .context("Failed to request enrollment token")?;
+
+        if response.status().is_success() {
+            response
+                .json::<Enrollment>()
+                .await
+                .context("Failed to parse enrollment response")
+        } else {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Failed to create enrollment: {} {}", status, error_text);
+            Err(anyhow::anyhow!("Failed to create enrollment: {status}"))
+        }
+    }
+
+    /// Enrollments issued by this account, with the devices that redeemed them
+    pub async fn get_enrollments(&self) -> Result<Vec<Enrollment>> {
+        let credentials = self.check_auth().await?;
+
+        http::throttle(&self.config).await;
+        let response = self
+            .client
+            .get(format!("{}/auth/enrollments", self.config.api.endpoint))
+            .bearer_auth(credentials.access_token.context("No access token")?)
+            .send()
+            .await
+            .context("Failed to get enrollments")?;
+
+        if response.status().is_success() {
+            response
+                .json::<Vec<Enrollment>>()
+                .await
+                .context("Failed to parse enrollments response")
+        } else {
+            error!("Failed to get enrollments: {}", response.status());
+            Err(anyhow::anyhow!("Failed to get enrollments"))
+        }
+    }
+
+    /// Revoke an enrollment and the credentials of the device that redeemed it
+    pub async fn revoke_enrollment(&self, id: &str) -> Result<()> {
+        let credentials = self.check_auth().await?;
+
+        http::throttle(&self.config).await;
+        let response = self
+            .client
+            .delete(format!(
+                "{}/auth/enrollments/{id}",
+                self.config.api.endpoint
+            ))
+            .bearer_auth(credentials.access_token.context("No access token")?)
+            .send()
+            .await
+            .context("Failed to revoke enrollment")?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            error!("Failed to revoke enrollment: {}", response.status());
+            Err(anyhow::anyhow!(
+                "Failed to revoke enrollment: {}",
+                response.status()
+            ))
+        }
+    }
 }
 
 pub fn prompt_for_credentials() -> Result<(String, String)> {
@@ -251,11 +455,24 @@ pub fn prompt_for_credentials() -> Result<(String, String)> {
 
     print!("Password: ");
     io::stdout().flush()?;
-    let password = rpassword::read_password()?;
+    let password = read_password()?;
 
     Ok((username, password))
 }
 
+/// Read a password without echo, or as the next line of input when stdin
+/// is not a terminal (scripts and tests piping credentials in)
+fn read_password() -> Result<String> {
+    use std::io::{self, IsTerminal};
+
+    if io::stdin().is_terminal() {
+        return Ok(rpassword::read_password()?);
+    }
+    let mut password = String::new();
+    io::stdin().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
 pub fn prompt_for_registration() -> Result<(String, String, String)> {
     use std::io::{self, Write};
 
@@ -273,7 +490,7 @@ pub fn prompt_for_registration() -> Result<(String, String, String)> {
 
     print!("Password: ");
     io::stdout().flush()?;
-    let password = rpassword::read_password()?;
+    let password = read_password()?;
 
     Ok((username, email, password))
 }
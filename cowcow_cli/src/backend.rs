@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use cpal::traits::StreamTrait;
+use std::f32::consts::PI;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::alignment::CaptureStamps;
+use crate::spill::{SpillConfig, SpillQueue, SpillStats};
+
+/// Length of each buffer the synthetic backend delivers
+const SYNTHETIC_BUFFER: Duration = Duration::from_millis(20);
+
+/// Level of the background noise under the synthetic speech and tone, so
+/// SNR and noise floor estimates have something to measure
+const NOISE_FLOOR: f32 = 0.002;
+
+/// Where recordings take their audio from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AudioBackend {
+    /// The input device, through cpal
+    #[default]
+    Device,
+    /// A generated test signal, for running the record pipeline without a
+    /// microphone (CI, demos)
+    Synthetic(Signal),
+}
+
+impl FromStr for AudioBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "device" | "cpal" => Ok(AudioBackend::Device),
+            "synthetic" => Ok(AudioBackend::Synthetic(Signal::default())),
+            _ => Err(format!(
+                "Unknown audio backend: {s} (use device or synthetic)"
+            )),
+        }
+    }
+}
+
+/// Test signal of the synthetic backend
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Signal {
+    /// Syllable-like bursts of a voiced tone, which passes speech QC
+    #[default]
+    Speech,
+    /// A steady sine tone
+    Sine { freq_hz: f32 },
+    /// White noise
+    Noise,
+    /// Nothing but the noise floor
+    Silence,
+    /// An audio file played once, then silence
+    Fixture(PathBuf),
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Speech => f.write_str("speech"),
+            Signal::Sine { freq_hz } => write!(f, "sine:{freq_hz}"),
+            Signal::Noise => f.write_str("noise"),
+            Signal::Silence => f.write_str("silence"),
+            Signal::Fixture(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    /// `speech`, `sine[:HZ]`, `noise`, `silence`, or the path of an audio file
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "speech" => return Ok(Signal::Speech),
+            "sine" => return Ok(Signal::Sine { freq_hz: 440.0 }),
+            "noise" => return Ok(Signal::Noise),
+            "silence" => return Ok(Signal::Silence),
+            _ => {}
+        }
+        if let Some(freq) = s.strip_prefix("sine:") {
+            return match freq.parse::<f32>() {
+                Ok(freq_hz) if freq_hz > 0.0 && freq_hz.is_finite() => Ok(Signal::Sine { freq_hz }),
+                _ => Err(format!("Invalid sine frequency: {freq}")),
+            };
+        }
+        let path = PathBuf::from(s);
+        if path.is_file() {
+            Ok(Signal::Fixture(path))
+        } else {
+            Err(format!(
+                "Unknown signal: {s} (use speech, sine[:HZ], noise, silence or an audio file)"
+            ))
+        }
+    }
+}
+
+/// An open input stream of either backend, with its capture buffer;
+/// capture stops when it is dropped
+pub struct InputStream {
+    source: Source,
+    queue: Arc<SpillQueue>,
+    stamps: Arc<CaptureStamps>,
+}
+
+enum Source {
+    Device(cpal::Stream),
+    Synthetic(tokio::task::JoinHandle<()>),
+}
+
+impl InputStream {
+    /// Capture from the input device, buffering through `queue`, with the
+    /// callback stamping buffers into `stamps`
+    pub fn device(
+        stream: cpal::Stream,
+        queue: Arc<SpillQueue>,
+        stamps: Arc<CaptureStamps>,
+    ) -> Self {
+        Self {
+            source: Source::Device(stream),
+            queue,
+            stamps,
+        }
+    }
+
+    /// When the stream's buffers were captured
+    pub fn stamps(&self) -> Arc<CaptureStamps> {
+        Arc::clone(&self.stamps)
+    }
+
+    /// How much audio has waited in the capture buffer so far
+    pub fn spill_stats(&self) -> SpillStats {
+        self.queue.stats()
+    }
+}
+
+impl Drop for InputStream {
+    fn drop(&mut self) {
+        // Stop the producer before the queue, so nothing is pushed after
+        match &self.source {
+            Source::Device(stream) => {
+                let _ = stream.pause();
+            }
+            Source::Synthetic(task) => task.abort(),
+        }
+        self.queue.close();
+    }
+}
+
+/// Start delivering `signal` in real time, through a capture buffer like the
+/// input device's; returns the rate it is delivered at
+pub fn open_synthetic(
+    signal: &Signal,
+    sample_rate: u32,
+    channels: u16,
+    spill: impl FnOnce(u32) -> SpillConfig,
+) -> Result<(InputStream, u32, mpsc::Receiver<Vec<f32>>)> {
+    let mut generator = Generator::new(signal, sample_rate, channels)?;
+    let rate = generator.sample_rate;
+    let frames = (rate as u64 * SYNTHETIC_BUFFER.as_millis() as u64 / 1000) as usize;
+    let (tx, rx) = mpsc::channel(32);
+    let queue = SpillQueue::start(spill(rate), tx);
+
+    let producer = Arc::clone(&queue);
+    let stamps = CaptureStamps::new(rate);
+    let stamper = Arc::clone(&stamps);
+    let task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SYNTHETIC_BUFFER);
+        loop {
+            interval.tick().await;
+            // Each buffer stands for the interval that just went by
+            stamper.stamp(frames, SYNTHETIC_BUFFER);
+            producer.push(&generator.next_buffer(frames));
+        }
+    });
+    let stream = InputStream {
+        source: Source::Synthetic(task),
+        queue,
+        stamps,
+    };
+    Ok((stream, rate, rx))
+}
+
+struct Generator {
+    signal: Signal,
+    sample_rate: u32,
+    channels: u16,
+    /// Frames generated so far
+    position: u64,
+    /// Interleaved samples of a fixture, at the fixture's rate
+    fixture: Vec<f32>,
+    noise: u32,
+}
+
+impl Generator {
+    fn new(signal: &Signal, sample_rate: u32, channels: u16) -> Result<Self> {
+        let (sample_rate, fixture) = match signal {
+            Signal::Fixture(path) => {
+                let audio = cowcow_core::decode::decode_file(path)
+                    .with_context(|| format!("Failed to load {}", path.display()))?;
+                (audio.sample_rate, audio.to_channels(channels))
+            }
+            _ => (sample_rate, Vec::new()),
+        };
+        Ok(Self {
+            signal: signal.clone(),
+            sample_rate,
+            channels,
+            position: 0,
+            fixture,
+            noise: 0x2545_f491,
+        })
+    }
+
+    /// Uniform noise in -1..1 (xorshift, so runs are repeatable)
+    fn noise(&mut self) -> f32 {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    fn next_buffer(&mut self, frames: usize) -> Vec<f32> {
+        let channels = self.channels as usize;
+        let mut buffer = Vec::with_capacity(frames * channels);
+        for _ in 0..frames {
+            let t = self.position as f32 / self.sample_rate as f32;
+            if let Signal::Fixture(_) = self.signal {
+                let start = self.position as usize * channels;
+                match self.fixture.get(start..start + channels) {
+                    Some(frame) => buffer.extend_from_slice(frame),
+                    None => buffer.extend((0..channels).map(|_| 0.0)),
+                }
+            } else {
+                let value = match self.signal {
+                    Signal::Speech => {
+                        // Four 150 ms syllables a second of a 155 Hz voice,
+                        // clear of mains hum harmonics
+                        let envelope = if (t * 4.0).fract() < 0.6 { 0.25 } else { 0.0 };
+                        envelope
+                            * ((2.0 * PI * 155.0 * t).sin()
+                                + 0.5 * (2.0 * PI * 310.0 * t).sin()
+                                + 0.25 * (2.0 * PI * 465.0 * t).sin())
+                    }
+                    Signal::Sine { freq_hz } => 0.25 * (2.0 * PI * freq_hz * t).sin(),
+                    Signal::Noise => 0.25 * self.noise(),
+                    Signal::Silence | Signal::Fixture(_) => 0.0,
+                };
+                let value = value + NOISE_FLOOR * self.noise();
+                buffer.extend((0..channels).map(|_| value));
+            }
+            self.position += 1;
+        }
+        buffer
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Corpus labels whose recordings are expected to pass QC; every other
+/// label (noisy, clipped, ...) is expected to be rejected
+pub const ACCEPT_LABELS: [&str; 2] = ["clean", "accept"];
+
+#[derive(Debug, Serialize)]
+pub struct BenchResult {
+    pub path: PathBuf,
+    pub label: String,
+    pub expected_accept: bool,
+    pub accepted: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LabelSummary {
+    pub total: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BenchReport {
+    pub true_accepts: usize,
+    pub false_accepts: usize,
+    pub true_rejects: usize,
+    pub false_rejects: usize,
+    /// Share of accepted recordings that were labeled clean
+    pub precision: f64,
+    /// Share of clean recordings that were accepted
+    pub recall: f64,
+    pub labels: BTreeMap<String, LabelSummary>,
+    /// Files whose decision did not match their label
+    pub mismatches: Vec<BenchResult>,
+    /// Files that could not be analyzed
+    pub errors: Vec<String>,
+}
+
+/// Run the QC pipeline and upload gates over a labeled corpus
+///
+/// The corpus holds one directory per label (`clean/`, `noisy/`,
+/// `clipped/`, ...) containing WAV fixtures, searched recursively.
+pub fn run_bench(corpus: &Path, config: &Config) -> Result<BenchReport> {
+    let mut report = BenchReport::default();
+    let processor_config = config.processor_builder().into_config();
+    let policy = config.qc_policy();
+
+    let mut labels: Vec<PathBuf> = fs::read_dir(corpus)
+        .with_context(|| format!("Failed to read corpus: {}", corpus.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    labels.sort();
+
+    for label_dir in labels {
+        let label = label_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let expected_accept = ACCEPT_LABELS.contains(&label.as_str());
+
+        for path in audio_files(&label_dir)? {
+            let metrics =
+                match cowcow_core::analyze_wav_file_with_config(&path, processor_config.clone()) {
+                    Ok(metrics) => metrics,
+                    Err(e) => {
+                        report.errors.push(format!("{}: {}", path.display(), e));
+                        continue;
+                    }
+                };
+
+            let qc_report = policy.evaluate(&metrics);
+            let accepted = qc_report.passed;
+            let reason = (!accepted).then(|| qc_report.failure_summary());
+
+            let summary = report.labels.entry(label.clone()).or_default();
+            summary.total += 1;
+            if accepted {
+                summary.accepted += 1;
+            } else {
+                summary.rejected += 1;
+            }
+
+            match (expected_accept, accepted) {
+                (true, true) => report.true_accepts += 1,
+                (false, false) => report.true_rejects += 1,
+                (true, false) => report.false_rejects += 1,
+                (false, true) => report.false_accepts += 1,
+            }
+
+            if expected_accept != accepted {
+                report.mismatches.push(BenchResult {
+                    path,
+                    label: label.clone(),
+                    expected_accept,
+                    accepted,
+                    reason,
+                });
+            }
+        }
+    }
+
+    report.precision = ratio(
+        report.true_accepts,
+        report.true_accepts + report.false_accepts,
+    );
+    report.recall = ratio(
+        report.true_accepts,
+        report.true_accepts + report.false_rejects,
+    );
+    Ok(report)
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Audio files (WAV, FLAC, MP3, ...) under `dir`, in path order
+fn audio_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(audio_files(&path)?);
+        } else if cowcow_core::decode::is_decodable(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+    use std::f32::consts::PI;
+    use tempfile::TempDir;
+
+    /// A fixture defect
+    #[derive(Clone, Copy)]
+    enum Defect {
+        None,
+        /// 50 Hz mains hum and its harmonics
+        Hum,
+        /// Speech barely above the room noise
+        Quiet,
+        /// Overdriven into clipping
+        Clipped,
+    }
+
+    /// Two seconds of a 137 Hz voice in syllables at 16 kHz over a faint
+    /// room noise, with `defect` added
+    fn write_fixture(path: &Path, defect: Defect) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        let mut noise = 0x2545_f491_u32;
+        for i in 0..32000 {
+            let t = i as f32 / 16000.0;
+            let envelope = if (t * 4.0).fract() < 0.6 { 0.15 } else { 0.0 };
+            let voice = envelope
+                * (1..24)
+                    .map(|k| (2.0 * PI * 137.0 * k as f32 * t).sin() / k as f32)
+                    .sum::<f32>();
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            let room = 0.002 * (noise as f32 / u32::MAX as f32 * 2.0 - 1.0);
+            let sample = match defect {
+                Defect::None => voice + room,
+                Defect::Hum => {
+                    voice
+                        + room
+                        + (1..4)
+                            .map(|k| 0.2 / k as f32 * (2.0 * PI * 50.0 * k as f32 * t).sin())
+                            .sum::<f32>()
+                }
+                Defect::Quiet => voice * 0.01 + room,
+                Defect::Clipped => (voice + room) * 8.0,
+            };
+            writer
+                .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+                .unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    /// Default config, minus the VAD gate: the fixtures are synthetic and
+    /// the VAD does not take them for speech
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.audio.min_vad_ratio = 0.0;
+        config
+    }
+
+    fn corpus(fixtures: &[(&str, Defect)]) -> TempDir {
+        let corpus = scratch_dir();
+        for (path, defect) in fixtures {
+            write_fixture(&corpus.path().join(path), *defect);
+        }
+        corpus
+    }
+
+    #[test]
+    fn test_bench_separates_labeled_corpus() {
+        let corpus = corpus(&[
+            ("clean/a.wav", Defect::None),
+            ("clean/studio/b.wav", Defect::None),
+            ("noisy/a.wav", Defect::Hum),
+            ("quiet/a.wav", Defect::Quiet),
+            ("clipped/a.wav", Defect::Clipped),
+        ]);
+        fs::write(corpus.path().join("clean/notes.txt"), "not audio").unwrap();
+
+        let report = run_bench(corpus.path(), &config()).unwrap();
+
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+        assert_eq!((report.true_accepts, report.true_rejects), (2, 3));
+        assert_eq!((report.precision, report.recall), (1.0, 1.0));
+        assert_eq!(report.labels["clean"].total, 2);
+        assert_eq!(report.labels["clipped"].rejected, 1);
+    }
+
+    #[test]
+    fn test_bench_counts_wrong_decisions() {
+        // A clipped take labeled clean, a clean one labeled noisy
+        let corpus = corpus(&[
+            ("clean/a.wav", Defect::Clipped),
+            ("clean/b.wav", Defect::None),
+            ("noisy/a.wav", Defect::None),
+        ]);
+
+        let report = run_bench(corpus.path(), &config()).unwrap();
+
+        assert_eq!((report.false_rejects, report.false_accepts), (1, 1));
+        assert_eq!((report.precision, report.recall), (0.5, 0.5));
+        assert_eq!(report.mismatches.len(), 2);
+        assert!(report.mismatches[0]
+            .reason
+            .as_deref()
+            .is_some_and(|reason| reason.contains("max_clipping")));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// A time-boxed collection drive, as published by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Campaign {
+    pub id: String,
+    pub name: String,
+    pub lang: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// Hours of speech the campaign aims to collect
+    pub target_hours: f64,
+    /// Hours uploaded so far, as of the last sync
+    #[serde(default)]
+    pub collected_hours: f64,
+    /// Multiplier applied to tokens earned by campaign recordings
+    #[serde(default = "default_multiplier")]
+    pub token_multiplier: f64,
+}
+
+fn default_multiplier() -> f64 {
+    1.0
+}
+
+type CampaignRow = (String, String, String, i64, i64, f64, f64, f64);
+
+impl Campaign {
+    fn from_row(row: CampaignRow) -> Self {
+        let (id, name, lang, starts_at, ends_at, target_hours, collected_hours, token_multiplier) =
+            row;
+        Self {
+            id,
+            name,
+            lang,
+            starts_at: DateTime::from_timestamp(starts_at, 0).unwrap_or_default(),
+            ends_at: DateTime::from_timestamp(ends_at, 0).unwrap_or_default(),
+            target_hours,
+            collected_hours,
+            token_multiplier,
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+
+    /// One-line status, e.g. `Campaign "Swahili sprint" ends in 3 days, 12.0h of sw still needed`
+    pub fn status_line(&self, now: DateTime<Utc>, pending_hours: f64) -> String {
+        let bonus = if self.token_multiplier != 1.0 {
+            format!(" (×{} tokens)", self.token_multiplier)
+        } else {
+            String::new()
+        };
+
+        if now < self.starts_at {
+            return format!(
+                "Campaign \"{}\" starts in {}{}",
+                self.name,
+                format_remaining(self.starts_at - now),
+                bonus
+            );
+        }
+        if now >= self.ends_at {
+            return format!("Campaign \"{}\" has ended", self.name);
+        }
+
+        let needed = self.target_hours - self.collected_hours - pending_hours;
+        let progress = if needed > 0.0 {
+            format!("{:.1}h of {} still needed", needed, self.lang)
+        } else {
+            "target reached".to_string()
+        };
+        format!(
+            "Campaign \"{}\" ends in {}, {}{}",
+            self.name,
+            format_remaining(self.ends_at - now),
+            progress,
+            bonus
+        )
+    }
+}
+
+/// Coarse countdown: days, then hours, then minutes
+fn format_remaining(remaining: chrono::Duration) -> String {
+    let minutes = remaining.num_minutes().max(0);
+    match (minutes / (24 * 60), minutes / 60) {
+        (1, _) => "1 day".to_string(),
+        (days, _) if days > 1 => format!("{days} days"),
+        (_, 1) => "1 hour".to_string(),
+        (_, hours) if hours > 1 => format!("{hours} hours"),
+        _ => format!("{minutes} min"),
+    }
+}
+
+/// Replace the local campaign cache with the server's list
+pub async fn store_campaigns(db: &SqlitePool, campaigns: &[Campaign]) -> Result<()> {
+    let mut tx = db.begin().await?;
+    sqlx::query("DELETE FROM campaigns")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear campaigns")?;
+
+    for campaign in campaigns {
+        sqlx::query(
+            r#"
+            INSERT INTO campaigns
+                (id, name, lang, starts_at, ends_at, target_hours, collected_hours, token_multiplier)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&campaign.id)
+        .bind(&campaign.name)
+        .bind(&campaign.lang)
+        .bind(campaign.starts_at.timestamp())
+        .bind(campaign.ends_at.timestamp())
+        .bind(campaign.target_hours)
+        .bind(campaign.collected_hours)
+        .bind(campaign.token_multiplier)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store campaign")?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Cached campaigns that have not ended yet
+pub async fn list_campaigns(db: &SqlitePool) -> Result<Vec<Campaign>> {
+    let rows: Vec<CampaignRow> = sqlx::query_as(
+        r#"
+        SELECT id, name, lang, starts_at, ends_at, target_hours, collected_hours, token_multiplier
+        FROM campaigns
+        WHERE ends_at > ?
+        ORDER BY ends_at
+        "#,
+    )
+    .bind(Utc::now().timestamp())
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch campaigns")?;
+
+    Ok(rows.into_iter().map(Campaign::from_row).collect())
+}
+
+pub async fn get_campaign(db: &SqlitePool, id: &str) -> Result<Option<Campaign>> {
+    let row: Option<CampaignRow> = sqlx::query_as(
+        r#"
+        SELECT id, name, lang, starts_at, ends_at, target_hours, collected_hours, token_multiplier
+        FROM campaigns
+        WHERE id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch campaign")?;
+
+    Ok(row.map(Campaign::from_row))
+}
+
+/// The running campaign for a language, if any
+pub async fn active_campaign(db: &SqlitePool, lang: &str) -> Result<Option<Campaign>> {
+    let now = Utc::now();
+    Ok(list_campaigns(db)
+        .await?
+        .into_iter()
+        .find(|campaign| campaign.lang == lang && campaign.is_active(now)))
+}
+
+/// Campaign for a new recording: the requested one, which must be running
+/// and match the language, or else the language's active campaign
+pub async fn campaign_for_recording(
+    db: &SqlitePool,
+    lang: &str,
+    requested: Option<&str>,
+) -> Result<Option<Campaign>> {
+    let Some(id) = requested else {
+        return active_campaign(db, lang).await;
+    };
+
+    let campaign = get_campaign(db, id)
+        .await?
+        .with_context(|| format!("Unknown campaign: {id} (run `cowcow campaigns sync`)"))?;
+    let now = Utc::now();
+    if now < campaign.starts_at {
+        return Err(anyhow::anyhow!(
+            "Campaign \"{}\" has not started yet",
+            campaign.name
+        ));
+    }
+    if now >= campaign.ends_at {
+        return Err(anyhow::anyhow!("Campaign \"{}\" has ended", campaign.name));
+    }
+    if campaign.lang != lang {
+        return Err(anyhow::anyhow!(
+            "Campaign \"{}\" collects {}, not {}",
+            campaign.name,
+            campaign.lang,
+            lang
+        ));
+    }
+    Ok(Some(campaign))
+}
+
+/// Hours recorded locally for a campaign that the server has not counted yet
+pub async fn pending_hours(db: &SqlitePool, campaign_id: &str) -> Result<f64> {
+    let seconds: Option<f64> = sqlx::query_scalar(
+        r#"
+        SELECT SUM(json_extract(qc_metrics, '$.duration_secs'))
+        FROM recordings
+        WHERE campaign_id = ? AND uploaded_at IS NULL AND archived = 0 AND take_selected = 1
+        "#,
+    )
+    .bind(campaign_id)
+    .fetch_one(db)
+    .await
+    .context("Failed to sum campaign recordings")?;
+
+    Ok(seconds.unwrap_or(0.0) / 3600.0)
+}
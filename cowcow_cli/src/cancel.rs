@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use tokio_util::sync::CancellationToken;
+
+/// Items completed between checkpoint saves, so a hard kill loses little
+const CHECKPOINT_EVERY: usize = 25;
+
+/// Checkpoints older than this are stale: the data has likely changed since,
+/// so the job starts over
+const CHECKPOINT_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// The one Ctrl-C listener of the process and the jobs it can stop
+struct Interrupts {
+    /// Cancelled by Ctrl-C; replaced for jobs started after that
+    generation: CancellationToken,
+    /// Jobs running with a token from [`on_ctrl_c`]
+    active: usize,
+}
+
+static INTERRUPTS: OnceLock<Mutex<Interrupts>> = OnceLock::new();
+
+fn interrupts() -> MutexGuard<'static, Interrupts> {
+    INTERRUPTS
+        .get_or_init(|| {
+            tokio::spawn(listen());
+            Mutex::new(Interrupts {
+                generation: CancellationToken::new(),
+                active: 0,
+            })
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Handle every Ctrl-C of the process: the first stops the running jobs
+/// after their current item, another one before they have stopped, or one
+/// with no job running, exits at once
+async fn listen() {
+    while tokio::signal::ctrl_c().await.is_ok() {
+        let interrupts = interrupts();
+        if interrupts.active == 0 || interrupts.generation.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("\n⏹️  Stopping after the current item (Ctrl-C again to quit now)");
+        interrupts.generation.cancel();
+    }
+}
+
+/// Cancellation of one job by Ctrl-C; the job counts as running until this
+/// is dropped
+pub struct Interruptible {
+    token: CancellationToken,
+}
+
+impl Deref for Interruptible {
+    type Target = CancellationToken;
+
+    fn deref(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for Interruptible {
+    fn drop(&mut self) {
+        let mut interrupts = interrupts();
+        interrupts.active = interrupts.active.saturating_sub(1);
+    }
+}
+
+/// Token cancelled on the first Ctrl-C, so a long command can stop after
+/// the item in progress; a second Ctrl-C exits at once
+///
+/// All jobs share one process-wide listener; a job started after a Ctrl-C
+/// gets a fresh token.
+pub fn on_ctrl_c() -> Interruptible {
+    let mut interrupts = interrupts();
+    if interrupts.generation.is_cancelled() {
+        interrupts.generation = CancellationToken::new();
+    }
+    interrupts.active += 1;
+    Interruptible {
+        token: interrupts.generation.child_token(),
+    }
+}
+
+/// Progress of an interruptible job, kept in the database so running the
+/// same job again skips what was already done
+pub struct Checkpoint {
+    job: String,
+    done: BTreeSet<String>,
+    unsaved: usize,
+    /// Whether progress is written to the database at all
+    persistent: bool,
+}
+
+impl Checkpoint {
+    /// Progress saved by an earlier, interrupted run of `job`, unless it is
+    /// too old to trust
+    pub async fn load(db: &SqlitePool, job: &str) -> Result<Self> {
+        let saved: Option<(String, i64)> =
+            sqlx::query_as("SELECT done, updated_at FROM job_checkpoints WHERE job = ?")
+                .bind(job)
+                .fetch_optional(db)
+                .await
+                .context("Failed to load checkpoint")?;
+        let now = chrono::Utc::now().timestamp();
+        let done = saved
+            .filter(|(_, updated_at)| now - updated_at <= CHECKPOINT_MAX_AGE_SECS)
+            .and_then(|(done, _)| serde_json::from_str(&done).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            job: job.to_string(),
+            done,
+            unsaved: 0,
+            persistent: true,
+        })
+    }
+
+    /// Progress of `job` that is never saved, for read-only databases
+    pub fn in_memory(job: &str) -> Self {
+        Self {
+            job: job.to_string(),
+            done: BTreeSet::new(),
+            unsaved: 0,
+            persistent: false,
+        }
+    }
+
+    /// Number of items done by earlier runs
+    pub fn resumed(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn is_done(&self, id: &str) -> bool {
+        self.done.contains(id)
+    }
+
+    /// Note `id` as done, saving every few items
+    pub async fn mark_done(&mut self, db: &SqlitePool, id: &str) -> Result<()> {
+        self.done.insert(id.to_string());
+        self.unsaved += 1;
+        if self.unsaved >= CHECKPOINT_EVERY {
+            self.save(db).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn save(&mut self, db: &SqlitePool) -> Result<()> {
+        if !self.persistent {
+            return Ok(());
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO job_checkpoints (job, done, updated_at) VALUES (?, ?, ?)
+            ON CONFLICT(job) DO UPDATE SET done = excluded.done, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&self.job)
+        .bind(serde_json::to_string(&self.done)?)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(db)
+        .await
+        .context("Failed to save checkpoint")?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    /// Forget the job's progress once it has run to the end
+    pub async fn finish(self, db: &SqlitePool) -> Result<()> {
+        if !self.persistent {
+            return Ok(());
+        }
+        sqlx::query("DELETE FROM job_checkpoints WHERE job = ?")
+            .bind(&self.job)
+            .execute(db)
+            .await
+            .context("Failed to clear checkpoint")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn database() -> SqlitePool {
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(
+            "CREATE TABLE job_checkpoints (job TEXT PRIMARY KEY, done TEXT NOT NULL, \
+             updated_at INTEGER NOT NULL)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_resumes_and_finishes() {
+        let db = database().await;
+        let mut checkpoint = Checkpoint::load(&db, "export").await.unwrap();
+        checkpoint.mark_done(&db, "a").await.unwrap();
+        checkpoint.mark_done(&db, "b").await.unwrap();
+        checkpoint.save(&db).await.unwrap();
+
+        let resumed = Checkpoint::load(&db, "export").await.unwrap();
+        assert_eq!(resumed.resumed(), 2);
+        assert!(resumed.is_done("a") && !resumed.is_done("c"));
+        assert_eq!(Checkpoint::load(&db, "other").await.unwrap().resumed(), 0);
+
+        resumed.finish(&db).await.unwrap();
+        assert_eq!(Checkpoint::load(&db, "export").await.unwrap().resumed(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stale_checkpoint_is_ignored() {
+        let db = database().await;
+        let stale = chrono::Utc::now().timestamp() - CHECKPOINT_MAX_AGE_SECS - 60;
+        sqlx::query("INSERT INTO job_checkpoints VALUES ('export', '[\"a\"]', ?)")
+            .bind(stale)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(Checkpoint::load(&db, "export").await.unwrap().resumed(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_share_one_listener() {
+        let first = on_ctrl_c();
+        let second = on_ctrl_c();
+        assert!(interrupts().active >= 2);
+        assert!(!first.is_cancelled() && !second.is_cancelled());
+
+        // A Ctrl-C stops the running jobs; later ones get a fresh token
+        interrupts().generation.cancel();
+        assert!(first.is_cancelled() && second.is_cancelled());
+        let third = on_ctrl_c();
+        assert!(!third.is_cancelled());
+        drop((first, second, third));
+    }
+}
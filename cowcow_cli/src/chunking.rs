@@ -0,0 +1,171 @@
+//! Chunk sizes of resumable uploads, adapted to the connection
+//!
+//! A fixed chunk size is wrong everywhere: on 2G a 1 MB chunk takes minutes
+//! and a dropped connection loses all of it, on fiber small chunks spend
+//! their time on round trips. The size here grows additively while chunks
+//! go through quickly, halves when one fails, and is capped at what the
+//! measured throughput moves in [`TARGET_CHUNK_SECS`] (AIMD, as TCP does
+//! with its window). The size learned is kept per server endpoint, so the
+//! next session starts where this one left off.
+
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+/// Chunks never get smaller than this...
+pub const MIN_CHUNK_BYTES: usize = 16 * 1024;
+
+/// ...or larger than this
+pub const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Growth after each chunk that went through within the target time
+pub const CHUNK_STEP_BYTES: usize = 64 * 1024;
+
+/// Time a chunk should take; a failure then costs at most this much resent
+pub const TARGET_CHUNK_SECS: f64 = 5.0;
+
+/// Chunk size following the connection's throughput and failures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkTuner {
+    size: usize,
+}
+
+impl ChunkTuner {
+    /// Start from `size` bytes, e.g. `upload.chunk_size` or the size learned
+    /// for the endpoint
+    pub fn new(size: usize) -> Self {
+        Self {
+            size: size.clamp(MIN_CHUNK_BYTES, MAX_CHUNK_BYTES),
+        }
+    }
+
+    /// Bytes to send in the next chunk
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// A chunk of `bytes` went through in `elapsed`
+    pub fn on_success(&mut self, bytes: usize, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if secs < TARGET_CHUNK_SECS {
+            self.size += CHUNK_STEP_BYTES;
+        }
+        if secs > 0.0 {
+            let fits = (bytes as f64 / secs * TARGET_CHUNK_SECS) as usize;
+            self.size = self.size.min(fits);
+        }
+        self.size = self.size.clamp(MIN_CHUNK_BYTES, MAX_CHUNK_BYTES);
+    }
+
+    /// A chunk failed or timed out
+    pub fn on_failure(&mut self) {
+        self.size = (self.size / 2).max(MIN_CHUNK_BYTES);
+    }
+}
+
+/// What was learned about uploading to an endpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EndpointTuning {
+    pub chunk_size: usize,
+    /// Whether the server offers resumable uploads, as found this run; not
+    /// stored, so one refusal (a proxy, a server briefly rolled back) does
+    /// not turn them off for good
+    pub resumable: bool,
+}
+
+/// Chunk size learned for `endpoint` by an earlier run
+pub async fn load_chunk_size(db: &SqlitePool, endpoint: &str) -> Result<Option<usize>> {
+    let row: Option<(i64,)> =
+        sqlx::query_as("SELECT chunk_size FROM upload_tuning WHERE endpoint = ?")
+            .bind(endpoint)
+            .fetch_optional(db)
+            .await
+            .context("Failed to load upload tuning")?;
+    Ok(row.map(|(chunk_size,)| chunk_size as usize))
+}
+
+pub async fn store_chunk_size(db: &SqlitePool, endpoint: &str, chunk_size: usize) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO upload_tuning (endpoint, chunk_size, updated_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT(endpoint) DO UPDATE SET
+            chunk_size = excluded.chunk_size,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(endpoint)
+    .bind(chunk_size as i64)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(db)
+    .await
+    .context("Failed to store upload tuning")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    #[test]
+    fn test_chunk_size_grows_additively_and_halves_on_failure() {
+        let mut tuner = ChunkTuner::new(256 * 1024);
+        tuner.on_success(256 * 1024, Duration::from_millis(100));
+        assert_eq!(tuner.size(), 256 * 1024 + CHUNK_STEP_BYTES);
+        tuner.on_failure();
+        assert_eq!(tuner.size(), (256 * 1024 + CHUNK_STEP_BYTES) / 2);
+
+        // Never below the floor, however many failures
+        for _ in 0..20 {
+            tuner.on_failure();
+        }
+        assert_eq!(tuner.size(), MIN_CHUNK_BYTES);
+        assert_eq!(ChunkTuner::new(usize::MAX).size(), MAX_CHUNK_BYTES);
+    }
+
+    #[test]
+    fn test_chunk_size_follows_a_slow_link() {
+        // 2G: 1 MB took 40 s, about 25 KB/s
+        let mut tuner = ChunkTuner::new(1024 * 1024);
+        tuner.on_success(1024 * 1024, Duration::from_secs(40));
+        assert_eq!(tuner.size(), 128 * 1024);
+
+        // Fiber: chunks keep growing up to the ceiling
+        let mut tuner = ChunkTuner::new(1024 * 1024);
+        for _ in 0..200 {
+            let size = tuner.size();
+            tuner.on_success(size, Duration::from_millis(50));
+        }
+        assert_eq!(tuner.size(), MAX_CHUNK_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_size_is_kept_per_endpoint() {
+        let library = TestLibrary::new().await;
+        let db = &library.db;
+
+        store_chunk_size(db, "https://a.example", 32 * 1024)
+            .await
+            .unwrap();
+        store_chunk_size(db, "https://b.example", 1024 * 1024)
+            .await
+            .unwrap();
+        store_chunk_size(db, "https://b.example", 4 * 1024 * 1024)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            load_chunk_size(db, "https://a.example").await.unwrap(),
+            Some(32 * 1024)
+        );
+        assert_eq!(
+            load_chunk_size(db, "https://b.example").await.unwrap(),
+            Some(4 * 1024 * 1024)
+        );
+        assert_eq!(
+            load_chunk_size(db, "https://c.example").await.unwrap(),
+            None
+        );
+    }
+}
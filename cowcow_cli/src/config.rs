@@ -1,9 +1,23 @@
 use anyhow::{Context, Result};
+use cowcow_core::filter::{FilterConfig, NoiseGate, DEFAULT_HIGH_PASS_HZ};
+use cowcow_core::normalize::NormalizeTarget;
+use cowcow_core::policy::{QcPolicy, QcRule};
+use cowcow_core::prompt_analysis::SPEAKING_RATE_METRIC;
+use cowcow_core::vad::VadBackend;
+use cowcow_core::wav::BitDepth;
+use cowcow_core::{AudioProcessorBuilder, DownmixStrategy};
 use dirs::home_dir;
+use reqwest::header::{HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-use tracing::info;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::controller::{ControllerAction, ControllerDevice};
+use crate::mode::RecordingMode;
+use crate::spill::SpillConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,18 +25,117 @@ pub struct Config {
     pub storage: StorageConfig,
     pub audio: AudioConfig,
     pub upload: UploadConfig,
+    #[serde(default)]
+    pub record: RecordConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub qc: QcConfig,
+    #[serde(default)]
+    pub reminders: ReminderConfig,
+    #[serde(default)]
+    pub asr: AsrConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    /// The config file as it was loaded, to merge edits other processes
+    /// made since instead of overwriting them
+    #[serde(skip)]
+    loaded: Option<toml::Table>,
+    /// `storage.data_dir` and `storage.recordings_dir` of the config file
+    /// while `--data-dir` points elsewhere, so saving keeps them
+    #[serde(skip)]
+    configured_dirs: Option<(PathBuf, Option<PathBuf>)>,
+    /// Set by `--read-only`: commands that write are refused and the
+    /// database is opened read-only
+    #[serde(skip)]
+    pub read_only: bool,
+}
+
+/// Three-way merge of the config being saved (`ours`) with the file on disk
+///
+/// Keys `ours` left as they were in `base` take the value on disk, so edits
+/// made by another process survive; keys changed here win, with a warning
+/// when the other process changed them too.
+fn merge_external_edits(
+    ours: &mut toml::Table,
+    base: &toml::Table,
+    theirs: &toml::Table,
+    path: &str,
+) {
+    let keys: BTreeSet<String> = ours
+        .keys()
+        .chain(base.keys())
+        .chain(theirs.keys())
+        .cloned()
+        .collect();
+
+    for key in keys {
+        let full_key = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        let (original, other) = (base.get(&key), theirs.get(&key));
+        if ours.get(&key) == original {
+            match other {
+                Some(other) => ours.insert(key, other.clone()),
+                None => ours.remove(&key),
+            };
+            continue;
+        }
+        if other == original || ours.get(&key) == other {
+            continue;
+        }
+
+        match (ours.get_mut(&key), original, other) {
+            (
+                Some(toml::Value::Table(mine)),
+                Some(toml::Value::Table(original)),
+                Some(toml::Value::Table(other)),
+            ) => merge_external_edits(mine, original, other, &full_key),
+            _ => warn!(
+                "Config key {full_key} was also changed by another process; keeping this change"
+            ),
+        }
+    }
+}
+
+/// Replace `path` with `content` through a temporary file and a rename
+fn write_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("toml.tmp");
+    let mut file = File::create(&temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiConfig {
     pub endpoint: String,
     pub timeout_secs: u64,
+    /// Requests sent to the server per second at most, across all commands
+    /// running in this process; 0 for no limit
+    #[serde(default = "default_max_requests_per_sec")]
+    pub max_requests_per_sec: f64,
+}
+
+fn default_max_requests_per_sec() -> f64 {
+    10.0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub data_dir: PathBuf,
     pub auto_upload: bool,
+    /// Recordings directory outside the data dir (e.g. an SD card)
+    #[serde(default)]
+    pub recordings_dir: Option<PathBuf>,
+    /// Keep the database encrypted with SQLCipher; needs the CLI built with
+    /// `--features sqlcipher`
+    #[serde(default)]
+    pub db_encryption: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,13 +145,457 @@ pub struct AudioConfig {
     pub min_snr_db: f32,
     pub max_clipping_pct: f32,
     pub min_vad_ratio: f32,
+    /// Voice activity detector: "webrtc" or "silero" (needs the `silero`
+    /// feature; other builds fall back to webrtc)
+    #[serde(default = "default_vad_backend")]
+    pub vad_backend: String,
+    /// Silero ONNX model, defaults to `<data_dir>/models/silero_vad.onnx`
+    #[serde(default)]
+    pub silero_model_path: Option<PathBuf>,
+    /// Sample level (0-1] at or above which audio counts as clipped
+    #[serde(default = "default_clip_threshold")]
+    pub clip_threshold: f32,
+    /// Maximum absolute DC offset (fraction of full scale) accepted for upload
+    #[serde(default = "default_max_dc_offset")]
+    pub max_dc_offset: f32,
+    /// Maximum energy below 50 Hz relative to total energy, in dB
+    #[serde(default = "default_max_rumble_db")]
+    pub max_rumble_db: f32,
+    /// Maximum mains hum level relative to total energy, in dB
+    #[serde(default = "default_max_hum_db")]
+    pub max_hum_db: f32,
+    /// Maximum number of dropped buffers accepted for upload
+    #[serde(default)]
+    pub max_dropouts: u32,
+    /// Maximum percentage of samples that are discontinuity clicks
+    #[serde(default = "default_max_glitch_pct")]
+    pub max_glitch_pct: f32,
+    /// Channel used for QC on multi-channel devices: "left", "right" or "average"
+    #[serde(default = "default_downmix")]
+    pub downmix: String,
+    /// Minimum seconds of detected speech required for upload (0 disables)
+    #[serde(default)]
+    pub min_speech_secs: f32,
+    /// Maximum estimated reverberation time (RT60) in seconds accepted for upload (0 disables)
+    #[serde(default)]
+    pub max_reverb: f32,
+    /// Slowest plausible reading of the prompt, in syllables per second of
+    /// speech (0 disables)
+    #[serde(default = "default_min_speaking_rate")]
+    pub min_speaking_rate: f32,
+    /// Fastest plausible reading of the prompt, in syllables per second of
+    /// speech (0 disables)
+    #[serde(default = "default_max_speaking_rate")]
+    pub max_speaking_rate: f32,
+    /// Cut silence before and after the speech from saved recordings (not
+    /// available in low-memory mode)
+    #[serde(default)]
+    pub auto_trim: bool,
+    /// Level saved recordings are normalized to, e.g. "-23 LUFS" or
+    /// "-1 dBFS" (unset keeps the device's level)
+    #[serde(default)]
+    pub normalize_target: Option<String>,
+    /// High-pass cutoff in Hz applied to saved recordings against wind and
+    /// handling rumble; `on` sets 80 Hz (unset disables)
+    #[serde(default)]
+    pub high_pass_hz: Option<f32>,
+    /// Turn down steady background noise (fans, hiss) in saved recordings
+    #[serde(default)]
+    pub noise_gate: bool,
+    /// Keep the unfiltered capture of filtered recordings as `<id>.raw.wav`
+    #[serde(default)]
+    pub keep_raw: bool,
+    /// Input device to record from, by name or number in `cowcow devices`
+    /// (unset uses the system default)
+    #[serde(default)]
+    pub input_device: Option<String>,
+    /// Noise floor in dBFS per input device, measured by `cowcow calibrate`
+    #[serde(default)]
+    pub noise_floors: BTreeMap<String, f32>,
+    /// File format recordings are saved in: "wav", "flac" (lossless,
+    /// about half the size) or "opus" (lossy, about a tenth of the size;
+    /// fine for ASR but not TTS, and needs the `opus` build feature; other
+    /// builds keep WAV)
+    #[serde(default = "default_audio_format")]
+    pub format: String,
+    /// Opus bitrate in bits per second
+    #[serde(default = "default_opus_bitrate")]
+    pub opus_bitrate: u32,
+    /// Sample format of WAV recordings: "16", "24" or "32f" (32-bit float);
+    /// FLAC recordings are always 16-bit
+    #[serde(default = "default_bit_depth")]
+    pub bit_depth: String,
+}
+
+fn default_audio_format() -> String {
+    "wav".to_string()
+}
+
+fn default_opus_bitrate() -> u32 {
+    cowcow_core::opus::DEFAULT_OPUS_BITRATE
+}
+
+fn default_bit_depth() -> String {
+    BitDepth::default().to_string()
+}
+
+fn default_vad_backend() -> String {
+    "webrtc".to_string()
+}
+
+fn default_clip_threshold() -> f32 {
+    cowcow_core::I16_FULL_SCALE
+}
+
+fn default_max_dc_offset() -> f32 {
+    0.05
+}
+
+fn default_max_rumble_db() -> f32 {
+    -10.0
+}
+
+fn default_max_hum_db() -> f32 {
+    -10.0
+}
+
+fn default_downmix() -> String {
+    "average".to_string()
+}
+
+fn default_max_glitch_pct() -> f32 {
+    0.01
+}
+
+fn default_min_speaking_rate() -> f32 {
+    1.5
+}
+
+fn default_max_speaking_rate() -> f32 {
+    9.0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadConfig {
     pub max_retries: u32,
     pub retry_delay_secs: u64,
+    /// Bytes per chunk of a resumable upload to start from; the size then
+    /// follows the connection and is remembered per server
     pub chunk_size: usize,
+    /// Recordings uploaded at the same time
+    #[serde(default = "default_upload_concurrency")]
+    pub concurrency: usize,
+    /// Speak HTTP/2 to a plain-text (`http://`) endpoint without first
+    /// negotiating it; HTTPS endpoints negotiate HTTP/2 on their own
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+    /// Upload only recordings approved in `cowcow review`; rejected ones
+    /// and ones marked for re-recording are never uploaded
+    #[serde(default)]
+    pub approved_only: bool,
+    /// Multipart field names sent instead of the defaults, keyed by default
+    /// name (e.g. `lang = "language"`); an empty name leaves the field out
+    #[serde(default)]
+    pub form: BTreeMap<String, String>,
+    /// Extra headers sent with every upload, e.g. a tenant ID
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+fn default_upload_concurrency() -> usize {
+    4
+}
+
+/// Multipart fields of an upload, by their default names
+pub const UPLOAD_FORM_FIELDS: [&str; 7] = [
+    "recording_id",
+    "lang",
+    "qc_metrics",
+    "file_path",
+    "file",
+    "campaign_id",
+    "transcript",
+];
+
+impl UploadConfig {
+    /// Name the given upload field is sent under, or `None` if it is left out
+    pub fn form_field<'a>(&'a self, field: &'a str) -> Option<&'a str> {
+        match self.form.get(field) {
+            Some(name) if name.is_empty() => None,
+            Some(name) => Some(name),
+            None => Some(field),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordConfig {
+    /// Reading pace used to highlight the current word of long prompts
+    #[serde(default = "default_karaoke_wpm")]
+    pub karaoke_wpm: u32,
+    /// Prompts with at least this many words get karaoke highlighting (0 disables)
+    #[serde(default = "default_karaoke_min_words")]
+    pub karaoke_min_words: usize,
+    /// Seconds of room tone captured at the start of each session (0 disables)
+    #[serde(default = "default_room_tone_secs")]
+    pub room_tone_secs: u32,
+    /// Seconds counted down before a recording starts (0 starts at once)
+    #[serde(default = "default_countdown_secs")]
+    pub countdown_secs: u32,
+    /// Seconds of silence after which a recording stops by itself (0 never
+    /// stops on silence)
+    #[serde(default = "default_silence_stop_secs")]
+    pub silence_stop_secs: f64,
+    /// RMS level (0-1) above which audio always counts as sound, whatever
+    /// the adaptive silence gate says (0 leaves it to the gate)
+    #[serde(default)]
+    pub silence_rms_threshold: f32,
+    /// Takes shorter than this many seconds are offered for a retake at once
+    /// (0 accepts any length)
+    #[serde(default = "default_min_duration_secs")]
+    pub min_duration_secs: f64,
+    /// Takes longer than this many seconds are flagged and can be split at
+    /// their pauses (0 for no limit)
+    #[serde(default = "default_max_duration_secs")]
+    pub max_duration_secs: f64,
+    /// Seconds without audio from the device before a recording's capture
+    /// is considered stalled and the input stream reopened (0 disables)
+    #[serde(default = "default_stall_timeout_secs")]
+    pub stall_timeout_secs: u32,
+    /// Recordings started within this many minutes of the last one share its session
+    #[serde(default = "default_session_timeout_mins")]
+    pub session_timeout_mins: u32,
+    /// Seconds of ambient noise sampled when calibrating an input device
+    #[serde(default = "default_calibration_secs")]
+    pub calibration_secs: u32,
+    /// Trade per-frame QC timelines and buffering headroom for a small
+    /// memory footprint (512 MB single-board kiosks)
+    #[serde(default)]
+    pub low_memory: bool,
+    /// Seconds of captured audio held in memory while the recording loop
+    /// catches up after a CPU spike
+    #[serde(default = "default_capture_buffer_secs")]
+    pub capture_buffer_secs: f64,
+    /// Spill captured audio to a scratch file once the memory buffer is
+    /// full, instead of dropping it
+    #[serde(default = "default_spill_to_disk")]
+    pub spill_to_disk: bool,
+    /// Languages recorded on this device; the first is used when `record`
+    /// is run without `--lang`
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Prompt material bundled per language, one `<lang>/` directory each
+    #[serde(default)]
+    pub prompts_dir: Option<PathBuf>,
+    /// Play the language's consent explanation (`<prompts_dir>/<lang>/consent.wav`)
+    /// and wait for the speaker to agree before every recording
+    #[serde(default)]
+    pub play_consent: bool,
+    /// Play every take back and let the contributor accept, re-record or
+    /// discard it before it is saved
+    #[serde(default)]
+    pub review_takes: bool,
+    /// Version of the consent form in force: when set, speakers must have
+    /// agreed to this version (`cowcow speakers agree`) to record or upload
+    #[serde(default)]
+    pub consent_form_version: Option<String>,
+}
+
+fn default_karaoke_wpm() -> u32 {
+    130
+}
+
+fn default_karaoke_min_words() -> usize {
+    20
+}
+
+fn default_room_tone_secs() -> u32 {
+    5
+}
+
+fn default_countdown_secs() -> u32 {
+    3
+}
+
+fn default_silence_stop_secs() -> f64 {
+    5.0
+}
+
+fn default_capture_buffer_secs() -> f64 {
+    5.0
+}
+
+fn default_spill_to_disk() -> bool {
+    true
+}
+
+fn default_min_duration_secs() -> f64 {
+    1.0
+}
+
+fn default_max_duration_secs() -> f64 {
+    30.0
+}
+
+fn default_stall_timeout_secs() -> u32 {
+    3
+}
+
+fn default_session_timeout_mins() -> u32 {
+    30
+}
+
+fn default_calibration_secs() -> u32 {
+    3
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            karaoke_wpm: default_karaoke_wpm(),
+            karaoke_min_words: default_karaoke_min_words(),
+            room_tone_secs: default_room_tone_secs(),
+            countdown_secs: default_countdown_secs(),
+            silence_stop_secs: default_silence_stop_secs(),
+            silence_rms_threshold: 0.0,
+            min_duration_secs: default_min_duration_secs(),
+            max_duration_secs: default_max_duration_secs(),
+            stall_timeout_secs: default_stall_timeout_secs(),
+            session_timeout_mins: default_session_timeout_mins(),
+            calibration_secs: default_calibration_secs(),
+            low_memory: false,
+            capture_buffer_secs: default_capture_buffer_secs(),
+            spill_to_disk: default_spill_to_disk(),
+            languages: Vec::new(),
+            prompts_dir: None,
+            play_consent: false,
+            review_takes: false,
+            consent_form_version: None,
+        }
+    }
+}
+
+impl RecordConfig {
+    /// Recorded consent explanation for `lang`, if the prompts directory has one
+    pub fn consent_audio(&self, lang: &str) -> Option<PathBuf> {
+        let path = self.prompts_dir.as_ref()?.join(lang).join("consent.wav");
+        path.is_file().then_some(path)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// Release manifest checked by `cowcow self-update`
+    #[serde(default = "default_release_url")]
+    pub release_url: String,
+    /// Base64 minisign public key for release signatures (overrides the built-in key)
+    #[serde(default)]
+    pub public_key: Option<String>,
+    #[serde(default = "default_update_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_release_url() -> String {
+    "https://github.com/deepubuntu/cowcow/releases/latest/download/release.json".to_string()
+}
+
+fn default_update_timeout_secs() -> u64 {
+    300
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            release_url: default_release_url(),
+            public_key: None,
+            timeout_secs: default_update_timeout_secs(),
+        }
+    }
+}
+
+/// Anonymous usage telemetry, off unless the user explicitly opts in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Random identifier created on opt-in and discarded on opt-out
+    #[serde(default)]
+    pub install_id: Option<String>,
+    /// Collection endpoint, defaults to `<api.endpoint>/telemetry`
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Nudges for contributors who have stopped recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderConfig {
+    /// Remind after this many days without a recording; 0 turns reminders off
+    #[serde(default = "default_remind_after_days")]
+    pub after_days: u32,
+    /// Let the daemon send desktop notifications, not just remind in the
+    /// terminal on the next run
+    #[serde(default = "default_desktop_reminders")]
+    pub desktop: bool,
+}
+
+fn default_remind_after_days() -> u32 {
+    3
+}
+
+fn default_desktop_reminders() -> bool {
+    true
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            after_days: default_remind_after_days(),
+            desktop: default_desktop_reminders(),
+        }
+    }
+}
+
+/// Hands-free controls while recording
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputConfig {
+    /// Foot pedals, USB buttons and the like, by a name of your choosing
+    #[serde(default)]
+    pub devices: BTreeMap<String, ControllerDevice>,
+}
+
+/// Automatic transcription (`cowcow transcribe --auto`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsrConfig {
+    /// whisper.cpp model file (e.g. `ggml-base.bin`)
+    pub model: Option<PathBuf>,
+    /// Flag a recording as a likely misread when the word error rate of the
+    /// model's hypothesis against the prompt is above this
+    #[serde(default = "default_misread_wer")]
+    pub misread_wer: f32,
+}
+
+fn default_misread_wer() -> f32 {
+    0.5
+}
+
+impl Default for AsrConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            misread_wer: default_misread_wer(),
+        }
+    }
+}
+
+/// Adjustments to the QC policy built from the audio thresholds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QcConfig {
+    /// Built-in rules to skip (e.g. "max_hum")
+    #[serde(default)]
+    pub disabled_rules: Vec<String>,
+    /// Extra rules, or replacements for built-in rules with the same name
+    #[serde(default)]
+    pub rules: Vec<QcRule>,
 }
 
 impl Default for Config {
@@ -51,10 +608,13 @@ impl Default for Config {
             api: ApiConfig {
                 endpoint: "http://localhost:8000".to_string(),
                 timeout_secs: 30,
+                max_requests_per_sec: default_max_requests_per_sec(),
             },
             storage: StorageConfig {
                 data_dir,
                 auto_upload: false,
+                recordings_dir: None,
+                db_encryption: false,
             },
             audio: AudioConfig {
                 sample_rate: 16000,
@@ -62,12 +622,50 @@ impl Default for Config {
                 min_snr_db: 20.0,
                 max_clipping_pct: 1.0,
                 min_vad_ratio: 80.0,
+                vad_backend: default_vad_backend(),
+                silero_model_path: None,
+                clip_threshold: default_clip_threshold(),
+                max_dc_offset: default_max_dc_offset(),
+                max_rumble_db: default_max_rumble_db(),
+                max_hum_db: default_max_hum_db(),
+                max_dropouts: 0,
+                max_glitch_pct: default_max_glitch_pct(),
+                min_speech_secs: 0.0,
+                max_reverb: 0.0,
+                min_speaking_rate: default_min_speaking_rate(),
+                max_speaking_rate: default_max_speaking_rate(),
+                auto_trim: false,
+                normalize_target: None,
+                high_pass_hz: None,
+                noise_gate: false,
+                keep_raw: false,
+                input_device: None,
+                noise_floors: BTreeMap::new(),
+                downmix: default_downmix(),
+                format: default_audio_format(),
+                opus_bitrate: default_opus_bitrate(),
+                bit_depth: default_bit_depth(),
             },
             upload: UploadConfig {
                 max_retries: 3,
                 retry_delay_secs: 2,
                 chunk_size: 1024 * 1024, // 1MB chunks
+                concurrency: default_upload_concurrency(),
+                http2_prior_knowledge: false,
+                approved_only: false,
+                form: BTreeMap::new(),
+                headers: BTreeMap::new(),
             },
+            record: RecordConfig::default(),
+            update: UpdateConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            qc: QcConfig::default(),
+            reminders: ReminderConfig::default(),
+            asr: AsrConfig::default(),
+            input: InputConfig::default(),
+            loaded: None,
+            configured_dirs: None,
+            read_only: false,
         }
     }
 }
@@ -81,10 +679,11 @@ impl Config {
                 format!("Failed to read config file: {}", config_path.display())
             })?;
 
-            let config: Config = toml::from_str(&content).context(format!(
+            let mut config: Config = toml::from_str(&content).context(format!(
                 "Failed to parse config file: {}",
                 config_path.display()
             ))?;
+            config.loaded = toml::from_str(&content).ok();
 
             info!("Loaded config from: {}", config_path.display());
             Ok(config)
@@ -106,9 +705,43 @@ impl Config {
             })?;
         }
 
-        let content = toml::to_string_pretty(self).context("Failed to serialize config to TOML")?;
+        // Serialize writers across processes; readers never see a partial
+        // file since it is replaced by a rename
+        let lock_path = config_path.with_extension("toml.lock");
+        let lock = File::create(&lock_path)
+            .with_context(|| format!("Failed to open config lock: {}", lock_path.display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock config: {}", lock_path.display()))?;
+
+        let mut saved = self.clone();
+        if let Some((data_dir, recordings_dir)) = &self.configured_dirs {
+            saved.storage.data_dir = data_dir.clone();
+            saved.storage.recordings_dir = recordings_dir.clone();
+        }
+        let mut table =
+            toml::Table::try_from(&saved).context("Failed to serialize config to TOML")?;
+        if let Some(base) = &self.loaded {
+            let on_disk = fs::read_to_string(&config_path)
+                .ok()
+                .and_then(|content| toml::from_str::<toml::Table>(&content).ok());
+            if let Some(on_disk) = on_disk.filter(|on_disk| on_disk != base) {
+                let mut merged = table.clone();
+                merge_external_edits(&mut merged, base, &on_disk, "");
+                if toml::Value::Table(merged.clone())
+                    .try_into::<Config>()
+                    .is_ok()
+                {
+                    info!("Merged config changes made by another process");
+                    table = merged;
+                } else {
+                    warn!("Config was changed by another process in an incompatible way; overwriting it");
+                }
+            }
+        }
 
-        fs::write(&config_path, content)
+        let content =
+            toml::to_string_pretty(&table).context("Failed to serialize config to TOML")?;
+        write_atomically(&config_path, content.as_bytes())
             .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
 
         info!("Saved config to: {}", config_path.display());
@@ -127,8 +760,37 @@ impl Config {
         &self.storage.data_dir
     }
 
+    /// Work on another data directory, such as a copied field backup, for
+    /// this run only; its recordings are expected under `recordings/`
+    pub fn use_data_dir(&mut self, data_dir: PathBuf) {
+        self.configured_dirs.get_or_insert_with(|| {
+            (
+                self.storage.data_dir.clone(),
+                self.storage.recordings_dir.clone(),
+            )
+        });
+        self.storage.data_dir = data_dir;
+        self.storage.recordings_dir = None;
+    }
+
+    /// Where a recording's audio is: the stored path, or the same
+    /// `<lang>/<file>` under the recordings directory when the data
+    /// directory was copied from elsewhere
+    pub fn locate_audio(&self, stored: &str) -> PathBuf {
+        crate::storage::locate_audio(stored, &self.recordings_dir())
+    }
+
     pub fn recordings_dir(&self) -> PathBuf {
-        self.storage.data_dir.join("recordings")
+        self.storage
+            .recordings_dir
+            .clone()
+            .unwrap_or_else(|| self.storage.data_dir.join("recordings"))
+    }
+
+    /// Internal directory holding recordings made while the external
+    /// recordings directory was unavailable
+    pub fn spool_dir(&self) -> PathBuf {
+        self.storage.data_dir.join("spool")
     }
 
     pub fn database_path(&self) -> PathBuf {
@@ -139,6 +801,182 @@ impl Config {
         self.storage.data_dir.join("credentials.json")
     }
 
+    pub fn telemetry_endpoint(&self) -> String {
+        self.telemetry
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("{}/telemetry", self.api.endpoint))
+    }
+
+    /// QC policy used to gate uploads
+    ///
+    /// Built from the `audio` thresholds, minus `qc.disabled_rules`, plus any
+    /// custom `qc.rules`.
+    pub fn qc_policy(&self) -> QcPolicy {
+        let audio = &self.audio;
+        let mut policy = QcPolicy::new()
+            .with_rule(QcRule::min("min_snr", "snr_db", audio.min_snr_db as f64))
+            .with_rule(QcRule::max(
+                "max_clipping",
+                "clipping_pct",
+                audio.max_clipping_pct as f64,
+            ))
+            .with_rule(QcRule::min(
+                "min_vad_ratio",
+                "vad_ratio",
+                audio.min_vad_ratio as f64,
+            ))
+            .with_rule(QcRule::max_abs(
+                "max_dc_offset",
+                "dc_offset",
+                audio.max_dc_offset as f64,
+            ))
+            .with_rule(QcRule::max(
+                "max_rumble",
+                "rumble_db",
+                audio.max_rumble_db as f64,
+            ))
+            .with_rule(QcRule::max("max_hum", "hum_db", audio.max_hum_db as f64))
+            .with_rule(QcRule::max(
+                "max_dropouts",
+                "dropout_count",
+                audio.max_dropouts as f64,
+            ))
+            .with_rule(QcRule::max(
+                "max_glitch",
+                "glitch_pct",
+                audio.max_glitch_pct as f64,
+            ));
+        if audio.min_speech_secs > 0.0 {
+            policy.set_rule(QcRule::min(
+                "min_speech",
+                "speech_secs",
+                audio.min_speech_secs as f64,
+            ));
+        }
+        if audio.max_reverb > 0.0 {
+            policy.set_rule(QcRule::max(
+                "max_reverb",
+                "reverb_rt60_secs",
+                audio.max_reverb as f64,
+            ));
+        }
+        // Only recordings with a prompt have a speaking rate; others skip these
+        if audio.min_speaking_rate > 0.0 {
+            policy.set_rule(QcRule::min(
+                "min_speaking_rate",
+                SPEAKING_RATE_METRIC,
+                audio.min_speaking_rate as f64,
+            ));
+        }
+        if audio.max_speaking_rate > 0.0 {
+            policy.set_rule(QcRule::max(
+                "max_speaking_rate",
+                SPEAKING_RATE_METRIC,
+                audio.max_speaking_rate as f64,
+            ));
+        }
+
+        for name in &self.qc.disabled_rules {
+            policy.remove_rule(name);
+        }
+        for rule in &self.qc.rules {
+            policy.set_rule(rule.clone());
+        }
+        policy
+    }
+
+    /// QC policy of music-mode recordings: the `music` preset, clipping at
+    /// most `audio.max_clipping_pct`
+    pub fn music_qc_policy(&self) -> QcPolicy {
+        let mut policy = QcPolicy::preset("music").unwrap_or_default();
+        policy.set_rule(QcRule::max(
+            "max_clipping",
+            "clipping_pct",
+            self.audio.max_clipping_pct as f64,
+        ));
+        policy
+    }
+
+    /// QC policy for a recording, by the mode its `metrics` were made in
+    pub fn qc_policy_for(&self, metrics: &serde_json::Value) -> QcPolicy {
+        match RecordingMode::of_metrics(metrics) {
+            RecordingMode::Speech => self.qc_policy(),
+            RecordingMode::Music => self.music_qc_policy(),
+        }
+    }
+
+    /// Audio processor builder reflecting the audio config
+    pub fn processor_builder(&self) -> AudioProcessorBuilder {
+        AudioProcessorBuilder::new(self.audio.sample_rate, self.audio.channels)
+            .vad_backend(self.vad_backend())
+            .clip_threshold(self.audio.clip_threshold)
+            .downmix(self.downmix())
+    }
+
+    /// Calibrated noise floor of an input device, if it has been measured
+    pub fn noise_floor_db(&self, device: &str) -> Option<f32> {
+        self.audio.noise_floors.get(device).copied()
+    }
+
+    /// Normalization applied to saved recordings, if any
+    pub fn normalize_target(&self) -> Option<NormalizeTarget> {
+        self.audio
+            .normalize_target
+            .as_deref()
+            .and_then(|target| target.parse().ok())
+    }
+
+    /// Filters applied to saved recordings
+    pub fn filter_config(&self) -> FilterConfig {
+        FilterConfig {
+            high_pass_hz: self.audio.high_pass_hz,
+            noise_gate: self.audio.noise_gate.then(NoiseGate::default),
+        }
+    }
+
+    /// Capture buffer for an input stream delivering `sample_rate`; low-memory
+    /// devices keep a second at most in memory and lean on the disk
+    pub fn spill_config(&self, sample_rate: u32) -> SpillConfig {
+        let samples_per_second = sample_rate as u64 * self.audio.channels as u64;
+        let memory_secs = if self.record.low_memory {
+            self.record.capture_buffer_secs.min(1.0)
+        } else {
+            self.record.capture_buffer_secs
+        };
+        SpillConfig {
+            memory_samples: (memory_secs * samples_per_second as f64) as usize,
+            spill_to_disk: self.record.spill_to_disk,
+            samples_per_second,
+        }
+    }
+
+    /// Downmix strategy selected in the audio config
+    pub fn downmix(&self) -> DownmixStrategy {
+        match self.audio.downmix.as_str() {
+            "left" => DownmixStrategy::Left,
+            "right" => DownmixStrategy::Right,
+            _ => DownmixStrategy::Average,
+        }
+    }
+
+    /// Sample format recordings are written in
+    pub fn bit_depth(&self) -> BitDepth {
+        self.audio.bit_depth.parse().unwrap_or_default()
+    }
+
+    /// VAD backend selected in the audio config
+    pub fn vad_backend(&self) -> VadBackend {
+        match self.audio.vad_backend.as_str() {
+            "silero" => VadBackend::Silero {
+                model_path: self.audio.silero_model_path.clone().unwrap_or_else(|| {
+                    self.storage.data_dir.join("models").join("silero_vad.onnx")
+                }),
+            },
+            _ => VadBackend::WebRtc,
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
         // Validate API endpoint
         if !self.api.endpoint.starts_with("http://") && !self.api.endpoint.starts_with("https://") {
@@ -147,11 +985,57 @@ impl Config {
             ));
         }
 
+        if !self.update.release_url.starts_with("https://") {
+            return Err(anyhow::anyhow!("Release URL must start with https://"));
+        }
+
         // Validate timeout
         if self.api.timeout_secs == 0 {
             return Err(anyhow::anyhow!("API timeout must be greater than 0"));
         }
 
+        if !(self.api.max_requests_per_sec >= 0.0 && self.api.max_requests_per_sec.is_finite()) {
+            return Err(anyhow::anyhow!(
+                "API request rate must be 0 (no limit) or a positive number"
+            ));
+        }
+
+        if !(self.record.silence_stop_secs >= 0.0 && self.record.silence_stop_secs.is_finite()) {
+            return Err(anyhow::anyhow!(
+                "Silence stop must be 0 (never) or a positive number of seconds"
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.record.silence_rms_threshold) {
+            return Err(anyhow::anyhow!(
+                "Silence RMS threshold must be between 0 and 1"
+            ));
+        }
+
+        for (name, secs) in [
+            ("Minimum", self.record.min_duration_secs),
+            ("Maximum", self.record.max_duration_secs),
+        ] {
+            if !(secs >= 0.0 && secs.is_finite()) {
+                return Err(anyhow::anyhow!(
+                    "{name} take duration must be 0 (off) or a positive number of seconds"
+                ));
+            }
+        }
+        if self.record.max_duration_secs > 0.0
+            && self.record.max_duration_secs < self.record.min_duration_secs
+        {
+            return Err(anyhow::anyhow!(
+                "Maximum take duration must not be shorter than the minimum"
+            ));
+        }
+
+        if !(self.record.capture_buffer_secs > 0.0 && self.record.capture_buffer_secs.is_finite()) {
+            return Err(anyhow::anyhow!(
+                "Capture buffer must be a positive number of seconds"
+            ));
+        }
+
         // Validate audio settings
         if self.audio.sample_rate == 0 {
             return Err(anyhow::anyhow!("Sample rate must be greater than 0"));
@@ -161,6 +1045,102 @@ impl Config {
             return Err(anyhow::anyhow!("Channel count must be greater than 0"));
         }
 
+        if self.audio.clip_threshold <= 0.0 || self.audio.clip_threshold > 1.0 {
+            return Err(anyhow::anyhow!("Clip threshold must be between 0 and 1"));
+        }
+
+        // A misspelled metric would skip its rule on every recording
+        let known_metrics = cowcow_core::policy::known_metrics();
+        for rule in &self.qc.rules {
+            if !known_metrics.contains(&rule.metric) {
+                return Err(anyhow::anyhow!(
+                    "QC rule '{}' checks unknown metric '{}'; known metrics: {}",
+                    rule.name,
+                    rule.metric,
+                    known_metrics.join(", ")
+                ));
+            }
+        }
+
+        if !matches!(self.audio.vad_backend.as_str(), "webrtc" | "silero") {
+            return Err(anyhow::anyhow!("VAD backend must be 'webrtc' or 'silero'"));
+        }
+
+        if !matches!(self.audio.format.as_str(), "wav" | "flac" | "opus") {
+            return Err(anyhow::anyhow!(
+                "Audio format must be 'wav', 'flac' or 'opus'"
+            ));
+        }
+
+        if !cowcow_core::opus::OPUS_BITRATE_RANGE.contains(&self.audio.opus_bitrate) {
+            return Err(anyhow::anyhow!(
+                "Opus bitrate must be between 6000 and 510000 bits/s"
+            ));
+        }
+
+        let bit_depth = self
+            .audio
+            .bit_depth
+            .parse::<BitDepth>()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        if self.audio.format == "flac" && bit_depth != BitDepth::Int16 {
+            return Err(anyhow::anyhow!(
+                "FLAC recordings are 16-bit; use format 'wav' for other bit depths"
+            ));
+        }
+
+        if !matches!(self.audio.downmix.as_str(), "left" | "right" | "average") {
+            return Err(anyhow::anyhow!(
+                "Downmix must be 'left', 'right' or 'average'"
+            ));
+        }
+
+        if let Some(target) = &self.audio.normalize_target {
+            target
+                .parse::<NormalizeTarget>()
+                .map_err(|e| anyhow::anyhow!(e))?;
+        }
+
+        if let Some(cutoff_hz) = self.audio.high_pass_hz {
+            if cutoff_hz <= 0.0 || cutoff_hz >= self.audio.sample_rate as f32 / 2.0 {
+                return Err(anyhow::anyhow!(
+                    "High-pass cutoff must be between 0 and half the sample rate"
+                ));
+            }
+        }
+
+        if self.upload.concurrency == 0 {
+            return Err(anyhow::anyhow!("Upload concurrency must be greater than 0"));
+        }
+
+        for field in self.upload.form.keys() {
+            if !UPLOAD_FORM_FIELDS.contains(&field.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Unknown upload form field: {} (expected one of {})",
+                    field,
+                    UPLOAD_FORM_FIELDS.join(", ")
+                ));
+            }
+        }
+        if self.upload.form_field("file").is_none() {
+            return Err(anyhow::anyhow!("The upload file field cannot be left out"));
+        }
+
+        for (name, value) in &self.upload.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid upload header name: {}", name))?;
+            HeaderValue::from_str(value)
+                .map_err(|_| anyhow::anyhow!("Invalid value for upload header {}", name))?;
+        }
+
+        if self.record.karaoke_wpm == 0 {
+            return Err(anyhow::anyhow!("Karaoke WPM must be greater than 0"));
+        }
+
+        for (name, device) in &self.input.devices {
+            device.validate(name)?;
+        }
+
         Ok(())
     }
 
@@ -179,11 +1159,32 @@ impl Config {
                     .parse::<u64>()
                     .context("Invalid timeout value, must be a positive integer")?;
             }
+            "api.max_requests_per_sec" => {
+                self.api.max_requests_per_sec = value
+                    .parse::<f64>()
+                    .context("Invalid rate, must be a number of requests per second")?;
+            }
             "storage.auto_upload" => {
                 self.storage.auto_upload = value
                     .parse::<bool>()
                     .context("Invalid auto_upload value, must be true or false")?;
             }
+            "storage.recordings_dir" => {
+                let dir = PathBuf::from(value);
+                crate::storage::prepare_recordings_dir(&dir)?;
+                self.storage.recordings_dir = Some(dir);
+            }
+            "storage.db_encryption" => {
+                let enabled = value
+                    .parse::<bool>()
+                    .context("Invalid db_encryption value, must be true or false")?;
+                if enabled && !cfg!(feature = "sqlcipher") {
+                    anyhow::bail!(
+                        "Database encryption needs the CLI built with --features sqlcipher"
+                    );
+                }
+                self.storage.db_encryption = enabled;
+            }
             "audio.sample_rate" => {
                 self.audio.sample_rate = value
                     .parse::<u32>()
@@ -217,6 +1218,153 @@ impl Config {
                     return Err(anyhow::anyhow!("VAD ratio must be between 0 and 1"));
                 }
             }
+            "audio.clip_threshold" => {
+                self.audio.clip_threshold = value
+                    .parse::<f32>()
+                    .context("Invalid clip threshold, must be a number between 0 and 1")?;
+            }
+            "audio.max_dc_offset" => {
+                self.audio.max_dc_offset = value
+                    .parse::<f32>()
+                    .context("Invalid DC offset, must be a number between 0 and 1")?;
+                if self.audio.max_dc_offset < 0.0 || self.audio.max_dc_offset > 1.0 {
+                    return Err(anyhow::anyhow!("DC offset must be between 0 and 1"));
+                }
+            }
+            "audio.max_rumble_db" => {
+                self.audio.max_rumble_db = value
+                    .parse::<f32>()
+                    .context("Invalid rumble level, must be a number in dB")?;
+            }
+            "audio.max_hum_db" => {
+                self.audio.max_hum_db = value
+                    .parse::<f32>()
+                    .context("Invalid hum level, must be a number in dB")?;
+            }
+            "audio.max_dropouts" => {
+                self.audio.max_dropouts = value
+                    .parse::<u32>()
+                    .context("Invalid dropout count, must be a non-negative integer")?;
+            }
+            "audio.max_glitch_pct" => {
+                self.audio.max_glitch_pct = value
+                    .parse::<f32>()
+                    .context("Invalid glitch percentage, must be a number between 0 and 100")?;
+                if self.audio.max_glitch_pct < 0.0 || self.audio.max_glitch_pct > 100.0 {
+                    return Err(anyhow::anyhow!(
+                        "Glitch percentage must be between 0 and 100"
+                    ));
+                }
+            }
+            "audio.min_speech_secs" => {
+                self.audio.min_speech_secs = value
+                    .parse::<f32>()
+                    .context("Invalid speech duration, must be a number of seconds")?;
+                if self.audio.min_speech_secs < 0.0 {
+                    return Err(anyhow::anyhow!("Speech duration cannot be negative"));
+                }
+            }
+            "audio.max_reverb" => {
+                self.audio.max_reverb = value
+                    .parse::<f32>()
+                    .context("Invalid reverberation time, must be a number of seconds")?;
+                if self.audio.max_reverb < 0.0 {
+                    return Err(anyhow::anyhow!("Reverberation time cannot be negative"));
+                }
+            }
+            "audio.min_speaking_rate" => {
+                self.audio.min_speaking_rate = value
+                    .parse::<f32>()
+                    .context("Invalid speaking rate, must be syllables per second")?;
+                if self.audio.min_speaking_rate < 0.0 {
+                    return Err(anyhow::anyhow!("Speaking rate cannot be negative"));
+                }
+            }
+            "audio.max_speaking_rate" => {
+                self.audio.max_speaking_rate = value
+                    .parse::<f32>()
+                    .context("Invalid speaking rate, must be syllables per second")?;
+                if self.audio.max_speaking_rate < 0.0 {
+                    return Err(anyhow::anyhow!("Speaking rate cannot be negative"));
+                }
+            }
+            "audio.downmix" => {
+                self.audio.downmix = value.to_string();
+            }
+            "audio.vad_backend" => {
+                // A config file naming silero still loads on other builds,
+                // which fall back to webrtc with a warning
+                if value == "silero" && !cfg!(feature = "silero") {
+                    anyhow::bail!("Silero VAD needs the CLI built with --features silero");
+                }
+                self.audio.vad_backend = value.to_string();
+            }
+            "audio.format" => {
+                let format = value.to_ascii_lowercase();
+                // A config file naming opus still loads on other builds,
+                // which keep recordings as WAV with a warning
+                if format == "opus" && !cfg!(feature = "opus") {
+                    anyhow::bail!("Opus recordings need the CLI built with --features opus");
+                }
+                self.audio.format = format;
+            }
+            "audio.opus_bitrate" => {
+                self.audio.opus_bitrate = value
+                    .parse::<u32>()
+                    .context("Invalid opus_bitrate, must be bits per second (e.g. 24000)")?;
+            }
+            "audio.bit_depth" => {
+                self.audio.bit_depth = value
+                    .parse::<BitDepth>()
+                    .map_err(|e| anyhow::anyhow!(e))?
+                    .to_string();
+            }
+            "audio.auto_trim" => {
+                self.audio.auto_trim = value
+                    .parse::<bool>()
+                    .context("Invalid auto_trim value, must be true or false")?;
+            }
+            "audio.normalize_target" => {
+                self.audio.normalize_target = match value {
+                    "" | "off" | "none" => None,
+                    target => Some(
+                        target
+                            .parse::<NormalizeTarget>()
+                            .map_err(|e| anyhow::anyhow!(e))?
+                            .to_string(),
+                    ),
+                };
+            }
+            "audio.high_pass_hz" => {
+                self.audio.high_pass_hz = match value {
+                    "" | "off" | "none" => None,
+                    "on" => Some(DEFAULT_HIGH_PASS_HZ),
+                    cutoff => Some(
+                        cutoff
+                            .parse::<f32>()
+                            .context("Invalid high-pass cutoff, must be a number in Hz")?,
+                    ),
+                };
+            }
+            "audio.noise_gate" => {
+                self.audio.noise_gate = value
+                    .parse::<bool>()
+                    .context("Invalid noise_gate value, must be true or false")?;
+            }
+            "audio.keep_raw" => {
+                self.audio.keep_raw = value
+                    .parse::<bool>()
+                    .context("Invalid keep_raw value, must be true or false")?;
+            }
+            "audio.input_device" => {
+                self.audio.input_device = match value {
+                    "" | "default" => None,
+                    name => Some(name.to_string()),
+                };
+            }
+            "audio.silero_model_path" => {
+                self.audio.silero_model_path = Some(PathBuf::from(value));
+            }
             "upload.max_retries" => {
                 self.upload.max_retries = value
                     .parse::<u32>()
@@ -232,6 +1380,242 @@ impl Config {
                     .parse::<usize>()
                     .context("Invalid chunk size, must be a positive integer")?;
             }
+            "upload.concurrency" => {
+                self.upload.concurrency = value
+                    .parse::<usize>()
+                    .context("Invalid concurrency, must be a positive integer")?;
+            }
+            "upload.http2_prior_knowledge" => {
+                self.upload.http2_prior_knowledge = value
+                    .parse::<bool>()
+                    .context("Invalid http2_prior_knowledge value, must be true or false")?;
+            }
+            "upload.approved_only" => {
+                self.upload.approved_only = value
+                    .parse::<bool>()
+                    .context("Invalid approved_only value, must be true or false")?;
+            }
+            key if key.starts_with("upload.form.") => {
+                let field = &key["upload.form.".len()..];
+                self.upload
+                    .form
+                    .insert(field.to_string(), value.to_string());
+            }
+            key if key.starts_with("upload.headers.") => {
+                let name = &key["upload.headers.".len()..];
+                if value.is_empty() {
+                    self.upload.headers.remove(name);
+                } else {
+                    self.upload
+                        .headers
+                        .insert(name.to_string(), value.to_string());
+                }
+            }
+            "record.karaoke_wpm" => {
+                self.record.karaoke_wpm = value
+                    .parse::<u32>()
+                    .context("Invalid words per minute, must be a positive integer")?;
+            }
+            "record.karaoke_min_words" => {
+                self.record.karaoke_min_words = value
+                    .parse::<usize>()
+                    .context("Invalid word count, must be a non-negative integer")?;
+            }
+            "record.room_tone_secs" => {
+                self.record.room_tone_secs = value
+                    .parse::<u32>()
+                    .context("Invalid room tone duration, must be a number of seconds")?;
+            }
+            "record.countdown_secs" => {
+                self.record.countdown_secs = value
+                    .parse::<u32>()
+                    .context("Invalid countdown, must be a number of seconds")?;
+            }
+            "record.silence_stop_secs" => {
+                self.record.silence_stop_secs = value
+                    .parse::<f64>()
+                    .context("Invalid silence stop, must be a number of seconds")?;
+            }
+            "record.silence_rms_threshold" => {
+                self.record.silence_rms_threshold = value
+                    .parse::<f32>()
+                    .context("Invalid RMS threshold, must be a number between 0 and 1")?;
+            }
+            "record.min_duration_secs" => {
+                self.record.min_duration_secs = value
+                    .parse::<f64>()
+                    .context("Invalid minimum duration, must be a number of seconds")?;
+            }
+            "record.max_duration_secs" => {
+                self.record.max_duration_secs = value
+                    .parse::<f64>()
+                    .context("Invalid maximum duration, must be a number of seconds")?;
+            }
+            "record.stall_timeout_secs" => {
+                self.record.stall_timeout_secs = value
+                    .parse::<u32>()
+                    .context("Invalid stall timeout, must be a number of seconds")?;
+            }
+            "record.session_timeout_mins" => {
+                self.record.session_timeout_mins = value
+                    .parse::<u32>()
+                    .context("Invalid session timeout, must be a number of minutes")?;
+            }
+            "record.calibration_secs" => {
+                let secs = value
+                    .parse::<u32>()
+                    .context("Invalid calibration duration, must be a number of seconds")?;
+                if secs == 0 {
+                    return Err(anyhow::anyhow!(
+                        "Calibration duration must be at least 1 second"
+                    ));
+                }
+                self.record.calibration_secs = secs;
+            }
+            "record.low_memory" => {
+                self.record.low_memory = value
+                    .parse::<bool>()
+                    .context("Invalid low_memory value, must be true or false")?;
+            }
+            "record.capture_buffer_secs" => {
+                self.record.capture_buffer_secs = value
+                    .parse::<f64>()
+                    .context("Invalid capture buffer, must be a number of seconds")?;
+            }
+            "record.spill_to_disk" => {
+                self.record.spill_to_disk = value
+                    .parse::<bool>()
+                    .context("Invalid spill_to_disk value, must be true or false")?;
+            }
+            "record.languages" => {
+                self.record.languages = value
+                    .split(',')
+                    .map(|lang| lang.trim().to_string())
+                    .filter(|lang| !lang.is_empty())
+                    .collect();
+            }
+            "record.prompts_dir" => {
+                self.record.prompts_dir = match value {
+                    "" => None,
+                    dir => {
+                        let dir = PathBuf::from(dir);
+                        if !dir.is_dir() {
+                            return Err(anyhow::anyhow!(
+                                "Prompts directory not found: {}",
+                                dir.display()
+                            ));
+                        }
+                        Some(dir)
+                    }
+                };
+            }
+            "record.play_consent" => {
+                self.record.play_consent = value
+                    .parse::<bool>()
+                    .context("Invalid play_consent value, must be true or false")?;
+            }
+            "record.review_takes" => {
+                self.record.review_takes = value
+                    .parse::<bool>()
+                    .context("Invalid review_takes value, must be true or false")?;
+            }
+            "record.consent_form_version" => {
+                self.record.consent_form_version = match value.trim() {
+                    "" => None,
+                    version => Some(version.to_string()),
+                };
+            }
+            "update.release_url" => {
+                if !value.starts_with("https://") {
+                    return Err(anyhow::anyhow!("Release URL must start with https://"));
+                }
+                self.update.release_url = value.to_string();
+            }
+            "update.public_key" => {
+                self.update.public_key = Some(value.to_string());
+            }
+            "update.timeout_secs" => {
+                self.update.timeout_secs = value
+                    .parse::<u64>()
+                    .context("Invalid timeout value, must be a positive integer")?;
+            }
+            "telemetry.endpoint" => {
+                if !value.starts_with("http://") && !value.starts_with("https://") {
+                    return Err(anyhow::anyhow!(
+                        "Telemetry endpoint must start with http:// or https://"
+                    ));
+                }
+                self.telemetry.endpoint = Some(value.to_string());
+            }
+            "reminders.after_days" => {
+                self.reminders.after_days = value
+                    .parse::<u32>()
+                    .context("Invalid after_days value, must be a non-negative integer")?;
+            }
+            "reminders.desktop" => {
+                self.reminders.desktop = value
+                    .parse::<bool>()
+                    .context("Invalid desktop value, must be true or false")?;
+            }
+            "asr.model" => {
+                self.asr.model = match value {
+                    "" => None,
+                    path => {
+                        let path = PathBuf::from(path);
+                        if !path.is_file() {
+                            return Err(anyhow::anyhow!(
+                                "Model file not found: {}",
+                                path.display()
+                            ));
+                        }
+                        Some(path)
+                    }
+                };
+            }
+            key if key.starts_with("input.devices.") => {
+                let rest = &key["input.devices.".len()..];
+                let (name, setting) = rest.split_once('.').context(
+                    "Expected input.devices.<name>.path or input.devices.<name>.buttons.<button>",
+                )?;
+                let device = self.input.devices.entry(name.to_string()).or_default();
+                match setting.split_once('.') {
+                    None if setting == "path" => {
+                        device.path = (!value.is_empty()).then(|| PathBuf::from(value));
+                    }
+                    Some(("buttons", button)) if value.is_empty() => {
+                        device.buttons.remove(button);
+                    }
+                    Some(("buttons", button)) => {
+                        let action = value
+                            .parse::<ControllerAction>()
+                            .map_err(|e| anyhow::anyhow!(e))?;
+                        device.buttons.insert(button.to_string(), action);
+                    }
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Unknown input device setting: {} (expected path or buttons.<button>)",
+                            setting
+                        ))
+                    }
+                }
+                if device.path.is_none() && device.buttons.is_empty() {
+                    self.input.devices.remove(name);
+                }
+            }
+            "asr.misread_wer" => {
+                self.asr.misread_wer = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|wer| *wer >= 0.0)
+                    .context("Invalid misread_wer value, must be a non-negative number")?;
+            }
+            "qc.disabled_rules" => {
+                self.qc.disabled_rules = value
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+            }
             _ => {
                 return Err(anyhow::anyhow!("Unknown configuration key: {}", key));
             }
@@ -247,15 +1631,75 @@ impl Config {
         vec![
             "api.endpoint",
             "api.timeout_secs",
+            "api.max_requests_per_sec",
             "storage.auto_upload",
+            "storage.recordings_dir",
+            "storage.db_encryption",
             "audio.sample_rate",
             "audio.channels",
             "audio.min_snr_db",
             "audio.max_clipping_pct",
             "audio.min_vad_ratio",
+            "audio.vad_backend",
+            "audio.format",
+            "audio.opus_bitrate",
+            "audio.bit_depth",
+            "audio.silero_model_path",
+            "audio.clip_threshold",
+            "audio.max_dc_offset",
+            "audio.max_rumble_db",
+            "audio.max_hum_db",
+            "audio.max_dropouts",
+            "audio.max_glitch_pct",
+            "audio.min_speech_secs",
+            "audio.max_reverb",
+            "audio.min_speaking_rate",
+            "audio.max_speaking_rate",
+            "audio.downmix",
+            "audio.auto_trim",
+            "audio.normalize_target",
+            "audio.high_pass_hz",
+            "audio.noise_gate",
+            "audio.keep_raw",
+            "audio.input_device",
             "upload.max_retries",
             "upload.retry_delay_secs",
             "upload.chunk_size",
+            "upload.concurrency",
+            "upload.http2_prior_knowledge",
+            "upload.approved_only",
+            "upload.form.<field>",
+            "upload.headers.<name>",
+            "record.karaoke_wpm",
+            "record.karaoke_min_words",
+            "record.room_tone_secs",
+            "record.countdown_secs",
+            "record.silence_stop_secs",
+            "record.silence_rms_threshold",
+            "record.min_duration_secs",
+            "record.max_duration_secs",
+            "record.stall_timeout_secs",
+            "record.session_timeout_mins",
+            "record.calibration_secs",
+            "record.low_memory",
+            "record.capture_buffer_secs",
+            "record.spill_to_disk",
+            "record.languages",
+            "record.prompts_dir",
+            "record.play_consent",
+            "record.review_takes",
+            "record.consent_form_version",
+            "update.release_url",
+            "update.public_key",
+            "update.timeout_secs",
+            "telemetry.endpoint",
+            "qc.disabled_rules",
+            "reminders.after_days",
+            "reminders.desktop",
+            "asr.model",
+            "asr.misread_wer",
+            "input.devices.<name>.path",
+            "input.devices.<name>.buttons.<button>",
         ]
     }
 }
@@ -266,6 +1710,53 @@ pub struct Credentials {
     pub api_key: Option<String>,
     pub username: Option<String>,
     pub expires_at: Option<u64>,
+    /// Scopes granted by the server; `None` for tokens issued without scopes,
+    /// which are not restricted locally
+    #[serde(default)]
+    pub scopes: Option<Vec<Scope>>,
+}
+
+/// What a credential may be used for, from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Record locally (enumerators)
+    Record,
+    /// Record and upload
+    Upload,
+    /// Everything, including settings, exports and deletions (coordinators)
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Record => "record",
+            Scope::Upload => "upload",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "record" => Ok(Scope::Record),
+            "upload" => Ok(Scope::Upload),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(anyhow::anyhow!(
+                "Unknown scope: {s} (expected record, upload or admin)"
+            )),
+        }
+    }
 }
 
 impl Credentials {
@@ -312,6 +1803,37 @@ impl Credentials {
         Ok(())
     }
 
+    /// Whether the credential allows `scope`; each scope includes the less
+    /// privileged ones
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|&granted| granted >= scope))
+    }
+
+    /// Refuse `operation` unless the credential allows `scope`
+    pub fn require_scope(&self, scope: Scope, operation: &str) -> Result<()> {
+        if self.has_scope(scope) {
+            return Ok(());
+        }
+
+        let granted = self
+            .scopes
+            .iter()
+            .flatten()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(anyhow::anyhow!(
+            "{} is not allowed for {} (scopes: {}); it needs the {} scope. Ask a coordinator, or run: cowcow auth login --scope {}",
+            operation,
+            self.username.as_deref().unwrap_or("this account"),
+            if granted.is_empty() { "none" } else { &granted },
+            scope,
+            scope
+        ))
+    }
+
     pub fn is_valid(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
             let now = std::time::SystemTime::now()
@@ -341,3 +1863,59 @@ impl Credentials {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_rules_on_unknown_metrics() {
+        let mut config = Config::default();
+        config.validate().unwrap();
+        for rule in &config.qc_policy().rules {
+            assert!(
+                cowcow_core::policy::known_metrics().contains(&rule.metric),
+                "{}",
+                rule.metric
+            );
+        }
+
+        config
+            .qc
+            .rules
+            .push(QcRule::max("max_hum", "hum_db", -30.0));
+        config
+            .qc
+            .rules
+            .push(QcRule::min("min_loudness", "loudness_lufs", -30.0));
+        config.validate().unwrap();
+
+        config.qc.rules.push(QcRule::min("min_snr", "snr_bd", 25.0));
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("'snr_bd'"), "{error}");
+    }
+
+    #[test]
+    fn test_silero_backend_without_the_feature_still_loads() {
+        let mut config = Config::default();
+        config.audio.vad_backend = "silero".to_string();
+        config.validate().unwrap();
+
+        let mut config = Config::default();
+        let set = config.set_value("audio.vad_backend", "silero");
+        assert_eq!(set.is_ok(), cfg!(feature = "silero"));
+        config.set_value("audio.vad_backend", "webrtc").unwrap();
+    }
+
+    #[test]
+    fn test_opus_format_without_the_feature_still_loads() {
+        let mut config = Config::default();
+        config.audio.format = "opus".to_string();
+        config.validate().unwrap();
+
+        let mut config = Config::default();
+        let set = config.set_value("audio.format", "Opus");
+        assert_eq!(set.is_ok(), cfg!(feature = "opus"));
+        config.set_value("audio.format", "wav").unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, OnceLock};
+use tracing::warn;
+
+/// What a pedal or button press does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControllerAction {
+    /// Start a take, or stop and keep the one being recorded
+    Record,
+    /// Pause or resume the take
+    Pause,
+    /// Go on to the next prompt of a script, skipping it if it was not
+    /// recorded
+    Next,
+}
+
+impl FromStr for ControllerAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "record" => Ok(ControllerAction::Record),
+            "pause" => Ok(ControllerAction::Pause),
+            "next" => Ok(ControllerAction::Next),
+            other => Err(format!(
+                "Unknown action '{other}' (expected record, pause or next)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ControllerAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ControllerAction::Record => "record",
+            ControllerAction::Pause => "pause",
+            ControllerAction::Next => "next",
+        })
+    }
+}
+
+/// A foot pedal, USB button or similar controller
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ControllerDevice {
+    /// Input device read directly, on Linux (e.g.
+    /// `/dev/input/by-id/usb-VEC_USB_Footpedal-event-if00`); buttons are
+    /// then input event names like `BTN_0` or codes. Without a path the
+    /// device is one that types keys, and buttons are key names like `F13`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub buttons: BTreeMap<String, ControllerAction>,
+}
+
+impl ControllerDevice {
+    pub fn validate(&self, name: &str) -> Result<()> {
+        for button in self.buttons.keys() {
+            let known = if self.path.is_some() {
+                event_code(button).is_some()
+            } else {
+                key_code(button).is_some()
+            };
+            if !known {
+                anyhow::bail!("Unknown button '{button}' of input device '{name}'");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Actions of the configured controllers, once [`start`] has run
+struct Controllers {
+    keys: HashMap<KeyCode, ControllerAction>,
+    /// Presses from the devices read directly
+    events: Mutex<Receiver<ControllerAction>>,
+    read_directly: bool,
+}
+
+static CONTROLLERS: OnceLock<Controllers> = OnceLock::new();
+
+/// Start listening to the configured controllers; devices read directly get
+/// a thread each. Later calls change nothing.
+pub fn start(devices: &BTreeMap<String, ControllerDevice>) {
+    CONTROLLERS.get_or_init(|| {
+        let (tx, rx) = mpsc::channel();
+        let mut keys = HashMap::new();
+        let mut read_directly = false;
+        for (name, device) in devices {
+            let Some(path) = &device.path else {
+                for (button, action) in &device.buttons {
+                    if let Some(code) = key_code(button) {
+                        keys.insert(code, *action);
+                    }
+                }
+                continue;
+            };
+            let buttons: HashMap<u16, ControllerAction> = device
+                .buttons
+                .iter()
+                .filter_map(|(button, action)| Some((event_code(button)?, *action)))
+                .collect();
+            read_directly = true;
+            let (name, path, tx) = (name.clone(), path.clone(), tx.clone());
+            std::thread::spawn(move || {
+                if let Err(e) = read_events(&path, &buttons, |action| tx.send(action).is_ok()) {
+                    warn!("Input device '{}' unavailable: {:#}", name, e);
+                }
+            });
+        }
+        Controllers {
+            keys,
+            events: Mutex::new(rx),
+            read_directly,
+        }
+    });
+}
+
+/// Whether any controller is configured and listened to
+pub fn is_active() -> bool {
+    CONTROLLERS
+        .get()
+        .is_some_and(|controllers| !controllers.keys.is_empty() || controllers.read_directly)
+}
+
+/// The action a key typed by a controller stands for
+pub fn action_for_key(key: &KeyEvent) -> Option<ControllerAction> {
+    CONTROLLERS.get()?.keys.get(&key.code).copied()
+}
+
+/// Next pending press of a button of a device read directly, without
+/// blocking
+pub fn poll_action() -> Option<ControllerAction> {
+    CONTROLLERS.get()?.events.lock().ok()?.try_recv().ok()
+}
+
+/// Drop presses of devices read directly that arrived before now, e.g.
+/// during playback, so they do not answer the next question
+pub fn discard_pending_actions() {
+    while poll_action().is_some() {}
+}
+
+/// Key of a controller that types keys, by name: `F13`, `PageDown`,
+/// `Enter`, `Space`, or a single character
+fn key_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    if let Some(n) = name
+        .strip_prefix(['F', 'f'])
+        .and_then(|n| n.parse::<u8>().ok())
+    {
+        return (1..=24).contains(&n).then_some(KeyCode::F(n));
+    }
+    Some(match name.to_ascii_lowercase().as_str() {
+        "enter" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "insert" => KeyCode::Insert,
+        "media_play_pause" => KeyCode::Media(crossterm::event::MediaKeyCode::PlayPause),
+        _ => return None,
+    })
+}
+
+/// Linux input event code of a button, by name (`BTN_0`..`BTN_9`,
+/// `BTN_LEFT`, `BTN_RIGHT`, `BTN_MIDDLE`, `KEY_F13`..`KEY_F24`) or number
+fn event_code(name: &str) -> Option<u16> {
+    if let Ok(code) = name.parse::<u16>() {
+        return Some(code);
+    }
+    let name = name.to_ascii_uppercase();
+    if let Some(n) = name
+        .strip_prefix("BTN_")
+        .and_then(|n| n.parse::<u16>().ok())
+    {
+        return (n <= 9).then_some(0x100 + n);
+    }
+    if let Some(n) = name
+        .strip_prefix("KEY_F")
+        .and_then(|n| n.parse::<u16>().ok())
+    {
+        return (13..=24).contains(&n).then_some(183 + n - 13);
+    }
+    match name.as_str() {
+        "BTN_LEFT" => Some(0x110),
+        "BTN_RIGHT" => Some(0x111),
+        "BTN_MIDDLE" => Some(0x112),
+        _ => None,
+    }
+}
+
+/// Read button presses from a Linux input device until `send` returns false
+#[cfg(target_os = "linux")]
+fn read_events(
+    path: &Path,
+    buttons: &HashMap<u16, ControllerAction>,
+    mut send: impl FnMut(ControllerAction) -> bool,
+) -> Result<()> {
+    use anyhow::Context;
+    use std::io::Read;
+    use tracing::info;
+
+    /// `EV_KEY`, the event type of key and button presses
+    const EV_KEY: u16 = 1;
+    /// `EVIOCGRAB`, `_IOW('E', 0x90, int)`
+    const EVIOCGRAB: u32 = 0x4004_4590;
+
+    let mut device =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    // Take the device for ourselves, so a pedal that also types keys does
+    // not type into the terminal; closing it lets go
+    use std::os::fd::AsRawFd;
+    // SAFETY: the descriptor is open for the call and EVIOCGRAB takes an int
+    if unsafe { libc::ioctl(device.as_raw_fd(), EVIOCGRAB as _, 1 as libc::c_int) } != 0 {
+        warn!(
+            "Could not grab {}; its presses reach other programs too: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        );
+    }
+    info!("Listening to input device {}", path.display());
+    // struct input_event: a timeval, then type, code and value
+    let time_len = 2 * std::mem::size_of::<std::ffi::c_long>();
+    let mut event = vec![0u8; time_len + 8];
+    loop {
+        device
+            .read_exact(&mut event)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let field = |offset: usize| [event[time_len + offset], event[time_len + offset + 1]];
+        let kind = u16::from_ne_bytes(field(0));
+        let code = u16::from_ne_bytes(field(2));
+        let value = i32::from_ne_bytes([
+            event[time_len + 4],
+            event[time_len + 5],
+            event[time_len + 6],
+            event[time_len + 7],
+        ]);
+        // Presses only: releases are 0 and auto-repeats 2
+        if kind != EV_KEY || value != 1 {
+            continue;
+        }
+        if let Some(action) = buttons.get(&code) {
+            if !send(*action) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_events(
+    path: &Path,
+    _buttons: &HashMap<u16, ControllerAction>,
+    _send: impl FnMut(ControllerAction) -> bool,
+) -> Result<()> {
+    anyhow::bail!(
+        "Reading {} directly needs Linux; configure the device to type keys instead",
+        path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::MediaKeyCode;
+
+    #[test]
+    fn test_key_code() {
+        assert_eq!(key_code("F13"), Some(KeyCode::F(13)));
+        assert_eq!(key_code("f5"), Some(KeyCode::F(5)));
+        assert_eq!(key_code("F25"), None);
+        assert_eq!(key_code("F0"), None);
+        assert_eq!(key_code("b"), Some(KeyCode::Char('b')));
+        assert_eq!(key_code("F"), Some(KeyCode::Char('F')));
+        assert_eq!(key_code("PageDown"), Some(KeyCode::PageDown));
+        assert_eq!(key_code("space"), Some(KeyCode::Char(' ')));
+        assert_eq!(
+            key_code("media_play_pause"),
+            Some(KeyCode::Media(MediaKeyCode::PlayPause))
+        );
+        assert_eq!(key_code("Fn"), None);
+        assert_eq!(key_code(""), None);
+    }
+
+    #[test]
+    fn test_event_code() {
+        assert_eq!(event_code("BTN_0"), Some(0x100));
+        assert_eq!(event_code("btn_9"), Some(0x109));
+        assert_eq!(event_code("BTN_10"), None);
+        assert_eq!(event_code("BTN_LEFT"), Some(0x110));
+        assert_eq!(event_code("BTN_MIDDLE"), Some(0x112));
+        // KEY_F13 is 183 and KEY_F24 194 in linux/input-event-codes.h
+        assert_eq!(event_code("KEY_F13"), Some(183));
+        assert_eq!(event_code("key_f24"), Some(194));
+        assert_eq!(event_code("KEY_F12"), None);
+        assert_eq!(event_code("288"), Some(288));
+        assert_eq!(event_code("BTN_TRIGGER_HAPPY"), None);
+    }
+
+    #[test]
+    fn test_validate_checks_buttons_by_device_kind() {
+        let mut device = ControllerDevice::default();
+        device
+            .buttons
+            .insert("F13".to_string(), ControllerAction::Record);
+        device.validate("pedal").unwrap();
+
+        // Read directly, buttons are input event names
+        device.path = Some(PathBuf::from("/dev/input/event7"));
+        let error = device.validate("pedal").unwrap_err().to_string();
+        assert!(error.contains("'F13'"), "{error}");
+        device.buttons.clear();
+        device
+            .buttons
+            .insert("KEY_F13".to_string(), ControllerAction::Pause);
+        device.validate("pedal").unwrap();
+
+        assert_eq!("Next".parse(), Ok(ControllerAction::Next));
+        assert!("stop".parse::<ControllerAction>().is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::streak;
+use crate::upload::UploadEvent;
+
+/// How often the daemon checks whether a reminder is due
+const REMINDER_CHECK: Duration = Duration::from_secs(3600);
+
+/// What a `cowcow` invocation asks of the daemon, one JSON line per
+/// connection
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Upload { force: bool, requalify: bool },
+}
+
+/// Status lines the daemon streams back until the request is served
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Reply {
+    /// The request joined the next upload run; `behind` says another run
+    /// has to finish first
+    Queued {
+        behind: bool,
+    },
+    Progress {
+        event: UploadEvent,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Control socket of the daemon
+pub fn socket_path(config: &Config) -> PathBuf {
+    config.data_dir().join("daemon.sock")
+}
+
+/// Hand an upload to a running daemon and print its progress; returns
+/// `false` when no daemon is listening
+#[cfg(unix)]
+pub async fn delegate_upload(config: &Config, force: bool, requalify: bool) -> Result<bool> {
+    use anyhow::Context;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let Ok(stream) = UnixStream::connect(socket_path(config)).await else {
+        return Ok(false);
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut request = serde_json::to_string(&Request::Upload { force, requalify })?;
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .context("Lost connection to the daemon")?
+    {
+        match serde_json::from_str::<Reply>(&line)? {
+            Reply::Queued { behind } => {
+                println!("📡 Upload handed to the running daemon");
+                if behind {
+                    println!("   Waiting for its current upload to finish...");
+                }
+            }
+            Reply::Progress { event } => match event {
+                UploadEvent::Started { pending } => {
+                    println!("⬆️  Uploading {pending} recording(s)");
+                }
+                UploadEvent::Uploaded {
+                    recording_id,
+                    tokens_awarded,
+                } => {
+                    if tokens_awarded > 0 {
+                        println!("✅ {recording_id}: +{tokens_awarded} tokens");
+                    } else {
+                        println!("✅ {recording_id}");
+                    }
+                }
+                UploadEvent::Failed { recording_id } => {
+                    println!("❌ {recording_id}: upload failed");
+                }
+                UploadEvent::Finished { uploaded, failed } => {
+                    println!("Upload summary: {uploaded} successful, {failed} failed");
+                    return Ok(true);
+                }
+            },
+            Reply::Error { message } => return Err(anyhow::anyhow!(message)),
+        }
+    }
+    Err(anyhow::anyhow!("The daemon closed the connection"))
+}
+
+#[cfg(not(unix))]
+pub async fn delegate_upload(_config: &Config, _force: bool, _requalify: bool) -> Result<bool> {
+    Ok(false)
+}
+
+/// Upload runs waiting to start, merged into one
+#[cfg(unix)]
+#[derive(Default)]
+struct Queue {
+    /// Run the waiting requests will be served by
+    next_run: u64,
+    running: bool,
+    /// Flags of the waiting requests, if any
+    waiting: Option<Request>,
+}
+
+/// Upload pending recordings every `interval` (zero only on request) until
+/// `cancel` fires, serving `cowcow upload` invocations over the control
+/// socket, and send reminder notifications when they are due
+///
+/// Invocations arriving while a run is in progress are coalesced into a
+/// single follow-up run, so there is never more than one uploader.
+#[cfg(unix)]
+pub async fn run(
+    config: &Config,
+    db: &SqlitePool,
+    interval: Duration,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    use anyhow::Context;
+    use std::sync::{Arc, Mutex};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::{broadcast, Notify};
+    use tracing::{info, warn};
+
+    let path = socket_path(config);
+    if UnixStream::connect(&path).await.is_ok() {
+        return Err(anyhow::anyhow!("A daemon is already running"));
+    }
+    // A socket left behind by a daemon that did not shut down cleanly
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    info!("Daemon listening on {}", path.display());
+
+    let queue = Arc::new(Mutex::new(Queue::default()));
+    let wake = Arc::new(Notify::new());
+    let (replies, _) = broadcast::channel::<(u64, Reply)>(256);
+
+    let accept = {
+        let (queue, wake, replies) = (queue.clone(), wake.clone(), replies.clone());
+        async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                };
+                let (queue, wake, replies) = (queue.clone(), wake.clone(), replies.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &queue, &wake, &replies).await {
+                        warn!("Daemon client failed: {e:#}");
+                    }
+                });
+            }
+        }
+    };
+
+    let uploads = async {
+        let mut ticker = (!interval.is_zero()).then(|| tokio::time::interval(interval));
+        loop {
+            let tick = async {
+                match ticker.as_mut() {
+                    Some(ticker) => {
+                        ticker.tick().await;
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = tick => {}
+                _ = wake.notified() => {}
+                _ = cancel.cancelled() => break,
+            }
+
+            let (run_id, request) = {
+                let mut queue = queue.lock().unwrap();
+                let run_id = queue.next_run;
+                queue.next_run += 1;
+                queue.running = true;
+                let request = queue.waiting.take().unwrap_or(Request::Upload {
+                    force: false,
+                    requalify: false,
+                });
+                (run_id, request)
+            };
+            let Request::Upload { force, requalify } = request;
+
+            if let Err(e) = upload_run(config, db, force, requalify, cancel, |reply| {
+                let _ = replies.send((run_id, reply));
+            })
+            .await
+            {
+                warn!("Upload run failed: {e:#}");
+                let _ = replies.send((
+                    run_id,
+                    Reply::Error {
+                        message: format!("{e:#}"),
+                    },
+                ));
+            }
+
+            let mut queue = queue.lock().unwrap();
+            queue.running = false;
+            if queue.waiting.is_some() {
+                wake.notify_one();
+            }
+        }
+    };
+
+    let reminders = async {
+        if !config.reminders.desktop {
+            return std::future::pending().await;
+        }
+        let mut ticker = tokio::time::interval(REMINDER_CHECK);
+        loop {
+            ticker.tick().await;
+            remind(config, db).await;
+        }
+    };
+
+    tokio::select! {
+        _ = accept => {}
+        _ = uploads => {}
+        _ = reminders => {}
+    }
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run(
+    _config: &Config,
+    _db: &SqlitePool,
+    _interval: Duration,
+    _cancel: &CancellationToken,
+) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "The daemon needs Unix domain sockets, which this platform lacks"
+    ))
+}
+
+/// Send the reminder due now as a desktop notification; one that cannot be
+/// shown is left for the terminal on the next run
+#[cfg(unix)]
+async fn remind(config: &Config, db: &SqlitePool) {
+    match streak::due_reminder(config, db).await {
+        Ok(Some(message)) => {
+            let shown = tokio::task::spawn_blocking(move || streak::notify_desktop(&message))
+                .await
+                .unwrap_or(false);
+            if shown {
+                streak::mark_reminded(config);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check for a reminder: {e:#}"),
+    }
+}
+
+/// Queue one invocation's request and stream the run serving it back
+#[cfg(unix)]
+async fn serve(
+    stream: tokio::net::UnixStream,
+    queue: &std::sync::Mutex<Queue>,
+    wake: &tokio::sync::Notify,
+    replies: &tokio::sync::broadcast::Sender<(u64, Reply)>,
+) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let Some(line) = BufReader::new(reader).lines().next_line().await? else {
+        return Ok(());
+    };
+    let Request::Upload { force, requalify } = serde_json::from_str(&line)?;
+
+    // Subscribe before queueing so no reply of the serving run is missed
+    let mut received = replies.subscribe();
+    let (run_id, behind) = {
+        let mut queue = queue.lock().unwrap();
+        let (was_force, was_requalify) = match queue.waiting {
+            Some(Request::Upload { force, requalify }) => (force, requalify),
+            None => (false, false),
+        };
+        queue.waiting = Some(Request::Upload {
+            force: force || was_force,
+            requalify: requalify || was_requalify,
+        });
+        (queue.next_run, queue.running)
+    };
+    wake.notify_one();
+
+    let encode = |reply: &Reply| {
+        let mut line = serde_json::to_string(reply).unwrap_or_default();
+        line.push('\n');
+        line
+    };
+    writer
+        .write_all(encode(&Reply::Queued { behind }).as_bytes())
+        .await?;
+    loop {
+        let (id, reply) = match received.recv().await {
+            Ok(received) => received,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+        if id != run_id {
+            continue;
+        }
+        writer.write_all(encode(&reply).as_bytes()).await?;
+        if matches!(
+            reply,
+            Reply::Error { .. }
+                | Reply::Progress {
+                    event: UploadEvent::Finished { .. }
+                }
+        ) {
+            return Ok(());
+        }
+    }
+}
+
+/// One upload run as `cowcow upload` would do it, reporting its progress
+#[cfg(unix)]
+async fn upload_run(
+    config: &Config,
+    db: &SqlitePool,
+    force: bool,
+    requalify: bool,
+    cancel: &CancellationToken,
+    report: impl Fn(Reply),
+) -> Result<()> {
+    use crate::auth::AuthClient;
+    use crate::config::Scope;
+    use crate::upload::UploadClient;
+    use tokio::sync::mpsc;
+
+    let credentials = AuthClient::new(config.clone())
+        .check_auth()
+        .await
+        .map_err(|_| anyhow::anyhow!("The daemon is not logged in; run `cowcow auth login`"))?;
+    credentials.require_scope(Scope::Upload, "Uploading")?;
+
+    let (events, mut received) = mpsc::unbounded_channel();
+    let upload = async move {
+        // Owning the client here drops its event sender when the run ends
+        UploadClient::new(config.clone())
+            .with_events(events)
+            .upload_pending_recordings(db, &credentials, force, requalify, cancel)
+            .await
+    };
+    let forward = async {
+        while let Some(event) = received.recv().await {
+            report(Reply::Progress { event });
+        }
+    };
+    let (result, ()) = tokio::join!(upload, forward);
+    result
+}
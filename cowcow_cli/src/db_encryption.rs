@@ -0,0 +1,342 @@
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::Connection;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// Environment variable holding the database passphrase, for scripts and
+/// services without a keyring
+pub const PASSPHRASE_ENV: &str = "COWCOW_DB_PASSPHRASE";
+
+/// Keyring service and account the passphrase is kept under
+const KEYRING_SERVICE: &str = "cowcow";
+const KEYRING_ACCOUNT: &str = "database";
+
+/// First bytes of every unencrypted SQLite database
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+/// The `PRAGMA key` value to open the database at `path` with, or `None`
+/// to open it unencrypted
+///
+/// With `enabled`, a `writable` database still stored unencrypted is
+/// encrypted in place first; a read-only one is opened as it is.
+pub async fn prepare(path: &Path, enabled: bool, writable: bool) -> Result<Option<String>> {
+    let plaintext = is_plaintext(path);
+    if !enabled {
+        if plaintext == Some(false) {
+            anyhow::bail!(
+                "{} is encrypted; set storage.db_encryption = true to open it",
+                path.display()
+            );
+        }
+        return Ok(None);
+    }
+    if !cfg!(feature = "sqlcipher") {
+        anyhow::bail!("Database encryption needs the CLI built with --features sqlcipher");
+    }
+    if plaintext == Some(true) && !writable {
+        return Ok(None);
+    }
+
+    let passphrase = passphrase(plaintext != Some(false))?;
+    if plaintext == Some(true) {
+        encrypt_in_place(path, passphrase).await?;
+        println!("🔒 Encrypted the database at {}", path.display());
+    }
+    Ok(Some(key_pragma(passphrase)))
+}
+
+/// Whether the passphrase is known without asking for it: already looked up
+/// in this run, or set in the environment
+pub fn passphrase_at_hand() -> bool {
+    PASSPHRASE.get().is_some()
+        || std::env::var(PASSPHRASE_ENV).is_ok_and(|passphrase| !passphrase.is_empty())
+}
+
+/// Rewrite the unencrypted database at `path` as an encrypted copy, then
+/// put the copy in its place
+async fn encrypt_in_place(path: &Path, passphrase: &str) -> Result<()> {
+    let encrypted = path.with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&encrypted);
+
+    // The attached copy is opened with the same flags, so it needs create
+    let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
+        .create_if_missing(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+    sqlx::query(&format!(
+        "ATTACH DATABASE {} AS encrypted KEY {}",
+        key_pragma(&encrypted.to_string_lossy()),
+        key_pragma(passphrase)
+    ))
+    .execute(&mut conn)
+    .await
+    .context("Failed to create the encrypted database")?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut conn)
+        .await
+        .context("Failed to encrypt the database")?;
+    sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    // A journal left next to the old database would be replayed into the
+    // new one
+    for suffix in ["-wal", "-shm"] {
+        let mut journal = path.as_os_str().to_owned();
+        journal.push(suffix);
+        let _ = std::fs::remove_file(journal);
+    }
+    std::fs::rename(&encrypted, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Whether the database at `path` is stored unencrypted; `None` when there
+/// is no database yet
+fn is_plaintext(path: &Path) -> Option<bool> {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path).ok()?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Some(&header == SQLITE_HEADER),
+        // An empty file becomes a database of whichever kind opens it
+        Err(_) => None,
+    }
+}
+
+/// The passphrase SQLCipher derives the database key from: the
+/// `COWCOW_DB_PASSPHRASE` environment variable, then the system keyring,
+/// then asked for on the terminal
+///
+/// A new database with no passphrase anywhere gets a random one, kept in the
+/// keyring when there is one. The passphrase is looked up once per run.
+fn passphrase(new_database: bool) -> Result<&'static str> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ => match keyring_get() {
+            Some(passphrase) => passphrase,
+            None => new_passphrase(new_database)?,
+        },
+    };
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
+
+fn new_passphrase(new_database: bool) -> Result<String> {
+    use std::io::IsTerminal;
+
+    if new_database {
+        let generated = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        if keyring_set(&generated) {
+            info!("Stored a new database passphrase in the system keyring");
+            return Ok(generated);
+        }
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "No database passphrase found; set {} or store it in the system keyring \
+             (service {}, account {})",
+            PASSPHRASE_ENV,
+            KEYRING_SERVICE,
+            KEYRING_ACCOUNT
+        );
+    }
+    print!("🔒 Database passphrase: ");
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let passphrase = rpassword::read_password().context("Failed to read passphrase")?;
+    if new_database {
+        print!("   Repeat it: ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        if rpassword::read_password()? != passphrase {
+            anyhow::bail!("The passphrases do not match");
+        }
+    }
+    if passphrase.is_empty() {
+        anyhow::bail!("The database passphrase cannot be empty");
+    }
+    Ok(passphrase)
+}
+
+/// The passphrase as a quoted SQL string, the form `PRAGMA key` takes
+fn key_pragma(passphrase: &str) -> String {
+    format!("'{}'", passphrase.replace('\'', "''"))
+}
+
+/// The passphrase stored in the system keyring (`secret-tool` on Linux,
+/// `security` on macOS)
+fn keyring_get() -> Option<String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args([
+            "find-generic-password",
+            "-s",
+            KEYRING_SERVICE,
+            "-a",
+            KEYRING_ACCOUNT,
+            "-w",
+        ]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args([
+            "lookup",
+            "service",
+            KEYRING_SERVICE,
+            "account",
+            KEYRING_ACCOUNT,
+        ]);
+        command
+    } else {
+        return None;
+    };
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let passphrase = String::from_utf8(output.stdout)
+        .ok()?
+        .trim_end()
+        .to_string();
+    (output.status.success() && !passphrase.is_empty()).then_some(passphrase)
+}
+
+/// Keep `passphrase` in the system keyring; returns whether it was stored
+fn keyring_set(passphrase: &str) -> bool {
+    use std::io::Write;
+
+    // The secret always goes over stdin, never on the command line where
+    // other users could see it
+    let (mut command, input) = if cfg!(target_os = "macos") {
+        // `security -i` reads its commands from stdin
+        let mut command = Command::new("security");
+        command.arg("-i");
+        let quoted = passphrase.replace('\\', "\\\\").replace('"', "\\\"");
+        let input = format!(
+            "add-generic-password -U -s {KEYRING_SERVICE} -a {KEYRING_ACCOUNT} -w \"{quoted}\"\n"
+        );
+        (command, input)
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args([
+            "store",
+            "--label=cowcow database",
+            "service",
+            KEYRING_SERVICE,
+            "account",
+            KEYRING_ACCOUNT,
+        ]);
+        (command, passphrase.to_string())
+    } else {
+        return false;
+    };
+    let result = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(input.as_bytes())?;
+            }
+            child.wait()
+        });
+    match result {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            warn!("Keyring exited with {}", status);
+            false
+        }
+        Err(e) => {
+            warn!("No system keyring: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    async fn create_database(path: &Path) -> Result<()> {
+        let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))?
+            .create_if_missing(true);
+        let mut conn = SqliteConnection::connect_with(&options).await?;
+        sqlx::query("CREATE TABLE takes (id TEXT); INSERT INTO takes VALUES ('a1')")
+            .execute(&mut conn)
+            .await?;
+        conn.close().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_is_plaintext() {
+        let scratch = scratch_dir();
+        let missing = scratch.path().join("missing.db");
+        assert_eq!(is_plaintext(&missing), None);
+
+        let empty = scratch.path().join("empty.db");
+        std::fs::write(&empty, b"").unwrap();
+        assert_eq!(is_plaintext(&empty), None);
+
+        let scrambled = scratch.path().join("scrambled.db");
+        std::fs::write(&scrambled, [0x5au8; 64]).unwrap();
+        assert_eq!(is_plaintext(&scrambled), Some(false));
+
+        let plain = scratch.path().join("plain.db");
+        create_database(&plain).await.unwrap();
+        assert_eq!(is_plaintext(&plain), Some(true));
+    }
+
+    #[test]
+    fn test_key_pragma_quotes() {
+        assert_eq!(key_pragma("it's"), "'it''s'");
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn test_encrypt_in_place_keeps_the_data() {
+        let scratch = scratch_dir();
+        let path = scratch.path().join("encrypt.db");
+        create_database(&path).await.unwrap();
+        encrypt_in_place(&path, "correct horse").await.unwrap();
+        assert_eq!(is_plaintext(&path), Some(false));
+
+        let open = |passphrase: &str| {
+            SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))
+                .unwrap()
+                .pragma("key", key_pragma(passphrase))
+        };
+        let mut conn = SqliteConnection::connect_with(&open("correct horse"))
+            .await
+            .unwrap();
+        let id: String = sqlx::query_scalar("SELECT id FROM takes")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(id, "a1");
+        conn.close().await.unwrap();
+
+        let mut conn = SqliteConnection::connect_with(&open("wrong"))
+            .await
+            .unwrap();
+        assert!(sqlx::query("SELECT COUNT(*) FROM sqlite_master")
+            .execute(&mut conn)
+            .await
+            .is_err());
+    }
+}
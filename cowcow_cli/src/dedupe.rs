@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use cowcow_core::fingerprint::{fingerprint_wav_file, Fingerprint};
+use serde::Serialize;
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::path::Path;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::delete;
+
+/// Default minimum fingerprint similarity for two recordings to count as
+/// duplicates; unrelated recordings score around 0.5
+pub const DEFAULT_THRESHOLD: f32 = 0.8;
+
+/// A recording whose audio matches an earlier recording
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    pub id: String,
+    /// The earliest matching recording, which is kept
+    pub duplicate_of: String,
+    pub similarity: f32,
+    pub wav_path: String,
+    /// Pinned recordings are flagged but never deleted
+    pub pinned: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct Candidate {
+    id: String,
+    lang: String,
+    wav_path: String,
+    uploaded_at: Option<i64>,
+    pinned: bool,
+    fingerprint: Option<String>,
+}
+
+/// Fingerprint a recording and store it; returns `None` if the audio cannot
+/// be read
+pub async fn store_fingerprint<'e>(
+    db: impl SqliteExecutor<'e>,
+    recording_id: &str,
+    wav_path: &Path,
+) -> Result<Option<Fingerprint>> {
+    let fingerprint = match fingerprint_wav_file(wav_path) {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            warn!("Failed to fingerprint {}: {}", wav_path.display(), e);
+            return Ok(None);
+        }
+    };
+
+    sqlx::query("UPDATE recordings SET fingerprint = ? WHERE id = ?")
+        .bind(fingerprint.to_hex())
+        .bind(recording_id)
+        .execute(db)
+        .await
+        .context("Failed to store fingerprint")?;
+
+    Ok(Some(fingerprint))
+}
+
+/// Find pending recordings whose audio matches an earlier recording in the
+/// same language (uploaded or not)
+///
+/// Recordings made before fingerprinting existed are fingerprinted on the way.
+pub async fn find_duplicates(
+    db: &SqlitePool,
+    config: &Config,
+    threshold: f32,
+) -> Result<Vec<Duplicate>> {
+    let candidates = sqlx::query_as::<_, Candidate>(
+        "SELECT id, lang, wav_path, uploaded_at, pinned, fingerprint FROM recordings ORDER BY created_at",
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    let mut fingerprinted: Vec<(Candidate, Fingerprint)> = Vec::new();
+    for candidate in candidates {
+        let wav_path = config.locate_audio(&candidate.wav_path);
+        let fingerprint = match candidate
+            .fingerprint
+            .as_deref()
+            .and_then(Fingerprint::from_hex)
+        {
+            Some(fingerprint) => Some(fingerprint),
+            None if wav_path.exists() => store_fingerprint(db, &candidate.id, &wav_path).await?,
+            None => None,
+        };
+        if let Some(fingerprint) = fingerprint.filter(|f| !f.is_empty()) {
+            fingerprinted.push((candidate, fingerprint));
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for (index, (candidate, fingerprint)) in fingerprinted.iter().enumerate() {
+        if candidate.uploaded_at.is_some() {
+            continue;
+        }
+
+        let original = fingerprinted[..index]
+            .iter()
+            .filter(|(earlier, _)| earlier.lang == candidate.lang)
+            .filter(|(earlier, _)| !duplicates.iter().any(|d: &Duplicate| d.id == earlier.id))
+            .filter(|(_, earlier)| comparable_length(earlier, fingerprint))
+            .map(|(earlier, earlier_fp)| (earlier, earlier_fp.similarity(fingerprint)))
+            .find(|(_, similarity)| *similarity >= threshold);
+
+        if let Some((earlier, similarity)) = original {
+            duplicates.push(Duplicate {
+                id: candidate.id.clone(),
+                duplicate_of: earlier.id.clone(),
+                similarity,
+                wav_path: candidate.wav_path.clone(),
+                pinned: candidate.pinned,
+            });
+        }
+    }
+
+    Ok(duplicates)
+}
+
+/// Mark a duplicate so uploads skip it
+pub async fn flag_duplicate(db: &SqlitePool, duplicate: &Duplicate) -> Result<()> {
+    sqlx::query("UPDATE recordings SET duplicate_of = ? WHERE id = ?")
+        .bind(&duplicate.duplicate_of)
+        .bind(&duplicate.id)
+        .execute(db)
+        .await
+        .context("Failed to flag duplicate")?;
+    Ok(())
+}
+
+/// Delete a duplicate as `cowcow delete` would, so its prompt is recounted
+/// and its take and translation links are cleaned up
+pub async fn delete_duplicate(
+    db: &SqlitePool,
+    config: &Config,
+    duplicate: &Duplicate,
+) -> Result<()> {
+    let targets = delete::targets(db, config, std::slice::from_ref(&duplicate.id)).await?;
+    delete::delete_recordings(db, &targets).await
+}
+
+/// Copies may be trimmed, but not to less than half the original
+fn comparable_length(a: &Fingerprint, b: &Fingerprint) -> bool {
+    let (short, long) = if a.0.len() < b.0.len() {
+        (a.0.len(), b.0.len())
+    } else {
+        (b.0.len(), a.0.len())
+    };
+    short * 2 >= long
+}
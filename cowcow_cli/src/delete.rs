@@ -0,0 +1,373 @@
+use anyhow::{Context, Result};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::{gc, prompts, storage, takes, translations};
+
+/// A recording `cowcow delete` matched
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Target {
+    pub id: String,
+    pub lang: String,
+    pub wav_path: String,
+    pub uploaded_at: Option<i64>,
+    /// Pinned recordings are never deleted
+    pub pinned: bool,
+    pub duration_secs: Option<f64>,
+    take_group: Option<String>,
+    translation_pair: Option<String>,
+    prompt_id: Option<String>,
+}
+
+impl Target {
+    pub fn uploaded(&self) -> bool {
+        self.uploaded_at.is_some()
+    }
+
+    /// Bytes on disk of the audio and sidecars deleting it would free
+    pub fn bytes(&self) -> u64 {
+        storage::recording_files(Path::new(&self.wav_path))
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+}
+
+/// The recordings with the given IDs, in that order, with their audio
+/// located under `config`'s recordings directory
+pub async fn targets(db: &SqlitePool, config: &Config, ids: &[String]) -> Result<Vec<Target>> {
+    let mut targets = Vec::with_capacity(ids.len());
+    for id in ids {
+        let target = sqlx::query_as::<_, Target>(
+            r#"
+            SELECT id, lang, wav_path, uploaded_at, pinned,
+                   CAST(json_extract(qc_metrics, '$.duration_secs') AS REAL) AS duration_secs,
+                   take_group, translation_pair, prompt_id
+            FROM recordings WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .context("Failed to fetch recording")?;
+        targets.extend(target.map(|target| {
+            Target {
+                wav_path: config
+                    .locate_audio(&target.wav_path)
+                    .to_string_lossy()
+                    .into_owned(),
+                ..target
+            }
+        }));
+    }
+    Ok(targets)
+}
+
+/// Remove recordings' database rows in one transaction, then their files
+///
+/// Another take of each prompt is selected if the selected one went, the
+/// other side of a translation pair is left unpaired, prompts are recounted
+/// and merge provenance is dropped, so a merge can bring a bundle's copy
+/// back. Files are only removed once the rows are gone for good: a failed
+/// transaction leaves everything as it was.
+pub async fn delete_recordings(db: &SqlitePool, targets: &[Target]) -> Result<()> {
+    let mut tx = db.begin().await?;
+    for target in targets {
+        delete_rows(&mut tx, target).await?;
+    }
+    tx.commit().await.context("Failed to delete recordings")?;
+
+    for target in targets {
+        storage::remove_recording_files(Path::new(&target.wav_path))?;
+    }
+    Ok(())
+}
+
+async fn delete_rows(db: &mut SqliteConnection, target: &Target) -> Result<()> {
+    sqlx::query("DELETE FROM upload_queue WHERE recording_id = ?")
+        .bind(&target.id)
+        .execute(&mut *db)
+        .await
+        .context("Failed to remove from upload queue")?;
+    sqlx::query("DELETE FROM merge_provenance WHERE recording_id = ?")
+        .bind(&target.id)
+        .execute(&mut *db)
+        .await
+        .context("Failed to remove merge provenance")?;
+    sqlx::query("DELETE FROM recordings WHERE id = ?")
+        .bind(&target.id)
+        .execute(&mut *db)
+        .await
+        .context("Failed to delete recording")?;
+
+    if let Some(group) = &target.take_group {
+        takes::forget_take(&mut *db, group).await?;
+    }
+    if let Some(pair) = &target.translation_pair {
+        translations::unpair(&mut *db, pair).await?;
+    }
+    if let Some(prompt_id) = &target.prompt_id {
+        prompts::refresh_count(&mut *db, prompt_id).await?;
+    }
+    Ok(())
+}
+
+/// `cowcow delete`: delete the recordings with the given IDs, keeping pinned
+/// ones and, with `uploaded_only`, ones not uploaded yet
+pub async fn run(
+    ids: &[String],
+    uploaded_only: bool,
+    dry_run: bool,
+    yes: bool,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    let mut targets = targets(db, config, ids).await?;
+    for target in &mut targets {
+        target.wav_path = storage::locate_audio(&target.wav_path, &config.recordings_dir())
+            .to_string_lossy()
+            .into_owned();
+    }
+    let pinned = targets.iter().filter(|target| target.pinned).count();
+    let not_uploaded = targets
+        .iter()
+        .filter(|target| !target.pinned && !target.uploaded())
+        .count();
+    targets.retain(|target| !target.pinned && (target.uploaded() || !uploaded_only));
+
+    if pinned > 0 {
+        println!("📌 Keeping {pinned} pinned recording(s)");
+    }
+    if uploaded_only && not_uploaded > 0 {
+        println!("⏳ Keeping {not_uploaded} recording(s) not uploaded yet");
+    }
+    if targets.is_empty() {
+        println!("No recordings to delete.");
+        return Ok(());
+    }
+
+    for target in &targets {
+        println!(
+            "  {}  {:<5}  {:>6}  {}",
+            target.id,
+            target.lang,
+            target
+                .duration_secs
+                .map_or("-".to_string(), |secs| format!("{secs:.1}s")),
+            if target.uploaded() {
+                "uploaded"
+            } else {
+                "pending"
+            }
+        );
+    }
+    let bytes: u64 = targets.iter().map(Target::bytes).sum();
+    let pending = targets.iter().filter(|target| !target.uploaded()).count();
+    if dry_run {
+        println!(
+            "🗑️  {} recording(s) ({}) would be deleted; run without --dry-run to delete them",
+            targets.len(),
+            gc::format_bytes(bytes)
+        );
+        return Ok(());
+    }
+
+    if pending > 0 {
+        println!("⚠️  {pending} of them have not been uploaded; their audio will be lost");
+    }
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("Deleting needs confirmation; pass --yes to delete without asking");
+        }
+        let answer = crate::ask(
+            &format!(
+                "Delete {} recording(s) ({})? [y/N]",
+                targets.len(),
+                gc::format_bytes(bytes)
+            ),
+            "",
+        )?;
+        if !answer.eq_ignore_ascii_case("y") && !answer.eq_ignore_ascii_case("yes") {
+            println!("Nothing was deleted");
+            return Ok(());
+        }
+    }
+
+    delete_recordings(db, &targets).await?;
+    println!(
+        "🗑️  Deleted {} recording(s), freeing {}",
+        targets.len(),
+        gc::format_bytes(bytes)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+    use std::path::PathBuf;
+
+    /// A library holding prompt `p1`
+    async fn library() -> TestLibrary {
+        let library = TestLibrary::new().await;
+        sqlx::query(
+            "INSERT INTO prompts (id, lang, text, created_at) VALUES ('p1', 'sw', 'habari', 0)",
+        )
+        .execute(&library.db)
+        .await
+        .unwrap();
+        library
+    }
+
+    /// A recording of prompt `p1` with its audio on disk
+    async fn add(library: &TestLibrary, id: &str, pinned: bool, uploaded: bool) -> PathBuf {
+        let wav_path = library.config.recordings_dir().join(format!("{id}.wav"));
+        fs::write(&wav_path, b"RIFF").unwrap();
+        sqlx::query(
+            "INSERT INTO recordings (id, lang, prompt, qc_metrics, created_at, wav_path, \
+             pinned, uploaded_at, prompt_id) VALUES (?, 'sw', 'habari', '{}', 0, ?, ?, ?, 'p1')",
+        )
+        .bind(id)
+        .bind(wav_path.to_string_lossy())
+        .bind(pinned)
+        .bind(uploaded.then_some(1))
+        .execute(&library.db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO upload_queue (recording_id, attempts, last_attempt) VALUES (?, 0, 0)",
+        )
+        .bind(id)
+        .execute(&library.db)
+        .await
+        .unwrap();
+        prompts::refresh_count(&library.db, "p1").await.unwrap();
+        wav_path
+    }
+
+    async fn stored_ids(library: &TestLibrary) -> Vec<String> {
+        sqlx::query_scalar("SELECT id FROM recordings ORDER BY id")
+            .fetch_all(&library.db)
+            .await
+            .unwrap()
+    }
+
+    async fn times_recorded(library: &TestLibrary) -> i64 {
+        sqlx::query_scalar("SELECT times_recorded FROM prompts WHERE id = 'p1'")
+            .fetch_one(&library.db)
+            .await
+            .unwrap()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_pinned_recordings() {
+        let library = library().await;
+        let kept = add(&library, "a", true, false).await;
+        let deleted = add(&library, "b", false, false).await;
+        sqlx::query(
+            "INSERT INTO merge_provenance VALUES ('bundle', 'x', 'b', 'hash', 'imported', 0)",
+        )
+        .execute(&library.db)
+        .await
+        .unwrap();
+
+        run(
+            &ids(&["a", "b"]),
+            false,
+            false,
+            true,
+            &library.db,
+            &library.config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stored_ids(&library).await, ids(&["a"]));
+        assert!(kept.exists() && !deleted.exists());
+        assert_eq!(times_recorded(&library).await, 1);
+        let provenance: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM merge_provenance")
+            .fetch_one(&library.db)
+            .await
+            .unwrap();
+        assert_eq!(provenance, 0);
+        let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM upload_queue")
+            .fetch_one(&library.db)
+            .await
+            .unwrap();
+        assert_eq!(queued, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_uploaded_only_keeps_pending_recordings() {
+        let library = library().await;
+        let pending = add(&library, "a", false, false).await;
+        add(&library, "b", false, true).await;
+
+        run(
+            &ids(&["a", "b"]),
+            true,
+            false,
+            true,
+            &library.db,
+            &library.config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stored_ids(&library).await, ids(&["a"]));
+        assert!(pending.exists());
+    }
+
+    #[tokio::test]
+    async fn test_delete_dry_run_changes_nothing() {
+        let library = library().await;
+        let a = add(&library, "a", false, true).await;
+        let b = add(&library, "b", false, false).await;
+
+        run(
+            &ids(&["a", "b"]),
+            false,
+            true,
+            true,
+            &library.db,
+            &library.config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stored_ids(&library).await, ids(&["a", "b"]));
+        assert!(a.exists() && b.exists());
+        assert_eq!(times_recorded(&library).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_audio_of_a_moved_data_dir() {
+        let library = library().await;
+        // Recorded before the data directory was copied here
+        let wav_path = library.config.recordings_dir().join("sw").join("a.wav");
+        fs::create_dir_all(wav_path.parent().unwrap()).unwrap();
+        fs::write(&wav_path, b"RIFF").unwrap();
+        library
+            .add_recording("a", "/old/data/recordings/sw/a.wav")
+            .await;
+
+        run(
+            &ids(&["a"]),
+            false,
+            false,
+            true,
+            &library.db,
+            &library.config,
+        )
+        .await
+        .unwrap();
+        assert!(stored_ids(&library).await.is_empty());
+        assert!(!wav_path.exists());
+    }
+}
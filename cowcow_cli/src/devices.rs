@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait};
+
+/// Sample rates listed for a device when its supported ranges include them
+const COMMON_SAMPLE_RATES: [u32; 9] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 96000, 192000,
+];
+
+/// An input device and the capture formats it supports
+#[derive(Debug, Clone)]
+pub struct InputDevice {
+    /// Position in the listing, from 1, accepted by `--device`
+    pub index: usize,
+    pub name: String,
+    pub is_default: bool,
+    pub channels: Vec<u16>,
+    pub sample_rates: Vec<u32>,
+    /// Sample rate and channel count the device opens with by default
+    pub default_format: Option<(u32, u16)>,
+}
+
+/// Name identifying an input device in the calibration config
+pub fn device_name(device: &cpal::Device) -> String {
+    device.name().unwrap_or_else(|_| "default".to_string())
+}
+
+/// Input devices of the default host, in the order they are listed
+pub fn list_input_devices() -> Result<Vec<InputDevice>> {
+    let host = cpal::default_host();
+    let default_name = host
+        .default_input_device()
+        .map(|device| device_name(&device));
+
+    let mut devices = Vec::new();
+    for (i, device) in host
+        .input_devices()
+        .context("Failed to list input devices")?
+        .enumerate()
+    {
+        let name = device_name(&device);
+        let mut channels = Vec::new();
+        let mut sample_rates = Vec::new();
+        if let Ok(configs) = device.supported_input_configs() {
+            for range in configs {
+                channels.push(range.channels());
+                sample_rates.extend(COMMON_SAMPLE_RATES.iter().copied().filter(|&rate| {
+                    range.min_sample_rate().0 <= rate && rate <= range.max_sample_rate().0
+                }));
+            }
+        }
+        channels.sort_unstable();
+        channels.dedup();
+        sample_rates.sort_unstable();
+        sample_rates.dedup();
+
+        devices.push(InputDevice {
+            index: i + 1,
+            is_default: default_name.as_deref() == Some(name.as_str()),
+            name,
+            channels,
+            sample_rates,
+            default_format: device
+                .default_input_config()
+                .ok()
+                .map(|config| (config.sample_rate().0, config.channels())),
+        });
+    }
+    Ok(devices)
+}
+
+/// Input device by name, or by its number in `cowcow devices`
+pub fn find_input_device(selector: &str) -> Result<cpal::Device> {
+    let devices: Vec<cpal::Device> = cpal::default_host()
+        .input_devices()
+        .context("Failed to list input devices")?
+        .collect();
+
+    let by_name = devices
+        .iter()
+        .position(|device| device_name(device) == selector);
+    let by_index = || {
+        selector
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| index.checked_sub(1))
+            .filter(|&index| index < devices.len())
+    };
+    by_name
+        .or_else(by_index)
+        .and_then(|index| devices.into_iter().nth(index))
+        .with_context(|| format!("Input device not found: {selector} (see `cowcow devices`)"))
+}
+
+/// Print the input devices, marking the system default and `selected`
+pub fn print_devices(devices: &[InputDevice], selected: Option<&str>) {
+    if devices.is_empty() {
+        println!("No input devices found");
+        return;
+    }
+
+    println!("🎤 Input devices:");
+    for device in devices {
+        let mut marks = Vec::new();
+        if device.is_default {
+            marks.push("system default");
+        }
+        if selected
+            .is_some_and(|selected| selected == device.name || selected == device.index.to_string())
+        {
+            marks.push("selected");
+        }
+        let marks = if marks.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", marks.join(", "))
+        };
+        println!("  {}. {}{marks}", device.index, device.name);
+
+        let join = |values: &[String]| {
+            if values.is_empty() {
+                "unknown".to_string()
+            } else {
+                values.join(", ")
+            }
+        };
+        let rates: Vec<String> = device.sample_rates.iter().map(u32::to_string).collect();
+        let channels: Vec<String> = device.channels.iter().map(u16::to_string).collect();
+        println!("     Sample rates (Hz): {}", join(&rates));
+        println!("     Channels: {}", join(&channels));
+        if let Some((rate, channels)) = device.default_format {
+            println!("     Default: {rate} Hz, {channels} channel(s)");
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::RecordingRow;
+
+/// Tolerance below which QC metric differences are ignored
+const QC_TOLERANCE: f64 = 0.05;
+
+/// One recording as it appears in an export manifest or device database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub lang: String,
+    pub prompt: Option<String>,
+    pub qc_metrics: serde_json::Value,
+    pub created_at: i64,
+    pub uploaded_at: Option<i64>,
+    pub wav_path: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FieldChange {
+    pub id: String,
+    pub field: String,
+    pub a: serde_json::Value,
+    pub b: serde_json::Value,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ContentMatch {
+    pub id_a: String,
+    pub id_b: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DiffReport {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Recordings with different ids but identical audio content
+    pub same_content: Vec<ContentMatch>,
+    pub metadata_changes: Vec<FieldChange>,
+    pub qc_changes: Vec<FieldChange>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+            && self.same_content.is_empty()
+            && self.metadata_changes.is_empty()
+            && self.qc_changes.is_empty()
+    }
+}
+
+/// Load an export manifest (`recordings.json`) or a device database (`cowcow.db`)
+pub async fn load_manifest(path: &Path) -> Result<BTreeMap<String, ManifestEntry>> {
+    let header =
+        fs::read(path).with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+
+    let entries = if header.starts_with(b"SQLite format 3") {
+        load_database(path).await?
+    } else {
+        serde_json::from_slice::<Vec<ManifestEntry>>(&header)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))?
+    };
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| (entry.id.clone(), entry))
+        .collect())
+}
+
+async fn load_database(path: &Path) -> Result<Vec<ManifestEntry>> {
+    let pool = SqlitePool::connect(&format!("sqlite:{}?mode=ro", path.display()))
+        .await
+        .with_context(|| format!("Failed to open database: {}", path.display()))?;
+
+    let rows = sqlx::query_as::<_, RecordingRow>(
+        "SELECT id, lang, prompt, qc_metrics, created_at, uploaded_at, wav_path FROM recordings",
+    )
+    .fetch_all(&pool)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    pool.close().await;
+
+    rows.into_iter()
+        .map(
+            |(id, lang, prompt, qc_metrics, created_at, uploaded_at, wav_path)| {
+                Ok(ManifestEntry {
+                    id,
+                    lang,
+                    prompt,
+                    qc_metrics: serde_json::from_str(&qc_metrics)
+                        .context("Failed to parse QC metrics")?,
+                    created_at,
+                    uploaded_at,
+                    wav_path,
+                })
+            },
+        )
+        .collect()
+}
+
+/// SHA-256 of a recording's audio file, if it is still on disk
+pub fn content_hash(path: &Path) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    Some(hex::encode(Sha256::digest(&data)))
+}
+
+/// Compare two manifests by recording id, optionally pairing unmatched
+/// recordings by audio content hash
+pub fn diff_manifests(
+    a: &BTreeMap<String, ManifestEntry>,
+    b: &BTreeMap<String, ManifestEntry>,
+    by_hash: bool,
+) -> DiffReport {
+    let mut report = DiffReport::default();
+
+    for (id, entry_a) in a {
+        match b.get(id) {
+            Some(entry_b) => compare_entries(entry_a, entry_b, &mut report),
+            None => report.only_in_a.push(id.clone()),
+        }
+    }
+
+    report.only_in_b = b
+        .keys()
+        .filter(|id| !a.contains_key(*id))
+        .cloned()
+        .collect();
+
+    if by_hash {
+        let hashes_b: HashMap<String, String> = report
+            .only_in_b
+            .iter()
+            .filter_map(|id| content_hash(Path::new(&b[id].wav_path)).map(|h| (h, id.clone())))
+            .collect();
+
+        let mut matched_a = Vec::new();
+        for id_a in &report.only_in_a {
+            if let Some(hash) = content_hash(Path::new(&a[id_a].wav_path)) {
+                if let Some(id_b) = hashes_b.get(&hash) {
+                    report.same_content.push(ContentMatch {
+                        id_a: id_a.clone(),
+                        id_b: id_b.clone(),
+                        sha256: hash,
+                    });
+                    matched_a.push(id_a.clone());
+                }
+            }
+        }
+
+        let matched_b: Vec<&String> = report.same_content.iter().map(|m| &m.id_b).collect();
+        report.only_in_a.retain(|id| !matched_a.contains(id));
+        report.only_in_b.retain(|id| !matched_b.contains(&id));
+    }
+
+    report
+}
+
+fn compare_entries(a: &ManifestEntry, b: &ManifestEntry, report: &mut DiffReport) {
+    let mut metadata = |field: &str, value_a: serde_json::Value, value_b: serde_json::Value| {
+        if value_a != value_b {
+            report.metadata_changes.push(FieldChange {
+                id: a.id.clone(),
+                field: field.to_string(),
+                a: value_a,
+                b: value_b,
+            });
+        }
+    };
+
+    metadata("lang", a.lang.clone().into(), b.lang.clone().into());
+    metadata("prompt", a.prompt.clone().into(), b.prompt.clone().into());
+    metadata("created_at", a.created_at.into(), b.created_at.into());
+    metadata("uploaded_at", a.uploaded_at.into(), b.uploaded_at.into());
+
+    let empty = serde_json::Map::new();
+    let qc_a = a.qc_metrics.as_object().unwrap_or(&empty);
+    let qc_b = b.qc_metrics.as_object().unwrap_or(&empty);
+
+    let mut metrics: Vec<&String> = qc_a.keys().chain(qc_b.keys()).collect();
+    metrics.sort();
+    metrics.dedup();
+
+    for metric in metrics {
+        let value_a = qc_a.get(metric).cloned().unwrap_or_default();
+        let value_b = qc_b.get(metric).cloned().unwrap_or_default();
+
+        let changed = match (value_a.as_f64(), value_b.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() > QC_TOLERANCE,
+            _ => value_a != value_b,
+        };
+
+        if changed {
+            report.qc_changes.push(FieldChange {
+                id: a.id.clone(),
+                field: metric.clone(),
+                a: value_a,
+                b: value_b,
+            });
+        }
+    }
+}
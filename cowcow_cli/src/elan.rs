@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Tier of the stretches of speech found by voice activity detection
+pub const SPEECH_TIER: &str = "speech";
+
+/// Tier of what was said: the transcript, or the prompt where there is none
+pub const TRANSCRIPT_TIER: &str = "transcript";
+
+/// What goes into one ELAN annotation file
+#[derive(Debug, Clone)]
+pub struct EafDocument<'a> {
+    /// Exported audio the annotations are linked to
+    pub media_path: &'a Path,
+    pub mime_type: &'a str,
+    pub duration_secs: f32,
+    /// Speech as (start, end) seconds
+    pub speech: &'a [(f32, f32)],
+    pub transcript: Option<&'a str>,
+}
+
+impl EafDocument<'_> {
+    /// The transcript spans the speech, or the whole recording when no
+    /// speech was detected
+    fn transcript_span(&self) -> (f32, f32) {
+        match (self.speech.first(), self.speech.last()) {
+            (Some(first), Some(last)) => (first.0, last.1),
+            _ => (0.0, self.duration_secs),
+        }
+    }
+
+    /// The document as EAF 3.0 XML
+    pub fn to_xml(&self) -> String {
+        let mut slots = Vec::new();
+        let mut slot = |secs: f32| {
+            slots.push((secs * 1000.0).round().max(0.0) as u64);
+            format!("ts{}", slots.len())
+        };
+        let speech: Vec<(String, String)> = self
+            .speech
+            .iter()
+            .map(|&(start, end)| (slot(start), slot(end)))
+            .collect();
+        let transcript = self.transcript.map(|text| {
+            let (start, end) = self.transcript_span();
+            (slot(start), slot(end), text)
+        });
+
+        let media_url = format!("file://{}", self.media_path.display());
+        let relative_url = format!(
+            "./{}",
+            self.media_path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        );
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            xml,
+            r#"<ANNOTATION_DOCUMENT AUTHOR="cowcow" DATE="{}" FORMAT="3.0" VERSION="3.0" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://www.mpi.nl/tools/elan/EAFv3.0.xsd">"#,
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+        );
+        let _ = writeln!(
+            xml,
+            r#"    <HEADER MEDIA_FILE="" TIME_UNITS="milliseconds">"#
+        );
+        let _ = writeln!(
+            xml,
+            r#"        <MEDIA_DESCRIPTOR MEDIA_URL="{}" MIME_TYPE="{}" RELATIVE_MEDIA_URL="{}"/>"#,
+            escape(&media_url),
+            escape(self.mime_type),
+            escape(&relative_url)
+        );
+        let annotations = speech.len() + usize::from(transcript.is_some());
+        let _ = writeln!(
+            xml,
+            r#"        <PROPERTY NAME="lastUsedAnnotationId">{annotations}</PROPERTY>"#
+        );
+        let _ = writeln!(xml, "    </HEADER>");
+
+        let _ = writeln!(xml, "    <TIME_ORDER>");
+        for (i, ms) in slots.iter().enumerate() {
+            let _ = writeln!(
+                xml,
+                r#"        <TIME_SLOT TIME_SLOT_ID="ts{}" TIME_VALUE="{ms}"/>"#,
+                i + 1
+            );
+        }
+        let _ = writeln!(xml, "    </TIME_ORDER>");
+
+        let mut annotation_id = 0;
+        let mut tier = |xml: &mut String, id: &str, annotations: &[(&str, &str, &str)]| {
+            let _ = writeln!(
+                xml,
+                r#"    <TIER LINGUISTIC_TYPE_REF="default-lt" TIER_ID="{id}">"#
+            );
+            for (start, end, value) in annotations {
+                annotation_id += 1;
+                let _ = writeln!(xml, "        <ANNOTATION>");
+                let _ = writeln!(
+                    xml,
+                    r#"            <ALIGNABLE_ANNOTATION ANNOTATION_ID="a{annotation_id}" TIME_SLOT_REF1="{start}" TIME_SLOT_REF2="{end}">"#
+                );
+                let _ = writeln!(
+                    xml,
+                    "                <ANNOTATION_VALUE>{}</ANNOTATION_VALUE>",
+                    escape(value)
+                );
+                let _ = writeln!(xml, "            </ALIGNABLE_ANNOTATION>");
+                let _ = writeln!(xml, "        </ANNOTATION>");
+            }
+            let _ = writeln!(xml, "    </TIER>");
+        };
+        let speech: Vec<(&str, &str, &str)> = speech
+            .iter()
+            .map(|(start, end)| (start.as_str(), end.as_str(), "speech"))
+            .collect();
+        tier(&mut xml, SPEECH_TIER, &speech);
+        let transcript: Vec<(&str, &str, &str)> = transcript
+            .iter()
+            .map(|(start, end, text)| (start.as_str(), end.as_str(), *text))
+            .collect();
+        tier(&mut xml, TRANSCRIPT_TIER, &transcript);
+
+        let _ = writeln!(
+            xml,
+            r#"    <LINGUISTIC_TYPE GRAPHIC_REFERENCES="false" LINGUISTIC_TYPE_ID="default-lt" TIME_ALIGNABLE="true"/>"#
+        );
+        let _ = writeln!(xml, "</ANNOTATION_DOCUMENT>");
+        xml
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_xml())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// `text` made safe for XML content and attribute values
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+/// Files younger than this may still be in use by a running command
+pub const DEFAULT_MIN_AGE: Duration = Duration::from_secs(24 * 3600);
+
+/// File in the data directory whose age says when the startup sweep last
+/// ran; it runs at most once per [`DEFAULT_MIN_AGE`]
+const SWEEP_MARKER: &str = ".last-gc";
+
+/// Why a file is garbage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GarbageKind {
+    /// Temporary or partial file left by an interrupted write
+    Temp,
+    /// Audio or sidecar of a recording the database does not know, left by
+    /// a take that crashed before it was saved
+    OrphanedRecording,
+    /// Room tone of a session the database does not know
+    OrphanedRoomTone,
+}
+
+impl fmt::Display for GarbageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GarbageKind::Temp => "temporary file",
+            GarbageKind::OrphanedRecording => "orphaned recording",
+            GarbageKind::OrphanedRoomTone => "orphaned room tone",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Garbage {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub kind: GarbageKind,
+}
+
+/// Totals of a collection
+#[derive(Debug, Default)]
+pub struct GcSummary {
+    pub removed: usize,
+    pub bytes: u64,
+    pub failed: usize,
+}
+
+/// Whether `name` is one of the names files are written under until they
+/// are complete (`*.part.<ext>`, `*.tmp`, `*.tmp.wav`, `*.trim.wav`)
+pub fn is_temp_name(name: &str) -> bool {
+    name.contains(".part.")
+        || name.ends_with(".tmp")
+        || name.ends_with(".tmp.wav")
+        || name.ends_with(".trim.wav")
+}
+
+/// Files and recordings the database refers to
+struct Referenced {
+    paths: HashSet<PathBuf>,
+    recording_ids: HashSet<String>,
+}
+
+impl Referenced {
+    async fn load(db: &SqlitePool) -> Result<Self> {
+        let recordings: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, wav_path FROM recordings")
+                .fetch_all(db)
+                .await
+                .context("Failed to fetch recordings")?;
+        let room_tones: Vec<(String,)> =
+            sqlx::query_as("SELECT room_tone_path FROM sessions WHERE room_tone_path IS NOT NULL")
+                .fetch_all(db)
+                .await
+                .context("Failed to fetch sessions")?;
+
+        let mut paths = HashSet::new();
+        for path in recordings
+            .iter()
+            .map(|(_, path)| path)
+            .chain(room_tones.iter().map(|(path,)| path))
+        {
+            let path = PathBuf::from(path);
+            if let Ok(canonical) = path.canonicalize() {
+                paths.insert(canonical);
+            }
+            paths.insert(path);
+        }
+        Ok(Self {
+            paths,
+            recording_ids: recordings.into_iter().map(|(id, _)| id).collect(),
+        })
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+            || path
+                .canonicalize()
+                .is_ok_and(|canonical| self.paths.contains(&canonical))
+    }
+}
+
+/// Garbage at least `min_age` old in the data, recordings and spool
+/// directories, plus temporary files in `extra_dirs` (such as export
+/// destinations); nothing the database refers to is ever included
+pub async fn find_garbage(
+    db: &SqlitePool,
+    config: &Config,
+    extra_dirs: &[PathBuf],
+    min_age: Duration,
+) -> Result<Vec<Garbage>> {
+    let referenced = Referenced::load(db).await?;
+    let mut garbage = Vec::new();
+    scan(config.data_dir(), false, None, min_age, &mut garbage)?;
+    for dir in [config.recordings_dir(), config.spool_dir()] {
+        scan(&dir, true, Some(&referenced), min_age, &mut garbage)?;
+    }
+    for dir in extra_dirs {
+        scan(dir, true, None, min_age, &mut garbage)?;
+    }
+    garbage.retain(|item| !referenced.contains(&item.path));
+    garbage.sort_by(|a, b| a.path.cmp(&b.path));
+    garbage.dedup_by(|a, b| a.path == b.path);
+    Ok(garbage)
+}
+
+/// Collect garbage under `dir`: temporary files always, and unknown
+/// recordings and room tone when `referenced` is given
+fn scan(
+    dir: &Path,
+    recursive: bool,
+    referenced: Option<&Referenced>,
+    min_age: Duration,
+    garbage: &mut Vec<Garbage>,
+) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            if recursive {
+                scan(&path, recursive, referenced, min_age, garbage)?;
+            }
+            continue;
+        }
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < min_age {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let kind = if is_temp_name(&name) {
+            Some(GarbageKind::Temp)
+        } else if let Some(referenced) = referenced {
+            let in_room_tone = dir.file_name().is_some_and(|dir| dir == "room_tone");
+            // Recording files are named `<id>.<ext>` or `<id>.<sidecar>.<ext>`
+            let recording_id = name
+                .get(..36)
+                .filter(|id| Uuid::parse_str(id).is_ok() && name[36..].starts_with('.'));
+            match recording_id {
+                _ if referenced.contains(&path) => None,
+                Some(_) if in_room_tone => Some(GarbageKind::OrphanedRoomTone),
+                Some(id) if !referenced.recording_ids.contains(id) => {
+                    Some(GarbageKind::OrphanedRecording)
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            garbage.push(Garbage {
+                path,
+                bytes: metadata.len(),
+                kind,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Delete the garbage, counting what was reclaimed
+pub fn remove(garbage: &[Garbage]) -> GcSummary {
+    let mut summary = GcSummary::default();
+    for item in garbage {
+        match fs::remove_file(&item.path) {
+            Ok(()) => {
+                summary.removed += 1;
+                summary.bytes += item.bytes;
+            }
+            Err(e) => {
+                warn!("Failed to delete {}: {}", item.path.display(), e);
+                summary.failed += 1;
+            }
+        }
+    }
+    summary
+}
+
+/// Delete temporary files left by interrupted commands, at most once a day
+///
+/// Only files with temporary names are swept here; orphaned recordings wait
+/// for `cowcow storage gc`, which shows what it deletes.
+pub fn startup_sweep(config: &Config) {
+    let marker = config.data_dir().join(SWEEP_MARKER);
+    let recently_swept = fs::metadata(&marker)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < DEFAULT_MIN_AGE);
+    if recently_swept || !config.data_dir().exists() {
+        return;
+    }
+
+    let mut garbage = Vec::new();
+    let scanned = scan(
+        config.data_dir(),
+        false,
+        None,
+        DEFAULT_MIN_AGE,
+        &mut garbage,
+    )
+    .and_then(|()| {
+        scan(
+            &config.recordings_dir(),
+            true,
+            None,
+            DEFAULT_MIN_AGE,
+            &mut garbage,
+        )
+    })
+    .and_then(|()| {
+        scan(
+            &config.spool_dir(),
+            true,
+            None,
+            DEFAULT_MIN_AGE,
+            &mut garbage,
+        )
+    });
+    if let Err(e) = scanned {
+        warn!("Failed to sweep temporary files: {:#}", e);
+        return;
+    }
+    garbage.sort_by(|a, b| a.path.cmp(&b.path));
+    garbage.dedup_by(|a, b| a.path == b.path);
+
+    let summary = remove(&garbage);
+    if summary.removed > 0 {
+        info!(
+            "Removed {} leftover temporary files ({})",
+            summary.removed,
+            format_bytes(summary.bytes)
+        );
+    }
+    if let Err(e) = fs::write(&marker, b"") {
+        warn!("Failed to update {}: {}", marker.display(), e);
+    }
+}
+
+/// Size in bytes for people, e.g. `12.3 MB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
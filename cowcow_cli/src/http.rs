@@ -0,0 +1,130 @@
+use reqwest::Client;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::config::Config;
+
+/// How long an idle pooled connection is kept for the next request
+const POOL_IDLE_SECS: u64 = 90;
+
+/// Interval of TCP and HTTP/2 keep-alive probes on pooled connections
+const KEEP_ALIVE_SECS: u64 = 30;
+
+/// HTTP client shared by every request to the API server
+///
+/// Building a client per request (or per command) throws away its
+/// connection pool, so each upload paid a fresh TCP and TLS handshake;
+/// over high-latency links that dominated the time per clip. One client
+/// keeps connections alive between uploads and, when the server speaks
+/// HTTP/2 (negotiated over TLS, or `upload.http2_prior_knowledge` for
+/// plain-text endpoints), multiplexes concurrent uploads over a single
+/// connection. The client is built from the first config it is asked for.
+pub fn shared_client(config: &Config) -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| build_client(config)).clone()
+}
+
+fn build_client(config: &Config) -> Client {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(config.api.timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(POOL_IDLE_SECS))
+        .pool_max_idle_per_host(config.upload.concurrency.max(1))
+        .tcp_keepalive(Duration::from_secs(KEEP_ALIVE_SECS))
+        .tcp_nodelay(true)
+        .http2_adaptive_window(true)
+        .http2_keep_alive_interval(Duration::from_secs(KEEP_ALIVE_SECS))
+        .http2_keep_alive_while_idle(true);
+    if config.upload.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    builder.build().unwrap()
+}
+
+/// Requests currently held back by the rate limiter
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Token bucket holding requests to `api.max_requests_per_sec`
+///
+/// Refills continuously and holds up to one second's worth of requests, so
+/// short bursts go out at once and bulk operations settle at the limit.
+struct Bucket {
+    per_sec: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(per_sec: f64) -> Self {
+        Self {
+            per_sec,
+            tokens: per_sec.max(1.0),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take a token, returning how long to wait before it is there
+    fn take(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.per_sec.max(1.0));
+        self.refilled = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_sec)
+        }
+    }
+}
+
+/// Counts a request as waiting until it is let through or dropped
+struct Waiting;
+
+impl Waiting {
+    fn start() -> Self {
+        WAITING.fetch_add(1, Ordering::Relaxed);
+        Waiting
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        WAITING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Wait for the rate limiter to let a request to the API server through
+///
+/// Every request goes through the same bucket, so parallel uploads and other
+/// calls share the limit; waiting requests go out in the order they came.
+/// Does nothing when `api.max_requests_per_sec` is 0.
+pub async fn throttle(config: &Config) {
+    static BUCKET: OnceLock<Option<Mutex<Bucket>>> = OnceLock::new();
+    let bucket = BUCKET.get_or_init(|| {
+        let per_sec = config.api.max_requests_per_sec;
+        (per_sec > 0.0).then(|| Mutex::new(Bucket::new(per_sec)))
+    });
+    let Some(bucket) = bucket else {
+        return;
+    };
+
+    let _waiting = Waiting::start();
+    // Held while sleeping, so requests queue up behind the lock in order
+    let mut bucket = bucket.lock().await;
+    let wait = bucket.take(Instant::now());
+    if wait >= Duration::from_secs(1) {
+        info!(
+            "Throttled to {} requests/s: waiting {:.1}s",
+            bucket.per_sec,
+            wait.as_secs_f64()
+        );
+    }
+    tokio::time::sleep(wait).await;
+}
+
+/// Number of requests the rate limiter is holding back right now
+pub fn throttled_requests() -> usize {
+    WAITING.load(Ordering::Relaxed)
+}
@@ -0,0 +1,355 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::elan::escape;
+
+const IMDI_NAMESPACE: &str = "http://www.mpi.nl/IMDI/Schema/IMDI";
+
+const IMDI_SCHEMA: &str = "http://www.mpi.nl/IMDI/Schema/IMDI_3.0.xsd";
+
+/// What the schema accepts for anything cowcow does not record
+const UNSPECIFIED: &str = "Unspecified";
+
+/// A speaker heard in a session, known to the archive by ID only
+#[derive(Debug, Clone)]
+pub struct Actor<'a> {
+    /// Speaker ID, the same across sessions; it stands in for the name
+    pub code: &'a str,
+    pub is_minor: bool,
+}
+
+/// An exported recording of a session
+#[derive(Debug, Clone)]
+pub struct MediaFile<'a> {
+    /// Path relative to the metadata file
+    pub link: String,
+    pub mime_type: &'a str,
+    pub size_bytes: Option<u64>,
+    /// What was said, shown as the file's description
+    pub text: Option<&'a str>,
+    /// ELAN annotation file of the recording, relative like `link`
+    pub annotations: Option<String>,
+}
+
+/// One IMDI session file: a bundle in the deposit
+#[derive(Debug, Clone)]
+pub struct SessionMetadata<'a> {
+    pub name: String,
+    pub title: &'a str,
+    /// YYYY-MM-DD
+    pub date: String,
+    pub description: Option<String>,
+    pub location: Option<&'a str>,
+    /// Environment and microphone, as far as they are known
+    pub recording_conditions: Option<String>,
+    /// Language codes of the recordings
+    pub languages: Vec<&'a str>,
+    pub actors: Vec<Actor<'a>>,
+    pub media: Vec<MediaFile<'a>>,
+}
+
+/// The top IMDI corpus node, linking every session
+#[derive(Debug, Clone)]
+pub struct CorpusMetadata<'a> {
+    pub name: &'a str,
+    pub title: &'a str,
+    /// (session name, link relative to the corpus file)
+    pub sessions: &'a [(String, String)],
+}
+
+impl SessionMetadata<'_> {
+    /// The session as IMDI 3.0 XML, with every element the schema requires;
+    /// what cowcow does not know is `Unspecified`
+    pub fn to_xml(&self) -> String {
+        let mut xml = header("SESSION");
+        let _ = writeln!(xml, "    <Session>");
+        element(&mut xml, 2, "Name", &self.name);
+        element(&mut xml, 2, "Title", self.title);
+        element(&mut xml, 2, "Date", &self.date);
+        if let Some(description) = &self.description {
+            element(&mut xml, 2, "Description", description);
+        }
+
+        let _ = writeln!(xml, "        <MDGroup>");
+        let _ = writeln!(xml, "            <Location>");
+        element(&mut xml, 4, "Continent", UNSPECIFIED);
+        element(&mut xml, 4, "Country", UNSPECIFIED);
+        if let Some(location) = self.location {
+            element(&mut xml, 4, "Address", location);
+        }
+        let _ = writeln!(xml, "            </Location>");
+        let _ = writeln!(xml, "            <Project>");
+        element(&mut xml, 4, "Name", self.title);
+        element(&mut xml, 4, "Title", self.title);
+        let _ = writeln!(xml, "                <Id/>");
+        let _ = writeln!(xml, "                <Contact/>");
+        let _ = writeln!(xml, "            </Project>");
+        let _ = writeln!(xml, "            <Keys/>");
+        let _ = writeln!(xml, "            <Content>");
+        element(&mut xml, 4, "Genre", UNSPECIFIED);
+        element(&mut xml, 4, "SubGenre", UNSPECIFIED);
+        element(&mut xml, 4, "Task", "Prompted reading");
+        element(&mut xml, 4, "Modalities", "Speech");
+        element(&mut xml, 4, "Subject", UNSPECIFIED);
+        let _ = writeln!(xml, "                <CommunicationContext>");
+        element(&mut xml, 5, "Interactivity", "non-interactive");
+        element(&mut xml, 5, "PlanningType", "planned");
+        element(&mut xml, 5, "Involvement", "elicited");
+        element(&mut xml, 5, "SocialContext", UNSPECIFIED);
+        element(&mut xml, 5, "EventStructure", "Monologue");
+        element(&mut xml, 5, "Channel", "Experimental setting");
+        let _ = writeln!(xml, "                </CommunicationContext>");
+        let _ = writeln!(xml, "                <Languages>");
+        for lang in &self.languages {
+            let _ = writeln!(xml, "                    <Language>");
+            element(&mut xml, 6, "Id", &language_id(lang));
+            element(&mut xml, 6, "Name", lang);
+            let _ = writeln!(xml, "                    </Language>");
+        }
+        let _ = writeln!(xml, "                </Languages>");
+        let _ = writeln!(xml, "                <Keys/>");
+        let _ = writeln!(xml, "            </Content>");
+        let _ = writeln!(xml, "            <Actors>");
+        for actor in &self.actors {
+            let _ = writeln!(xml, "                <Actor>");
+            element(&mut xml, 5, "Role", "Speaker");
+            element(&mut xml, 5, "Name", actor.code);
+            element(&mut xml, 5, "FullName", actor.code);
+            element(&mut xml, 5, "Code", actor.code);
+            element(&mut xml, 5, "FamilySocialRole", UNSPECIFIED);
+            let _ = writeln!(xml, "                    <Languages/>");
+            element(&mut xml, 5, "EthnicGroup", UNSPECIFIED);
+            element(&mut xml, 5, "Age", UNSPECIFIED);
+            element(&mut xml, 5, "BirthDate", UNSPECIFIED);
+            element(&mut xml, 5, "Sex", UNSPECIFIED);
+            element(&mut xml, 5, "Education", UNSPECIFIED);
+            element(&mut xml, 5, "Anonymized", "true");
+            // Archives restrict access to minors' recordings
+            if actor.is_minor {
+                let _ = writeln!(xml, "                    <Keys>");
+                let _ = writeln!(
+                    xml,
+                    r#"                        <Key Name="Minor">true</Key>"#
+                );
+                let _ = writeln!(xml, "                    </Keys>");
+            } else {
+                let _ = writeln!(xml, "                    <Keys/>");
+            }
+            let _ = writeln!(xml, "                </Actor>");
+        }
+        let _ = writeln!(xml, "            </Actors>");
+        let _ = writeln!(xml, "        </MDGroup>");
+
+        let _ = writeln!(xml, "        <Resources>");
+        for media in &self.media {
+            let _ = writeln!(xml, "            <MediaFile>");
+            element(&mut xml, 4, "ResourceLink", &media.link);
+            element(&mut xml, 4, "Type", "audio");
+            element(&mut xml, 4, "Format", media.mime_type);
+            match media.size_bytes {
+                Some(size) => element(&mut xml, 4, "Size", &size.to_string()),
+                None => element(&mut xml, 4, "Size", UNSPECIFIED),
+            }
+            element(&mut xml, 4, "Quality", UNSPECIFIED);
+            element(
+                &mut xml,
+                4,
+                "RecordingConditions",
+                self.recording_conditions.as_deref().unwrap_or(UNSPECIFIED),
+            );
+            let _ = writeln!(xml, "                <TimePosition>");
+            element(&mut xml, 5, "Start", UNSPECIFIED);
+            let _ = writeln!(xml, "                </TimePosition>");
+            access(&mut xml);
+            if let Some(text) = media.text {
+                element(&mut xml, 4, "Description", text);
+            }
+            let _ = writeln!(xml, "                <Keys/>");
+            let _ = writeln!(xml, "            </MediaFile>");
+        }
+        for media in &self.media {
+            let Some(annotations) = &media.annotations else {
+                continue;
+            };
+            let _ = writeln!(xml, "            <WrittenResource>");
+            element(&mut xml, 4, "ResourceLink", annotations);
+            element(&mut xml, 4, "MediaResourceLink", &media.link);
+            element(&mut xml, 4, "Date", &self.date);
+            element(&mut xml, 4, "Type", "Annotation");
+            element(&mut xml, 4, "SubType", "ELAN");
+            element(&mut xml, 4, "Format", "text/x-eaf+xml");
+            element(&mut xml, 4, "Size", UNSPECIFIED);
+            let _ = writeln!(xml, "                <Validation>");
+            element(&mut xml, 5, "Type", UNSPECIFIED);
+            element(&mut xml, 5, "Methodology", UNSPECIFIED);
+            let _ = writeln!(xml, "                </Validation>");
+            element(&mut xml, 4, "Derivation", "Annotation");
+            element(&mut xml, 4, "CharacterEncoding", "UTF-8");
+            element(&mut xml, 4, "ContentEncoding", UNSPECIFIED);
+            element(&mut xml, 4, "LanguageId", UNSPECIFIED);
+            element(&mut xml, 4, "Anonymized", "true");
+            access(&mut xml);
+            let _ = writeln!(xml, "                <Keys/>");
+            let _ = writeln!(xml, "            </WrittenResource>");
+        }
+        let _ = writeln!(xml, "        </Resources>");
+        let _ = writeln!(xml, "    </Session>");
+        let _ = writeln!(xml, "</METATRANSCRIPT>");
+        xml
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_xml())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl CorpusMetadata<'_> {
+    /// The corpus node as IMDI 3.0 XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = header("CORPUS");
+        let _ = writeln!(xml, "    <Corpus>");
+        element(&mut xml, 2, "Name", self.name);
+        element(&mut xml, 2, "Title", self.title);
+        for (name, link) in self.sessions {
+            let _ = writeln!(
+                xml,
+                r#"        <CorpusLink Name="{}">{}</CorpusLink>"#,
+                escape(name),
+                escape(link)
+            );
+        }
+        let _ = writeln!(xml, "    </Corpus>");
+        let _ = writeln!(xml, "</METATRANSCRIPT>");
+        xml
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_xml())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn header(kind: &str) -> String {
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<METATRANSCRIPT xmlns="{IMDI_NAMESPACE}" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="{IMDI_NAMESPACE} {IMDI_SCHEMA}" Date="{}" FormatId="IMDI 3.03" Originator="cowcow {}" Type="{kind}" Version="0">"#,
+        chrono::Utc::now().format("%Y-%m-%d"),
+        env!("CARGO_PKG_VERSION")
+    );
+    xml
+}
+
+/// IMDI's identifier of a language code: ISO 639-1 or 639-3 by its length
+fn language_id(lang: &str) -> String {
+    match lang.len() {
+        2 => format!("ISO639-1:{lang}"),
+        3 => format!("ISO639-3:{lang}"),
+        _ => UNSPECIFIED.to_string(),
+    }
+}
+
+/// Access block of a resource, left to the archive to fill in
+fn access(xml: &mut String) {
+    let _ = writeln!(xml, "                <Access>");
+    element(xml, 5, "Availability", UNSPECIFIED);
+    element(xml, 5, "Date", UNSPECIFIED);
+    element(xml, 5, "Owner", UNSPECIFIED);
+    element(xml, 5, "Publisher", UNSPECIFIED);
+    let _ = writeln!(xml, "                    <Contact/>");
+    let _ = writeln!(xml, "                </Access>");
+}
+
+/// One `<name>text</name>` line, indented by `depth` levels
+fn element(xml: &mut String, depth: usize, name: &str, text: &str) {
+    let _ = writeln!(
+        xml,
+        "{}<{name}>{}</{name}>",
+        "    ".repeat(depth),
+        escape(text)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> SessionMetadata<'static> {
+        SessionMetadata {
+            name: "session-20240601-0a1b2c3d".to_string(),
+            title: "Swahili read speech",
+            date: "2024-06-01".to_string(),
+            description: Some("cowcow session 0a1b2c3d".to_string()),
+            location: Some("Mombasa & coast"),
+            recording_conditions: Some("quiet room, headset".to_string()),
+            languages: vec!["sw"],
+            actors: vec![Actor {
+                code: "spk-7",
+                is_minor: true,
+            }],
+            media: vec![MediaFile {
+                link: "../recordings/sw_r1.wav".to_string(),
+                mime_type: "audio/x-wav",
+                size_bytes: Some(32044),
+                text: Some("Habari <yako>"),
+                annotations: Some("../recordings/sw_r1.eaf".to_string()),
+            }],
+        }
+    }
+
+    /// The XML with the parts that change from run to run replaced
+    fn stable(xml: &str) -> String {
+        xml.replacen(
+            &format!("Date=\"{}\"", chrono::Utc::now().format("%Y-%m-%d")),
+            "Date=\"DATE\"",
+            1,
+        )
+        .replacen(
+            &format!("cowcow {}", env!("CARGO_PKG_VERSION")),
+            "cowcow VERSION",
+            1,
+        )
+    }
+
+    #[test]
+    fn test_session_snapshot() {
+        // Element order and required elements follow IMDI_3.0.xsd
+        let expected = include_str!("../tests/snapshots/session.imdi");
+        assert_eq!(stable(&session().to_xml()), expected);
+    }
+
+    #[test]
+    fn test_session_without_speakers_or_annotations() {
+        let mut session = session();
+        session.actors.clear();
+        session.media[0].annotations = None;
+        session.languages = vec!["swh"];
+        let xml = session.to_xml();
+        assert!(xml.contains("<Actors>\n            </Actors>"));
+        assert!(!xml.contains("<WrittenResource>"));
+        assert!(xml.contains("<Id>ISO639-3:swh</Id>"));
+    }
+
+    #[test]
+    fn test_corpus_links_sessions() {
+        let sessions = vec![(
+            "session-1".to_string(),
+            "sessions/session-1.imdi".to_string(),
+        )];
+        let xml = CorpusMetadata {
+            name: "cowcow",
+            title: "Read & spoken",
+            sessions: &sessions,
+        }
+        .to_xml();
+        assert!(xml.contains(r#"Type="CORPUS""#));
+        assert!(xml.contains("<Title>Read &amp; spoken</Title>"));
+        assert!(
+            xml.contains(r#"<CorpusLink Name="session-1">sessions/session-1.imdi</CorpusLink>"#)
+        );
+    }
+}
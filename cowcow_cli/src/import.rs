@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use cowcow_core::prompt_analysis::PromptAnalysis;
+use cowcow_core::resample::Resampler;
+use cowcow_core::timeline::QcTimeline;
+use cowcow_core::{decode, wav};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::dedupe;
+use crate::storage;
+
+/// Totals of an import run
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// Files imported by an earlier run
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// How files are brought into the library
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    pub lang: String,
+    /// Prompt of files without a transcript
+    pub prompt: Option<String>,
+    /// Transcript per file, from [`load_transcripts`]
+    pub transcripts: HashMap<String, String>,
+    /// Hard-link files already in the library's format instead of copying
+    pub link: bool,
+}
+
+impl ImportOptions {
+    /// Transcript of `file`, looked up by its path, file name, or file name
+    /// without extension
+    fn prompt_for(&self, file: &Path) -> Option<&str> {
+        let keys = [
+            Some(file.to_string_lossy()),
+            file.file_name().map(|name| name.to_string_lossy()),
+            file.file_stem().map(|stem| stem.to_string_lossy()),
+        ];
+        keys.into_iter()
+            .flatten()
+            .find_map(|key| self.transcripts.get(key.as_ref()))
+            .map(String::as_str)
+            .or(self.prompt.as_deref())
+    }
+}
+
+/// Files to import from `inputs`: files as given, and the audio files in
+/// directories (and their subdirectories with `recursive`), in path order
+pub fn collect_files(inputs: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut found = audio_files(input, recursive)
+                .with_context(|| format!("Failed to read {}", input.display()))?;
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn audio_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                files.extend(audio_files(&path, recursive)?);
+            }
+        } else if decode::is_decodable(&path) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Transcripts from a CSV file with a header row: the file column
+/// (`file`, `path` or `filename`) and the text column (`transcript`, `text`
+/// or `prompt`)
+pub fn load_transcripts(path: &Path) -> Result<HashMap<String, String>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.trim().to_ascii_lowercase().as_str()))
+    };
+    let file_column = column(&["file", "path", "filename"])
+        .context("Transcript CSV needs a file, path or filename column")?;
+    let text_column = column(&["transcript", "text", "prompt"])
+        .context("Transcript CSV needs a transcript, text or prompt column")?;
+
+    let mut transcripts = HashMap::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("Invalid CSV row {}", row + 2))?;
+        let (Some(file), Some(text)) = (record.get(file_column), record.get(text_column)) else {
+            continue;
+        };
+        let (file, text) = (file.trim(), text.trim());
+        if !file.is_empty() && !text.is_empty() {
+            transcripts.insert(file.to_string(), text.to_string());
+        }
+    }
+    Ok(transcripts)
+}
+
+/// Import audio files recorded elsewhere
+///
+/// Each file is decoded (WAV, FLAC, MP3, Ogg Vorbis or M4A) and converted to
+/// the configured sample rate, channel count and bit depth (WAVs already in
+/// that format are copied or linked as they are), then QC'd and queued for
+/// upload like a fresh recording. Files imported before are skipped; files
+/// that fail are reported and skipped.
+pub async fn import_files(
+    files: &[PathBuf],
+    options: &ImportOptions,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<ImportSummary> {
+    let location = storage::recordings_location(config)?;
+    let output_dir = location.path().join(&options.lang);
+    fs::create_dir_all(&output_dir)?;
+
+    let mut summary = ImportSummary::default();
+    for file in files {
+        let source = file.canonicalize().unwrap_or_else(|_| file.clone());
+        let existing: Option<(String,)> =
+            sqlx::query_as("SELECT id FROM recordings WHERE imported_from = ?")
+                .bind(source.to_string_lossy())
+                .fetch_optional(db)
+                .await?;
+        if let Some((recording_id,)) = existing {
+            println!(
+                "⏭️  {} already imported as {}",
+                file.display(),
+                recording_id
+            );
+            summary.skipped += 1;
+            continue;
+        }
+
+        match import_file(&source, options, &output_dir, db, config).await {
+            Ok((recording_id, passed)) => {
+                let verdict = if passed { "✅" } else { "❌ QC failed" };
+                println!("📥 {} → {} {}", file.display(), recording_id, verdict);
+                summary.imported += 1;
+            }
+            Err(e) => {
+                println!("⚠️  {}: {:#}", file.display(), e);
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Import one file, returning the new recording ID and whether it passed QC
+async fn import_file(
+    file: &Path,
+    options: &ImportOptions,
+    output_dir: &Path,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<(String, bool)> {
+    if !decode::is_decodable(file) {
+        return Err(anyhow::anyhow!(
+            "Unsupported file type (expected one of: {})",
+            decode::DECODABLE_EXTENSIONS.join(", ")
+        ));
+    }
+
+    let recording_id = Uuid::new_v4().to_string();
+    let wav_path = output_dir.join(format!("{recording_id}.wav"));
+    if in_library_format(file, config) {
+        if !(options.link && fs::hard_link(file, &wav_path).is_ok()) {
+            fs::copy(file, &wav_path)
+                .with_context(|| format!("Failed to copy {}", file.display()))?;
+        }
+    } else {
+        let audio = decode::decode_file(file)?;
+        if audio.samples.is_empty() {
+            return Err(anyhow::anyhow!("No audio decoded"));
+        }
+        write_wav(&audio, &wav_path, config)?;
+    }
+
+    let prompt = options.prompt_for(file);
+    let timeline =
+        cowcow_core::analyze_wav_timeline(&wav_path, config.processor_builder().into_config())?;
+    if let Err(e) = timeline.save(&QcTimeline::sidecar_path(&wav_path)) {
+        warn!("Failed to save QC timeline: {}", e);
+    }
+    let metrics = timeline.summary();
+    let metrics_json = match prompt.and_then(|text| PromptAnalysis::new(text, &metrics)) {
+        Some(analysis) => analysis.with_metrics(&metrics),
+        None => serde_json::to_value(&metrics)?,
+    };
+    let qc_report = config.qc_policy().evaluate_json(&metrics_json);
+
+    // The recording and its queue entry are stored together
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO recordings (id, lang, prompt, qc_metrics, qc_report, created_at, wav_path,
+                                imported_from)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&recording_id)
+    .bind(&options.lang)
+    .bind(prompt)
+    .bind(metrics_json.to_string())
+    .bind(serde_json::to_string(&qc_report)?)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(wav_path.to_string_lossy())
+    .bind(file.to_string_lossy())
+    .execute(&mut *tx)
+    .await
+    .context("Failed to save recording")?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO upload_queue (recording_id, attempts, last_attempt)
+        VALUES (?, 0, 0)
+        "#,
+    )
+    .bind(&recording_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await.context("Failed to save recording")?;
+
+    dedupe::store_fingerprint(db, &recording_id, &wav_path).await?;
+
+    Ok((recording_id, qc_report.passed))
+}
+
+/// Whether `file` is a WAV in the configured sample rate, channel count and
+/// bit depth, and so can go into the library unchanged
+fn in_library_format(file: &Path, config: &Config) -> bool {
+    let expected = config
+        .bit_depth()
+        .spec(config.audio.sample_rate, config.audio.channels);
+    decode::is_wav(file)
+        && hound::WavReader::open(file).is_ok_and(|reader| reader.spec() == expected)
+}
+
+/// Write decoded audio as a WAV in the configured format
+fn write_wav(audio: &decode::DecodedAudio, wav_path: &Path, config: &Config) -> Result<()> {
+    let channels = config.audio.channels;
+    let mut resampler = Resampler::new(audio.sample_rate, config.audio.sample_rate, channels)?;
+    let mut samples = resampler.process(&audio.to_channels(channels))?;
+    samples.extend(resampler.flush()?);
+
+    let spec = config.bit_depth().spec(config.audio.sample_rate, channels);
+    let mut writer = hound::WavWriter::create(wav_path, spec)
+        .with_context(|| format!("Failed to write {}", wav_path.display()))?;
+    for &sample in &samples {
+        wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+/// Words per minute added or removed by a single +/- key press
+pub const WPM_STEP: u32 = 10;
+const MIN_WPM: u32 = 40;
+const MAX_WPM: u32 = 300;
+
+/// Highlights the word a speaker should be reading at a steady pace
+#[derive(Debug, Clone)]
+pub struct KaraokePrompt {
+    words: Vec<String>,
+    wpm: u32,
+    /// Words already covered before the last rate change
+    offset_words: f64,
+    /// Audio time at which the current rate took effect
+    rate_changed_at: f64,
+}
+
+impl KaraokePrompt {
+    pub fn new(prompt: &str, wpm: u32) -> Self {
+        Self {
+            words: prompt.split_whitespace().map(str::to_string).collect(),
+            wpm: wpm.clamp(MIN_WPM, MAX_WPM),
+            offset_words: 0.0,
+            rate_changed_at: 0.0,
+        }
+    }
+
+    pub fn wpm(&self) -> u32 {
+        self.wpm
+    }
+
+    /// Change the reading rate at `elapsed_secs` without jumping the highlight
+    pub fn adjust(&mut self, delta: i32, elapsed_secs: f64) {
+        self.offset_words = self.position(elapsed_secs);
+        self.rate_changed_at = elapsed_secs;
+        self.wpm = self
+            .wpm
+            .saturating_add_signed(delta)
+            .clamp(MIN_WPM, MAX_WPM);
+    }
+
+    /// Fractional word index expected at `elapsed_secs` of audio
+    fn position(&self, elapsed_secs: f64) -> f64 {
+        let since_change = (elapsed_secs - self.rate_changed_at).max(0.0);
+        self.offset_words + since_change * self.wpm as f64 / 60.0
+    }
+
+    /// Index of the word that should be spoken now, if any remain
+    pub fn current_word(&self, elapsed_secs: f64) -> Option<usize> {
+        let index = self.position(elapsed_secs) as usize;
+        (index < self.words.len()).then_some(index)
+    }
+}
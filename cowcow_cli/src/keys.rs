@@ -0,0 +1,114 @@
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+
+/// How long a key counts as held after its last press or auto-repeat, on
+/// terminals that do not report releases; covers the delay before auto-repeat
+/// starts
+const HOLD_WITHOUT_RELEASE: Duration = Duration::from_millis(600);
+
+/// Puts the terminal in raw mode for single-key controls, restoring it on drop
+///
+/// Raw mode disables line buffering and echo, but also output post-processing,
+/// so drop the guard before printing multi-line output.
+pub struct RawModeGuard {
+    enabled: bool,
+}
+
+impl RawModeGuard {
+    /// Enable raw mode when stdin is an interactive terminal
+    pub fn enable() -> Self {
+        let enabled = std::io::stdin().is_terminal() && terminal::enable_raw_mode().is_ok();
+        Self { enabled }
+    }
+
+    /// Whether single-key controls are available
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+/// Next pending key press, without blocking
+pub fn poll_key() -> Option<KeyEvent> {
+    while let Some(key) = poll_key_event() {
+        if key.kind == KeyEventKind::Press {
+            return Some(key);
+        }
+    }
+    None
+}
+
+/// Next pending key event (press, repeat or release), without blocking
+pub fn poll_key_event() -> Option<KeyEvent> {
+    while event::poll(Duration::ZERO).ok()? {
+        if let Event::Key(key) = event::read().ok()? {
+            return Some(key);
+        }
+    }
+    None
+}
+
+/// Whether the push-to-talk key is held down
+///
+/// Terminals that report key releases are asked to; elsewhere the key counts
+/// as held while its auto-repeat keeps arriving. Enable raw mode first, and
+/// drop this before reading lines from the terminal again.
+pub struct PushToTalk {
+    releases: bool,
+    /// Last press or auto-repeat, until the key is released
+    pressed_at: Option<Instant>,
+}
+
+impl PushToTalk {
+    pub fn enable() -> Self {
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false)
+            && execute!(
+                std::io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )
+            .is_ok();
+        Self {
+            releases,
+            pressed_at: None,
+        }
+    }
+
+    /// Take in an event of the push-to-talk key
+    pub fn key(&mut self, key: &KeyEvent) {
+        self.pressed_at = match key.kind {
+            KeyEventKind::Release => None,
+            _ => Some(Instant::now()),
+        };
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.pressed_at
+            .is_some_and(|at| self.releases || at.elapsed() < HOLD_WITHOUT_RELEASE)
+    }
+}
+
+impl Drop for PushToTalk {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(std::io::stdout(), PopKeyboardEnhancementFlags);
+        }
+    }
+}
+
+/// Ctrl-C arrives as a key press while raw mode is active
+pub fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
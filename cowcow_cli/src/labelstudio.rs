@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// One task of a Label Studio JSON export
+#[derive(Debug, Deserialize)]
+struct Task {
+    #[serde(default)]
+    data: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Annotation {
+    #[serde(default)]
+    was_cancelled: bool,
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    result: Vec<Region>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Region {
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+/// Transcripts read from a Label Studio export
+#[derive(Debug, Default)]
+pub struct LabelStudioImport {
+    /// (recording ID, transcript) in task order
+    pub transcripts: Vec<(String, String)>,
+    /// Tasks whose recording could not be told
+    pub unmatched: usize,
+    /// Tasks without a transcript
+    pub untranscribed: usize,
+}
+
+/// Corrected transcripts from a Label Studio JSON export (`export.json`)
+///
+/// A task's recording is its `recording_id` or `id` data field, or else the
+/// recording ID in the name of its audio file (as exported by cowcow,
+/// `<lang>_<id>.wav`, with any prefix Label Studio added on upload). Its
+/// transcript is the text of the latest annotation that was not cancelled.
+pub fn load_export(path: &Path) -> Result<LabelStudioImport> {
+    let json = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let tasks: Vec<Task> = serde_json::from_slice(&json)
+        .with_context(|| format!("{} is not a Label Studio JSON export", path.display()))?;
+
+    let mut import = LabelStudioImport::default();
+    for task in tasks {
+        let Some(recording_id) = recording_id(&task.data) else {
+            import.unmatched += 1;
+            continue;
+        };
+        match transcript(&task.annotations) {
+            Some(text) => import.transcripts.push((recording_id, text)),
+            None => import.untranscribed += 1,
+        }
+    }
+    Ok(import)
+}
+
+fn recording_id(data: &serde_json::Map<String, serde_json::Value>) -> Option<String> {
+    for field in ["recording_id", "id"] {
+        if let Some(id) = data.get(field).and_then(|value| value.as_str()) {
+            return Some(id.to_string());
+        }
+    }
+    data.values()
+        .filter_map(|value| value.as_str())
+        .find_map(uuid_in)
+}
+
+/// The last UUID in a file name or URL
+fn uuid_in(text: &str) -> Option<String> {
+    let name = text.rsplit('/').next().unwrap_or(text);
+    (0..name.len().saturating_sub(35))
+        .rev()
+        .filter_map(|start| name.get(start..start + 36))
+        .find(|candidate| uuid::Uuid::parse_str(candidate).is_ok())
+        .map(str::to_string)
+}
+
+/// Text of the latest annotation that was not cancelled, its text areas
+/// joined by spaces
+fn transcript(annotations: &[Annotation]) -> Option<String> {
+    let annotation = annotations
+        .iter()
+        .filter(|annotation| !annotation.was_cancelled)
+        .max_by(|a, b| a.updated_at.cmp(&b.updated_at))?;
+    let lines: Vec<&str> = annotation
+        .result
+        .iter()
+        .filter_map(|region| region.value.get("text"))
+        .flat_map(|text| match text {
+            serde_json::Value::Array(lines) => lines.iter().filter_map(|l| l.as_str()).collect(),
+            serde_json::Value::String(line) => vec![line.as_str()],
+            _ => Vec::new(),
+        })
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
@@ -0,0 +1,446 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Order of `cowcow list` results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Created,
+    Duration,
+    Snr,
+    Lang,
+    Speaker,
+}
+
+impl SortKey {
+    fn column(&self) -> &'static str {
+        match self {
+            SortKey::Created => "r.created_at",
+            SortKey::Duration => "json_extract(r.qc_metrics, '$.duration_secs')",
+            SortKey::Snr => "json_extract(r.qc_metrics, '$.snr_db')",
+            SortKey::Lang => "r.lang",
+            SortKey::Speaker => "s.name",
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "created" | "date" => Ok(SortKey::Created),
+            "duration" => Ok(SortKey::Duration),
+            "snr" => Ok(SortKey::Snr),
+            "lang" => Ok(SortKey::Lang),
+            "speaker" => Ok(SortKey::Speaker),
+            _ => Err(format!(
+                "Unknown sort key: {s} (use created, duration, snr, lang or speaker)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortKey::Created => "created",
+            SortKey::Duration => "duration",
+            SortKey::Snr => "snr",
+            SortKey::Lang => "lang",
+            SortKey::Speaker => "speaker",
+        })
+    }
+}
+
+/// How `cowcow list` prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(ListFormat::Table),
+            "json" => Ok(ListFormat::Json),
+            "csv" => Ok(ListFormat::Csv),
+            _ => Err(format!(
+                "Unknown output format: {s} (use table, json or csv)"
+            )),
+        }
+    }
+}
+
+/// Filters, order and page of a recordings listing
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    pub lang: Option<String>,
+    /// Upload status: uploaded, pending or failed
+    pub status: Option<String>,
+    /// Speaker profile ID or name
+    pub speaker: Option<String>,
+    /// Tags the recordings must all carry
+    pub tags: Vec<String>,
+    pub min_snr: Option<f32>,
+    /// Maximum clipping percentage
+    pub max_clipping: Option<f32>,
+    /// Minimum voice activity percentage
+    pub min_vad: Option<f32>,
+    /// Only recordings made at or after this Unix timestamp
+    pub since: Option<i64>,
+    /// Only recordings whose QC report passed (`Some(true)`) or failed
+    pub qc_passed: Option<bool>,
+    pub include_archived: bool,
+    pub sort: SortKey,
+    /// Oldest, shortest, ... first instead of last
+    pub ascending: bool,
+    pub limit: u32,
+    /// 1-based page number
+    pub page: u32,
+}
+
+/// One row of a recordings listing
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub id: String,
+    pub lang: String,
+    pub prompt: Option<String>,
+    pub speaker_id: Option<String>,
+    pub speaker_name: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+    pub uploaded_at: Option<i64>,
+    pub duration_secs: Option<f64>,
+    pub snr_db: Option<f64>,
+    pub clipping_pct: Option<f64>,
+    pub vad_ratio: Option<f64>,
+    /// Outcome of the QC policy at record time, `None` if it was not evaluated
+    pub qc_passed: Option<bool>,
+    pub pinned: bool,
+    pub archived: bool,
+}
+
+/// One page of a recordings listing
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingPage {
+    /// Recordings matching the filters, across all pages
+    pub total: i64,
+    pub page: u32,
+    pub limit: u32,
+    pub recordings: Vec<RecordingSummary>,
+}
+
+impl RecordingSummary {
+    /// Archived, uploaded or pending
+    pub fn status(&self) -> &'static str {
+        if self.archived {
+            "archived"
+        } else if self.uploaded_at.is_some() {
+            "uploaded"
+        } else {
+            "pending"
+        }
+    }
+}
+
+impl RecordingPage {
+    pub fn page_count(&self) -> u32 {
+        (self.total.max(0) as u64).div_ceil(self.limit.max(1) as u64) as u32
+    }
+}
+
+/// Tags given on the command line as stored in `recordings.tags`: trimmed,
+/// lowercased and comma-separated, or `None` when there are none
+pub fn tags_column(tags: &[String]) -> Result<Option<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.contains(',') {
+            bail!("Tags cannot contain commas: {tag}");
+        }
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    Ok((!normalized.is_empty()).then(|| normalized.join(",")))
+}
+
+/// Fetch one page of recordings matching `query`
+pub async fn list_recordings(db: &SqlitePool, query: &ListQuery) -> Result<RecordingPage> {
+    if query.limit == 0 || query.page == 0 {
+        bail!("--limit and --page must be at least 1");
+    }
+
+    let mut filters = String::from(" WHERE 1=1");
+    let mut params: Vec<String> = Vec::new();
+
+    if let Some(lang) = &query.lang {
+        filters.push_str(" AND r.lang = ?");
+        params.push(lang.clone());
+    }
+
+    match query.status.as_deref() {
+        Some("uploaded") => filters.push_str(" AND r.uploaded_at IS NOT NULL"),
+        Some("pending") => filters.push_str(" AND r.uploaded_at IS NULL"),
+        Some("failed") => filters
+            .push_str(" AND r.id IN (SELECT recording_id FROM upload_queue WHERE attempts > 0)"),
+        Some(other) => bail!("Unknown status: {other} (use uploaded, pending or failed)"),
+        None => {}
+    }
+
+    if let Some(speaker) = &query.speaker {
+        filters.push_str(" AND (r.speaker_id = ? OR s.name = ?)");
+        params.push(speaker.clone());
+        params.push(speaker.clone());
+    }
+
+    for tag in tags_column(&query.tags)?
+        .iter()
+        .flat_map(|tags| tags.split(','))
+    {
+        filters.push_str(" AND ',' || r.tags || ',' LIKE ?");
+        params.push(format!("%,{tag},%"));
+    }
+
+    if let Some(min_snr) = query.min_snr {
+        filters.push_str(" AND json_extract(r.qc_metrics, '$.snr_db') >= CAST(? AS REAL)");
+        params.push(min_snr.to_string());
+    }
+
+    if let Some(max_clipping) = query.max_clipping {
+        filters.push_str(" AND json_extract(r.qc_metrics, '$.clipping_pct') <= CAST(? AS REAL)");
+        params.push(max_clipping.to_string());
+    }
+
+    if let Some(min_vad) = query.min_vad {
+        filters.push_str(" AND json_extract(r.qc_metrics, '$.vad_ratio') >= CAST(? AS REAL)");
+        params.push(min_vad.to_string());
+    }
+
+    if let Some(since) = query.since {
+        filters.push_str(" AND r.created_at >= CAST(? AS INTEGER)");
+        params.push(since.to_string());
+    }
+
+    match query.qc_passed {
+        Some(true) => filters.push_str(" AND json_extract(r.qc_report, '$.passed') = 1"),
+        Some(false) => filters.push_str(" AND json_extract(r.qc_report, '$.passed') = 0"),
+        None => {}
+    }
+
+    // Archived recordings stay out unless asked for
+    if !query.include_archived {
+        filters.push_str(" AND r.archived = 0");
+    }
+
+    let from = " FROM recordings r LEFT JOIN speakers s ON s.id = r.speaker_id";
+
+    let count_sql = format!("SELECT COUNT(*){from}{filters}");
+    let mut count = sqlx::query_scalar::<_, i64>(&count_sql);
+    for param in &params {
+        count = count.bind(param);
+    }
+    let total = count
+        .fetch_one(db)
+        .await
+        .context("Failed to count recordings")?;
+
+    // Missing values sort last either way; ties fall back to newest first
+    let direction = if query.ascending { "ASC" } else { "DESC" };
+    let column = query.sort.column();
+    let list_sql = format!(
+        r#"
+        SELECT r.id, r.lang, r.prompt, r.speaker_id, s.name AS speaker_name, r.tags,
+               r.created_at, r.uploaded_at,
+               CAST(json_extract(r.qc_metrics, '$.duration_secs') AS REAL) AS duration_secs,
+               CAST(json_extract(r.qc_metrics, '$.snr_db') AS REAL) AS snr_db,
+               CAST(json_extract(r.qc_metrics, '$.clipping_pct') AS REAL) AS clipping_pct,
+               CAST(json_extract(r.qc_metrics, '$.vad_ratio') AS REAL) AS vad_ratio,
+               json_extract(r.qc_report, '$.passed') AS qc_passed,
+               r.pinned, r.archived
+        {from}{filters}
+        ORDER BY {column} IS NULL, {column} {direction}, r.created_at DESC
+        LIMIT ? OFFSET ?
+        "#
+    );
+    let mut rows = sqlx::query(&list_sql);
+    for param in &params {
+        rows = rows.bind(param);
+    }
+    let offset = (query.page as i64 - 1) * query.limit as i64;
+    let rows = rows
+        .bind(query.limit as i64)
+        .bind(offset)
+        .fetch_all(db)
+        .await
+        .context("Failed to fetch recordings")?;
+
+    let recordings = rows
+        .into_iter()
+        .map(|row| RecordingSummary {
+            id: row.get("id"),
+            lang: row.get("lang"),
+            prompt: row.get("prompt"),
+            speaker_id: row.get("speaker_id"),
+            speaker_name: row.get("speaker_name"),
+            tags: row
+                .get::<Option<String>, _>("tags")
+                .map(|tags| tags.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
+            created_at: row.get("created_at"),
+            uploaded_at: row.get("uploaded_at"),
+            duration_secs: row.get("duration_secs"),
+            snr_db: row.get("snr_db"),
+            clipping_pct: row.get("clipping_pct"),
+            vad_ratio: row.get("vad_ratio"),
+            qc_passed: row.get::<Option<i64>, _>("qc_passed").map(|p| p != 0),
+            pinned: row.get("pinned"),
+            archived: row.get("archived"),
+        })
+        .collect();
+
+    Ok(RecordingPage {
+        total,
+        page: query.page,
+        limit: query.limit,
+        recordings,
+    })
+}
+
+/// Print a page of recordings as an aligned table
+pub fn print_table(page: &RecordingPage) {
+    if page.recordings.is_empty() {
+        println!("No recordings found.");
+        return;
+    }
+
+    println!(
+        "{:<8}  {:<16}  {:<5}  {:>7}  {:>6}  {:>5}  {:>5}  {:<4}  {:<8}  {:<14}  PROMPT",
+        "ID", "CREATED", "LANG", "SECS", "SNR", "CLIP%", "VAD%", "QC", "STATUS", "SPEAKER"
+    );
+    let metric = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{value:.1}"));
+    for recording in &page.recordings {
+        let created = chrono::DateTime::from_timestamp(recording.created_at, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let qc = match recording.qc_passed {
+            Some(true) => "pass",
+            Some(false) => "FAIL",
+            None => "-",
+        };
+        let speaker = recording
+            .speaker_name
+            .as_deref()
+            .or(recording.speaker_id.as_deref())
+            .unwrap_or("-");
+        let mut prompt = recording.prompt.clone().unwrap_or_default();
+        if !recording.tags.is_empty() {
+            prompt = format!("[{}] {prompt}", recording.tags.join(","));
+        }
+
+        println!(
+            "{:<8}  {:<16}  {:<5}  {:>7}  {:>6}  {:>5}  {:>5}  {:<4}  {:<8}  {:<14}  {}",
+            &recording.id[..recording.id.len().min(8)],
+            created,
+            recording.lang,
+            metric(recording.duration_secs),
+            metric(recording.snr_db),
+            metric(recording.clipping_pct),
+            metric(recording.vad_ratio),
+            qc,
+            recording.status(),
+            truncate(speaker, 14),
+            truncate(&prompt, 40),
+        );
+    }
+
+    let first = (page.page as i64 - 1) * page.limit as i64 + 1;
+    let last = first + page.recordings.len() as i64 - 1;
+    println!(
+        "Showing {first}-{last} of {} (page {}/{})",
+        page.total,
+        page.page,
+        page.page_count()
+    );
+}
+
+/// Columns of `cowcow list --output csv`
+const CSV_HEADER: [&str; 13] = [
+    "id",
+    "lang",
+    "duration_secs",
+    "snr_db",
+    "clipping_pct",
+    "vad_ratio",
+    "qc_passed",
+    "status",
+    "created_at",
+    "uploaded_at",
+    "speaker",
+    "tags",
+    "prompt",
+];
+
+/// Write a page of recordings as CSV with a header row; times are RFC 3339
+/// (UTC) and tags are separated by semicolons
+pub fn write_csv(page: &RecordingPage, out: impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(CSV_HEADER)?;
+    let number = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+    let time = |timestamp: Option<i64>| {
+        timestamp
+            .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default()
+    };
+    for recording in &page.recordings {
+        writer.write_record([
+            recording.id.clone(),
+            recording.lang.clone(),
+            number(recording.duration_secs),
+            number(recording.snr_db),
+            number(recording.clipping_pct),
+            number(recording.vad_ratio),
+            recording
+                .qc_passed
+                .map(|passed| passed.to_string())
+                .unwrap_or_default(),
+            recording.status().to_string(),
+            time(Some(recording.created_at)),
+            time(recording.uploaded_at),
+            recording
+                .speaker_name
+                .clone()
+                .or(recording.speaker_id.clone())
+                .unwrap_or_default(),
+            recording.tags.join(";"),
+            recording.prompt.clone().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        format!("{cut}…")
+    }
+}
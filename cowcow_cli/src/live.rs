@@ -0,0 +1,332 @@
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, LineGauge, Paragraph, Sparkline, Widget, Wrap};
+use ratatui::{Frame, Terminal, TerminalOptions, Viewport};
+use std::collections::VecDeque;
+use std::io::{IsTerminal, Stdout};
+use std::time::{Duration, Instant};
+
+use crate::karaoke::KaraokePrompt;
+
+/// Bottom of the level meter, in dBFS
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Peak level counted as clipping
+const CLIP_LEVEL: f32 = 0.999;
+
+/// How long the clip indicator stays lit after a clipped chunk
+const CLIP_HOLD: Duration = Duration::from_millis(1500);
+
+/// Shortest time between redraws
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lines given to the prompt at most; longer prompts are cut off
+const MAX_PROMPT_LINES: u16 = 6;
+
+/// Rows of the rolling waveform
+const WAVEFORM_ROWS: u16 = 3;
+
+/// What the recording loop knows at a point of the take
+#[derive(Debug, Clone, Default)]
+pub struct LiveStatus {
+    pub elapsed_secs: f64,
+    /// Length the take stops at, if any
+    pub duration_secs: Option<f64>,
+    /// Silence so far, and how much of it stops the take
+    pub silence_secs: Option<f64>,
+    pub silence_limit_secs: Option<f64>,
+    /// Time spent paused, while paused
+    pub paused_secs: Option<f64>,
+    /// What starts the take, while waiting for it to start
+    pub standby: Option<&'static str>,
+    pub snr_db: f32,
+    pub vad_ratio: f32,
+    /// Silence gate readout
+    pub gate: String,
+}
+
+/// Live recording display: level meter with clip indicator, rolling
+/// waveform, elapsed and remaining time, and the prompt
+///
+/// Drawn in an inline viewport below the text already on screen, so the
+/// prompt and instructions printed before the take stay visible. Without a
+/// terminal nothing is drawn and messages are printed as plain lines.
+pub struct LiveDisplay {
+    terminal: Option<Terminal<CrosstermBackend<Stdout>>>,
+    prompt: Option<String>,
+    /// Whether single-key controls are available, to list them
+    keys: bool,
+    /// Whether Space is held to talk rather than pressed to pause
+    push_to_talk: bool,
+    /// Peak of each recent chunk, newest last, as a percentage of full scale
+    waveform: VecDeque<u64>,
+    waveform_len: usize,
+    level_db: f32,
+    clipped_at: Option<Instant>,
+    last_draw: Option<Instant>,
+}
+
+impl LiveDisplay {
+    /// Start the display below the cursor, showing `prompt` when given
+    pub fn new(prompt: Option<&str>, keys: bool) -> Self {
+        let width = crossterm::terminal::size().map_or(80, |(width, _)| width);
+        let terminal = std::io::stdout()
+            .is_terminal()
+            .then(|| {
+                let height = Self::height(prompt, width);
+                Terminal::with_options(
+                    CrosstermBackend::new(std::io::stdout()),
+                    TerminalOptions {
+                        viewport: Viewport::Inline(height),
+                    },
+                )
+                .ok()
+            })
+            .flatten();
+        Self {
+            terminal,
+            prompt: prompt.map(str::to_string),
+            keys,
+            push_to_talk: false,
+            waveform: VecDeque::new(),
+            waveform_len: width.saturating_sub(2).max(1) as usize,
+            level_db: METER_FLOOR_DB,
+            clipped_at: None,
+            last_draw: None,
+        }
+    }
+
+    /// List Space as held to talk
+    pub fn with_push_to_talk(mut self, push_to_talk: bool) -> Self {
+        self.push_to_talk = push_to_talk;
+        self
+    }
+
+    /// Lines the prompt wraps to at `width`, capped
+    fn prompt_lines(prompt: Option<&str>, width: u16) -> u16 {
+        let Some(prompt) = prompt else {
+            return 0;
+        };
+        let usable = width.saturating_sub(4).max(1) as usize;
+        let lines = prompt.chars().count().div_ceil(usable) as u16;
+        lines.clamp(1, MAX_PROMPT_LINES)
+    }
+
+    fn height(prompt: Option<&str>, width: u16) -> u16 {
+        // Border, meter, waveform, time, stats, keys, border
+        let prompt_lines = Self::prompt_lines(prompt, width);
+        2 + prompt_lines + u16::from(prompt_lines > 0) + 1 + WAVEFORM_ROWS + 3
+    }
+
+    /// Take in a chunk of captured audio
+    pub fn push_audio(&mut self, samples: &[f32]) {
+        let peak = samples.iter().fold(0.0f32, |peak, &x| peak.max(x.abs()));
+        self.level_db = (20.0 * peak.max(1e-5).log10()).max(METER_FLOOR_DB);
+        if peak >= CLIP_LEVEL {
+            self.clipped_at = Some(Instant::now());
+        }
+        self.waveform
+            .push_back((peak.min(1.0) * 100.0).round() as u64);
+        while self.waveform.len() > self.waveform_len {
+            self.waveform.pop_front();
+        }
+    }
+
+    /// Redraw, at most every [`REDRAW_INTERVAL`]
+    pub fn draw(&mut self, status: &LiveStatus, karaoke: Option<&KaraokePrompt>) {
+        if self
+            .last_draw
+            .is_some_and(|last| last.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        let Some(mut terminal) = self.terminal.take() else {
+            return;
+        };
+        // A failed draw leaves the recording running without the display
+        if terminal
+            .draw(|frame| self.render(frame, status, karaoke))
+            .is_ok()
+        {
+            self.terminal = Some(terminal);
+        }
+    }
+
+    /// Show a line above the display
+    pub fn message(&mut self, message: &str) {
+        match self.terminal.as_mut() {
+            Some(terminal) => {
+                let _ = terminal.insert_before(1, |buf| {
+                    Paragraph::new(message).render(buf.area, buf);
+                });
+            }
+            None => println!("{message}"),
+        }
+    }
+
+    /// Remove the display, leaving the cursor where it started
+    pub fn finish(&mut self) {
+        if let Some(mut terminal) = self.terminal.take() {
+            let _ = terminal.clear();
+            let _ = terminal.show_cursor();
+        }
+    }
+
+    fn render(&self, frame: &mut Frame, status: &LiveStatus, karaoke: Option<&KaraokePrompt>) {
+        let title = match (status.standby, status.paused_secs) {
+            (Some(_), _) => " ⏳ Waiting ".yellow().bold(),
+            (None, Some(_)) => " ⏸  Paused ".yellow().bold(),
+            (None, None) => " 🎙  Recording ".red().bold(),
+        };
+        let block = Block::bordered().title(title);
+        let area = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let prompt_lines = Self::prompt_lines(self.prompt.as_deref(), frame.area().width);
+        let [prompt_area, _, meter_area, waveform_area, time_area, stats_area, keys_area] =
+            Layout::vertical([
+                Constraint::Length(prompt_lines),
+                Constraint::Length(u16::from(prompt_lines > 0)),
+                Constraint::Length(1),
+                Constraint::Length(WAVEFORM_ROWS),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .areas(area);
+
+        if let Some(prompt) = &self.prompt {
+            frame.render_widget(
+                Paragraph::new(prompt_text(prompt, karaoke, status.elapsed_secs))
+                    .wrap(Wrap { trim: true }),
+                prompt_area,
+            );
+        }
+        self.render_meter(frame, meter_area);
+
+        let waveform: Vec<u64> = self.waveform.iter().copied().collect();
+        frame.render_widget(
+            Sparkline::default()
+                .data(&waveform)
+                .max(100)
+                .style(Style::default().fg(Color::Cyan)),
+            waveform_area,
+        );
+
+        let mut time = vec![Span::raw(format!("{} elapsed", clock(status.elapsed_secs)))];
+        if let Some(duration_secs) = status.duration_secs {
+            time.push(Span::raw(format!(
+                " · {} left",
+                clock((duration_secs - status.elapsed_secs).max(0.0))
+            )));
+        }
+        if let Some(standby) = status.standby {
+            time.push(format!(" · {standby}").yellow());
+        } else if let Some(paused_secs) = status.paused_secs {
+            let resume = if self.push_to_talk {
+                "hold Space to talk"
+            } else {
+                "Space to resume"
+            };
+            time.push(format!(" · paused {paused_secs:.0}s, {resume}").yellow());
+        } else if let Some(silence_secs) = status.silence_secs {
+            let silence = match status.silence_limit_secs {
+                Some(limit_secs) => format!(" · silence {silence_secs:.1}s of {limit_secs:.1}s"),
+                None => format!(" · silence {silence_secs:.1}s"),
+            };
+            time.push(silence.dim());
+        }
+        if let Some(karaoke) = karaoke {
+            time.push(format!(" · {} wpm", karaoke.wpm()).dim());
+        }
+        frame.render_widget(Line::from(time), time_area);
+
+        frame.render_widget(
+            Line::from(format!(
+                "SNR {:.1} dB · VAD {:.1}% · {}",
+                status.snr_db, status.vad_ratio, status.gate
+            ))
+            .dim(),
+            stats_area,
+        );
+        let keys = match (self.keys, self.push_to_talk, karaoke.is_some()) {
+            (false, _, _) => "",
+            (true, true, true) => "Hold Space to talk · Q stop · R restart · +/- pace",
+            (true, true, false) => "Hold Space to talk · Q stop · R restart",
+            (true, false, true) => "Space pause · Q stop · R restart · +/- pace",
+            (true, false, false) => "Space pause · Q stop · R restart",
+        };
+        frame.render_widget(Line::from(keys).dim(), keys_area);
+    }
+
+    fn render_meter(&self, frame: &mut Frame, area: Rect) {
+        let clipping = self.clipped_at.is_some_and(|at| at.elapsed() < CLIP_HOLD);
+        let [gauge_area, clip_area] =
+            Layout::horizontal([Constraint::Min(10), Constraint::Length(6)]).areas(area);
+
+        let ratio = ((self.level_db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0);
+        let color = if clipping || self.level_db > -3.0 {
+            Color::Red
+        } else if self.level_db > -12.0 {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+        frame.render_widget(
+            LineGauge::default()
+                .ratio(ratio as f64)
+                .label(format!("{:>4.0} dB", self.level_db))
+                .filled_style(Style::default().fg(color))
+                .unfilled_style(Style::default().fg(Color::DarkGray)),
+            gauge_area,
+        );
+        let clip = if clipping {
+            Span::styled(" CLIP", Style::default().fg(Color::White).bg(Color::Red))
+        } else {
+            Span::raw(" clip").dim()
+        };
+        frame.render_widget(Line::from(clip), clip_area);
+    }
+}
+
+impl Drop for LiveDisplay {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// The prompt, with words already due dimmed and the current one
+/// highlighted when it is paced
+fn prompt_text<'a>(
+    prompt: &'a str,
+    karaoke: Option<&KaraokePrompt>,
+    elapsed_secs: f64,
+) -> Text<'a> {
+    let Some(karaoke) = karaoke else {
+        return Text::from(prompt);
+    };
+    let current = karaoke.current_word(elapsed_secs);
+    let mut spans = Vec::new();
+    for (index, word) in prompt.split_whitespace().enumerate() {
+        if index > 0 {
+            spans.push(Span::raw(" "));
+        }
+        let style = match current {
+            Some(current) if index == current => Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::REVERSED),
+            Some(current) if index > current => Style::default(),
+            _ => Style::default().add_modifier(Modifier::DIM),
+        };
+        spans.push(Span::styled(word, style));
+    }
+    Text::from(Line::from(spans))
+}
+
+/// `mm:ss.s`
+fn clock(secs: f64) -> String {
+    format!("{:02}:{:04.1}", (secs / 60.0) as u64, secs % 60.0)
+}
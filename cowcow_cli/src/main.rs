@@ -1,5 +1,8 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::IsTerminal;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -23,42 +26,200 @@ struct ExportConfig {
     min_snr: Option<f32>,
     max_clipping: Option<f32>,
     min_vad: Option<f32>,
+    min_speech_secs: Option<f32>,
     days: u32,
+    include_archived: bool,
+    /// Also export takes that were not selected for their prompt
+    all_takes: bool,
+    audio_format: transcode::AudioFormat,
+    /// Bitrate of Opus exports, in bits per second
+    opus_bitrate: u32,
+    /// Where recordings copied with their data directory are found
+    recordings_dir: PathBuf,
+    /// Keep resume progress in memory instead of the database
+    read_only: bool,
+    /// Consent form version in force, to report speakers' consent against
+    consent_form_version: Option<String>,
+    /// Only recordings from these sessions
+    sessions: sessions::SessionFilter,
+    /// Only recordings a reviewer approved
+    approved_only: bool,
+    /// Title of an archive deposit
+    title: Option<String>,
 }
 
-use clap::{Parser, Subcommand};
-use cowcow_core::{AudioProcessor, QcMetrics};
+#[derive(Debug, Clone)]
+struct RecordOptions {
+    lang: String,
+    duration: Option<u32>,
+    prompt: Option<String>,
+    /// ID of the prompt in the script it was read from
+    prompt_id: Option<String>,
+    /// Elicitation fields when the prompt is a question to answer
+    prompt_fields: Option<script::PromptFields>,
+    /// Pronunciation hint shown with the prompt
+    hint: Option<String>,
+    /// A native speaker's reading of the prompt, offered before recording
+    exemplar: Option<PathBuf>,
+    speaker: Option<String>,
+    /// Force karaoke prompt highlighting at this reading rate
+    wpm: Option<u32>,
+    /// Campaign to count the recording towards
+    campaign: Option<String>,
+    /// Measure the device noise floor before recording
+    calibrate: bool,
+    /// Labels to file the recording under
+    tags: Vec<String>,
+    /// Session to add the recording to, whenever it started
+    session: Option<String>,
+    /// Where and with what a new session is recorded
+    session_details: sessions::SessionDetails,
+    /// Play the consent explanation before recording
+    consent: bool,
+    mode: RecordingMode,
+    /// Let the contributor review the take before it is saved
+    review: bool,
+    /// Stop the take after `record.silence_stop_secs` of silence
+    silence_stop: bool,
+    start_mode: StartMode,
+    backend: AudioBackend,
+    /// Record a translation of each kept take in this language
+    translate_to: Option<String>,
+    /// Text of the translation to show while it is recorded
+    translation: Option<String>,
+    /// Translation pair the take belongs to
+    translation_pair: Option<String>,
+    /// Another go after a re-recorded take: consent and calibration are not
+    /// asked for again
+    retake: bool,
+}
+
+/// What becomes of a finished take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TakeDecision {
+    Accept,
+    Rerecord,
+    Discard,
+}
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use cowcow_core::policy::QcReport;
+use cowcow_core::prompt_analysis::PromptAnalysis;
+use cowcow_core::silence::{self, SilenceGate};
+use cowcow_core::timeline::QcTimeline;
+use cowcow_core::trim::TRIM_PADDING_SECS;
+use cowcow_core::SnrEstimator;
+use cowcow_core::{flac, opus, wav, QcMetrics};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use indicatif::{ProgressBar, ProgressStyle};
-use sqlx::sqlite::SqlitePool;
+use crossterm::event::{KeyCode, KeyEventKind};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use sqlx::Row;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
+mod alignment;
 mod auth;
+mod backend;
+mod bench;
+mod campaigns;
+mod cancel;
+mod chunking;
 mod config;
+mod controller;
+mod daemon;
+mod db_encryption;
+mod dedupe;
+mod delete;
+mod devices;
+mod diff;
+mod elan;
+mod gc;
+mod http;
+mod imdi;
+mod import;
+mod karaoke;
+mod keys;
+mod labelstudio;
+mod list;
+mod live;
+mod merge;
+mod mode;
+mod outliers;
+mod pair;
+mod playback;
+mod prompts;
+mod reanalyze;
+mod receipts;
+mod review;
+mod script;
+mod sessions;
+mod simulate;
+mod speakers;
+mod spill;
+mod storage;
+mod streak;
+mod takes;
+mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod transcode;
+mod transcripts;
+mod translations;
+mod trend;
+mod update;
 mod upload;
+mod watchdog;
+mod word_align;
 
+use alignment::CaptureAlignment;
 use auth::{prompt_for_credentials, prompt_for_registration, AuthClient};
-use config::Config;
+use backend::{AudioBackend, InputStream, Signal};
+use config::{Config, Credentials, Scope};
+use controller::ControllerAction;
+use devices::device_name;
+use mode::{RecordingMode, StartMode};
+use spill::{SpillQueue, SpillStats};
 use upload::UploadClient;
 
 /// Cowcow CLI - Offline-first data collection for low-resource languages
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Minimize memory use (overrides `record.low_memory`)
+    #[arg(long, global = true)]
+    low_memory: bool,
+
+    /// Use this data directory instead of `storage.data_dir`, such as a
+    /// copied field backup
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Refuse commands that would write and open the database read-only, to
+    /// inspect a backup without changing it
+    #[arg(long, global = true)]
+    read_only: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once per run, so the size of `Record` does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
+    /// Set up this device: server, data directory, input device, languages,
+    /// microphone calibration and a test upload (kept out of the dataset and
+    /// tokens)
+    Init,
+
     /// Record audio with quality control
     Record {
-        /// Language code (e.g., "sw" for Swahili)
+        /// Language code (e.g., "sw" for Swahili); defaults to the first of
+        /// `record.languages`
         #[arg(short, long)]
-        lang: String,
+        lang: Option<String>,
 
         /// Recording duration in seconds (optional)
         #[arg(short, long)]
@@ -67,6 +228,304 @@ enum Commands {
         /// Prompt text to read
         #[arg(short, long)]
         prompt: Option<String>,
+
+        /// Record every prompt of a script in turn: a text file with one
+        /// prompt per line, or a CSV/TSV with prompt and ID columns
+        #[arg(long, conflicts_with = "prompt")]
+        script: Option<PathBuf>,
+
+        /// Read this prompt from the prompt library (see `cowcow prompts list`)
+        #[arg(long, conflicts_with_all = ["prompt", "script"])]
+        prompt_id: Option<String>,
+
+        /// Read a prompt from the library, picked at random with the
+        /// language's least-recorded prompts most likely
+        #[arg(long, conflicts_with_all = ["prompt", "script", "prompt_id"])]
+        next_prompt: bool,
+
+        /// With --next-prompt, also favour prompts with characters the
+        /// language's recordings have little of
+        #[arg(long, requires = "next_prompt")]
+        coverage: bool,
+
+        /// Pronunciation hint to show with the prompt (scripts and the
+        /// prompt library take it from a `hint` column)
+        #[arg(long, conflicts_with = "script")]
+        hint: Option<String>,
+
+        /// Audio of a native speaker reading the prompt, which can be played
+        /// before recording; whether it was is stored with the recording
+        #[arg(long, conflicts_with = "script")]
+        exemplar: Option<PathBuf>,
+
+        /// Speaker profile ID (see `cowcow speakers list`)
+        #[arg(long)]
+        speaker: Option<String>,
+
+        /// Highlight the prompt at this many words per minute (adjust live with +/-)
+        #[arg(long)]
+        wpm: Option<u32>,
+
+        /// Campaign to count the recording towards (defaults to the
+        /// language's running campaign)
+        #[arg(long)]
+        campaign: Option<String>,
+
+        /// Measure the input device's noise floor before recording
+        #[arg(long)]
+        calibrate: bool,
+
+        /// Tag the recording (repeatable), for filtering with `cowcow list --tag`
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Play the language's consent explanation and wait for the speaker
+        /// to agree first (always on with `record.play_consent`)
+        #[arg(long)]
+        consent: bool,
+
+        /// Input device by name or number in `cowcow devices` (overrides
+        /// `audio.input_device`)
+        #[arg(long)]
+        device: Option<String>,
+
+        /// Recording profile: `music` stops on level instead of voice
+        /// activity, skips auto-trim and is QC'd on loudness and clipping only
+        #[arg(long, default_value = "speech")]
+        mode: RecordingMode,
+
+        /// Play the take back and accept, re-record or discard it before it
+        /// is saved (always on with `record.review_takes`)
+        #[arg(long)]
+        review: bool,
+
+        /// Keep recording through silence until stopped with Q or the
+        /// --duration is reached (for long pauses in narratives)
+        #[arg(long)]
+        no_silence_stop: bool,
+
+        /// How the take starts: `enter` counts down, `voice` starts at the
+        /// first speech (dropping the silence before it), `ptt` records
+        /// only while Space is held
+        #[arg(long, default_value = "enter")]
+        start_mode: StartMode,
+
+        /// Audio source: the input `device`, or a `synthetic` test signal
+        /// for running without a microphone
+        #[arg(long, default_value = "device")]
+        backend: AudioBackend,
+
+        /// Signal of the synthetic backend: speech, sine[:HZ], noise,
+        /// silence, or an audio file played once
+        #[arg(long)]
+        signal: Option<Signal>,
+
+        /// After each kept take, record its translation in this language;
+        /// the two are linked as a translation pair
+        #[arg(long)]
+        translate_to: Option<String>,
+
+        /// Text of the translation to read (scripts take it from a
+        /// `translation` column)
+        #[arg(long, requires = "translate_to", conflicts_with = "script")]
+        translation: Option<String>,
+
+        /// Add the recording to this session (see `cowcow sessions list`)
+        /// instead of the current one
+        #[arg(long, conflicts_with_all = ["location", "microphone", "environment"])]
+        session: Option<String>,
+
+        /// Where the session is recorded (e.g. "Kisumu market"); a change
+        /// starts a new session
+        #[arg(long)]
+        location: Option<String>,
+
+        /// Microphone model used for the session
+        #[arg(long)]
+        microphone: Option<String>,
+
+        /// Kind of place: studio, quiet-room, home, office, classroom,
+        /// outdoor, market, street, vehicle or other
+        #[arg(long)]
+        environment: Option<sessions::Environment>,
+    },
+
+    /// List input devices with their supported sample rates and channels
+    Devices,
+
+    /// Play back a recording
+    Play {
+        /// Recording ID (or a unique prefix of it)
+        recording_id: String,
+
+        /// Playback speed from 0.5 to 2.0; pitch is preserved
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+    },
+
+    /// Review recordings one after another: play each, check its metrics,
+    /// approve or reject it and leave notes, or trim its start and end
+    Review {
+        /// Review only this recording (ID or a unique prefix of it);
+        /// otherwise flagged outliers come first, then recordings that
+        /// failed QC, then the rest
+        recording_id: Option<String>,
+
+        /// Only recordings in this language
+        #[arg(long, conflicts_with = "recording_id")]
+        lang: Option<String>,
+
+        /// Recordings with this verdict (unreviewed, approved, rejected or
+        /// needs_rerecord)
+        #[arg(long, default_value = "unreviewed", conflicts_with = "recording_id")]
+        status: review::ReviewStatus,
+    },
+
+    /// Re-run QC over stored recordings and update their metrics
+    Reanalyze {
+        /// Only recordings in this language
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only recordings made on or after this date (YYYY-MM-DD)
+        #[arg(long, value_parser = reanalyze::parse_since)]
+        since: Option<i64>,
+    },
+
+    /// Correct the text of a recording to what was actually said, when it
+    /// deviates from the prompt
+    Transcribe {
+        /// Recording ID (or a unique prefix of it)
+        #[arg(
+            required_unless_present_any = ["from_csv", "auto"],
+            conflicts_with_all = ["from_csv", "auto"]
+        )]
+        recording_id: Option<String>,
+
+        /// Set many transcripts from a CSV with id and transcript columns
+        #[arg(long, conflicts_with = "auto")]
+        from_csv: Option<PathBuf>,
+
+        /// Run a local whisper model over recordings without a hypothesis,
+        /// flagging likely misreads (needs the `whisper` feature)
+        #[arg(long)]
+        auto: bool,
+
+        /// With --auto, the whisper.cpp model file instead of `asr.model`
+        #[arg(long, requires = "auto")]
+        model: Option<PathBuf>,
+
+        /// With --auto, only recordings in this language
+        #[arg(long, requires = "auto")]
+        lang: Option<String>,
+
+        /// With --auto, transcribe recordings that already have a hypothesis
+        #[arg(long, requires = "auto")]
+        force: bool,
+    },
+
+    /// Time each word of recordings' transcripts with a whisper model's
+    /// token timestamps, for export as CTM and JSON (needs the `whisper`
+    /// feature)
+    Align {
+        /// ggml whisper model file (defaults to `asr.model`)
+        #[arg(long)]
+        model: Option<PathBuf>,
+
+        /// Only recordings in this language
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Re-align recordings that already have word timings
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Protect a recording from deletion by pruning or `dedupe --delete`
+    Pin {
+        /// Recording ID (or a unique prefix of it)
+        recording_id: String,
+    },
+
+    /// Remove a recording's pin
+    Unpin {
+        /// Recording ID (or a unique prefix of it)
+        recording_id: String,
+    },
+
+    /// Keep a recording on disk but leave it out of uploads and exports
+    Archive {
+        /// Recording ID (or a unique prefix of it)
+        recording_id: String,
+    },
+
+    /// Return an archived recording to uploads and exports
+    Unarchive {
+        /// Recording ID (or a unique prefix of it)
+        recording_id: String,
+    },
+
+    /// Delete recordings: their audio, database rows and upload queue
+    /// entries
+    ///
+    /// Give a recording ID or filters; pinned recordings are never deleted.
+    Delete {
+        /// Recording ID (or a unique prefix of it)
+        #[arg(conflicts_with_all = ["lang", "status", "speaker", "tags", "qc_failed", "since"])]
+        recording_id: Option<String>,
+
+        /// Only recordings in this language
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only recordings with this upload status (uploaded, pending, failed)
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Only recordings of this speaker profile ID or name
+        #[arg(long)]
+        speaker: Option<String>,
+
+        /// Only recordings with this tag (repeatable; all must match)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Only recordings that failed QC
+        #[arg(long)]
+        qc_failed: bool,
+
+        /// Only recordings made on or after this date (YYYY-MM-DD)
+        #[arg(long, value_parser = reanalyze::parse_since)]
+        since: Option<i64>,
+
+        /// Also delete archived recordings that match
+        #[arg(long)]
+        include_archived: bool,
+
+        /// Keep recordings that have not been uploaded yet
+        #[arg(long)]
+        uploaded_only: bool,
+
+        /// Show what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Find recordings whose audio duplicates an earlier recording
+    ///
+    /// Duplicates are flagged so uploads skip them, or deleted with --delete.
+    Dedupe {
+        /// Minimum fingerprint similarity (0-1) to treat recordings as duplicates
+        #[arg(long, default_value_t = dedupe::DEFAULT_THRESHOLD)]
+        threshold: f32,
+
+        /// Delete duplicates instead of flagging them
+        #[arg(long)]
+        delete: bool,
     },
 
     /// Upload queued recordings
@@ -74,20 +533,172 @@ enum Commands {
         /// Force upload even if QC metrics are poor
         #[arg(short, long)]
         force: bool,
+
+        /// Re-evaluate recordings previously skipped for QC against the
+        /// current thresholds
+        #[arg(long)]
+        requalify: bool,
+    },
+
+    /// Stay running and upload in the background; `cowcow upload` hands
+    /// its work to a running daemon instead of competing with it
+    Daemon {
+        /// Seconds between background uploads (0 uploads only when asked)
+        #[arg(long, default_value_t = 300)]
+        interval_secs: u64,
+    },
+
+    /// List recordings, newest first
+    List {
+        /// Filter by language code
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Filter by upload status (uploaded, pending, failed)
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Filter by speaker profile ID or name
+        #[arg(long)]
+        speaker: Option<String>,
+
+        /// Only recordings with this tag (repeatable; all must match)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Minimum SNR in dB
+        #[arg(long)]
+        min_snr: Option<f32>,
+
+        /// Maximum clipping percentage
+        #[arg(long)]
+        max_clipping: Option<f32>,
+
+        /// Minimum VAD ratio percentage
+        #[arg(long)]
+        min_vad: Option<f32>,
+
+        /// Only recordings made on or after this date (YYYY-MM-DD)
+        #[arg(long, value_parser = reanalyze::parse_since)]
+        since: Option<i64>,
+
+        /// Only recordings that passed QC
+        #[arg(long, conflicts_with = "qc_failed")]
+        qc_passed: bool,
+
+        /// Only recordings that failed QC
+        #[arg(long)]
+        qc_failed: bool,
+
+        /// Also list archived recordings
+        #[arg(long)]
+        include_archived: bool,
+
+        /// Sort by created, duration, snr, lang or speaker
+        #[arg(long, default_value = "created")]
+        sort: list::SortKey,
+
+        /// Sort ascending instead of descending
+        #[arg(long)]
+        asc: bool,
+
+        /// Recordings per page
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+
+        /// Page to show, starting at 1
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+
+        /// Output format (table, json or csv)
+        #[arg(short, long, default_value = "table")]
+        output: list::ListFormat,
     },
 
     /// Show recording statistics
-    Stats,
+    Stats {
+        /// Chart a QC metric's daily mean and the daily recording count
+        /// instead: snr, clipping, vad, speech, duration, rumble, hum,
+        /// reverb or rate
+        #[arg(long)]
+        trend: Option<trend::TrendMetric>,
+
+        /// Days charted by --trend (1 to 3650)
+        #[arg(long, default_value = "30", value_parser = clap::value_parser!(u32).range(1..=3650))]
+        days: u32,
+
+        /// With --trend, only recordings from this input device
+        #[arg(long, requires = "trend")]
+        device: Option<String>,
+
+        /// Only recordings from this session
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Only recordings from sessions in this kind of place
+        #[arg(long)]
+        environment: Option<sessions::Environment>,
+
+        /// Only recordings from sessions at this location
+        #[arg(long)]
+        location: Option<String>,
+    },
+
+    /// Show your recording streak and the last two weeks of activity
+    Progress,
 
     /// Check system health
     Doctor,
 
+    /// Measure the input device's noise floor for accurate SNR
+    Calibrate,
+
+    /// Import audio files (WAV, FLAC, MP3, OGG or M4A) as recordings
+    Import {
+        /// Audio files, or directories of them, to import
+        #[arg(required_unless_present = "from_labelstudio")]
+        files: Vec<PathBuf>,
+
+        /// Language code (e.g., "sw" for Swahili)
+        #[arg(short, long, required_unless_present = "from_labelstudio")]
+        lang: Option<String>,
+
+        /// Prompt text the files were read from
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Also import from subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// CSV of each file's transcript (file and transcript columns);
+        /// overrides --prompt for the files it lists
+        #[arg(long)]
+        transcript_csv: Option<PathBuf>,
+
+        /// Hard-link WAVs already in the library's format instead of
+        /// copying them
+        #[arg(long)]
+        link: bool,
+
+        /// Instead of audio, pull corrected transcripts back from a Label
+        /// Studio JSON export
+        #[arg(long, conflicts_with_all = ["files", "prompt", "recursive", "transcript_csv", "link"])]
+        from_labelstudio: Option<PathBuf>,
+    },
+
     /// Export recordings to a directory
     Export {
-        /// Export format (json, wav, or both)
+        /// Export format: json, wav, both, elan (audio with ELAN
+        /// annotation files), or elar (a language archive deposit: elan plus
+        /// IMDI metadata)
         #[arg(short, long)]
         format: String,
 
+        /// Title of the deposit, for --format elar
+        #[arg(long)]
+        title: Option<String>,
+
         /// Destination directory
         #[arg(short, long)]
         dest: PathBuf,
@@ -112,9 +723,42 @@ enum Commands {
         #[arg(long)]
         min_vad: Option<f32>,
 
+        /// Minimum seconds of detected speech
+        #[arg(long)]
+        min_speech_secs: Option<f32>,
+
         /// Export recordings from this many days ago
         #[arg(long, default_value = "30")]
         days: u32,
+
+        /// Also export archived recordings
+        #[arg(long)]
+        include_archived: bool,
+
+        /// Export every take of a prompt, not just the selected one
+        #[arg(long)]
+        all_takes: bool,
+
+        /// Only recordings approved in `cowcow review`
+        #[arg(long)]
+        approved_only: bool,
+
+        /// Only recordings from this session
+        #[arg(long)]
+        session: Option<String>,
+
+        /// Only recordings from sessions in this kind of place
+        #[arg(long)]
+        environment: Option<sessions::Environment>,
+
+        /// Only recordings from sessions at this location
+        #[arg(long)]
+        location: Option<String>,
+
+        /// Audio format of exported recordings (wav, flac, opus, mp3, ogg or m4a);
+        /// mp3, ogg and m4a need ffmpeg
+        #[arg(long, default_value = "wav")]
+        audio_format: transcode::AudioFormat,
     },
 
     /// Authentication commands
@@ -134,818 +778,5971 @@ enum Commands {
         #[command(subcommand)]
         command: TokensCommands,
     },
-}
-
-#[derive(Subcommand)]
-enum AuthCommands {
-    /// Login with username and password
-    Login,
 
-    /// Register a new account
-    Register,
+    /// Merge export bundles from other devices into this library, keeping
+    /// one copy of recordings found on several devices
+    Merge {
+        /// Bundle directories written by `cowcow export --format both`
+        #[arg(required = true)]
+        bundles: Vec<PathBuf>,
+    },
 
-    /// Logout (clear stored credentials)
-    Logout,
+    /// Compare two export manifests or device databases
+    Diff {
+        /// First manifest (recordings.json) or database (cowcow.db)
+        a: PathBuf,
 
-    /// Show current authentication status
-    Status,
-}
+        /// Second manifest (recordings.json) or database (cowcow.db)
+        b: PathBuf,
 
-#[derive(Subcommand)]
-enum ConfigCommands {
-    /// Show current configuration
-    Show,
+        /// Also pair unmatched recordings by audio content hash
+        #[arg(long)]
+        by_hash: bool,
 
-    /// Set configuration values
-    Set {
-        /// Configuration key (e.g., "api.endpoint")
-        key: String,
+        /// Output format (text or json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
 
-        /// Configuration value
-        value: String,
+    /// Collection campaigns published by the server
+    Campaigns {
+        #[command(subcommand)]
+        command: CampaignsCommands,
     },
 
-    /// Reset configuration to defaults
-    Reset,
-}
+    /// Pair a companion mobile app by showing a QR code to scan
+    ///
+    /// The code carries the server endpoint, the campaign and a single-use
+    /// enrollment token scoped to what the device may do.
+    Pair {
+        #[command(subcommand)]
+        command: Option<PairCommands>,
 
-#[derive(Subcommand)]
-enum TokensCommands {
-    /// Show current token balance
-    Balance,
+        /// Campaign (project) the device records for
+        #[arg(long)]
+        campaign: Option<String>,
 
-    /// Show token transaction history
-    History {
-        /// Show transactions from this many days ago
-        #[arg(short, long, default_value = "30")]
-        days: u32,
-    },
-}
+        /// What the device may do: record or upload
+        #[arg(long, default_value = "upload")]
+        scope: Scope,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+        /// Minutes the code stays valid
+        #[arg(long, default_value = "15")]
+        expires_mins: u32,
 
-    // Parse command line arguments
-    let cli = Cli::parse();
+        /// Name to recognize the device by, e.g. the enumerator's name
+        #[arg(long)]
+        label: Option<String>,
 
-    // Load configuration
-    let config = Config::load()?;
-    config.validate()?;
+        /// Also save the QR code as an SVG image
+        #[arg(long)]
+        svg: Option<PathBuf>,
+    },
 
-    match cli.command {
-        Commands::Record {
-            lang,
-            duration,
-            prompt,
-        } => {
-            let db = init_db(&config).await?;
-            record_audio(&lang, duration, prompt, &db, &config).await?;
-        }
-        Commands::Upload { force } => {
-            let db = init_db(&config).await?;
-            upload_recordings(force, &db, &config).await?;
+    /// Prompt library: import scripts and see which prompts still need
+    /// recordings
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommands,
+    },
+
+    /// Per-speaker statements of uploaded work, for paying contributors
+    Receipts {
+        #[command(subcommand)]
+        command: ReceiptsCommands,
+    },
+
+    /// Recording sessions with their place, microphone and machine
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommands,
+    },
+
+    /// Takes of the same prompt: list them and choose the one to upload
+    Takes {
+        #[command(subcommand)]
+        command: TakesCommands,
+    },
+
+    /// Speaker profile and guardian consent commands
+    Speakers {
+        #[command(subcommand)]
+        command: SpeakersCommands,
+    },
+
+    /// Recordings storage commands
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommands,
+    },
+
+    /// Quality control tooling
+    Qc {
+        #[command(subcommand)]
+        command: QcCommands,
+    },
+
+    /// Anonymous usage telemetry (opt-in)
+    Telemetry {
+        #[command(subcommand)]
+        command: TelemetryCommands,
+    },
+
+    /// Update cowcow to the latest signed release
+    SelfUpdate {
+        /// Only check whether an update is available
+        #[arg(long)]
+        check: bool,
+
+        /// Restore the version replaced by the last update
+        #[arg(long)]
+        rollback: bool,
+
+        /// Install without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum QcCommands {
+    /// Measure QC accept/reject accuracy on a labeled corpus
+    ///
+    /// The corpus has one directory per label; recordings under `clean/` (or
+    /// `accept/`) should pass QC and all others should be rejected.
+    Bench {
+        /// Corpus directory
+        #[arg(long)]
+        corpus: PathBuf,
+
+        /// Output format (text or json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+
+        /// Fail if precision falls below this value (0-1)
+        #[arg(long)]
+        min_precision: Option<f64>,
+
+        /// Fail if recall falls below this value (0-1)
+        #[arg(long)]
+        min_recall: Option<f64>,
+    },
+    /// Re-evaluate past recordings against a hypothetical QC policy
+    ///
+    /// Reports how many recordings would pass or fail and the resulting
+    /// dataset hours, compared with the configured policy. Nothing is
+    /// changed.
+    Simulate {
+        /// Start from a preset policy (asr-training, tts or lenient)
+        /// instead of the configured one
+        #[arg(long)]
+        preset: Option<String>,
+
+        /// Override a rule's threshold, e.g. --threshold min_snr=18 (repeatable)
+        #[arg(long = "threshold", value_name = "RULE=VALUE")]
+        thresholds: Vec<String>,
+
+        /// Only recordings made in the last N days
+        #[arg(long, default_value = "90")]
+        days: u32,
+
+        /// Output format (text or json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TelemetryCommands {
+    /// Opt in to anonymous usage telemetry
+    Enable,
+
+    /// Opt out and delete any buffered events
+    Disable,
+
+    /// Show whether telemetry is enabled and how many events are buffered
+    Status,
+
+    /// Print the buffered events exactly as they would be sent
+    Show,
+
+    /// Send buffered events now
+    Upload,
+}
+
+#[derive(Subcommand)]
+enum StorageCommands {
+    /// Show where recordings are stored and how many are spooled
+    Status,
+
+    /// Move spooled recordings to the configured recordings directory
+    Migrate,
+
+    /// Delete temporary files and recordings the database does not know,
+    /// left behind by crashes; files the database refers to are never touched
+    Gc {
+        /// Only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Leave files younger than this alone, as they may still be in use
+        #[arg(long, default_value_t = 24)]
+        min_age_hours: u64,
+
+        /// Also sweep temporary files from this directory, such as an
+        /// export destination (repeatable)
+        #[arg(long = "dir")]
+        dirs: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommands {
+    /// Login with username and password
+    Login {
+        /// Scope to request: record, upload or admin (repeatable; defaults
+        /// to what the server grants the account)
+        #[arg(long = "scope")]
+        scopes: Vec<Scope>,
+    },
+
+    /// Register a new account
+    Register,
+
+    /// Logout (clear stored credentials)
+    Logout,
+
+    /// Show current authentication status
+    Status,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Show current configuration
+    Show,
+
+    /// Set configuration values
+    Set {
+        /// Configuration key (e.g., "api.endpoint")
+        key: String,
+
+        /// Configuration value
+        value: String,
+    },
+
+    /// Reset configuration to defaults
+    Reset,
+}
+
+#[derive(Subcommand)]
+enum TokensCommands {
+    /// Show current token balance
+    Balance,
+
+    /// Show token transaction history
+    History {
+        /// Show transactions from this many days ago
+        #[arg(short, long, default_value = "30")]
+        days: u32,
+    },
+}
+
+#[derive(Subcommand)]
+enum CampaignsCommands {
+    /// Fetch the current campaigns from the server
+    Sync,
+
+    /// Show running and upcoming campaigns with their countdown
+    List,
+}
+
+#[derive(Subcommand)]
+enum PromptsCommands {
+    /// Add or update prompts from a script: one prompt per line, or a
+    /// CSV/TSV with prompt and ID columns (or question, answer_type and
+    /// follow_up columns for elicitation)
+    Import {
+        /// Script file
+        file: PathBuf,
+
+        /// Language of the prompts; defaults to the first of
+        /// `record.languages`
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        /// Domain to file the prompts under (e.g. "health", "news")
+        #[arg(long)]
+        domain: Option<String>,
+    },
+
+    /// List prompts with how often each has been recorded
+    List {
+        /// Only prompts in this language
+        #[arg(short, long)]
+        lang: Option<String>,
+
+        /// Only prompts in this domain
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Only prompts that have no recordings yet
+        #[arg(long)]
+        unrecorded: bool,
+    },
+
+    /// Show how many prompts of each language and domain have recordings
+    Stats {
+        /// Only prompts in this language
+        #[arg(short, long)]
+        lang: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReceiptsCommands {
+    /// Export each speaker's uploaded minutes, accepted takes and tokens
+    /// for a month
+    Export {
+        /// Month of the uploads (YYYY-MM, UTC)
+        #[arg(long)]
+        month: receipts::Month,
+
+        /// Output format (csv or json)
+        #[arg(long, default_value = "csv")]
+        format: receipts::ReceiptFormat,
+
+        /// File to write (default: standard output)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TakesCommands {
+    /// List prompts read more than once, with each take's QC; the selected
+    /// take is marked with *
+    List {
+        /// Only the takes of this recording's prompt
+        recording_id: Option<String>,
+    },
+
+    /// Upload and export this take instead of the automatically selected one
+    Select {
+        /// Recording ID of the take
+        recording_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionsCommands {
+    /// List sessions, newest first, with their recording counts
+    List {
+        /// Only sessions in this kind of place
+        #[arg(long)]
+        environment: Option<sessions::Environment>,
+
+        /// Only sessions at this location
+        #[arg(long)]
+        location: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PairCommands {
+    /// Show paired devices and pending codes
+    List,
+
+    /// Revoke a pairing and the device's access
+    Revoke {
+        /// Pairing ID (from `cowcow pair list`)
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SpeakersCommands {
+    /// Add a speaker profile
+    Add {
+        /// Speaker name or pseudonym
+        #[arg(short, long)]
+        name: String,
+
+        /// Mark the speaker as a minor (requires guardian consent before upload)
+        #[arg(long)]
+        minor: bool,
+    },
+
+    /// List speaker profiles with their consent status
+    List,
+
+    /// Record guardian consent for a minor speaker
+    Consent {
+        /// Speaker ID
+        speaker_id: String,
+
+        /// Spoken consent recording from the guardian (instead of typed confirmation)
+        #[arg(long)]
+        audio_file: Option<PathBuf>,
+
+        /// Number of days the consent remains valid
+        #[arg(long, default_value = "365")]
+        valid_days: u32,
+    },
+
+    /// Revoke guardian consent for a speaker
+    Revoke {
+        /// Speaker ID
+        speaker_id: String,
+    },
+
+    /// Record a speaker's own agreement to the consent form
+    Agree {
+        /// Speaker ID
+        speaker_id: String,
+
+        /// Version of the form agreed to (defaults to
+        /// `record.consent_form_version`)
+        #[arg(long)]
+        form_version: Option<String>,
+
+        /// Spoken consent recording (instead of typed confirmation)
+        #[arg(long, conflicts_with = "record_secs")]
+        audio_file: Option<PathBuf>,
+
+        /// Record the spoken consent from the microphone for this many seconds
+        #[arg(long)]
+        record_secs: Option<u32>,
+    },
+
+    /// Withdraw a speaker's consent; their recordings are no longer uploaded
+    Withdraw {
+        /// Speaker ID
+        speaker_id: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    tracing_subscriber::fmt::init();
+
+    // Parse command line arguments
+    let matches = Cli::command().get_matches();
+    let command_path = command_path(&matches);
+    let cli = Cli::from_arg_matches(&matches)?;
+
+    // Load configuration
+    let mut config = Config::load()?;
+    config.validate()?;
+    if cli.low_memory {
+        config.record.low_memory = true;
+    }
+    if let Some(data_dir) = cli.data_dir {
+        config.use_data_dir(data_dir);
+    }
+    config.read_only = cli.read_only;
+    if config.read_only && !READ_ONLY_COMMANDS.contains(&command_path.as_str()) {
+        return Err(anyhow::anyhow!(
+            "`{command_path}` would change the data directory; it is disabled with --read-only"
+        ));
+    }
+
+    if !config.read_only {
+        gc::startup_sweep(&config);
+    }
+    if !matches!(command_path.as_str(), "record" | "daemon" | "progress") {
+        remind_in_terminal(&config).await;
+    }
+
+    let started = std::time::Instant::now();
+    let result = run_command(cli.command, &config).await;
+    if !config.read_only {
+        telemetry::record_command(&config, &command_path, started.elapsed(), &result);
+    }
+
+    // Telemetry is sent alongside recordings, never on its own schedule
+    if result.is_ok() && command_path == "upload" && config.telemetry.enabled {
+        if let Err(e) = telemetry::upload(&config).await {
+            warn!("Failed to upload telemetry: {}", e);
+        }
+    }
+
+    result
+}
+
+/// Remind a contributor who has stopped recording, at most once a day, when
+/// they next run another command in a terminal
+async fn remind_in_terminal(config: &Config) {
+    if config.read_only || !std::io::stderr().is_terminal() || !config.database_path().exists() {
+        return;
+    }
+    // Never ask for a passphrase, or encrypt the database, for a reminder
+    if config.storage.db_encryption && !db_encryption::passphrase_at_hand() {
+        return;
+    }
+    let reminder = match open_db_read_only(config, false).await {
+        Ok(db) => streak::due_reminder(config, &db).await,
+        Err(e) => Err(e),
+    };
+    match reminder {
+        Ok(Some(message)) => {
+            eprintln!("⏰ {message}");
+            streak::mark_reminded(config);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to check for a reminder: {:#}", e),
+    }
+}
+
+/// Commands that only read the data directory, the ones `--read-only` allows
+const READ_ONLY_COMMANDS: [&str; 17] = [
+    "list",
+    "stats",
+    "progress",
+    "export",
+    "play",
+    "diff",
+    "qc simulate",
+    "devices",
+    "storage status",
+    "speakers list",
+    "campaigns list",
+    "prompts list",
+    "prompts stats",
+    "receipts export",
+    "sessions list",
+    "config show",
+    "telemetry status",
+];
+
+/// Subcommand names only (e.g. `speakers add`), never argument values
+fn command_path(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub)) = current.subcommand() {
+        names.push(name);
+        current = sub;
+    }
+    names.join(" ")
+}
+
+async fn run_command(command: Commands, config: &Config) -> Result<()> {
+    match command {
+        Commands::Init => {
+            AuthClient::new(config.clone())
+                .require_scope_or_offline(Scope::Admin, "Changing settings")?;
+            run_setup_wizard(config).await?;
+        }
+        Commands::Record {
+            lang,
+            duration,
+            prompt,
+            prompt_id,
+            next_prompt,
+            coverage,
+            hint,
+            exemplar,
+            script,
+            speaker,
+            wpm,
+            campaign,
+            calibrate,
+            tags,
+            consent,
+            device,
+            mode,
+            review,
+            no_silence_stop,
+            start_mode,
+            backend,
+            signal,
+            translate_to,
+            translation,
+            session,
+            location,
+            microphone,
+            environment,
+        } => {
+            let backend = match (backend, signal) {
+                (AudioBackend::Synthetic(_), Some(signal)) => AudioBackend::Synthetic(signal),
+                (AudioBackend::Device, Some(_)) => {
+                    anyhow::bail!("--signal needs --backend synthetic")
+                }
+                (backend, None) => backend,
+            };
+            if start_mode == StartMode::Ptt && !std::io::stdin().is_terminal() {
+                anyhow::bail!("Push-to-talk needs an interactive terminal");
+            }
+            let mut config = config.clone();
+            if let Some(device) = device {
+                config.audio.input_device = Some(device);
+            }
+            let config = &config;
+            let db = init_db(config).await?;
+            controller::start(&config.input.devices);
+            let mut stored_prompt = match &prompt_id {
+                Some(id) => Some(
+                    prompts::get_prompt(&db, id)
+                        .await?
+                        .with_context(|| format!("Unknown prompt: {id}"))?,
+                ),
+                None => None,
+            };
+            let lang = lang
+                .or_else(|| stored_prompt.as_ref().map(|stored| stored.lang.clone()))
+                .or_else(|| config.record.languages.first().cloned())
+                .context("No language given: pass --lang or set record.languages")?;
+            if translate_to.as_deref() == Some(lang.as_str()) {
+                anyhow::bail!("--translate-to must be another language than the take's");
+            }
+            if next_prompt {
+                let next = prompts::next_prompt(&db, &lang, coverage)
+                    .await?
+                    .with_context(|| {
+                        format!("No prompts for language '{lang}': run `cowcow prompts import`")
+                    })?;
+                println!(
+                    "📜 Next prompt: {} (recorded {} time(s) so far)",
+                    next.id, next.times_recorded
+                );
+                stored_prompt = Some(next);
+            }
+            let options = RecordOptions {
+                lang,
+                duration,
+                prompt_id: stored_prompt.as_ref().map(|stored| stored.id.clone()),
+                prompt_fields: stored_prompt.as_ref().and_then(prompts::Prompt::structured),
+                hint: hint.or_else(|| stored_prompt.as_ref().and_then(|p| p.hint.clone())),
+                exemplar: exemplar.or_else(|| {
+                    stored_prompt
+                        .as_ref()
+                        .and_then(|p| p.exemplar.as_ref().map(PathBuf::from))
+                }),
+                prompt: stored_prompt.map(|stored| stored.text).or(prompt),
+                speaker,
+                wpm,
+                campaign,
+                calibrate,
+                tags,
+                session,
+                session_details: sessions::SessionDetails {
+                    location,
+                    microphone,
+                    environment,
+                },
+                consent,
+                mode,
+                review,
+                silence_stop: !no_silence_stop,
+                start_mode,
+                backend,
+                translate_to,
+                translation,
+                translation_pair: None,
+                retake: false,
+            };
+            if let Some(script) = script {
+                let prompts = script::load_script(&script)?;
+                record_script(&prompts, options, &db, config).await?;
+                return Ok(());
+            }
+            record_take(options, &db, config).await?;
+        }
+        Commands::Devices => {
+            let devices = devices::list_input_devices()?;
+            devices::print_devices(&devices, config.audio.input_device.as_deref());
+        }
+        Commands::Play {
+            recording_id,
+            speed,
+        } => {
+            let db = init_db(config).await?;
+            play_recording(&recording_id, speed, &db, config).await?;
+        }
+        Commands::Review {
+            recording_id,
+            lang,
+            status,
+        } => {
+            let db = init_db(config).await?;
+            let ids = match recording_id {
+                Some(recording_id) => vec![find_recording(&recording_id, &db).await?.0],
+                None => review::queue(&db, lang.as_deref(), status).await?,
+            };
+            if ids.is_empty() {
+                println!("No {status} recordings to review");
+                return Ok(());
+            }
+            review_recordings(&ids, &db, config).await?;
+        }
+        Commands::Align { model, lang, force } => {
+            let db = init_db(config).await?;
+            let model = model.or_else(|| config.asr.model.clone()).context(
+                "No whisper model: pass --model or run `cowcow config set asr.model <path>`",
+            )?;
+            let model = std::sync::Arc::new(cowcow_core::asr::WhisperModel::load(&model)?);
+            let cancel = cancel::on_ctrl_c();
+            let summary =
+                word_align::align_library(&db, config, model, lang.as_deref(), force, &cancel)
+                    .await?;
+            println!(
+                "{} Aligned {} recordings",
+                if summary.cancelled { "⏹️ " } else { "✅" },
+                summary.aligned
+            );
+            if summary.cancelled {
+                println!("   Interrupted; run the same command again to carry on");
+            }
+            if summary.missing > 0 {
+                println!("⚠️  {} recordings have no audio on disk", summary.missing);
+            }
+            if summary.failed > 0 {
+                println!("❌ {} recordings could not be aligned", summary.failed);
+            }
+        }
+        Commands::Reanalyze { lang, since } => {
+            let db = init_db(config).await?;
+            let cancel = cancel::on_ctrl_c();
+            let summary =
+                reanalyze::reanalyze_library(&db, config, lang.as_deref(), since, &cancel).await?;
+            let flagged = outliers::flag_outliers(&db).await?;
+            if summary.resumed > 0 {
+                println!(
+                    "⏩ Resumed: {} recordings were re-analyzed by an earlier run",
+                    summary.resumed
+                );
+            }
+            println!(
+                "{} Re-analyzed {} recordings; {} changed QC verdict",
+                if summary.cancelled { "⏹️ " } else { "✅" },
+                summary.reanalyzed,
+                summary.changed
+            );
+            if summary.cancelled {
+                println!("   Interrupted; run the same command again to resume");
+            }
+            if flagged > 0 {
+                println!("🔎 {flagged} outliers flagged for `cowcow review`");
+            }
+            if summary.missing > 0 {
+                println!("⚠️  {} recordings have no audio on disk", summary.missing);
+            }
+            if summary.failed > 0 {
+                println!("❌ {} recordings could not be analyzed", summary.failed);
+            }
+        }
+        Commands::Pin { recording_id } => {
+            let db = init_db(config).await?;
+            set_recording_flag(&recording_id, "pinned", true, &db).await?;
+        }
+        Commands::Unpin { recording_id } => {
+            let db = init_db(config).await?;
+            set_recording_flag(&recording_id, "pinned", false, &db).await?;
+        }
+        Commands::Transcribe {
+            recording_id,
+            from_csv,
+            auto,
+            model,
+            lang,
+            force,
+        } => {
+            let db = init_db(config).await?;
+            if auto {
+                let model = model.or_else(|| config.asr.model.clone()).context(
+                    "No whisper model: pass --model or run `cowcow config set asr.model <path>`",
+                )?;
+                let model = std::sync::Arc::new(cowcow_core::asr::WhisperModel::load(&model)?);
+                let cancel = cancel::on_ctrl_c();
+                let summary = transcripts::transcribe_library(
+                    &db,
+                    config,
+                    model,
+                    lang.as_deref(),
+                    force,
+                    &cancel,
+                )
+                .await?;
+                println!(
+                    "🗣️  Transcribed {} recording(s), {} missing audio, {} failed",
+                    summary.transcribed, summary.missing, summary.failed
+                );
+                if !summary.misreads.is_empty() {
+                    println!(
+                        "⚠️  {} likely misread(s), straying from the prompt:",
+                        summary.misreads.len()
+                    );
+                    for (id, wer) in &summary.misreads {
+                        println!("   {id}  WER {:.0}%", wer * 100.0);
+                    }
+                }
+                if summary.cancelled {
+                    println!("⏹️  Interrupted; run the same command again to carry on");
+                }
+                return Ok(());
+            }
+            match (recording_id, from_csv) {
+                (_, Some(path)) => save_transcripts(&transcripts::load_csv(&path)?, &db).await?,
+                (Some(recording_id), None) => {
+                    transcribe_recording(&recording_id, &db, config).await?
+                }
+                (None, None) => unreachable!("clap requires a recording ID or --from-csv"),
+            }
+        }
+        Commands::Archive { recording_id } => {
+            let db = init_db(config).await?;
+            set_recording_flag(&recording_id, "archived", true, &db).await?;
+        }
+        Commands::Unarchive { recording_id } => {
+            let db = init_db(config).await?;
+            set_recording_flag(&recording_id, "archived", false, &db).await?;
+        }
+        Commands::Delete {
+            recording_id,
+            lang,
+            status,
+            speaker,
+            tags,
+            qc_failed,
+            since,
+            include_archived,
+            uploaded_only,
+            dry_run,
+            yes,
+        } => {
+            let db = init_db(config).await?;
+            let ids = match recording_id {
+                Some(recording_id) => vec![find_recording(&recording_id, &db).await?.0],
+                None => {
+                    if lang.is_none()
+                        && status.is_none()
+                        && speaker.is_none()
+                        && tags.is_empty()
+                        && !qc_failed
+                        && since.is_none()
+                    {
+                        anyhow::bail!(
+                            "Give a recording ID or at least one filter (--lang, --status, \
+                             --speaker, --tag, --qc-failed, --since)"
+                        );
+                    }
+                    let query = list::ListQuery {
+                        lang,
+                        status,
+                        speaker,
+                        tags,
+                        min_snr: None,
+                        max_clipping: None,
+                        min_vad: None,
+                        since,
+                        qc_passed: qc_failed.then_some(false),
+                        include_archived,
+                        sort: list::SortKey::Created,
+                        ascending: true,
+                        limit: u32::MAX,
+                        page: 1,
+                    };
+                    list::list_recordings(&db, &query)
+                        .await?
+                        .recordings
+                        .into_iter()
+                        .map(|recording| recording.id)
+                        .collect()
+                }
+            };
+            if !dry_run {
+                AuthClient::new(config.clone())
+                    .require_scope_or_offline(Scope::Admin, "Deleting recordings")?;
+            }
+            delete::run(&ids, uploaded_only, dry_run, yes, &db, config).await?;
+        }
+        Commands::Dedupe { threshold, delete } => {
+            if delete {
+                AuthClient::new(config.clone())
+                    .require_scope_or_offline(Scope::Admin, "Deleting duplicates")?;
+            }
+            let db = init_db(config).await?;
+            dedupe_recordings(threshold, delete, &db, config).await?;
+        }
+        Commands::Upload { force, requalify } => {
+            let db = init_db(config).await?;
+            upload_recordings(force, requalify, &db, config).await?;
+        }
+        Commands::Daemon { interval_secs } => {
+            let db = init_db(config).await?;
+            println!("🐄 Daemon running; Ctrl-C to stop");
+            daemon::run(
+                config,
+                &db,
+                Duration::from_secs(interval_secs),
+                &cancel::on_ctrl_c(),
+            )
+            .await?;
+        }
+        Commands::List {
+            lang,
+            status,
+            speaker,
+            tags,
+            min_snr,
+            max_clipping,
+            min_vad,
+            since,
+            qc_passed,
+            qc_failed,
+            include_archived,
+            sort,
+            asc,
+            limit,
+            page,
+            output,
+        } => {
+            let db = init_db(config).await?;
+            let query = list::ListQuery {
+                lang,
+                status,
+                speaker,
+                tags,
+                min_snr,
+                max_clipping,
+                min_vad,
+                since,
+                qc_passed: (qc_passed || qc_failed).then_some(qc_passed),
+                include_archived,
+                sort,
+                ascending: asc,
+                limit,
+                page,
+            };
+            let page = list::list_recordings(&db, &query).await?;
+            match output {
+                list::ListFormat::Table => list::print_table(&page),
+                list::ListFormat::Json => println!("{}", serde_json::to_string_pretty(&page)?),
+                list::ListFormat::Csv => list::write_csv(&page, std::io::stdout().lock())?,
+            }
+        }
+        Commands::Stats {
+            trend,
+            days,
+            device,
+            session,
+            environment,
+            location,
+        } => {
+            let db = init_db(config).await?;
+            let filter = sessions::SessionFilter {
+                session,
+                environment,
+                location,
+            };
+            match trend {
+                Some(metric) => {
+                    let points =
+                        trend::daily_trend(&db, metric, days, device.as_deref(), &filter).await?;
+                    trend::print_trend(metric, &points);
+                }
+                None => show_stats(&db, &filter).await?,
+            }
+        }
+        Commands::Progress => {
+            let db = init_db(config).await?;
+            show_progress(config, &db).await?;
+        }
+        Commands::Doctor => {
+            check_health(config).await?;
+        }
+        Commands::Calibrate => {
+            calibrate_device(config).await?;
+        }
+        Commands::Import {
+            files,
+            lang,
+            prompt,
+            recursive,
+            transcript_csv,
+            link,
+            from_labelstudio,
+        } => {
+            let db = init_db(config).await?;
+            if let Some(path) = from_labelstudio {
+                let import = labelstudio::load_export(&path)?;
+                save_transcripts(&import.transcripts, &db).await?;
+                if import.unmatched > 0 {
+                    println!("⚠️  {} task(s) did not name a recording", import.unmatched);
+                }
+                if import.untranscribed > 0 {
+                    println!("   {} task(s) had no transcript", import.untranscribed);
+                }
+                return Ok(());
+            }
+            let lang = lang.context("--lang is required to import audio")?;
+            let files = import::collect_files(&files, recursive)?;
+            if files.is_empty() {
+                println!("No audio files found");
+                return Ok(());
+            }
+            let options = import::ImportOptions {
+                lang,
+                prompt,
+                transcripts: match &transcript_csv {
+                    Some(path) => import::load_transcripts(path)?,
+                    None => HashMap::new(),
+                },
+                link,
+            };
+            let summary = import::import_files(&files, &options, &db, config).await?;
+            println!(
+                "✅ Imported {} file(s), {} already imported, {} failed",
+                summary.imported, summary.skipped, summary.failed
+            );
+        }
+        Commands::Export {
+            format,
+            title,
+            dest,
+            lang,
+            status,
+            min_snr,
+            max_clipping,
+            min_vad,
+            min_speech_secs,
+            days,
+            include_archived,
+            all_takes,
+            approved_only,
+            audio_format,
+            session,
+            environment,
+            location,
+        } => {
+            // An analyst's read-only copy carries whatever credentials the
+            // device had, and exporting from it changes nothing
+            if !config.read_only {
+                AuthClient::new(config.clone())
+                    .require_scope_or_offline(Scope::Admin, "Exporting")?;
+            }
+            let db = init_db(config).await?;
+            let export_config = ExportConfig {
+                format,
+                dest,
+                lang,
+                status,
+                min_snr,
+                max_clipping,
+                min_vad,
+                min_speech_secs,
+                days,
+                include_archived,
+                all_takes,
+                audio_format,
+                opus_bitrate: config.audio.opus_bitrate,
+                recordings_dir: config.recordings_dir(),
+                read_only: config.read_only,
+                consent_form_version: config.record.consent_form_version.clone(),
+                sessions: sessions::SessionFilter {
+                    session,
+                    environment,
+                    location,
+                },
+                approved_only,
+                title,
+            };
+            export_recordings(export_config, &db, &cancel::on_ctrl_c()).await?;
+        }
+        Commands::Auth { command } => {
+            handle_auth_command(command, config).await?;
+        }
+        Commands::Config { command } => {
+            handle_config_command(command, config).await?;
+        }
+        Commands::Tokens { command } => {
+            handle_tokens_command(command, config).await?;
+        }
+        Commands::Merge { bundles } => {
+            AuthClient::new(config.clone())
+                .require_scope_or_offline(Scope::Admin, "Merging bundles")?;
+            let db = init_db(config).await?;
+            for bundle in &bundles {
+                let summary = merge::merge_bundle(&db, config, bundle).await?;
+                println!(
+                    "📦 {}: {} added, {} renamed (ID collision), {} duplicates, {} already merged",
+                    bundle.display(),
+                    summary.added,
+                    summary.renamed,
+                    summary.duplicates,
+                    summary.already_merged
+                );
+                if summary.upload_settled > 0 {
+                    println!(
+                        "   {} local recordings were already uploaded from another device",
+                        summary.upload_settled
+                    );
+                }
+                if summary.missing_audio > 0 {
+                    println!(
+                        "⚠️  {} recordings have no audio in the bundle",
+                        summary.missing_audio
+                    );
+                }
+            }
+        }
+        Commands::Diff {
+            a,
+            b,
+            by_hash,
+            output,
+        } => {
+            diff_recordings(&a, &b, by_hash, &output).await?;
+        }
+        Commands::Campaigns { command } => {
+            let db = init_db(config).await?;
+            handle_campaigns_command(command, &db, config).await?;
+        }
+        Commands::Prompts { command } => {
+            let db = init_db(config).await?;
+            handle_prompts_command(command, &db, config).await?;
+        }
+        Commands::Receipts { command } => {
+            let db = init_db(config).await?;
+            handle_receipts_command(command, &db, config).await?;
+        }
+        Commands::Sessions { command } => {
+            let db = init_db(config).await?;
+            handle_sessions_command(command, &db).await?;
+        }
+        Commands::Takes { command } => {
+            let db = init_db(config).await?;
+            handle_takes_command(command, &db).await?;
+        }
+        Commands::Pair {
+            command,
+            campaign,
+            scope,
+            expires_mins,
+            label,
+            svg,
+        } => {
+            let db = init_db(config).await?;
+            match command {
+                Some(command) => handle_pair_command(command, &db, config).await?,
+                None => {
+                    let request = pair::EnrollmentRequest {
+                        scope,
+                        campaign_id: campaign,
+                        expires_in_secs: expires_mins as u64 * 60,
+                        label,
+                    };
+                    pair_device(request, svg.as_deref(), &db, config).await?;
+                }
+            }
+        }
+        Commands::Speakers { command } => {
+            let db = init_db(config).await?;
+            handle_speakers_command(command, &db, config).await?;
+        }
+        Commands::Storage { command } => {
+            let db = init_db(config).await?;
+            handle_storage_command(command, &db, config).await?;
+        }
+        Commands::Qc { command } => {
+            handle_qc_command(command, config).await?;
+        }
+        Commands::Telemetry { command } => {
+            handle_telemetry_command(command, config).await?;
+        }
+        Commands::SelfUpdate {
+            check,
+            rollback,
+            yes,
+        } => {
+            self_update(check, rollback, yes, config).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Database memory map size in low-memory mode
+const LOW_MEMORY_MMAP_BYTES: u64 = 64 * 1024 * 1024;
+
+async fn init_db(config: &Config) -> Result<SqlitePool> {
+    let db_path = config.database_path();
+
+    // A snapshot is read as it is: no directories, schema upgrades or
+    // journal files are created
+    if config.read_only {
+        return open_db_read_only(config, true).await;
+    }
+
+    // Create directory if it doesn't exist
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Create recordings directory (external directories are prepared by `config set`)
+    if config.storage.recordings_dir.is_none() {
+        std::fs::create_dir_all(config.recordings_dir())?;
+    }
+
+    let mut options = SqliteConnectOptions::from_str(&format!("sqlite:{}", db_path.display()))?
+        .create_if_missing(true);
+    if let Some(key) = db_encryption::prepare(&db_path, config.storage.db_encryption, true).await? {
+        options = options.pragma("key", key);
+    }
+    let mut pool_options = SqlitePoolOptions::new();
+    if config.record.low_memory {
+        // Read through a memory map instead of a heap page cache, on one connection
+        options = options
+            .pragma("mmap_size", LOW_MEMORY_MMAP_BYTES.to_string())
+            .pragma("cache_size", "-512");
+        pool_options = pool_options.max_connections(1);
+    }
+    let pool = open_pool(pool_options, options).await?;
+
+    // Create tables if they don't exist
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS recordings (
+            id TEXT PRIMARY KEY,
+            lang TEXT NOT NULL,
+            prompt TEXT,
+            qc_metrics TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            uploaded_at INTEGER,
+            wav_path TEXT NOT NULL
+        );
+        
+        CREATE TABLE IF NOT EXISTS upload_queue (
+            recording_id TEXT PRIMARY KEY,
+            attempts INTEGER NOT NULL,
+            last_attempt INTEGER,
+            FOREIGN KEY (recording_id) REFERENCES recordings(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS speakers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            is_minor INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            started_at INTEGER NOT NULL,
+            room_tone_path TEXT,
+            noise_floor_db REAL
+        );
+
+        CREATE TABLE IF NOT EXISTS campaigns (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            lang TEXT NOT NULL,
+            starts_at INTEGER NOT NULL,
+            ends_at INTEGER NOT NULL,
+            target_hours REAL NOT NULL,
+            collected_hours REAL NOT NULL DEFAULT 0,
+            token_multiplier REAL NOT NULL DEFAULT 1
+        );
+
+        CREATE TABLE IF NOT EXISTS prompts (
+            id TEXT PRIMARY KEY,
+            lang TEXT NOT NULL,
+            text TEXT NOT NULL,
+            domain TEXT,
+            times_recorded INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            fields TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS guardian_consents (
+            id TEXT PRIMARY KEY,
+            speaker_id TEXT NOT NULL,
+            guardian_name TEXT NOT NULL,
+            method TEXT NOT NULL,
+            consent_path TEXT,
+            granted_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            revoked_at INTEGER,
+            FOREIGN KEY (speaker_id) REFERENCES speakers(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS consent_explanations (
+            id TEXT PRIMARY KEY,
+            speaker_id TEXT,
+            lang TEXT NOT NULL,
+            explanation_path TEXT NOT NULL,
+            agreed INTEGER NOT NULL,
+            answered_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS speaker_consents (
+            id TEXT PRIMARY KEY,
+            speaker_id TEXT NOT NULL,
+            form_version TEXT NOT NULL,
+            method TEXT NOT NULL,
+            consent_path TEXT,
+            granted_at INTEGER NOT NULL,
+            withdrawn_at INTEGER,
+            FOREIGN KEY (speaker_id) REFERENCES speakers(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS paired_devices (
+            id TEXT PRIMARY KEY,
+            label TEXT,
+            scope TEXT NOT NULL,
+            campaign_id TEXT,
+            created_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            redeemed_at INTEGER,
+            device_name TEXT,
+            revoked_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS merge_provenance (
+            source TEXT NOT NULL,
+            source_id TEXT NOT NULL,
+            recording_id TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            merged_at INTEGER NOT NULL,
+            PRIMARY KEY (source, source_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS upload_tuning (
+            endpoint TEXT PRIMARY KEY,
+            chunk_size INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS job_checkpoints (
+            job TEXT PRIMARY KEY,
+            done TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .execute(&pool)
+    .await?;
+
+    // Columns added after the initial schema
+    ensure_column(&pool, "recordings", "speaker_id", "TEXT").await?;
+    ensure_column(&pool, "recordings", "qc_report", "TEXT").await?;
+    ensure_column(&pool, "recordings", "trim_start_secs", "REAL").await?;
+    ensure_column(&pool, "recordings", "trim_end_secs", "REAL").await?;
+    ensure_column(&pool, "recordings", "auto_trim_start_secs", "REAL").await?;
+    ensure_column(&pool, "recordings", "auto_trim_end_secs", "REAL").await?;
+    ensure_column(&pool, "recordings", "session_id", "TEXT").await?;
+    ensure_column(&pool, "recordings", "fingerprint", "TEXT").await?;
+    ensure_column(&pool, "recordings", "content_hash", "TEXT").await?;
+    ensure_column(&pool, "recordings", "device", "TEXT").await?;
+    ensure_column(&pool, "recordings", "outlier_metrics", "TEXT").await?;
+    ensure_column(&pool, "recordings", "duplicate_of", "TEXT").await?;
+    ensure_column(&pool, "recordings", "campaign_id", "TEXT").await?;
+    ensure_column(&pool, "recordings", "pinned", "INTEGER NOT NULL DEFAULT 0").await?;
+    ensure_column(&pool, "recordings", "capture_started_at_ms", "INTEGER").await?;
+    ensure_column(&pool, "recordings", "tags", "TEXT").await?;
+    ensure_column(&pool, "recordings", "imported_from", "TEXT").await?;
+    ensure_column(&pool, "recordings", "word_alignment", "TEXT").await?;
+    ensure_column(&pool, "recordings", "paused_secs", "REAL").await?;
+    ensure_column(&pool, "recordings", "prompt_id", "TEXT").await?;
+    ensure_column(&pool, "recordings", "tokens_awarded", "INTEGER").await?;
+    ensure_column(&pool, "recordings", "stall_incidents", "TEXT").await?;
+    ensure_column(&pool, "recordings", "prompt_fields", "TEXT").await?;
+    for column in [
+        "location",
+        "microphone",
+        "environment",
+        "os",
+        "host",
+        "app_version",
+        "input_device",
+    ] {
+        ensure_column(&pool, "sessions", column, "TEXT").await?;
+    }
+    // Earlier versions stored the machine's host name, which names
+    // contributors in exported sessions; it is no longer recorded
+    sqlx::query("UPDATE sessions SET host = NULL WHERE host IS NOT NULL")
+        .execute(&pool)
+        .await
+        .context("Failed to clear session host names")?;
+    ensure_column(&pool, "prompts", "fields", "TEXT").await?;
+    ensure_column(&pool, "prompts", "hint", "TEXT").await?;
+    ensure_column(&pool, "prompts", "exemplar", "TEXT").await?;
+    ensure_column(
+        &pool,
+        "recordings",
+        "archived",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+    .await?;
+    ensure_column(&pool, "recordings", "take_group", "TEXT").await?;
+    ensure_column(
+        &pool,
+        "recordings",
+        "take_selected",
+        "INTEGER NOT NULL DEFAULT 1",
+    )
+    .await?;
+    ensure_column(
+        &pool,
+        "recordings",
+        "take_chosen",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+    .await?;
+    ensure_column(&pool, "recordings", "translation_pair", "TEXT").await?;
+    ensure_column(&pool, "recordings", "transcript", "TEXT").await?;
+    ensure_column(&pool, "recordings", "exemplar_played", "INTEGER").await?;
+    ensure_column(&pool, "recordings", "asr_hypothesis", "TEXT").await?;
+    ensure_column(&pool, "recordings", "asr_confidence", "REAL").await?;
+    ensure_column(&pool, "recordings", "asr_wer", "REAL").await?;
+    ensure_column(
+        &pool,
+        "recordings",
+        "review_status",
+        "TEXT NOT NULL DEFAULT 'unreviewed'",
+    )
+    .await?;
+    ensure_column(&pool, "recordings", "review_notes", "TEXT").await?;
+    ensure_column(&pool, "recordings", "reviewed_at", "INTEGER").await?;
+    ensure_column(&pool, "upload_queue", "skip_reason", "TEXT").await?;
+    ensure_column(&pool, "upload_queue", "skip_detail", "TEXT").await?;
+    ensure_column(&pool, "upload_queue", "skip_policy", "TEXT").await?;
+
+    // Outlier baselines are read per speaker and device after every take
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS recordings_baseline ON recordings (speaker_id, device)",
+    )
+    .execute(&pool)
+    .await
+    .context("Failed to index recordings")?;
+
+    Ok(pool)
+}
+
+/// Open the database only to read it, without creating or upgrading it;
+/// an `immutable` one is not even locked, for snapshots nothing else writes
+async fn open_db_read_only(config: &Config, immutable: bool) -> Result<SqlitePool> {
+    let db_path = config.database_path();
+    if !db_path.exists() {
+        return Err(anyhow::anyhow!("No database at {}", db_path.display()));
+    }
+    let mut options = SqliteConnectOptions::from_str(&format!("sqlite:{}", db_path.display()))?
+        .read_only(true)
+        .immutable(immutable);
+    let key = db_encryption::prepare(&db_path, config.storage.db_encryption, false).await?;
+    if let Some(key) = key {
+        options = options.pragma("key", key);
+    }
+    open_pool(SqlitePoolOptions::new(), options).await
+}
+
+/// Connect to the database, explaining the error an encrypted database gives
+/// with the wrong passphrase
+async fn open_pool(
+    pool_options: SqlitePoolOptions,
+    options: SqliteConnectOptions,
+) -> Result<SqlitePool> {
+    let pool = pool_options.connect_with(options).await?;
+    // A wrong key only shows on the first read
+    if let Err(e) = sqlx::query("SELECT COUNT(*) FROM sqlite_master")
+        .execute(&pool)
+        .await
+    {
+        if e.to_string().contains("file is not a database") {
+            return Err(anyhow::anyhow!(
+                "Failed to open the database: wrong passphrase, or not a database"
+            ));
+        }
+        return Err(e.into());
+    }
+    Ok(pool)
+}
+
+/// Add a column to an existing table if an older database lacks it
+async fn ensure_column(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+            .bind(table)
+            .bind(column)
+            .fetch_one(pool)
+            .await?;
+
+    if exists == 0 {
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to add column {table}.{column}"))?;
+    }
+
+    Ok(())
+}
+
+async fn record_audio(
+    options: RecordOptions,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<TakeDecision> {
+    let RecordOptions {
+        lang,
+        duration,
+        prompt,
+        prompt_id,
+        prompt_fields,
+        hint,
+        exemplar,
+        speaker,
+        wpm,
+        campaign,
+        calibrate,
+        mut tags,
+        session,
+        session_details,
+        consent,
+        mode,
+        review,
+        silence_stop,
+        start_mode,
+        backend,
+        translate_to: _,
+        translation: _,
+        translation_pair,
+        retake,
+    } = options;
+    if mode == RecordingMode::Music && !tags.iter().any(|tag| tag == "music") {
+        tags.push("music".to_string());
+    }
+    let tags = list::tags_column(&tags)?;
+    let lang = lang.as_str();
+    info!("Starting recording for language: {}", lang);
+
+    // Recordings of a library prompt are linked to it even when its text
+    // was given directly
+    let (prompt_id, prompt_fields, hint, exemplar) = match (prompt_id, &prompt) {
+        (None, Some(text)) => match prompts::find_by_text(db, lang, text).await? {
+            Some(stored) => {
+                let fields = prompt_fields.or_else(|| stored.structured());
+                let hint = hint.or(stored.hint);
+                let exemplar = exemplar.or_else(|| stored.exemplar.map(PathBuf::from));
+                (Some(stored.id), fields, hint, exemplar)
+            }
+            None => (None, prompt_fields, hint, exemplar),
+        },
+        (prompt_id, _) => (prompt_id, prompt_fields, hint, exemplar),
+    };
+
+    let campaign = campaigns::campaign_for_recording(db, lang, campaign.as_deref()).await?;
+    if let Some(campaign) = &campaign {
+        let pending = campaigns::pending_hours(db, &campaign.id).await?;
+        println!("📣 {}", campaign.status_line(chrono::Utc::now(), pending));
+    }
+
+    if let Some(speaker_id) = &speaker {
+        let profile = speakers::get_speaker(db, speaker_id)
+            .await?
+            .with_context(|| format!("Unknown speaker: {speaker_id}"))?;
+
+        let (status, _) = speakers::consent_status(db, config, &profile.id).await?;
+        if !status.is_cleared() {
+            anyhow::bail!(
+                "{} has no valid consent to form {} ({}). Run: cowcow speakers agree {}",
+                profile.name,
+                config.record.consent_form_version.as_deref().unwrap_or("-"),
+                status.as_str(),
+                profile.id
+            );
+        }
+
+        if !speakers::speaker_cleared_for_upload(db, config, &profile).await? {
+            println!(
+                "⚠️  {} is a minor without valid guardian consent. Recordings will be kept locally and blocked from upload.",
+                profile.name
+            );
+            println!("   Run: cowcow speakers consent {}", profile.id);
+        }
+    }
+
+    if !retake
+        && (consent || config.record.play_consent)
+        && !play_consent_explanation(db, config, lang, speaker.as_deref()).await?
+    {
+        println!("🚫 Consent declined; nothing was recorded");
+        return Ok(TakeDecision::Discard);
+    }
+
+    // Initialize audio input
+    let (device_name, mut capture_rate, stream, mut rx) = open_capture(&backend, config)?;
+    let mut resampler = cowcow_core::resample::Resampler::new(
+        capture_rate,
+        config.audio.sample_rate,
+        config.audio.channels,
+    )?;
+    if !resampler.is_passthrough() {
+        info!(
+            "Device does not support {} Hz, resampling from {} Hz",
+            config.audio.sample_rate, capture_rate
+        );
+    }
+
+    let low_memory = config.record.low_memory;
+    let mut stream = Some(stream);
+
+    let calibrated_floor_db = if calibrate && !retake {
+        Some(calibrate_noise_floor(&mut rx, capture_rate, &device_name, config).await?)
+    } else {
+        None
+    };
+
+    // Create output directory, spooling internally if the recordings drive is missing
+    let location = storage::recordings_location(config)?;
+    if let storage::RecordingsLocation::Spool(spool_dir) = &location {
+        println!(
+            "⚠️  Recordings directory {} is not mounted, saving to {} for now.",
+            config.recordings_dir().display(),
+            spool_dir.display()
+        );
+    } else if storage::spooled_count(config) > 0 {
+        match storage::migrate_spool(db, config).await {
+            Ok(summary) => println!(
+                "📦 Moved {} spooled recording(s) to {}",
+                summary.moved,
+                config.recordings_dir().display()
+            ),
+            Err(e) => error!("Failed to migrate spooled recordings: {}", e),
+        }
+    }
+
+    // Recordings made close together in the same place share a session and
+    // its room tone
+    let described = session.is_some()
+        || session_details.location.is_some()
+        || session_details.microphone.is_some()
+        || session_details.environment.is_some();
+    let current = match &session {
+        Some(id) => Some(
+            sessions::get_session(db, id)
+                .await?
+                .with_context(|| format!("Unknown session: {id}"))?,
+        ),
+        None => sessions::current_session(db, config)
+            .await?
+            .filter(|current| !session_details.conflicts_with(current)),
+    };
+    let session = match current {
+        Some(session) => session,
+        None => {
+            let room_tone = if config.record.room_tone_secs > 0 {
+                println!(
+                    "🤫 New session: capturing {}s of room tone, please stay quiet...",
+                    config.record.room_tone_secs
+                );
+                Some(
+                    capture_ambient(&mut rx, capture_rate, config.record.room_tone_secs, config)
+                        .await?,
+                )
+            } else {
+                None
+            };
+            let noise_floor_db = room_tone
+                .as_deref()
+                .map(|samples| room_tone_floor_db(samples, config));
+            let spec = config
+                .bit_depth()
+                .spec(config.audio.sample_rate, config.audio.channels);
+            sessions::create_session(
+                db,
+                room_tone.as_deref().map(|samples| (samples, spec)),
+                noise_floor_db,
+                &location.path().join("room_tone"),
+                &session_details,
+                &device_name,
+            )
+            .await?
+        }
+    };
+    if described && !retake {
+        println!("📍 Session {} ({})", session.id, session.describe());
+    }
+
+    // Create audio processor, calibrated to the freshest noise floor known:
+    // this run's calibration, the session's room tone, then the device's
+    let mut builder = config.processor_builder();
+    let noise_floor_db = calibrated_floor_db
+        .or(session.noise_floor_db)
+        .or_else(|| config.noise_floor_db(&device_name));
+    if let Some(noise_floor_db) = noise_floor_db {
+        builder = builder.snr_estimator(SnrEstimator::FixedFloor { noise_floor_db });
+    }
+    let mut processor = builder.build()?;
+    info!("Using {} VAD", processor.vad_backend());
+
+    let output_dir = location.path().join(lang);
+    std::fs::create_dir_all(&output_dir)?;
+
+    // Generate unique ID for this recording
+    let recording_id = Uuid::new_v4();
+    let wav_path = output_dir.join(format!("{recording_id}.wav"));
+
+    // Create WAV writer
+    let spec = config
+        .bit_depth()
+        .spec(config.audio.sample_rate, config.audio.channels);
+    let mut writer = hound::WavWriter::create(&wav_path, spec)?;
+
+    // Optional clean-up filtering of what is saved; QC below describes the
+    // audio as captured
+    let filter_config = config.filter_config();
+    let mut filter = filter_config.is_enabled().then(|| {
+        cowcow_core::filter::FilterChain::new(
+            config.audio.sample_rate,
+            config.audio.channels,
+            filter_config,
+        )
+    });
+    let mut raw_writer = match &filter {
+        Some(_) if config.audio.keep_raw => Some(hound::WavWriter::create(
+            storage::raw_path(&wav_path),
+            spec,
+        )?),
+        _ => None,
+    };
+
+    // Analyze in fixed frames so the saved timeline lines up with the audio
+    let mut timeline = if low_memory {
+        QcTimeline::summary_only()
+    } else {
+        QcTimeline::new()
+    };
+    let frame_len = QcTimeline::frame_len(config.audio.sample_rate, config.audio.channels);
+    let mut pending = Vec::with_capacity(frame_len);
+    let mut clock = alignment::CaptureClock::new(config.audio.sample_rate);
+    if let Some(stream) = &stream {
+        clock.follow(stream.stamps());
+    }
+    let _start_time = std::time::Instant::now();
+    let duration = duration.map(|d| Duration::from_secs(d as u64));
+
+    // Track actual audio duration based on samples processed
+    let mut total_samples_processed = 0u64;
+    // Interleaved samples per second of audio
+    let samples_per_second = config.audio.sample_rate as u64 * config.audio.channels as u64;
+
+    // Silence detection parameters
+    // Releasing the key is how a push-to-talk take goes quiet
+    let silence_stop_secs =
+        (silence_stop && start_mode != StartMode::Ptt && config.record.silence_stop_secs > 0.0)
+            .then_some(config.record.silence_stop_secs);
+    let rms_threshold_db = (config.record.silence_rms_threshold > 0.0)
+        .then(|| 20.0 * config.record.silence_rms_threshold.log10());
+    let mut silence_start_samples = None::<u64>; // Track when silence started
+
+    // Silence is judged against the room's noise floor rather than a fixed
+    // level, starting from the floor measured for this session
+    let mut silence_gate = SilenceGate::new(noise_floor_db);
+
+    // Reopen the input stream if the audio stack stops delivering samples
+    let mut watchdog = watchdog::CaptureWatchdog::new(config.record.stall_timeout_secs);
+    let mut stalled_out = None::<anyhow::Error>;
+    let mut spill_stats = SpillStats::default();
+
+    // Long prompts (or an explicit --wpm) get karaoke-style pacing; a
+    // question is answered, not read along with
+    let mut karaoke = prompt
+        .as_deref()
+        .filter(|_| prompt_fields.is_none())
+        .and_then(|text| {
+            let long_prompt = config.record.karaoke_min_words > 0
+                && text.split_whitespace().count() >= config.record.karaoke_min_words;
+            (wpm.is_some() || long_prompt).then(|| {
+                karaoke::KaraokePrompt::new(text, wpm.unwrap_or(config.record.karaoke_wpm))
+            })
+        });
+
+    // Display prompt if provided
+    if let Some(fields) = &prompt_fields {
+        println!("\n❓ Please answer the following question:");
+        println!("\"{}\"", fields.question);
+        if let Some(answer_type) = &fields.answer_type {
+            println!("   Expected answer: {answer_type}");
+        }
+        if let Some(follow_up) = &fields.follow_up {
+            println!("   Then follow up with: {follow_up}");
+        }
+    } else if let Some(prompt_text) = &prompt {
+        println!("\nPlease read the following text:");
+        println!("\"{prompt_text}\"");
+        if let Some(karaoke) = &karaoke {
+            println!(
+                "The current word will be highlighted at {} words per minute (+/- to adjust).",
+                karaoke.wpm()
+            );
+        }
+    }
+    if let Some(hint) = hint.as_ref().filter(|_| prompt.is_some()) {
+        println!("   Pronunciation: {hint}");
+    }
+    // A missing exemplar should not stop the contributor from recording
+    let exemplar = exemplar.and_then(|path| match playback::Clip::load(&path) {
+        Ok(clip) => Some(clip),
+        Err(e) => {
+            warn!("Exemplar {} unavailable: {:#}", path.display(), e);
+            None
+        }
+    });
+    let mut exemplar_played = None;
+    if exemplar.is_some() || (prompt.is_some() && start_mode == StartMode::Enter) {
+        exemplar_played = wait_for_start(start_mode, exemplar.as_ref()).await?;
+    }
+
+    // Give user time to prepare; the other start modes wait for the
+    // contributor instead
+    if start_mode == StartMode::Enter && config.record.countdown_secs > 0 {
+        println!("Get ready to speak...");
+        for i in (1..=config.record.countdown_secs).rev() {
+            println!("Starting in {i}...");
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+    // Presses from before the take, e.g. during the exemplar, must not stop it
+    controller::discard_pending_actions();
+    if silence_stop_secs.is_none() && duration.is_none() {
+        println!("Recording until you press Q (silence does not stop it)");
+    }
+    let standby = match start_mode {
+        StartMode::Enter => {
+            println!("🎙️  RECORDING NOW!");
+            None
+        }
+        StartMode::Voice => {
+            println!("👂 Listening: recording starts when you start speaking");
+            Some("start speaking when ready")
+        }
+        StartMode::Ptt => {
+            println!("🎙️  Hold Space while you speak");
+            Some("hold Space to talk")
+        }
+    };
+    let mut raw_mode = Some(keys::RawModeGuard::enable());
+    let mut push_to_talk = (start_mode == StartMode::Ptt).then(keys::PushToTalk::enable);
+    let mut live = live::LiveDisplay::new(
+        prompt_fields
+            .as_ref()
+            .map(|fields| fields.question.as_str())
+            .or(prompt.as_deref()),
+        raw_mode
+            .as_ref()
+            .is_some_and(keys::RawModeGuard::is_enabled),
+    )
+    .with_push_to_talk(push_to_talk.is_some());
+    let mut status = live::LiveStatus {
+        duration_secs: duration.map(|duration| duration.as_secs_f64()),
+        silence_limit_secs: silence_stop_secs,
+        standby,
+        ..Default::default()
+    };
+
+    // Voice-activated takes keep a little audio from before the speech was
+    // heard, so the first word's onset is not clipped
+    let pre_roll_len = (TRIM_PADDING_SECS as f64 * samples_per_second as f64) as usize;
+    let mut pre_roll = std::collections::VecDeque::with_capacity(pre_roll_len);
+
+    // Audio arriving while paused is dropped; the pause is timed separately
+    let mut paused_since = None::<std::time::Instant>;
+    let mut paused = Duration::ZERO;
+    loop {
+        let elapsed_secs = total_samples_processed as f64 / samples_per_second as f64;
+        let mut interrupted = false;
+        let mut restart = false;
+        let mut actions = Vec::new();
+        while let Some(key) = keys::poll_key_event() {
+            if key.kind == KeyEventKind::Press {
+                if let Some(action) = controller::action_for_key(&key) {
+                    actions.push(action);
+                    continue;
+                }
+            }
+            if let Some(push_to_talk) = push_to_talk.as_mut() {
+                if key.code == KeyCode::Char(' ') {
+                    push_to_talk.key(&key);
+                    continue;
+                }
+            }
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                _ if keys::is_interrupt(&key) => interrupted = true,
+                KeyCode::Char('q') | KeyCode::Char('Q') => interrupted = true,
+                KeyCode::Char('r') | KeyCode::Char('R') => restart = true,
+                KeyCode::Char(' ') => match paused_since.take() {
+                    Some(since) => paused += since.elapsed(),
+                    None => paused_since = Some(std::time::Instant::now()),
+                },
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    if let Some(karaoke) = karaoke.as_mut() {
+                        karaoke.adjust(karaoke::WPM_STEP as i32, elapsed_secs)
+                    }
+                }
+                KeyCode::Char('-') => {
+                    if let Some(karaoke) = karaoke.as_mut() {
+                        karaoke.adjust(-(karaoke::WPM_STEP as i32), elapsed_secs)
+                    }
+                }
+                _ => {}
+            }
+        }
+        actions.extend(std::iter::from_fn(controller::poll_action));
+        for action in actions {
+            match action {
+                // A voice-activated take can be started by hand too
+                ControllerAction::Record if status.standby.is_some() && push_to_talk.is_none() => {
+                    status.standby = None;
+                    live.message("🎙️  Recording");
+                }
+                ControllerAction::Record | ControllerAction::Next => interrupted = true,
+                ControllerAction::Pause => match paused_since.take() {
+                    Some(since) => paused += since.elapsed(),
+                    None => paused_since = Some(std::time::Instant::now()),
+                },
+            }
+        }
+        // Push-to-talk takes start at the first hold and pause between holds
+        if let Some(push_to_talk) = &push_to_talk {
+            let held = push_to_talk.is_held();
+            if status.standby.is_some() {
+                if held {
+                    status.standby = None;
+                }
+            } else if held {
+                if let Some(since) = paused_since.take() {
+                    paused += since.elapsed();
+                }
+            } else if paused_since.is_none() {
+                paused_since = Some(std::time::Instant::now());
+            }
+        }
+        if restart {
+            live.finish();
+            drop(raw_mode.take());
+            drop(writer);
+            drop(raw_writer);
+            storage::remove_recording_files(&wav_path)?;
+            println!("🔁 Restarting the take");
+            return Ok(TakeDecision::Rerecord);
+        }
+        if interrupted {
+            live.finish();
+            drop(raw_mode.take());
+            println!("Recording stopped");
+            break;
+        }
+
+        // Use timeout to avoid infinite waiting
+        let timeout_result = tokio::time::timeout(
+            Duration::from_millis(10), // Shorter timeout for more responsive processing
+            rx.recv(),
+        )
+        .await;
+
+        if let Ok(Some(captured)) = &timeout_result {
+            clock.received(captured.len() / config.audio.channels as usize);
+        }
+        match timeout_result {
+            Ok(Some(_)) if push_to_talk.is_some() && status.standby.is_some() => {
+                watchdog.feed();
+                live.draw(&status, karaoke.as_ref());
+            }
+            Ok(Some(_)) if paused_since.is_some() => {
+                watchdog.feed();
+                status.paused_secs = Some(
+                    (paused + paused_since.map_or(Duration::ZERO, |since| since.elapsed()))
+                        .as_secs_f64(),
+                );
+                live.draw(&status, karaoke.as_ref());
+            }
+            Ok(Some(captured)) => {
+                watchdog.feed();
+                let mut samples = resampler.process(&captured)?;
+                if samples.is_empty() {
+                    continue;
+                }
+
+                // Until speech is heard, only the gate and the pre-roll see
+                // the audio; the gate learns the room's floor meanwhile
+                if status.standby.is_some() {
+                    let level_db = silence::level_db(&samples);
+                    let gate = silence_gate.update(
+                        level_db,
+                        false,
+                        samples.len() as f64 / samples_per_second as f64,
+                    );
+                    pre_roll.extend(samples.iter().copied());
+                    while pre_roll.len() > pre_roll_len.max(samples.len()) {
+                        pre_roll.pop_front();
+                    }
+                    live.push_audio(&samples);
+                    if !(gate.active
+                        || rms_threshold_db.is_some_and(|threshold| level_db > threshold))
+                    {
+                        live.draw(&status, karaoke.as_ref());
+                        continue;
+                    }
+                    status.standby = None;
+                    live.message("🗣️  Speech heard, recording");
+                    // Whole frames of the pre-roll, so channels stay interleaved
+                    let channels = config.audio.channels as usize;
+                    let skip = pre_roll.len() % channels;
+                    samples = pre_roll.drain(..).skip(skip).collect();
+                }
+                clock.push(
+                    samples.len() / config.audio.channels as usize,
+                    resampler.buffered_frames(),
+                );
+
+                // Process complete frames; live stats show the latest one
+                pending.extend_from_slice(&samples);
+                let mut start = 0;
+                while pending.len() - start >= frame_len {
+                    timeline.push(processor.process_chunk(&pending[start..start + frame_len]));
+                    start += frame_len;
+                }
+                pending.drain(..start);
+                let chunk_metrics = timeline.last().cloned().unwrap_or_default();
+
+                // Write samples to WAV file
+                match filter.as_mut() {
+                    Some(filter) => {
+                        for &sample in &filter.process(&samples) {
+                            wav::write_sample(&mut writer, sample)?;
+                        }
+                    }
+                    None => {
+                        for &sample in &samples {
+                            wav::write_sample(&mut writer, sample)?;
+                        }
+                    }
+                }
+                if let Some(raw_writer) = raw_writer.as_mut() {
+                    for &sample in &samples {
+                        wav::write_sample(raw_writer, sample)?;
+                    }
+                }
+
+                // Update total samples processed
+                total_samples_processed += samples.len() as u64;
+
+                // Calculate actual audio duration based on samples processed
+                let actual_duration = Duration::from_secs_f64(
+                    total_samples_processed as f64 / samples_per_second as f64,
+                );
+
+                // Consider voice activity if the level clears the adaptive
+                // gate, or VAD hears speech just above the noise floor.
+                // Music holds notes and rests VAD does not see as voice, so
+                // only the level counts. A configured RMS threshold counts
+                // as sound whatever the gate says
+                let vad_threshold = 0.01; // VAD ratio threshold (1%)
+                let level_db = silence::level_db(&samples);
+                let gate = silence_gate.update(
+                    level_db,
+                    mode == RecordingMode::Speech && chunk_metrics.vad_ratio > vad_threshold,
+                    samples.len() as f64 / samples_per_second as f64,
+                );
+                let has_voice_activity =
+                    gate.active || rms_threshold_db.is_some_and(|threshold| level_db > threshold);
+
+                if has_voice_activity {
+                    // Voice detected - reset silence timer
+                    silence_start_samples = None;
+                } else {
+                    // No voice detected - track silence duration
+                    if silence_start_samples.is_none() {
+                        // Start tracking silence from this chunk
+                        silence_start_samples =
+                            Some(total_samples_processed - samples.len() as u64);
+                    }
+                }
+
+                // Check if we should stop due to silence
+                let mut stop_reason = None;
+                if let Some(silence_start) = silence_start_samples {
+                    let silence_duration_samples = total_samples_processed - silence_start;
+                    let silence_duration_secs =
+                        silence_duration_samples as f64 / samples_per_second as f64;
+
+                    if silence_stop_secs.is_some_and(|stop_secs| silence_duration_secs >= stop_secs)
+                    {
+                        stop_reason =
+                            Some(format!("Silence detected for {silence_duration_secs:.1}s"));
+                    }
+                }
+
+                // Check duration based on actual audio processed (not wall clock time)
+                if stop_reason.is_none() {
+                    if let Some(dur) = duration {
+                        if actual_duration >= dur {
+                            stop_reason = Some(format!(
+                                "Duration reached: {actual_duration:.2?} (actual audio duration)"
+                            ));
+                        }
+                    }
+                }
+
+                // Update the live display with the level and silence so far
+                live.push_audio(&samples);
+                status.elapsed_secs = actual_duration.as_secs_f64();
+                status.paused_secs = None;
+                status.silence_secs = silence_start_samples.map(|silence_start| {
+                    (total_samples_processed - silence_start) as f64 / samples_per_second as f64
+                });
+                status.snr_db = chunk_metrics.snr_db;
+                status.vad_ratio = chunk_metrics.vad_ratio;
+                status.gate = format!(
+                    "Gate {:.0} dB vs {:.0} dB (floor {:.0} dB), {}",
+                    gate.level_db,
+                    gate.threshold_db,
+                    gate.floor_db,
+                    gate.reason.as_str()
+                );
+                live.draw(&status, karaoke.as_ref());
+
+                // Stop recording if conditions are met
+                if let Some(reason) = stop_reason {
+                    live.finish();
+                    drop(raw_mode.take());
+                    println!("{reason}");
+                    break;
+                }
+            }
+            Ok(None) => {
+                live.finish();
+                drop(raw_mode.take());
+                println!("Channel closed");
+                break;
+            }
+            Err(_) => {
+                // Timeout - just continue the loop without checking duration
+                // This ensures we only stop based on actual audio data processed
+                let Some(gap) = watchdog.stalled() else {
+                    continue;
+                };
+
+                // The audio stack stopped delivering: reopen the stream, a
+                // few times at most
+                let at_secs = total_samples_processed as f64 / samples_per_second as f64;
+                if let Some(stalled) = stream.take() {
+                    spill_stats.merge(&stalled.spill_stats());
+                }
+                let reopened = if watchdog.can_rebuild() {
+                    open_capture(&backend, config).and_then(|(name, rate, stream, rx)| {
+                        // Another device would splice a different
+                        // microphone into the middle of the take
+                        if name != device_name {
+                            return Err(anyhow::anyhow!("the input switched to {name}"));
+                        }
+                        Ok((rate, stream, rx))
+                    })
+                } else {
+                    Err(anyhow::anyhow!(
+                        "it stalled {} times in this take",
+                        watchdog.incidents().len()
+                    ))
+                };
+                match reopened {
+                    Ok((reopened_rate, reopened_stream, reopened_rx)) => {
+                        if reopened_rate != capture_rate {
+                            info!(
+                                "Reopened stream runs at {} Hz instead of {} Hz, resampling from it",
+                                reopened_rate, capture_rate
+                            );
+                            resampler = cowcow_core::resample::Resampler::new(
+                                reopened_rate,
+                                config.audio.sample_rate,
+                                config.audio.channels,
+                            )?;
+                            capture_rate = reopened_rate;
+                        }
+                        clock.follow(reopened_stream.stamps());
+                        stream = Some(reopened_stream);
+                        rx = reopened_rx;
+                        watchdog.record(at_secs, gap, true);
+                        live.message(&format!(
+                            "⚠️  No audio from {} for {:.0}s; reopened the input stream",
+                            device_name,
+                            gap.as_secs_f64()
+                        ));
+                    }
+                    Err(e) => {
+                        watchdog.record(at_secs, gap, false);
+                        live.finish();
+                        drop(raw_mode.take());
+                        stalled_out = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(stream) = stream.take() {
+        spill_stats.merge(&stream.spill_stats());
+    }
+    drop(push_to_talk);
+    if spill_stats != SpillStats::default() {
+        info!(
+            "Capture buffer: peak {:.1}s in memory, {:.1}s spilled to disk (peak {:.1}s), {:.1}s dropped",
+            spill_stats.peak_memory_secs,
+            spill_stats.spilled_secs,
+            spill_stats.peak_disk_secs,
+            spill_stats.dropped_secs
+        );
+    }
+
+    if let Some(e) = stalled_out {
+        println!(
+            "❌ {} stopped delivering audio and could not be reopened ({:#})",
+            device_name, e
+        );
+        println!(
+            "   Check that it is plugged in and not in use by another app, then run `cowcow devices`"
+        );
+        if total_samples_processed == 0 {
+            drop(writer);
+            drop(raw_writer);
+            storage::remove_recording_files(&wav_path)?;
+            anyhow::bail!("Nothing was recorded: the audio device stalled");
+        }
+        println!(
+            "   Keeping the {:.1}s recorded before it stalled",
+            total_samples_processed as f64 / samples_per_second as f64
+        );
+    }
+
+    // Stopped before speech was heard or Space held
+    if status.standby.is_some() {
+        drop(writer);
+        drop(raw_writer);
+        storage::remove_recording_files(&wav_path)?;
+        println!("Nothing was recorded");
+        return Ok(TakeDecision::Discard);
+    }
+
+    if let Some(since) = paused_since {
+        paused += since.elapsed();
+    }
+
+    // Write the resampler tail so the file keeps the full duration
+    let tail = resampler.flush()?;
+    let filtered_tail = match filter.as_mut() {
+        Some(filter) => {
+            let mut filtered = filter.process(&tail);
+            filtered.extend(filter.flush());
+            filtered
+        }
+        None => tail.clone(),
+    };
+    for &sample in &filtered_tail {
+        wav::write_sample(&mut writer, sample)?;
+    }
+    if let Some(mut raw_writer) = raw_writer {
+        for &sample in &tail {
+            wav::write_sample(&mut raw_writer, sample)?;
+        }
+        raw_writer.finalize()?;
+    }
+    pending.extend(tail);
+    if !pending.is_empty() {
+        timeline.push(processor.process_chunk(&pending));
+    }
+
+    writer.finalize()?;
+    println!("Recording complete!");
+
+    // Measured before normalization so QC sees the level as performed
+    let loudness_lufs = match mode {
+        RecordingMode::Speech => None,
+        RecordingMode::Music => Some(mode::measure_loudness(&wav_path)?),
+    };
+
+    // Cut the silence around the speech, keeping the timeline in step; quiet
+    // intros and fade-outs of music are kept
+    let mut auto_trim = None;
+    if config.audio.auto_trim && mode == RecordingMode::Speech {
+        let mut trimmed = timeline.clone();
+        if let Some((start_secs, end_secs)) = trimmed
+            .trim_silence(TRIM_PADDING_SECS)
+            .filter(|&(start_secs, end_secs)| start_secs > 0.0 || end_secs > 0.0)
+        {
+            match cowcow_core::trim::trim_wav_file(&wav_path, start_secs, end_secs) {
+                Ok(()) => {
+                    println!(
+                        "✂️  Trimmed {start_secs:.1}s of leading and {end_secs:.1}s of trailing silence"
+                    );
+                    timeline = trimmed;
+                    auto_trim = Some((start_secs, end_secs));
+                }
+                Err(e) => warn!("Failed to trim silence: {}", e),
+            }
+        }
+    }
+
+    // Catch coughs and false starts before they reach the queue, and offer
+    // to split takes that ran on; music is left to run as long as it needs
+    let mut split = false;
+    if mode == RecordingMode::Speech {
+        let duration_secs = timeline.summary().duration_secs as f64;
+        let min_secs = config.record.min_duration_secs;
+        let max_secs = config.record.max_duration_secs;
+        let interactive = std::io::stdin().is_terminal();
+        if min_secs > 0.0 && duration_secs < min_secs {
+            println!(
+                "⏱️  That take was only {duration_secs:.1}s, under the {min_secs:.1}s minimum"
+            );
+            let answer = if interactive {
+                ask(
+                    "Press Enter to record again, K to keep it, D to discard",
+                    "",
+                )?
+            } else {
+                "d".to_string()
+            };
+            match answer.to_ascii_lowercase().as_str() {
+                "k" | "keep" => {}
+                "d" => {
+                    storage::remove_recording_files(&wav_path)?;
+                    println!("🗑️  Take discarded");
+                    return Ok(TakeDecision::Discard);
+                }
+                _ => {
+                    storage::remove_recording_files(&wav_path)?;
+                    return Ok(TakeDecision::Rerecord);
+                }
+            }
+        } else if max_secs > 0.0 && duration_secs > max_secs {
+            println!(
+                "⏱️  Thank you for keeping going! That take ran {duration_secs:.0}s, past the \
+                 {max_secs:.0}s we aim for; shorter clips are easier to check and transcribe."
+            );
+            // A prompted take is one reading of one prompt; its parts would
+            // each claim the whole prompt text
+            let prompted = prompt.is_some() || prompt_fields.is_some() || prompt_id.is_some();
+            if interactive && !prompted && !timeline.is_summary_only() {
+                let answer = ask(
+                    "Press Enter to split it at its pauses, K to keep it whole, R to re-record",
+                    "",
+                )?;
+                match answer.to_ascii_lowercase().as_str() {
+                    "k" | "keep" => {}
+                    "r" => {
+                        storage::remove_recording_files(&wav_path)?;
+                        return Ok(TakeDecision::Rerecord);
+                    }
+                    _ => split = true,
+                }
+            }
+        }
+    }
+
+    // Even out device gain; QC below describes the audio as captured
+    if let Some(target) = config.normalize_target() {
+        match cowcow_core::normalize::normalize_wav_file(&wav_path, target) {
+            Ok(gain_db) => info!("Normalized to {} ({:+.1} dB)", target, gain_db),
+            Err(e) => warn!("Failed to normalize recording: {}", e),
+        }
+    }
+
+    let parts = if split {
+        let max_secs = config.record.max_duration_secs as f32;
+        match split_take(recording_id, &wav_path, &timeline, max_secs) {
+            Ok(parts) => {
+                println!("✂️  Split into {} parts at its pauses", parts.len());
+                parts
+            }
+            Err(e) => {
+                warn!("Failed to split the take, keeping it whole: {:#}", e);
+                vec![(recording_id, wav_path, timeline)]
+            }
+        }
+    } else {
+        vec![(recording_id, wav_path, timeline)]
+    };
+    let capture = clock.finish();
+    let stalls = watchdog.incidents();
+
+    // Split takes that ran on at their pauses; the first part keeps the
+    // take's id and sidecars
+    let part_count = parts.len();
+    for (index, (recording_id, wav_path, timeline)) in parts.into_iter().enumerate() {
+        let first = index == 0;
+        if part_count > 1 {
+            println!("\n📎 Part {} of {}", index + 1, part_count);
+        }
+
+        // Compress once every in-place edit of the WAV is done
+        let wav_path = match config.audio.format.as_str() {
+            "flac" => {
+                let flac_path = wav_path.with_extension(flac::FLAC_EXTENSION);
+                flac::encode_wav_file(&wav_path, &flac_path)?;
+                std::fs::remove_file(&wav_path)
+                    .with_context(|| format!("Failed to remove {}", wav_path.display()))?;
+                flac_path
+            }
+            "opus" => {
+                let opus_path = wav_path.with_extension(opus::OPUS_EXTENSION);
+                match opus::encode_wav_file(&wav_path, &opus_path, config.audio.opus_bitrate) {
+                    Ok(()) => {
+                        std::fs::remove_file(&wav_path)
+                            .with_context(|| format!("Failed to remove {}", wav_path.display()))?;
+                        opus_path
+                    }
+                    // Never lose a take to a missing codec; keep the WAV instead
+                    Err(e) => {
+                        warn!("Failed to encode Opus, keeping WAV: {:#}", e);
+                        let _ = std::fs::remove_file(&opus_path);
+                        wav_path
+                    }
+                }
+            }
+            _ => wav_path,
+        };
+
+        // Keep the per-frame metrics next to the recording for reviewers
+        if !timeline.is_summary_only() {
+            if let Err(e) = timeline.save(&QcTimeline::sidecar_path(&wav_path)) {
+                warn!("Failed to save QC timeline: {}", e);
+            }
+        }
+
+        // Keep the capture timing for aligning with video or sensor logs
+        let capture = capture.as_ref().filter(|_| first);
+        if let Some(capture) = capture {
+            if let Err(e) = capture.save(&CaptureAlignment::sidecar_path(&wav_path)) {
+                warn!("Failed to save capture timing: {}", e);
+            }
+        }
+
+        // Calculate average metrics
+        let avg_metrics = timeline.summary();
+
+        // Display quality metrics
+        println!("\nRecording Quality Metrics:");
+        println!("  SNR: {:.1} dB", avg_metrics.snr_db);
+        println!("  Clipping: {:.1}%", avg_metrics.clipping_pct);
+        println!("  Voice Activity: {:.1}%", avg_metrics.vad_ratio);
+        println!(
+            "  Speech: {:.1}s of {:.1}s (leading silence {:.1}s, trailing {:.1}s)",
+            avg_metrics.speech_secs,
+            avg_metrics.duration_secs,
+            avg_metrics.leading_silence_secs,
+            avg_metrics.trailing_silence_secs
+        );
+        println!("  DC Offset: {:+.3}", avg_metrics.dc_offset);
+        println!("  Rumble (<50 Hz): {:.1} dB", avg_metrics.rumble_db);
+        println!("  Mains Hum: {:.1} dB", avg_metrics.hum_db);
+        if let Some(noise_floor_db) = session.noise_floor_db {
+            println!("  Noise Floor (session room tone): {noise_floor_db:.1} dBFS");
+        }
+        if avg_metrics.reverb_rt60_secs > 0.0 {
+            println!("  Reverb (RT60): {:.2}s", avg_metrics.reverb_rt60_secs);
+        }
+        if avg_metrics.voiced_ratio > 0.0 {
+            println!(
+                "  Pitch: {:.0} Hz mean ({:.0}-{:.0} Hz), voiced {:.1}%",
+                avg_metrics.f0_mean_hz,
+                avg_metrics.f0_min_hz,
+                avg_metrics.f0_max_hz,
+                avg_metrics.voiced_ratio
+            );
+        }
+        if config.audio.channels > 1 {
+            for (index, channel) in processor.channel_metrics().iter().enumerate() {
+                println!(
+                    "  Channel {}: {:.1} dBFS RMS, peak {:.2}, clipping {:.1}%, DC {:+.3}",
+                    index + 1,
+                    channel.rms_db,
+                    channel.peak,
+                    channel.clipping_pct,
+                    channel.dc_offset
+                );
+            }
+        }
+        println!("  Dropouts: {}", avg_metrics.dropout_count);
+        println!("  Glitches: {:.3}%", avg_metrics.glitch_pct);
+        if first && !paused.is_zero() {
+            println!("  Paused: {:.1}s (not recorded)", paused.as_secs_f64());
+        }
+        if first && spill_stats.spilled_secs > 0.0 {
+            println!(
+                "  Capture Backlog: {:.1}s spilled to disk (up to {:.1}s behind){}",
+                spill_stats.spilled_secs,
+                spill_stats.peak_memory_secs + spill_stats.peak_disk_secs,
+                if spill_stats.dropped_secs > 0.0 {
+                    ""
+                } else {
+                    ", none lost"
+                }
+            );
+        }
+        if first && spill_stats.dropped_secs > 0.0 {
+            println!(
+                "⚠️  {:.1}s of audio was lost with the capture buffer full; raise \
+                 record.capture_buffer_secs or enable record.spill_to_disk",
+                spill_stats.dropped_secs
+            );
+        }
+        if avg_metrics.dropout_count > 0 {
+            println!(
+                "⚠️  Audio buffers were dropped during capture, listen back before uploading."
+            );
+        }
+        let stalls = if first { stalls } else { &[][..] };
+        if !stalls.is_empty() {
+            println!(
+                "⚠️  Capture stalled {} time(s) (first at {:.1}s); listen back before uploading.",
+                stalls.len(),
+                stalls[0].at_secs
+            );
+        }
+
+        // Reading speed relative to the prompt, stored alongside the audio
+        // metrics; sung lyrics have no reading speed
+        let prompt_analysis = prompt
+            .as_deref()
+            .filter(|_| mode == RecordingMode::Speech && prompt_fields.is_none() && part_count == 1)
+            .and_then(|text| PromptAnalysis::new(text, &avg_metrics));
+        let metrics_json = match (&prompt_analysis, loudness_lufs) {
+            (_, Some(loudness_lufs)) => {
+                println!("  Loudness: {loudness_lufs:.1} LUFS");
+                mode::music_metrics(&avg_metrics, loudness_lufs)?
+            }
+            (Some(analysis), None) => {
+                println!(
+                    "  Speaking Rate: {:.1} syllables/s ({} syllables)",
+                    analysis.speaking_rate, analysis.prompt_syllables
+                );
+                analysis.with_metrics(&avg_metrics)
+            }
+            (None, None) => serde_json::to_value(&avg_metrics)?,
+        };
+
+        let qc_report = config
+            .qc_policy_for(&metrics_json)
+            .evaluate_json(&metrics_json);
+        if qc_report.passed {
+            println!("✅ QC passed ({} rules)", qc_report.results.len());
+        } else {
+            println!("❌ QC failed:");
+            for failure in qc_report.failures() {
+                println!("  {}", failure.describe());
+            }
+        }
+
+        // Nothing is saved until the contributor keeps the take
+        if (review || config.record.review_takes) && std::io::stdin().is_terminal() {
+            let decision = review_take(&wav_path).await?;
+            if decision != TakeDecision::Accept {
+                storage::remove_recording_files(&wav_path)?;
+                // Half a take cannot be re-recorded on its own
+                if part_count > 1 {
+                    println!("🗑️  Part discarded");
+                    continue;
+                }
+                if decision == TakeDecision::Discard {
+                    println!("🗑️  Take discarded");
+                }
+                return Ok(decision);
+            }
+        }
+
+        // Save to database
+        sqlx::query(
+            r#"
+            INSERT INTO recordings
+                (id, lang, prompt, qc_metrics, qc_report, created_at, wav_path, speaker_id, session_id, campaign_id,
+                 auto_trim_start_secs, auto_trim_end_secs, device, capture_started_at_ms, tags, paused_secs,
+                 prompt_id, stall_incidents, prompt_fields, translation_pair, exemplar_played)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(recording_id.to_string())
+        .bind(lang)
+        .bind(&prompt)
+        .bind(metrics_json.to_string())
+        .bind(serde_json::to_string(&qc_report)?)
+        .bind(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64,
+        )
+        .bind(wav_path.to_string_lossy())
+        .bind(&speaker)
+        .bind(&session.id)
+        .bind(campaign.as_ref().map(|c| &c.id))
+        .bind(auto_trim.filter(|_| first).map(|(start_secs, _)| start_secs))
+        .bind(
+            auto_trim
+                .filter(|_| index + 1 == part_count)
+                .map(|(_, end_secs)| end_secs),
+        )
+        .bind(&device_name)
+        .bind(capture.as_ref().map(|capture| capture.capture_started_at_ms))
+        .bind(&tags)
+        .bind(if first { paused.as_secs_f64() } else { 0.0 })
+        .bind(&prompt_id)
+        .bind(if stalls.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(stalls)?)
+        })
+        .bind(prompt_fields.as_ref().map(serde_json::to_string).transpose()?)
+        // Half a take has no translation of its own
+        .bind(translation_pair.as_ref().filter(|_| part_count == 1))
+        .bind(exemplar_played)
+        .execute(db)
+        .await?;
+        if let Some(prompt_id) = prompt_id.as_ref().filter(|_| first) {
+            prompts::refresh_count(db, prompt_id).await?;
+        }
+
+        // Add to upload queue
+        sqlx::query(
+            r#"
+            INSERT INTO upload_queue (recording_id, attempts, last_attempt)
+            VALUES (?, 0, 0)
+            "#,
+        )
+        .bind(recording_id.to_string())
+        .execute(db)
+        .await?;
+
+        // Fingerprint now so `cowcow dedupe` only has to compare; low-memory
+        // devices leave it to `cowcow dedupe`
+        if !low_memory {
+            dedupe::store_fingerprint(db, &recording_id.to_string(), &wav_path).await?;
+        }
+
+        info!("Recording saved: {}", wav_path.display());
+
+        // Another read of the same prompt in this session is a take of it
+        if part_count == 1 {
+            if let Some(slot) = takes::link_take(db, &recording_id.to_string()).await? {
+                let number = slot
+                    .number(&recording_id.to_string())
+                    .unwrap_or(slot.takes.len());
+                match slot.selected() {
+                    Some(selected) if selected.id == recording_id.to_string() => println!(
+                        "🎬 Take {} of this prompt, selected as the best so far",
+                        number
+                    ),
+                    Some(selected) => println!(
+                        "🎬 Take {} of this prompt; take {} stays selected (`cowcow takes select {}` to use this one)",
+                        number,
+                        slot.number(&selected.id).unwrap_or(1),
+                        recording_id
+                    ),
+                    None => println!("🎬 Take {number} of this prompt"),
+                }
+            }
+        }
+
+        // Compare against this speaker's other recordings on this device
+        if let Some(metrics) = outliers::flag_take_outliers(db, &recording_id.to_string()).await? {
+            println!(
+                "⚠️  Unusual for this speaker and device ({}); `cowcow review` lists it first",
+                metrics.replace(',', ", ")
+            );
+        }
+    }
+
+    // Auto-upload if configured
+    if config.storage.auto_upload {
+        println!("Auto-uploading recording...");
+        upload_recordings(false, false, db, config).await?;
+    }
+
+    Ok(TakeDecision::Accept)
+}
+
+/// Cut a take into parts of at most `max_secs` at its pauses
+///
+/// The first part replaces the take under its id, so the capture sidecars
+/// stay with it; the others get new ids next to it.
+fn split_take(
+    recording_id: Uuid,
+    wav_path: &Path,
+    timeline: &QcTimeline,
+    max_secs: f32,
+) -> Result<Vec<(Uuid, PathBuf, QcTimeline)>> {
+    let timelines = timeline.split(max_secs);
+    let part_secs: Vec<f32> = timelines
+        .iter()
+        .map(|part| part.summary().duration_secs)
+        .collect();
+    let mut ids = vec![recording_id];
+    let mut part_paths = vec![wav_path.with_extension("wav.part")];
+    for _ in 1..timelines.len() {
+        let id = Uuid::new_v4();
+        part_paths.push(wav_path.with_file_name(format!("{id}.wav")));
+        ids.push(id);
+    }
+    cowcow_core::trim::split_wav_file(wav_path, &part_secs, &part_paths)?;
+    std::fs::rename(&part_paths[0], wav_path)
+        .with_context(|| format!("Failed to replace {}", wav_path.display()))?;
+    part_paths[0] = wav_path.to_path_buf();
+
+    Ok(ids
+        .into_iter()
+        .zip(part_paths)
+        .zip(timelines)
+        .map(|((id, path), timeline)| (id, path, timeline))
+        .collect())
+}
+
+/// Record each prompt of a script in turn, one accepted take per prompt,
+/// then summarize the session
+///
+/// Before each prompt the contributor can skip it, go back to record the
+/// previous one again, or finish early. Consent is asked for and the device
+/// calibrated once, before the first prompt.
+async fn record_script(
+    prompts: &[script::ScriptPrompt],
+    mut options: RecordOptions,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    if (options.consent || config.record.play_consent)
+        && !play_consent_explanation(db, config, &options.lang, options.speaker.as_deref()).await?
+    {
+        println!("🚫 Consent declined; nothing was recorded");
+        return Ok(());
+    }
+    let mut config = config.clone();
+    config.record.play_consent = false;
+    options.consent = false;
+
+    let mut summary = script::ScriptSummary::default();
+    let mut index = 0;
+    while index < prompts.len() {
+        let prompt = &prompts[index];
+        println!();
+        match &prompt.id {
+            Some(id) => println!("📜 Prompt {}/{} ({})", index + 1, prompts.len(), id),
+            None => println!("📜 Prompt {}/{}", index + 1, prompts.len()),
+        }
+        println!("   {}", prompt.text);
+        if let Some(answer_type) = prompt.fields.as_ref().and_then(|f| f.answer_type.as_ref()) {
+            println!("   Expected answer: {answer_type}");
+        }
+        let choice = match ask_hands_free(
+            "Enter to record, S to skip, B to repeat the previous prompt, Q to finish",
+        )
+        .await?
+        {
+            HandsFree::Key(key) => key,
+            HandsFree::Action(ControllerAction::Record) => String::new(),
+            HandsFree::Action(ControllerAction::Next) => "s".to_string(),
+            HandsFree::Action(ControllerAction::Pause) => continue,
+        };
+        match choice.as_str() {
+            "s" => {
+                summary.skipped.push(index + 1);
+                index += 1;
+                continue;
+            }
+            "b" => {
+                index = index.saturating_sub(1);
+                continue;
+            }
+            "q" => break,
+            _ => {}
+        }
+
+        let take = RecordOptions {
+            prompt: Some(prompt.text.clone()),
+            prompt_id: prompt.id.clone(),
+            prompt_fields: prompt.fields.clone(),
+            hint: prompt.hint.clone(),
+            exemplar: prompt.exemplar.clone(),
+            translation: prompt.translation.clone(),
+            ..options.clone()
+        };
+        if record_take(take, db, &config).await? == TakeDecision::Accept {
+            summary.recorded += 1;
+            summary.skipped.retain(|&skipped| skipped != index + 1);
+        } else {
+            summary.discarded += 1;
+        }
+        options.calibrate = false;
+        index += 1;
+    }
+    summary.remaining = prompts.len() - index;
+
+    println!();
+    println!(
+        "📜 Script session: {} recorded, {} discarded, {} skipped",
+        summary.recorded,
+        summary.discarded,
+        summary.skipped.len()
+    );
+    if !summary.skipped.is_empty() {
+        let skipped: Vec<String> = summary.skipped.iter().map(usize::to_string).collect();
+        println!("   Skipped prompts: {}", skipped.join(", "));
+    }
+    if summary.remaining > 0 {
+        println!(
+            "   Finished early with {} prompt(s) not reached",
+            summary.remaining
+        );
+    }
+    Ok(())
+}
+
+/// Record a take until it is kept or discarded, then, with
+/// `options.translate_to`, its translation, linked to it as a translation
+/// pair; returns what became of the first take
+async fn record_take(
+    mut options: RecordOptions,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<TakeDecision> {
+    let target_lang = options.translate_to.take();
+    let translation = options.translation.take();
+    if target_lang.is_some() {
+        options.translation_pair = Some(Uuid::new_v4().to_string());
+    }
+    let decision = loop {
+        match record_audio(options.clone(), db, config).await? {
+            TakeDecision::Rerecord => options.retake = true,
+            decision => break decision,
+        }
+    };
+    let (Some(target_lang), Some(pair_id)) = (target_lang, options.translation_pair.clone()) else {
+        return Ok(decision);
+    };
+    if decision != TakeDecision::Accept {
+        return Ok(decision);
+    }
+    let Some(source) = translations::source_of(db, &pair_id).await? else {
+        println!("⚠️  The take was split into parts; record their translations separately");
+        return Ok(decision);
+    };
+
+    println!();
+    println!("🔁 Now say it in {target_lang}");
+    if let Some(text) = &source.prompt {
+        println!("   {}: {}", source.lang, text);
+    }
+    // Consent and calibration carry over from the first take; the campaign
+    // is the translation language's own
+    let mut translated = RecordOptions {
+        lang: target_lang,
+        prompt: translation,
+        prompt_id: None,
+        prompt_fields: None,
+        hint: None,
+        exemplar: None,
+        campaign: None,
+        retake: true,
+        ..options
+    };
+    loop {
+        match record_audio(translated.clone(), db, config).await? {
+            TakeDecision::Accept => {
+                if translations::is_paired(db, &pair_id).await? {
+                    println!("🔗 Linked with {} as a translation pair", source.id);
+                } else {
+                    translations::unpair(db, &pair_id).await?;
+                    println!(
+                        "⚠️  The translation was split into parts; {} is kept without one",
+                        source.id
+                    );
+                }
+                break;
+            }
+            TakeDecision::Rerecord => translated.retake = true,
+            TakeDecision::Discard => {
+                translations::unpair(db, &pair_id).await?;
+                println!("   {} is kept without a translation", source.id);
+                break;
+            }
+        }
+    }
+    Ok(decision)
+}
+
+/// Wait for the contributor to start a take, playing the prompt's exemplar
+/// as often as they ask; returns whether it was played, when there was one
+async fn wait_for_start(
+    start_mode: StartMode,
+    exemplar: Option<&playback::Clip>,
+) -> Result<Option<bool>> {
+    let Some(exemplar) = exemplar else {
+        if controller::is_active() {
+            loop {
+                match ask_hands_free("Press Enter or the record button to start recording").await? {
+                    HandsFree::Action(ControllerAction::Record) => return Ok(None),
+                    HandsFree::Key(key) if key.is_empty() => return Ok(None),
+                    _ => {}
+                }
+            }
+        }
+        println!("Press Enter to start recording...");
+        std::io::stdin().read_line(&mut String::new())?;
+        return Ok(None);
+    };
+    let question = if start_mode == StartMode::Enter {
+        "Press Enter to start recording, E to hear a native speaker read it"
+    } else {
+        "Press Enter when ready, E to hear a native speaker read it"
+    };
+    let mut played = false;
+    loop {
+        let answer = match ask_hands_free(question).await? {
+            HandsFree::Key(key) => key,
+            HandsFree::Action(ControllerAction::Record) => String::new(),
+            HandsFree::Action(_) => continue,
+        };
+        match answer.as_str() {
+            "" => return Ok(Some(played)),
+            "e" => {
+                println!("🔊 Playing the exemplar ({:.1}s)", exemplar.duration_secs());
+                let playing = exemplar.clone();
+                match tokio::task::spawn_blocking(move || playback::play(&playing)).await? {
+                    Ok(()) => played = true,
+                    Err(e) => warn!("Failed to play the exemplar: {:#}", e),
+                }
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Play a finished take back until the contributor keeps, re-records or
+/// discards it
+async fn review_take(path: &Path) -> Result<TakeDecision> {
+    let clip = playback::Clip::load(path)?;
+    loop {
+        println!("🔊 Playing back the take ({:.1}s)", clip.duration_secs());
+        let playing = clip.clone();
+        tokio::task::spawn_blocking(move || playback::play(&playing)).await??;
+
+        loop {
+            let answer = ask(
+                "Press Enter to keep the take, P to play it again, R to re-record, D to discard",
+                "",
+            )?;
+            match answer.to_ascii_lowercase().as_str() {
+                "" | "k" | "y" | "yes" => return Ok(TakeDecision::Accept),
+                "p" => break,
+                "r" => return Ok(TakeDecision::Rerecord),
+                "d" if confirm("Discard this take?")? => return Ok(TakeDecision::Discard),
+                _ => continue,
+            }
+        }
+    }
+}
+
+async fn upload_recordings(
+    force: bool,
+    requalify: bool,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    // A running daemon owns uploading; hand the work to it
+    if daemon::delegate_upload(config, force, requalify).await? {
+        return Ok(());
+    }
+
+    let auth_client = AuthClient::new(config.clone());
+    let upload_client = UploadClient::new(config.clone());
+
+    // Check authentication
+    let credentials = match auth_client.check_auth().await {
+        Ok(creds) => creds,
+        Err(_) => {
+            println!("Authentication required. Please login first.");
+            println!("Run: cowcow auth login");
+            return Ok(());
+        }
+    };
+
+    credentials.require_scope(Scope::Upload, "Uploading")?;
+
+    // Upload pending recordings
+    upload_client
+        .upload_pending_recordings(db, &credentials, force, requalify, &cancel::on_ctrl_c())
+        .await?;
+
+    // Refresh campaign progress while online
+    match auth_client.get_campaigns().await {
+        Ok(list) => campaigns::store_campaigns(db, &list).await?,
+        Err(e) => warn!("Failed to sync campaigns: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Days shown in the activity strip of `cowcow progress`
+const PROGRESS_DAYS: i64 = 14;
+
+async fn show_progress(config: &Config, db: &SqlitePool) -> Result<()> {
+    let streak = streak::load(db).await?;
+    let today = chrono::Local::now().date_naive();
+    let Some(last) = streak.last_recorded else {
+        println!("📭 No recordings yet; run `cowcow record` to start your streak");
+        return Ok(());
+    };
+
+    println!("🔥 Current streak: {} day(s)", streak.current_days);
+    println!("🏆 Longest streak: {} day(s)", streak.longest_days);
+    let ago = match streak.days_since_last(today).unwrap_or(0) {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        days => format!("{days} days ago"),
+    };
+    println!("📅 Last recorded: {last} ({ago})");
+    println!(
+        "   Recorded on {} of the last 7 days",
+        streak.days_this_week
+    );
+
+    let strip: String = (0..PROGRESS_DAYS)
+        .rev()
+        .map(|back| today - chrono::Duration::days(back))
+        .map(|day| if streak.recorded_on(day) { '■' } else { '·' })
+        .collect();
+    println!("   Last {PROGRESS_DAYS} days: {strip} (today on the right)");
+
+    if let Some(reminder) = streak.reminder(today, config.reminders.after_days) {
+        println!("⏰ {reminder}");
+    } else if streak.current_days > 0 && last < today {
+        println!("💡 Record today to keep your streak going");
+    }
+    Ok(())
+}
+
+async fn show_stats(db: &SqlitePool, filter: &sessions::SessionFilter) -> Result<()> {
+    let (condition, params) = filter.condition("session_id");
+    let query = format!(
+        r#"
+        SELECT 
+            COUNT(*) as total_recordings,
+            COUNT(CASE WHEN uploaded_at IS NOT NULL THEN 1 END) as uploaded_recordings,
+            COUNT(CASE WHEN uploaded_at IS NULL AND archived = 0 AND take_selected = 1 THEN 1 END)
+                as pending_recordings,
+            COUNT(CASE WHEN take_selected = 0 AND archived = 0 THEN 1 END) as alternate_takes,
+            COUNT(CASE WHEN archived = 1 THEN 1 END) as archived_recordings,
+            COUNT(CASE WHEN pinned = 1 THEN 1 END) as pinned_recordings
+        FROM recordings
+        WHERE 1=1{condition}
+        "#
+    );
+    let mut stats = sqlx::query(&query);
+    for param in &params {
+        stats = stats.bind(param);
+    }
+    let stats = stats.fetch_one(db).await?;
+
+    println!("📊 Recording Statistics");
+    println!(
+        "  Total recordings: {}",
+        stats.get::<i64, _>("total_recordings")
+    );
+    println!("  Uploaded: {}", stats.get::<i64, _>("uploaded_recordings"));
+    println!("  Pending: {}", stats.get::<i64, _>("pending_recordings"));
+    let alternate_takes = stats.get::<i64, _>("alternate_takes");
+    if alternate_takes > 0 {
+        println!("  Alternate takes (not uploaded): {alternate_takes}");
+    }
+    println!("  Archived: {}", stats.get::<i64, _>("archived_recordings"));
+    println!("  Pinned: {}", stats.get::<i64, _>("pinned_recordings"));
+
+    // Recordings per kind of place, to tell noisy sessions from quiet ones
+    let query = format!(
+        r#"
+        SELECT COALESCE(s.environment, 'unspecified'), COUNT(*),
+            AVG(json_extract(r.qc_metrics, '$.snr_db'))
+        FROM recordings r LEFT JOIN sessions s ON s.id = r.session_id
+        WHERE 1=1{}
+        GROUP BY 1 ORDER BY 1
+        "#,
+        filter.condition("r.session_id").0
+    );
+    let mut environments = sqlx::query_as::<_, (String, i64, Option<f64>)>(&query);
+    for param in &params {
+        environments = environments.bind(param);
+    }
+    let environments = environments.fetch_all(db).await?;
+    if environments.len() > 1 || environments.iter().any(|(env, ..)| env != "unspecified") {
+        println!("  By environment:");
+        for (environment, count, snr_db) in environments {
+            match snr_db {
+                Some(snr_db) => println!("    {environment}: {count} (mean SNR {snr_db:.1} dB)"),
+                None => println!("    {environment}: {count}"),
+            }
+        }
+    }
+
+    // QC reports stored at record time
+    let query = format!("SELECT qc_report FROM recordings WHERE 1=1{condition}");
+    let mut reports = sqlx::query_scalar::<_, Option<String>>(&query);
+    for param in &params {
+        reports = reports.bind(param);
+    }
+    let reports = reports.fetch_all(db).await?;
+    let (mut passed, mut failed, mut unchecked) = (0, 0, 0);
+    let mut rule_failures: std::collections::BTreeMap<String, usize> = Default::default();
+    for report in reports {
+        match report.and_then(|json| serde_json::from_str::<QcReport>(&json).ok()) {
+            Some(report) if report.passed => passed += 1,
+            Some(report) => {
+                failed += 1;
+                for failure in report.failures() {
+                    *rule_failures.entry(failure.rule.clone()).or_default() += 1;
+                }
+            }
+            None => unchecked += 1,
+        }
+    }
+    println!("  QC passed: {passed}");
+    println!("  QC failed: {failed}");
+    if unchecked > 0 {
+        println!("  QC not evaluated: {unchecked}");
+    }
+    for (rule, count) in rule_failures {
+        println!("    {rule}: {count} failure(s)");
+    }
+
+    // Why queued recordings were held back at the last upload
+    let query = format!(
+        "SELECT skip_reason, COUNT(*) FROM upload_queue WHERE skip_reason IS NOT NULL AND recording_id IN (SELECT id FROM recordings WHERE 1=1{condition}) GROUP BY skip_reason ORDER BY skip_reason",
+    );
+    let mut skips = sqlx::query_as::<_, (String, i64)>(&query);
+    for param in &params {
+        skips = skips.bind(param);
+    }
+    let skips = skips.fetch_all(db).await?;
+    if !skips.is_empty() {
+        println!("  Skipped at upload:");
+        for (reason, count) in skips {
+            println!("    {reason}: {count}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Open the input stream, forwarding captured buffers over a channel
+///
+/// Buffers the recording loop is not ready for wait in a capture buffer
+/// that spills to disk (see [`SpillQueue`]) rather than being dropped.
+fn open_input_stream(
+    device: &cpal::Device,
+    capture_rate: u32,
+    config: &Config,
+) -> Result<(InputStream, mpsc::Receiver<Vec<f32>>)> {
+    let config_audio = cpal::StreamConfig {
+        channels: config.audio.channels,
+        sample_rate: cpal::SampleRate(capture_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    // Create channels for audio processing
+    let buffer = if config.record.low_memory { 4 } else { 32 };
+    let (tx, rx) = mpsc::channel(buffer); // Smaller buffer for better flow control
+    let queue = SpillQueue::start(config.spill_config(capture_rate), tx);
+
+    // Start recording stream
+    let producer = std::sync::Arc::clone(&queue);
+    let stamps = alignment::CaptureStamps::new(capture_rate);
+    let stamper = std::sync::Arc::clone(&stamps);
+    let channels = config.audio.channels.max(1) as usize;
+    let stream = device.build_input_stream(
+        &config_audio,
+        move |data: &[f32], info: &cpal::InputCallbackInfo| {
+            stamper.stamp_callback(data.len() / channels, info);
+            producer.push(data)
+        },
+        move |err| {
+            error!("Audio stream error: {}", err);
+        },
+        None,
+    )?;
+
+    stream.play()?;
+    Ok((InputStream::device(stream, queue, stamps), rx))
+}
+
+/// Open the input of `backend`; returns its name, the rate it captures at
+/// and its stream
+fn open_capture(
+    backend: &AudioBackend,
+    config: &Config,
+) -> Result<(String, u32, InputStream, mpsc::Receiver<Vec<f32>>)> {
+    match backend {
+        AudioBackend::Device => {
+            let device = input_device(config)?;
+            // Capture at the configured rate if the device offers it,
+            // otherwise at the device's default rate and resample
+            let capture_rate = capture_sample_rate(&device, config);
+            let (stream, rx) = open_input_stream(&device, capture_rate, config)?;
+            Ok((device_name(&device), capture_rate, stream, rx))
+        }
+        AudioBackend::Synthetic(signal) => {
+            let (stream, capture_rate, rx) = backend::open_synthetic(
+                signal,
+                config.audio.sample_rate,
+                config.audio.channels,
+                |rate| config.spill_config(rate),
+            )?;
+            Ok((format!("synthetic {signal}"), capture_rate, stream, rx))
+        }
+    }
+}
+
+/// Input device set in `audio.input_device`, or the system default
+fn input_device(config: &Config) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    match &config.audio.input_device {
+        Some(selector) => devices::find_input_device(selector),
+        None => host
+            .default_input_device()
+            .context("No input device available"),
+    }
+}
+
+/// Measure the input device's noise floor and store it in the config
+async fn calibrate_device(config: &Config) -> Result<()> {
+    let device = input_device(config)?;
+    let name = device_name(&device);
+    let capture_rate = capture_sample_rate(&device, config);
+    let (_stream, mut rx) = open_input_stream(&device, capture_rate, config)?;
+
+    calibrate_noise_floor(&mut rx, capture_rate, &name, config).await?;
+    Ok(())
+}
+
+/// Sample ambient noise, save the measured floor for `device` and return it
+async fn calibrate_noise_floor(
+    rx: &mut mpsc::Receiver<Vec<f32>>,
+    capture_rate: u32,
+    device: &str,
+    config: &Config,
+) -> Result<f32> {
+    let noise_floor_db = measure_noise_floor(rx, capture_rate, device, config).await?;
+
+    // Save onto the config file as stored, not the command-line overrides
+    let mut stored = Config::load()?;
+    stored
+        .audio
+        .noise_floors
+        .insert(device.to_string(), noise_floor_db);
+    stored.save()?;
+    Ok(noise_floor_db)
+}
+
+/// Sample ambient noise and return the floor measured for `device`
+async fn measure_noise_floor(
+    rx: &mut mpsc::Receiver<Vec<f32>>,
+    capture_rate: u32,
+    device: &str,
+    config: &Config,
+) -> Result<f32> {
+    let secs = config.record.calibration_secs.max(1);
+    println!("🎚️  Calibrating {device}: sampling {secs}s of ambient noise, please stay quiet...");
+
+    let samples = capture_ambient(rx, capture_rate, secs, config).await?;
+    let noise_floor_db = room_tone_floor_db(&samples, config);
+
+    println!("✅ Noise floor: {noise_floor_db:.1} dBFS");
+    if noise_floor_db > NOISY_FLOOR_DB {
+        println!("⚠️  The room is noisy; recordings here are likely to fail the SNR check");
+    }
+    Ok(noise_floor_db)
+}
+
+/// Noise floors above this leave little headroom for speech SNR
+const NOISY_FLOOR_DB: f32 = -40.0;
+
+/// Capture `secs` of audio at the configured rate
+async fn capture_ambient(
+    rx: &mut mpsc::Receiver<Vec<f32>>,
+    capture_rate: u32,
+    secs: u32,
+    config: &Config,
+) -> Result<Vec<f32>> {
+    let mut resampler = cowcow_core::resample::Resampler::new(
+        capture_rate,
+        config.audio.sample_rate,
+        config.audio.channels,
+    )?;
+    let wanted = secs as usize * config.audio.sample_rate as usize * config.audio.channels as usize;
+
+    // Drop audio buffered before the capture started
+    while rx.try_recv().is_ok() {}
+
+    let mut samples = Vec::with_capacity(wanted);
+    while samples.len() < wanted {
+        let captured = rx
+            .recv()
+            .await
+            .context("Audio stream closed during ambient noise capture")?;
+        samples.extend(resampler.process(&captured)?);
+    }
+    samples.truncate(wanted);
+    Ok(samples)
+}
+
+/// Record a speaker's spoken consent from the input device into a
+/// temporary WAV in the data directory, returning its path
+async fn record_consent_clip(
+    speaker: &speakers::Speaker,
+    form_version: &str,
+    secs: u32,
+    config: &Config,
+) -> Result<PathBuf> {
+    let device = input_device(config)?;
+    let capture_rate = capture_sample_rate(&device, config);
+    let (_stream, mut rx) = open_input_stream(&device, capture_rate, config)?;
+
+    println!(
+        "🎙️  {}, please state that you agree to consent form {} ({}s)...",
+        speaker.name, form_version, secs
+    );
+    let samples = capture_ambient(&mut rx, capture_rate, secs, config).await?;
+
+    let path = config
+        .data_dir()
+        .join(format!("consent-{}.tmp.wav", Uuid::new_v4()));
+    let spec = config
+        .bit_depth()
+        .spec(config.audio.sample_rate, config.audio.channels);
+    let mut writer = hound::WavWriter::create(&path, spec)?;
+    for &sample in &samples {
+        wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+    Ok(path)
+}
+
+/// Noise floor of a room tone capture, measured on the channel average
+fn room_tone_floor_db(samples: &[f32], config: &Config) -> f32 {
+    let channels = config.audio.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    cowcow_core::dsp::noise_floor_db(&mono, config.audio.sample_rate)
+}
+
+/// Sample rate to open the input stream at
+///
+/// Prefers the configured rate; falls back to the device's default rate,
+/// which the recorder then resamples to the configured one.
+fn capture_sample_rate(device: &cpal::Device, config: &Config) -> u32 {
+    let target = config.audio.sample_rate;
+    let supports_target = device.supported_input_configs().is_ok_and(|mut configs| {
+        configs.any(|range| {
+            range.channels() == config.audio.channels
+                && range.min_sample_rate().0 <= target
+                && target <= range.max_sample_rate().0
+        })
+    });
+
+    if supports_target {
+        return target;
+    }
+
+    device
+        .default_input_config()
+        .map(|default| default.sample_rate().0)
+        .unwrap_or(target)
+}
+
+/// Seconds of speech recorded for the `cowcow init` test upload
+const TEST_CLIP_SECS: u32 = 3;
+
+/// Interactive first-run setup, saving the config once every step is done
+async fn run_setup_wizard(config: &Config) -> Result<()> {
+    let mut config = config.clone();
+    println!("🐄 Cowcow setup (press Enter to keep the value in brackets)");
+
+    loop {
+        let endpoint = ask("Server endpoint", &config.api.endpoint)?;
+        match config.set_value("api.endpoint", &endpoint) {
+            Ok(()) => break,
+            Err(e) => println!("❌ {e}"),
+        }
+    }
+
+    let data_dir = ask(
+        "Data directory",
+        &config.storage.data_dir.display().to_string(),
+    )?;
+    config.storage.data_dir = PathBuf::from(data_dir);
+    std::fs::create_dir_all(&config.storage.data_dir).with_context(|| {
+        format!(
+            "Failed to create data directory: {}",
+            config.storage.data_dir.display()
+        )
+    })?;
+
+    let devices: Vec<String> = cpal::default_host()
+        .input_devices()
+        .map(|devices| devices.map(|device| device_name(&device)).collect())
+        .unwrap_or_default();
+    if devices.is_empty() {
+        println!("⚠️  No input devices found; the system default will be used");
+    } else {
+        println!("Input devices:");
+        for (index, name) in devices.iter().enumerate() {
+            println!("  {}. {name}", index + 1);
+        }
+        let current = config
+            .audio
+            .input_device
+            .clone()
+            .or_else(|| {
+                input_device(&config)
+                    .ok()
+                    .map(|device| device_name(&device))
+            })
+            .unwrap_or_default();
+        loop {
+            let answer = ask("Input device (number or name)", &current)?;
+            let chosen = match answer.parse::<usize>() {
+                Ok(index) => index.checked_sub(1).and_then(|index| devices.get(index)),
+                Err(_) => devices.iter().find(|name| **name == answer),
+            };
+            match chosen {
+                Some(name) => {
+                    config.audio.input_device = Some(name.clone());
+                    break;
+                }
+                None => println!("❌ Unknown input device: {answer}"),
+            }
+        }
+    }
+
+    loop {
+        let languages = ask(
+            "Language codes, comma-separated (e.g. sw,en)",
+            &config.record.languages.join(","),
+        )?;
+        config.set_value("record.languages", &languages)?;
+        if !config.record.languages.is_empty() {
+            break;
+        }
+        println!("❌ At least one language is required");
+    }
+
+    let calibrate = confirm("Calibrate the microphone now?")?;
+    let test_upload = confirm(&format!(
+        "Record a {TEST_CLIP_SECS}s test clip and test-upload it to {}?",
+        config.api.endpoint
+    ))?;
+    if calibrate || test_upload {
+        let device = input_device(&config)?;
+        let name = device_name(&device);
+        let capture_rate = capture_sample_rate(&device, &config);
+        let (_stream, mut rx) = open_input_stream(&device, capture_rate, &config)?;
+
+        if calibrate {
+            let noise_floor_db = measure_noise_floor(&mut rx, capture_rate, &name, &config).await?;
+            config.audio.noise_floors.insert(name, noise_floor_db);
+        }
+        if test_upload {
+            // A failed test upload is reported, not fatal: field setups are
+            // often done offline
+            if let Err(e) = check_test_clip(&mut rx, capture_rate, &config).await {
+                println!("❌ Test upload failed: {e:#}");
+            }
+        }
+    }
+
+    config.validate()?;
+    config.save()?;
+    println!("✅ Setup complete; change settings later with `cowcow config set`");
+    Ok(())
+}
+
+/// Record a short clip and test-upload it, logging in first if needed
+///
+/// The clip goes to the server's test endpoint, which takes it as an upload
+/// but keeps nothing and awards no tokens, so it never lands in the dataset.
+/// The clip is also checked against QC.
+async fn check_test_clip(
+    rx: &mut mpsc::Receiver<Vec<f32>>,
+    capture_rate: u32,
+    config: &Config,
+) -> Result<()> {
+    let auth_client = AuthClient::new(config.clone());
+    auth_client.health_check().await?;
+    let credentials = match auth_client.check_auth().await {
+        Ok(credentials) => credentials,
+        Err(_) => {
+            println!("🔑 Log in to {}", config.api.endpoint);
+            let (username, password) = prompt_for_credentials()?;
+            auth_client.login(username, password, &[]).await?;
+            auth_client.check_auth().await?
+        }
+    };
+    credentials.require_scope(Scope::Upload, "Uploading")?;
+
+    println!("🎙️  Say a sentence now...");
+    let samples = capture_ambient(rx, capture_rate, TEST_CLIP_SECS, config).await?;
+    let wav_path = config.data_dir().join("init-test.wav");
+    let spec = config
+        .bit_depth()
+        .spec(config.audio.sample_rate, config.audio.channels);
+    let mut writer = hound::WavWriter::create(&wav_path, spec)?;
+    for &sample in &samples {
+        wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+
+    let uploaded = test_upload_clip(&wav_path, config, &credentials).await;
+    let _ = std::fs::remove_file(&wav_path);
+    let (metrics, bytes) = uploaded?;
+
+    println!(
+        "✅ Test upload of {bytes} bytes accepted by {} (not added to the dataset or tokens); \
+         test clip SNR {:.1} dB",
+        config.api.endpoint, metrics.snr_db
+    );
+    let report = config.qc_policy().evaluate(&metrics);
+    if !report.passed {
+        println!(
+            "⚠️  Recordings like this would fail QC: {}",
+            report.failure_summary()
+        );
+    }
+    Ok(())
+}
+
+/// Analyze the `cowcow init` test clip and send it to the test endpoint,
+/// returning its metrics and the bytes sent
+async fn test_upload_clip(
+    wav_path: &Path,
+    config: &Config,
+    credentials: &Credentials,
+) -> Result<(QcMetrics, u64)> {
+    let metrics =
+        cowcow_core::analyze_wav_timeline(wav_path, config.processor_builder().into_config())?
+            .summary();
+    let lang = config
+        .record
+        .languages
+        .first()
+        .map_or("und", String::as_str);
+    let bytes = UploadClient::new(config.clone())
+        .test_upload(
+            lang,
+            &serde_json::to_string(&metrics)?,
+            wav_path,
+            credentials,
+        )
+        .await?;
+    Ok((metrics, bytes))
+}
+
+/// Ask a question on the terminal, returning `default` for an empty answer
+fn ask(question: &str, default: &str) -> Result<String> {
+    use std::io::{self, Write};
+
+    if default.is_empty() {
+        print!("{question}: ");
+    } else {
+        print!("{question} [{default}]: ");
+    }
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// An answer from the keyboard, or a press of a configured controller
+enum HandsFree {
+    /// The answer typed, in lowercase; empty for Enter
+    Key(String),
+    Action(ControllerAction),
+}
+
+/// Ask a question like [`ask`], but with controllers configured answer on a
+/// single key press, so that a pedal or button can answer too
+async fn ask_hands_free(question: &str) -> Result<HandsFree> {
+    use std::io::Write;
+
+    if !controller::is_active() || !std::io::stdin().is_terminal() {
+        return Ok(HandsFree::Key(ask(question, "")?.to_ascii_lowercase()));
+    }
+    print!("{question}: ");
+    std::io::stdout().flush()?;
+    controller::discard_pending_actions();
+    let raw_mode = keys::RawModeGuard::enable();
+    let answer = loop {
+        if let Some(key) = keys::poll_key() {
+            if keys::is_interrupt(&key) {
+                drop(raw_mode);
+                println!();
+                anyhow::bail!("Interrupted");
+            }
+            if let Some(action) = controller::action_for_key(&key) {
+                break HandsFree::Action(action);
+            }
+            match key.code {
+                KeyCode::Enter => break HandsFree::Key(String::new()),
+                KeyCode::Char(c) => break HandsFree::Key(c.to_lowercase().collect()),
+                _ => {}
+            }
+        }
+        if let Some(action) = controller::poll_action() {
+            break HandsFree::Action(action);
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    };
+    drop(raw_mode);
+    println!();
+    Ok(answer)
+}
+
+/// Play the recorded consent explanation for `lang` until the speaker agrees
+/// or declines; returns whether they agreed
+///
+/// The explanation is spoken so participants who cannot read the prompt
+/// text still know what they are agreeing to.
+async fn play_consent_explanation(
+    db: &SqlitePool,
+    config: &Config,
+    lang: &str,
+    speaker: Option<&str>,
+) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    // Consent is only ever given by someone at the keyboard
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("Consent must be given at a terminal; nothing was recorded");
+    }
+    let path = config.record.consent_audio(lang).with_context(|| {
+        format!(
+            "No consent explanation for language '{lang}': add {}",
+            config
+                .record
+                .prompts_dir
+                .as_deref()
+                .unwrap_or(Path::new("<record.prompts_dir>"))
+                .join(lang)
+                .join("consent.wav")
+                .display()
+        )
+    })?;
+    let clip = playback::Clip::load(&path)?;
+
+    loop {
+        println!(
+            "🔊 Playing the consent explanation ({:.0}s)",
+            clip.duration_secs()
+        );
+        let playing = clip.clone();
+        tokio::task::spawn_blocking(move || playback::play(&playing)).await??;
+
+        print!("Type Y to agree and start recording, R to listen again, N to decline: ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            anyhow::bail!("No answer to the consent explanation; nothing was recorded");
+        }
+        let agreed = match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => true,
+            "n" | "no" => false,
+            _ => continue,
+        };
+        speakers::record_consent_explanation(db, speaker, lang, &path, agreed).await?;
+        return Ok(agreed);
+    }
+}
+
+/// Ask a yes/no question on the terminal, defaulting to yes
+fn confirm(question: &str) -> Result<bool> {
+    let answer = ask(&format!("{question} [Y/n]"), "")?;
+    Ok(!answer.eq_ignore_ascii_case("n") && !answer.eq_ignore_ascii_case("no"))
+}
+
+async fn check_health(config: &Config) -> Result<()> {
+    println!("🔍 System Health Check");
+
+    // Check audio device
+    println!(
+        "  Audio device: {}",
+        if input_device(config).is_ok() {
+            "✅"
+        } else {
+            "❌"
+        }
+    );
+
+    // Check storage
+    let storage_dir = config.data_dir();
+    println!(
+        "  Storage directory: {}",
+        if storage_dir.exists() { "✅" } else { "❌" }
+    );
+
+    // Check recordings directory
+    println!(
+        "  Recordings directory: {}",
+        if storage::recordings_dir_available(config) {
+            "✅"
+        } else {
+            "❌ (not mounted, spooling internally)"
+        }
+    );
+
+    // Check database
+    let db_path = config.database_path();
+    println!("  Database: {}", if db_path.exists() { "✅" } else { "❌" });
+
+    // Check server connection
+    let auth_client = AuthClient::new(config.clone());
+    match auth_client.health_check().await {
+        Ok(_) => println!("  Server connection: ✅"),
+        Err(_) => println!("  Server connection: ❌"),
+    }
+
+    // Check authentication
+    match auth_client.check_auth().await {
+        Ok(_) => println!("  Authentication: ✅"),
+        Err(_) => println!("  Authentication: ❌"),
+    }
+
+    Ok(())
+}
+
+async fn export_recordings(
+    config: ExportConfig,
+    db: &SqlitePool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    use std::fs;
+
+    if config.audio_format.needs_ffmpeg()
+        && config.format != "json"
+        && !transcode::ffmpeg_available()
+    {
+        return Err(anyhow::anyhow!(
+            "Exporting {} audio needs ffmpeg; install it or use --audio-format wav",
+            config.audio_format
+        ));
+    }
+
+    // Create destination directory if it doesn't exist
+    fs::create_dir_all(&config.dest).context("Failed to create destination directory")?;
+
+    // Build query with filters
+    let mut query = String::from(
+        "SELECT id, lang, prompt, qc_metrics, created_at, uploaded_at, wav_path FROM recordings WHERE 1=1",
+    );
+    let mut params: Vec<String> = Vec::new();
+
+    // Language filter
+    if let Some(lang_filter) = &config.lang {
+        query.push_str(" AND lang = ?");
+        params.push(lang_filter.clone());
+    }
+
+    // Status filter
+    match config.status.as_deref() {
+        Some("uploaded") => {
+            query.push_str(" AND uploaded_at IS NOT NULL");
+        }
+        Some("pending") => {
+            query.push_str(" AND uploaded_at IS NULL");
+        }
+        Some("failed") => {
+            query.push_str(" AND id IN (SELECT recording_id FROM upload_queue WHERE attempts > 0)");
+        }
+        _ => {}
+    }
+
+    // Archived recordings stay out unless asked for
+    if !config.include_archived {
+        query.push_str(" AND archived = 0");
+    }
+
+    // Only the selected take of a prompt read more than once
+    if !config.all_takes {
+        query.push_str(" AND take_selected = 1");
+    }
+
+    if config.approved_only {
+        query.push_str(" AND review_status = 'approved'");
+    }
+
+    let (session_condition, session_params) = config.sessions.condition("session_id");
+    query.push_str(&session_condition);
+    params.extend(session_params);
+
+    // Date filter
+    let start_timestamp = chrono::Utc::now().timestamp() - (config.days as i64 * 24 * 60 * 60);
+    query.push_str(" AND created_at >= ?");
+    params.push(start_timestamp.to_string());
+
+    query.push_str(" ORDER BY created_at DESC");
+
+    // Execute query
+    let mut query_builder = sqlx::query_as::<_, RecordingRow>(&query);
+
+    for param in &params {
+        query_builder = query_builder.bind(param);
+    }
+
+    let recordings = query_builder
+        .fetch_all(db)
+        .await
+        .context("Failed to fetch recordings")?;
+
+    // Filter by QC metrics
+    let mut filtered_recordings = Vec::new();
+    for recording in recordings {
+        let qc_metrics: serde_json::Value =
+            serde_json::from_str(&recording.3).context("Failed to parse QC metrics")?;
+
+        let snr = qc_metrics
+            .get("snr_db")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0) as f32;
+        let clipping = qc_metrics
+            .get("clipping_pct")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(100.0) as f32;
+        let vad = qc_metrics
+            .get("vad_ratio")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0) as f32;
+        let speech_secs = qc_metrics
+            .get("speech_secs")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0) as f32;
+
+        // Apply QC filters
+        if let Some(min_snr_val) = config.min_snr {
+            if snr < min_snr_val {
+                continue;
+            }
+        }
+
+        if let Some(max_clipping_val) = config.max_clipping {
+            if clipping > max_clipping_val {
+                continue;
+            }
+        }
+
+        if let Some(min_vad_val) = config.min_vad {
+            if vad < min_vad_val {
+                continue;
+            }
+        }
+
+        if let Some(min_speech_val) = config.min_speech_secs {
+            if speech_secs < min_speech_val {
+                continue;
+            }
+        }
+
+        filtered_recordings.push(recording);
+    }
+    for recording in &mut filtered_recordings {
+        recording.6 = storage::locate_audio(&recording.6, &config.recordings_dir)
+            .to_string_lossy()
+            .into_owned();
+    }
+
+    // An archive deposit is public: speakers whose consent was withdrawn,
+    // and minors without a guardian's consent, stay out of it
+    if config.format == "elar" {
+        let mut withheld = 0;
+        let mut cleared_speakers: HashMap<String, bool> = HashMap::new();
+        let mut deposited = Vec::with_capacity(filtered_recordings.len());
+        for recording in filtered_recordings {
+            let (speaker_id,): (Option<String>,) =
+                sqlx::query_as("SELECT speaker_id FROM recordings WHERE id = ?")
+                    .bind(&recording.0)
+                    .fetch_one(db)
+                    .await
+                    .context("Failed to fetch speaker")?;
+            let cleared = match speaker_id {
+                None => true,
+                Some(speaker_id) => match cleared_speakers.get(&speaker_id) {
+                    Some(&cleared) => cleared,
+                    None => {
+                        let cleared = match speakers::get_speaker(db, &speaker_id).await? {
+                            Some(speaker) => {
+                                speakers::speaker_cleared(
+                                    db,
+                                    config.consent_form_version.as_deref(),
+                                    &speaker,
+                                )
+                                .await?
+                            }
+                            None => false,
+                        };
+                        cleared_speakers.insert(speaker_id, cleared);
+                        cleared
+                    }
+                },
+            };
+            if cleared {
+                deposited.push(recording);
+            } else {
+                withheld += 1;
+            }
+        }
+        if withheld > 0 {
+            println!(
+                "🔒 {withheld} recording(s) left out: their speaker's consent was withdrawn or \
+                 is missing, or a minor has no valid guardian consent"
+            );
+        }
+        filtered_recordings = deposited;
+    }
+
+    if filtered_recordings.is_empty() {
+        println!("No recordings found matching the specified criteria.");
+        return Ok(());
+    }
+
+    println!(
+        "Found {} recordings matching criteria",
+        filtered_recordings.len()
+    );
+
+    // Export based on format
+    let capture_starts = alignment::capture_starts(db).await?;
+    let (json, audio, elan) = match config.format.as_str() {
+        "json" => (true, false, false),
+        "wav" => (false, true, false),
+        "both" => (true, true, false),
+        "elan" | "elar" => (false, true, true),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid format. Use 'json', 'wav', 'both', 'elan' or 'elar'"
+            ));
+        }
+    };
+    if json {
+        let ids: Vec<String> = filtered_recordings
+            .iter()
+            .map(|recording| recording.0.clone())
+            .collect();
+        let details = RecordingDetails {
+            consents: speakers::recording_consents(
+                db,
+                config.consent_form_version.as_deref(),
+                &ids,
+            )
+            .await?,
+            prompt_fields: prompts::recording_fields(db, &ids).await?,
+            transcripts: transcripts::recording_transcripts(db, &ids).await?,
+            exemplars_played: prompts::exemplars_played(db, &ids).await?,
+            verdicts: review::recording_verdicts(db, &ids).await?,
+        };
+        export_json(
+            &filtered_recordings,
+            &capture_starts,
+            &details,
+            &config.dest,
+        )
+        .await?;
+    }
+    if audio {
+        let completed = export_wav(
+            &filtered_recordings,
+            &config.dest,
+            config.audio_format,
+            config.opus_bitrate,
+            db,
+            !config.read_only,
+            cancel,
+        )
+        .await?;
+        if !completed {
+            println!("⏹️  Export interrupted; run the same command again to resume");
+            return Ok(());
+        }
+    }
+    if elan {
+        let written =
+            export_elan(&filtered_recordings, &config.dest, config.audio_format, db).await?;
+        println!("🏷️  ELAN: {written} annotation file(s) (.eaf) next to the audio");
+    }
+    if config.format == "elar" {
+        let title = config
+            .title
+            .as_deref()
+            .unwrap_or("cowcow speech recordings");
+        let bundles = export_imdi(
+            &filtered_recordings,
+            &config.dest,
+            config.audio_format,
+            title,
+            db,
+        )
+        .await?;
+        println!(
+            "🗄️  IMDI: {} with {bundles} session file(s) in sessions/",
+            config.dest.join("corpus.imdi").display()
+        );
+    }
+    export_sessions(
+        &filtered_recordings,
+        &config.dest,
+        config.format != "json",
+        &config.recordings_dir,
+        db,
+    )
+    .await?;
+    let languages: Vec<(String, String)> = filtered_recordings
+        .iter()
+        .map(|recording| (recording.0.clone(), recording.1.clone()))
+        .collect();
+    let aligned = word_align::export_alignments(db, &languages, &config.dest).await?;
+    if aligned > 0 {
+        println!("🔤 Word alignments: {aligned} recordings in alignments.ctm and alignments.json");
+    }
+    let ids: Vec<String> = languages.into_iter().map(|(id, _)| id).collect();
+    let paired = translations::export_pairs(db, &ids, &config.dest).await?;
+    if paired > 0 {
+        println!("🔁 Translation pairs: {paired} in translations.tsv and translations.json");
+    }
+
+    println!("✅ Export completed to: {}", config.dest.display());
+    Ok(())
+}
+
+/// Write an ELAN annotation file (`.eaf`) next to each exported audio file,
+/// with tiers of its detected speech and of its transcript (or prompt)
+async fn export_elan(
+    recordings: &[RecordingRow],
+    dest: &Path,
+    audio_format: transcode::AudioFormat,
+    db: &SqlitePool,
+) -> Result<usize> {
+    let audio_dir = dest.join("recordings");
+    let mut written = 0;
+    for recording in recordings {
+        let exported = audio_dir.join(format!(
+            "{}_{}.{}",
+            recording.1,
+            recording.0,
+            audio_format.extension()
+        ));
+        // ELAN wants an absolute media URL as well as the relative one
+        let Ok(media_path) = std::fs::canonicalize(&exported) else {
+            continue;
+        };
+        let speech = QcTimeline::load(&QcTimeline::sidecar_path(Path::new(&recording.6)))
+            .map(|timeline| timeline.speech_spans())
+            .unwrap_or_default();
+        let qc_metrics: serde_json::Value = serde_json::from_str(&recording.3).unwrap_or_default();
+        let transcript = transcripts::transcript(db, &recording.0)
+            .await?
+            .or_else(|| recording.2.clone());
+        elan::EafDocument {
+            media_path: &media_path,
+            mime_type: audio_format.mime_type(),
+            duration_secs: qc_metrics
+                .get("duration_secs")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0) as f32,
+            speech: &speech,
+            transcript: transcript.as_deref(),
+        }
+        .write(&media_path.with_extension("eaf"))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Write the IMDI metadata of an archive deposit: a session file under
+/// `sessions/` for each recording session (recordings made outside one are
+/// grouped by day), linking its exported audio and annotations, and
+/// `corpus.imdi` linking the sessions; returns how many sessions there are
+async fn export_imdi(
+    recordings: &[RecordingRow],
+    dest: &Path,
+    audio_format: transcode::AudioFormat,
+    title: &str,
+    db: &SqlitePool,
+) -> Result<usize> {
+    let ids: Vec<String> = recordings.iter().map(|r| r.0.clone()).collect();
+    let mut bundles: Vec<(String, Option<sessions::Session>, Vec<&RecordingRow>)> = Vec::new();
+    let mut bundled = HashSet::new();
+    for (session, members) in sessions::sessions_for_recordings(db, &ids).await? {
+        let date = chrono::DateTime::from_timestamp(session.started_at, 0)
+            .unwrap_or_default()
+            .format("%Y%m%d");
+        let name = format!("session-{date}-{}", &session.id[..8.min(session.id.len())]);
+        let members: Vec<&RecordingRow> = recordings
+            .iter()
+            .filter(|r| members.contains(&r.0))
+            .collect();
+        bundled.extend(members.iter().map(|r| r.0.clone()));
+        bundles.push((name, Some(session), members));
+    }
+    for recording in recordings.iter().filter(|r| !bundled.contains(&r.0)) {
+        let date = chrono::DateTime::from_timestamp(recording.4, 0)
+            .unwrap_or_default()
+            .format("%Y%m%d");
+        let name = format!("recordings-{date}");
+        match bundles.iter_mut().find(|(n, _, _)| *n == name) {
+            Some((_, _, members)) => members.push(recording),
+            None => bundles.push((name, None, vec![recording])),
+        }
+    }
+
+    let mut speaker_of: HashMap<&str, String> = HashMap::new();
+    let mut speakers: HashMap<String, speakers::Speaker> = HashMap::new();
+    for id in &ids {
+        let (speaker_id,): (Option<String>,) =
+            sqlx::query_as("SELECT speaker_id FROM recordings WHERE id = ?")
+                .bind(id)
+                .fetch_one(db)
+                .await
+                .context("Failed to fetch speaker")?;
+        let Some(speaker_id) = speaker_id else {
+            continue;
+        };
+        if !speakers.contains_key(&speaker_id) {
+            if let Some(speaker) = speakers::get_speaker(db, &speaker_id).await? {
+                speakers.insert(speaker_id.clone(), speaker);
+            }
+        }
+        speaker_of.insert(id, speaker_id);
+    }
+
+    let transcripts = transcripts::recording_transcripts(db, &ids).await?;
+
+    let sessions_dir = dest.join("sessions");
+    std::fs::create_dir_all(&sessions_dir).context("Failed to create sessions directory")?;
+    let mut links = Vec::new();
+    for (name, session, members) in &bundles {
+        let mut languages: Vec<&str> = Vec::new();
+        let mut actors: Vec<imdi::Actor> = Vec::new();
+        let mut media = Vec::new();
+        for recording in members {
+            let file_name = format!("{}_{}", recording.1, recording.0);
+            let audio = dest
+                .join("recordings")
+                .join(format!("{file_name}.{}", audio_format.extension()));
+            let Ok(metadata) = std::fs::metadata(&audio) else {
+                continue;
+            };
+            if !languages.contains(&recording.1.as_str()) {
+                languages.push(&recording.1);
+            }
+            let speaker = speaker_of
+                .get(recording.0.as_str())
+                .and_then(|speaker_id| speakers.get(speaker_id));
+            if let Some(speaker) = speaker {
+                // Speakers go into the archive by ID only, as in JSON exports
+                if !actors.iter().any(|actor| actor.code == speaker.id) {
+                    actors.push(imdi::Actor {
+                        code: &speaker.id,
+                        is_minor: speaker.is_minor,
+                    });
+                }
+            }
+            let annotations = audio.with_extension("eaf");
+            media.push(imdi::MediaFile {
+                link: format!("../recordings/{file_name}.{}", audio_format.extension()),
+                mime_type: audio_format.mime_type(),
+                size_bytes: Some(metadata.len()),
+                text: transcripts
+                    .get(&recording.0)
+                    .map(String::as_str)
+                    .or(recording.2.as_deref()),
+                annotations: annotations
+                    .exists()
+                    .then(|| format!("../recordings/{file_name}.eaf")),
+            });
+        }
+        if media.is_empty() {
+            continue;
+        }
+
+        let date = match session {
+            Some(session) => session.started_at,
+            None => members.first().map_or(0, |r| r.4),
+        };
+        let conditions: Vec<&str> = session
+            .iter()
+            .flat_map(|s| [s.environment.as_deref(), s.microphone.as_deref()])
+            .flatten()
+            .collect();
+        imdi::SessionMetadata {
+            name: name.clone(),
+            title,
+            date: chrono::DateTime::from_timestamp(date, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%d")
+                .to_string(),
+            description: session.as_ref().map(|s| format!("cowcow session {}", s.id)),
+            location: session.as_ref().and_then(|s| s.location.as_deref()),
+            recording_conditions: (!conditions.is_empty()).then(|| conditions.join(", ")),
+            languages,
+            actors,
+            media,
+        }
+        .write(&sessions_dir.join(format!("{name}.imdi")))?;
+        links.push((name.clone(), format!("sessions/{name}.imdi")));
+    }
+
+    imdi::CorpusMetadata {
+        name: "cowcow",
+        title,
+        sessions: &links,
+    }
+    .write(&dest.join("corpus.imdi"))?;
+    Ok(links.len())
+}
+
+/// What `recordings.json` adds to each exported recording, by recording ID
+struct RecordingDetails {
+    consents: HashMap<String, serde_json::Value>,
+    prompt_fields: HashMap<String, serde_json::Value>,
+    transcripts: HashMap<String, String>,
+    exemplars_played: HashMap<String, bool>,
+    verdicts: HashMap<String, review::Verdict>,
+}
+
+/// Write `recordings.json`; capture start times (see
+/// [`alignment::capture_starts`]) are included with millisecond precision
+async fn export_json(
+    recordings: &[RecordingRow],
+    capture_starts: &HashMap<String, (i64, f64)>,
+    details: &RecordingDetails,
+    dest: &Path,
+) -> Result<()> {
+    use std::fs::File;
+    use std::io::Write;
+
+    let json_path = dest.join("recordings.json");
+    let mut file = File::create(&json_path).context("Failed to create JSON file")?;
+
+    writeln!(file, "[")?;
+
+    for (i, recording) in recordings.iter().enumerate() {
+        let qc_metrics: serde_json::Value = serde_json::from_str(&recording.3)?;
+        let capture = capture_starts.get(&recording.0);
+
+        let record = serde_json::json!({
+            "id": recording.0,
+            "lang": recording.1,
+            "prompt": recording.2,
+            // Question, expected answer and follow-up of a structured prompt
+            "prompt_fields": details.prompt_fields.get(&recording.0),
+            // What was actually said, where it was corrected from the prompt
+            "transcript": details.transcripts.get(&recording.0),
+            // Whether the speaker heard the prompt read to them first
+            "exemplar_played": details.exemplars_played.get(&recording.0),
+            // The reviewer's verdict and notes, once reviewed
+            "review": details.verdicts.get(&recording.0),
+            "qc_metrics": qc_metrics,
+            "created_at": recording.4,
+            "uploaded_at": recording.5,
+            "wav_path": recording.6,
+            // When capture began, and when the first sample of the
+            // (possibly trimmed) audio was captured
+            "capture_started_at": capture
+                .and_then(|&(started_at_ms, _)| alignment::format_timestamp_ms(started_at_ms)),
+            "audio_started_at": capture.and_then(|&(started_at_ms, trimmed_secs)| {
+                alignment::format_timestamp_ms(
+                    started_at_ms + (trimmed_secs * 1000.0).round() as i64,
+                )
+            }),
+            // The speaker's consent to the form in force, as of the export
+            "consent": details.consents.get(&recording.0),
+        });
+
+        if i == recordings.len() - 1 {
+            writeln!(file, "  {}", serde_json::to_string_pretty(&record)?)?;
+        } else {
+            writeln!(file, "  {},", serde_json::to_string_pretty(&record)?)?;
+        }
+    }
+
+    writeln!(file, "]")?;
+    println!("📄 JSON export: {}", json_path.display());
+    Ok(())
+}
+
+/// Write `sessions.json` (noise floors and member recordings) and, with
+/// audio exports, each session's room tone
+async fn export_sessions(
+    recordings: &[RecordingRow],
+    dest: &Path,
+    copy_audio: bool,
+    recordings_dir: &Path,
+    db: &SqlitePool,
+) -> Result<()> {
+    use std::fs;
+
+    let ids: Vec<String> = recordings.iter().map(|r| r.0.clone()).collect();
+    let sessions = sessions::sessions_for_recordings(db, &ids).await?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+
+    let room_tone_dir = dest.join("room_tone");
+    let mut manifest = Vec::new();
+    let mut copied_files = 0;
+    for (session, recording_ids) in &sessions {
+        let mut room_tone = None;
+        if let Some(stored) = session.room_tone_path.as_deref() {
+            let source = storage::locate_audio(stored, recordings_dir);
+            if copy_audio && source.exists() {
+                fs::create_dir_all(&room_tone_dir)
+                    .context("Failed to create room tone directory")?;
+                let filename = format!("{}.wav", session.id);
+                fs::copy(&source, room_tone_dir.join(&filename))
+                    .context("Failed to copy room tone")?;
+                room_tone = Some(format!("room_tone/{filename}"));
+                copied_files += 1;
+            }
+        }
+
+        manifest.push(serde_json::json!({
+            "id": session.id,
+            "started_at": session.started_at,
+            "noise_floor_db": session.noise_floor_db,
+            "location": session.location,
+            "microphone": session.microphone,
+            "environment": session.environment,
+            "os": session.os,
+            "app_version": session.app_version,
+            "input_device": session.input_device,
+            "room_tone": room_tone,
+            "recording_ids": recording_ids,
+        }));
+    }
+
+    fs::write(
+        dest.join("sessions.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .context("Failed to write sessions.json")?;
+
+    println!(
+        "🔇 Sessions export: {} sessions, {} room tone files",
+        sessions.len(),
+        copied_files
+    );
+    Ok(())
+}
+
+/// Write the recordings' audio under `recordings/`, returning whether every
+/// file was written
+///
+/// Files are written under a temporary name and renamed when complete.
+/// When `cancel` fires the export stops between files, and with `resumable`
+/// the same export started again skips those already written.
+async fn export_wav(
+    recordings: &[RecordingRow],
+    dest: &Path,
+    audio_format: transcode::AudioFormat,
+    opus_bitrate: u32,
+    db: &SqlitePool,
+    resumable: bool,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    use std::fs;
+
+    let wav_dir = dest.join("recordings");
+    fs::create_dir_all(&wav_dir).context("Failed to create WAV directory")?;
+
+    let job = format!("export:{}:{}", wav_dir.display(), audio_format);
+    let mut checkpoint = if resumable {
+        cancel::Checkpoint::load(db, &job).await?
+    } else {
+        cancel::Checkpoint::in_memory(&job)
+    };
+    if checkpoint.resumed() > 0 {
+        println!(
+            "⏩ Resuming export: {} files were written by an earlier run",
+            checkpoint.resumed()
+        );
+    }
+
+    let mut copied_files = 0;
+
+    for recording in recordings {
+        if cancel.is_cancelled() {
+            checkpoint.save(db).await?;
+            println!(
+                "🎵 {} export: {} files written to {}",
+                audio_format.extension().to_uppercase(),
+                copied_files,
+                wav_dir.display()
+            );
+            return Ok(false);
+        }
+        if checkpoint.is_done(&recording.0) {
+            continue;
+        }
+
+        let source_path = Path::new(&recording.6);
+        if source_path.exists() {
+            let filename = format!(
+                "{}_{}.{}",
+                recording.1,
+                recording.0,
+                audio_format.extension()
+            );
+            let dest_path = wav_dir.join(&filename);
+
+            // Keep the extension last, since ffmpeg picks the container by it
+            let part_path = dest_path.with_extension(format!("part.{}", audio_format.extension()));
+            if let Err(e) =
+                transcode::export_audio(source_path, &part_path, audio_format, opus_bitrate)
+            {
+                let _ = fs::remove_file(&part_path);
+                checkpoint.save(db).await?;
+                return Err(e);
+            }
+            fs::rename(&part_path, &dest_path)
+                .with_context(|| format!("Failed to write {}", dest_path.display()))?;
+            copied_files += 1;
+
+            let timeline_path = QcTimeline::sidecar_path(source_path);
+            if timeline_path.exists() {
+                fs::copy(&timeline_path, QcTimeline::sidecar_path(&dest_path))
+                    .context("Failed to copy QC timeline")?;
+            }
+            let capture_path = CaptureAlignment::sidecar_path(source_path);
+            if capture_path.exists() {
+                fs::copy(&capture_path, CaptureAlignment::sidecar_path(&dest_path))
+                    .context("Failed to copy capture timing")?;
+            }
+            // Audio-only exports carry corrected text next to the audio
+            if let Some(transcript) = transcripts::transcript(db, &recording.0).await? {
+                fs::write(dest_path.with_extension("txt"), format!("{transcript}\n"))
+                    .context("Failed to write transcript")?;
+            }
+        }
+        checkpoint.mark_done(db, &recording.0).await?;
+    }
+    checkpoint.finish(db).await?;
+
+    println!(
+        "🎵 {} export: {} files written to {}",
+        audio_format.extension().to_uppercase(),
+        copied_files,
+        wav_dir.display()
+    );
+    Ok(true)
+}
+
+/// Look up a recording by ID or unique ID prefix, returning its full ID
+/// and WAV path
+async fn find_recording(recording_id: &str, db: &SqlitePool) -> Result<(String, String)> {
+    let matches: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, wav_path FROM recordings WHERE id LIKE ? || '%' LIMIT 2")
+            .bind(recording_id)
+            .fetch_all(db)
+            .await
+            .context("Failed to look up recording")?;
+
+    match matches.as_slice() {
+        [single] => Ok(single.clone()),
+        [] => Err(anyhow::anyhow!("Recording not found: {}", recording_id)),
+        _ => Err(anyhow::anyhow!(
+            "Recording ID prefix is ambiguous: {}",
+            recording_id
+        )),
+    }
+}
+
+/// Set the `pinned` or `archived` flag of a recording
+async fn set_recording_flag(
+    recording_id: &str,
+    flag: &str,
+    value: bool,
+    db: &SqlitePool,
+) -> Result<()> {
+    let (id, _) = find_recording(recording_id, db).await?;
+    sqlx::query(&format!("UPDATE recordings SET {flag} = ? WHERE id = ?"))
+        .bind(value)
+        .bind(&id)
+        .execute(db)
+        .await
+        .with_context(|| format!("Failed to update {flag} flag"))?;
+
+    match (flag, value) {
+        ("pinned", true) => println!("📌 Pinned {id}; it will not be pruned or deleted"),
+        ("pinned", false) => println!("Unpinned {id}"),
+        ("archived", true) => {
+            println!("🗄️  Archived {id}; it stays on disk but is not uploaded or exported")
+        }
+        _ => println!("Unarchived {id}"),
+    }
+    Ok(())
+}
+
+async fn play_recording(
+    recording_id: &str,
+    speed: f32,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    let (id, wav_path) = find_recording(recording_id, db).await?;
+
+    let clip = playback::Clip::load(&config.locate_audio(&wav_path))?;
+    let clip = if speed == 1.0 {
+        clip
+    } else {
+        clip.stretched(speed)?
+    };
+
+    println!(
+        "▶️  Playing {} ({:.1}s at {:.2}x)",
+        id,
+        clip.duration_secs(),
+        speed
+    );
+    tokio::task::spawn_blocking(move || playback::play(&clip)).await??;
+    Ok(())
+}
+
+/// Show a recording's prompt and transcript and let the contributor type
+/// what was actually said, playing the recording back on request
+async fn transcribe_recording(recording_id: &str, db: &SqlitePool, config: &Config) -> Result<()> {
+    let (id, wav_path) = find_recording(recording_id, db).await?;
+    let (lang, prompt, transcript, uploaded_at): (
+        String,
+        Option<String>,
+        Option<String>,
+        Option<i64>,
+    ) = sqlx::query_as("SELECT lang, prompt, transcript, uploaded_at FROM recordings WHERE id = ?")
+        .bind(&id)
+        .fetch_one(db)
+        .await
+        .context("Failed to fetch recording")?;
+
+    println!("📝 {id} ({lang})");
+    println!("   Prompt: {}", prompt.as_deref().unwrap_or("(none)"));
+    match &transcript {
+        Some(transcript) => println!("   Transcript: {transcript}"),
+        None => println!("   Transcript: (none; the prompt stands for it)"),
+    }
+    if uploaded_at.is_some() {
+        println!("⚠️  Already uploaded; the server keeps the text it was sent");
+    }
+
+    loop {
+        let answer = ask(
+            "Type what was said; Enter keeps it as it is, P plays the recording, - clears it",
+            "",
+        )?;
+        match answer.as_str() {
+            "" => {
+                println!("Transcript unchanged");
+                return Ok(());
+            }
+            "p" | "P" => {
+                let clip = playback::Clip::load(&config.locate_audio(&wav_path))?;
+                println!("▶️  Playing ({:.1}s)", clip.duration_secs());
+                tokio::task::spawn_blocking(move || playback::play(&clip)).await??;
+            }
+            "-" => {
+                transcripts::set_transcript(db, &id, None).await?;
+                println!("🧹 Transcript cleared; the prompt stands for it again");
+                return Ok(());
+            }
+            text => {
+                transcripts::set_transcript(db, &id, Some(text)).await?;
+                println!("✅ Transcript saved");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Store transcripts by recording ID (or unique prefix), reporting the ones
+/// that match no recording
+async fn save_transcripts(rows: &[(String, String)], db: &SqlitePool) -> Result<()> {
+    let mut updated = 0;
+    let mut failed = 0;
+    for (recording_id, text) in rows {
+        match find_recording(recording_id, db).await {
+            Ok((id, _)) => {
+                transcripts::set_transcript(db, &id, Some(text)).await?;
+                updated += 1;
+            }
+            Err(e) => {
+                println!("⚠️  {e}");
+                failed += 1;
+            }
+        }
+    }
+    println!("📝 {updated} transcript(s) saved");
+    if failed > 0 {
+        println!("⚠️  {failed} row(s) did not match a recording");
+    }
+    Ok(())
+}
+
+/// Show each recording with its metrics and take the reviewer's verdict;
+/// stops early on Q
+async fn review_recordings(ids: &[String], db: &SqlitePool, config: &Config) -> Result<()> {
+    use review::ReviewStatus;
+
+    let mut tally: BTreeMap<ReviewStatus, usize> = BTreeMap::new();
+    'recordings: for (n, id) in ids.iter().enumerate() {
+        let item = review::load_item(db, id).await?;
+        let mut status = item.status();
+        let mut notes = item.review_notes.clone();
+
+        println!();
+        println!("🔎 [{}/{}] {} ({})", n + 1, ids.len(), item.id, item.lang);
+        if let Some(prompt) = &item.prompt {
+            println!("   Prompt: {prompt}");
+        }
+        if let Some(transcript) = item
+            .transcript
+            .as_ref()
+            .filter(|t| Some(*t) != item.prompt.as_ref())
+        {
+            println!("   Transcript: {transcript}");
         }
-        Commands::Stats => {
-            let db = init_db(&config).await?;
-            show_stats(&db).await?;
+        let metric = |name: &str| {
+            item.metric(name)
+                .map_or("-".to_string(), |value| format!("{value:.1}"))
+        };
+        println!(
+            "   {}s, SNR {} dB, clipping {}%, VAD {}%",
+            metric("duration_secs"),
+            metric("snr_db"),
+            metric("clipping_pct"),
+            // Stored as a percentage already
+            item.metric("vad_ratio")
+                .map_or("-".to_string(), |vad| format!("{vad:.0}"))
+        );
+        match item.report() {
+            Some(report) if report.passed => println!("   ✅ QC passed"),
+            Some(report) => println!("   ❌ QC failed: {}", report.failure_summary()),
+            None => {}
         }
-        Commands::Doctor => {
-            check_health(&config).await?;
+        if let Some(metrics) = &item.outlier_metrics {
+            println!(
+                "   ⚠️  Unusual for its speaker and device: {}",
+                metrics.replace(',', ", ")
+            );
         }
-        Commands::Export {
-            format,
-            dest,
-            lang,
-            status,
-            min_snr,
-            max_clipping,
-            min_vad,
-            days,
-        } => {
-            let db = init_db(&config).await?;
-            let export_config = ExportConfig {
-                format,
-                dest,
-                lang,
-                status,
-                min_snr,
-                max_clipping,
-                min_vad,
-                days,
-            };
-            export_recordings(export_config, &db).await?;
+        if item.uploaded_at.is_some() {
+            println!("   ☁️  Already uploaded");
         }
-        Commands::Auth { command } => {
-            handle_auth_command(command, &config).await?;
+        if status != ReviewStatus::Unreviewed {
+            println!("   Verdict so far: {status}");
         }
-        Commands::Config { command } => {
-            handle_config_command(command, &config).await?;
+        if let Some(notes) = &notes {
+            println!("   Notes: {notes}");
         }
-        Commands::Tokens { command } => {
-            handle_tokens_command(command, &config).await?;
+
+        loop {
+            let answer = ask(
+                "P play, A approve, R reject, N needs re-record, E edit notes, T trim, S skip, Q quit",
+                "s",
+            )?;
+            let verdict = match answer.to_ascii_lowercase().as_str() {
+                "p" => {
+                    let played = match playback::Clip::load(&config.locate_audio(&item.wav_path)) {
+                        Ok(clip) => {
+                            tokio::task::spawn_blocking(move || playback::play(&clip)).await?
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = played {
+                        println!("⚠️  Could not play the recording: {e:#}");
+                    }
+                    continue;
+                }
+                "a" => ReviewStatus::Approved,
+                "r" => ReviewStatus::Rejected,
+                "n" => ReviewStatus::NeedsRerecord,
+                "e" => {
+                    let edited = ask("Notes (- to clear)", notes.as_deref().unwrap_or(""))?;
+                    notes = (edited != "-").then_some(edited);
+                    review::set_verdict(db, id, status, notes.as_deref()).await?;
+                    continue;
+                }
+                "t" => {
+                    if let Err(e) = trim_recording(id, db, config).await {
+                        println!("⚠️  {e:#}");
+                    }
+                    continue;
+                }
+                "s" => continue 'recordings,
+                "q" => break 'recordings,
+                _ => continue,
+            };
+            // Say what is wrong, so the speaker can do better next time
+            if verdict != ReviewStatus::Approved && notes.is_none() {
+                let reason = ask("Reason (optional)", "")?;
+                notes = (!reason.is_empty()).then_some(reason);
+            }
+            status = verdict;
+            review::set_verdict(db, id, status, notes.as_deref()).await?;
+            *tally.entry(status).or_default() += 1;
+            break;
         }
     }
 
+    if tally.is_empty() {
+        println!("No verdicts given");
+    } else {
+        let summary: Vec<String> = tally
+            .iter()
+            .map(|(status, count)| format!("{count} {status}"))
+            .collect();
+        println!("✅ Reviewed: {}", summary.join(", "));
+    }
     Ok(())
 }
 
-async fn init_db(config: &Config) -> Result<SqlitePool> {
-    let db_path = config.database_path();
-
-    // Create directory if it doesn't exist
-    if let Some(parent) = db_path.parent() {
-        std::fs::create_dir_all(parent)?;
+/// Trim a recording's start and end interactively
+async fn trim_recording(recording_id: &str, db: &SqlitePool, config: &Config) -> Result<()> {
+    let (id, wav_path) = find_recording(recording_id, db).await?;
+    let (uploaded_at, trim_start_secs, trim_end_secs, outlier_metrics): (
+        Option<i64>,
+        Option<f64>,
+        Option<f64>,
+        Option<String>,
+    ) = sqlx::query_as(
+        "SELECT uploaded_at, trim_start_secs, trim_end_secs, outlier_metrics FROM recordings WHERE id = ?",
+    )
+    .bind(&id)
+    .fetch_one(db)
+    .await
+    .context("Failed to fetch recording")?;
+    if uploaded_at.is_some() {
+        return Err(anyhow::anyhow!(
+            "Recording {id} is already uploaded; trimming would not change the uploaded copy"
+        ));
     }
 
-    // Create recordings directory
-    let recordings_dir = config.recordings_dir();
-    std::fs::create_dir_all(&recordings_dir)?;
-
-    let pool = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", db_path.display())).await?;
+    // Edit from the untrimmed audio so earlier trims can be undone
+    let wav_path = PathBuf::from(wav_path);
+    let original = review::original_path(&wav_path);
+    let source = if original.exists() {
+        &original
+    } else {
+        &wav_path
+    };
+    let clip = playback::Clip::load(source)?;
+    let editor = review::TrimEditor::new(
+        clip,
+        trim_start_secs.unwrap_or(0.0),
+        trim_end_secs.unwrap_or(0.0),
+    );
 
-    // Create tables if they don't exist
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS recordings (
-            id TEXT PRIMARY KEY,
-            lang TEXT NOT NULL,
-            prompt TEXT,
-            qc_metrics TEXT NOT NULL,
-            created_at INTEGER NOT NULL,
-            uploaded_at INTEGER,
-            wav_path TEXT NOT NULL
-        );
-        
-        CREATE TABLE IF NOT EXISTS upload_queue (
-            recording_id TEXT PRIMARY KEY,
-            attempts INTEGER NOT NULL,
-            last_attempt INTEGER,
-            FOREIGN KEY (recording_id) REFERENCES recordings(id)
+    println!("✂️  Reviewing {id}");
+    if let Some(metrics) = outlier_metrics {
+        println!(
+            "⚠️  Flagged as unusual for its speaker and device: {}",
+            metrics.replace(',', ", ")
         );
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+    }
+    let trim = tokio::task::spawn_blocking(move || review::edit_trim(editor)).await??;
+    println!();
 
-    Ok(pool)
+    let Some((start_secs, end_secs)) = trim else {
+        println!("Trim cancelled, recording unchanged");
+        return Ok(());
+    };
+    review::save_trim(db, config, &id, &wav_path, start_secs, end_secs).await?;
+    println!(
+        "✅ Trimmed {:.2}s from the start and {:.2}s from the end (original kept at {})",
+        start_secs,
+        end_secs,
+        original.display()
+    );
+    Ok(())
 }
 
-async fn record_audio(
-    lang: &str,
-    duration: Option<u32>,
-    prompt: Option<String>,
+async fn dedupe_recordings(
+    threshold: f32,
+    delete: bool,
     db: &SqlitePool,
     config: &Config,
 ) -> Result<()> {
-    info!("Starting recording for language: {}", lang);
-
-    // Initialize audio device
-    let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .context("No input device available")?;
-
-    let config_audio = cpal::StreamConfig {
-        channels: config.audio.channels,
-        sample_rate: cpal::SampleRate(config.audio.sample_rate),
-        buffer_size: cpal::BufferSize::Default,
-    };
-
-    // Create audio processor
-    let mut processor = AudioProcessor::new(config.audio.sample_rate, config.audio.channels)?;
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(anyhow::anyhow!("Threshold must be between 0 and 1"));
+    }
 
-    // Create channels for audio processing
-    let (tx, mut rx) = mpsc::channel(32); // Smaller buffer for better flow control
+    let duplicates = dedupe::find_duplicates(db, config, threshold).await?;
+    if duplicates.is_empty() {
+        println!("No duplicate recordings found");
+        return Ok(());
+    }
 
-    // Start recording stream
-    let stream = device.build_input_stream(
-        &config_audio,
-        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-            // Use try_send but with error handling
-            match tx.try_send(data.to_vec()) {
-                Ok(()) => {} // Success
-                Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
-                    // Channel is full - this is normal under high load, just drop this chunk
-                }
-                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
-                    // Receiver dropped - stop trying to send
-                }
+    let mut deleted = 0;
+    for duplicate in &duplicates {
+        // Pinned recordings are protected from deletion; flag them instead
+        let action = if delete && !duplicate.pinned {
+            dedupe::delete_duplicate(db, config, duplicate).await?;
+            deleted += 1;
+            "🗑️  Deleted"
+        } else {
+            dedupe::flag_duplicate(db, duplicate).await?;
+            if duplicate.pinned {
+                "📌 Flagged (pinned)"
+            } else {
+                "⚠️  Flagged"
             }
-        },
-        move |err| {
-            error!("Audio stream error: {}", err);
-        },
-        None,
-    )?;
-
-    stream.play()?;
-
-    // Create output directory
-    let output_dir = config.recordings_dir().join(lang);
-    std::fs::create_dir_all(&output_dir)?;
-
-    // Generate unique ID for this recording
-    let recording_id = Uuid::new_v4();
-    let wav_path = output_dir.join(format!("{recording_id}.wav"));
-
-    // Create WAV writer
-    let spec = hound::WavSpec {
-        channels: config.audio.channels,
-        sample_rate: config.audio.sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
-    let mut writer = hound::WavWriter::create(&wav_path, spec)?;
+        };
+        println!(
+            "{} {} duplicates {} (similarity {:.2})",
+            action, duplicate.id, duplicate.duplicate_of, duplicate.similarity
+        );
+    }
 
-    // Process audio data
-    let mut metrics = Vec::new();
-    let _start_time = std::time::Instant::now();
-    let duration = duration.map(|d| Duration::from_secs(d as u64));
+    let flagged = duplicates.len() - deleted;
+    if deleted > 0 {
+        println!("\nDeleted {deleted} duplicate recording(s)");
+    }
+    if flagged > 0 {
+        println!(
+            "\nFlagged {flagged} duplicate recording(s); they will not be uploaded.{}",
+            if delete {
+                ""
+            } else {
+                " Run with --delete to remove them."
+            }
+        );
+    }
+    Ok(())
+}
 
-    // Track actual audio duration based on samples processed
-    let mut total_samples_processed = 0u64;
-    let samples_per_second = config.audio.sample_rate as u64;
+async fn diff_recordings(a: &Path, b: &Path, by_hash: bool, output: &str) -> Result<()> {
+    let manifest_a = diff::load_manifest(a).await?;
+    let manifest_b = diff::load_manifest(b).await?;
+    let report = diff::diff_manifests(&manifest_a, &manifest_b, by_hash);
 
-    // Silence detection parameters
-    let silence_threshold_secs = 5.0; // Stop after 5 seconds of silence
-    let mut silence_start_samples = None::<u64>; // Track when silence started
+    match output {
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        "text" => {}
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid output format. Use 'text' or 'json'"
+            ))
+        }
+    }
 
-    // Create progress bar
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} Recording... {msg}")
-            .unwrap(),
-    );
+    println!("🔀 Comparing {} and {}", a.display(), b.display());
+    println!("  Recordings: {} vs {}", manifest_a.len(), manifest_b.len());
 
-    // Display prompt if provided
-    if let Some(prompt_text) = &prompt {
-        println!("\nPlease read the following text:");
-        println!("\"{prompt_text}\"");
-        println!("Press Enter to start recording...");
-        std::io::stdin().read_line(&mut String::new())?;
+    if report.is_empty() {
+        println!("✅ No differences found");
+        return Ok(());
     }
 
-    // Give user time to prepare
-    println!("Get ready to speak...");
-    for i in (1..=3).rev() {
-        println!("Starting in {i}...");
-        std::thread::sleep(std::time::Duration::from_secs(1));
+    if !report.only_in_a.is_empty() {
+        println!("\nOnly in {} ({}):", a.display(), report.only_in_a.len());
+        for id in &report.only_in_a {
+            println!("  - {id}");
+        }
     }
-    println!("🎙️  RECORDING NOW!");
-    loop {
-        // Use timeout to avoid infinite waiting
-        let timeout_result = tokio::time::timeout(
-            Duration::from_millis(10), // Shorter timeout for more responsive processing
-            rx.recv(),
-        )
-        .await;
 
-        match timeout_result {
-            Ok(Some(samples)) => {
-                // Process chunk
-                let chunk_metrics = processor.process_chunk(&samples);
-                metrics.push(chunk_metrics.clone());
+    if !report.only_in_b.is_empty() {
+        println!("\nOnly in {} ({}):", b.display(), report.only_in_b.len());
+        for id in &report.only_in_b {
+            println!("  + {id}");
+        }
+    }
 
-                // Write samples to WAV file
-                for &sample in &samples {
-                    writer.write_sample((sample * 32767.0) as i16)?;
-                }
+    if !report.same_content.is_empty() {
+        println!(
+            "\nSame audio under different ids ({}):",
+            report.same_content.len()
+        );
+        for content in &report.same_content {
+            println!(
+                "  {} = {} ({})",
+                content.id_a,
+                content.id_b,
+                &content.sha256[..12]
+            );
+        }
+    }
 
-                // Update total samples processed
-                total_samples_processed += samples.len() as u64;
+    if !report.metadata_changes.is_empty() {
+        println!(
+            "\nMetadata differences ({}):",
+            report.metadata_changes.len()
+        );
+        for change in &report.metadata_changes {
+            println!(
+                "  {} {}: {} → {}",
+                change.id, change.field, change.a, change.b
+            );
+        }
+    }
 
-                // Calculate actual audio duration based on samples processed
-                let actual_duration = Duration::from_secs_f64(
-                    total_samples_processed as f64 / samples_per_second as f64,
-                );
+    if !report.qc_changes.is_empty() {
+        println!("\nQC differences ({}):", report.qc_changes.len());
+        for change in &report.qc_changes {
+            println!(
+                "  {} {}: {} → {}",
+                change.id, change.field, change.a, change.b
+            );
+        }
+    }
 
-                // Silence detection logic
-                // Calculate RMS of the current chunk
-                let rms = {
-                    let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
-                    (sum_squares / samples.len() as f32).sqrt()
-                };
+    Ok(())
+}
 
-                // Consider voice activity if either VAD detects it OR RMS is above threshold
-                let vad_threshold = 0.01; // VAD ratio threshold (1%)
-                let rms_threshold = 0.005; // RMS level threshold (adjusted to 0.005 for better voice sensitivity)
-                let has_voice_activity =
-                    chunk_metrics.vad_ratio > vad_threshold || rms > rms_threshold;
+async fn handle_auth_command(command: AuthCommands, config: &Config) -> Result<()> {
+    let auth_client = AuthClient::new(config.clone());
 
-                if has_voice_activity {
-                    // Voice detected - reset silence timer
-                    silence_start_samples = None;
-                } else {
-                    // No voice detected - track silence duration
-                    if silence_start_samples.is_none() {
-                        // Start tracking silence from this chunk
-                        silence_start_samples =
-                            Some(total_samples_processed - samples.len() as u64);
+    match command {
+        AuthCommands::Login { scopes } => {
+            let (username, password) = prompt_for_credentials()?;
+            match auth_client.login(username, password, &scopes).await {
+                Ok(_) => println!("✅ Login successful!"),
+                Err(e) => println!("❌ Login failed: {e}"),
+            }
+        }
+        AuthCommands::Register => {
+            let (username, email, password) = prompt_for_registration()?;
+            match auth_client.register(username, email, password).await {
+                Ok(_) => println!("✅ Registration successful! You can now login."),
+                Err(e) => println!("❌ Registration failed: {e}"),
+            }
+        }
+        AuthCommands::Logout => {
+            auth_client.logout().await?;
+            println!("✅ Logged out successfully");
+        }
+        AuthCommands::Status => match auth_client.check_auth().await {
+            Ok(creds) => {
+                println!("✅ Authenticated");
+                if let Some(username) = &creds.username {
+                    println!("  Username: {username}");
+                }
+                match &creds.scopes {
+                    Some(scopes) => {
+                        let scopes: Vec<&str> = scopes.iter().map(|scope| scope.as_str()).collect();
+                        println!("  Scopes: {}", scopes.join(", "));
                     }
+                    None => println!("  Scopes: unrestricted"),
+                }
+                if let Some(expires_at) = creds.expires_at {
+                    let expires =
+                        chrono::DateTime::from_timestamp(expires_at as i64, 0).unwrap_or_default();
+                    println!("  Expires: {}", expires.format("%Y-%m-%d %H:%M:%S"));
                 }
+            }
+            Err(_) => println!("❌ Not authenticated"),
+        },
+    }
 
-                // Check if we should stop due to silence
-                let mut stop_reason = None;
-                if let Some(silence_start) = silence_start_samples {
-                    let silence_duration_samples = total_samples_processed - silence_start;
-                    let silence_duration_secs =
-                        silence_duration_samples as f64 / samples_per_second as f64;
+    Ok(())
+}
 
-                    if silence_duration_secs >= silence_threshold_secs {
-                        stop_reason =
-                            Some(format!("Silence detected for {silence_duration_secs:.1}s"));
-                    }
+async fn handle_config_command(command: ConfigCommands, config: &Config) -> Result<()> {
+    match command {
+        ConfigCommands::Show => {
+            let config_toml = toml::to_string_pretty(config)?;
+            println!("📁 Current Configuration:");
+            println!("{config_toml}");
+        }
+        ConfigCommands::Set { key, value } => {
+            AuthClient::new(config.clone())
+                .require_scope_or_offline(Scope::Admin, "Changing settings")?;
+            let mut config_copy = config.clone();
+            match config_copy.set_value(&key, &value) {
+                Ok(_) => {
+                    config_copy.save()?;
+                    println!("✅ Configuration updated: {key} = {value}");
                 }
-
-                // Check duration based on actual audio processed (not wall clock time)
-                if stop_reason.is_none() {
-                    if let Some(dur) = duration {
-                        if actual_duration >= dur {
-                            stop_reason = Some(format!(
-                                "Duration reached: {actual_duration:.2?} (actual audio duration)"
-                            ));
-                        }
+                Err(e) => {
+                    println!("❌ Failed to set configuration: {e}");
+                    println!("Available keys:");
+                    for available_key in Config::get_available_keys() {
+                        println!("  - {available_key}");
                     }
                 }
+            }
+        }
+        ConfigCommands::Reset => {
+            AuthClient::new(config.clone())
+                .require_scope_or_offline(Scope::Admin, "Resetting settings")?;
+            let default_config = Config::default();
+            default_config.save()?;
+            println!("✅ Configuration reset to defaults");
+        }
+    }
 
-                // Update progress with silence information
-                let silence_info = if let Some(silence_start) = silence_start_samples {
-                    let silence_duration_samples = total_samples_processed - silence_start;
-                    let silence_duration_secs =
-                        silence_duration_samples as f64 / samples_per_second as f64;
-                    format!(" | Silence: {silence_duration_secs:.1}s")
-                } else {
-                    String::new()
-                };
+    Ok(())
+}
 
-                let voice_activity_info = if has_voice_activity {
-                    " | VOICE DETECTED"
-                } else {
-                    ""
-                };
+async fn handle_tokens_command(command: TokensCommands, config: &Config) -> Result<()> {
+    let auth_client = AuthClient::new(config.clone());
 
-                pb.set_message(format!(
-                    "SNR: {:.1} dB | Clipping: {:.1}% | VAD: {:.1}% | RMS: {:.4}{}{}",
-                    chunk_metrics.snr_db,
-                    chunk_metrics.clipping_pct,
-                    chunk_metrics.vad_ratio,
-                    rms,
-                    silence_info,
-                    voice_activity_info
-                ));
+    match command {
+        TokensCommands::Balance => {
+            let balance = auth_client.get_token_balance().await?;
+            println!("💰 Token Balance Summary:");
+            println!("  Current Balance: {} tokens", balance.balance);
+            println!("  Total Earned: {} tokens", balance.total_earned);
+            println!("  Total Spent: {} tokens", balance.total_spent);
+        }
+        TokensCommands::History { days } => {
+            let history = auth_client.get_token_history(days).await?;
+            println!("📜 Token Transaction History (last {days} days):");
 
-                // Stop recording if conditions are met
-                if let Some(reason) = stop_reason {
-                    println!("{reason}");
-                    break;
+            if history.is_empty() {
+                println!("  No transactions found.");
+            } else {
+                for tx in history {
+                    println!(
+                        "  {} | {} | {:+} tokens | Balance: {} | {}",
+                        tx.date.format("%Y-%m-%d %H:%M:%S"),
+                        tx.transaction_type,
+                        tx.amount,
+                        tx.balance,
+                        tx.notes
+                    );
                 }
             }
-            Ok(None) => {
-                println!("Channel closed");
-                break;
-            }
-            Err(_) => {
-                // Timeout - just continue the loop without checking duration
-                // This ensures we only stop based on actual audio data processed
-                continue;
-            }
         }
     }
 
-    writer.finalize()?;
-    pb.finish_with_message("Recording complete!");
-
-    // Calculate average metrics
-    let avg_metrics = QcMetrics {
-        snr_db: metrics.iter().map(|m| m.snr_db).sum::<f32>() / metrics.len() as f32,
-        clipping_pct: metrics.iter().map(|m| m.clipping_pct).sum::<f32>() / metrics.len() as f32,
-        vad_ratio: metrics.iter().map(|m| m.vad_ratio).sum::<f32>() / metrics.len() as f32,
-    };
+    Ok(())
+}
 
-    // Display quality metrics
-    println!("\nRecording Quality Metrics:");
-    println!("  SNR: {:.1} dB", avg_metrics.snr_db);
-    println!("  Clipping: {:.1}%", avg_metrics.clipping_pct);
-    println!("  Voice Activity: {:.1}%", avg_metrics.vad_ratio);
+async fn handle_prompts_command(
+    command: PromptsCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        PromptsCommands::Import { file, lang, domain } => {
+            let lang = lang
+                .or_else(|| config.record.languages.first().cloned())
+                .context("No language given: pass --lang or set record.languages")?;
+            let summary = prompts::import_prompts(db, &file, &lang, domain.as_deref()).await?;
+            println!(
+                "✅ Imported {} new prompt(s), updated {}",
+                summary.added, summary.updated
+            );
+            if summary.linked > 0 {
+                println!(
+                    "   Linked {} earlier recording(s) to their prompts",
+                    summary.linked
+                );
+            }
+        }
+        PromptsCommands::List {
+            lang,
+            domain,
+            unrecorded,
+        } => {
+            let list =
+                prompts::list_prompts(db, lang.as_deref(), domain.as_deref(), unrecorded).await?;
+            println!("📜 Prompts:");
 
-    // Save to database
-    sqlx::query(
-        r#"
-        INSERT INTO recordings (id, lang, prompt, qc_metrics, created_at, wav_path)
-        VALUES (?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(recording_id.to_string())
-    .bind(lang)
-    .bind(prompt)
-    .bind(serde_json::to_string(&avg_metrics)?)
-    .bind(
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64,
-    )
-    .bind(wav_path.to_string_lossy())
-    .execute(db)
-    .await?;
+            if list.is_empty() {
+                println!("  No prompts found. Run: cowcow prompts import <file>");
+            }
 
-    // Add to upload queue
-    sqlx::query(
-        r#"
-        INSERT INTO upload_queue (recording_id, attempts, last_attempt)
-        VALUES (?, 0, 0)
-        "#,
-    )
-    .bind(recording_id.to_string())
-    .execute(db)
-    .await?;
+            for prompt in list {
+                println!(
+                    "  {} | {} | {} | {}x | {}",
+                    prompt.id,
+                    prompt.lang,
+                    prompt.domain.as_deref().unwrap_or("-"),
+                    prompt.times_recorded,
+                    prompt.text
+                );
+            }
+        }
+        PromptsCommands::Stats { lang } => {
+            let stats = prompts::prompt_stats(db, lang.as_deref()).await?;
+            println!("📜 Prompt coverage:");
 
-    info!("Recording saved: {}", wav_path.display());
+            if stats.is_empty() {
+                println!("  No prompts found. Run: cowcow prompts import <file>");
+            }
 
-    // Auto-upload if configured
-    if config.storage.auto_upload {
-        println!("Auto-uploading recording...");
-        upload_recordings(false, db, config).await?;
+            for row in stats {
+                println!(
+                    "  {} | {} | {}/{} prompts recorded ({} still need recordings) | {} recordings",
+                    row.lang,
+                    row.domain.as_deref().unwrap_or("-"),
+                    row.recorded,
+                    row.prompts,
+                    row.prompts - row.recorded,
+                    row.recordings
+                );
+            }
+        }
     }
 
     Ok(())
 }
 
-async fn upload_recordings(force: bool, db: &SqlitePool, config: &Config) -> Result<()> {
-    let auth_client = AuthClient::new(config.clone());
-    let upload_client = UploadClient::new(config.clone());
-
-    // Check authentication
-    let credentials = match auth_client.check_auth().await {
-        Ok(creds) => creds,
-        Err(_) => {
-            println!("Authentication required. Please login first.");
-            println!("Run: cowcow auth login");
-            return Ok(());
+async fn handle_takes_command(command: TakesCommands, db: &SqlitePool) -> Result<()> {
+    match command {
+        TakesCommands::List { recording_id } => {
+            let slots = takes::list_slots(db, recording_id.as_deref()).await?;
+            if slots.is_empty() {
+                println!("No prompts with more than one take");
+                return Ok(());
+            }
+            for slot in slots {
+                let prompt = slot
+                    .takes
+                    .first()
+                    .and_then(|take| take.prompt.as_deref())
+                    .unwrap_or("");
+                println!("\"{prompt}\"");
+                for (index, take) in slot.takes.iter().enumerate() {
+                    let created = chrono::DateTime::from_timestamp(take.created_at, 0)
+                        .map(|created| created.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    let qc = match take.qc_passed() {
+                        Some(true) => "QC passed",
+                        Some(false) => "QC failed",
+                        None => "QC unknown",
+                    };
+                    let mut notes = Vec::new();
+                    if take.take_chosen {
+                        notes.push("chosen");
+                    }
+                    if take.uploaded_at.is_some() {
+                        notes.push("uploaded");
+                    }
+                    println!(
+                        "  {} take {}  {}  {}  {}  SNR {:.1} dB  {:.1}s{}",
+                        if take.take_selected { "*" } else { " " },
+                        index + 1,
+                        take.id,
+                        created,
+                        qc,
+                        take.metric("snr_db").unwrap_or(0.0),
+                        take.metric("duration_secs").unwrap_or(0.0),
+                        if notes.is_empty() {
+                            String::new()
+                        } else {
+                            format!("  ({})", notes.join(", "))
+                        }
+                    );
+                }
+            }
         }
-    };
-
-    // Upload pending recordings
-    upload_client
-        .upload_pending_recordings(db, &credentials, force)
-        .await?;
+        TakesCommands::Select { recording_id } => {
+            let slot = takes::select_take(db, &recording_id).await?;
+            println!(
+                "✅ Take {} of {} selected; the others stay local",
+                slot.number(&recording_id).unwrap_or(1),
+                slot.takes.len()
+            );
+        }
+    }
 
     Ok(())
 }
 
-async fn show_stats(db: &SqlitePool) -> Result<()> {
-    let stats = sqlx::query(
-        r#"
-        SELECT 
-            COUNT(*) as total_recordings,
-            COUNT(CASE WHEN uploaded_at IS NOT NULL THEN 1 END) as uploaded_recordings,
-            COUNT(CASE WHEN uploaded_at IS NULL THEN 1 END) as pending_recordings
-        FROM recordings
-        "#,
-    )
-    .fetch_one(db)
-    .await?;
-
-    println!("📊 Recording Statistics");
-    println!(
-        "  Total recordings: {}",
-        stats.get::<i64, _>("total_recordings")
-    );
-    println!("  Uploaded: {}", stats.get::<i64, _>("uploaded_recordings"));
-    println!("  Pending: {}", stats.get::<i64, _>("pending_recordings"));
+async fn handle_sessions_command(command: SessionsCommands, db: &SqlitePool) -> Result<()> {
+    match command {
+        SessionsCommands::List {
+            environment,
+            location,
+        } => {
+            let filter = sessions::SessionFilter {
+                session: None,
+                environment,
+                location,
+            };
+            let listings = sessions::list_sessions(db, &filter).await?;
+            if listings.is_empty() {
+                println!("No sessions");
+                return Ok(());
+            }
+            for listing in listings {
+                let session = &listing.session;
+                let started = chrono::DateTime::from_timestamp(session.started_at, 0)
+                    .map(|started| started.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{}  {}  {} recording(s)  {}",
+                    session.id,
+                    started,
+                    listing.recordings,
+                    session.describe()
+                );
+                let machine: Vec<&str> = [&session.input_device, &session.os]
+                    .into_iter()
+                    .filter_map(|part| part.as_deref())
+                    .collect();
+                if !machine.is_empty() {
+                    println!("    {}", machine.join(" · "));
+                }
+            }
+        }
+    }
 
     Ok(())
 }
 
-async fn check_health(config: &Config) -> Result<()> {
-    println!("🔍 System Health Check");
-
-    // Check audio device
-    let host = cpal::default_host();
-    let device = host.default_input_device();
-    println!(
-        "  Audio device: {}",
-        if device.is_some() { "✅" } else { "❌" }
-    );
-
-    // Check storage
-    let storage_dir = config.data_dir();
-    println!(
-        "  Storage directory: {}",
-        if storage_dir.exists() { "✅" } else { "❌" }
-    );
-
-    // Check database
-    let db_path = config.database_path();
-    println!("  Database: {}", if db_path.exists() { "✅" } else { "❌" });
-
-    // Check server connection
-    let auth_client = AuthClient::new(config.clone());
-    match auth_client.health_check().await {
-        Ok(_) => println!("  Server connection: ✅"),
-        Err(_) => println!("  Server connection: ❌"),
-    }
-
-    // Check authentication
-    match auth_client.check_auth().await {
-        Ok(_) => println!("  Authentication: ✅"),
-        Err(_) => println!("  Authentication: ❌"),
+async fn handle_receipts_command(
+    command: ReceiptsCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        ReceiptsCommands::Export {
+            month,
+            format,
+            output,
+        } => {
+            let auth = AuthClient::new(config.clone());
+            let verification = match receipts::verify(&auth, month).await {
+                Ok(verification) => Some(verification),
+                Err(e) => {
+                    eprintln!(
+                        "⚠️  Could not check the uploads with the server ({e:#}); accepted takes \
+                         and tokens are local figures, marked unverified"
+                    );
+                    None
+                }
+            };
+            let list = receipts::monthly_receipts(db, month, verification.as_ref()).await?;
+            match output {
+                Some(path) => {
+                    let file = std::fs::File::create(&path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                    receipts::write_receipts(&list, format, file)?;
+                    println!(
+                        "✅ Wrote {} receipt(s) for {} to {}",
+                        list.len(),
+                        month,
+                        path.display()
+                    );
+                }
+                None => receipts::write_receipts(&list, format, std::io::stdout().lock())?,
+            }
+        }
     }
 
     Ok(())
 }
 
-async fn export_recordings(config: ExportConfig, db: &SqlitePool) -> Result<()> {
-    use std::fs;
-
-    // Create destination directory if it doesn't exist
-    fs::create_dir_all(&config.dest).context("Failed to create destination directory")?;
-
-    // Build query with filters
-    let mut query = String::from("SELECT * FROM recordings WHERE 1=1");
-    let mut params: Vec<String> = Vec::new();
+async fn handle_campaigns_command(
+    command: CampaignsCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        CampaignsCommands::Sync => {
+            let auth_client = AuthClient::new(config.clone());
+            let list = auth_client.get_campaigns().await?;
+            campaigns::store_campaigns(db, &list).await?;
+            println!("✅ Synced {} campaign(s)", list.len());
+        }
+        CampaignsCommands::List => {
+            let list = campaigns::list_campaigns(db).await?;
+            println!("📣 Campaigns:");
 
-    // Language filter
-    if let Some(lang_filter) = &config.lang {
-        query.push_str(" AND lang = ?");
-        params.push(lang_filter.clone());
-    }
+            if list.is_empty() {
+                println!("  No running or upcoming campaigns. Run: cowcow campaigns sync");
+            }
 
-    // Status filter
-    match config.status.as_deref() {
-        Some("uploaded") => {
-            query.push_str(" AND uploaded_at IS NOT NULL");
-        }
-        Some("pending") => {
-            query.push_str(" AND uploaded_at IS NULL");
-        }
-        Some("failed") => {
-            query.push_str(" AND id IN (SELECT recording_id FROM upload_queue WHERE attempts > 0)");
+            let now = chrono::Utc::now();
+            for campaign in list {
+                let pending = campaigns::pending_hours(db, &campaign.id).await?;
+                println!(
+                    "  {} | {} | {:.1}/{:.1}h",
+                    campaign.id,
+                    campaign.status_line(now, pending),
+                    campaign.collected_hours + pending,
+                    campaign.target_hours
+                );
+            }
         }
-        _ => {}
     }
 
-    // Date filter
-    let start_timestamp = chrono::Utc::now().timestamp() - (config.days as i64 * 24 * 60 * 60);
-    query.push_str(" AND created_at >= ?");
-    params.push(start_timestamp.to_string());
+    Ok(())
+}
 
-    query.push_str(" ORDER BY created_at DESC");
+/// Issue an enrollment token and show it as a QR code for the companion app
+async fn pair_device(
+    request: pair::EnrollmentRequest,
+    svg: Option<&Path>,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    let auth_client = AuthClient::new(config.clone());
+    auth_client.require_scope(Scope::Admin, "Pairing devices")?;
+    if request.scope == Scope::Admin {
+        return Err(anyhow::anyhow!(
+            "Companion apps can be paired with record or upload scope, not admin"
+        ));
+    }
+    if request.expires_in_secs == 0 {
+        return Err(anyhow::anyhow!("--expires-mins must be at least 1"));
+    }
 
-    // Execute query
-    let mut query_builder = sqlx::query_as::<_, RecordingRow>(&query);
+    let enrollment = auth_client.create_enrollment(&request).await?;
+    let uri = pair::pairing_uri(&config.api.endpoint, &enrollment)?;
+    pair::record_enrollment(db, &enrollment, request.label.as_deref()).await?;
 
-    for param in &params {
-        query_builder = query_builder.bind(param);
+    println!("{}", pair::render_qr(&uri)?);
+    println!("📱 Scan with the Cowcow companion app");
+    println!("  Server: {}", config.api.endpoint);
+    if let Some(campaign_id) = &enrollment.campaign_id {
+        println!("  Campaign: {campaign_id}");
     }
+    println!("  Scope: {}", enrollment.scope);
+    println!(
+        "  Expires: {}",
+        enrollment
+            .expires_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+    println!("  Pairing ID: {}", enrollment.id);
 
-    let recordings = query_builder
-        .fetch_all(db)
-        .await
-        .context("Failed to fetch recordings")?;
+    if let Some(path) = svg {
+        pair::save_qr_svg(&uri, path)?;
+        println!("🖼️  QR code saved to {}", path.display());
+    }
+    Ok(())
+}
 
-    // Filter by QC metrics
-    let mut filtered_recordings = Vec::new();
-    for recording in recordings {
-        let qc_metrics: serde_json::Value =
-            serde_json::from_str(&recording.3).context("Failed to parse QC metrics")?;
+async fn handle_pair_command(
+    command: PairCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    let auth_client = AuthClient::new(config.clone());
+    match command {
+        PairCommands::List => {
+            // Learn which codes have been scanned while online
+            match auth_client.get_enrollments().await {
+                Ok(enrollments) => pair::store_enrollments(db, &enrollments).await?,
+                Err(e) => warn!("Failed to sync pairings: {}", e),
+            }
 
-        let snr = qc_metrics
-            .get("snr_db")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0) as f32;
-        let clipping = qc_metrics
-            .get("clipping_pct")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(100.0) as f32;
-        let vad = qc_metrics
-            .get("vad_ratio")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0) as f32;
+            let devices = pair::list_paired(db).await?;
+            println!("📱 Paired devices:");
+            if devices.is_empty() {
+                println!("  None yet. Run: cowcow pair");
+            }
 
-        // Apply QC filters
-        if let Some(min_snr_val) = config.min_snr {
-            if snr < min_snr_val {
-                continue;
+            let now = chrono::Utc::now().timestamp();
+            for device in devices {
+                println!(
+                    "  {} | {:<8} | {} | {}{}",
+                    device.id,
+                    device.status(now),
+                    device.scope,
+                    device
+                        .device_name
+                        .as_deref()
+                        .or(device.label.as_deref())
+                        .unwrap_or("-"),
+                    device
+                        .campaign_id
+                        .as_ref()
+                        .map(|campaign| format!(" | campaign {campaign}"))
+                        .unwrap_or_default()
+                );
             }
         }
-
-        if let Some(max_clipping_val) = config.max_clipping {
-            if clipping > max_clipping_val {
-                continue;
+        PairCommands::Revoke { id } => {
+            auth_client.require_scope(Scope::Admin, "Revoking devices")?;
+            auth_client.revoke_enrollment(&id).await?;
+            if !pair::mark_revoked(db, &id).await? {
+                warn!("Pairing {} was not issued from this machine", id);
             }
+            println!("✅ Pairing {id} revoked");
         }
+    }
 
-        if let Some(min_vad_val) = config.min_vad {
-            if vad < min_vad_val {
-                continue;
+    Ok(())
+}
+
+async fn handle_speakers_command(
+    command: SpeakersCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        SpeakersCommands::Add { name, minor } => {
+            let speaker = speakers::add_speaker(db, &name, minor).await?;
+            println!("✅ Speaker added: {} ({})", speaker.name, speaker.id);
+            if speaker.is_minor {
+                println!("   Guardian consent is required before recordings can be uploaded.");
+                println!("   Run: cowcow speakers consent {}", speaker.id);
             }
         }
+        SpeakersCommands::List => {
+            let profiles = speakers::list_speakers(db).await?;
+            println!("🗣️  Speakers:");
 
-        filtered_recordings.push(recording);
-    }
+            if profiles.is_empty() {
+                println!("  No speakers found.");
+            }
 
-    if filtered_recordings.is_empty() {
-        println!("No recordings found matching the specified criteria.");
-        return Ok(());
-    }
+            let now = chrono::Utc::now().timestamp();
+            for speaker in profiles {
+                let consent_status = if !speaker.is_minor {
+                    "adult".to_string()
+                } else {
+                    match speakers::latest_guardian_consent(db, &speaker.id).await? {
+                        Some(consent) if consent.is_valid_at(now) => {
+                            let expires = chrono::DateTime::from_timestamp(consent.expires_at, 0)
+                                .unwrap_or_default();
+                            format!(
+                                "minor, consent by {} until {}",
+                                consent.guardian_name,
+                                expires.format("%Y-%m-%d")
+                            )
+                        }
+                        Some(consent) if consent.revoked_at.is_some() => {
+                            "minor, consent revoked".to_string()
+                        }
+                        Some(_) => "minor, consent expired".to_string(),
+                        None => "minor, no consent".to_string(),
+                    }
+                };
+                let (status, consent) = speakers::consent_status(db, config, &speaker.id).await?;
+                let form_status = match (status, consent) {
+                    (speakers::ConsentStatus::Valid, Some(consent)) => {
+                        let granted = chrono::DateTime::from_timestamp(consent.granted_at, 0)
+                            .unwrap_or_default();
+                        format!(
+                            "form {} agreed {}",
+                            consent.form_version,
+                            granted.format("%Y-%m-%d")
+                        )
+                    }
+                    (speakers::ConsentStatus::Outdated, Some(consent)) => {
+                        format!("form {} outdated", consent.form_version)
+                    }
+                    (status, _) => format!("form consent {}", status.as_str().replace('_', " ")),
+                };
+                println!(
+                    "  {} | {} | {} | {}",
+                    speaker.id, speaker.name, consent_status, form_status
+                );
+            }
+        }
+        SpeakersCommands::Consent {
+            speaker_id,
+            audio_file,
+            valid_days,
+        } => {
+            let speaker = speakers::get_speaker(db, &speaker_id)
+                .await?
+                .with_context(|| format!("Unknown speaker: {speaker_id}"))?;
+
+            if !speaker.is_minor {
+                println!(
+                    "ℹ️  {} is not marked as a minor; guardian consent is not required.",
+                    speaker.name
+                );
+                return Ok(());
+            }
 
-    println!(
-        "Found {} recordings matching criteria",
-        filtered_recordings.len()
-    );
+            if let Some(path) = &audio_file {
+                if !path.exists() {
+                    return Err(anyhow::anyhow!(
+                        "Consent recording not found: {}",
+                        path.display()
+                    ));
+                }
+            }
 
-    // Export based on format
-    match config.format.as_str() {
-        "json" => {
-            export_json(&filtered_recordings, &config.dest).await?;
+            let guardian_name = speakers::prompt_for_guardian_confirmation(&speaker)?;
+            let consent = speakers::record_guardian_consent(
+                db,
+                config,
+                &speaker,
+                &guardian_name,
+                audio_file.as_deref(),
+                valid_days,
+            )
+            .await?;
+
+            let expires =
+                chrono::DateTime::from_timestamp(consent.expires_at, 0).unwrap_or_default();
+            println!(
+                "✅ Guardian consent recorded ({}) for {} until {}",
+                consent.method,
+                speaker.name,
+                expires.format("%Y-%m-%d")
+            );
         }
-        "wav" => {
-            export_wav(&filtered_recordings, &config.dest).await?;
+        SpeakersCommands::Revoke { speaker_id } => {
+            let revoked = speakers::revoke_guardian_consent(db, &speaker_id).await?;
+            if revoked > 0 {
+                println!("✅ Guardian consent revoked for {speaker_id}");
+            } else {
+                println!("ℹ️  No active guardian consent found for {speaker_id}");
+            }
         }
-        "both" => {
-            export_json(&filtered_recordings, &config.dest).await?;
-            export_wav(&filtered_recordings, &config.dest).await?;
+        SpeakersCommands::Agree {
+            speaker_id,
+            form_version,
+            audio_file,
+            record_secs,
+        } => {
+            let speaker = speakers::get_speaker(db, &speaker_id)
+                .await?
+                .with_context(|| format!("Unknown speaker: {speaker_id}"))?;
+            let form_version = form_version
+                .or_else(|| config.record.consent_form_version.clone())
+                .context(
+                    "No consent form version: pass --form-version or set record.consent_form_version",
+                )?;
+            if let Some(path) = &audio_file {
+                if !path.exists() {
+                    return Err(anyhow::anyhow!(
+                        "Consent recording not found: {}",
+                        path.display()
+                    ));
+                }
+            }
+
+            let recorded = match record_secs {
+                Some(secs) => {
+                    Some(record_consent_clip(&speaker, &form_version, secs, config).await?)
+                }
+                None => {
+                    if audio_file.is_none()
+                        && !confirm(&format!(
+                            "Does {} agree to consent form {}?",
+                            speaker.name, form_version
+                        ))?
+                    {
+                        println!("🚫 Consent not recorded");
+                        return Ok(());
+                    }
+                    None
+                }
+            };
+            let clip = recorded.as_deref().or(audio_file.as_deref());
+            let consent =
+                speakers::record_speaker_consent(db, config, &speaker, &form_version, clip).await;
+            if let Some(recorded) = &recorded {
+                let _ = std::fs::remove_file(recorded);
+            }
+            let consent = consent?;
+            println!(
+                "✅ Consent to form {} recorded ({}) for {}",
+                consent.form_version, consent.method, speaker.name
+            );
         }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid format. Use 'json', 'wav', or 'both'"
-            ));
+        SpeakersCommands::Withdraw { speaker_id } => {
+            let withdrawn = speakers::withdraw_speaker_consent(db, &speaker_id).await?;
+            if withdrawn > 0 {
+                println!(
+                    "✅ Consent withdrawn for {speaker_id}; their recordings will not be uploaded"
+                );
+            } else {
+                println!("ℹ️  No active consent found for {speaker_id}");
+            }
         }
     }
 
-    println!("✅ Export completed to: {}", config.dest.display());
     Ok(())
 }
 
-async fn export_json(recordings: &[RecordingRow], dest: &Path) -> Result<()> {
-    use std::fs::File;
-    use std::io::Write;
-
-    let json_path = dest.join("recordings.json");
-    let mut file = File::create(&json_path).context("Failed to create JSON file")?;
-
-    writeln!(file, "[")?;
+async fn handle_storage_command(
+    command: StorageCommands,
+    db: &SqlitePool,
+    config: &Config,
+) -> Result<()> {
+    match command {
+        StorageCommands::Status => {
+            let available = storage::recordings_dir_available(config);
+            println!("💾 Recordings Storage:");
+            println!(
+                "  Recordings directory: {}",
+                config.recordings_dir().display()
+            );
+            println!(
+                "  Status: {}",
+                if available {
+                    "✅ available"
+                } else {
+                    "❌ not mounted"
+                }
+            );
+            println!("  Spool directory: {}", config.spool_dir().display());
+            println!("  Spooled recordings: {}", storage::spooled_count(config));
+        }
+        StorageCommands::Migrate => {
+            if config.storage.recordings_dir.is_none() {
+                println!("ℹ️  No external recordings directory configured");
+                println!("   Run: cowcow config set storage.recordings_dir <path>");
+                return Ok(());
+            }
 
-    for (i, recording) in recordings.iter().enumerate() {
-        let qc_metrics: serde_json::Value = serde_json::from_str(&recording.3)?;
+            let summary = storage::migrate_spool(db, config).await?;
+            println!(
+                "✅ Moved {} spooled recording(s) to {}",
+                summary.moved,
+                config.recordings_dir().display()
+            );
+            if summary.failed > 0 {
+                println!("⚠️  {} recording(s) could not be moved", summary.failed);
+            }
+        }
+        StorageCommands::Gc {
+            dry_run,
+            min_age_hours,
+            dirs,
+        } => {
+            let min_age = Duration::from_secs(min_age_hours * 3600);
+            let garbage = gc::find_garbage(db, config, &dirs, min_age).await?;
+            if garbage.is_empty() {
+                println!("✅ No leftover files found");
+                return Ok(());
+            }
 
-        let record = serde_json::json!({
-            "id": recording.0,
-            "lang": recording.1,
-            "prompt": recording.2,
-            "qc_metrics": qc_metrics,
-            "created_at": recording.4,
-            "uploaded_at": recording.5,
-            "wav_path": recording.6
-        });
+            for item in &garbage {
+                println!(
+                    "  {} ({}, {})",
+                    item.path.display(),
+                    item.kind,
+                    gc::format_bytes(item.bytes)
+                );
+            }
+            let total: u64 = garbage.iter().map(|item| item.bytes).sum();
+            if dry_run {
+                println!(
+                    "🧹 {} files ({}) would be deleted; run without --dry-run to delete them",
+                    garbage.len(),
+                    gc::format_bytes(total)
+                );
+                return Ok(());
+            }
 
-        if i == recordings.len() - 1 {
-            writeln!(file, "  {}", serde_json::to_string_pretty(&record)?)?;
-        } else {
-            writeln!(file, "  {},", serde_json::to_string_pretty(&record)?)?;
+            let summary = gc::remove(&garbage);
+            println!(
+                "🧹 Deleted {} files, reclaiming {}",
+                summary.removed,
+                gc::format_bytes(summary.bytes)
+            );
+            if summary.failed > 0 {
+                println!("⚠️  {} file(s) could not be deleted", summary.failed);
+            }
         }
     }
 
-    writeln!(file, "]")?;
-    println!("📄 JSON export: {}", json_path.display());
     Ok(())
 }
 
-async fn export_wav(recordings: &[RecordingRow], dest: &Path) -> Result<()> {
-    use std::fs;
+async fn self_update(check: bool, rollback: bool, yes: bool, config: &Config) -> Result<()> {
+    if rollback {
+        update::rollback()?;
+        println!("✅ Restored the previous version");
+        return Ok(());
+    }
 
-    let wav_dir = dest.join("recordings");
-    fs::create_dir_all(&wav_dir).context("Failed to create WAV directory")?;
+    let updater = update::Updater::new(config.clone());
+    println!("🔍 Checking {}", config.update.release_url);
+    let manifest = updater.fetch_manifest().await?;
 
-    let mut copied_files = 0;
+    let Some(asset) = updater.newer_release(&manifest)? else {
+        println!("✅ cowcow {} is up to date", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    };
 
-    for recording in recordings {
-        let source_path = Path::new(&recording.6);
-        if source_path.exists() {
-            let filename = format!("{}_{}.wav", recording.1, recording.0);
-            let dest_path = wav_dir.join(&filename);
+    println!(
+        "⬆️  cowcow {} is available (installed: {})",
+        manifest.version,
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some(notes) = &manifest.notes {
+        println!("{notes}");
+    }
+    if check {
+        return Ok(());
+    }
 
-            fs::copy(source_path, &dest_path).context("Failed to copy WAV file")?;
-            copied_files += 1;
+    if !yes {
+        use std::io::{self, Write};
+        print!("Install now? [y/N]: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            println!("Update cancelled");
+            return Ok(());
         }
     }
 
+    let binary = updater.download_verified(&manifest.version, asset).await?;
+    update::install(&binary)?;
+    println!("✅ Updated to cowcow {}", manifest.version);
     println!(
-        "🎵 WAV export: {} files copied to {}",
-        copied_files,
-        wav_dir.display()
+        "   Previous version kept at {} (undo with: cowcow self-update --rollback)",
+        update::backup_path()?.display()
     );
+
     Ok(())
 }
 
-async fn handle_auth_command(command: AuthCommands, config: &Config) -> Result<()> {
-    let auth_client = AuthClient::new(config.clone());
-
+async fn handle_telemetry_command(command: TelemetryCommands, config: &Config) -> Result<()> {
     match command {
-        AuthCommands::Login => {
-            let (username, password) = prompt_for_credentials()?;
-            match auth_client.login(username, password).await {
-                Ok(_) => println!("✅ Login successful!"),
-                Err(e) => println!("❌ Login failed: {e}"),
-            }
-        }
-        AuthCommands::Register => {
-            let (username, email, password) = prompt_for_registration()?;
-            match auth_client.register(username, email, password).await {
-                Ok(_) => println!("✅ Registration successful! You can now login."),
-                Err(e) => println!("❌ Registration failed: {e}"),
+        TelemetryCommands::Enable => {
+            use std::io::Write;
+
+            println!("📊 Anonymous usage telemetry");
+            println!("  Collected: command names (no arguments), success or failure,");
+            println!("  a coarse error class, cowcow version, OS, CPU count and audio settings.");
+            println!("  Never collected: names, prompts, file paths, recordings or credentials.");
+            println!(
+                "  Events are kept in {} and sent to {} when you upload recordings.",
+                telemetry::buffer_path(config).display(),
+                config.telemetry_endpoint()
+            );
+            print!("Share anonymous usage data? [y/N]: ");
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                println!("Telemetry left disabled");
+                return Ok(());
             }
+
+            let mut config = config.clone();
+            config.telemetry.enabled = true;
+            config.telemetry.install_id = Some(Uuid::new_v4().to_string());
+            config.save()?;
+            println!("✅ Telemetry enabled, thank you!");
         }
-        AuthCommands::Logout => {
-            auth_client.logout().await?;
-            println!("✅ Logged out successfully");
+        TelemetryCommands::Disable => {
+            let mut config = config.clone();
+            config.telemetry.enabled = false;
+            config.telemetry.install_id = None;
+            config.save()?;
+            telemetry::clear(&config)?;
+            println!("✅ Telemetry disabled and buffered events deleted");
         }
-        AuthCommands::Status => match auth_client.check_auth().await {
-            Ok(creds) => {
-                println!("✅ Authenticated");
-                if let Some(username) = creds.username {
-                    println!("  Username: {username}");
-                }
-                if let Some(expires_at) = creds.expires_at {
-                    let expires =
-                        chrono::DateTime::from_timestamp(expires_at as i64, 0).unwrap_or_default();
-                    println!("  Expires: {}", expires.format("%Y-%m-%d %H:%M:%S"));
+        TelemetryCommands::Status => {
+            println!(
+                "📊 Telemetry: {}",
+                if config.telemetry.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
                 }
+            );
+            println!("  Endpoint: {}", config.telemetry_endpoint());
+            println!(
+                "  Buffered events: {}",
+                telemetry::pending_events(config)?.len()
+            );
+        }
+        TelemetryCommands::Show => {
+            let events = telemetry::pending_events(config)?;
+            if events.is_empty() {
+                println!("No buffered telemetry events");
+            } else {
+                println!("{}", serde_json::to_string_pretty(&events)?);
             }
-            Err(_) => println!("❌ Not authenticated"),
-        },
+        }
+        TelemetryCommands::Upload => {
+            let sent = telemetry::upload(config).await?;
+            println!("✅ Sent {sent} telemetry event(s)");
+        }
     }
 
     Ok(())
 }
 
-async fn handle_config_command(command: ConfigCommands, config: &Config) -> Result<()> {
+async fn handle_qc_command(command: QcCommands, config: &Config) -> Result<()> {
     match command {
-        ConfigCommands::Show => {
-            let config_toml = toml::to_string_pretty(config)?;
-            println!("📁 Current Configuration:");
-            println!("{config_toml}");
-        }
-        ConfigCommands::Set { key, value } => {
-            let mut config_copy = config.clone();
-            match config_copy.set_value(&key, &value) {
-                Ok(_) => {
-                    config_copy.save()?;
-                    println!("✅ Configuration updated: {key} = {value}");
+        QcCommands::Bench {
+            corpus,
+            output,
+            min_precision,
+            min_recall,
+        } => {
+            let report = bench::run_bench(&corpus, config)?;
+
+            if output == "json" {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("🧪 QC Benchmark: {}", corpus.display());
+                for (label, summary) in &report.labels {
+                    println!(
+                        "  {:<12} {:>4} files | accepted {:>4} | rejected {:>4}",
+                        label, summary.total, summary.accepted, summary.rejected
+                    );
                 }
-                Err(e) => {
-                    println!("❌ Failed to set configuration: {e}");
-                    println!("Available keys:");
-                    for available_key in Config::get_available_keys() {
-                        println!("  - {available_key}");
+                println!(
+                    "  Accepts: {} correct, {} wrong | Rejects: {} correct, {} wrong",
+                    report.true_accepts,
+                    report.false_accepts,
+                    report.true_rejects,
+                    report.false_rejects
+                );
+                println!("  Precision: {:.3}", report.precision);
+                println!("  Recall: {:.3}", report.recall);
+
+                if !report.mismatches.is_empty() {
+                    println!("\nMismatches:");
+                    for mismatch in &report.mismatches {
+                        println!(
+                            "  {} [{}] {}{}",
+                            mismatch.path.display(),
+                            mismatch.label,
+                            if mismatch.accepted {
+                                "accepted"
+                            } else {
+                                "rejected"
+                            },
+                            mismatch
+                                .reason
+                                .as_ref()
+                                .map(|r| format!(" ({r})"))
+                                .unwrap_or_default()
+                        );
                     }
                 }
+                for error in &report.errors {
+                    println!("  ⚠️  {error}");
+                }
+            }
+
+            if min_precision.is_some_and(|min| report.precision < min) {
+                return Err(anyhow::anyhow!(
+                    "Precision {:.3} is below the required minimum",
+                    report.precision
+                ));
+            }
+            if min_recall.is_some_and(|min| report.recall < min) {
+                return Err(anyhow::anyhow!(
+                    "Recall {:.3} is below the required minimum",
+                    report.recall
+                ));
             }
         }
-        ConfigCommands::Reset => {
-            let default_config = Config::default();
-            default_config.save()?;
-            println!("✅ Configuration reset to defaults");
+        QcCommands::Simulate {
+            preset,
+            thresholds,
+            days,
+            output,
+        } => {
+            let current = config.qc_policy();
+            let candidate = simulate::candidate_policy(preset.as_deref(), &thresholds, &current)?;
+            let db = init_db(config).await?;
+            let report = simulate::simulate(&db, days, &current, candidate).await?;
+
+            if output == "json" {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                simulate::print_report(&report, preset.as_deref().unwrap_or("configured policy"));
+            }
         }
     }
 
     Ok(())
 }
 
-async fn handle_tokens_command(command: TokensCommands, config: &Config) -> Result<()> {
-    let auth_client = AuthClient::new(config.clone());
-
-    match command {
-        TokensCommands::Balance => {
-            let balance = auth_client.get_token_balance().await?;
-            println!("💰 Token Balance Summary:");
-            println!("  Current Balance: {} tokens", balance.balance);
-            println!("  Total Earned: {} tokens", balance.total_earned);
-            println!("  Total Spent: {} tokens", balance.total_spent);
-        }
-        TokensCommands::History { days } => {
-            let history = auth_client.get_token_history(days).await?;
-            println!("📜 Token Transaction History (last {days} days):");
-
-            if history.is_empty() {
-                println!("  No transactions found.");
-            } else {
-                for tx in history {
-                    println!(
-                        "  {} | {} | {:+} tokens | Balance: {} | {}",
-                        tx.date.format("%Y-%m-%d %H:%M:%S"),
-                        tx.transaction_type,
-                        tx.amount,
-                        tx.balance,
-                        tx.notes
-                    );
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    #[test]
+    fn test_read_only_commands_exist() {
+        for path in READ_ONLY_COMMANDS {
+            let mut command = Cli::command();
+            for name in path.split(' ') {
+                command = command
+                    .find_subcommand(name)
+                    .unwrap_or_else(|| panic!("`{path}` is not a command"))
+                    .clone();
             }
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_command_path_names_subcommands_only() {
+        let matches = Cli::command()
+            .try_get_matches_from(["cowcow", "qc", "simulate", "--days", "30"])
+            .unwrap();
+        assert_eq!(command_path(&matches), "qc simulate");
+    }
+
+    #[tokio::test]
+    async fn test_ensure_column_is_idempotent() {
+        let library = TestLibrary::new().await;
+        library.add_recording("r1", "").await;
+
+        ensure_column(&library.db, "recordings", "extra", "TEXT")
+            .await
+            .unwrap();
+        ensure_column(&library.db, "recordings", "extra", "TEXT")
+            .await
+            .unwrap();
+        let columns: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('recordings') WHERE name = 'extra'",
+        )
+        .fetch_one(&library.db)
+        .await
+        .unwrap();
+        assert_eq!(columns, 1);
+        library.db.close().await;
+
+        // Upgrading the schema again on the next start keeps the data
+        let db = init_db(&library.config).await.unwrap();
+        let (review_status,): (String,) =
+            sqlx::query_as("SELECT review_status FROM recordings WHERE id = 'r1'")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(review_status, "unreviewed");
+        db.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_read_only_open_refuses_writes() {
+        let library = TestLibrary::new().await;
+        library.add_recording("r1", "").await;
+        library.db.close().await;
+
+        for immutable in [false, true] {
+            let db = open_db_read_only(&library.config, immutable).await.unwrap();
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM recordings")
+                .fetch_one(&db)
+                .await
+                .unwrap();
+            assert_eq!(count, 1);
+            assert!(sqlx::query("DELETE FROM recordings")
+                .execute(&db)
+                .await
+                .is_err());
+            db.close().await;
+        }
+    }
 }
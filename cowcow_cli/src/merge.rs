@@ -0,0 +1,456 @@
+use anyhow::{Context, Result};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+use cowcow_core::decode::DECODABLE_EXTENSIONS;
+use cowcow_core::timeline::QcTimeline;
+
+use crate::alignment::CaptureAlignment;
+use crate::config::Config;
+use crate::dedupe;
+use crate::diff::{self, ManifestEntry};
+use crate::storage;
+
+/// How a bundle recording was merged, as stored in `merge_provenance`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// Copied in under its own ID
+    Added,
+    /// Copied in under a new ID, since another recording already had its ID
+    Renamed,
+    /// Same audio as a recording already in the library; not copied
+    Duplicate,
+}
+
+impl MergeOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeOutcome::Added => "added",
+            MergeOutcome::Renamed => "renamed",
+            MergeOutcome::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// Totals of merging one bundle
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    pub added: usize,
+    pub renamed: usize,
+    pub duplicates: usize,
+    /// Duplicates that had been uploaded from the other device, so the local
+    /// copy is no longer uploaded
+    pub upload_settled: usize,
+    /// Recordings merged from this bundle before
+    pub already_merged: usize,
+    pub missing_audio: usize,
+}
+
+/// Merge an export bundle from another device (a directory written by
+/// `cowcow export --format both`) into the local library
+///
+/// Recordings whose audio matches one already in the library, from any
+/// device, are not copied: the existing recording stays the canonical copy,
+/// and is marked uploaded if the bundle's copy was, so the clip is neither
+/// uploaded nor rewarded twice. A bundle recording whose ID is taken by
+/// different audio gets a new ID. Where each bundle recording ended up is
+/// kept in `merge_provenance`, keyed by the hash of the bundle's manifest
+/// (bundles from different devices may well share a directory name), so
+/// merging the same bundle again changes nothing.
+///
+/// The bundle is merged in one transaction: if anything fails, no recording
+/// of it is added and the audio copied so far is removed again.
+pub async fn merge_bundle(db: &SqlitePool, config: &Config, bundle: &Path) -> Result<MergeSummary> {
+    let manifest_path = bundle.join("recordings.json");
+    let manifest = diff::load_manifest(&manifest_path).await?;
+    let source = diff::content_hash(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+
+    let canonical = local_content_hashes(db, config).await?;
+    let mut copied = Vec::new();
+    let mut tx = db.begin().await?;
+    let merged = merge_entries(
+        &mut tx,
+        config,
+        bundle,
+        &source,
+        &manifest,
+        canonical,
+        &mut copied,
+    )
+    .await;
+    let merged = match merged {
+        Ok(summary) => tx
+            .commit()
+            .await
+            .context("Failed to merge bundle")
+            .map(|_| summary),
+        Err(e) => Err(e),
+    };
+    if merged.is_err() {
+        for path in &copied {
+            let _ = fs::remove_file(path);
+        }
+    }
+    merged
+}
+
+async fn merge_entries(
+    db: &mut SqliteConnection,
+    config: &Config,
+    bundle: &Path,
+    source: &str,
+    manifest: &BTreeMap<String, ManifestEntry>,
+    mut canonical: HashMap<String, String>,
+    copied: &mut Vec<PathBuf>,
+) -> Result<MergeSummary> {
+    let location = storage::recordings_location(config)?;
+
+    let mut entries: Vec<&ManifestEntry> = manifest.values().collect();
+    entries.sort_by_key(|entry| entry.created_at);
+
+    let mut summary = MergeSummary::default();
+    for entry in entries {
+        let merged: Option<(String,)> = sqlx::query_as(
+            "SELECT recording_id FROM merge_provenance WHERE source = ? AND source_id = ?",
+        )
+        .bind(source)
+        .bind(&entry.id)
+        .fetch_optional(&mut *db)
+        .await
+        .context("Failed to check merge provenance")?;
+        if merged.is_some() {
+            summary.already_merged += 1;
+            continue;
+        }
+
+        let Some(audio) = bundle_audio(bundle, entry) else {
+            warn!("No audio for {} in {}", entry.id, bundle.display());
+            summary.missing_audio += 1;
+            continue;
+        };
+        let hash = diff::content_hash(&audio)
+            .with_context(|| format!("Failed to read {}", audio.display()))?;
+
+        let (recording_id, outcome) = match canonical.get(&hash) {
+            Some(existing) => {
+                let existing = existing.clone();
+                if let Some(uploaded_at) = entry.uploaded_at {
+                    if settle_upload(&mut *db, &existing, uploaded_at).await? {
+                        summary.upload_settled += 1;
+                    }
+                }
+                summary.duplicates += 1;
+                (existing, MergeOutcome::Duplicate)
+            }
+            None => {
+                let taken: Option<(String,)> =
+                    sqlx::query_as("SELECT id FROM recordings WHERE id = ?")
+                        .bind(&entry.id)
+                        .fetch_optional(&mut *db)
+                        .await?;
+                let (recording_id, outcome) = match taken {
+                    Some(_) => (Uuid::new_v4().to_string(), MergeOutcome::Renamed),
+                    None => (entry.id.clone(), MergeOutcome::Added),
+                };
+
+                let output_dir = location.path().join(&entry.lang);
+                fs::create_dir_all(&output_dir)?;
+                // Kept in the format it was exported in
+                let extension = audio.extension().unwrap_or_default().to_string_lossy();
+                let wav_path = output_dir.join(format!("{recording_id}.{extension}"));
+                copy_file(&audio, &wav_path, copied)
+                    .with_context(|| format!("Failed to copy {}", audio.display()))?;
+                let timeline = QcTimeline::sidecar_path(&audio);
+                if timeline.exists() {
+                    copy_file(&timeline, &QcTimeline::sidecar_path(&wav_path), copied)
+                        .context("Failed to copy QC timeline")?;
+                }
+                // The capture timing carries the capture start time across
+                let capture = CaptureAlignment::sidecar_path(&audio);
+                let capture_started_at_ms = match CaptureAlignment::load(&capture) {
+                    Ok(alignment) => {
+                        copy_file(&capture, &CaptureAlignment::sidecar_path(&wav_path), copied)
+                            .context("Failed to copy capture timing")?;
+                        Some(alignment.capture_started_at_ms)
+                    }
+                    Err(_) => None,
+                };
+
+                let qc_report = config
+                    .qc_policy_for(&entry.qc_metrics)
+                    .evaluate_json(&entry.qc_metrics);
+                sqlx::query(
+                    r#"
+                    INSERT INTO recordings
+                        (id, lang, prompt, qc_metrics, qc_report, created_at, uploaded_at, wav_path, content_hash,
+                         capture_started_at_ms)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&recording_id)
+                .bind(&entry.lang)
+                .bind(&entry.prompt)
+                .bind(entry.qc_metrics.to_string())
+                .bind(serde_json::to_string(&qc_report)?)
+                .bind(entry.created_at)
+                .bind(entry.uploaded_at)
+                .bind(wav_path.to_string_lossy())
+                .bind(&hash)
+                .bind(capture_started_at_ms)
+                .execute(&mut *db)
+                .await
+                .context("Failed to save merged recording")?;
+
+                if entry.uploaded_at.is_none() {
+                    sqlx::query(
+                        "INSERT INTO upload_queue (recording_id, attempts, last_attempt) VALUES (?, 0, 0)",
+                    )
+                    .bind(&recording_id)
+                    .execute(&mut *db)
+                    .await?;
+                }
+                dedupe::store_fingerprint(&mut *db, &recording_id, &wav_path).await?;
+
+                match outcome {
+                    MergeOutcome::Renamed => summary.renamed += 1,
+                    _ => summary.added += 1,
+                }
+                canonical.insert(hash.clone(), recording_id.clone());
+                (recording_id, outcome)
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO merge_provenance
+                (source, source_id, recording_id, content_hash, outcome, merged_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(source)
+        .bind(&entry.id)
+        .bind(&recording_id)
+        .bind(&hash)
+        .bind(outcome.as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&mut *db)
+        .await
+        .context("Failed to record merge provenance")?;
+    }
+
+    Ok(summary)
+}
+
+/// A bundle recording's audio, `recordings/<lang>_<id>.<ext>` in whichever
+/// format the bundle was exported in
+fn bundle_audio(bundle: &Path, entry: &ManifestEntry) -> Option<PathBuf> {
+    DECODABLE_EXTENSIONS
+        .iter()
+        .map(|extension| {
+            bundle
+                .join("recordings")
+                .join(format!("{}_{}.{extension}", entry.lang, entry.id))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Copy `from` to `to`, noting `to` so a failed merge can remove it
+fn copy_file(from: &Path, to: &Path, copied: &mut Vec<PathBuf>) -> Result<()> {
+    fs::copy(from, to)?;
+    copied.push(to.to_path_buf());
+    Ok(())
+}
+
+/// Content hash of every local recording still on disk, hashing (and
+/// storing) any not hashed yet
+async fn local_content_hashes(db: &SqlitePool, config: &Config) -> Result<HashMap<String, String>> {
+    let rows: Vec<(String, String, Option<String>)> =
+        sqlx::query_as("SELECT id, wav_path, content_hash FROM recordings ORDER BY created_at")
+            .fetch_all(db)
+            .await
+            .context("Failed to fetch recordings")?;
+
+    let mut hashes = HashMap::new();
+    for (id, wav_path, stored) in rows {
+        let hash = match stored {
+            Some(hash) => hash,
+            None => {
+                let Some(hash) = diff::content_hash(&config.locate_audio(&wav_path)) else {
+                    continue;
+                };
+                sqlx::query("UPDATE recordings SET content_hash = ? WHERE id = ?")
+                    .bind(&hash)
+                    .bind(&id)
+                    .execute(db)
+                    .await
+                    .context("Failed to store content hash")?;
+                hash
+            }
+        };
+        // The earliest recording of a clip is its canonical copy
+        hashes.entry(hash).or_insert(id);
+    }
+    Ok(hashes)
+}
+
+/// Mark a pending recording as uploaded by another device; returns whether
+/// it was still pending
+async fn settle_upload(
+    db: &mut SqliteConnection,
+    recording_id: &str,
+    uploaded_at: i64,
+) -> Result<bool> {
+    let settled =
+        sqlx::query("UPDATE recordings SET uploaded_at = ? WHERE id = ? AND uploaded_at IS NULL")
+            .bind(uploaded_at)
+            .bind(recording_id)
+            .execute(&mut *db)
+            .await
+            .context("Failed to mark recording uploaded")?
+            .rows_affected()
+            > 0;
+
+    if settled {
+        sqlx::query("DELETE FROM upload_queue WHERE recording_id = ?")
+            .bind(recording_id)
+            .execute(db)
+            .await
+            .context("Failed to remove from upload queue")?;
+    }
+    Ok(settled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    /// A bundle directory `bundles/<device>/<name>` holding `(id, lang, file
+    /// extension, audio)` recordings, each with a QC timeline
+    fn bundle(
+        library: &TestLibrary,
+        device: &str,
+        name: &str,
+        recordings: &[(&str, &str, &str, &[u8])],
+    ) -> PathBuf {
+        let bundle = library
+            .config
+            .storage
+            .data_dir
+            .join("bundles")
+            .join(device)
+            .join(name);
+        fs::create_dir_all(bundle.join("recordings")).unwrap();
+        let mut manifest = Vec::new();
+        for (index, (id, lang, extension, audio)) in recordings.iter().enumerate() {
+            let audio_path = bundle.join(format!("recordings/{lang}_{id}.{extension}"));
+            fs::write(&audio_path, audio).unwrap();
+            fs::write(QcTimeline::sidecar_path(&audio_path), "{}").unwrap();
+            manifest.push(serde_json::json!({
+                "id": id,
+                "lang": lang,
+                "prompt": "habari",
+                "qc_metrics": {},
+                "created_at": index,
+                "uploaded_at": null,
+                "wav_path": format!("/elsewhere/{lang}/{id}.{extension}"),
+            }));
+        }
+        fs::write(
+            bundle.join("recordings.json"),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+        bundle
+    }
+
+    async fn recordings(library: &TestLibrary) -> Vec<(String, String)> {
+        sqlx::query_as("SELECT id, wav_path FROM recordings ORDER BY id")
+            .fetch_all(&library.db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_merge_keeps_the_bundle_format_and_sidecars() {
+        let library = TestLibrary::new().await;
+        let bundle = bundle(
+            &library,
+            "phone",
+            "export",
+            &[("a", "sw", "flac", b"fLaC a")],
+        );
+
+        let summary = merge_bundle(&library.db, &library.config, &bundle)
+            .await
+            .unwrap();
+        assert_eq!((summary.added, summary.missing_audio), (1, 0));
+
+        let recordings = recordings(&library).await;
+        let wav_path = Path::new(&recordings[0].1);
+        assert_eq!(wav_path, library.config.recordings_dir().join("sw/a.flac"));
+        assert_eq!(fs::read(wav_path).unwrap(), b"fLaC a");
+        assert!(QcTimeline::sidecar_path(wav_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_merge_tells_bundles_apart_by_content() {
+        let library = TestLibrary::new().await;
+        let first = bundle(
+            &library,
+            "phone",
+            "export",
+            &[("a", "sw", "wav", b"RIFF a")],
+        );
+        let second = bundle(
+            &library,
+            "tablet",
+            "export",
+            &[("b", "sw", "wav", b"RIFF b")],
+        );
+
+        merge_bundle(&library.db, &library.config, &first)
+            .await
+            .unwrap();
+        let again = merge_bundle(&library.db, &library.config, &first)
+            .await
+            .unwrap();
+        assert_eq!((again.added, again.already_merged), (0, 1));
+
+        // Named like the first, but another device's bundle
+        let summary = merge_bundle(&library.db, &library.config, &second)
+            .await
+            .unwrap();
+        assert_eq!((summary.added, summary.already_merged), (1, 0));
+        assert_eq!(recordings(&library).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_merge_leaves_the_library_as_it_was() {
+        let library = TestLibrary::new().await;
+        let bundle = bundle(
+            &library,
+            "phone",
+            "export",
+            &[("a", "sw", "wav", b"RIFF a"), ("b", "zu", "wav", b"RIFF b")],
+        );
+        // The second recording's language directory cannot be created
+        fs::write(library.config.recordings_dir().join("zu"), "").unwrap();
+
+        assert!(merge_bundle(&library.db, &library.config, &bundle)
+            .await
+            .is_err());
+        assert!(recordings(&library).await.is_empty());
+        assert!(!library.config.recordings_dir().join("sw/a.wav").exists());
+        let provenance: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM merge_provenance")
+            .fetch_one(&library.db)
+            .await
+            .unwrap();
+        assert_eq!(provenance, 0);
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use cowcow_core::normalize::{loudness_metric, LOUDNESS_METRIC};
+use cowcow_core::QcMetrics;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::playback::Clip;
+
+/// Recording profile
+///
+/// Speech is QC'd against the configured thresholds. Music (singing,
+/// instruments, oral-tradition songs) would fail VAD-based rules, so it stops
+/// on level alone and is QC'd on loudness and clipping only.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordingMode {
+    #[default]
+    Speech,
+    Music,
+}
+
+impl RecordingMode {
+    /// QC metrics key marking the mode of non-speech recordings
+    pub const METRIC: &'static str = "recording_mode";
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RecordingMode::Speech => "speech",
+            RecordingMode::Music => "music",
+        }
+    }
+
+    /// Mode a recording's stored QC metrics were made in
+    pub fn of_metrics(metrics: &serde_json::Value) -> Self {
+        match metrics.get(Self::METRIC).and_then(|mode| mode.as_str()) {
+            Some("music") => RecordingMode::Music,
+            _ => RecordingMode::Speech,
+        }
+    }
+}
+
+impl fmt::Display for RecordingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RecordingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "speech" => Ok(RecordingMode::Speech),
+            "music" | "singing" => Ok(RecordingMode::Music),
+            _ => Err(format!("Unknown recording mode: {s} (use speech or music)")),
+        }
+    }
+}
+
+/// How a take starts capturing
+///
+/// `Enter` is the countdown (after Enter when there is a prompt). `Voice`
+/// listens and starts at the first speech, dropping the silence before it.
+/// `Ptt` records only while Space is held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartMode {
+    #[default]
+    Enter,
+    Voice,
+    Ptt,
+}
+
+impl fmt::Display for StartMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StartMode::Enter => "enter",
+            StartMode::Voice => "voice",
+            StartMode::Ptt => "ptt",
+        })
+    }
+}
+
+impl FromStr for StartMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "enter" | "countdown" => Ok(StartMode::Enter),
+            "voice" | "vad" => Ok(StartMode::Voice),
+            "ptt" | "push-to-talk" => Ok(StartMode::Ptt),
+            _ => Err(format!("Unknown start mode: {s} (use enter, voice or ptt)")),
+        }
+    }
+}
+
+/// Integrated loudness of the recording at `audio_path`, as QC'd in music
+/// mode
+pub fn measure_loudness(audio_path: &Path) -> Result<f32> {
+    let clip = Clip::load(audio_path)?;
+    Ok(loudness_metric(
+        &clip.samples,
+        clip.sample_rate,
+        clip.channels,
+    ))
+}
+
+/// QC metrics of a music recording: the audio metrics plus the mode and its
+/// integrated loudness
+pub fn music_metrics(metrics: &QcMetrics, loudness_lufs: f32) -> Result<serde_json::Value> {
+    let mut json = serde_json::to_value(metrics)?;
+    if let Some(object) = json.as_object_mut() {
+        object.insert(
+            RecordingMode::METRIC.to_string(),
+            RecordingMode::Music.as_str().into(),
+        );
+        object.insert(LOUDNESS_METRIC.to_string(), loudness_lufs.into());
+    }
+    Ok(json)
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use cowcow_core::outliers;
+use cowcow_core::prompt_analysis::SPEAKING_RATE_METRIC;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+
+/// QC metrics compared against the speaker and device's other recordings
+pub const OUTLIER_METRICS: [&str; 8] = [
+    "snr_db",
+    "clipping_pct",
+    "dc_offset",
+    "rumble_db",
+    "hum_db",
+    "glitch_pct",
+    "reverb_rt60_secs",
+    SPEAKING_RATE_METRIC,
+];
+
+/// Speaker and input device whose recordings form one baseline
+type Baseline = (Option<String>, Option<String>);
+
+/// Flag recordings whose QC metrics are outliers among the recordings of the
+/// same speaker on the same input device, storing the offending metrics in
+/// `recordings.outlier_metrics`; returns how many are flagged
+///
+/// Absolute QC thresholds cannot see a device that is quietly worse than
+/// usual, so each speaker and device combination is its own baseline.
+/// Archived recordings are left out of the baselines.
+pub async fn flag_outliers(db: &SqlitePool) -> Result<usize> {
+    Ok(flag(db, None).await?.len())
+}
+
+/// Flag outliers as [`flag_outliers`] does, but only among the recordings of
+/// the same speaker and device as recording `id`, the only baseline a new
+/// take changes; returns the metrics `id` is an outlier on, comma-separated
+pub async fn flag_take_outliers(db: &SqlitePool, id: &str) -> Result<Option<String>> {
+    let baseline: Option<Baseline> =
+        sqlx::query_as("SELECT speaker_id, device FROM recordings WHERE id = ?")
+            .bind(id)
+            .fetch_optional(db)
+            .await
+            .context("Failed to fetch recording")?;
+    let Some(baseline) = baseline else {
+        return Ok(None);
+    };
+    let flagged = flag(db, Some(&baseline)).await?;
+    Ok(flagged.get(id).map(|metrics| metrics.join(",")))
+}
+
+/// Recompute the flags of one baseline, or of all of them
+async fn flag(
+    db: &SqlitePool,
+    baseline: Option<&Baseline>,
+) -> Result<BTreeMap<String, Vec<&'static str>>> {
+    let (speaker_id, device) = baseline.cloned().unwrap_or_default();
+    let rows: Vec<(String, Option<String>, Option<String>, String)> = sqlx::query_as(
+        r#"
+        SELECT id, speaker_id, device, qc_metrics FROM recordings
+        WHERE archived = 0 AND (? OR (speaker_id IS ? AND device IS ?))
+        "#,
+    )
+    .bind(baseline.is_none())
+    .bind(&speaker_id)
+    .bind(&device)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    let mut groups: HashMap<Baseline, Vec<(String, serde_json::Value)>> = HashMap::new();
+    for (id, speaker_id, device, qc_metrics) in rows {
+        let metrics = serde_json::from_str(&qc_metrics).unwrap_or_default();
+        groups
+            .entry((speaker_id, device))
+            .or_default()
+            .push((id, metrics));
+    }
+
+    let mut flagged: BTreeMap<String, Vec<&'static str>> = BTreeMap::new();
+    for recordings in groups.values() {
+        for metric in OUTLIER_METRICS {
+            let (ids, values): (Vec<&String>, Vec<f32>) = recordings
+                .iter()
+                .filter_map(|(id, metrics)| Some((id, metrics.get(metric)?.as_f64()? as f32)))
+                .unzip();
+            for (id, outlier) in ids.into_iter().zip(outliers::outliers(&values)) {
+                if outlier {
+                    flagged.entry(id.clone()).or_default().push(metric);
+                }
+            }
+        }
+    }
+
+    // Recompute every flag, so recordings that now fit in are cleared
+    let mut tx = db.begin().await?;
+    sqlx::query(
+        "UPDATE recordings SET outlier_metrics = NULL \
+         WHERE ? OR (speaker_id IS ? AND device IS ?)",
+    )
+    .bind(baseline.is_none())
+    .bind(&speaker_id)
+    .bind(&device)
+    .execute(&mut *tx)
+    .await?;
+    for (id, metrics) in &flagged {
+        sqlx::query("UPDATE recordings SET outlier_metrics = ? WHERE id = ?")
+            .bind(metrics.join(","))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await.context("Failed to store outlier flags")?;
+
+    Ok(flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    async fn add(db: &SqlitePool, id: &str, speaker_id: &str, snr_db: f32, flags: Option<&str>) {
+        sqlx::query(
+            "INSERT INTO recordings (id, lang, prompt, qc_metrics, created_at, wav_path, \
+             speaker_id, device, outlier_metrics) VALUES (?, 'sw', 'habari', ?, 0, '', ?, 'mic', ?)",
+        )
+        .bind(id)
+        .bind(serde_json::json!({ "snr_db": snr_db }).to_string())
+        .bind(speaker_id)
+        .bind(flags)
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    async fn flags(db: &SqlitePool, id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT outlier_metrics FROM recordings WHERE id = ?")
+            .bind(id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_take_outliers_only_rescan_the_takes_baseline() {
+        let library = TestLibrary::new().await;
+
+        let snr = [24.0, 25.5, 26.0, 25.0, 23.5, 24.5, 25.0, 26.5];
+        for (i, snr_db) in snr.iter().enumerate() {
+            add(&library.db, &format!("a{i}"), "amina", *snr_db, None).await;
+            // A stale flag in another baseline
+            let stale = (i == 0).then_some("snr_db");
+            add(&library.db, &format!("b{i}"), "baraka", *snr_db, stale).await;
+        }
+        add(&library.db, "take", "amina", 5.0, None).await;
+
+        assert_eq!(
+            flag_take_outliers(&library.db, "take")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("snr_db")
+        );
+        assert_eq!(flags(&library.db, "a0").await, None);
+        assert_eq!(flags(&library.db, "b0").await.as_deref(), Some("snr_db"));
+        assert_eq!(
+            flag_take_outliers(&library.db, "missing").await.unwrap(),
+            None
+        );
+
+        // The full scan recomputes every baseline
+        assert_eq!(flag_outliers(&library.db).await.unwrap(), 1);
+        assert_eq!(flags(&library.db, "b0").await, None);
+        assert_eq!(flags(&library.db, "take").await.as_deref(), Some("snr_db"));
+    }
+}
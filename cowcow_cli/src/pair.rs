@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Scope;
+
+/// Link the companion app opens to provision itself
+pub const PAIRING_URI: &str = "cowcow://pair";
+
+/// What a new pairing grants the companion app
+#[derive(Debug, Clone, Serialize)]
+pub struct EnrollmentRequest {
+    pub scope: Scope,
+    /// Campaign (project) the device will record for
+    pub campaign_id: Option<String>,
+    /// How long the code can be scanned for
+    pub expires_in_secs: u64,
+    /// Name to recognize the device by, e.g. the enumerator's name
+    pub label: Option<String>,
+}
+
+/// A single-use enrollment token, as issued by the server
+///
+/// The companion app redeems the token for credentials of its own, limited
+/// to `scope`; the token itself is only returned when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrollment {
+    pub id: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    pub scope: Scope,
+    #[serde(default)]
+    pub campaign_id: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    /// When a device redeemed the token
+    #[serde(default)]
+    pub redeemed_at: Option<DateTime<Utc>>,
+    /// Model or name the device reported when redeeming
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+/// A pairing as tracked in the local database
+#[derive(Debug, Clone, Serialize)]
+pub struct PairedDevice {
+    pub id: String,
+    pub label: Option<String>,
+    pub scope: String,
+    pub campaign_id: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub redeemed_at: Option<i64>,
+    pub device_name: Option<String>,
+    pub revoked_at: Option<i64>,
+}
+
+impl PairedDevice {
+    pub fn status(&self, now: i64) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.redeemed_at.is_some() {
+            "paired"
+        } else if self.expires_at <= now {
+            "expired"
+        } else {
+            "waiting"
+        }
+    }
+}
+
+/// Pairing link carrying the server endpoint, project and enrollment token
+pub fn pairing_uri(endpoint: &str, enrollment: &Enrollment) -> Result<String> {
+    let token = enrollment
+        .token
+        .as_deref()
+        .context("Server did not return an enrollment token")?;
+
+    let mut uri = Url::parse(PAIRING_URI)?;
+    {
+        let mut query = uri.query_pairs_mut();
+        query.append_pair("endpoint", endpoint);
+        if let Some(campaign_id) = &enrollment.campaign_id {
+            query.append_pair("project", campaign_id);
+        }
+        query.append_pair("token", token);
+        query.append_pair("scope", enrollment.scope.as_str());
+        query.append_pair("expires", &enrollment.expires_at.timestamp().to_string());
+    }
+    Ok(uri.into())
+}
+
+/// QR code of `payload` drawn with half-block characters, light on dark so
+/// it scans from dark terminals too
+pub fn render_qr(payload: &str) -> Result<String> {
+    let code = QrCode::new(payload.as_bytes()).context("Pairing link is too long for a QR code")?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Save the QR code of `payload` as an SVG image, e.g. for printing
+pub fn save_qr_svg(payload: &str, path: &Path) -> Result<()> {
+    let code = QrCode::new(payload.as_bytes()).context("Pairing link is too long for a QR code")?;
+    let svg = code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(320, 320)
+        .build();
+    fs::write(path, svg).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Track a newly issued enrollment
+pub async fn record_enrollment(
+    db: &SqlitePool,
+    enrollment: &Enrollment,
+    label: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO paired_devices (id, label, scope, campaign_id, created_at, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&enrollment.id)
+    .bind(label)
+    .bind(enrollment.scope.as_str())
+    .bind(&enrollment.campaign_id)
+    .bind(Utc::now().timestamp())
+    .bind(enrollment.expires_at.timestamp())
+    .execute(db)
+    .await
+    .context("Failed to store pairing")?;
+    Ok(())
+}
+
+/// Note which tracked enrollments have been redeemed, and by what device
+pub async fn store_enrollments(db: &SqlitePool, enrollments: &[Enrollment]) -> Result<()> {
+    let mut tx = db.begin().await?;
+    for enrollment in enrollments {
+        sqlx::query(
+            r#"
+            UPDATE paired_devices
+            SET redeemed_at = ?, device_name = COALESCE(?, device_name)
+            WHERE id = ?
+            "#,
+        )
+        .bind(enrollment.redeemed_at.map(|time| time.timestamp()))
+        .bind(&enrollment.device_name)
+        .bind(&enrollment.id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await.context("Failed to update pairings")?;
+    Ok(())
+}
+
+/// Tracked pairings, newest first
+pub async fn list_paired(db: &SqlitePool) -> Result<Vec<PairedDevice>> {
+    type Row = (
+        String,
+        Option<String>,
+        String,
+        Option<String>,
+        i64,
+        i64,
+        Option<i64>,
+        Option<String>,
+        Option<i64>,
+    );
+    let rows: Vec<Row> = sqlx::query_as(
+        r#"
+        SELECT id, label, scope, campaign_id, created_at, expires_at, redeemed_at, device_name,
+               revoked_at
+        FROM paired_devices ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch pairings")?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                id,
+                label,
+                scope,
+                campaign_id,
+                created_at,
+                expires_at,
+                redeemed_at,
+                device_name,
+                revoked_at,
+            )| PairedDevice {
+                id,
+                label,
+                scope,
+                campaign_id,
+                created_at,
+                expires_at,
+                redeemed_at,
+                device_name,
+                revoked_at,
+            },
+        )
+        .collect())
+}
+
+/// Mark a pairing revoked; returns whether it is tracked here
+pub async fn mark_revoked(db: &SqlitePool, id: &str) -> Result<bool> {
+    let result =
+        sqlx::query("UPDATE paired_devices SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(Utc::now().timestamp())
+            .bind(id)
+            .execute(db)
+            .await
+            .context("Failed to revoke pairing")?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use cowcow_core::decode;
+use cowcow_core::resample::Resampler;
+use cowcow_core::stretch::TimeStretcher;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Decoded audio ready for playback
+#[derive(Debug, Clone)]
+pub struct Clip {
+    /// Interleaved samples in [-1.0, 1.0]
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl Clip {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !decode::is_wav(path) {
+            let audio = decode::decode_file(path)?;
+            return Ok(Self {
+                samples: audio.samples,
+                sample_rate: audio.sample_rate,
+                channels: audio.channels,
+            });
+        }
+
+        let reader = hound::WavReader::open(path)
+            .with_context(|| format!("Failed to open recording: {}", path.display()))?;
+        let spec = reader.spec();
+        let samples = decode::read_wav_samples(reader).context("Failed to read recording")?;
+
+        Ok(Self {
+            samples,
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+        })
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / (self.sample_rate as f64 * self.channels as f64)
+    }
+
+    /// Change the playback speed without changing the pitch
+    pub fn stretched(&self, speed: f32) -> Result<Self> {
+        let stretcher = TimeStretcher::new(self.sample_rate, self.channels, speed)?;
+        Ok(Self {
+            samples: stretcher.process(&self.samples),
+            ..self.clone()
+        })
+    }
+}
+
+/// Play a clip on the default output device, blocking until it finishes
+pub fn play(clip: &Clip) -> Result<()> {
+    let host = cpal::default_host();
+    let device = host
+        .default_output_device()
+        .context("No output device available")?;
+    let output_config = device
+        .default_output_config()
+        .context("Failed to get default output config")?;
+    let out_rate = output_config.sample_rate().0;
+    let out_channels = output_config.channels() as usize;
+
+    // Match the device rate, then map channels (mono is copied to every speaker)
+    let mut resampler = Resampler::new(clip.sample_rate, out_rate, clip.channels)?;
+    let mut samples = resampler.process(&clip.samples)?;
+    samples.extend(resampler.flush()?);
+
+    let in_channels = clip.channels as usize;
+    let mapped: Vec<f32> = samples
+        .chunks_exact(in_channels)
+        .flat_map(|frame| (0..out_channels).map(move |c| frame[c.min(in_channels - 1)]))
+        .collect();
+
+    let position = Arc::new(Mutex::new(0usize));
+    let finished = Arc::new(AtomicBool::new(false));
+    let stream_config = cpal::StreamConfig {
+        channels: out_channels as u16,
+        sample_rate: cpal::SampleRate(out_rate),
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let stream = {
+        let position = position.clone();
+        let finished = finished.clone();
+        device.build_output_stream(
+            &stream_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let mut position = position.lock().unwrap_or_else(|e| e.into_inner());
+                for sample in data.iter_mut() {
+                    *sample = mapped.get(*position).copied().unwrap_or(0.0);
+                    *position += 1;
+                }
+                if *position >= mapped.len() {
+                    finished.store(true, Ordering::Relaxed);
+                }
+            },
+            |err| tracing::error!("Playback stream error: {}", err),
+            None,
+        )?
+    };
+
+    stream.play()?;
+    while !finished.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    // Let the device drain its last buffer
+    std::thread::sleep(Duration::from_millis(100));
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::script::{self, PromptFields};
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Prompt {
+    pub id: String,
+    pub lang: String,
+    pub text: String,
+    pub domain: Option<String>,
+    /// Saved recordings of the prompt
+    pub times_recorded: i64,
+    /// Structured prompt as JSON (see [`PromptFields`])
+    pub fields: Option<String>,
+    /// How to say a difficult word or phrase of the prompt
+    pub hint: Option<String>,
+    /// Path of a native speaker's reading of the prompt
+    pub exemplar: Option<String>,
+}
+
+impl Prompt {
+    /// Elicitation fields, when this is a question rather than text to read
+    pub fn structured(&self) -> Option<PromptFields> {
+        self.fields
+            .as_deref()
+            .and_then(|fields| serde_json::from_str(fields).ok())
+    }
+}
+
+/// Totals of a prompt import
+#[derive(Debug, Default)]
+pub struct PromptImport {
+    pub added: usize,
+    pub updated: usize,
+    /// Recordings made before the import that read one of its prompts and
+    /// are now linked to it
+    pub linked: u64,
+}
+
+/// Coverage of one language and domain
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PromptStats {
+    pub lang: String,
+    pub domain: Option<String>,
+    pub prompts: i64,
+    /// Prompts with at least one recording
+    pub recorded: i64,
+    pub recordings: i64,
+}
+
+const PROMPT_COLUMNS: &str = "id, lang, text, domain, times_recorded, fields, hint, exemplar";
+
+/// How much more likely `--coverage` makes the prompt richest in
+/// under-recorded characters than one with none
+const COVERAGE_WEIGHT: f64 = 3.0;
+
+/// ID of a prompt that has none in its script: stable for the same text, so
+/// importing a script again updates rather than duplicates
+pub fn derived_id(lang: &str, text: &str) -> String {
+    let digest = Sha256::digest(text.trim().as_bytes());
+    format!("{lang}-{}", &hex::encode(digest)[..12])
+}
+
+/// Add or update the prompts of a script file (see [`script::load_script`])
+/// in `lang`, then link earlier recordings of the same text to them
+pub async fn import_prompts(
+    db: &SqlitePool,
+    path: &Path,
+    lang: &str,
+    domain: Option<&str>,
+) -> Result<PromptImport> {
+    let prompts = script::load_script(path)?;
+    let mut summary = PromptImport::default();
+    let mut tx = db.begin().await?;
+    for prompt in prompts {
+        let id = prompt.id.unwrap_or_else(|| derived_id(lang, &prompt.text));
+        // Stored absolute, since recording is not run from the script's
+        // directory
+        let exemplar = match &prompt.exemplar {
+            Some(exemplar) => Some(std::fs::canonicalize(exemplar).with_context(|| {
+                format!("Exemplar of prompt {id} not found: {}", exemplar.display())
+            })?),
+            None => None,
+        };
+        let existing: Option<(String,)> = sqlx::query_as("SELECT id FROM prompts WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO prompts (id, lang, text, domain, fields, hint, exemplar, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET lang = excluded.lang, text = excluded.text,
+                domain = excluded.domain, fields = excluded.fields, hint = excluded.hint,
+                exemplar = excluded.exemplar
+            "#,
+        )
+        .bind(&id)
+        .bind(lang)
+        .bind(&prompt.text)
+        .bind(domain)
+        .bind(
+            prompt
+                .fields
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(&prompt.hint)
+        .bind(exemplar.as_ref().map(|exemplar| exemplar.to_string_lossy()))
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save prompt")?;
+        if existing.is_some() {
+            summary.updated += 1;
+        } else {
+            summary.added += 1;
+        }
+    }
+
+    summary.linked = sqlx::query(
+        r#"
+        UPDATE recordings SET prompt_id = (
+            SELECT p.id FROM prompts p
+            WHERE p.lang = recordings.lang AND p.text = TRIM(recordings.prompt)
+        )
+        WHERE prompt_id IS NULL AND EXISTS (
+            SELECT 1 FROM prompts p
+            WHERE p.lang = recordings.lang AND p.text = TRIM(recordings.prompt)
+        )
+        "#,
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to link recordings to prompts")?
+    .rows_affected();
+    tx.commit().await?;
+
+    refresh_counts(db).await?;
+    Ok(summary)
+}
+
+/// Recount `times_recorded` from the recordings linked to each prompt
+pub async fn refresh_counts(db: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE prompts SET times_recorded = (
+            SELECT COUNT(*) FROM recordings WHERE recordings.prompt_id = prompts.id
+        )
+        "#,
+    )
+    .execute(db)
+    .await
+    .context("Failed to count prompt recordings")?;
+    Ok(())
+}
+
+/// Recount `times_recorded` of prompt `id` alone
+pub async fn refresh_count<'e>(db: impl SqliteExecutor<'e>, id: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE prompts SET times_recorded = \
+         (SELECT COUNT(*) FROM recordings WHERE prompt_id = ?) WHERE id = ?",
+    )
+    .bind(id)
+    .bind(id)
+    .execute(db)
+    .await
+    .context("Failed to count prompt recordings")?;
+    Ok(())
+}
+
+pub async fn get_prompt(db: &SqlitePool, id: &str) -> Result<Option<Prompt>> {
+    sqlx::query_as::<_, Prompt>(&format!(
+        "SELECT {PROMPT_COLUMNS} FROM prompts WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch prompt")
+}
+
+/// The prompt with exactly this text in `lang`, to link a recording of
+/// free text to
+pub async fn find_by_text(db: &SqlitePool, lang: &str, text: &str) -> Result<Option<Prompt>> {
+    sqlx::query_as::<_, Prompt>(&format!(
+        "SELECT {PROMPT_COLUMNS} FROM prompts WHERE lang = ? AND text = ? \
+         ORDER BY created_at LIMIT 1"
+    ))
+    .bind(lang)
+    .bind(text.trim())
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch prompt")
+}
+
+/// Structured prompts of the recordings in `recording_ids` that have one,
+/// for the export metadata
+pub async fn recording_fields(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<HashMap<String, serde_json::Value>> {
+    let mut fields = HashMap::new();
+    for id in recording_ids {
+        let stored: Option<(Option<String>,)> =
+            sqlx::query_as("SELECT prompt_fields FROM recordings WHERE id = ?")
+                .bind(id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to fetch recording prompt")?;
+        if let Some(value) = stored
+            .and_then(|(stored,)| stored)
+            .and_then(|stored| serde_json::from_str(&stored).ok())
+        {
+            fields.insert(id.clone(), value);
+        }
+    }
+    Ok(fields)
+}
+
+/// Whether the speaker heard the prompt's exemplar before recording, for the
+/// recordings in `recording_ids` that were offered one
+pub async fn exemplars_played(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<HashMap<String, bool>> {
+    let mut played = HashMap::new();
+    for id in recording_ids {
+        let stored: Option<(Option<bool>,)> =
+            sqlx::query_as("SELECT exemplar_played FROM recordings WHERE id = ?")
+                .bind(id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to fetch recording prompt")?;
+        if let Some(value) = stored.and_then(|(stored,)| stored) {
+            played.insert(id.clone(), value);
+        }
+    }
+    Ok(played)
+}
+
+/// Prompts in import order, optionally only one language's or domain's, or
+/// only those without recordings
+pub async fn list_prompts(
+    db: &SqlitePool,
+    lang: Option<&str>,
+    domain: Option<&str>,
+    unrecorded: bool,
+) -> Result<Vec<Prompt>> {
+    sqlx::query_as::<_, Prompt>(&format!(
+        r#"
+        SELECT {PROMPT_COLUMNS} FROM prompts
+        WHERE (? IS NULL OR lang = ?) AND (? IS NULL OR domain = ?)
+            AND (NOT ? OR times_recorded = 0)
+        ORDER BY lang, created_at, rowid
+        "#
+    ))
+    .bind(lang)
+    .bind(lang)
+    .bind(domain)
+    .bind(domain)
+    .bind(unrecorded)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch prompts")
+}
+
+/// Coverage per language and domain
+pub async fn prompt_stats(db: &SqlitePool, lang: Option<&str>) -> Result<Vec<PromptStats>> {
+    sqlx::query_as::<_, PromptStats>(
+        r#"
+        SELECT lang, domain, COUNT(*) AS prompts,
+            SUM(times_recorded > 0) AS recorded, SUM(times_recorded) AS recordings
+        FROM prompts
+        WHERE ? IS NULL OR lang = ?
+        GROUP BY lang, domain
+        ORDER BY lang, domain
+        "#,
+    )
+    .bind(lang)
+    .bind(lang)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch prompt stats")
+}
+
+/// Pick the next prompt to record in `lang`, or `None` when there are none
+///
+/// Prompts are drawn at random, weighted towards the least recorded: one
+/// recording more than the least recorded prompt quarters the chance, two
+/// ninths it. With `coverage` prompts are also favoured for characters the
+/// recordings so far have little of.
+pub async fn next_prompt(db: &SqlitePool, lang: &str, coverage: bool) -> Result<Option<Prompt>> {
+    let mut candidates = list_prompts(db, Some(lang), None, false).await?;
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let counts = coverage.then(|| character_counts(&candidates));
+    let weights = selection_weights(&candidates, counts.as_ref());
+    let roll = (Uuid::new_v4().as_u128() >> 64) as u64 as f64 / u64::MAX as f64;
+    Ok(Some(candidates.swap_remove(pick_weighted(&weights, roll))))
+}
+
+/// How often each character has been read, over all recordings of `prompts`
+pub fn character_counts(prompts: &[Prompt]) -> HashMap<char, f64> {
+    let mut counts = HashMap::new();
+    for prompt in prompts.iter().filter(|prompt| prompt.times_recorded > 0) {
+        for c in letters(&prompt.text) {
+            *counts.entry(c).or_insert(0.0) += prompt.times_recorded as f64;
+        }
+    }
+    counts
+}
+
+/// Selection weight of each prompt: favouring the least recorded and,
+/// given `counts` from [`character_counts`], those with rarely read
+/// characters
+pub fn selection_weights(prompts: &[Prompt], counts: Option<&HashMap<char, f64>>) -> Vec<f64> {
+    let least = prompts
+        .iter()
+        .map(|prompt| prompt.times_recorded)
+        .min()
+        .unwrap_or(0);
+    let rarity = |prompt: &Prompt| {
+        let Some(counts) = counts else {
+            return 0.0;
+        };
+        let letters: HashSet<char> = letters(&prompt.text).collect();
+        if letters.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = letters
+            .iter()
+            .map(|c| 1.0 / (1.0 + counts.get(c).copied().unwrap_or(0.0)))
+            .sum();
+        total / letters.len() as f64
+    };
+    let rarities: Vec<f64> = prompts.iter().map(rarity).collect();
+    let rarest = rarities.iter().copied().fold(0.0, f64::max);
+
+    prompts
+        .iter()
+        .zip(rarities)
+        .map(|(prompt, rarity)| {
+            let extra = (prompt.times_recorded - least) as f64;
+            let coverage = if rarest > 0.0 {
+                1.0 + COVERAGE_WEIGHT * rarity / rarest
+            } else {
+                1.0
+            };
+            coverage / (1.0 + extra).powi(2)
+        })
+        .collect()
+}
+
+/// Index chosen by `roll` in [0, 1] among `weights`
+pub fn pick_weighted(weights: &[f64], roll: f64) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut target = roll.clamp(0.0, 1.0) * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return i;
+        }
+        target -= weight;
+    }
+    weights.len().saturating_sub(1)
+}
+
+fn letters(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+}
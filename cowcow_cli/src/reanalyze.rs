@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveTime};
+use cowcow_core::policy::QcReport;
+use cowcow_core::prompt_analysis::PromptAnalysis;
+use cowcow_core::timeline::QcTimeline;
+use cowcow_core::SnrEstimator;
+use indicatif::{ProgressBar, ProgressStyle};
+use sqlx::SqlitePool;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+
+use crate::cancel::Checkpoint;
+use crate::config::Config;
+use crate::mode::{self, RecordingMode};
+use crate::upload;
+
+/// Totals of a re-analysis run
+#[derive(Debug, Default)]
+pub struct ReanalyzeSummary {
+    pub reanalyzed: usize,
+    /// Recordings whose QC verdict flipped
+    pub changed: usize,
+    /// Recordings whose audio is no longer on disk
+    pub missing: usize,
+    pub failed: usize,
+    /// Recordings already re-analyzed by an interrupted earlier run
+    pub resumed: usize,
+    /// Whether the run was interrupted; running it again resumes
+    pub cancelled: bool,
+}
+
+/// Parse a `--since` date (`YYYY-MM-DD`, UTC) into a Unix timestamp
+pub fn parse_since(value: &str) -> Result<i64, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc().timestamp())
+        .map_err(|_| format!("Invalid date: {value} (expected YYYY-MM-DD)"))
+}
+
+/// Re-run QC on a recording's audio and store the new metrics, QC report
+/// and timeline, calibrated as the recording was
+///
+/// An earlier QC skip is cleared, since its verdict may no longer apply.
+pub async fn reanalyze_recording(
+    db: &SqlitePool,
+    config: &Config,
+    recording_id: &str,
+    wav_path: &Path,
+) -> Result<QcReport> {
+    // An answer to a structured prompt has no text it was read from
+    let (prompt, noise_floor_db, previous_metrics): (Option<String>, Option<f32>, String) =
+        sqlx::query_as(
+            r#"
+        SELECT CASE WHEN r.prompt_fields IS NULL THEN r.prompt END, s.noise_floor_db, r.qc_metrics
+        FROM recordings r LEFT JOIN sessions s ON s.id = r.session_id
+        WHERE r.id = ?
+        "#,
+        )
+        .bind(recording_id)
+        .fetch_one(db)
+        .await
+        .context("Failed to fetch recording")?;
+    let mode = serde_json::from_str(&previous_metrics)
+        .map(|metrics| RecordingMode::of_metrics(&metrics))
+        .unwrap_or_default();
+
+    let mut builder = config.processor_builder();
+    if let Some(noise_floor_db) = noise_floor_db {
+        builder = builder.snr_estimator(SnrEstimator::FixedFloor { noise_floor_db });
+    }
+    let timeline = cowcow_core::analyze_wav_timeline(wav_path, builder.into_config())?;
+    timeline.save(&QcTimeline::sidecar_path(wav_path))?;
+
+    let metrics = timeline.summary();
+    let metrics_json = match mode {
+        RecordingMode::Music => mode::music_metrics(&metrics, mode::measure_loudness(wav_path)?)?,
+        RecordingMode::Speech => match prompt
+            .as_deref()
+            .and_then(|text| PromptAnalysis::new(text, &metrics))
+        {
+            Some(analysis) => analysis.with_metrics(&metrics),
+            None => serde_json::to_value(&metrics)?,
+        },
+    };
+    let qc_report = config
+        .qc_policy_for(&metrics_json)
+        .evaluate_json(&metrics_json);
+
+    // Metrics and skip change together, or not at all
+    let mut tx = db.begin().await?;
+    sqlx::query("UPDATE recordings SET qc_metrics = ?, qc_report = ? WHERE id = ?")
+        .bind(metrics_json.to_string())
+        .bind(serde_json::to_string(&qc_report)?)
+        .bind(recording_id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save QC metrics")?;
+    upload::clear_skip(&mut *tx, recording_id).await?;
+    tx.commit().await.context("Failed to save QC metrics")?;
+    Ok(qc_report)
+}
+
+/// Re-run QC over every stored recording, optionally only one language's or
+/// those made since a Unix timestamp
+///
+/// Stops between recordings when `cancel` fires; the same run started again
+/// picks up where it stopped.
+pub async fn reanalyze_library(
+    db: &SqlitePool,
+    config: &Config,
+    lang: Option<&str>,
+    since: Option<i64>,
+    cancel: &CancellationToken,
+) -> Result<ReanalyzeSummary> {
+    let recordings: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT id, wav_path, qc_report FROM recordings
+        WHERE (? IS NULL OR lang = ?) AND created_at >= ?
+        ORDER BY created_at
+        "#,
+    )
+    .bind(lang)
+    .bind(lang)
+    .bind(since.unwrap_or(0))
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    let pb = ProgressBar::new(recordings.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.green} {pos}/{len} recordings re-analyzed ({eta} left)")
+            .unwrap(),
+    );
+
+    let job = format!("reanalyze:{}:{}", lang.unwrap_or("*"), since.unwrap_or(0));
+    let mut checkpoint = Checkpoint::load(db, &job).await?;
+    let mut summary = ReanalyzeSummary {
+        resumed: checkpoint.resumed(),
+        ..Default::default()
+    };
+    for (id, wav_path, previous_report) in recordings {
+        if cancel.is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+        pb.inc(1);
+        if checkpoint.is_done(&id) {
+            continue;
+        }
+        let wav_path = config.locate_audio(&wav_path);
+        if !wav_path.exists() {
+            summary.missing += 1;
+            checkpoint.mark_done(db, &id).await?;
+            continue;
+        }
+
+        match reanalyze_recording(db, config, &id, &wav_path).await {
+            Ok(report) => {
+                summary.reanalyzed += 1;
+                let previously_passed = previous_report
+                    .as_deref()
+                    .and_then(|json| serde_json::from_str::<QcReport>(json).ok())
+                    .map(|report| report.passed);
+                if previously_passed.is_some_and(|passed| passed != report.passed) {
+                    summary.changed += 1;
+                }
+            }
+            Err(e) => {
+                // Not checkpointed, so a resumed run tries it again
+                pb.println(format!("⚠️  {id}: {e:#}"));
+                summary.failed += 1;
+                continue;
+            }
+        }
+        checkpoint.mark_done(db, &id).await?;
+    }
+    pb.finish_and_clear();
+
+    if summary.cancelled {
+        checkpoint.save(db).await?;
+    } else {
+        checkpoint.finish(db).await?;
+    }
+    Ok(summary)
+}
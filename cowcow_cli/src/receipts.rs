@@ -0,0 +1,311 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::auth::AuthClient;
+
+/// A calendar month (UTC) that receipts cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Month {
+    pub first_day: NaiveDate,
+}
+
+impl Month {
+    /// Unix timestamps of the month's first second and of the next month's
+    pub fn bounds(&self) -> (i64, i64) {
+        let next = if self.first_day.month() == 12 {
+            NaiveDate::from_ymd_opt(self.first_day.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(self.first_day.year(), self.first_day.month() + 1, 1)
+        }
+        .unwrap_or(NaiveDate::MAX);
+        let timestamp = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc().timestamp();
+        (timestamp(self.first_day), timestamp(next))
+    }
+}
+
+impl fmt::Display for Month {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first_day.format("%Y-%m"))
+    }
+}
+
+impl FromStr for Month {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NaiveDate::parse_from_str(&format!("{s}-01"), "%Y-%m-%d")
+            .map(|first_day| Month { first_day })
+            .map_err(|_| format!("Invalid month: {s} (expected YYYY-MM)"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ReceiptFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ReceiptFormat::Csv),
+            "json" => Ok(ReceiptFormat::Json),
+            _ => Err(format!("Unknown receipt format: {s} (use csv or json)")),
+        }
+    }
+}
+
+/// What one speaker submitted in a month
+#[derive(Debug, Clone, Serialize)]
+pub struct Receipt {
+    pub month: String,
+    /// Empty for recordings made without a speaker profile
+    pub speaker_id: String,
+    pub speaker_name: String,
+    pub uploaded_recordings: i64,
+    pub uploaded_minutes: f64,
+    /// Uploaded recordings the server accepted
+    pub accepted_takes: i64,
+    /// Tokens the server awarded for the uploads
+    pub tokens: i64,
+    /// Whether the server confirmed the accepted takes and tokens; if not,
+    /// they are the local QC verdicts and recorded awards, which the
+    /// contributor's machine can change
+    pub verified: bool,
+}
+
+const CSV_HEADER: [&str; 8] = [
+    "month",
+    "speaker_id",
+    "speaker_name",
+    "uploaded_recordings",
+    "uploaded_minutes",
+    "accepted_takes",
+    "tokens",
+    "verified",
+];
+
+/// The server's record of a month's uploads
+#[derive(Debug, Default)]
+pub struct Verification {
+    /// Recordings the server accepted
+    pub accepted: HashSet<String>,
+    /// Tokens awarded per recording
+    pub tokens: HashMap<String, i64>,
+}
+
+/// Fetch the server's recordings and the token history since `month` began
+pub async fn verify(auth: &AuthClient, month: Month) -> Result<Verification> {
+    let today = chrono::Utc::now().date_naive();
+    let days = (today - month.first_day).num_days().max(0) as u32 + 1;
+
+    let accepted = auth
+        .get_recordings()
+        .await?
+        .into_iter()
+        .filter(|recording| recording.status.as_deref() == Some("completed"))
+        .map(|recording| recording.id)
+        .collect();
+    let mut tokens = HashMap::new();
+    for transaction in auth.get_token_history(days).await? {
+        if let Some(recording_id) = transaction.recording_id {
+            *tokens.entry(recording_id).or_default() += transaction.amount as i64;
+        }
+    }
+    Ok(Verification { accepted, tokens })
+}
+
+#[derive(sqlx::FromRow)]
+struct Upload {
+    id: String,
+    speaker_id: String,
+    speaker_name: String,
+    duration_secs: Option<f64>,
+    passed: bool,
+    tokens_awarded: Option<i64>,
+}
+
+/// One receipt per speaker for the recordings uploaded in `month`, with
+/// accepted takes and tokens from `verification` where the server was
+/// reached
+///
+/// Token awards the server reports for uploads that have none recorded
+/// locally are stored, so later unverified receipts show them too.
+pub async fn monthly_receipts(
+    db: &SqlitePool,
+    month: Month,
+    verification: Option<&Verification>,
+) -> Result<Vec<Receipt>> {
+    let (start, end) = month.bounds();
+    let uploads = sqlx::query_as::<_, Upload>(
+        r#"
+        SELECT r.id,
+            COALESCE(r.speaker_id, '') AS speaker_id,
+            COALESCE(s.name, '') AS speaker_name,
+            CAST(json_extract(r.qc_metrics, '$.duration_secs') AS REAL) AS duration_secs,
+            COALESCE(json_extract(r.qc_report, '$.passed') = 1, 0) AS passed,
+            r.tokens_awarded
+        FROM recordings r
+        LEFT JOIN speakers s ON s.id = r.speaker_id
+        WHERE r.uploaded_at >= ? AND r.uploaded_at < ?
+        ORDER BY speaker_name, speaker_id
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch uploaded recordings")?;
+
+    let mut receipts: Vec<Receipt> = Vec::new();
+    for upload in uploads {
+        let (accepted, tokens) = match verification {
+            Some(verification) => {
+                let tokens = verification.tokens.get(&upload.id).copied();
+                if let (Some(tokens), None) = (tokens, upload.tokens_awarded) {
+                    sqlx::query("UPDATE recordings SET tokens_awarded = ? WHERE id = ?")
+                        .bind(tokens)
+                        .bind(&upload.id)
+                        .execute(db)
+                        .await
+                        .context("Failed to store awarded tokens")?;
+                }
+                (
+                    verification.accepted.contains(&upload.id),
+                    tokens.unwrap_or(0),
+                )
+            }
+            None => (upload.passed, upload.tokens_awarded.unwrap_or(0)),
+        };
+
+        if receipts.last().map(|r| &r.speaker_id) != Some(&upload.speaker_id) {
+            receipts.push(Receipt {
+                month: month.to_string(),
+                speaker_id: upload.speaker_id,
+                speaker_name: upload.speaker_name,
+                uploaded_recordings: 0,
+                uploaded_minutes: 0.0,
+                accepted_takes: 0,
+                tokens: 0,
+                verified: verification.is_some(),
+            });
+        }
+        let receipt = receipts.last_mut().expect("receipt pushed above");
+        receipt.uploaded_recordings += 1;
+        receipt.accepted_takes += accepted as i64;
+        receipt.tokens += tokens;
+        receipt.uploaded_minutes += upload.duration_secs.unwrap_or(0.0) / 60.0;
+    }
+    for receipt in &mut receipts {
+        receipt.uploaded_minutes = (receipt.uploaded_minutes * 100.0).round() / 100.0;
+    }
+    Ok(receipts)
+}
+
+/// Write receipts as CSV with a header row, or as a JSON array
+pub fn write_receipts(receipts: &[Receipt], format: ReceiptFormat, out: impl Write) -> Result<()> {
+    match format {
+        ReceiptFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            if receipts.is_empty() {
+                // The header alone, so a quiet month still reads as one
+                writer.write_record(CSV_HEADER)?;
+            }
+            for receipt in receipts {
+                writer.serialize(receipt)?;
+            }
+            writer.flush()?;
+        }
+        ReceiptFormat::Json => {
+            let mut out = out;
+            serde_json::to_writer_pretty(&mut out, receipts)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    /// Two uploads in June 2024 by Amina, both passing local QC, one of
+    /// them with no tokens recorded; and one in July
+    async fn library() -> TestLibrary {
+        let library = TestLibrary::new().await;
+        sqlx::query("INSERT INTO speakers (id, name, created_at) VALUES ('s1', 'Amina', 0)")
+            .execute(&library.db)
+            .await
+            .unwrap();
+        let june = "2024-06".parse::<Month>().unwrap().bounds().0;
+        let july = "2024-07".parse::<Month>().unwrap().bounds().0;
+        for (id, uploaded_at, tokens) in [
+            ("a", june, Some(12)),
+            ("b", june + 60, None),
+            ("c", july, Some(5)),
+        ] {
+            sqlx::query(
+                "INSERT INTO recordings (id, lang, qc_metrics, qc_report, created_at, wav_path, \
+                 uploaded_at, speaker_id, tokens_awarded) \
+                 VALUES (?, 'sw', '{\"duration_secs\": 30.0}', '{\"passed\": true}', 0, '', ?, 's1', ?)",
+            )
+            .bind(id)
+            .bind(uploaded_at)
+            .bind(tokens)
+            .execute(&library.db)
+            .await
+            .unwrap();
+        }
+        library
+    }
+
+    #[tokio::test]
+    async fn test_unverified_receipts_use_local_figures() {
+        let library = library().await;
+        let receipts = monthly_receipts(&library.db, "2024-06".parse().unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(receipts.len(), 1);
+        let receipt = &receipts[0];
+        assert_eq!(receipt.speaker_name, "Amina");
+        assert_eq!(receipt.uploaded_recordings, 2);
+        assert_eq!(receipt.uploaded_minutes, 1.0);
+        assert_eq!(receipt.accepted_takes, 2);
+        assert_eq!(receipt.tokens, 12);
+        assert!(!receipt.verified);
+    }
+
+    #[tokio::test]
+    async fn test_verified_receipts_use_the_server_and_backfill_tokens() {
+        let library = library().await;
+        let verification = Verification {
+            accepted: HashSet::from(["b".to_string()]),
+            tokens: HashMap::from([("a".to_string(), 10), ("b".to_string(), 14)]),
+        };
+        let receipts =
+            monthly_receipts(&library.db, "2024-06".parse().unwrap(), Some(&verification))
+                .await
+                .unwrap();
+        let receipt = &receipts[0];
+        assert_eq!(receipt.accepted_takes, 1);
+        assert_eq!(receipt.tokens, 24);
+        assert!(receipt.verified);
+
+        // The missing award is stored; the recorded one is left alone
+        let stored: Vec<Option<i64>> =
+            sqlx::query_scalar("SELECT tokens_awarded FROM recordings ORDER BY id")
+                .fetch_all(&library.db)
+                .await
+                .unwrap();
+        assert_eq!(stored, vec![Some(12), Some(14), Some(5)]);
+    }
+}
@@ -0,0 +1,470 @@
+use anyhow::{Context, Result};
+use cowcow_core::flac::{self, FlacWriter};
+use cowcow_core::opus;
+use cowcow_core::policy::QcReport;
+use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::{cursor, terminal, QueueableCommand};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::keys;
+use crate::playback::{self, Clip};
+use crate::reanalyze;
+
+/// Boundary step for the arrow keys
+pub const NUDGE_SECS: f64 = 0.05;
+
+/// Boundary step for shift + arrow keys
+pub const COARSE_NUDGE_SECS: f64 = 0.5;
+
+/// Shortest audio a trim may leave
+const MIN_KEPT_SECS: f64 = 0.2;
+
+/// Levels of the coarse waveform, quietest first
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A reviewer's verdict on a recording
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    #[default]
+    Unreviewed,
+    Approved,
+    Rejected,
+    /// Usable prompt, unusable take: the speaker should read it again
+    NeedsRerecord,
+}
+
+impl ReviewStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::Unreviewed => "unreviewed",
+            ReviewStatus::Approved => "approved",
+            ReviewStatus::Rejected => "rejected",
+            ReviewStatus::NeedsRerecord => "needs_rerecord",
+        }
+    }
+}
+
+impl FromStr for ReviewStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "unreviewed" => Ok(ReviewStatus::Unreviewed),
+            "approved" => Ok(ReviewStatus::Approved),
+            "rejected" => Ok(ReviewStatus::Rejected),
+            "needs_rerecord" => Ok(ReviewStatus::NeedsRerecord),
+            other => Err(format!(
+                "Unknown review status '{other}' (expected unreviewed, approved, rejected or needs_rerecord)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A recording as shown in `cowcow review`
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReviewItem {
+    pub id: String,
+    pub lang: String,
+    pub prompt: Option<String>,
+    pub transcript: Option<String>,
+    pub wav_path: String,
+    pub qc_metrics: String,
+    pub qc_report: Option<String>,
+    pub outlier_metrics: Option<String>,
+    pub uploaded_at: Option<i64>,
+    pub review_status: String,
+    pub review_notes: Option<String>,
+}
+
+impl ReviewItem {
+    pub fn status(&self) -> ReviewStatus {
+        self.review_status.parse().unwrap_or_default()
+    }
+
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        let metrics: serde_json::Value = serde_json::from_str(&self.qc_metrics).ok()?;
+        metrics.get(name)?.as_f64()
+    }
+
+    /// The QC report stored when the recording was made or reanalyzed
+    pub fn report(&self) -> Option<QcReport> {
+        serde_json::from_str(self.qc_report.as_deref()?).ok()
+    }
+}
+
+/// A verdict as exported with a recording
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub status: ReviewStatus,
+    pub notes: Option<String>,
+    pub reviewed_at: Option<i64>,
+}
+
+/// Recordings with the given verdict, optionally only one language's, in
+/// review order: flagged outliers first, then recordings that failed QC,
+/// then the rest, oldest first
+pub async fn queue(
+    db: &SqlitePool,
+    lang: Option<&str>,
+    status: ReviewStatus,
+) -> Result<Vec<String>> {
+    let ids: Vec<(String,)> = sqlx::query_as(
+        r#"
+        SELECT id FROM recordings
+        WHERE archived = 0 AND review_status = ? AND (? IS NULL OR lang = ?)
+        ORDER BY outlier_metrics IS NULL,
+                 COALESCE(json_extract(qc_report, '$.passed'), 1),
+                 created_at ASC
+        "#,
+    )
+    .bind(status.as_str())
+    .bind(lang)
+    .bind(lang)
+    .fetch_all(db)
+    .await
+    .context("Failed to find recordings to review")?;
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+pub async fn load_item(db: &SqlitePool, recording_id: &str) -> Result<ReviewItem> {
+    sqlx::query_as(
+        r#"
+        SELECT id, lang, prompt, transcript, wav_path, qc_metrics, qc_report, outlier_metrics,
+               uploaded_at, review_status, review_notes
+        FROM recordings WHERE id = ?
+        "#,
+    )
+    .bind(recording_id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch recording")?
+    .with_context(|| format!("Recording not found: {recording_id}"))
+}
+
+/// Store a verdict and the reviewer's notes (`None` clears them); setting a
+/// recording back to unreviewed forgets when it was reviewed
+pub async fn set_verdict(
+    db: &SqlitePool,
+    recording_id: &str,
+    status: ReviewStatus,
+    notes: Option<&str>,
+) -> Result<()> {
+    let reviewed_at = (status != ReviewStatus::Unreviewed).then(|| chrono::Utc::now().timestamp());
+    sqlx::query(
+        "UPDATE recordings SET review_status = ?, review_notes = ?, reviewed_at = ? WHERE id = ?",
+    )
+    .bind(status.as_str())
+    .bind(notes.filter(|notes| !notes.trim().is_empty()))
+    .bind(reviewed_at)
+    .bind(recording_id)
+    .execute(db)
+    .await
+    .context("Failed to store review verdict")?;
+    Ok(())
+}
+
+/// Verdicts of the given recordings that have been reviewed, by recording ID
+pub async fn recording_verdicts(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<HashMap<String, Verdict>> {
+    let mut verdicts = HashMap::new();
+    for id in recording_ids {
+        let row: Option<(String, Option<String>, Option<i64>)> = sqlx::query_as(
+            "SELECT review_status, review_notes, reviewed_at FROM recordings \
+             WHERE id = ? AND review_status != 'unreviewed'",
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .context("Failed to fetch review verdict")?;
+        if let Some((status, notes, reviewed_at)) = row {
+            verdicts.insert(
+                id.clone(),
+                Verdict {
+                    status: status.parse().unwrap_or_default(),
+                    notes,
+                    reviewed_at,
+                },
+            );
+        }
+    }
+    Ok(verdicts)
+}
+
+/// Where the untrimmed audio of a trimmed recording is kept
+/// (`<id>.original.wav`, or `.original.flac` for FLAC recordings)
+pub fn original_path(wav_path: &Path) -> PathBuf {
+    let extension = wav_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("wav");
+    wav_path.with_extension(format!("original.{extension}"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Boundary {
+    Start,
+    End,
+}
+
+/// Start and end points of a recording being trimmed
+pub struct TrimEditor {
+    clip: Clip,
+    start_secs: f64,
+    end_secs: f64,
+    editing: Boundary,
+}
+
+impl TrimEditor {
+    /// Edit `clip`, initially cutting `trim_start_secs` from the start and
+    /// `trim_end_secs` from the end
+    pub fn new(clip: Clip, trim_start_secs: f64, trim_end_secs: f64) -> Self {
+        let duration = clip.duration_secs();
+        let start_secs = trim_start_secs.clamp(0.0, duration);
+        let end_secs = (duration - trim_end_secs).clamp(start_secs, duration);
+        Self {
+            clip,
+            start_secs,
+            end_secs,
+            editing: Boundary::Start,
+        }
+    }
+
+    /// Seconds cut from the start and from the end
+    pub fn trim_secs(&self) -> (f64, f64) {
+        (self.start_secs, self.clip.duration_secs() - self.end_secs)
+    }
+
+    /// Move the selected boundary, keeping at least [`MIN_KEPT_SECS`] between them
+    pub fn nudge(&mut self, delta_secs: f64) {
+        let duration = self.clip.duration_secs();
+        let min_kept = MIN_KEPT_SECS.min(duration);
+        match self.editing {
+            Boundary::Start => {
+                self.start_secs =
+                    (self.start_secs + delta_secs).clamp(0.0, self.end_secs - min_kept)
+            }
+            Boundary::End => {
+                self.end_secs =
+                    (self.end_secs + delta_secs).clamp(self.start_secs + min_kept, duration)
+            }
+        }
+    }
+
+    pub fn switch_boundary(&mut self) {
+        self.editing = match self.editing {
+            Boundary::Start => Boundary::End,
+            Boundary::End => Boundary::Start,
+        };
+    }
+
+    /// The audio between the boundaries
+    pub fn trimmed(&self) -> Clip {
+        let frame = |secs: f64| {
+            (secs * self.clip.sample_rate as f64).round() as usize * self.clip.channels as usize
+        };
+        let end = frame(self.end_secs).min(self.clip.samples.len());
+        let start = frame(self.start_secs).min(end);
+        Clip {
+            samples: self.clip.samples[start..end].to_vec(),
+            ..self.clip.clone()
+        }
+    }
+
+    /// One status line: the waveform (trimmed parts dotted, boundaries as
+    /// brackets) followed by the boundary times
+    pub fn render(&self, width: usize) -> String {
+        let duration = self.clip.duration_secs().max(f64::EPSILON);
+        let column = |secs: f64| ((secs / duration * width as f64) as usize).min(width - 1);
+        let (start_col, end_col) = (column(self.start_secs), column(self.end_secs));
+
+        let peaks = self.peaks(width);
+        let loudest = peaks.iter().copied().fold(f32::EPSILON, f32::max);
+        let wave: String = peaks
+            .iter()
+            .enumerate()
+            .map(|(col, &peak)| match col {
+                _ if col == start_col && self.editing == Boundary::Start => '[',
+                _ if col == end_col && self.editing == Boundary::End => ']',
+                _ if col < start_col || col > end_col => '·',
+                _ => {
+                    let level = (peak / loudest).sqrt() * (BARS.len() - 1) as f32;
+                    BARS[level.round() as usize]
+                }
+            })
+            .collect();
+
+        let marker = |boundary: Boundary, secs: f64| {
+            if self.editing == boundary {
+                format!(">{secs:.2}s<")
+            } else {
+                format!("{secs:.2}s")
+            }
+        };
+        format!(
+            "{} {} – {} of {:.2}s",
+            wave,
+            marker(Boundary::Start, self.start_secs),
+            marker(Boundary::End, self.end_secs),
+            self.clip.duration_secs()
+        )
+    }
+
+    /// Peak level of each of `columns` equal slices of the clip
+    fn peaks(&self, columns: usize) -> Vec<f32> {
+        let len = self.clip.samples.len();
+        (0..columns)
+            .map(|col| {
+                let from = col * len / columns;
+                let to = ((col + 1) * len / columns).max(from);
+                self.clip.samples[from..to]
+                    .iter()
+                    .fold(0.0f32, |peak, &x| peak.max(x.abs()))
+            })
+            .collect()
+    }
+}
+
+/// Run the trim editor until the user saves (returning the seconds cut from
+/// the start and end) or cancels
+pub fn edit_trim(mut editor: TrimEditor) -> Result<Option<(f64, f64)>> {
+    println!("Trim controls:");
+    println!(
+        "  ←/→       nudge the selected boundary by {NUDGE_SECS}s (shift: {COARSE_NUDGE_SECS}s)"
+    );
+    println!("  Tab       switch between start and end");
+    println!("  p         preview the trimmed audio");
+    println!("  Enter     save the trim    Esc/q  cancel");
+
+    let _raw_mode = keys::RawModeGuard::enable();
+    let mut stdout = std::io::stdout();
+    let mut dirty = true;
+    loop {
+        if dirty {
+            let (cols, _) = terminal::size().unwrap_or((80, 24));
+            let width = (cols as usize).saturating_sub(34).max(20);
+            stdout.queue(cursor::MoveToColumn(0))?;
+            stdout.queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+            write!(stdout, "{}", editor.render(width))?;
+            stdout.flush()?;
+            dirty = false;
+        }
+
+        let Some(key) = keys::poll_key() else {
+            std::thread::sleep(Duration::from_millis(20));
+            continue;
+        };
+        let step = if key.modifiers.contains(KeyModifiers::SHIFT) {
+            COARSE_NUDGE_SECS
+        } else {
+            NUDGE_SECS
+        };
+        match key.code {
+            _ if keys::is_interrupt(&key) => return Ok(None),
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+            KeyCode::Enter => return Ok(Some(editor.trim_secs())),
+            KeyCode::Left => editor.nudge(-step),
+            KeyCode::Right => editor.nudge(step),
+            KeyCode::Tab | KeyCode::BackTab => editor.switch_boundary(),
+            KeyCode::Char('p') => playback::play(&editor.trimmed())?,
+            _ => continue,
+        }
+        dirty = true;
+    }
+}
+
+/// Trim a recording, keeping its untrimmed audio next to it, and redo its QC
+///
+/// The trim is always cut from the original audio, so a later trim can
+/// restore material an earlier one removed.
+pub async fn save_trim(
+    db: &SqlitePool,
+    config: &Config,
+    recording_id: &str,
+    wav_path: &Path,
+    trim_start_secs: f64,
+    trim_end_secs: f64,
+) -> Result<()> {
+    let original = original_path(wav_path);
+    if !original.exists() {
+        fs::rename(wav_path, &original)
+            .with_context(|| format!("Failed to keep original {}", original.display()))?;
+    }
+
+    let clip = Clip::load(&original)?;
+    let trimmed = TrimEditor::new(clip, trim_start_secs, trim_end_secs).trimmed();
+    if flac::is_flac(wav_path) {
+        let mut writer = FlacWriter::create(wav_path, trimmed.sample_rate, trimmed.channels)
+            .with_context(|| format!("Failed to write {}", wav_path.display()))?;
+        for &sample in &trimmed.samples {
+            writer.write_sample((sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16)?;
+        }
+        writer.finalize()?;
+    } else {
+        // Opus is encoded from a WAV written next to the recording
+        let is_opus = opus::is_opus(wav_path);
+        let pcm_path = if is_opus {
+            wav_path.with_extension("trim.wav")
+        } else {
+            wav_path.to_path_buf()
+        };
+        // Keep the recording's sample format
+        let spec = match hound::WavReader::open(&original) {
+            Ok(reader) => hound::WavSpec {
+                channels: trimmed.channels,
+                sample_rate: trimmed.sample_rate,
+                ..reader.spec()
+            },
+            Err(_) => config
+                .bit_depth()
+                .spec(trimmed.sample_rate, trimmed.channels),
+        };
+        let mut writer = hound::WavWriter::create(&pcm_path, spec)
+            .with_context(|| format!("Failed to write {}", pcm_path.display()))?;
+        for &sample in &trimmed.samples {
+            cowcow_core::wav::write_sample(&mut writer, sample)?;
+        }
+        writer.finalize()?;
+        if is_opus {
+            let encoded = opus::encode_wav_file(&pcm_path, wav_path, config.audio.opus_bitrate);
+            let _ = fs::remove_file(&pcm_path);
+            encoded?;
+        }
+    }
+
+    // QC again on what will be uploaded
+    reanalyze::reanalyze_recording(db, config, recording_id, wav_path).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE recordings
+        SET trim_start_secs = ?, trim_end_secs = ?, fingerprint = NULL, content_hash = NULL,
+            word_alignment = NULL
+        WHERE id = ?
+        "#,
+    )
+    .bind(trim_start_secs)
+    .bind(trim_end_secs)
+    .bind(recording_id)
+    .execute(db)
+    .await
+    .context("Failed to save trim")?;
+    Ok(())
+}
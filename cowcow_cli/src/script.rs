@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One prompt of a recording script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptPrompt {
+    /// Prompt ID from the script's ID column, stored with the recording
+    pub id: Option<String>,
+    pub text: String,
+    /// Elicitation fields, for a question rather than a sentence to read
+    pub fields: Option<PromptFields>,
+    /// Text of the prompt in the language it is translated into
+    pub translation: Option<String>,
+    /// How to say a difficult word or phrase of the prompt
+    pub hint: Option<String>,
+    /// A native speaker reading the prompt, played on request before
+    /// recording
+    pub exemplar: Option<PathBuf>,
+}
+
+/// A structured prompt: a question put to the speaker rather than text to
+/// read out, stored with the recording as its metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptFields {
+    /// The stimulus question
+    pub question: String,
+    /// Kind of answer expected (e.g. "a number", "a short story")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer_type: Option<String>,
+    /// What to ask once the speaker has answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_up: Option<String>,
+}
+
+/// Totals of a script session
+#[derive(Debug, Default)]
+pub struct ScriptSummary {
+    pub recorded: usize,
+    /// Takes discarded in review
+    pub discarded: usize,
+    /// Prompts passed over, by position in the script (from 1)
+    pub skipped: Vec<usize>,
+    /// Prompts never reached because the session was stopped early
+    pub remaining: usize,
+}
+
+/// Prompts of a script file
+///
+/// Plain text has one prompt per line; blank lines and lines starting with
+/// `#` are ignored. CSV and TSV files (by extension) have a header row with
+/// a text column (`prompt`, `text` or `sentence`) and optionally an ID
+/// column (`id` or `prompt_id`); without a recognised header the first of
+/// two columns is the ID and the second the text.
+///
+/// Elicitation scripts name the text column `question` (or `stimulus`) and
+/// may add `answer_type` and `follow_up` columns; their rows become
+/// structured prompts. A `translation` column holds the text read for
+/// `record --translate-to`, a `hint` column a pronunciation hint, and an
+/// `exemplar` column the path of a clip of the prompt read aloud, relative
+/// to the script.
+pub fn load_script(path: &Path) -> Result<Vec<ScriptPrompt>> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let prompts = match extension.as_deref() {
+        Some("csv") => load_table(path, b',')?,
+        Some("tsv") => load_table(path, b'\t')?,
+        _ => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| ScriptPrompt {
+                id: None,
+                text: line.to_string(),
+                fields: None,
+                translation: None,
+                hint: None,
+                exemplar: None,
+            })
+            .collect(),
+    };
+    if prompts.is_empty() {
+        anyhow::bail!("{} has no prompts", path.display());
+    }
+    Ok(prompts)
+}
+
+fn load_table(path: &Path, delimiter: u8) -> Result<Vec<ScriptPrompt>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut rows = reader.records();
+
+    let Some(first) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let first = first.context("Invalid script row 1")?;
+    let column = |names: &[&str]| {
+        first
+            .iter()
+            .position(|header| names.contains(&header.trim().to_ascii_lowercase().as_str()))
+    };
+    let question_column = column(&["question", "stimulus"]);
+    let (id_column, text_column, header) =
+        match question_column.or_else(|| column(&["prompt", "text", "sentence"])) {
+            Some(text) => (column(&["id", "prompt_id"]), text, true),
+            None if first.len() >= 2 => (Some(0), 1, false),
+            None => (None, 0, false),
+        };
+    let answer_column = header
+        .then(|| column(&["answer_type", "answer", "expected_answer"]))
+        .flatten();
+    let follow_up_column = header
+        .then(|| column(&["follow_up", "followup", "follow-up"]))
+        .flatten();
+    let translation_column = header.then(|| column(&["translation"])).flatten();
+    let hint_column = header.then(|| column(&["hint", "pronunciation"])).flatten();
+    let exemplar_column = header
+        .then(|| column(&["exemplar", "exemplar_audio", "audio"]))
+        .flatten();
+    let script_dir = path.parent().unwrap_or(Path::new("."));
+    let structured =
+        question_column.is_some() || answer_column.is_some() || follow_up_column.is_some();
+
+    let mut prompts = Vec::new();
+    let mut add = |record: &csv::StringRecord| {
+        let Some(text) = record.get(text_column).map(str::trim) else {
+            return;
+        };
+        if text.is_empty() {
+            return;
+        }
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        prompts.push(ScriptPrompt {
+            id: field(id_column),
+            text: text.to_string(),
+            fields: structured.then(|| PromptFields {
+                question: text.to_string(),
+                answer_type: field(answer_column),
+                follow_up: field(follow_up_column),
+            }),
+            translation: field(translation_column),
+            hint: field(hint_column),
+            exemplar: field(exemplar_column).map(|exemplar| script_dir.join(exemplar)),
+        });
+    };
+    if !header {
+        add(&first);
+    }
+    for (row, record) in rows.enumerate() {
+        add(&record.with_context(|| format!("Invalid script row {}", row + 2))?);
+    }
+    Ok(prompts)
+}
@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::update;
+
+const SESSION_COLUMNS: &str = "id, started_at, room_tone_path, noise_floor_db, location, \
+     microphone, environment, os, app_version, input_device";
+
+/// Kind of place a session is recorded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    Studio,
+    QuietRoom,
+    Home,
+    Office,
+    Classroom,
+    Outdoor,
+    Market,
+    Street,
+    Vehicle,
+    Other,
+}
+
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Studio => "studio",
+            Environment::QuietRoom => "quiet-room",
+            Environment::Home => "home",
+            Environment::Office => "office",
+            Environment::Classroom => "classroom",
+            Environment::Outdoor => "outdoor",
+            Environment::Market => "market",
+            Environment::Street => "street",
+            Environment::Vehicle => "vehicle",
+            Environment::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Environment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "studio" => Ok(Environment::Studio),
+            "quiet-room" | "quiet" => Ok(Environment::QuietRoom),
+            "home" => Ok(Environment::Home),
+            "office" => Ok(Environment::Office),
+            "classroom" => Ok(Environment::Classroom),
+            "outdoor" | "outdoors" => Ok(Environment::Outdoor),
+            "market" => Ok(Environment::Market),
+            "street" => Ok(Environment::Street),
+            "vehicle" => Ok(Environment::Vehicle),
+            "other" => Ok(Environment::Other),
+            _ => Err(format!(
+                "Unknown environment: {s} (use studio, quiet-room, home, office, classroom, \
+                 outdoor, market, street, vehicle or other)"
+            )),
+        }
+    }
+}
+
+/// Where and with what a session is recorded, as the contributor describes it
+#[derive(Debug, Clone, Default)]
+pub struct SessionDetails {
+    /// Free-form place label (e.g. "Kisumu market")
+    pub location: Option<String>,
+    /// Microphone model
+    pub microphone: Option<String>,
+    pub environment: Option<Environment>,
+}
+
+impl SessionDetails {
+    /// Whether `session` was described differently, so a recording made with
+    /// these details belongs in a new session
+    pub fn conflicts_with(&self, session: &Session) -> bool {
+        let differs = |given: Option<&str>, stored: Option<&str>| {
+            given.is_some_and(|given| Some(given) != stored)
+        };
+        differs(self.location.as_deref(), session.location.as_deref())
+            || differs(self.microphone.as_deref(), session.microphone.as_deref())
+            || differs(
+                self.environment.as_ref().map(Environment::as_str),
+                session.environment.as_deref(),
+            )
+    }
+}
+
+/// Restricts recordings to the sessions matching all of its fields
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    pub session: Option<String>,
+    pub environment: Option<Environment>,
+    pub location: Option<String>,
+}
+
+impl SessionFilter {
+    /// SQL condition on the session ID `column` of a query, starting with
+    /// ` AND` (empty without filters), and its parameters in order
+    pub fn condition(&self, column: &str) -> (String, Vec<String>) {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+        if let Some(session) = &self.session {
+            clauses.push("id = ?");
+            params.push(session.clone());
+        }
+        if let Some(environment) = self.environment {
+            clauses.push("environment = ?");
+            params.push(environment.as_str().to_string());
+        }
+        if let Some(location) = &self.location {
+            clauses.push("location = ? COLLATE NOCASE");
+            params.push(location.clone());
+        }
+        if clauses.is_empty() {
+            return (String::new(), params);
+        }
+        (
+            format!(
+                " AND {column} IN (SELECT id FROM sessions WHERE {})",
+                clauses.join(" AND ")
+            ),
+            params,
+        )
+    }
+}
+
+/// A run of recordings made in the same place, sharing one room tone capture
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: String,
+    pub started_at: i64,
+    /// Room tone captured at the start of the session
+    pub room_tone_path: Option<String>,
+    /// Noise floor measured from the room tone, in dBFS
+    pub noise_floor_db: Option<f32>,
+    pub location: Option<String>,
+    pub microphone: Option<String>,
+    pub environment: Option<String>,
+    /// Operating system and platform of the recording machine
+    pub os: Option<String>,
+    /// cowcow version the session was recorded with
+    pub app_version: Option<String>,
+    /// Input device the session started on
+    pub input_device: Option<String>,
+}
+
+impl Session {
+    /// Environment, location and microphone, as far as they are known
+    pub fn describe(&self) -> String {
+        let parts: Vec<&str> = [&self.environment, &self.location, &self.microphone]
+            .into_iter()
+            .filter_map(|part| part.as_deref())
+            .collect();
+        if parts.is_empty() {
+            "no details".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// A session with how many recordings were made in it
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SessionListing {
+    #[sqlx(flatten)]
+    pub session: Session,
+    pub recordings: i64,
+}
+
+/// The most recent session, if a recording was made in it within
+/// `record.session_timeout_mins`
+pub async fn current_session(db: &SqlitePool, config: &Config) -> Result<Option<Session>> {
+    let cutoff = chrono::Utc::now().timestamp() - config.record.session_timeout_mins as i64 * 60;
+
+    sqlx::query_as::<_, Session>(&format!(
+        r#"
+        SELECT {SESSION_COLUMNS}
+        FROM sessions s
+        WHERE MAX(
+            s.started_at,
+            COALESCE((SELECT MAX(created_at) FROM recordings r WHERE r.session_id = s.id), 0)
+        ) >= ?
+        ORDER BY s.started_at DESC
+        LIMIT 1
+        "#
+    ))
+    .bind(cutoff)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch current session")
+}
+
+pub async fn get_session(db: &SqlitePool, id: &str) -> Result<Option<Session>> {
+    sqlx::query_as::<_, Session>(&format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?"
+    ))
+    .bind(id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch session")
+}
+
+/// Sessions, newest first, with their recording counts
+pub async fn list_sessions(db: &SqlitePool, filter: &SessionFilter) -> Result<Vec<SessionListing>> {
+    let (condition, params) = filter.condition("s.id");
+    let query = format!(
+        r#"
+        SELECT {SESSION_COLUMNS},
+            (SELECT COUNT(*) FROM recordings r WHERE r.session_id = s.id) AS recordings
+        FROM sessions s
+        WHERE 1=1{condition}
+        ORDER BY started_at DESC
+        "#
+    );
+    let mut query = sqlx::query_as::<_, SessionListing>(&query);
+    for param in &params {
+        query = query.bind(param);
+    }
+    query
+        .fetch_all(db)
+        .await
+        .context("Failed to fetch sessions")
+}
+
+/// Start a session described by `details` on `input_device`, saving its
+/// room tone (if any) under `room_tone_dir`; the machine it is recorded on
+/// is noted automatically
+pub async fn create_session(
+    db: &SqlitePool,
+    room_tone: Option<(&[f32], hound::WavSpec)>,
+    noise_floor_db: Option<f32>,
+    room_tone_dir: &Path,
+    details: &SessionDetails,
+    input_device: &str,
+) -> Result<Session> {
+    let id = Uuid::new_v4().to_string();
+
+    let room_tone_path = match room_tone {
+        Some((samples, spec)) => {
+            fs::create_dir_all(room_tone_dir).with_context(|| {
+                format!(
+                    "Failed to create room tone directory: {}",
+                    room_tone_dir.display()
+                )
+            })?;
+            let path = room_tone_dir.join(format!("{id}.wav"));
+            let mut writer = hound::WavWriter::create(&path, spec)?;
+            for &sample in samples {
+                cowcow_core::wav::write_sample(&mut writer, sample)?;
+            }
+            writer.finalize()?;
+            Some(path.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+
+    let session = Session {
+        id,
+        started_at: chrono::Utc::now().timestamp(),
+        room_tone_path,
+        noise_floor_db,
+        location: details.location.clone(),
+        microphone: details.microphone.clone(),
+        environment: details.environment.map(|env| env.as_str().to_string()),
+        os: Some(os_description()),
+        app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        input_device: Some(input_device.to_string()),
+    };
+
+    sqlx::query(&format!(
+        "INSERT INTO sessions ({SESSION_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    ))
+    .bind(&session.id)
+    .bind(session.started_at)
+    .bind(&session.room_tone_path)
+    .bind(session.noise_floor_db)
+    .bind(&session.location)
+    .bind(&session.microphone)
+    .bind(&session.environment)
+    .bind(&session.os)
+    .bind(&session.app_version)
+    .bind(&session.input_device)
+    .execute(db)
+    .await
+    .context("Failed to insert session")?;
+
+    info!("Started session {}", session.id);
+    Ok(session)
+}
+
+/// Sessions of the given recordings, with the ids of the recordings in each
+pub async fn sessions_for_recordings(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<Vec<(Session, Vec<String>)>> {
+    let links: Vec<(String, String)> = sqlx::query_as(
+        "SELECT id, session_id FROM recordings WHERE session_id IS NOT NULL ORDER BY created_at",
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recording sessions")?;
+
+    let mut sessions: Vec<(Session, Vec<String>)> = Vec::new();
+    for (recording_id, session_id) in links {
+        if !recording_ids.contains(&recording_id) {
+            continue;
+        }
+        if let Some((_, ids)) = sessions.iter_mut().find(|(s, _)| s.id == session_id) {
+            ids.push(recording_id);
+            continue;
+        }
+
+        let session = sqlx::query_as::<_, Session>(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?"
+        ))
+        .bind(&session_id)
+        .fetch_optional(db)
+        .await
+        .context("Failed to fetch session")?;
+        if let Some(session) = session {
+            sessions.push((session, vec![recording_id]));
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Distribution name where the system reports one, and the platform
+fn os_description() -> String {
+    let platform = update::platform();
+    let name = fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|release| {
+            release.lines().find_map(|line| {
+                line.strip_prefix("PRETTY_NAME=")
+                    .map(|name| name.trim_matches('"').to_string())
+            })
+        });
+    match name {
+        Some(name) => format!("{name} ({platform})"),
+        None => platform,
+    }
+}
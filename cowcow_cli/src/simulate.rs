@@ -0,0 +1,189 @@
+use anyhow::{bail, Context, Result};
+use cowcow_core::policy::{QcPolicy, QcReport, PRESETS};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+use crate::mode::RecordingMode;
+
+/// Outcome of one policy over the simulated recordings
+#[derive(Debug, Default, Serialize)]
+pub struct PolicyOutcome {
+    pub passed: usize,
+    pub failed: usize,
+    /// Audio duration of the passing recordings
+    pub passed_hours: f64,
+    /// Recordings failing each rule; one recording can fail several
+    pub rule_failures: BTreeMap<String, usize>,
+}
+
+impl PolicyOutcome {
+    fn push(&mut self, report: &QcReport, duration_secs: f64) {
+        if report.passed {
+            self.passed += 1;
+            self.passed_hours += duration_secs / 3600.0;
+        } else {
+            self.failed += 1;
+        }
+        for failure in report.failures() {
+            *self.rule_failures.entry(failure.rule.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// Historical recordings evaluated against the configured policy and a
+/// candidate one
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub days: u32,
+    pub recordings: usize,
+    pub total_hours: f64,
+    pub current: PolicyOutcome,
+    pub candidate: PolicyOutcome,
+    pub candidate_policy: QcPolicy,
+    /// Passing today, failing under the candidate
+    pub newly_rejected: usize,
+    /// Failing today, passing under the candidate
+    pub newly_accepted: usize,
+}
+
+/// The policy to simulate: a preset (or the configured policy) with
+/// `rule=value` threshold overrides applied
+///
+/// Overridden rules missing from the preset are taken from `current`.
+pub fn candidate_policy(
+    preset: Option<&str>,
+    thresholds: &[String],
+    current: &QcPolicy,
+) -> Result<QcPolicy> {
+    let mut policy = match preset {
+        Some(name) => QcPolicy::preset(name)
+            .with_context(|| format!("Unknown preset: {name} (use {})", PRESETS.join(", ")))?,
+        None => current.clone(),
+    };
+
+    for threshold in thresholds {
+        let (name, value) = threshold
+            .split_once('=')
+            .with_context(|| format!("Expected rule=value, got: {threshold}"))?;
+        let name = name.trim();
+        let value: f64 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid threshold for {name}: {value}"))?;
+
+        if !policy.set_threshold(name, value) {
+            let Some(rule) = current.rules.iter().find(|rule| rule.name == name) else {
+                bail!("Unknown QC rule: {name}");
+            };
+            let mut rule = rule.clone();
+            rule.threshold = value;
+            policy.set_rule(rule);
+        }
+    }
+
+    Ok(policy)
+}
+
+/// Re-evaluate the non-archived recordings of the last `days` days against
+/// `current` and `candidate`
+///
+/// Music-mode recordings have a policy of their own and are left out.
+pub async fn simulate(
+    db: &SqlitePool,
+    days: u32,
+    current: &QcPolicy,
+    candidate: QcPolicy,
+) -> Result<SimulationReport> {
+    let since = chrono::Utc::now().timestamp() - days as i64 * 86_400;
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT qc_metrics FROM recordings WHERE archived = 0 AND created_at >= ?")
+            .bind(since)
+            .fetch_all(db)
+            .await
+            .context("Failed to fetch recordings")?;
+
+    let mut report = SimulationReport {
+        days,
+        recordings: 0,
+        total_hours: 0.0,
+        current: PolicyOutcome::default(),
+        candidate: PolicyOutcome::default(),
+        candidate_policy: candidate,
+        newly_rejected: 0,
+        newly_accepted: 0,
+    };
+
+    for (qc_metrics,) in rows {
+        let metrics: serde_json::Value = serde_json::from_str(&qc_metrics).unwrap_or_default();
+        if RecordingMode::of_metrics(&metrics) == RecordingMode::Music {
+            continue;
+        }
+        report.recordings += 1;
+        let duration_secs = metrics
+            .get("duration_secs")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        report.total_hours += duration_secs / 3600.0;
+
+        let before = current.evaluate_json(&metrics);
+        let after = report.candidate_policy.evaluate_json(&metrics);
+        match (before.passed, after.passed) {
+            (true, false) => report.newly_rejected += 1,
+            (false, true) => report.newly_accepted += 1,
+            _ => {}
+        }
+        report.current.push(&before, duration_secs);
+        report.candidate.push(&after, duration_secs);
+    }
+
+    Ok(report)
+}
+
+/// Print a simulation report side by side
+pub fn print_report(report: &SimulationReport, label: &str) {
+    println!(
+        "🧪 QC simulation: {label} on {} recordings ({:.1} h) from the last {} days",
+        report.recordings, report.total_hours, report.days
+    );
+    if report.recordings == 0 {
+        return;
+    }
+
+    println!("  {:<14} {:>10} {:>10}", "", "CURRENT", "CANDIDATE");
+    println!(
+        "  {:<14} {:>10} {:>10}",
+        "Pass", report.current.passed, report.candidate.passed
+    );
+    println!(
+        "  {:<14} {:>10} {:>10}",
+        "Fail", report.current.failed, report.candidate.failed
+    );
+    println!(
+        "  {:<14} {:>10.2} {:>10.2}",
+        "Dataset hours", report.current.passed_hours, report.candidate.passed_hours
+    );
+    println!(
+        "  Newly rejected: {} | Newly accepted: {}",
+        report.newly_rejected, report.newly_accepted
+    );
+
+    println!("\nCandidate rules:");
+    for rule in &report.candidate_policy.rules {
+        let failures = report
+            .candidate
+            .rule_failures
+            .get(&rule.name)
+            .copied()
+            .unwrap_or(0);
+        let current = report
+            .current
+            .rule_failures
+            .get(&rule.name)
+            .map_or("-".to_string(), |count| count.to_string());
+        println!(
+            "  {:<20} {:<18} {:>8}  fails {:>5} (now {})",
+            rule.name, rule.metric, rule.threshold, failures, current
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::Config;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Speaker {
+    pub id: String,
+    pub name: String,
+    pub is_minor: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct GuardianConsent {
+    pub id: String,
+    pub speaker_id: String,
+    pub guardian_name: String,
+    pub method: String,
+    pub consent_path: Option<String>,
+    pub granted_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+/// A speaker's own agreement to a version of the consent form
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SpeakerConsent {
+    pub id: String,
+    pub speaker_id: String,
+    pub form_version: String,
+    /// "typed" or "recording"
+    pub method: String,
+    pub consent_path: Option<String>,
+    pub granted_at: i64,
+    pub withdrawn_at: Option<i64>,
+}
+
+/// Where a speaker stands against the consent form in force
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentStatus {
+    /// Agreed to the current form
+    Valid,
+    /// No form is in force (`record.consent_form_version` unset) and the
+    /// speaker has not agreed to one
+    NotRequired,
+    /// Never agreed to a form
+    Missing,
+    /// Agreed to an earlier version of the form
+    Outdated,
+    Withdrawn,
+}
+
+impl ConsentStatus {
+    /// Whether the speaker may record and upload
+    pub fn is_cleared(&self) -> bool {
+        matches!(self, ConsentStatus::Valid | ConsentStatus::NotRequired)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentStatus::Valid => "valid",
+            ConsentStatus::NotRequired => "not_required",
+            ConsentStatus::Missing => "missing",
+            ConsentStatus::Outdated => "outdated",
+            ConsentStatus::Withdrawn => "withdrawn",
+        }
+    }
+
+    /// Status given the speaker's latest consent and the form version in
+    /// force, if any
+    pub fn of(consent: Option<&SpeakerConsent>, form_version: Option<&str>) -> Self {
+        match (consent, form_version) {
+            (Some(consent), _) if consent.withdrawn_at.is_some() => ConsentStatus::Withdrawn,
+            (Some(consent), Some(version)) if consent.form_version != version => {
+                ConsentStatus::Outdated
+            }
+            (Some(_), _) => ConsentStatus::Valid,
+            (None, Some(_)) => ConsentStatus::Missing,
+            (None, None) => ConsentStatus::NotRequired,
+        }
+    }
+}
+
+impl GuardianConsent {
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
+
+pub async fn add_speaker(db: &SqlitePool, name: &str, is_minor: bool) -> Result<Speaker> {
+    let speaker = Speaker {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        is_minor,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    sqlx::query("INSERT INTO speakers (id, name, is_minor, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&speaker.id)
+        .bind(&speaker.name)
+        .bind(speaker.is_minor)
+        .bind(speaker.created_at)
+        .execute(db)
+        .await
+        .context("Failed to insert speaker")?;
+
+    info!("Added speaker: {} ({})", speaker.name, speaker.id);
+    Ok(speaker)
+}
+
+pub async fn get_speaker(db: &SqlitePool, id: &str) -> Result<Option<Speaker>> {
+    sqlx::query_as::<_, Speaker>("SELECT id, name, is_minor, created_at FROM speakers WHERE id = ?")
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .context("Failed to fetch speaker")
+}
+
+pub async fn list_speakers(db: &SqlitePool) -> Result<Vec<Speaker>> {
+    sqlx::query_as::<_, Speaker>(
+        "SELECT id, name, is_minor, created_at FROM speakers ORDER BY created_at ASC",
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch speakers")
+}
+
+/// Store a guardian consent for a minor speaker
+///
+/// When `audio_file` is given the spoken consent clip is copied into the
+/// data directory so it travels with the rest of the library.
+pub async fn record_guardian_consent(
+    db: &SqlitePool,
+    config: &Config,
+    speaker: &Speaker,
+    guardian_name: &str,
+    audio_file: Option<&Path>,
+    valid_days: u32,
+) -> Result<GuardianConsent> {
+    let id = Uuid::new_v4().to_string();
+    let granted_at = chrono::Utc::now().timestamp();
+    let expires_at = granted_at + valid_days as i64 * 24 * 60 * 60;
+
+    let (method, consent_path) = match audio_file {
+        Some(source) => ("recording", Some(store_consent_clip(config, &id, source)?)),
+        None => ("typed", None),
+    };
+
+    let consent = GuardianConsent {
+        id,
+        speaker_id: speaker.id.clone(),
+        guardian_name: guardian_name.to_string(),
+        method: method.to_string(),
+        consent_path,
+        granted_at,
+        expires_at,
+        revoked_at: None,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO guardian_consents
+            (id, speaker_id, guardian_name, method, consent_path, granted_at, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&consent.id)
+    .bind(&consent.speaker_id)
+    .bind(&consent.guardian_name)
+    .bind(&consent.method)
+    .bind(&consent.consent_path)
+    .bind(consent.granted_at)
+    .bind(consent.expires_at)
+    .execute(db)
+    .await
+    .context("Failed to store guardian consent")?;
+
+    info!(
+        "Recorded guardian consent for speaker {} (expires {})",
+        speaker.id, consent.expires_at
+    );
+    Ok(consent)
+}
+
+/// Copy a spoken consent clip into the data directory, so it travels with
+/// the rest of the library, returning where it was stored
+fn store_consent_clip(config: &Config, id: &str, source: &Path) -> Result<String> {
+    let consent_dir = config.data_dir().join("consent");
+    fs::create_dir_all(&consent_dir).with_context(|| {
+        format!(
+            "Failed to create consent directory: {}",
+            consent_dir.display()
+        )
+    })?;
+
+    let extension = source
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "wav".to_string());
+    let dest = consent_dir.join(format!("{id}.{extension}"));
+    fs::copy(source, &dest)
+        .with_context(|| format!("Failed to copy consent recording: {}", source.display()))?;
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Store the answer given after the spoken consent explanation at `path`
+/// was played, so the agreement can be shown later
+pub async fn record_consent_explanation(
+    db: &SqlitePool,
+    speaker_id: Option<&str>,
+    lang: &str,
+    path: &Path,
+    agreed: bool,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO consent_explanations
+            (id, speaker_id, lang, explanation_path, agreed, answered_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(speaker_id)
+    .bind(lang)
+    .bind(path.to_string_lossy())
+    .bind(agreed)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(db)
+    .await
+    .context("Failed to store consent answer")?;
+    Ok(())
+}
+
+/// Store a speaker's agreement to `form_version` of the consent form,
+/// optionally with a spoken consent clip
+pub async fn record_speaker_consent(
+    db: &SqlitePool,
+    config: &Config,
+    speaker: &Speaker,
+    form_version: &str,
+    audio_file: Option<&Path>,
+) -> Result<SpeakerConsent> {
+    let id = Uuid::new_v4().to_string();
+    let (method, consent_path) = match audio_file {
+        Some(source) => ("recording", Some(store_consent_clip(config, &id, source)?)),
+        None => ("typed", None),
+    };
+    let consent = SpeakerConsent {
+        id,
+        speaker_id: speaker.id.clone(),
+        form_version: form_version.to_string(),
+        method: method.to_string(),
+        consent_path,
+        granted_at: chrono::Utc::now().timestamp(),
+        withdrawn_at: None,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO speaker_consents
+            (id, speaker_id, form_version, method, consent_path, granted_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&consent.id)
+    .bind(&consent.speaker_id)
+    .bind(&consent.form_version)
+    .bind(&consent.method)
+    .bind(&consent.consent_path)
+    .bind(consent.granted_at)
+    .execute(db)
+    .await
+    .context("Failed to store speaker consent")?;
+
+    info!(
+        "Recorded consent to form {} for speaker {}",
+        consent.form_version, speaker.id
+    );
+    Ok(consent)
+}
+
+/// Most recent consent a speaker gave, withdrawn or not
+pub async fn latest_speaker_consent(
+    db: &SqlitePool,
+    speaker_id: &str,
+) -> Result<Option<SpeakerConsent>> {
+    sqlx::query_as::<_, SpeakerConsent>(
+        r#"
+        SELECT id, speaker_id, form_version, method, consent_path, granted_at, withdrawn_at
+        FROM speaker_consents
+        WHERE speaker_id = ?
+        ORDER BY granted_at DESC, rowid DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(speaker_id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch speaker consent")
+}
+
+pub async fn withdraw_speaker_consent(db: &SqlitePool, speaker_id: &str) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE speaker_consents SET withdrawn_at = ? WHERE speaker_id = ? AND withdrawn_at IS NULL",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .bind(speaker_id)
+    .execute(db)
+    .await
+    .context("Failed to withdraw speaker consent")?;
+
+    Ok(result.rows_affected())
+}
+
+/// Where a speaker stands against the consent form in force
+pub async fn consent_status(
+    db: &SqlitePool,
+    config: &Config,
+    speaker_id: &str,
+) -> Result<(ConsentStatus, Option<SpeakerConsent>)> {
+    let consent = latest_speaker_consent(db, speaker_id).await?;
+    let status = ConsentStatus::of(
+        consent.as_ref(),
+        config.record.consent_form_version.as_deref(),
+    );
+    Ok((status, consent))
+}
+
+pub async fn revoke_guardian_consent(db: &SqlitePool, speaker_id: &str) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE guardian_consents SET revoked_at = ? WHERE speaker_id = ? AND revoked_at IS NULL",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .bind(speaker_id)
+    .execute(db)
+    .await
+    .context("Failed to revoke guardian consent")?;
+
+    Ok(result.rows_affected())
+}
+
+/// Most recent guardian consent on file for a speaker, valid or not
+pub async fn latest_guardian_consent(
+    db: &SqlitePool,
+    speaker_id: &str,
+) -> Result<Option<GuardianConsent>> {
+    sqlx::query_as::<_, GuardianConsent>(
+        r#"
+        SELECT id, speaker_id, guardian_name, method, consent_path,
+               granted_at, expires_at, revoked_at
+        FROM guardian_consents
+        WHERE speaker_id = ?
+        ORDER BY granted_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(speaker_id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to fetch guardian consent")
+}
+
+/// Whether recordings from this speaker may leave the device
+///
+/// Speakers need their own consent to the form in force, if there is one;
+/// minors also need an unexpired, unrevoked guardian consent.
+pub async fn speaker_cleared_for_upload(
+    db: &SqlitePool,
+    config: &Config,
+    speaker: &Speaker,
+) -> Result<bool> {
+    speaker_cleared(db, config.record.consent_form_version.as_deref(), speaker).await
+}
+
+/// Whether a speaker's recordings may leave the device: consent to the
+/// form in force that was not withdrawn, and for minors a guardian's
+/// consent that has not expired
+pub async fn speaker_cleared(
+    db: &SqlitePool,
+    form_version: Option<&str>,
+    speaker: &Speaker,
+) -> Result<bool> {
+    let consent = latest_speaker_consent(db, &speaker.id).await?;
+    if !ConsentStatus::of(consent.as_ref(), form_version).is_cleared() {
+        return Ok(false);
+    }
+    if !speaker.is_minor {
+        return Ok(true);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    Ok(latest_guardian_consent(db, &speaker.id)
+        .await?
+        .is_some_and(|consent| consent.is_valid_at(now)))
+}
+
+pub fn prompt_for_guardian_confirmation(speaker: &Speaker) -> Result<String> {
+    use std::io::{self, Write};
+
+    println!(
+        "Guardian consent is required for {} (minor speaker).",
+        speaker.name
+    );
+    print!("Guardian full name: ");
+    io::stdout().flush()?;
+    let mut guardian_name = String::new();
+    io::stdin().read_line(&mut guardian_name)?;
+    let guardian_name = guardian_name.trim().to_string();
+
+    if guardian_name.is_empty() {
+        return Err(anyhow::anyhow!("Guardian name cannot be empty"));
+    }
+
+    println!(
+        "I, {guardian_name}, consent to {} being recorded and to the recordings being shared.",
+        speaker.name
+    );
+    print!("Guardian, type your full name again to confirm: ");
+    io::stdout().flush()?;
+    let mut confirmation = String::new();
+    io::stdin().read_line(&mut confirmation)?;
+
+    if confirmation.trim() != guardian_name {
+        return Err(anyhow::anyhow!(
+            "Confirmation did not match guardian name, consent not recorded"
+        ));
+    }
+
+    Ok(guardian_name)
+}
+
+/// Consent status of the speakers of the given recordings, by recording ID,
+/// for exports; recordings without a speaker are left out
+pub async fn recording_consents(
+    db: &SqlitePool,
+    form_version: Option<&str>,
+    recording_ids: &[String],
+) -> Result<HashMap<String, serde_json::Value>> {
+    let mut by_speaker: HashMap<String, serde_json::Value> = HashMap::new();
+    let mut consents = HashMap::new();
+    for id in recording_ids {
+        let speaker_id: Option<(Option<String>,)> =
+            sqlx::query_as("SELECT speaker_id FROM recordings WHERE id = ?")
+                .bind(id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to fetch recording speaker")?;
+        let Some(speaker_id) = speaker_id.and_then(|(speaker_id,)| speaker_id) else {
+            continue;
+        };
+        if !by_speaker.contains_key(&speaker_id) {
+            let consent = latest_speaker_consent(db, &speaker_id).await?;
+            let status = ConsentStatus::of(consent.as_ref(), form_version);
+            by_speaker.insert(
+                speaker_id.clone(),
+                serde_json::json!({
+                    "speaker_id": speaker_id,
+                    "status": status.as_str(),
+                    "form_version": consent.as_ref().map(|c| &c.form_version),
+                    "method": consent.as_ref().map(|c| &c.method),
+                    "granted_at": consent.as_ref().map(|c| c.granted_at),
+                    "withdrawn_at": consent.as_ref().and_then(|c| c.withdrawn_at),
+                }),
+            );
+        }
+        consents.insert(id.clone(), by_speaker[&speaker_id].clone());
+    }
+    Ok(consents)
+}
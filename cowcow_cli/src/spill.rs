@@ -0,0 +1,431 @@
+use crossbeam_queue::ArrayQueue;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread::Thread;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+
+/// How much audio a capture buffer holds at most, and whether it may spill
+/// the rest to disk
+#[derive(Debug, Clone, Copy)]
+pub struct SpillConfig {
+    /// Interleaved samples kept in memory before spilling
+    pub memory_samples: usize,
+    pub spill_to_disk: bool,
+    /// Interleaved samples per second, for reporting
+    pub samples_per_second: u64,
+}
+
+/// How much of a take had to wait in the capture buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpillStats {
+    /// Largest backlog held in memory, in seconds of audio
+    pub peak_memory_secs: f64,
+    /// Audio that went through the scratch file, in seconds
+    pub spilled_secs: f64,
+    /// Largest backlog held on disk, in seconds of audio
+    pub peak_disk_secs: f64,
+    /// Audio lost because the buffer was full and could not spill
+    pub dropped_secs: f64,
+}
+
+impl SpillStats {
+    /// Add the figures of another stream of the same take
+    pub fn merge(&mut self, other: &SpillStats) {
+        self.peak_memory_secs = self.peak_memory_secs.max(other.peak_memory_secs);
+        self.spilled_secs += other.spilled_secs;
+        self.peak_disk_secs = self.peak_disk_secs.max(other.peak_disk_secs);
+        self.dropped_secs += other.dropped_secs;
+    }
+}
+
+/// Capture buffer between the audio callback and the recording loop
+///
+/// The callback never waits: it hands its buffers to a lock-free ring and
+/// returns. A relay thread takes them off the ring and feeds the recording
+/// loop's channel in order; buffers the loop is not ready for queue in
+/// memory, then, once the memory budget is used up, in a raw PCM scratch
+/// file, so a recording loop held up by a CPU spike catches up later
+/// instead of losing audio. All file I/O happens on the relay thread.
+pub struct SpillQueue {
+    config: SpillConfig,
+    /// Captured buffers on their way to the relay thread
+    ring: ArrayQueue<Vec<f32>>,
+    /// Emptied buffers handed back for the callback to fill again, so it
+    /// rarely allocates
+    recycled: ArrayQueue<Vec<f32>>,
+    closed: AtomicBool,
+    /// Samples the callback dropped because the ring was full
+    ring_dropped_samples: AtomicU64,
+    relay: OnceLock<Thread>,
+    counters: Mutex<Counters>,
+}
+
+/// Buffers on the ring at most; at the usual 10 ms per callback this is
+/// several seconds of a stalled relay thread
+const RING_BUFFERS: usize = 512;
+
+/// How long the relay thread sleeps when it has nothing to do, unless the
+/// callback wakes it sooner
+const RELAY_IDLE: Duration = Duration::from_millis(5);
+
+/// Figures of the backlog, counted in samples; converted to seconds by
+/// [`SpillQueue::stats`]
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    peak_memory_samples: usize,
+    spilled_samples: u64,
+    peak_disk_samples: usize,
+    dropped_samples: u64,
+}
+
+/// Buffers the recording loop was not ready for, in capture order: in
+/// memory first, then in a scratch file. Owned by the relay thread.
+struct Backlog {
+    config: SpillConfig,
+    memory: VecDeque<Vec<f32>>,
+    memory_samples: usize,
+    /// Buffers queued behind the memory ones, on disk
+    scratch: Option<Scratch>,
+    /// The scratch file could not be used; later overflow is dropped
+    spill_failed: bool,
+    counters: Counters,
+}
+
+impl Backlog {
+    fn new(config: SpillConfig) -> Self {
+        Self {
+            config,
+            memory: VecDeque::new(),
+            memory_samples: 0,
+            scratch: None,
+            spill_failed: false,
+            counters: Counters::default(),
+        }
+    }
+
+    fn on_disk(&self) -> bool {
+        self.scratch
+            .as_ref()
+            .is_some_and(|scratch| scratch.buffers > 0)
+    }
+
+    /// Queue a buffer behind the others, spilling it to disk when memory is
+    /// full, or dropping it when it cannot spill
+    fn push(&mut self, buffer: Vec<f32>) {
+        // Once anything is on disk, later buffers queue behind it there
+        if !self.on_disk() && self.memory_samples + buffer.len() <= self.config.memory_samples {
+            self.memory_samples += buffer.len();
+            self.counters.peak_memory_samples =
+                self.counters.peak_memory_samples.max(self.memory_samples);
+            self.memory.push_back(buffer);
+            return;
+        }
+        if !self.config.spill_to_disk || self.spill_failed {
+            self.counters.dropped_samples += buffer.len() as u64;
+            return;
+        }
+        if self.scratch.is_none() {
+            match Scratch::create() {
+                Ok(scratch) => {
+                    info!(
+                        "Capture buffer full, spilling to {}",
+                        scratch.path.display()
+                    );
+                    self.scratch = Some(scratch);
+                }
+                Err(e) => {
+                    warn!("Failed to create capture spill file: {}", e);
+                    self.spill_failed = true;
+                    self.counters.dropped_samples += buffer.len() as u64;
+                    return;
+                }
+            }
+        }
+        let Some(scratch) = self.scratch.as_mut() else {
+            return;
+        };
+        match scratch.push(&buffer) {
+            Ok(()) => {
+                self.counters.spilled_samples += buffer.len() as u64;
+                self.counters.peak_disk_samples =
+                    self.counters.peak_disk_samples.max(scratch.samples);
+            }
+            Err(e) => {
+                warn!("Failed to spill captured audio to disk: {}", e);
+                self.spill_failed = true;
+                self.counters.dropped_samples += buffer.len() as u64;
+            }
+        }
+    }
+
+    /// Take the oldest buffer off the backlog
+    fn pop(&mut self) -> Option<Vec<f32>> {
+        if let Some(buffer) = self.memory.pop_front() {
+            self.memory_samples -= buffer.len();
+            return Some(buffer);
+        }
+        let scratch = self
+            .scratch
+            .as_mut()
+            .filter(|scratch| scratch.buffers > 0)?;
+        match scratch.pop() {
+            Ok(buffer) => Some(buffer),
+            Err(e) => {
+                warn!("Failed to read spilled audio back: {}", e);
+                self.counters.dropped_samples += scratch.samples as u64;
+                self.scratch = None;
+                self.spill_failed = true;
+                None
+            }
+        }
+    }
+
+    /// Put a buffer the recording loop refused back at the front
+    fn unpop(&mut self, buffer: Vec<f32>) {
+        self.memory_samples += buffer.len();
+        self.memory.push_front(buffer);
+    }
+}
+
+/// Raw PCM scratch file: each buffer is its sample count (u32) followed by
+/// its samples (f32), little-endian
+struct Scratch {
+    path: PathBuf,
+    writer: File,
+    reader: File,
+    buffers: usize,
+    samples: usize,
+}
+
+impl Scratch {
+    fn create() -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("cowcow-spill-{}.pcm", uuid::Uuid::new_v4()));
+        let writer = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)?;
+        let reader = File::open(&path)?;
+        Ok(Self {
+            path,
+            writer,
+            reader,
+            buffers: 0,
+            samples: 0,
+        })
+    }
+
+    fn push(&mut self, buffer: &[f32]) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(4 + buffer.len() * 4);
+        bytes.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        for sample in buffer {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        self.writer.write_all(&bytes)?;
+        self.buffers += 1;
+        self.samples += buffer.len();
+        Ok(())
+    }
+
+    fn pop(&mut self) -> std::io::Result<Vec<f32>> {
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize * 4];
+        self.reader.read_exact(&mut bytes)?;
+        let buffer: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]))
+            .collect();
+        self.buffers -= 1;
+        self.samples -= buffer.len();
+
+        // Start the file over once it is drained, so it only grows as large
+        // as the longest backlog
+        if self.buffers == 0 {
+            self.writer.set_len(0)?;
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+        Ok(buffer)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl SpillQueue {
+    /// Queue feeding `tx`, through a relay thread that stops once the queue
+    /// is closed or `tx`'s receiver is dropped
+    pub fn start(config: SpillConfig, tx: mpsc::Sender<Vec<f32>>) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            config,
+            ring: ArrayQueue::new(RING_BUFFERS),
+            recycled: ArrayQueue::new(RING_BUFFERS),
+            closed: AtomicBool::new(false),
+            ring_dropped_samples: AtomicU64::new(0),
+            relay: OnceLock::new(),
+            counters: Mutex::new(Counters::default()),
+        });
+        let relay = Arc::clone(&queue);
+        let handle = std::thread::spawn(move || relay.relay(tx));
+        let _ = queue.relay.set(handle.thread().clone());
+        queue
+    }
+
+    /// Queue a captured buffer; never waits, locks or touches the disk, so
+    /// it is safe to call from the audio callback
+    pub fn push(&self, buffer: &[f32]) {
+        if self.closed.load(Ordering::Acquire) {
+            return;
+        }
+        let mut owned = self.recycled.pop().unwrap_or_default();
+        owned.clear();
+        owned.extend_from_slice(buffer);
+        if self.ring.push(owned).is_err() {
+            self.ring_dropped_samples
+                .fetch_add(buffer.len() as u64, Ordering::Relaxed);
+        }
+        if let Some(relay) = self.relay.get() {
+            relay.unpark();
+        }
+    }
+
+    /// Move captured buffers into the backlog and on to `tx`, until closed
+    fn relay(&self, tx: mpsc::Sender<Vec<f32>>) {
+        let mut backlog = Backlog::new(self.config);
+        while !self.closed.load(Ordering::Acquire) {
+            while let Some(mut buffer) = self.ring.pop() {
+                backlog.push(buffer.clone());
+                buffer.clear();
+                let _ = self.recycled.push(buffer);
+            }
+            // Before forwarding, so a reader never sees a buffer ahead of
+            // the stats that account for it
+            *self.lock_counters() = backlog.counters;
+            while let Some(buffer) = backlog.pop() {
+                match tx.try_send(buffer) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(buffer)) => {
+                        backlog.unpop(buffer);
+                        break;
+                    }
+                    Err(TrySendError::Closed(_)) => {
+                        self.close();
+                        return;
+                    }
+                }
+            }
+            *self.lock_counters() = backlog.counters;
+            if self.ring.is_empty() {
+                std::thread::park_timeout(RELAY_IDLE);
+            }
+        }
+        // The backlog, and its scratch file, go with the thread
+        drop(backlog);
+    }
+
+    fn lock_counters(&self) -> MutexGuard<'_, Counters> {
+        self.counters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Stop relaying; whatever is still queued is discarded
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        if let Some(relay) = self.relay.get() {
+            relay.unpark();
+        }
+    }
+
+    pub fn stats(&self) -> SpillStats {
+        let counters = *self.lock_counters();
+        let ring_dropped = self.ring_dropped_samples.load(Ordering::Relaxed);
+        let secs = |samples: f64| samples / self.config.samples_per_second.max(1) as f64;
+        SpillStats {
+            peak_memory_secs: secs(counters.peak_memory_samples as f64),
+            spilled_secs: secs(counters.spilled_samples as f64),
+            peak_disk_secs: secs(counters.peak_disk_samples as f64),
+            dropped_secs: secs((counters.dropped_samples + ring_dropped) as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(memory_samples: usize, spill_to_disk: bool) -> SpillConfig {
+        SpillConfig {
+            memory_samples,
+            spill_to_disk,
+            samples_per_second: 2,
+        }
+    }
+
+    fn drain(backlog: &mut Backlog) -> Vec<f32> {
+        std::iter::from_fn(|| backlog.pop()).flatten().collect()
+    }
+
+    #[test]
+    fn test_backlog_drops_overflow_without_spilling() {
+        let mut backlog = Backlog::new(config(4, false));
+        for i in 0..3 {
+            backlog.push(vec![i as f32; 2]);
+        }
+        assert_eq!(drain(&mut backlog), vec![0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(backlog.counters.dropped_samples, 2);
+        assert_eq!(backlog.counters.spilled_samples, 0);
+        assert_eq!(backlog.counters.peak_memory_samples, 4);
+    }
+
+    #[test]
+    fn test_backlog_spills_and_drains_in_capture_order() {
+        let mut backlog = Backlog::new(config(4, true));
+        for i in 0..5 {
+            backlog.push(vec![i as f32; 2]);
+        }
+        assert_eq!(backlog.counters.spilled_samples, 6);
+        assert_eq!(backlog.counters.peak_disk_samples, 6);
+
+        // A buffer arriving while memory has room still queues behind the
+        // ones on disk
+        assert_eq!(backlog.pop(), Some(vec![0.0, 0.0]));
+        backlog.push(vec![5.0; 2]);
+        let drained = drain(&mut backlog);
+        let expected: Vec<f32> = (1..6).flat_map(|i| [i as f32; 2]).collect();
+        assert_eq!(drained, expected);
+        assert!(backlog.memory.is_empty() && !backlog.on_disk());
+        assert_eq!(backlog.counters.dropped_samples, 0);
+
+        // The drained scratch file starts over
+        backlog.push(vec![6.0; 2]);
+        assert!(!backlog.on_disk());
+    }
+
+    #[test]
+    fn test_queue_relays_everything_to_a_late_reader() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let queue = SpillQueue::start(config(4, true), tx);
+        for i in 0..50 {
+            queue.push(&[i as f32, i as f32]);
+        }
+        for i in 0..50 {
+            assert_eq!(rx.blocking_recv(), Some(vec![i as f32, i as f32]));
+        }
+        let stats = queue.stats();
+        assert!(stats.spilled_secs > 0.0);
+        assert_eq!(stats.dropped_secs, 0.0);
+
+        queue.close();
+        assert_eq!(rx.blocking_recv(), None);
+    }
+}
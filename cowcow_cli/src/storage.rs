@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use cowcow_core::decode;
+use cowcow_core::timeline::QcTimeline;
+
+use crate::alignment::CaptureAlignment;
+use crate::config::Config;
+use crate::review;
+
+/// Marker file proving an external recordings directory is actually mounted
+///
+/// An unmounted SD card usually leaves an empty mount point behind, so the
+/// directory existing is not enough; writing there would silently fill
+/// internal storage.
+pub const STORAGE_MARKER: &str = ".cowcow-storage";
+
+/// Where new recordings should be written
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingsLocation {
+    /// The configured recordings directory
+    Primary(PathBuf),
+    /// Internal spool used while the configured directory is unavailable
+    Spool(PathBuf),
+}
+
+impl RecordingsLocation {
+    pub fn path(&self) -> &Path {
+        match self {
+            RecordingsLocation::Primary(path) | RecordingsLocation::Spool(path) => path,
+        }
+    }
+}
+
+/// Where the unfiltered capture of a filtered recording is kept (`<id>.raw.wav`)
+pub fn raw_path(wav_path: &Path) -> PathBuf {
+    wav_path.with_extension("raw.wav")
+}
+
+/// Where a recording's audio is: the same `<lang>/<file>` under
+/// `recordings_dir` if it is there, so a moved or copied data directory (or
+/// `--data-dir`) reads its own files, else the stored path
+pub fn locate_audio(stored: &str, recordings_dir: &Path) -> PathBuf {
+    let stored = Path::new(stored);
+    let mut tail: Vec<_> = stored.components().rev().take(2).collect();
+    tail.reverse();
+    let local = tail
+        .iter()
+        .fold(recordings_dir.to_path_buf(), |path, part| path.join(part));
+    if local.exists() {
+        local
+    } else {
+        stored.to_path_buf()
+    }
+}
+
+/// A recording's audio (trimmed, original and raw), QC timeline and capture
+/// timing, whether they exist or not
+pub fn recording_files(wav_path: &Path) -> [PathBuf; 5] {
+    [
+        wav_path.to_path_buf(),
+        QcTimeline::sidecar_path(wav_path),
+        CaptureAlignment::sidecar_path(wav_path),
+        review::original_path(wav_path),
+        raw_path(wav_path),
+    ]
+}
+
+/// Remove a recording's audio (trimmed, original and raw), QC timeline and
+/// capture timing, whichever exist
+pub fn remove_recording_files(wav_path: &Path) -> Result<()> {
+    for path in recording_files(wav_path) {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the configured recordings directory can be written to right now
+pub fn recordings_dir_available(config: &Config) -> bool {
+    match &config.storage.recordings_dir {
+        Some(dir) => dir.join(STORAGE_MARKER).is_file(),
+        None => true,
+    }
+}
+
+/// Resolve the directory new recordings go to, falling back to the spool
+pub fn recordings_location(config: &Config) -> Result<RecordingsLocation> {
+    let location = if recordings_dir_available(config) {
+        RecordingsLocation::Primary(config.recordings_dir())
+    } else {
+        warn!(
+            "Recordings directory {} is not mounted, spooling to {}",
+            config.recordings_dir().display(),
+            config.spool_dir().display()
+        );
+        RecordingsLocation::Spool(config.spool_dir())
+    };
+
+    fs::create_dir_all(location.path()).with_context(|| {
+        format!(
+            "Failed to create recordings directory: {}",
+            location.path().display()
+        )
+    })?;
+    Ok(location)
+}
+
+/// Create an external recordings directory and mark it as cowcow storage
+pub fn prepare_recordings_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create recordings directory: {}", dir.display()))?;
+    fs::write(dir.join(STORAGE_MARKER), "")
+        .with_context(|| format!("Failed to write storage marker in {}", dir.display()))?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct MigrationSummary {
+    pub moved: usize,
+    pub failed: usize,
+}
+
+/// Move spooled recordings into the configured recordings directory
+///
+/// Files are copied before the spooled original is removed, since the two
+/// directories are usually on different filesystems. Each recording's
+/// `wav_path` is updated once its file is safely in place.
+pub async fn migrate_spool(db: &SqlitePool, config: &Config) -> Result<MigrationSummary> {
+    let mut summary = MigrationSummary::default();
+    let spool_dir = config.spool_dir();
+
+    if config.storage.recordings_dir.is_none() || !spool_dir.exists() {
+        return Ok(summary);
+    }
+    if !recordings_dir_available(config) {
+        return Err(anyhow::anyhow!(
+            "Recordings directory {} is not mounted",
+            config.recordings_dir().display()
+        ));
+    }
+
+    let recordings_dir = config.recordings_dir();
+    for lang_entry in fs::read_dir(&spool_dir)? {
+        let lang_dir = lang_entry?.path();
+        if !lang_dir.is_dir() {
+            continue;
+        }
+        let lang = lang_dir.file_name().context("Invalid spool directory")?;
+        let dest_dir = recordings_dir.join(lang);
+        fs::create_dir_all(&dest_dir)?;
+
+        for file_entry in fs::read_dir(&lang_dir)? {
+            let source = file_entry?.path();
+            // QC timelines travel with their recording
+            if !is_recording_audio(&source) {
+                continue;
+            }
+            let Some(file_name) = source.file_name() else {
+                continue;
+            };
+            let dest = dest_dir.join(file_name);
+
+            match move_recording(db, &source, &dest).await {
+                Ok(()) => summary.moved += 1,
+                Err(e) => {
+                    warn!("Failed to migrate {}: {}", source.display(), e);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        // Leave the language directory behind if anything failed to move
+        let _ = fs::remove_dir(&lang_dir);
+    }
+
+    info!(
+        "Migrated {} spooled recording(s) to {}",
+        summary.moved,
+        recordings_dir.display()
+    );
+    Ok(summary)
+}
+
+async fn move_recording(db: &SqlitePool, source: &Path, dest: &Path) -> Result<()> {
+    fs::copy(source, dest).with_context(|| format!("Failed to copy to {}", dest.display()))?;
+
+    sqlx::query("UPDATE recordings SET wav_path = ? WHERE wav_path = ?")
+        .bind(dest.to_string_lossy())
+        .bind(source.to_string_lossy())
+        .execute(db)
+        .await
+        .context("Failed to update recording path")?;
+    sqlx::query("UPDATE sessions SET room_tone_path = ? WHERE room_tone_path = ?")
+        .bind(dest.to_string_lossy())
+        .bind(source.to_string_lossy())
+        .execute(db)
+        .await
+        .context("Failed to update room tone path")?;
+
+    fs::remove_file(source)
+        .with_context(|| format!("Failed to remove spooled file {}", source.display()))?;
+
+    let timeline = QcTimeline::sidecar_path(source);
+    if timeline.exists() {
+        fs::copy(&timeline, QcTimeline::sidecar_path(dest))
+            .and_then(|_| fs::remove_file(&timeline))
+            .with_context(|| format!("Failed to move QC timeline {}", timeline.display()))?;
+    }
+    let capture = CaptureAlignment::sidecar_path(source);
+    if capture.exists() {
+        fs::copy(&capture, CaptureAlignment::sidecar_path(dest))
+            .and_then(|_| fs::remove_file(&capture))
+            .with_context(|| format!("Failed to move capture timing {}", capture.display()))?;
+    }
+    Ok(())
+}
+
+/// Whether a spooled file is a recording's audio (WAV, FLAC or Opus)
+fn is_recording_audio(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+        || decode::is_compressed_recording(path)
+}
+
+/// Number of recordings currently waiting in the spool
+pub fn spooled_count(config: &Config) -> usize {
+    let Ok(langs) = fs::read_dir(config.spool_dir()) else {
+        return 0;
+    };
+    langs
+        .filter_map(|entry| fs::read_dir(entry.ok()?.path()).ok())
+        .map(|files| {
+            files
+                .filter_map(|file| file.ok())
+                .filter(|file| is_recording_audio(&file.path()))
+                .count()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn test_locate_audio_prefers_the_data_dir() {
+        let scratch = scratch_dir();
+        let root = scratch.path();
+        let original = root.join("original/recordings/sw/a.wav");
+        let copy = root.join("copy/recordings/sw/a.wav");
+        for path in [&original, &copy] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"RIFF").unwrap();
+        }
+        let stored = original.to_string_lossy();
+
+        // The copy reads its own file, though the original still exists
+        assert_eq!(locate_audio(&stored, &root.join("copy/recordings")), copy);
+        assert_eq!(
+            locate_audio(&stored, &root.join("original/recordings")),
+            original
+        );
+        // Recordings kept outside the data directory stay where they are
+        assert_eq!(locate_audio(&stored, &root.join("empty")), original);
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, Local, NaiveDate};
+use sqlx::SqlitePool;
+use std::fs;
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+/// File in the data directory whose age says when the contributor was last
+/// reminded; they are reminded at most once per [`REMINDER_INTERVAL`]
+const REMINDER_MARKER: &str = ".last-reminder";
+
+const REMINDER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 3600);
+
+/// Days in a row with at least one recording, in local time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Streak {
+    /// Run of recording days ending today, or yesterday while today is
+    /// still open
+    pub current_days: u32,
+    pub longest_days: u32,
+    pub last_recorded: Option<NaiveDate>,
+    /// Days with recordings in the last week, today included
+    pub days_this_week: u32,
+    /// Recording days, oldest first
+    days: Vec<NaiveDate>,
+}
+
+impl Streak {
+    /// Streak over the distinct `days` recordings were made on, as of `today`
+    pub fn from_days(mut days: Vec<NaiveDate>, today: NaiveDate) -> Self {
+        days.sort();
+        days.dedup();
+
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in &days {
+            run = match previous {
+                Some(previous) if day - previous == Duration::days(1) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(day);
+        }
+
+        let last_recorded = days.last().copied();
+        let current_days = match last_recorded {
+            Some(last) if today - last <= Duration::days(1) => run,
+            _ => 0,
+        };
+        let week_start = today - Duration::days(6);
+        let days_this_week = days.iter().filter(|day| **day >= week_start).count() as u32;
+
+        Self {
+            current_days,
+            longest_days: longest,
+            last_recorded,
+            days_this_week,
+            days,
+        }
+    }
+
+    /// Whole days since the last recording, if there was one
+    pub fn days_since_last(&self, today: NaiveDate) -> Option<i64> {
+        self.last_recorded.map(|last| (today - last).num_days())
+    }
+
+    pub fn recorded_on(&self, day: NaiveDate) -> bool {
+        self.days.binary_search(&day).is_ok()
+    }
+
+    /// Reminder for a contributor who has not recorded in `after_days` or
+    /// more; never for one who has not started
+    pub fn reminder(&self, today: NaiveDate, after_days: u32) -> Option<String> {
+        if after_days == 0 {
+            return None;
+        }
+        let idle = self.days_since_last(today)?;
+        if idle < after_days as i64 {
+            return None;
+        }
+        Some(format!(
+            "You haven't recorded in {idle} days. Your longest streak is {} day(s); \
+             run `cowcow record` to start a new one.",
+            self.longest_days
+        ))
+    }
+}
+
+/// Streak of the recordings in the library, archived ones included since
+/// they were recorded all the same
+pub async fn load(db: &SqlitePool) -> Result<Streak> {
+    let days: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT date(created_at, 'unixepoch', 'localtime') FROM recordings",
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recording days")?;
+    let days = days
+        .into_iter()
+        .filter_map(|(day,)| NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok())
+        .collect();
+    Ok(Streak::from_days(days, Local::now().date_naive()))
+}
+
+/// The reminder due now, if any; once it has been shown,
+/// [`mark_reminded`] holds the next one off for a day
+pub async fn due_reminder(config: &Config, db: &SqlitePool) -> Result<Option<String>> {
+    if config.reminders.after_days == 0 || config.read_only {
+        return Ok(None);
+    }
+    let marker = config.data_dir().join(REMINDER_MARKER);
+    let recently_reminded = fs::metadata(&marker)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < REMINDER_INTERVAL);
+    if recently_reminded {
+        return Ok(None);
+    }
+
+    let streak = load(db).await?;
+    Ok(streak.reminder(Local::now().date_naive(), config.reminders.after_days))
+}
+
+pub fn mark_reminded(config: &Config) {
+    if let Err(e) = fs::write(config.data_dir().join(REMINDER_MARKER), b"") {
+        warn!("Failed to note the reminder: {}", e);
+    }
+}
+
+/// Show `message` as a desktop notification, through the platform's own
+/// notifier (`osascript` on macOS, `notify-send` on other Unix systems);
+/// returns whether one was shown
+pub fn notify_desktop(message: &str) -> bool {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {message:?} with title \"cowcow\""
+        ));
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=cowcow", "cowcow", message]);
+        command
+    } else {
+        return false;
+    };
+
+    match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(status) if status.success() => {
+            info!("Sent reminder notification");
+            true
+        }
+        Ok(status) => {
+            warn!("Desktop notifier exited with {}", status);
+            false
+        }
+        Err(e) => {
+            warn!("No desktop notifier available: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use sqlx::{SqliteConnection, SqliteExecutor, SqlitePool};
+
+/// One take of a prompt, as listed by `cowcow takes list`
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Take {
+    pub id: String,
+    pub prompt: Option<String>,
+    pub created_at: i64,
+    pub qc_metrics: String,
+    pub qc_report: Option<String>,
+    pub take_selected: bool,
+    pub take_chosen: bool,
+    pub uploaded_at: Option<i64>,
+}
+
+impl Take {
+    /// Whether the take passed QC when it was recorded
+    pub fn qc_passed(&self) -> Option<bool> {
+        let report: serde_json::Value = serde_json::from_str(self.qc_report.as_deref()?).ok()?;
+        report.get("passed")?.as_bool()
+    }
+
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        let metrics: serde_json::Value = serde_json::from_str(&self.qc_metrics).ok()?;
+        metrics.get(name)?.as_f64()
+    }
+
+    /// Ranking for automatic selection: passing QC first, then the higher
+    /// SNR, then the later take
+    fn rank(&self) -> (bool, f64, i64) {
+        (
+            self.qc_passed().unwrap_or(false),
+            self.metric("snr_db").unwrap_or(f64::MIN),
+            self.created_at,
+        )
+    }
+}
+
+/// The takes of one prompt slot, in recording order
+#[derive(Debug, Clone)]
+pub struct TakeSlot {
+    pub takes: Vec<Take>,
+}
+
+impl TakeSlot {
+    /// 1-based number of the take with `id`
+    pub fn number(&self, id: &str) -> Option<usize> {
+        self.takes
+            .iter()
+            .position(|take| take.id == id)
+            .map(|i| i + 1)
+    }
+
+    pub fn selected(&self) -> Option<&Take> {
+        self.takes.iter().find(|take| take.take_selected)
+    }
+}
+
+const TAKE_COLUMNS: &str = "id, prompt, created_at, qc_metrics, qc_report, \
+     take_selected, take_chosen, uploaded_at";
+
+/// Link a new recording to the earlier takes of the same prompt by the same
+/// speaker in its session, and select the slot's best take unless one was
+/// chosen by hand
+///
+/// Returns the slot when the recording is not its first take. Recordings
+/// without a prompt or session are never linked.
+pub async fn link_take(db: &SqlitePool, recording_id: &str) -> Result<Option<TakeSlot>> {
+    let earlier: Option<(String, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT e.id, e.take_group
+        FROM recordings r
+        JOIN recordings e
+            ON e.session_id = r.session_id
+            AND e.speaker_id IS r.speaker_id
+            AND e.lang = r.lang
+            AND (e.prompt_id = r.prompt_id
+                OR (r.prompt_id IS NULL AND e.prompt_id IS NULL AND e.prompt = r.prompt))
+        WHERE r.id = ? AND e.id != r.id AND e.archived = 0
+        ORDER BY e.created_at ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(recording_id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to look up earlier takes")?;
+    let Some((first_id, group)) = earlier else {
+        return Ok(None);
+    };
+
+    let group = group.unwrap_or(first_id);
+    sqlx::query("UPDATE recordings SET take_group = ? WHERE id IN (?, ?) OR take_group = ?")
+        .bind(&group)
+        .bind(&group)
+        .bind(recording_id)
+        .bind(&group)
+        .execute(db)
+        .await?;
+
+    let slot = load_slot(db, &group).await?;
+    if slot.takes.iter().any(|take| take.take_chosen) {
+        return Ok(Some(slot));
+    }
+    select_best(db, &group, &slot).await?;
+    Ok(Some(load_slot(db, &group).await?))
+}
+
+/// Select the best take of a slot whose selected take was deleted
+pub async fn forget_take(db: &mut SqliteConnection, group: &str) -> Result<()> {
+    let slot = load_slot(&mut *db, group).await?;
+    if slot.selected().is_none() {
+        select_best(db, group, &slot).await?;
+    }
+    Ok(())
+}
+
+async fn select_best<'e>(db: impl SqliteExecutor<'e>, group: &str, slot: &TakeSlot) -> Result<()> {
+    let best = slot.takes.iter().max_by(|a, b| {
+        a.rank()
+            .partial_cmp(&b.rank())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    if let Some(best) = best {
+        set_selected(db, group, &best.id, false).await?;
+    }
+    Ok(())
+}
+
+/// The takes sharing a slot with `group`
+async fn load_slot<'e>(db: impl SqliteExecutor<'e>, group: &str) -> Result<TakeSlot> {
+    let takes = sqlx::query_as::<_, Take>(&format!(
+        "SELECT {TAKE_COLUMNS} FROM recordings WHERE take_group = ? AND archived = 0 \
+         ORDER BY created_at ASC"
+    ))
+    .bind(group)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch takes")?;
+    Ok(TakeSlot { takes })
+}
+
+/// Make `id` the one selected take of its slot; a take chosen by hand stays
+/// selected whatever later takes score
+async fn set_selected<'e>(
+    db: impl SqliteExecutor<'e>,
+    group: &str,
+    id: &str,
+    chosen: bool,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE recordings SET take_selected = (id = ?), take_chosen = (id = ? AND ?) \
+         WHERE take_group = ?",
+    )
+    .bind(id)
+    .bind(id)
+    .bind(chosen)
+    .bind(group)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Slots with more than one take, newest first; or just the slot of
+/// `recording_id`
+pub async fn list_slots(db: &SqlitePool, recording_id: Option<&str>) -> Result<Vec<TakeSlot>> {
+    let groups: Vec<(String,)> =
+        match recording_id {
+            Some(id) => {
+                sqlx::query_as(
+                    "SELECT take_group FROM recordings WHERE id = ? AND take_group IS NOT NULL",
+                )
+                .bind(id)
+                .fetch_all(db)
+                .await?
+            }
+            None => sqlx::query_as(
+                "SELECT take_group FROM recordings WHERE take_group IS NOT NULL AND archived = 0 \
+                 GROUP BY take_group ORDER BY MAX(created_at) DESC",
+            )
+            .fetch_all(db)
+            .await?,
+        };
+    let mut slots = Vec::new();
+    for (group,) in groups {
+        slots.push(load_slot(db, &group).await?);
+    }
+    Ok(slots)
+}
+
+/// Choose `recording_id` as the take of its slot that is uploaded and
+/// exported
+pub async fn select_take(db: &SqlitePool, recording_id: &str) -> Result<TakeSlot> {
+    let (group,): (Option<String>,) =
+        sqlx::query_as("SELECT take_group FROM recordings WHERE id = ?")
+            .bind(recording_id)
+            .fetch_optional(db)
+            .await?
+            .with_context(|| format!("Recording not found: {recording_id}"))?;
+    let group = group.with_context(|| {
+        format!("{recording_id} is the only take of its prompt; there is nothing to choose")
+    })?;
+    set_selected(db, &group, recording_id, true).await?;
+    load_slot(db, &group).await
+}
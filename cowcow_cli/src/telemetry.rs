@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::http;
+
+/// One anonymous usage event
+///
+/// Only the command path (never its arguments), the outcome, a coarse error
+/// class and basic device specs are recorded. No usernames, file paths,
+/// prompts or audio leave the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    /// Random per-install identifier, regenerated on every opt-in
+    pub install_id: String,
+    pub timestamp: i64,
+    pub version: String,
+    /// Subcommand path, e.g. `record` or `speakers add`
+    pub command: String,
+    pub success: bool,
+    pub error_class: Option<String>,
+    pub duration_ms: u64,
+    pub device: DeviceSpecs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSpecs {
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl DeviceSpecs {
+    fn collect(config: &Config) -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            sample_rate: config.audio.sample_rate,
+            channels: config.audio.channels,
+        }
+    }
+}
+
+/// Local buffer of events waiting to be uploaded
+pub fn buffer_path(config: &Config) -> PathBuf {
+    config.data_dir().join("telemetry.jsonl")
+}
+
+/// Coarse, non-identifying category for a command failure
+pub fn error_class(error: &anyhow::Error) -> &'static str {
+    for cause in error.chain() {
+        if cause.is::<reqwest::Error>() {
+            return "network";
+        }
+        if cause.is::<sqlx::Error>() {
+            return "database";
+        }
+        if cause.is::<hound::Error>() {
+            return "audio_format";
+        }
+        if cause.is::<cpal::BuildStreamError>()
+            || cause.is::<cpal::PlayStreamError>()
+            || cause.is::<cpal::DefaultStreamConfigError>()
+        {
+            return "audio_device";
+        }
+        if cause.is::<std::io::Error>() {
+            return "io";
+        }
+    }
+    "other"
+}
+
+/// Buffer an event for a finished command; a no-op unless the user opted in
+///
+/// Telemetry must never break a command, so failures are only logged.
+pub fn record_command(config: &Config, command: &str, duration: Duration, result: &Result<()>) {
+    let Some(install_id) = config.telemetry.install_id.clone() else {
+        return;
+    };
+    if !config.telemetry.enabled || command.is_empty() || command.starts_with("telemetry") {
+        return;
+    }
+
+    let event = TelemetryEvent {
+        install_id,
+        timestamp: chrono::Utc::now().timestamp(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        command: command.to_string(),
+        success: result.is_ok(),
+        error_class: result.as_ref().err().map(|e| error_class(e).to_string()),
+        duration_ms: duration.as_millis() as u64,
+        device: DeviceSpecs::collect(config),
+    };
+
+    if let Err(e) = append_event(config, &event) {
+        warn!("Failed to buffer telemetry event: {}", e);
+    }
+}
+
+fn append_event(config: &Config, event: &TelemetryEvent) -> Result<()> {
+    let path = buffer_path(config);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+/// Events buffered locally, oldest first
+pub fn pending_events(config: &Config) -> Result<Vec<TelemetryEvent>> {
+    let path = buffer_path(config);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read telemetry buffer: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Delete all buffered events
+pub fn clear(config: &Config) -> Result<()> {
+    let path = buffer_path(config);
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove telemetry buffer: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Send buffered events and clear the buffer on success
+pub async fn upload(config: &Config) -> Result<usize> {
+    if !config.telemetry.enabled {
+        return Err(anyhow::anyhow!(
+            "Telemetry is disabled, run `cowcow telemetry enable` first"
+        ));
+    }
+
+    let events = pending_events(config)?;
+    if events.is_empty() {
+        return Ok(0);
+    }
+
+    let endpoint = config.telemetry_endpoint();
+    http::throttle(config).await;
+    http::shared_client(config)
+        .post(&endpoint)
+        .json(&events)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send telemetry to {endpoint}"))?
+        .error_for_status()
+        .context("Telemetry upload rejected")?;
+
+    clear(config)?;
+    info!("Uploaded {} telemetry event(s)", events.len());
+    Ok(events.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+    use cowcow_server_stub::StubServer;
+
+    #[tokio::test]
+    async fn test_upload_sends_buffered_events_and_clears_them() {
+        let server = StubServer::start().await.unwrap();
+        let data_dir = scratch_dir();
+        let mut config = Config::default();
+        config.storage.data_dir = data_dir.path().to_path_buf();
+        config.api.endpoint = server.url();
+        config.telemetry.enabled = true;
+        config.telemetry.install_id = Some(uuid::Uuid::new_v4().to_string());
+
+        record_command(&config, "record", Duration::from_millis(1200), &Ok(()));
+        let failed = Err(anyhow::anyhow!("no input device"));
+        record_command(&config, "upload", Duration::from_millis(300), &failed);
+        assert_eq!(upload(&config).await.unwrap(), 2);
+
+        let events = server.telemetry_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["command"], "record");
+        assert_eq!(events[1]["error_class"], "other");
+        assert!(pending_events(&config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+//! Fixtures shared by the unit tests
+
+use sqlx::SqlitePool;
+use tempfile::TempDir;
+
+use crate::config::Config;
+
+/// A scratch directory, removed when dropped
+pub fn scratch_dir() -> TempDir {
+    TempDir::new().unwrap()
+}
+
+/// A data directory with a fresh database, removed when dropped
+pub struct TestLibrary {
+    pub config: Config,
+    pub db: SqlitePool,
+    _dir: TempDir,
+}
+
+impl TestLibrary {
+    pub async fn new() -> Self {
+        let dir = scratch_dir();
+        let mut config = Config::default();
+        config.storage.data_dir = dir.path().to_path_buf();
+        let db = crate::init_db(&config).await.unwrap();
+        Self {
+            config,
+            db,
+            _dir: dir,
+        }
+    }
+
+    /// Add a Swahili recording of "habari" with empty metrics
+    pub async fn add_recording(&self, id: &str, wav_path: &str) {
+        sqlx::query(
+            "INSERT INTO recordings (id, lang, prompt, qc_metrics, created_at, wav_path) \
+             VALUES (?, 'sw', 'habari', '{}', 0, ?)",
+        )
+        .bind(id)
+        .bind(wav_path)
+        .execute(&self.db)
+        .await
+        .unwrap();
+    }
+}
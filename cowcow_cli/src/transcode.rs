@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use cowcow_core::{decode, flac, opus};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Audio format of exported recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AudioFormat {
+    /// WAV, copied as recorded
+    #[default]
+    Wav,
+    /// Lossless FLAC, encoded without ffmpeg
+    Flac,
+    /// Opus in Ogg, encoded without ffmpeg (needs the `opus` feature)
+    Opus,
+    Mp3,
+    Ogg,
+    M4a,
+}
+
+impl AudioFormat {
+    /// Whether exporting in this format runs ffmpeg
+    pub fn needs_ffmpeg(&self) -> bool {
+        !matches!(
+            self,
+            AudioFormat::Wav | AudioFormat::Flac | AudioFormat::Opus
+        )
+    }
+
+    /// Format a recording is stored in, from its extension
+    pub fn of_recording(path: &Path) -> Self {
+        if flac::is_flac(path) {
+            AudioFormat::Flac
+        } else if opus::is_opus(path) {
+            AudioFormat::Opus
+        } else {
+            AudioFormat::Wav
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Opus => opus::OPUS_EXTENSION,
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::M4a => "m4a",
+        }
+    }
+
+    /// MIME type of exported files, as annotation tools expect it
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/x-wav",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Opus | AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::M4a => "audio/mp4",
+        }
+    }
+
+    /// ffmpeg encoder arguments; speech-quality settings that keep files
+    /// small without audible artefacts
+    fn encoder_args(&self) -> &'static [&'static str] {
+        match self {
+            AudioFormat::Wav => &["-c:a", "pcm_s16le"],
+            AudioFormat::Flac => &["-c:a", "flac"],
+            AudioFormat::Opus => &["-c:a", "libopus", "-b:a", "24k"],
+            AudioFormat::Mp3 => &["-c:a", "libmp3lame", "-q:a", "2"],
+            AudioFormat::Ogg => &["-c:a", "libvorbis", "-q:a", "5"],
+            AudioFormat::M4a => &["-c:a", "aac", "-b:a", "128k"],
+        }
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "wav" => Ok(AudioFormat::Wav),
+            "flac" => Ok(AudioFormat::Flac),
+            "opus" => Ok(AudioFormat::Opus),
+            "mp3" => Ok(AudioFormat::Mp3),
+            "ogg" | "vorbis" => Ok(AudioFormat::Ogg),
+            "m4a" | "aac" => Ok(AudioFormat::M4a),
+            _ => Err(format!(
+                "Unknown audio format: {s} (use wav, flac, opus, mp3, ogg or m4a)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Whether an `ffmpeg` binary is on the PATH
+pub fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Encode a WAV file as `format`
+///
+/// symphonia only decodes, so lossy formats are encoded by ffmpeg.
+pub fn transcode_wav(source: &Path, dest: &Path, format: AudioFormat) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(source)
+        .args(format.encoder_args())
+        .arg(dest)
+        .output()
+        .context("Failed to run ffmpeg")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to encode {}: {}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Write a recording (WAV, FLAC or Opus) to `dest` in `format`, copying it
+/// when it is already in that format
+///
+/// Compressed recordings are decoded to WAV first; `opus_bitrate` (bits per
+/// second) applies when encoding Opus.
+pub fn export_audio(
+    source: &Path,
+    dest: &Path,
+    format: AudioFormat,
+    opus_bitrate: u32,
+) -> Result<()> {
+    let source_format = AudioFormat::of_recording(source);
+    if source_format == format {
+        fs::copy(source, dest).with_context(|| format!("Failed to copy {}", source.display()))?;
+        return Ok(());
+    }
+
+    // The FLAC encoder takes 16-bit WAV only, so 24-bit and float recordings
+    // go through a 16-bit copy
+    if source_format != AudioFormat::Wav || (format == AudioFormat::Flac && !is_16_bit(source)) {
+        if format == AudioFormat::Wav {
+            return decode_to_wav(source, dest);
+        }
+        let temp_path = dest.with_extension("tmp.wav");
+        decode_to_wav(source, &temp_path)?;
+        let result = export_audio(&temp_path, dest, format, opus_bitrate);
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+
+    match format {
+        AudioFormat::Flac => flac::encode_wav_file(source, dest),
+        AudioFormat::Opus => opus::encode_wav_file(source, dest, opus_bitrate),
+        _ => transcode_wav(source, dest, format),
+    }
+}
+
+fn is_16_bit(path: &Path) -> bool {
+    hound::WavReader::open(path).is_ok_and(|reader| {
+        let spec = reader.spec();
+        spec.bits_per_sample == 16 && spec.sample_format == hound::SampleFormat::Int
+    })
+}
+
+/// Decode a recording into a 16-bit WAV file
+fn decode_to_wav(source: &Path, dest: &Path) -> Result<()> {
+    let audio = decode::decode_file(source)?;
+    let spec = hound::WavSpec {
+        channels: audio.channels,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(dest, spec)
+        .with_context(|| format!("Failed to write {}", dest.display()))?;
+    for &sample in &audio.samples {
+        writer.write_sample((sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use cowcow_core::asr::{self, WhisperModel};
+use indicatif::{ProgressBar, ProgressStyle};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::playback::Clip;
+
+/// Totals of an automatic transcription run
+#[derive(Debug, Default)]
+pub struct AutoSummary {
+    pub transcribed: usize,
+    /// Recordings whose hypothesis strays from the prompt, with its word
+    /// error rate
+    pub misreads: Vec<(String, f32)>,
+    /// Recordings whose audio is no longer on disk
+    pub missing: usize,
+    pub failed: usize,
+    /// Whether the run was interrupted; running it again carries on
+    pub cancelled: bool,
+}
+
+/// Store what was actually said in a recording (`None` to go back to the
+/// prompt); its word alignment, made against the old text, is dropped
+pub async fn set_transcript(
+    db: &SqlitePool,
+    recording_id: &str,
+    transcript: Option<&str>,
+) -> Result<()> {
+    sqlx::query("UPDATE recordings SET transcript = ?, word_alignment = NULL WHERE id = ?")
+        .bind(transcript)
+        .bind(recording_id)
+        .execute(db)
+        .await
+        .context("Failed to store transcript")?;
+    Ok(())
+}
+
+/// A recording's transcript, if it has one
+pub async fn transcript(db: &SqlitePool, recording_id: &str) -> Result<Option<String>> {
+    let stored: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT transcript FROM recordings WHERE id = ?")
+            .bind(recording_id)
+            .fetch_optional(db)
+            .await
+            .context("Failed to fetch transcript")?;
+    Ok(stored.and_then(|(stored,)| stored))
+}
+
+/// Transcripts of the given recordings that have one, by recording ID
+pub async fn recording_transcripts(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<HashMap<String, String>> {
+    let mut transcripts = HashMap::new();
+    for id in recording_ids {
+        if let Some(transcript) = transcript(db, id).await? {
+            transcripts.insert(id.clone(), transcript);
+        }
+    }
+    Ok(transcripts)
+}
+
+/// Run `model` over every recording without a hypothesis yet (all of them
+/// with `force`), optionally only one language's, storing each hypothesis
+/// with its confidence and its word error rate against the prompt
+///
+/// The hypothesis is kept apart from the transcript, which stays what a
+/// person confirmed was said. The model runs on a blocking thread, one
+/// recording at a time.
+pub async fn transcribe_library(
+    db: &SqlitePool,
+    config: &Config,
+    model: Arc<WhisperModel>,
+    lang: Option<&str>,
+    force: bool,
+    cancel: &CancellationToken,
+) -> Result<AutoSummary> {
+    let recordings: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+        r#"
+        SELECT id, lang, wav_path, CASE WHEN prompt_fields IS NULL THEN prompt END
+        FROM recordings
+        WHERE archived = 0 AND (? IS NULL OR lang = ?) AND (? OR asr_hypothesis IS NULL)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(lang)
+    .bind(lang)
+    .bind(force)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    let pb = ProgressBar::new(recordings.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.green} {pos}/{len} recordings transcribed ({eta} left)")
+            .unwrap(),
+    );
+
+    let mut summary = AutoSummary::default();
+    for (id, lang, audio_path, prompt) in recordings {
+        if cancel.is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+        pb.inc(1);
+        let audio_path = config.locate_audio(&audio_path);
+        if !audio_path.exists() {
+            summary.missing += 1;
+            continue;
+        }
+        // whisper knows languages by ISO 639-1 code; it detects any other
+        let model_lang = (lang.len() == 2).then_some(lang);
+        let model = Arc::clone(&model);
+        let hypothesis = tokio::task::spawn_blocking(move || {
+            let clip = Clip::load(&audio_path)?;
+            model.transcribe(
+                &clip.samples,
+                clip.sample_rate,
+                clip.channels,
+                model_lang.as_deref(),
+            )
+        })
+        .await
+        .context("Transcription task failed")?;
+        let hypothesis = match hypothesis {
+            Ok(hypothesis) => hypothesis,
+            Err(e) => {
+                pb.println(format!("⚠️  {id}: {e:#}"));
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let wer = prompt
+            .as_deref()
+            .filter(|prompt| !prompt.trim().is_empty())
+            .map(|prompt| asr::word_error_rate(prompt, &hypothesis.text));
+        sqlx::query(
+            "UPDATE recordings SET asr_hypothesis = ?, asr_confidence = ?, asr_wer = ? \
+             WHERE id = ?",
+        )
+        .bind(&hypothesis.text)
+        .bind(hypothesis.confidence)
+        .bind(wer)
+        .bind(&id)
+        .execute(db)
+        .await
+        .context("Failed to store hypothesis")?;
+        summary.transcribed += 1;
+        if let Some(wer) = wer.filter(|wer| *wer > config.asr.misread_wer) {
+            summary.misreads.push((id, wer));
+        }
+    }
+    pb.finish_and_clear();
+    Ok(summary)
+}
+
+/// Transcripts from a CSV file with a header row: the recording column
+/// (`id` or `recording_id`, full or a unique prefix) and the text column
+/// (`transcript` or `text`); rows are returned in file order
+pub fn load_csv(path: &Path) -> Result<Vec<(String, String)>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.trim().to_ascii_lowercase().as_str()))
+    };
+    let id_column = column(&["id", "recording_id"])
+        .context("Transcript CSV needs an id or recording_id column")?;
+    let text_column = column(&["transcript", "text"])
+        .context("Transcript CSV needs a transcript or text column")?;
+
+    let mut transcripts = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("Invalid CSV row {}", row + 2))?;
+        let (Some(id), Some(text)) = (record.get(id_column), record.get(text_column)) else {
+            continue;
+        };
+        let (id, text) = (id.trim(), text.trim());
+        if !id.is_empty() && !text.is_empty() {
+            transcripts.push((id.to_string(), text.to_string()));
+        }
+    }
+    Ok(transcripts)
+}
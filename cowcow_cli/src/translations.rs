@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// One side of a translation pair
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PairedRecording {
+    pub id: String,
+    pub lang: String,
+    pub prompt: Option<String>,
+}
+
+/// A sentence in one language and its translation spoken in another
+#[derive(Debug, Clone, Serialize)]
+pub struct TranslationPair {
+    pub pair_id: String,
+    /// The recording made first
+    pub source: PairedRecording,
+    pub target: PairedRecording,
+}
+
+/// The recording saved into `pair_id`, once its first side has been kept
+pub async fn source_of(db: &SqlitePool, pair_id: &str) -> Result<Option<PairedRecording>> {
+    sqlx::query_as(
+        "SELECT id, lang, COALESCE(transcript, prompt) AS prompt FROM recordings \
+         WHERE translation_pair = ? ORDER BY created_at ASC LIMIT 1",
+    )
+    .bind(pair_id)
+    .fetch_optional(db)
+    .await
+    .context("Failed to look up the translation source")
+}
+
+/// Whether both sides of `pair_id` were kept; a take split into parts is
+/// saved without its pair
+pub async fn is_paired(db: &SqlitePool, pair_id: &str) -> Result<bool> {
+    let sides: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM recordings WHERE translation_pair = ?")
+            .bind(pair_id)
+            .fetch_one(db)
+            .await
+            .context("Failed to look up the translation pair")?;
+    Ok(sides == 2)
+}
+
+/// Leave the recordings of `pair_id` unpaired, when its translation was
+/// never kept
+pub async fn unpair<'e>(db: impl SqliteExecutor<'e>, pair_id: &str) -> Result<()> {
+    sqlx::query("UPDATE recordings SET translation_pair = NULL WHERE translation_pair = ?")
+        .bind(pair_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Pairs with both sides among `recording_ids`, in recording order
+pub async fn pairs(db: &SqlitePool, recording_ids: &[String]) -> Result<Vec<TranslationPair>> {
+    let wanted: HashSet<&str> = recording_ids.iter().map(String::as_str).collect();
+    let rows: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT translation_pair, id, lang, COALESCE(transcript, prompt) FROM recordings \
+         WHERE translation_pair IS NOT NULL ORDER BY created_at ASC",
+    )
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch translation pairs")?;
+
+    let mut sides: HashMap<String, Vec<PairedRecording>> = HashMap::new();
+    let mut order = Vec::new();
+    for (pair_id, id, lang, prompt) in rows {
+        let entry = sides.entry(pair_id.clone()).or_insert_with(|| {
+            order.push(pair_id.clone());
+            Vec::new()
+        });
+        entry.push(PairedRecording { id, lang, prompt });
+    }
+
+    let mut pairs = Vec::new();
+    for pair_id in order {
+        let Some(mut sides) = sides.remove(&pair_id) else {
+            continue;
+        };
+        if sides.len() != 2 || !sides.iter().all(|side| wanted.contains(side.id.as_str())) {
+            continue;
+        }
+        let target = sides.remove(1);
+        let source = sides.remove(0);
+        pairs.push(TranslationPair {
+            pair_id,
+            source,
+            target,
+        });
+    }
+    Ok(pairs)
+}
+
+/// Write `translations.tsv` and `translations.json`, a parallel speech corpus
+/// of the pairs among the exported recordings; returns how many there were
+///
+/// Utterances are named like the exported audio (`<lang>_<id>`).
+pub async fn export_pairs(db: &SqlitePool, recording_ids: &[String], dest: &Path) -> Result<usize> {
+    let pairs = pairs(db, recording_ids).await?;
+    if pairs.is_empty() {
+        return Ok(0);
+    }
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_path(dest.join("translations.tsv"))
+        .context("Failed to write translations.tsv")?;
+    writer.write_record([
+        "pair_id",
+        "source_utterance",
+        "source_lang",
+        "source_text",
+        "target_utterance",
+        "target_lang",
+        "target_text",
+    ])?;
+    for pair in &pairs {
+        writer.write_record([
+            pair.pair_id.as_str(),
+            &format!("{}_{}", pair.source.lang, pair.source.id),
+            &pair.source.lang,
+            pair.source.prompt.as_deref().unwrap_or(""),
+            &format!("{}_{}", pair.target.lang, pair.target.id),
+            &pair.target.lang,
+            pair.target.prompt.as_deref().unwrap_or(""),
+        ])?;
+    }
+    writer.flush()?;
+
+    fs::write(
+        dest.join("translations.json"),
+        serde_json::to_string_pretty(&pairs)?,
+    )
+    .context("Failed to write translations.json")?;
+    Ok(pairs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    async fn add(db: &SqlitePool, id: &str, lang: &str, text: &str, pair_id: &str, at: i64) {
+        sqlx::query(
+            "INSERT INTO recordings (id, lang, prompt, qc_metrics, created_at, wav_path, \
+             translation_pair) VALUES (?, ?, ?, '{}', ?, '', ?)",
+        )
+        .bind(id)
+        .bind(lang)
+        .bind(text)
+        .bind(at)
+        .bind(pair_id)
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_export_pairs() {
+        let library = TestLibrary::new().await;
+        add(&library.db, "s1", "sw", "habari", "p1", 1).await;
+        add(&library.db, "t1", "en", "hello", "p1", 2).await;
+        add(&library.db, "s2", "sw", "asante", "p2", 3).await;
+        add(&library.db, "t2", "en", "thanks", "p2", 4).await;
+        // Its translation was never kept
+        add(&library.db, "s3", "sw", "karibu", "p3", 5).await;
+
+        assert!(is_paired(&library.db, "p1").await.unwrap());
+        assert!(!is_paired(&library.db, "p3").await.unwrap());
+        assert_eq!(
+            source_of(&library.db, "p2").await.unwrap().unwrap().id,
+            "s2"
+        );
+
+        // Only pairs with both sides exported
+        let all = ids(&["s1", "t1", "s2", "t2", "s3"]);
+        let found = pairs(&library.db, &all).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            (found[0].source.id.as_str(), found[0].target.id.as_str()),
+            ("s1", "t1")
+        );
+        assert_eq!(found[1].target.prompt.as_deref(), Some("thanks"));
+        assert_eq!(
+            pairs(&library.db, &ids(&["s1", "s2", "t2"]))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let dest = library.config.storage.data_dir.join("export");
+        fs::create_dir_all(&dest).unwrap();
+        assert_eq!(
+            export_pairs(&library.db, &ids(&["s3"]), &dest)
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(!dest.join("translations.tsv").exists());
+        assert_eq!(export_pairs(&library.db, &all, &dest).await.unwrap(), 2);
+        let tsv = fs::read_to_string(dest.join("translations.tsv")).unwrap();
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "p1\tsw_s1\tsw\thabari\ten_t1\ten\thello");
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dest.join("translations.json")).unwrap())
+                .unwrap();
+        assert_eq!(json[1]["source"]["id"], "s2");
+
+        unpair(&library.db, "p1").await.unwrap();
+        assert!(source_of(&library.db, "p1").await.unwrap().is_none());
+        assert_eq!(pairs(&library.db, &all).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate, Utc};
+use cowcow_core::prompt_analysis::SPEAKING_RATE_METRIC;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::sessions::SessionFilter;
+
+/// Levels of a sparkline, lowest first
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// QC metric charted by `stats --trend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendMetric {
+    Snr,
+    Clipping,
+    Vad,
+    Speech,
+    Duration,
+    Rumble,
+    Hum,
+    Reverb,
+    SpeakingRate,
+}
+
+impl TrendMetric {
+    /// Key of the metric in `recordings.qc_metrics`
+    pub fn key(&self) -> &'static str {
+        match self {
+            TrendMetric::Snr => "snr_db",
+            TrendMetric::Clipping => "clipping_pct",
+            TrendMetric::Vad => "vad_ratio",
+            TrendMetric::Speech => "speech_secs",
+            TrendMetric::Duration => "duration_secs",
+            TrendMetric::Rumble => "rumble_db",
+            TrendMetric::Hum => "hum_db",
+            TrendMetric::Reverb => "reverb_rt60_secs",
+            TrendMetric::SpeakingRate => SPEAKING_RATE_METRIC,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrendMetric::Snr => "SNR (dB)",
+            TrendMetric::Clipping => "Clipping (%)",
+            TrendMetric::Vad => "Speech ratio",
+            TrendMetric::Speech => "Speech (s)",
+            TrendMetric::Duration => "Duration (s)",
+            TrendMetric::Rumble => "Rumble (dB)",
+            TrendMetric::Hum => "Hum (dB)",
+            TrendMetric::Reverb => "Reverb RT60 (s)",
+            TrendMetric::SpeakingRate => "Speaking rate (syllables/s)",
+        }
+    }
+}
+
+impl FromStr for TrendMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "snr" | "snr_db" => Ok(TrendMetric::Snr),
+            "clipping" | "clipping_pct" => Ok(TrendMetric::Clipping),
+            "vad" | "vad_ratio" => Ok(TrendMetric::Vad),
+            "speech" | "speech_secs" => Ok(TrendMetric::Speech),
+            "duration" | "duration_secs" => Ok(TrendMetric::Duration),
+            "rumble" | "rumble_db" => Ok(TrendMetric::Rumble),
+            "hum" | "hum_db" => Ok(TrendMetric::Hum),
+            "reverb" | "reverb_rt60_secs" => Ok(TrendMetric::Reverb),
+            "rate" | "speaking_rate" => Ok(TrendMetric::SpeakingRate),
+            _ => Err(format!(
+                "Unknown trend metric: {s} (use snr, clipping, vad, speech, duration, rumble, \
+                 hum, reverb or rate)"
+            )),
+        }
+    }
+}
+
+/// One day of a trend
+#[derive(Debug, Clone)]
+pub struct DailyPoint {
+    pub day: NaiveDate,
+    pub recordings: i64,
+    /// Mean of the metric over the day's recordings that have it
+    pub mean: Option<f64>,
+}
+
+/// Daily recording counts and means of `metric` over the last `days` days
+/// (UTC, today included), optionally for one input device only; days
+/// without recordings are included empty
+pub async fn daily_trend(
+    db: &SqlitePool,
+    metric: TrendMetric,
+    days: u32,
+    device: Option<&str>,
+    filter: &SessionFilter,
+) -> Result<Vec<DailyPoint>> {
+    let today = Utc::now().date_naive();
+    let first_day = today
+        .checked_sub_signed(Duration::days(days.max(1) as i64 - 1))
+        .unwrap_or(NaiveDate::MIN);
+    let since = first_day
+        .and_hms_opt(0, 0, 0)
+        .map(|start| start.and_utc().timestamp())
+        .unwrap_or_default();
+
+    let (condition, params) = filter.condition("session_id");
+    let query = format!(
+        r#"
+        SELECT date(created_at, 'unixepoch') AS day, COUNT(*),
+            AVG(json_extract(qc_metrics, '$.{}'))
+        FROM recordings
+        WHERE created_at >= ? AND (? IS NULL OR device = ?){condition}
+        GROUP BY day
+        "#,
+        metric.key()
+    );
+    let mut rows = sqlx::query_as::<_, (String, i64, Option<f64>)>(&query)
+        .bind(since)
+        .bind(device)
+        .bind(device);
+    for param in &params {
+        rows = rows.bind(param);
+    }
+    let by_day: HashMap<String, (i64, Option<f64>)> = rows
+        .fetch_all(db)
+        .await
+        .context("Failed to fetch daily metrics")?
+        .into_iter()
+        .map(|(day, recordings, mean)| (day, (recordings, mean)))
+        .collect();
+
+    Ok(first_day
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| {
+            let (recordings, mean) = by_day
+                .get(&day.format("%Y-%m-%d").to_string())
+                .copied()
+                .unwrap_or((0, None));
+            DailyPoint {
+                day,
+                recordings,
+                mean,
+            }
+        })
+        .collect())
+}
+
+/// One bar per value, scaled between the lowest and highest; missing values
+/// are left blank
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    let known = values.iter().flatten();
+    let low = known.clone().copied().fold(f64::INFINITY, f64::min);
+    let high = known.copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| match value {
+            None => ' ',
+            Some(_) if high <= low => BARS[BARS.len() / 2],
+            Some(value) => {
+                let level = (value - low) / (high - low) * (BARS.len() - 1) as f64;
+                BARS[level.round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// Print the metric and recording count charts of a trend
+pub fn print_trend(metric: TrendMetric, points: &[DailyPoint]) {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return;
+    };
+    let span = format!("{} → {}", first.day, last.day);
+
+    let means: Vec<Option<f64>> = points.iter().map(|point| point.mean).collect();
+    println!("📈 {}, daily mean", metric.label());
+    if means.iter().all(Option::is_none) {
+        println!("  No recordings with this metric");
+    } else {
+        let known = means.iter().flatten();
+        let low = known.clone().copied().fold(f64::INFINITY, f64::min);
+        let high = known.copied().fold(f64::NEG_INFINITY, f64::max);
+        println!("  {}", sparkline(&means));
+        println!("  min {low:.2} · max {high:.2} · {span}");
+        // The last day with recordings against the first, for a quick read
+        // of the direction
+        let recorded: Vec<&DailyPoint> = points.iter().filter(|p| p.mean.is_some()).collect();
+        if let (Some(from), Some(to)) = (recorded.first(), recorded.last()) {
+            if from.day != to.day {
+                println!(
+                    "  {} {:.2} → {} {:.2}",
+                    from.day,
+                    from.mean.unwrap_or_default(),
+                    to.day,
+                    to.mean.unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    let counts: Vec<Option<f64>> = points
+        .iter()
+        .map(|point| Some(point.recordings as f64))
+        .collect();
+    let total: i64 = points.iter().map(|point| point.recordings).sum();
+    let busiest = points
+        .iter()
+        .map(|point| point.recordings)
+        .max()
+        .unwrap_or(0);
+    println!("📊 Recordings per day");
+    println!("  {}", sparkline(&counts));
+    println!("  {total} total · max {busiest}/day · {span}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestLibrary;
+
+    #[tokio::test]
+    async fn test_trend_covers_the_longest_allowed_range() {
+        let library = TestLibrary::new().await;
+
+        let points = daily_trend(
+            &library.db,
+            TrendMetric::Snr,
+            3650,
+            None,
+            &SessionFilter::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(points.len(), 3650);
+        assert_eq!(points.last().unwrap().day, Utc::now().date_naive());
+        assert!(points.iter().all(|point| point.recordings == 0));
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+/// Release signing key baked in at build time, overridable with `update.public_key`
+const BUILTIN_PUBLIC_KEY: Option<&str> = option_env!("COWCOW_UPDATE_PUBLIC_KEY");
+
+/// Release manifest published next to the binaries
+///
+/// The manifest itself is unsigned; each binary's minisign signature carries
+/// the release it belongs to in its trusted comment, `cowcow <version>
+/// <platform>` (e.g. `minisign -S -t "cowcow 0.4.0 linux-x86_64"`), so an
+/// older signed binary cannot be passed off as a new release.
+#[derive(Debug, Deserialize)]
+pub struct ReleaseManifest {
+    pub version: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Assets keyed by platform, e.g. `linux-x86_64`
+    pub assets: HashMap<String, ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub url: String,
+    /// Minisign signature, defaults to `<url>.minisig`
+    #[serde(default)]
+    pub signature_url: Option<String>,
+}
+
+/// Platform key used to pick the release asset
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+pub struct Updater {
+    client: Client,
+    config: Config,
+}
+
+impl Updater {
+    pub fn new(config: Config) -> Self {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(config.update.timeout_secs))
+            .build()
+            .unwrap();
+
+        Self { client, config }
+    }
+
+    pub async fn fetch_manifest(&self) -> Result<ReleaseManifest> {
+        let url = &self.config.update.release_url;
+        require_https(url)?;
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch release manifest from {url}"))?
+            .error_for_status()
+            .context("Release manifest request failed")?;
+
+        response
+            .json()
+            .await
+            .context("Failed to parse release manifest")
+    }
+
+    /// Newer release for this platform, if there is one
+    pub fn newer_release<'a>(
+        &self,
+        manifest: &'a ReleaseManifest,
+    ) -> Result<Option<&'a ReleaseAsset>> {
+        let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))?;
+        let latest = semver::Version::parse(&manifest.version)
+            .with_context(|| format!("Invalid release version: {}", manifest.version))?;
+
+        if latest <= current {
+            return Ok(None);
+        }
+
+        manifest
+            .assets
+            .get(&platform())
+            .map(Some)
+            .with_context(|| format!("Release {} has no build for {}", latest, platform()))
+    }
+
+    /// Download the release binary of `version` and check its signature,
+    /// including that it was signed as that release for this platform
+    pub async fn download_verified(&self, version: &str, asset: &ReleaseAsset) -> Result<Vec<u8>> {
+        let public_key = self
+            .config
+            .update
+            .public_key
+            .as_deref()
+            .or(BUILTIN_PUBLIC_KEY)
+            .context("No release signing key configured, set update.public_key")?;
+        let public_key = minisign_verify::PublicKey::from_base64(public_key)
+            .map_err(|e| anyhow::anyhow!("Invalid release signing key: {e}"))?;
+
+        let binary = self.download(&asset.url).await?;
+        let signature_url = asset
+            .signature_url
+            .clone()
+            .unwrap_or_else(|| format!("{}.minisig", asset.url));
+        let signature = self.download(&signature_url).await?;
+        let signature = minisign_verify::Signature::decode(&String::from_utf8_lossy(&signature))
+            .map_err(|e| anyhow::anyhow!("Invalid release signature: {e}"))?;
+
+        public_key
+            .verify(&binary, &signature, false)
+            .map_err(|e| anyhow::anyhow!("Release signature verification failed: {e}"))?;
+        check_trusted_comment(signature.trusted_comment(), version, &platform())?;
+
+        info!("Verified signature of {}", asset.url);
+        Ok(binary)
+    }
+
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        require_https(url)?;
+        self.client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {url}"))?
+            .error_for_status()
+            .with_context(|| format!("Download failed: {url}"))?
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .with_context(|| format!("Failed to read {url}"))
+    }
+}
+
+/// Releases are only fetched over HTTPS
+pub fn require_https(url: &str) -> Result<()> {
+    if !url.starts_with("https://") {
+        anyhow::bail!("Refusing to fetch a release over plain HTTP: {url}");
+    }
+    Ok(())
+}
+
+/// Check that a signature's trusted comment names `version` for `platform`
+/// and that `version` is newer than the running one
+///
+/// The trusted comment is covered by the signature, unlike the manifest, so
+/// this is what stops a replayed older release.
+fn check_trusted_comment(comment: &str, version: &str, platform: &str) -> Result<()> {
+    let mut fields = comment.split_whitespace();
+    let (Some("cowcow"), Some(signed_version), Some(signed_platform)) =
+        (fields.next(), fields.next(), fields.next())
+    else {
+        anyhow::bail!("Release signature does not name its version: \"{comment}\"");
+    };
+    if signed_version != version || signed_platform != platform {
+        anyhow::bail!(
+            "Release signature is for cowcow {signed_version} ({signed_platform}), \
+             not {version} ({platform})"
+        );
+    }
+    let signed = semver::Version::parse(signed_version)
+        .with_context(|| format!("Invalid signed release version: {signed_version}"))?;
+    let current = semver::Version::parse(env!("CARGO_PKG_VERSION"))?;
+    if signed <= current {
+        anyhow::bail!("Refusing to install cowcow {signed}, not newer than {current}");
+    }
+    Ok(())
+}
+
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    exe.with_file_name(name)
+}
+
+/// Previous binary kept after an update so it can be restored
+pub fn backup_path() -> Result<PathBuf> {
+    Ok(sibling(&std::env::current_exe()?, ".old"))
+}
+
+/// Replace the running binary, keeping the previous one as a backup
+///
+/// The new binary must start and report its version before it is swapped in;
+/// if the swap itself fails the previous binary is put back.
+pub fn install(binary: &[u8]) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the running binary")?;
+    let staged = sibling(&exe, ".new");
+    let backup = sibling(&exe, ".old");
+
+    fs::write(&staged, binary)
+        .with_context(|| format!("Failed to stage update at {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let smoke_test = Command::new(&staged).arg("--version").output();
+    if !smoke_test.is_ok_and(|output| output.status.success()) {
+        let _ = fs::remove_file(&staged);
+        return Err(anyhow::anyhow!(
+            "Downloaded binary failed to start, update aborted"
+        ));
+    }
+
+    let _ = fs::remove_file(&backup);
+    fs::rename(&exe, &backup).with_context(|| format!("Failed to back up {}", exe.display()))?;
+
+    if let Err(e) = fs::rename(&staged, &exe) {
+        warn!("Failed to install update, restoring previous binary: {}", e);
+        fs::rename(&backup, &exe).context("Failed to restore previous binary")?;
+        return Err(e).context("Failed to install update");
+    }
+
+    Ok(())
+}
+
+/// Restore the binary that was replaced by the last update
+pub fn rollback() -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the running binary")?;
+    let backup = sibling(&exe, ".old");
+    if !backup.exists() {
+        return Err(anyhow::anyhow!("No previous version to roll back to"));
+    }
+
+    let discarded = sibling(&exe, ".rollback");
+    fs::rename(&exe, &discarded)
+        .with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    if let Err(e) = fs::rename(&backup, &exe) {
+        fs::rename(&discarded, &exe).context("Failed to restore current binary")?;
+        return Err(e).context("Failed to restore previous binary");
+    }
+
+    // Removing a running executable fails on Windows; it is cleaned up next time
+    let _ = fs::remove_file(&discarded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trusted_comment_binds_version_and_platform() {
+        let current = semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        let newer = semver::Version::new(current.major + 1, 0, 0).to_string();
+        let comment = format!("cowcow {newer} linux-x86_64");
+        assert!(check_trusted_comment(&comment, &newer, "linux-x86_64").is_ok());
+
+        // A signature of another release or platform, or none at all
+        assert!(check_trusted_comment(&comment, "99.0.0", "linux-x86_64").is_err());
+        assert!(check_trusted_comment(&comment, &newer, "macos-aarch64").is_err());
+        assert!(check_trusted_comment("timestamp:1 file:cowcow", &newer, "linux-x86_64").is_err());
+
+        // A replay of the running release or an older one
+        let same = current.to_string();
+        let comment = format!("cowcow {same} linux-x86_64");
+        assert!(check_trusted_comment(&comment, &same, "linux-x86_64").is_err());
+        let comment = "cowcow 0.0.1 linux-x86_64";
+        assert!(check_trusted_comment(comment, "0.0.1", "linux-x86_64").is_err());
+    }
+
+    #[test]
+    fn test_require_https() {
+        assert!(require_https("https://example.com/release.json").is_ok());
+        assert!(require_https("http://example.com/release.json").is_err());
+        assert!(require_https("file:///tmp/release.json").is_err());
+    }
+}
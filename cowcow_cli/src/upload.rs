@@ -1,21 +1,25 @@
 use anyhow::{Context, Result};
+use cowcow_core::policy::QcPolicy;
+use cowcow_core::{flac, opus};
+use futures::stream::{self, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteExecutor, SqlitePool};
 use std::fs;
 use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
+use crate::chunking::{self, ChunkTuner, EndpointTuning};
 use crate::config::{Config, Credentials};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UploadRequest {
-    pub recording_id: String,
-    pub lang: String,
-    pub qc_metrics: String,
-    pub file_path: String,
-}
+use crate::http;
+use crate::mode::RecordingMode;
+use crate::speakers;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UploadResponse {
@@ -25,82 +29,432 @@ pub struct UploadResponse {
     pub message: Option<String>,
 }
 
+/// Opens, or resumes, a resumable upload of one recording
+#[derive(Debug, Serialize)]
+struct ResumableStart<'a> {
+    recording_id: &'a str,
+    lang: &'a str,
+    qc_metrics: &'a str,
+    file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    campaign_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transcript: Option<&'a str>,
+    file_name: String,
+    content_type: &'static str,
+    size: u64,
+}
+
+/// A resumable upload and how much of it the server has
+#[derive(Debug, Deserialize)]
+struct ResumableSession {
+    upload_id: String,
+    offset: u64,
+}
+
+/// Where the server's copy of a resumable upload ends
+#[derive(Debug, Deserialize)]
+struct ResumableOffset {
+    offset: u64,
+}
+
+/// Progress of an upload run, as streamed to `cowcow upload` invocations
+/// handed to the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum UploadEvent {
+    Started {
+        pending: usize,
+    },
+    Uploaded {
+        recording_id: String,
+        tokens_awarded: u32,
+    },
+    Failed {
+        recording_id: String,
+    },
+    Finished {
+        uploaded: usize,
+        failed: usize,
+    },
+}
+
+/// Why a queued recording was held back at upload, stored in
+/// `upload_queue.skip_reason`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    MissingFile,
+    Consent,
+    Duplicate,
+    Qc,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::MissingFile => "missing_file",
+            SkipReason::Consent => "consent",
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::Qc => "qc",
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// MIME type of a recording's audio
+fn content_type(path: &Path) -> &'static str {
+    if flac::is_flac(path) {
+        "audio/flac"
+    } else if opus::is_opus(path) {
+        "audio/ogg; codecs=opus"
+    } else {
+        "audio/wav"
+    }
+}
+
+/// Shows on a progress bar when the rate limiter is holding uploads back,
+/// until dropped
+struct ThrottleDisplay(tokio::task::JoinHandle<()>);
+
+impl ThrottleDisplay {
+    fn start(pb: &ProgressBar, per_sec: f64) -> Self {
+        let pb = pb.clone();
+        Self(tokio::spawn(async move {
+            loop {
+                let waiting = http::throttled_requests();
+                pb.set_message(if waiting > 0 {
+                    format!("⏳ throttled to {per_sec} req/s, {waiting} waiting")
+                } else {
+                    String::new()
+                });
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+        }))
+    }
+}
+
+impl Drop for ThrottleDisplay {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Identifies a QC policy so skips made under older thresholds can be told
+/// apart from skips under the current ones
+fn policy_id(policy: &QcPolicy) -> String {
+    let json = serde_json::to_string(policy).unwrap_or_default();
+    hex::encode(Sha256::digest(json.as_bytes()))
+}
+
+async fn record_skip(
+    db: &SqlitePool,
+    recording_id: &str,
+    reason: SkipReason,
+    detail: &str,
+    policy: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE upload_queue SET skip_reason = ?, skip_detail = ?, skip_policy = ? WHERE recording_id = ?",
+    )
+    .bind(reason.as_str())
+    .bind(detail)
+    .bind(policy)
+    .bind(recording_id)
+    .execute(db)
+    .await
+    .context("Failed to record upload skip")?;
+    Ok(())
+}
+
+/// Forget why a recording was skipped, so the next upload evaluates it afresh
+pub async fn clear_skip<'e>(db: impl SqliteExecutor<'e>, recording_id: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE upload_queue SET skip_reason = NULL, skip_detail = NULL, skip_policy = NULL WHERE recording_id = ?",
+    )
+    .bind(recording_id)
+    .execute(db)
+    .await
+    .context("Failed to clear upload skip")?;
+    Ok(())
+}
+
+/// Fields sent with an upload only when the recording has them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UploadExtras<'a> {
+    pub campaign_id: Option<&'a str>,
+    /// What was actually said, where it was corrected from the prompt
+    pub transcript: Option<&'a str>,
+}
+
+/// A recording waiting in the upload queue
+#[derive(sqlx::FromRow)]
+struct PendingRecording {
+    id: String,
+    lang: String,
+    qc_metrics: String,
+    wav_path: String,
+    speaker_id: Option<String>,
+    duplicate_of: Option<String>,
+    campaign_id: Option<String>,
+    transcript: Option<String>,
+    attempts: i64,
+    skip_reason: Option<String>,
+    skip_policy: Option<String>,
+}
+
 pub struct UploadClient {
     client: Client,
     config: Config,
+    events: Option<mpsc::UnboundedSender<UploadEvent>>,
+    /// Chunk size and resumable support of the endpoint, shared by the
+    /// uploads of a run; resumable support is checked again every run
+    tuning: Mutex<EndpointTuning>,
 }
 
 impl UploadClient {
     pub fn new(config: Config) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(config.api.timeout_secs))
-            .build()
-            .unwrap();
+        let client = http::shared_client(&config);
+        let tuning = Mutex::new(EndpointTuning {
+            chunk_size: ChunkTuner::new(config.upload.chunk_size).size(),
+            resumable: true,
+        });
+        Self {
+            client,
+            config,
+            events: None,
+            tuning,
+        }
+    }
+
+    fn lock_tuning(&self) -> MutexGuard<'_, EndpointTuning> {
+        self.tuning
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn tune_chunks(&self, adjust: impl FnOnce(&mut ChunkTuner)) {
+        let mut tuning = self.lock_tuning();
+        let mut tuner = ChunkTuner::new(tuning.chunk_size);
+        adjust(&mut tuner);
+        tuning.chunk_size = tuner.size();
+    }
+
+    /// `request` with the credentials and the configured extra headers
+    fn authorize(&self, mut request: RequestBuilder, credentials: &Credentials) -> RequestBuilder {
+        if let Some(access_token) = &credentials.access_token {
+            request = request.bearer_auth(access_token);
+        }
+        if let Some(api_key) = &credentials.api_key {
+            request = request.header("X-API-Key", api_key);
+        }
+        for (name, value) in &self.config.upload.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Report the progress of uploads to `events`
+    pub fn with_events(mut self, events: mpsc::UnboundedSender<UploadEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
 
-        Self { client, config }
+    fn emit(&self, event: UploadEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
     }
 
+    /// Upload a recording in resumable chunks, or in one request to servers
+    /// without resumable uploads
     pub async fn upload_recording(
         &self,
         recording_id: &str,
         lang: &str,
         qc_metrics: &str,
         file_path: &Path,
+        extras: &UploadExtras<'_>,
         credentials: &Credentials,
     ) -> Result<UploadResponse> {
-        let upload_url = format!("{}/recordings/upload", self.config.api.endpoint);
+        if self.lock_tuning().resumable {
+            let start = ResumableStart {
+                recording_id,
+                lang,
+                qc_metrics,
+                file_path: file_path.to_string_lossy().into_owned(),
+                campaign_id: extras.campaign_id,
+                transcript: extras.transcript,
+                file_name: file_name(file_path),
+                content_type: content_type(file_path),
+                size: 0,
+            };
+            match self.upload_resumable(start, file_path, credentials).await? {
+                Some(response) => return Ok(response),
+                None => {
+                    info!("Server has no resumable uploads; sending recordings whole");
+                    self.lock_tuning().resumable = false;
+                }
+            }
+        }
+        self.upload_whole(
+            recording_id,
+            lang,
+            qc_metrics,
+            file_path,
+            extras,
+            credentials,
+        )
+        .await
+    }
 
-        // Read the audio file
-        let file_data = fs::read(file_path)
+    /// Send a recording chunk by chunk, picking up where the server's copy
+    /// ends after a failed chunk; `None` if the server has no resumable
+    /// uploads
+    async fn upload_resumable(
+        &self,
+        mut start: ResumableStart<'_>,
+        file_path: &Path,
+        credentials: &Credentials,
+    ) -> Result<Option<UploadResponse>> {
+        let uploads_url = format!("{}/recordings/uploads", self.config.api.endpoint);
+        let data = fs::read(file_path)
             .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+        start.size = data.len() as u64;
 
-        info!(
-            "Uploading recording: {} ({} bytes)",
-            recording_id,
-            file_data.len()
-        );
-
-        // Create multipart form
-        let form = reqwest::multipart::Form::new()
-            .text("recording_id", recording_id.to_string())
-            .text("lang", lang.to_string())
-            .text("qc_metrics", qc_metrics.to_string())
-            .text("file_path", file_path.to_string_lossy().to_string())
-            .part(
-                "file",
-                reqwest::multipart::Part::bytes(file_data)
-                    .file_name(file_path.file_name().unwrap().to_string_lossy().to_string())
-                    .mime_str("audio/wav")?,
+        let Some(session) = self
+            .start_session(&uploads_url, &start, credentials)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let session_url = format!("{uploads_url}/{}", session.upload_id);
+        if session.offset > 0 {
+            info!(
+                "Resuming upload of {} at {} of {} bytes",
+                start.recording_id,
+                session.offset,
+                data.len()
             );
-
-        // Create progress bar
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner:.green} Uploading {msg}")
-                .unwrap(),
-        );
-        pb.set_message(format!("recording {recording_id}"));
-
-        let mut request = self.client.post(&upload_url);
-
-        // Add authentication headers
-        if let Some(access_token) = &credentials.access_token {
-            request = request.bearer_auth(access_token);
         }
 
-        if let Some(api_key) = &credentials.api_key {
-            request = request.header("X-API-Key", api_key);
+        let mut offset = session.offset.min(start.size) as usize;
+        let mut failures = 0;
+        while offset < data.len() {
+            let end = (offset + self.lock_tuning().chunk_size).min(data.len());
+            let chunk = &data[offset..end];
+            let started = Instant::now();
+            match self
+                .send_chunk(&session_url, offset, chunk, credentials)
+                .await
+            {
+                Ok(next) => {
+                    self.tune_chunks(|tuner| tuner.on_success(chunk.len(), started.elapsed()));
+                    offset = (next as usize).min(data.len());
+                }
+                Err(e) => {
+                    self.tune_chunks(ChunkTuner::on_failure);
+                    failures += 1;
+                    if failures > self.config.upload.max_retries {
+                        return Err(e);
+                    }
+                    warn!(
+                        "Chunk of {} at {} failed, retrying smaller: {:#}",
+                        start.recording_id, offset, e
+                    );
+                    // Carry on from whatever the server has
+                    offset = self
+                        .start_session(&uploads_url, &start, credentials)
+                        .await?
+                        .context("Server stopped offering resumable uploads")?
+                        .offset
+                        .min(start.size) as usize;
+                }
+            }
         }
 
-        let response = request
-            .multipart(form)
+        http::throttle(&self.config).await;
+        let request = self.client.post(format!("{session_url}/complete"));
+        let response = self
+            .authorize(request, credentials)
             .send()
             .await
-            .with_context(|| format!("Failed to send upload request to {upload_url}"))?;
+            .context("Failed to complete upload")?;
+        Ok(Some(self.upload_response(response).await?))
+    }
+
+    /// Open a resumable upload, or look up the one already open for the
+    /// recording; `None` if the server has no resumable uploads
+    async fn start_session(
+        &self,
+        uploads_url: &str,
+        start: &ResumableStart<'_>,
+        credentials: &Credentials,
+    ) -> Result<Option<ResumableSession>> {
+        http::throttle(&self.config).await;
+        let request = self.client.post(uploads_url).json(start);
+        let response = self
+            .authorize(request, credentials)
+            .send()
+            .await
+            .with_context(|| format!("Failed to start upload at {uploads_url}"))?;
+        match response.status() {
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => Ok(None),
+            status if status.is_success() => Ok(Some(
+                response
+                    .json()
+                    .await
+                    .context("Failed to parse upload session")?,
+            )),
+            status => {
+                let error_text = response.text().await.unwrap_or_default();
+                Err(anyhow::anyhow!(
+                    "Failed to start upload: {status} {error_text}"
+                ))
+            }
+        }
+    }
 
-        pb.finish_with_message("Upload complete");
+    /// Send the chunk starting at `offset`; returns where the server's copy
+    /// now ends, which is not past the chunk when the server had less
+    async fn send_chunk(
+        &self,
+        session_url: &str,
+        offset: usize,
+        chunk: &[u8],
+        credentials: &Credentials,
+    ) -> Result<u64> {
+        http::throttle(&self.config).await;
+        let request = self
+            .client
+            .put(session_url)
+            .header("Upload-Offset", offset.to_string())
+            .header("Content-Type", "application/offset+octet-stream")
+            .body(chunk.to_vec());
+        let response = self
+            .authorize(request, credentials)
+            .send()
+            .await
+            .context("Failed to send chunk")?;
+        let status = response.status();
+        // A conflict reports the offset the server expects instead
+        if status.is_success() || status == StatusCode::CONFLICT {
+            let ResumableOffset { offset } = response
+                .json()
+                .await
+                .context("Failed to parse chunk response")?;
+            Ok(offset)
+        } else {
+            let error_text = response.text().await.unwrap_or_default();
+            Err(anyhow::anyhow!("Chunk rejected: {status} {error_text}"))
+        }
+    }
 
+    async fn upload_response(&self, response: reqwest::Response) -> Result<UploadResponse> {
         if response.status().is_success() {
             let upload_response: UploadResponse = response
                 .json()
@@ -122,22 +476,127 @@ impl UploadClient {
         }
     }
 
+    /// Upload a recording in one multipart request
+    async fn upload_whole(
+        &self,
+        recording_id: &str,
+        lang: &str,
+        qc_metrics: &str,
+        file_path: &Path,
+        extras: &UploadExtras<'_>,
+        credentials: &Credentials,
+    ) -> Result<UploadResponse> {
+        let upload_url = format!("{}/recordings/upload", self.config.api.endpoint);
+        let form = self.upload_form(recording_id, lang, qc_metrics, file_path, extras)?;
+
+        let request = self.authorize(self.client.post(&upload_url), credentials);
+        http::throttle(&self.config).await;
+        let response = request
+            .multipart(form)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send upload request to {upload_url}"))?;
+        self.upload_response(response).await
+    }
+
+    /// Send a recording to the server's test endpoint, which takes it as an
+    /// upload (credentials, scope and form) but stores nothing and awards no
+    /// tokens; returns the bytes sent
+    pub async fn test_upload(
+        &self,
+        lang: &str,
+        qc_metrics: &str,
+        file_path: &Path,
+        credentials: &Credentials,
+    ) -> Result<u64> {
+        let test_url = format!("{}/recordings/upload/test", self.config.api.endpoint);
+        let recording_id = uuid::Uuid::new_v4().to_string();
+        let form = self.upload_form(
+            &recording_id,
+            lang,
+            qc_metrics,
+            file_path,
+            &UploadExtras::default(),
+        )?;
+
+        let request = self.authorize(self.client.post(&test_url), credentials);
+        http::throttle(&self.config).await;
+        let response = request
+            .multipart(form)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send test upload to {test_url}"))?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            anyhow::bail!("The server has no test upload endpoint ({test_url})");
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Test upload rejected: {status} {error_text}");
+        }
+        Ok(fs::metadata(file_path)?.len())
+    }
+
+    /// The multipart form of a recording, under the field names the server
+    /// expects
+    fn upload_form(
+        &self,
+        recording_id: &str,
+        lang: &str,
+        qc_metrics: &str,
+        file_path: &Path,
+        extras: &UploadExtras<'_>,
+    ) -> Result<reqwest::multipart::Form> {
+        // Read the audio file
+        let file_data = fs::read(file_path)
+            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+
+        info!(
+            "Uploading recording: {} ({} bytes)",
+            recording_id,
+            file_data.len()
+        );
+
+        let upload_config = &self.config.upload;
+        let mut form = reqwest::multipart::Form::new();
+        let mut fields = vec![
+            ("recording_id", recording_id.to_string()),
+            ("lang", lang.to_string()),
+            ("qc_metrics", qc_metrics.to_string()),
+            ("file_path", file_path.to_string_lossy().to_string()),
+        ];
+        if let Some(campaign_id) = extras.campaign_id {
+            fields.push(("campaign_id", campaign_id.to_string()));
+        }
+        if let Some(transcript) = extras.transcript {
+            fields.push(("transcript", transcript.to_string()));
+        }
+        for (field, value) in fields {
+            if let Some(name) = upload_config.form_field(field) {
+                form = form.text(name.to_string(), value);
+            }
+        }
+        let file_field = upload_config.form_field("file").unwrap_or("file");
+        Ok(form.part(
+            file_field.to_string(),
+            reqwest::multipart::Part::bytes(file_data)
+                .file_name(file_name(file_path))
+                .mime_str(content_type(file_path))?,
+        ))
+    }
+
+    /// Upload every queued recording that may be uploaded
+    ///
+    /// When `cancel` fires no further uploads start; those in flight finish,
+    /// and the rest stay queued for the next run.
     pub async fn upload_pending_recordings(
         &self,
         db: &SqlitePool,
         credentials: &Credentials,
         force: bool,
+        requalify: bool,
+        cancel: &CancellationToken,
     ) -> Result<()> {
-        // Get pending recordings from upload queue
-        #[derive(sqlx::FromRow)]
-        struct PendingRecording {
-            id: String,
-            lang: String,
-            qc_metrics: String,
-            wav_path: String,
-            attempts: i64,
-        }
-
         let pending_recordings = sqlx::query_as::<_, PendingRecording>(
             r#"
             SELECT 
@@ -145,165 +604,499 @@ impl UploadClient {
                 r.lang,
                 r.qc_metrics,
                 r.wav_path,
-                uq.attempts
+                r.speaker_id,
+                r.duplicate_of,
+                r.campaign_id,
+                r.transcript,
+                uq.attempts,
+                uq.skip_reason,
+                uq.skip_policy
             FROM recordings r
             JOIN upload_queue uq ON r.id = uq.recording_id
-            WHERE r.uploaded_at IS NULL
+            WHERE r.uploaded_at IS NULL AND r.archived = 0 AND r.take_selected = 1
+              AND r.review_status NOT IN ('rejected', 'needs_rerecord')
+              AND (? = 0 OR r.review_status = 'approved')
             ORDER BY r.created_at ASC
             "#,
         )
+        .bind(self.config.upload.approved_only)
         .fetch_all(db)
         .await
         .context("Failed to fetch pending recordings")?;
 
         if pending_recordings.is_empty() {
             info!("No pending recordings to upload");
+            self.emit(UploadEvent::Finished {
+                uploaded: 0,
+                failed: 0,
+            });
             return Ok(());
         }
 
         info!("Found {} pending recordings", pending_recordings.len());
 
-        let mut successful_uploads = 0;
-        let mut failed_uploads = 0;
+        // Music-mode recordings are held to their own policy
+        let speech_policy = self.config.qc_policy();
+        let music_policy = self.config.music_qc_policy();
+        let speech_policy_id = policy_id(&speech_policy);
+        let music_policy_id = policy_id(&music_policy);
+
+        let mut requalified = 0;
+        let mut qc_held = 0;
 
+        let mut ready = Vec::new();
         for recording in pending_recordings {
-            let file_path = Path::new(&recording.wav_path);
+            let file_path = self.config.locate_audio(&recording.wav_path);
 
             // Check if file exists
             if !file_path.exists() {
                 warn!("File not found: {}, skipping", recording.wav_path);
+                record_skip(
+                    db,
+                    &recording.id,
+                    SkipReason::MissingFile,
+                    &recording.wav_path,
+                    None,
+                )
+                .await?;
                 continue;
             }
 
-            // Check quality metrics if not forcing
-            if !force {
-                if let Ok(metrics) =
-                    serde_json::from_str::<serde_json::Value>(&recording.qc_metrics)
-                {
-                    if let Some(snr) = metrics.get("snr_db").and_then(|v| v.as_f64()) {
-                        if snr < self.config.audio.min_snr_db as f64 {
-                            warn!(
-                                "Skipping recording {} due to low SNR: {:.1} dB",
-                                recording.id, snr
-                            );
-                            continue;
-                        }
-                    }
-
-                    if let Some(clipping) = metrics.get("clipping_pct").and_then(|v| v.as_f64()) {
-                        if clipping > self.config.audio.max_clipping_pct as f64 {
-                            warn!(
-                                "Skipping recording {} due to high clipping: {:.1}%",
-                                recording.id, clipping
-                            );
-                            continue;
-                        }
+            // Speakers need valid consent to the form in force, and minors
+            // valid guardian consent; --force does not override this
+            if let Some(speaker_id) = &recording.speaker_id {
+                let cleared = match speakers::get_speaker(db, speaker_id).await? {
+                    Some(speaker) => {
+                        speakers::speaker_cleared_for_upload(db, &self.config, &speaker).await?
                     }
+                    None => false,
+                };
 
-                    if let Some(vad) = metrics.get("vad_ratio").and_then(|v| v.as_f64()) {
-                        if vad < self.config.audio.min_vad_ratio as f64 {
-                            warn!(
-                                "Skipping recording {} due to low VAD ratio: {:.1}%",
-                                recording.id, vad
-                            );
-                            continue;
-                        }
-                    }
+                if !cleared {
+                    warn!(
+                        "Skipping recording {}: speaker {} has no valid consent",
+                        recording.id, speaker_id
+                    );
+                    record_skip(db, &recording.id, SkipReason::Consent, speaker_id, None).await?;
+                    continue;
                 }
             }
 
-            // Attempt upload with retry logic
-            let mut attempts = recording.attempts;
-            let mut success = false;
-
-            while attempts < self.config.upload.max_retries as i64 && !success {
-                match self
-                    .upload_recording(
-                        &recording.id,
-                        &recording.lang,
-                        &recording.qc_metrics,
-                        file_path,
-                        credentials,
-                    )
-                    .await
+            // Check duplicates and quality metrics if not forcing
+            if !force {
+                if let Some(original) = &recording.duplicate_of {
+                    warn!(
+                        "Skipping recording {}: duplicate of {}",
+                        recording.id, original
+                    );
+                    record_skip(db, &recording.id, SkipReason::Duplicate, original, None).await?;
+                    continue;
+                }
+
+                let metrics = serde_json::from_str::<serde_json::Value>(&recording.qc_metrics);
+                let (policy, current_policy) = match metrics.as_ref().map(RecordingMode::of_metrics)
                 {
-                    Ok(response) => {
-                        // Mark as uploaded
-                        let now = chrono::Utc::now().timestamp();
-                        sqlx::query("UPDATE recordings SET uploaded_at = ? WHERE id = ?")
-                            .bind(now)
-                            .bind(&recording.id)
-                            .execute(db)
-                            .await
-                            .context("Failed to update recording status")?;
-
-                        // Remove from upload queue
-                        sqlx::query("DELETE FROM upload_queue WHERE recording_id = ?")
-                            .bind(&recording.id)
-                            .execute(db)
-                            .await
-                            .context("Failed to remove from upload queue")?;
-
-                        successful_uploads += 1;
-                        success = true;
-
-                        // Display success message with tokens
-                        if response.tokens_awarded > 0 {
-                            println!(
-                                "✅ Upload complete! +{} tokens earned 🎉",
-                                response.tokens_awarded
-                            );
-                            if let Some(message) = &response.message {
-                                println!("   {message}");
-                            }
-                        } else {
-                            println!("✅ Upload complete!");
-                        }
+                    Ok(RecordingMode::Music) => (&music_policy, &music_policy_id),
+                    _ => (&speech_policy, &speech_policy_id),
+                };
 
-                        info!("Successfully uploaded recording: {}", recording.id);
-                    }
-                    Err(e) => {
-                        attempts += 1;
+                // A QC skip stands until the thresholds change or --requalify
+                // asks for it to be re-evaluated
+                let qc_skipped = recording.skip_reason.as_deref() == Some(SkipReason::Qc.as_str());
+                if qc_skipped
+                    && !requalify
+                    && recording.skip_policy.as_deref() == Some(current_policy.as_str())
+                {
+                    qc_held += 1;
+                    continue;
+                }
+
+                if let Ok(metrics) = &metrics {
+                    let report = policy.evaluate_json(metrics);
+                    if !report.passed {
+                        let summary = report.failure_summary();
                         warn!(
-                            "Upload attempt {} failed for {}: {}",
-                            attempts, recording.id, e
+                            "Skipping recording {} due to failed QC: {}",
+                            recording.id, summary
                         );
-
-                        // Update attempt count
-                        let now = chrono::Utc::now().timestamp();
-                        sqlx::query("UPDATE upload_queue SET attempts = ?, last_attempt = ? WHERE recording_id = ?")
-                            .bind(attempts)
-                            .bind(now)
-                            .bind(&recording.id)
-                            .execute(db)
-                            .await
-                        .context("Failed to update upload queue")?;
-
-                        if attempts < self.config.upload.max_retries as i64 {
-                            // Wait before retrying
-                            let delay = std::time::Duration::from_secs(
-                                self.config.upload.retry_delay_secs * (attempts as u64),
-                            );
-                            info!("Retrying in {} seconds...", delay.as_secs());
-                            tokio::time::sleep(delay).await;
-                        }
+                        record_skip(
+                            db,
+                            &recording.id,
+                            SkipReason::Qc,
+                            &summary,
+                            Some(current_policy),
+                        )
+                        .await?;
+                        qc_held += 1;
+                        continue;
                     }
                 }
+
+                if qc_skipped {
+                    info!("Recording {} now passes QC", recording.id);
+                    requalified += 1;
+                }
             }
 
-            if !success {
-                failed_uploads += 1;
-                error!(
-                    "Failed to upload recording after {} attempts: {}",
-                    attempts, recording.id
-                );
+            if recording.skip_reason.is_some() {
+                clear_skip(db, &recording.id).await?;
+            }
+            ready.push(recording);
+        }
+
+        // Start from the chunk size learned for this server
+        let endpoint = &self.config.api.endpoint;
+        if let Some(chunk_size) = chunking::load_chunk_size(db, endpoint).await? {
+            self.lock_tuning().chunk_size = chunk_size;
+        }
+
+        // Uploads run side by side over the shared client's pooled (and,
+        // where the server supports it, multiplexed HTTP/2) connections
+        self.emit(UploadEvent::Started {
+            pending: ready.len(),
+        });
+        let pb = ProgressBar::new(ready.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{bar:40.green} {pos}/{len} uploaded ({eta} left) {msg}")
+                .unwrap(),
+        );
+        let throttle_display = ThrottleDisplay::start(&pb, self.config.api.max_requests_per_sec);
+        let started = Instant::now();
+        let results: Vec<Result<Option<bool>>> = stream::iter(ready)
+            .map(|recording| {
+                let pb = &pb;
+                async move {
+                    if cancel.is_cancelled() {
+                        return Ok(None);
+                    }
+                    let uploaded = self
+                        .upload_with_retries(db, credentials, &recording, pb, cancel)
+                        .await;
+                    pb.inc(1);
+                    uploaded.map(Some)
+                }
+            })
+            .buffer_unordered(self.config.upload.concurrency.max(1))
+            .collect()
+            .await;
+        drop(throttle_display);
+        pb.finish_and_clear();
+        let chunk_size = self.lock_tuning().chunk_size;
+        chunking::store_chunk_size(db, endpoint, chunk_size).await?;
+
+        let mut successful_uploads = 0;
+        let mut failed_uploads = 0;
+        let mut not_started = 0;
+        for uploaded in results {
+            match uploaded? {
+                Some(true) => successful_uploads += 1,
+                Some(false) => failed_uploads += 1,
+                None => not_started += 1,
             }
         }
+        self.emit(UploadEvent::Finished {
+            uploaded: successful_uploads,
+            failed: failed_uploads,
+        });
+        if successful_uploads > 0 {
+            let elapsed = started.elapsed();
+            info!(
+                "Uploaded {} recordings in {:.1}s ({:.0} ms per recording)",
+                successful_uploads,
+                elapsed.as_secs_f64(),
+                elapsed.as_secs_f64() * 1000.0 / successful_uploads as f64
+            );
+        }
 
         info!(
             "Upload summary: {} successful, {} failed",
             successful_uploads, failed_uploads
         );
+        if not_started > 0 {
+            println!(
+                "⏹️  Upload interrupted; {not_started} recording(s) stay queued for the next run"
+            );
+        }
+        if requalified > 0 {
+            println!("♻️  {requalified} previously skipped recording(s) now pass QC");
+        }
+        if qc_held > 0 {
+            println!(
+                "⏸️  {qc_held} recording(s) held back by QC; they are re-evaluated when thresholds change"
+            );
+        }
         Ok(())
     }
+
+    /// Upload one recording, retrying up to `upload.max_retries` attempts in
+    /// total (fewer if `cancel` fires); returns whether it was uploaded
+    async fn upload_with_retries(
+        &self,
+        db: &SqlitePool,
+        credentials: &Credentials,
+        recording: &PendingRecording,
+        pb: &ProgressBar,
+        cancel: &CancellationToken,
+    ) -> Result<bool> {
+        let file_path = self.config.locate_audio(&recording.wav_path);
+        let mut attempts = recording.attempts;
+
+        while attempts < self.config.upload.max_retries as i64 {
+            let started = Instant::now();
+            match self
+                .upload_recording(
+                    &recording.id,
+                    &recording.lang,
+                    &recording.qc_metrics,
+                    &file_path,
+                    &UploadExtras {
+                        campaign_id: recording.campaign_id.as_deref(),
+                        transcript: recording.transcript.as_deref(),
+                    },
+                    credentials,
+                )
+                .await
+            {
+                Ok(response) => {
+                    // Mark as uploaded and dequeue together, so an
+                    // interruption never leaves one without the other
+                    let now = chrono::Utc::now().timestamp();
+                    let mut tx = db.begin().await?;
+                    sqlx::query(
+                        "UPDATE recordings SET uploaded_at = ?, tokens_awarded = ? WHERE id = ?",
+                    )
+                    .bind(now)
+                    .bind(response.tokens_awarded)
+                    .bind(&recording.id)
+                    .execute(&mut *tx)
+                    .await
+                    .context("Failed to update recording status")?;
+
+                    // Remove from upload queue
+                    sqlx::query("DELETE FROM upload_queue WHERE recording_id = ?")
+                        .bind(&recording.id)
+                        .execute(&mut *tx)
+                        .await
+                        .context("Failed to remove from upload queue")?;
+                    tx.commit()
+                        .await
+                        .context("Failed to update recording status")?;
+
+                    // Display success message with tokens
+                    if response.tokens_awarded > 0 {
+                        pb.println(format!(
+                            "✅ Upload complete! +{} tokens earned 🎉",
+                            response.tokens_awarded
+                        ));
+                        if let Some(message) = &response.message {
+                            pb.println(format!("   {message}"));
+                        }
+                    } else {
+                        pb.println("✅ Upload complete!");
+                    }
+
+                    info!(
+                        "Successfully uploaded recording: {} in {} ms",
+                        recording.id,
+                        started.elapsed().as_millis()
+                    );
+                    self.emit(UploadEvent::Uploaded {
+                        recording_id: recording.id.clone(),
+                        tokens_awarded: response.tokens_awarded,
+                    });
+                    return Ok(true);
+                }
+                Err(e) => {
+                    attempts += 1;
+                    warn!(
+                        "Upload attempt {} failed for {}: {}",
+                        attempts, recording.id, e
+                    );
+
+                    // Update attempt count
+                    let now = chrono::Utc::now().timestamp();
+                    sqlx::query(
+                        "UPDATE upload_queue SET attempts = ?, last_attempt = ? WHERE recording_id = ?",
+                    )
+                    .bind(attempts)
+                    .bind(now)
+                    .bind(&recording.id)
+                    .execute(db)
+                    .await
+                    .context("Failed to update upload queue")?;
+
+                    if attempts < self.config.upload.max_retries as i64 {
+                        // Wait before retrying
+                        let delay = std::time::Duration::from_secs(
+                            self.config.upload.retry_delay_secs * (attempts as u64),
+                        );
+                        info!("Retrying in {} seconds...", delay.as_secs());
+                        tokio::select! {
+                            _ = tokio::time::sleep(delay) => {}
+                            _ = cancel.cancelled() => break,
+                        }
+                    }
+                }
+            }
+        }
+
+        error!(
+            "Failed to upload recording after {} attempts: {}",
+            attempts, recording.id
+        );
+        self.emit(UploadEvent::Failed {
+            recording_id: recording.id.clone(),
+        });
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+    use cowcow_server_stub::StubServer;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// A client of `server` starting from 16 KiB chunks, with the API key of
+    /// a fresh account
+    fn client(server: &StubServer) -> (UploadClient, Credentials) {
+        server.add_user("amina", "secret");
+        let mut config = Config::default();
+        config.api.endpoint = server.url();
+        config.upload.chunk_size = chunking::MIN_CHUNK_BYTES;
+        let credentials = Credentials {
+            access_token: None,
+            api_key: server.api_key("amina"),
+            username: Some("amina".to_string()),
+            expires_at: None,
+            scopes: None,
+        };
+        (UploadClient::new(config), credentials)
+    }
+
+    /// 100 KB of audio to upload, in `dir`
+    fn audio(dir: &TempDir) -> PathBuf {
+        let path = dir.path().join("take.wav");
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    async fn upload(client: &UploadClient, credentials: &Credentials, path: &Path) {
+        client
+            .upload_recording(
+                "rec-1",
+                "sw",
+                r#"{"snr_db": 30.0}"#,
+                path,
+                &UploadExtras::default(),
+                credentials,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_sends_resumable_chunks() {
+        let server = StubServer::start().await.unwrap();
+        let (client, credentials) = client(&server);
+        let dir = scratch_dir();
+        let path = audio(&dir);
+
+        upload(&client, &credentials, &path).await;
+        let uploads = server.uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].recording_id, "rec-1");
+        assert_eq!(uploads[0].audio_bytes, 100_000);
+        assert!(
+            uploads[0].chunks > 1,
+            "sent in {} chunk(s)",
+            uploads[0].chunks
+        );
+        assert!(client.lock_tuning().resumable);
+        assert!(client.lock_tuning().chunk_size > chunking::MIN_CHUNK_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_upload_falls_back_to_one_request() {
+        let server = StubServer::start().await.unwrap();
+        server.disable_resumable_uploads();
+        let (client, credentials) = client(&server);
+        let dir = scratch_dir();
+        let path = audio(&dir);
+
+        upload(&client, &credentials, &path).await;
+        let uploads = server.uploads();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].audio_bytes, 100_000);
+        assert_eq!(uploads[0].chunks, 0);
+        assert!(!client.lock_tuning().resumable);
+    }
+
+    #[tokio::test]
+    async fn test_upload_is_neither_kept_nor_rewarded() {
+        let server = StubServer::start().await.unwrap();
+        let (client, credentials) = client(&server);
+        let dir = scratch_dir();
+        let path = audio(&dir);
+
+        let bytes = client
+            .test_upload("sw", r#"{"snr_db": 30.0}"#, &path, &credentials)
+            .await
+            .unwrap();
+        assert_eq!(bytes, 100_000);
+        let test_uploads = server.test_uploads();
+        assert_eq!(test_uploads.len(), 1);
+        assert_eq!(test_uploads[0].audio_bytes, 100_000);
+        assert!(server.uploads().is_empty());
+        assert_eq!(server.balance("amina"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_upload_needs_the_upload_scope() {
+        let server = StubServer::start().await.unwrap();
+        let (client, _) = client(&server);
+        let login: serde_json::Value = reqwest::Client::new()
+            .post(format!("{}/auth/token", server.url()))
+            .form(&[
+                ("username", "amina"),
+                ("password", "secret"),
+                ("scope", "record"),
+            ])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(login["scope"], "record");
+        assert!(login["api_key"].is_null());
+        // Scopes left unknown, so only the server can refuse
+        let credentials = Credentials {
+            access_token: login["access_token"].as_str().map(str::to_string),
+            api_key: None,
+            username: Some("amina".to_string()),
+            expires_at: None,
+            scopes: None,
+        };
+        let dir = scratch_dir();
+        let path = audio(&dir);
+
+        let result = client
+            .upload_recording(
+                "rec-1",
+                "sw",
+                r#"{"snr_db": 30.0}"#,
+                &path,
+                &UploadExtras::default(),
+                &credentials,
+            )
+            .await;
+        let error = format!("{:#}", result.unwrap_err());
+        assert!(error.contains("lacks the upload scope"), "{error}");
+        assert!(server.uploads().is_empty());
+    }
 }
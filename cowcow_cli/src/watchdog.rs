@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Times the input stream is reopened in one take before giving up
+pub const MAX_STREAM_REBUILDS: usize = 3;
+
+/// A stretch of a take during which the device delivered no audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StallIncident {
+    /// Position in the recorded audio where the gap falls, in seconds
+    pub at_secs: f64,
+    /// How long no audio arrived before the stall was declared, in seconds
+    pub gap_secs: f64,
+    /// Whether reopening the input stream brought the audio back
+    pub recovered: bool,
+}
+
+/// Notices when the audio stack stops delivering samples mid-recording
+///
+/// The recording loop feeds it every buffer it receives; once nothing has
+/// arrived for the timeout it reports a stall, and the loop reopens the
+/// input stream or gives up.
+#[derive(Debug)]
+pub struct CaptureWatchdog {
+    timeout: Option<Duration>,
+    last_audio: Instant,
+    incidents: Vec<StallIncident>,
+}
+
+impl CaptureWatchdog {
+    /// A watchdog that fires after `timeout_secs` without audio; 0 disables it
+    pub fn new(timeout_secs: u32) -> Self {
+        Self {
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs as u64)),
+            last_audio: Instant::now(),
+            incidents: Vec::new(),
+        }
+    }
+
+    /// Audio arrived
+    pub fn feed(&mut self) {
+        self.last_audio = Instant::now();
+    }
+
+    /// How long the capture has been silent, once that is past the timeout
+    pub fn stalled(&self) -> Option<Duration> {
+        let gap = self.last_audio.elapsed();
+        self.timeout.filter(|timeout| gap >= *timeout).map(|_| gap)
+    }
+
+    /// Whether another stream rebuild is allowed
+    pub fn can_rebuild(&self) -> bool {
+        self.incidents.len() < MAX_STREAM_REBUILDS
+    }
+
+    /// Note a stall at `at_secs` into the take and restart the clock
+    pub fn record(&mut self, at_secs: f64, gap: Duration, recovered: bool) {
+        self.incidents.push(StallIncident {
+            at_secs,
+            gap_secs: gap.as_secs_f64(),
+            recovered,
+        });
+        self.feed();
+    }
+
+    pub fn incidents(&self) -> &[StallIncident] {
+        &self.incidents
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use cowcow_core::asr::WhisperModel;
+use cowcow_core::word_timing::WordAlignment;
+use indicatif::{ProgressBar, ProgressStyle};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::playback::Clip;
+
+/// Totals of an alignment run
+#[derive(Debug, Default)]
+pub struct AlignSummary {
+    pub aligned: usize,
+    /// Recordings whose audio is no longer on disk
+    pub missing: usize,
+    pub failed: usize,
+    /// Whether the run was interrupted; running it again carries on
+    pub cancelled: bool,
+}
+
+/// Time the words of a recording's transcript with `model` and store them
+///
+/// `lang` is the recording's language; the model runs on a blocking thread.
+pub async fn align_recording(
+    db: &SqlitePool,
+    model: Arc<WhisperModel>,
+    recording_id: &str,
+    lang: &str,
+    audio_path: PathBuf,
+    transcript: String,
+) -> Result<WordAlignment> {
+    // whisper knows languages by ISO 639-1 code; it detects any other
+    let model_lang = (lang.len() == 2).then(|| lang.to_string());
+    let alignment = tokio::task::spawn_blocking(move || {
+        let clip = Clip::load(&audio_path)?;
+        model.align_words(
+            &clip.samples,
+            clip.sample_rate,
+            clip.channels,
+            &transcript,
+            model_lang.as_deref(),
+        )
+    })
+    .await
+    .context("Alignment task failed")??;
+    sqlx::query("UPDATE recordings SET word_alignment = ? WHERE id = ?")
+        .bind(serde_json::to_string(&alignment)?)
+        .bind(recording_id)
+        .execute(db)
+        .await
+        .context("Failed to save word alignment")?;
+    Ok(alignment)
+}
+
+/// Align every recording with a transcript that has not been aligned yet
+/// (all of them with `force`), optionally only one language's
+///
+/// Stops between recordings when `cancel` fires; running it again skips
+/// those already aligned.
+pub async fn align_library(
+    db: &SqlitePool,
+    config: &Config,
+    model: Arc<WhisperModel>,
+    lang: Option<&str>,
+    force: bool,
+    cancel: &CancellationToken,
+) -> Result<AlignSummary> {
+    let recordings: Vec<(String, String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT id, lang, wav_path, COALESCE(NULLIF(TRIM(transcript), ''), prompt) FROM recordings
+        WHERE (NULLIF(TRIM(transcript), '') IS NOT NULL
+                OR (prompt IS NOT NULL AND TRIM(prompt) != '' AND prompt_fields IS NULL))
+            AND (? IS NULL OR lang = ?) AND (? OR word_alignment IS NULL)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(lang)
+    .bind(lang)
+    .bind(force)
+    .fetch_all(db)
+    .await
+    .context("Failed to fetch recordings")?;
+
+    let pb = ProgressBar::new(recordings.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.green} {pos}/{len} recordings aligned ({eta} left)")
+            .unwrap(),
+    );
+
+    let mut summary = AlignSummary::default();
+    for (id, recording_lang, audio_path, transcript) in recordings {
+        if cancel.is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+        pb.inc(1);
+        let audio_path = config.locate_audio(&audio_path);
+        if !audio_path.exists() {
+            summary.missing += 1;
+            continue;
+        }
+        let model = Arc::clone(&model);
+        match align_recording(db, model, &id, &recording_lang, audio_path, transcript).await {
+            Ok(_) => summary.aligned += 1,
+            Err(e) => {
+                pb.println(format!("⚠️  {id}: {e:#}"));
+                summary.failed += 1;
+            }
+        }
+    }
+    pb.finish_and_clear();
+    Ok(summary)
+}
+
+/// Stored word alignments of the given recordings, by recording ID
+pub async fn alignments(
+    db: &SqlitePool,
+    recording_ids: &[String],
+) -> Result<HashMap<String, WordAlignment>> {
+    let mut alignments = HashMap::new();
+    for id in recording_ids {
+        let stored: Option<(Option<String>,)> =
+            sqlx::query_as("SELECT word_alignment FROM recordings WHERE id = ?")
+                .bind(id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to fetch word alignment")?;
+        let parsed = stored
+            .and_then(|(json,)| json)
+            .and_then(|json| serde_json::from_str(&json).ok());
+        if let Some(alignment) = parsed {
+            alignments.insert(id.clone(), alignment);
+        }
+    }
+    Ok(alignments)
+}
+
+/// Write `alignments.ctm` and `alignments.json` for the recordings that have
+/// been aligned, returning how many were
+///
+/// Utterances are named like the exported audio (`<lang>_<id>`).
+pub async fn export_alignments(
+    db: &SqlitePool,
+    recordings: &[(String, String)],
+    dest: &Path,
+) -> Result<usize> {
+    let ids: Vec<String> = recordings.iter().map(|(id, _)| id.clone()).collect();
+    let alignments = alignments(db, &ids).await?;
+    if alignments.is_empty() {
+        return Ok(0);
+    }
+
+    let mut ctm = String::new();
+    let mut manifest = serde_json::Map::new();
+    for (id, lang) in recordings {
+        let Some(alignment) = alignments.get(id) else {
+            continue;
+        };
+        let utterance = format!("{lang}_{id}");
+        ctm.push_str(&alignment.to_ctm(&utterance));
+        manifest.insert(utterance, serde_json::to_value(alignment)?);
+    }
+
+    fs::write(dest.join("alignments.ctm"), ctm).context("Failed to write alignments.ctm")?;
+    fs::write(
+        dest.join("alignments.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .context("Failed to write alignments.json")?;
+    Ok(alignments.len())
+}
@@ -0,0 +1,161 @@
+//! The CLI against the in-memory stub server: login, a recording (from the
+//! synthetic backend, since tests have no microphone), upload and stats,
+//! each step as a separate `cowcow` run in a scratch home directory
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use cowcow_server_stub::StubServer;
+
+/// Scratch home directory holding the config and data directory of one test
+struct Home(PathBuf);
+
+impl Home {
+    fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("cowcow-e2e-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Run `cowcow args...`, with `stdin` as its input
+    fn cowcow(&self, args: &[&str], stdin: &str) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_cowcow_cli"))
+            .args(args)
+            .env("HOME", &self.0)
+            .env_remove("RUST_LOG")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("cowcow_cli runs");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    /// Run `cowcow args...`, expect it to succeed and return its output
+    fn run(&self, args: &[&str]) -> String {
+        self.run_with_input(args, "")
+    }
+
+    fn run_with_input(&self, args: &[&str], stdin: &str) -> String {
+        let output = self.cowcow(args, stdin);
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        assert!(
+            output.status.success(),
+            "cowcow {args:?} failed\n{stdout}\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        stdout
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Two seconds of a voiced-sounding 16 kHz tone with pauses, as a 16-bit WAV
+fn write_take(path: &Path) {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for i in 0..32000 {
+        let t = i as f32 / 16000.0;
+        let syllable = if (t * 4.0).fract() < 0.6 { 0.4 } else { 0.0 };
+        let sample = syllable
+            * ((2.0 * std::f32::consts::PI * 180.0 * t).sin()
+                + 0.5 * (2.0 * std::f32::consts::PI * 360.0 * t).sin());
+        writer
+            .write_sample((sample * 0.6 * i16::MAX as f32) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_login_record_upload_stats() {
+    let server = StubServer::start().await.unwrap();
+    server.add_user("amina", "correct horse");
+    let home = Home::new();
+
+    home.run(&["config", "set", "api.endpoint", &server.url()]);
+    // Nothing to wait for: no countdown, and no room tone taken from the
+    // test signal
+    home.run(&["config", "set", "record.countdown_secs", "0"]);
+    home.run(&["config", "set", "record.room_tone_secs", "0"]);
+
+    // A wrong password is refused without storing credentials
+    let refused = home.run_with_input(&["auth", "login"], "amina\nwrong\n");
+    assert!(refused.contains("Login failed"), "{refused}");
+    let login = home.run_with_input(&["auth", "login"], "amina\ncorrect horse\n");
+    assert!(login.contains("Login successful"), "{login}");
+
+    let recorded = home.run(&[
+        "record",
+        "--backend",
+        "synthetic",
+        "--lang",
+        "sw",
+        "--prompt",
+        "Habari ya asubuhi",
+        "--duration",
+        "2",
+    ]);
+    assert!(recorded.contains("Recording complete"), "{recorded}");
+    assert!(recorded.contains("Duration reached"), "{recorded}");
+
+    // A fixture file plays through the same pipeline
+    let take = home.0.join("take.wav");
+    write_take(&take);
+    home.run(&[
+        "record",
+        "--backend",
+        "synthetic",
+        "--signal",
+        take.to_str().unwrap(),
+        "--lang",
+        "sw",
+        "--duration",
+        "2",
+    ]);
+    let stats = home.run(&["stats"]);
+    assert!(stats.contains("Total recordings: 2"), "{stats}");
+    assert!(stats.contains("Pending: 2"), "{stats}");
+
+    home.run(&["upload", "--force"]);
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 2);
+    assert_eq!(uploads[0].username, "amina");
+    assert_eq!(uploads[0].lang, "sw");
+    assert!(uploads[0].audio_bytes > 0);
+    assert!(uploads[0].qc_metrics.get("snr_db").is_some());
+
+    let stats = home.run(&["stats"]);
+    assert!(stats.contains("Uploaded: 2"), "{stats}");
+    assert!(stats.contains("Pending: 0"), "{stats}");
+
+    let balance = home.run(&["tokens", "balance"]);
+    assert!(
+        balance.contains(&format!(
+            "Current Balance: {} tokens",
+            server.balance("amina")
+        )),
+        "{balance}"
+    );
+    assert!(server.balance("amina") >= cowcow_server_stub::TOKENS_PER_MINUTE);
+
+    // Nothing is uploaded twice
+    home.run(&["upload", "--force"]);
+    assert_eq!(server.uploads().len(), 2);
+}
@@ -0,0 +1,98 @@
+//! Generates `cowcow.h`, the C header for the FFI functions, in `OUT_DIR`
+//!
+//! Builds never touch the source tree: the committed `include/cowcow.h` is
+//! refreshed only on request, by pointing `COWCOW_HEADER_DIR` at the
+//! directory to copy the header into.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=COWCOW_HEADER_DIR");
+
+    // Without the C API there is nothing to declare
+    if env::var_os("CARGO_FEATURE_FFI").is_none() {
+        return;
+    }
+
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("Failed to read cbindgen.toml");
+
+    // The header exports no other constants, so define the API version here
+    let ffi = fs::read_to_string(crate_dir.join("src").join("ffi.rs")).unwrap();
+    let api_version = ffi
+        .lines()
+        .find_map(|line| line.strip_prefix("pub const COWCOW_API_VERSION: u32 = "))
+        .and_then(|rest| rest.strip_suffix(';'))
+        .expect("COWCOW_API_VERSION not found in src/ffi.rs");
+    config.after_includes = Some(format!(
+        "\n// Version of the C API this header describes\n#define COWCOW_API_VERSION {api_version}"
+    ));
+
+    // A header that cannot be generated should not stop Rust users building
+    let bindings = match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => bindings,
+        Err(e) => {
+            println!("cargo:warning=Failed to generate cowcow.h: {e}");
+            return;
+        }
+    };
+    let mut generated = Vec::new();
+    bindings.write(&mut generated);
+    let header = PathBuf::from(env::var("OUT_DIR").unwrap()).join("cowcow.h");
+    fs::write(
+        &header,
+        strip_doc_links(&String::from_utf8(generated).unwrap()),
+    )
+    .expect("Failed to write cowcow.h");
+
+    if let Some(dir) = env::var_os("COWCOW_HEADER_DIR") {
+        let target = PathBuf::from(dir).join("cowcow.h");
+        fs::copy(&header, &target)
+            .unwrap_or_else(|e| panic!("Failed to copy cowcow.h to {}: {e}", target.display()));
+    }
+}
+
+/// Rewrite rustdoc links in the copied doc comments as plain C names
+///
+/// `[`ffi::cowcow_free`]` becomes `cowcow_free` and `[`CowcowStatus::Ok`]`
+/// the enumerator `COWCOW_STATUS_OK` that cbindgen generates for it.
+fn strip_doc_links(header: &str) -> String {
+    let mut out = String::with_capacity(header.len());
+    let mut rest = header;
+    while let Some(start) = rest.find("[`") {
+        let Some(len) = rest[start + 2..].find("`]") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = &rest[start + 2..start + 2 + len];
+        let mut segments = path.rsplit("::");
+        let name = segments.next().unwrap_or(path);
+        match segments.next() {
+            Some("CowcowStatus") => {
+                out.push_str("COWCOW_STATUS_");
+                out.push_str(&screaming_snake_case(name));
+            }
+            _ => out.push_str(name),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `InvalidArgument` -> `INVALID_ARGUMENT`, as cbindgen renames variants
+fn screaming_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
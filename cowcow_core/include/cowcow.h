@@ -0,0 +1,277 @@
+/* Cowcow C API. Generated by cbindgen from cowcow_core; do not edit. */
+
+#ifndef COWCOW_H
+#define COWCOW_H
+
+/* Check cowcow_api_version() against COWCOW_API_VERSION and pass it to cowcow_init(). */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Version of the C API this header describes
+#define COWCOW_API_VERSION 1
+
+// How multi-channel audio is reduced to the mono signal QC runs on
+typedef enum CowcowDownmix {
+  // Mean of all channels
+  COWCOW_DOWNMIX_AVERAGE = 0,
+  // First channel only
+  COWCOW_DOWNMIX_LEFT = 1,
+  // Second channel only (first channel for mono input)
+  COWCOW_DOWNMIX_RIGHT = 2,
+} CowcowDownmix;
+
+// Outcome of an FFI call
+typedef enum CowcowStatus {
+  COWCOW_STATUS_OK = 0,
+  // A required pointer argument was null
+  COWCOW_STATUS_NULL_POINTER = 1,
+  // A path argument was not valid UTF-8
+  COWCOW_STATUS_INVALID_PATH = 2,
+  // A file could not be opened, read or written
+  COWCOW_STATUS_IO = 3,
+  // A file is not a WAV file the library can read
+  COWCOW_STATUS_FORMAT = 4,
+  // An argument (sample rate, channel count, ...) is not supported
+  COWCOW_STATUS_INVALID_ARGUMENT = 5,
+  // Anything else, such as a VAD failure
+  COWCOW_STATUS_INTERNAL = 6,
+} CowcowStatus;
+
+// Opaque analysis settings shared by the `cowcow_analyze_*` functions
+//
+// Created by cowcow_init and released with cowcow_free; a context
+// is read-only during analysis, so it may be shared between threads as
+// long as cowcow_set_downmix is not called concurrently.
+typedef struct CowcowContext CowcowContext;
+
+// Opaque handle for analyzing a recording chunk by chunk over the C ABI
+//
+// Created by cowcow_processor_new and released with
+// cowcow_processor_free; a handle must not be used from two threads at
+// once.
+typedef struct CowcowProcessor CowcowProcessor;
+
+// The three metrics analyze_wav has always returned
+//
+// Frozen: callers compiled against the original header read exactly this
+// layout, so new metrics go in QcMetrics and reach C through
+// cowcow_analyze_wav_v2 only.
+typedef struct QcMetricsV1 {
+  // Signal-to-noise ratio in decibels
+  float snr_db;
+  // Percentage of samples that are clipped
+  float clipping_pct;
+  // Ratio of frames classified as speech by VAD
+  float vad_ratio;
+} QcMetricsV1;
+
+// Quality control metrics for audio recordings
+typedef struct QcMetrics {
+  // Signal-to-noise ratio in decibels
+  float snr_db;
+  // Percentage of samples that are clipped
+  float clipping_pct;
+  // Ratio of frames classified as speech by VAD
+  float vad_ratio;
+  // Mean sample value (DC offset) as a fraction of full scale
+  float dc_offset;
+  // Energy below 50 Hz relative to total energy, in decibels
+  float rumble_db;
+  // Mains hum (50 or 60 Hz and harmonics) relative to total energy, in decibels
+  float hum_db;
+  // Number of dropped or held buffers (runs of repeated samples cutting into the signal)
+  uint32_t dropout_count;
+  // Percentage of samples that are discontinuity clicks
+  float glitch_pct;
+  // Length of the analyzed audio in seconds
+  float duration_secs;
+  // Seconds of audio classified as speech by VAD
+  float speech_secs;
+  // Silence before the first speech frame, in seconds
+  float leading_silence_secs;
+  // Silence after the last speech frame, in seconds
+  float trailing_silence_secs;
+  // Estimated reverberation time (RT60) in seconds, 0 when no usable
+  // speech offset was found
+  float reverb_rt60_secs;
+  // Lowest fundamental frequency (pitch) in Hz, 0 when nothing was voiced
+  float f0_min_hz;
+  // Highest fundamental frequency in Hz, 0 when nothing was voiced
+  float f0_max_hz;
+  // Mean fundamental frequency over voiced frames in Hz
+  float f0_mean_hz;
+  // Percentage of frames with a detectable pitch; well below `vad_ratio`
+  // suggests whispering
+  float voiced_ratio;
+} QcMetrics;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Analyze a WAV file and return QC metrics (unsafe C FFI)
+//
+// The original entry point, unchanged for existing mobile integrations: it
+// returns the original three-field QcMetricsV1, and errors are logged
+// and reported as failing metrics. New C code should use
+// cowcow_analyze_wav_v2 or cowcow_analyze_file, and Rust code
+// the safe analyze_wav_file or analyze_reader.
+//
+// # Safety
+//
+// This function dereferences a raw pointer. The caller must ensure that:
+// - `path` is a valid pointer to a null-terminated C string
+// - The string pointed to by `path` is valid UTF-8 or UTF-8 compatible
+// - The pointer remains valid for the duration of the function call
+struct QcMetricsV1 analyze_wav(const char *path);
+
+// Analyze a WAV file into `metrics` (unsafe C FFI)
+//
+// Returns COWCOW_STATUS_OK on success. On failure `metrics` is left
+// untouched and cowcow_last_error_message describes the error.
+//
+// # Safety
+//
+// This function dereferences raw pointers. The caller must ensure that:
+// - `path` is a valid pointer to a null-terminated UTF-8 C string
+// - `metrics` is a valid pointer to writable QcMetrics
+// - Both pointers remain valid for the duration of the function call
+enum CowcowStatus cowcow_analyze_wav_v2(const char *path, struct QcMetrics *metrics);
+
+// Message describing the last error on the calling thread, or null if no
+// call on this thread has failed yet
+//
+// The string is owned by the library and stays valid until the next failing
+// call on the same thread.
+const char *cowcow_last_error_message(void);
+
+// Library release, e.g. `"0.1.0"`, as a static string the caller must not
+// free
+const char *cowcow_version(void);
+
+// The COWCOW_API_VERSION the library was built with
+uint32_t cowcow_api_version(void);
+
+// Create an analysis context with the default settings, storing it in
+// `context`
+//
+// `api_version` must be the `COWCOW_API_VERSION` from the caller's header;
+// a library implementing a different version fails with
+// COWCOW_STATUS_INVALID_ARGUMENT instead of misreading arguments.
+//
+// # Safety
+//
+// `context` must be a valid pointer to writable storage for a handle.
+enum CowcowStatus cowcow_init(uint32_t api_version, struct CowcowContext **context);
+
+// Set how multi-channel audio is reduced to mono (default: average)
+//
+// # Safety
+//
+// `context` must be a live handle from cowcow_init.
+enum CowcowStatus cowcow_set_downmix(struct CowcowContext *context, enum CowcowDownmix downmix);
+
+// Analyze an audio file into `metrics`: WAV of any sample format, FLAC,
+// MP3, Ogg Vorbis or M4A, and Opus when built with the `opus` feature
+//
+// # Safety
+//
+// `context` must be a live handle from cowcow_init, `path` a valid
+// null-terminated UTF-8 C string and `metrics` a valid pointer to writable
+// QcMetrics.
+enum CowcowStatus cowcow_analyze_file(const struct CowcowContext *context,
+                                      const char *path,
+                                      struct QcMetrics *metrics);
+
+// Analyze `len` interleaved samples (in [-1.0, 1.0]) of a complete
+// recording at `sample_rate` with `channels` channels into `metrics`
+//
+// Audio at rates QC does not run at natively (e.g. 44.1 kHz) is resampled
+// first. `len` must be a multiple of the channel count.
+//
+// # Safety
+//
+// `context` must be a live handle from cowcow_init, `samples` must
+// point to `len` readable floats and `metrics` to writable QcMetrics.
+enum CowcowStatus cowcow_analyze_buffer(const struct CowcowContext *context,
+                                        const float *samples,
+                                        uintptr_t len,
+                                        uint32_t sample_rate,
+                                        uint16_t channels,
+                                        struct QcMetrics *metrics);
+
+// Release a context; null is ignored
+//
+// # Safety
+//
+// `context` must be null or a handle from cowcow_init that has not
+// been freed, and must not be used afterwards.
+void cowcow_free(struct CowcowContext *context);
+
+// Create a streaming processor for interleaved audio at `sample_rate` with
+// `channels` channels, storing its handle in `processor`
+//
+// # Safety
+//
+// `processor` must be a valid pointer to writable storage for a handle.
+enum CowcowStatus cowcow_processor_new(uint32_t sample_rate,
+                                       uint16_t channels,
+                                       struct CowcowProcessor **processor);
+
+// Analyze the next `len` interleaved samples (in [-1.0, 1.0]) of a
+// recording, writing the chunk's metrics to `metrics` for live feedback
+//
+// `len` must be a whole number of frames, i.e. a multiple of the channel
+// count.
+//
+// # Safety
+//
+// `processor` must be a live handle from cowcow_processor_new,
+// `samples` must point to `len` readable floats and `metrics` to writable
+// QcMetrics.
+enum CowcowStatus cowcow_processor_process_chunk(struct CowcowProcessor *processor,
+                                                 const float *samples,
+                                                 uintptr_t len,
+                                                 struct QcMetrics *metrics);
+
+// Write the metrics of the whole recording processed so far to `metrics`,
+// and reset the processor for the next recording
+//
+// # Safety
+//
+// `processor` must be a live handle from cowcow_processor_new and
+// `metrics` a valid pointer to writable QcMetrics.
+enum CowcowStatus cowcow_processor_finalize(struct CowcowProcessor *processor,
+                                            struct QcMetrics *metrics);
+
+// Release a processor handle; null is ignored
+//
+// # Safety
+//
+// `processor` must be null or a handle from cowcow_processor_new that
+// has not been freed, and must not be used afterwards.
+void cowcow_processor_free(struct CowcowProcessor *processor);
+
+// Filter a WAV file of any sample format into another of the same format
+// (unsafe C FFI)
+//
+// A `high_pass_hz` of 0 or less disables the high-pass; a
+// `gate_reduction_db` of 0 disables the noise gate. On failure the status
+// says why and cowcow_last_error_message describes the error.
+//
+// # Safety
+//
+// `input` and `output` must be valid pointers to null-terminated UTF-8 C
+// strings that stay valid for the duration of the call.
+enum CowcowStatus cowcow_filter_wav(const char *input,
+                                    const char *output,
+                                    float high_pass_hz,
+                                    float gate_threshold_db,
+                                    float gate_reduction_db);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* COWCOW_H */
@@ -0,0 +1,252 @@
+//! Automatic transcription and word timing, and how far a hypothesis strays
+//! from the prompt
+//!
+//! Transcription and word timing run a local whisper.cpp model (behind the `whisper`
+//! feature, which builds whisper.cpp). The word error rate needs no model,
+//! so recordings can be checked against prompts wherever the hypotheses came
+//! from.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Sample rate whisper models take their audio at
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// What a model heard in a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hypothesis {
+    pub text: String,
+    /// Mean probability of the text's tokens, from 0 to 1
+    pub confidence: f32,
+}
+
+/// A loaded whisper model, reused across recordings
+#[cfg(feature = "whisper")]
+pub struct WhisperModel {
+    context: whisper_rs::WhisperContext,
+}
+
+#[cfg(feature = "whisper")]
+impl WhisperModel {
+    /// Load a ggml model file (e.g. `ggml-base.bin`)
+    pub fn load(path: &Path) -> Result<Self> {
+        let context = whisper_rs::WhisperContext::new_with_params(
+            &path.to_string_lossy(),
+            whisper_rs::WhisperContextParameters::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to load {}: {e}", path.display()))?;
+        Ok(Self { context })
+    }
+
+    /// Transcribe interleaved audio in [-1.0, 1.0], in `lang` (an ISO 639-1
+    /// code) or whatever language the model detects
+    pub fn transcribe(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u16,
+        lang: Option<&str>,
+    ) -> Result<Hypothesis> {
+        use whisper_rs::{FullParams, SamplingStrategy};
+
+        let audio = whisper_audio(samples, sample_rate, channels)?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(lang.or(Some("auto")));
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+
+        let error = |e: whisper_rs::WhisperError| anyhow::anyhow!("Transcription failed: {e}");
+        let mut state = self.context.create_state().map_err(error)?;
+        state.full(params, &audio).map_err(error)?;
+
+        let eot = self.context.token_eot();
+        let mut text = String::new();
+        let mut probabilities = Vec::new();
+        for segment in 0..state.full_n_segments().map_err(error)? {
+            text.push_str(&state.full_get_segment_text(segment).map_err(error)?);
+            for token in 0..state.full_n_tokens(segment).map_err(error)? {
+                // Timestamps and other special tokens come after end-of-text
+                if state.full_get_token_id(segment, token).map_err(error)? < eot {
+                    probabilities.push(state.full_get_token_prob(segment, token).map_err(error)?);
+                }
+            }
+        }
+        let confidence = if probabilities.is_empty() {
+            0.0
+        } else {
+            probabilities.iter().sum::<f32>() / probabilities.len() as f32
+        };
+        Ok(Hypothesis {
+            text: text.trim().to_string(),
+            confidence,
+        })
+    }
+
+    /// Time the words of `transcript` in interleaved audio in [-1.0, 1.0]
+    ///
+    /// The model transcribes the audio prompted with the transcript, one word
+    /// per segment with token timestamps, and the transcript is matched to
+    /// what it heard ([`crate::word_timing::match_transcript`]).
+    pub fn align_words(
+        &self,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u16,
+        transcript: &str,
+        lang: Option<&str>,
+    ) -> Result<crate::word_timing::WordAlignment> {
+        use crate::word_timing::{match_transcript, WordAlignment, WordTiming, WHISPER_ALIGNER};
+        use whisper_rs::{FullParams, SamplingStrategy};
+
+        let audio = whisper_audio(samples, sample_rate, channels)?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(lang.or(Some("auto")));
+        params.set_initial_prompt(transcript);
+        params.set_token_timestamps(true);
+        params.set_split_on_word(true);
+        params.set_max_len(1);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+
+        let error = |e: whisper_rs::WhisperError| anyhow::anyhow!("Alignment failed: {e}");
+        let mut state = self.context.create_state().map_err(error)?;
+        state.full(params, &audio).map_err(error)?;
+
+        // Segment times are in hundredths of a second
+        let mut heard = Vec::new();
+        for segment in 0..state.full_n_segments().map_err(error)? {
+            let word = state.full_get_segment_text(segment).map_err(error)?;
+            if normalized_word(&word).is_empty() {
+                continue;
+            }
+            heard.push(WordTiming {
+                word: word.trim().to_string(),
+                start_secs: state.full_get_segment_t0(segment).map_err(error)? as f64 / 100.0,
+                end_secs: state.full_get_segment_t1(segment).map_err(error)? as f64 / 100.0,
+            });
+        }
+        Ok(WordAlignment {
+            aligner: WHISPER_ALIGNER.to_string(),
+            words: match_transcript(transcript, &heard)?,
+        })
+    }
+}
+
+/// Interleaved audio as whisper takes it: mono at [`WHISPER_SAMPLE_RATE`]
+#[cfg(feature = "whisper")]
+fn whisper_audio(samples: &[f32], sample_rate: u32, channels: u16) -> Result<Vec<f32>> {
+    let channels = channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    let mut resampler = crate::resample::Resampler::new(sample_rate, WHISPER_SAMPLE_RATE, 1)?;
+    let mut audio = resampler.process(&mono)?;
+    audio.extend(resampler.flush()?);
+    Ok(audio)
+}
+
+/// A loaded whisper model, reused across recordings
+#[cfg(not(feature = "whisper"))]
+pub struct WhisperModel {
+    _private: (),
+}
+
+#[cfg(not(feature = "whisper"))]
+impl WhisperModel {
+    /// Load a ggml model file (e.g. `ggml-base.bin`)
+    pub fn load(_path: &Path) -> Result<Self> {
+        anyhow::bail!("cowcow_core was built without the `whisper` feature")
+    }
+
+    /// Transcribe interleaved audio in [-1.0, 1.0], in `lang` (an ISO 639-1
+    /// code) or whatever language the model detects
+    pub fn transcribe(
+        &self,
+        _samples: &[f32],
+        _sample_rate: u32,
+        _channels: u16,
+        _lang: Option<&str>,
+    ) -> Result<Hypothesis> {
+        anyhow::bail!("cowcow_core was built without the `whisper` feature")
+    }
+
+    /// Time the words of `transcript` in interleaved audio in [-1.0, 1.0]
+    pub fn align_words(
+        &self,
+        _samples: &[f32],
+        _sample_rate: u32,
+        _channels: u16,
+        _transcript: &str,
+        _lang: Option<&str>,
+    ) -> Result<crate::word_timing::WordAlignment> {
+        anyhow::bail!("cowcow_core was built without the `whisper` feature")
+    }
+}
+
+/// A word as compared against what was heard: lowercase, without
+/// punctuation
+pub(crate) fn normalized_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Words of `text` as compared for the error rate
+fn normalized_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(normalized_word)
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Word error rate of `hypothesis` against `reference`: substituted,
+/// deleted and inserted words over the reference's words, ignoring case and
+/// punctuation; 0 when both are empty
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f32 {
+    let reference = normalized_words(reference);
+    let hypothesis = normalized_words(hypothesis);
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+
+    // Edit distance over words, one row at a time
+    let mut previous: Vec<usize> = (0..=hypothesis.len()).collect();
+    for (i, expected) in reference.iter().enumerate() {
+        let mut row = vec![i + 1; hypothesis.len() + 1];
+        for (j, heard) in hypothesis.iter().enumerate() {
+            let substitution = previous[j] + usize::from(expected != heard);
+            row[j + 1] = substitution.min(previous[j + 1] + 1).min(row[j] + 1);
+        }
+        previous = row;
+    }
+    previous[hypothesis.len()] as f32 / reference.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_error_rate() {
+        assert_eq!(
+            word_error_rate("Habari ya asubuhi.", "habari ya asubuhi"),
+            0.0
+        );
+        assert!(
+            (word_error_rate("habari ya asubuhi", "habari za asubuhi") - 1.0 / 3.0).abs() < 1e-6
+        );
+        assert!((word_error_rate("habari ya asubuhi", "habari") - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(word_error_rate("habari", "habari ya asubuhi njema"), 3.0);
+        assert_eq!(word_error_rate("", ""), 0.0);
+        assert_eq!(word_error_rate("", "habari"), 1.0);
+    }
+}
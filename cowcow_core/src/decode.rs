@@ -0,0 +1,223 @@
+//! Decoding of compressed and non-16-bit audio files (via symphonia)
+
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::warn;
+
+/// File extensions [`decode_file`] understands
+pub const DECODABLE_EXTENSIONS: [&str; 9] = [
+    "wav", "flac", "opus", "mp3", "ogg", "oga", "m4a", "mp4", "aac",
+];
+
+/// Decoded audio
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    /// Interleaved samples in [-1.0, 1.0]
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl DecodedAudio {
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / (self.sample_rate as f64 * self.channels.max(1) as f64)
+    }
+
+    /// Interleaved samples with `channels` channels: mono sources are
+    /// duplicated, mono targets get the channel average, and otherwise
+    /// channels are dropped or the last one repeated
+    pub fn to_channels(&self, channels: u16) -> Vec<f32> {
+        let (from, to) = (self.channels.max(1) as usize, channels.max(1) as usize);
+        if from == to {
+            return self.samples.clone();
+        }
+
+        let mut out = Vec::with_capacity(self.samples.len() / from * to);
+        for frame in self.samples.chunks_exact(from) {
+            if to == 1 {
+                out.push(frame.iter().sum::<f32>() / from as f32);
+            } else {
+                out.extend((0..to).map(|channel| frame[channel.min(from - 1)]));
+            }
+        }
+        out
+    }
+}
+
+/// Whether a file's extension is one [`decode_file`] understands
+pub fn is_decodable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            DECODABLE_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Whether a file is a WAV file by its extension
+pub fn is_wav(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav") || ext.eq_ignore_ascii_case("wave"))
+}
+
+/// All samples of a WAV file as interleaved floats in [-1.0, 1.0], whatever
+/// its sample format (8 to 32-bit integer or 32-bit float)
+pub fn read_wav_samples<R: std::io::Read>(
+    reader: hound::WavReader<R>,
+) -> Result<Vec<f32>, hound::Error> {
+    let spec = reader.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect(),
+        hound::SampleFormat::Int if spec.bits_per_sample <= 16 => reader
+            .into_samples::<i16>()
+            .map(|sample| sample.map(|s| s as f32 / (1u32 << (spec.bits_per_sample - 1)) as f32))
+            .collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect()
+        }
+    }
+}
+
+/// Whether a recording is stored compressed (FLAC or Opus) rather than as
+/// WAV, and so has to go through [`decode_file`]
+pub fn is_compressed_recording(path: &Path) -> bool {
+    crate::flac::is_flac(path) || crate::opus::is_opus(path)
+}
+
+/// Decode the first audio track of a WAV (any bit depth), FLAC, Opus, MP3,
+/// Ogg Vorbis or M4A/AAC file
+///
+/// Corrupt packets are skipped with a warning rather than failing the file.
+/// Opus needs the `opus` feature.
+pub fn decode_file<P: AsRef<Path>>(path: P) -> Result<DecodedAudio> {
+    let path = path.as_ref();
+    if crate::opus::is_opus(path) {
+        return crate::opus::decode_file(path);
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_context(|| format!("Unsupported audio format: {}", path.display()))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .with_context(|| format!("No audio track in {}", path.display()))?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate;
+    let mut channels = track.codec_params.channels.map(|c| c.count() as u16);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .with_context(|| format!("Unsupported codec in {}", path.display()))?;
+
+    let mut samples = Vec::new();
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(e).context("Failed to read audio packet"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(e)) => {
+                warn!("Skipping corrupt packet in {}: {}", path.display(), e);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to decode audio"),
+        };
+
+        let spec = *decoded.spec();
+        sample_rate = Some(spec.rate);
+        channels = Some(spec.channels.count() as u16);
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * spec.channels.count() => {
+                buffer
+            }
+            _ => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: sample_rate.context("Unknown sample rate")?,
+        channels: channels
+            .filter(|&c| c > 0)
+            .context("Unknown channel count")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_float_wav() {
+        let path = std::env::temp_dir().join(format!("cowcow-decode-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..4410 {
+            let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin();
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(-sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let audio = decode_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(is_decodable(&path));
+        assert!(!is_decodable(Path::new("notes.txt")));
+        assert_eq!(audio.sample_rate, 44100);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples.len(), 8820);
+        assert!((audio.duration_secs() - 0.1).abs() < 1e-6);
+        assert!((audio.samples[20] + audio.samples[21]).abs() < 1e-6);
+
+        let mono = audio.to_channels(1);
+        assert_eq!(mono.len(), 4410);
+        assert!(mono.iter().all(|x| x.abs() < 1e-6));
+        assert_eq!(audio.to_channels(2), audio.samples);
+    }
+}
@@ -0,0 +1,205 @@
+//! Small signal processing building blocks shared by QC and filtering
+
+use std::f32::consts::PI;
+
+/// Second-order IIR filter (RBJ audio EQ cookbook designs)
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Butterworth Q, maximally flat passband
+    pub const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    pub fn low_pass(sample_rate: u32, cutoff_hz: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, cutoff_hz, q);
+        Self::normalized(
+            (1.0 - cos_w0) / 2.0,
+            1.0 - cos_w0,
+            (1.0 - cos_w0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn high_pass(sample_rate: u32, cutoff_hz: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, cutoff_hz, q);
+        Self::normalized(
+            (1.0 + cos_w0) / 2.0,
+            -(1.0 + cos_w0),
+            (1.0 + cos_w0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Filter from raw coefficients, normalized by `a0`
+    pub fn from_coefficients(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self::normalized(b[0], b[1], b[2], a[0], a[1], a[2])
+    }
+
+    fn prewarp(sample_rate: u32, cutoff_hz: f32, q: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Filter one sample (transposed direct form II)
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Clear the filter memory
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// Energy of the sinusoidal component at `freq_hz` (Goertzel algorithm)
+///
+/// Scaled so that a pure tone returns the same value as the sum of squared
+/// samples, making it directly comparable to total chunk energy.
+pub fn goertzel_energy(samples: &[f32], sample_rate: u32, freq_hz: f32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let w = 2.0 * PI * freq_hz / sample_rate as f32;
+    let coeff = 2.0 * w.cos();
+    let (mut s1, mut s2) = (0.0f32, 0.0f32);
+
+    for &x in samples {
+        let s0 = x + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+
+    let power = s1 * s1 + s2 * s2 - coeff * s1 * s2;
+    2.0 * power / samples.len() as f32
+}
+
+/// Power spectrum of one frame via radix-2 FFT, bins `0..=n/2`
+///
+/// The frame length must be a power of two; apply any window beforehand.
+pub fn power_spectrum(frame: &[f32]) -> Vec<f32> {
+    let n = frame.len();
+    let mut re = frame.to_vec();
+    let mut im = vec![0.0f32; n];
+    fft(&mut re, &mut im);
+
+    (0..=n / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect()
+}
+
+/// In-place radix-2 FFT of a complex signal
+///
+/// The length must be a power of two.
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    assert!(n.is_power_of_two(), "FFT length must be a power of two");
+    assert_eq!(n, im.len(), "FFT real and imaginary parts differ in length");
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let step = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (step * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// In-place inverse of [`fft`], scaled so that `ifft(fft(x)) == x`
+pub fn ifft(re: &mut [f32], im: &mut [f32]) {
+    // Conjugate, transform, conjugate again
+    im.iter_mut().for_each(|x| *x = -*x);
+    fft(re, im);
+    let scale = 1.0 / re.len() as f32;
+    re.iter_mut().for_each(|x| *x *= scale);
+    im.iter_mut().for_each(|x| *x *= -scale);
+}
+
+/// Mean sample value
+pub fn mean(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().sum::<f32>() / samples.len() as f32
+}
+
+/// Convert an energy ratio to decibels, floored to avoid `-inf`
+pub fn energy_ratio_db(numerator: f32, denominator: f32) -> f32 {
+    const FLOOR_DB: f32 = -100.0;
+    if denominator <= f32::EPSILON || numerator <= f32::EPSILON {
+        return FLOOR_DB;
+    }
+    (10.0 * (numerator / denominator).log10()).max(FLOOR_DB)
+}
+
+/// Noise floor of a stretch of room tone, in dBFS
+///
+/// Median RMS level of 30ms frames, so a cough or door slam during the
+/// capture does not raise the floor.
+pub fn noise_floor_db(samples: &[f32], sample_rate: u32) -> f32 {
+    let frame_len = (sample_rate as usize * 30 / 1000).max(1);
+    let mut levels: Vec<f32> = samples
+        .chunks_exact(frame_len)
+        .map(|frame| {
+            let mean_square = frame.iter().map(|&x| x * x).sum::<f32>() / frame_len as f32;
+            10.0 * mean_square.max(1e-10).log10()
+        })
+        .collect();
+
+    if levels.is_empty() {
+        return -100.0;
+    }
+    levels.sort_by(f32::total_cmp);
+    levels[levels.len() / 2]
+}
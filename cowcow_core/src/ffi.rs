@@ -0,0 +1,537 @@
+//! The C API, declared for C and C++ integrators in the generated
+//! `include/cowcow.h`
+//!
+//! Every fallible FFI function returns a [`CowcowStatus`] and writes its
+//! result through an out-parameter. On failure, a description of the error
+//! is kept per thread and can be read with [`cowcow_last_error_message`].
+//!
+//! Integrators call [`cowcow_init`] with the [`COWCOW_API_VERSION`] their
+//! header was generated for, analyze files and buffers with
+//! [`cowcow_analyze_file`] and [`cowcow_analyze_buffer`], and release the
+//! context with [`cowcow_free`]. The API version only changes when a
+//! function signature or struct layout changes incompatibly;
+//! [`cowcow_version`] reports the library release.
+//!
+//! Besides the whole-file functions, a [`CowcowProcessor`] handle gives
+//! recorder apps live QC while recording: create it with
+//! [`cowcow_processor_new`], feed buffers to
+//! [`cowcow_processor_process_chunk`], read the recording's metrics with
+//! [`cowcow_processor_finalize`] and release it with
+//! [`cowcow_processor_free`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+
+use crate::{
+    analyze_samples_timeline, analyze_wav_timeline, AudioError, AudioProcessor, DownmixStrategy,
+    ProcessorConfig, QcAccumulator, QcMetrics, DEFAULT_ANALYSIS_RATE,
+};
+
+/// Version of the C API this library implements
+pub const COWCOW_API_VERSION: u32 = 1;
+
+/// Outcome of an FFI call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CowcowStatus {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A path argument was not valid UTF-8
+    InvalidPath = 2,
+    /// A file could not be opened, read or written
+    Io = 3,
+    /// A file is not a WAV file the library can read
+    Format = 4,
+    /// An argument (sample rate, channel count, ...) is not supported
+    InvalidArgument = 5,
+    /// Anything else, such as a VAD failure
+    Internal = 6,
+}
+
+impl From<&AudioError> for CowcowStatus {
+    fn from(error: &AudioError) -> Self {
+        match error {
+            AudioError::FileOpen(_) => CowcowStatus::Io,
+            AudioError::WavFormat(hound::Error::IoError(_)) => CowcowStatus::Io,
+            AudioError::WavFormat(_) | AudioError::Decode(_) => CowcowStatus::Format,
+            AudioError::Speed(_) | AudioError::SampleRate(_) | AudioError::NoChannels => {
+                CowcowStatus::InvalidArgument
+            }
+            AudioError::VadError(_) | AudioError::Resample(_) => CowcowStatus::Internal,
+        }
+    }
+}
+
+impl From<&anyhow::Error> for CowcowStatus {
+    fn from(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<AudioError>() {
+                return error.into();
+            }
+            if cause.is::<std::io::Error>() {
+                return CowcowStatus::Io;
+            }
+            if let Some(error) = cause.downcast_ref::<hound::Error>() {
+                return match error {
+                    hound::Error::IoError(_) => CowcowStatus::Io,
+                    _ => CowcowStatus::Format,
+                };
+            }
+        }
+        CowcowStatus::Internal
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Remember an error for [`cowcow_last_error_message`] and return its status
+pub(crate) fn fail(status: CowcowStatus, message: impl std::fmt::Display) -> CowcowStatus {
+    let message = message.to_string();
+    tracing::error!("{}", message);
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+/// Record an analysis error and return its status
+pub(crate) fn fail_with(error: impl Into<anyhow::Error>) -> CowcowStatus {
+    let error = error.into();
+    fail((&error).into(), format!("{error:#}"))
+}
+
+/// Read a path argument
+///
+/// # Safety
+///
+/// `path` must be null or a valid pointer to a null-terminated C string.
+pub(crate) unsafe fn path_arg(path: *const c_char) -> Result<PathBuf, CowcowStatus> {
+    if path.is_null() {
+        return Err(fail(CowcowStatus::NullPointer, "Path argument is null"));
+    }
+    CStr::from_ptr(path)
+        .to_str()
+        .map(PathBuf::from)
+        .map_err(|_| fail(CowcowStatus::InvalidPath, "Path is not valid UTF-8"))
+}
+
+/// Message describing the last error on the calling thread, or null if no
+/// call on this thread has failed yet
+///
+/// The string is owned by the library and stays valid until the next failing
+/// call on the same thread.
+#[no_mangle]
+pub extern "C" fn cowcow_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Library release, e.g. `"0.1.0"`, as a static string the caller must not
+/// free
+#[no_mangle]
+pub extern "C" fn cowcow_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// The [`COWCOW_API_VERSION`] the library was built with
+#[no_mangle]
+pub extern "C" fn cowcow_api_version() -> u32 {
+    COWCOW_API_VERSION
+}
+
+/// How multi-channel audio is reduced to the mono signal QC runs on
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CowcowDownmix {
+    /// Mean of all channels
+    Average = 0,
+    /// First channel only
+    Left = 1,
+    /// Second channel only (first channel for mono input)
+    Right = 2,
+}
+
+impl From<CowcowDownmix> for DownmixStrategy {
+    fn from(downmix: CowcowDownmix) -> Self {
+        match downmix {
+            CowcowDownmix::Average => DownmixStrategy::Average,
+            CowcowDownmix::Left => DownmixStrategy::Left,
+            CowcowDownmix::Right => DownmixStrategy::Right,
+        }
+    }
+}
+
+/// Opaque analysis settings shared by the `cowcow_analyze_*` functions
+///
+/// Created by [`cowcow_init`] and released with [`cowcow_free`]; a context
+/// is read-only during analysis, so it may be shared between threads as
+/// long as [`cowcow_set_downmix`] is not called concurrently.
+pub struct CowcowContext {
+    config: ProcessorConfig,
+}
+
+/// Create an analysis context with the default settings, storing it in
+/// `context`
+///
+/// `api_version` must be the `COWCOW_API_VERSION` from the caller's header;
+/// a library implementing a different version fails with
+/// [`CowcowStatus::InvalidArgument`] instead of misreading arguments.
+///
+/// # Safety
+///
+/// `context` must be a valid pointer to writable storage for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_init(
+    api_version: u32,
+    context: *mut *mut CowcowContext,
+) -> CowcowStatus {
+    if context.is_null() {
+        return fail(CowcowStatus::NullPointer, "Context argument is null");
+    }
+    if api_version != COWCOW_API_VERSION {
+        return fail(
+            CowcowStatus::InvalidArgument,
+            format!(
+                "Header is for C API version {api_version}, but the library implements version {COWCOW_API_VERSION}"
+            ),
+        );
+    }
+    *context = Box::into_raw(Box::new(CowcowContext {
+        config: ProcessorConfig::new(DEFAULT_ANALYSIS_RATE, 1),
+    }));
+    CowcowStatus::Ok
+}
+
+/// Set how multi-channel audio is reduced to mono (default: average)
+///
+/// # Safety
+///
+/// `context` must be a live handle from [`cowcow_init`].
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_set_downmix(
+    context: *mut CowcowContext,
+    downmix: CowcowDownmix,
+) -> CowcowStatus {
+    if context.is_null() {
+        return fail(CowcowStatus::NullPointer, "Context argument is null");
+    }
+    (*context).config.downmix = downmix.into();
+    CowcowStatus::Ok
+}
+
+/// Analyze an audio file into `metrics`: WAV of any sample format, FLAC,
+/// MP3, Ogg Vorbis or M4A, and Opus when built with the `opus` feature
+///
+/// # Safety
+///
+/// `context` must be a live handle from [`cowcow_init`], `path` a valid
+/// null-terminated UTF-8 C string and `metrics` a valid pointer to writable
+/// [`QcMetrics`].
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_analyze_file(
+    context: *const CowcowContext,
+    path: *const c_char,
+    metrics: *mut QcMetrics,
+) -> CowcowStatus {
+    if context.is_null() || metrics.is_null() {
+        return fail(
+            CowcowStatus::NullPointer,
+            "Context or metrics argument is null",
+        );
+    }
+    let path = match path_arg(path) {
+        Ok(path) => path,
+        Err(status) => return status,
+    };
+
+    match analyze_wav_timeline(&path, (*context).config.clone()) {
+        Ok(timeline) => {
+            *metrics = timeline.summary();
+            CowcowStatus::Ok
+        }
+        Err(e) => fail_with(e),
+    }
+}
+
+/// Analyze `len` interleaved samples (in [-1.0, 1.0]) of a complete
+/// recording at `sample_rate` with `channels` channels into `metrics`
+///
+/// Audio at rates QC does not run at natively (e.g. 44.1 kHz) is resampled
+/// first. `len` must be a multiple of the channel count.
+///
+/// # Safety
+///
+/// `context` must be a live handle from [`cowcow_init`], `samples` must
+/// point to `len` readable floats and `metrics` to writable [`QcMetrics`].
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_analyze_buffer(
+    context: *const CowcowContext,
+    samples: *const f32,
+    len: usize,
+    sample_rate: u32,
+    channels: u16,
+    metrics: *mut QcMetrics,
+) -> CowcowStatus {
+    if context.is_null() || samples.is_null() || metrics.is_null() {
+        return fail(
+            CowcowStatus::NullPointer,
+            "Context, samples or metrics argument is null",
+        );
+    }
+    if channels == 0 {
+        return fail_with(AudioError::NoChannels);
+    }
+    if !len.is_multiple_of(channels as usize) {
+        return fail(
+            CowcowStatus::InvalidArgument,
+            format!("Buffer of {len} samples is not a whole number of {channels}-channel frames"),
+        );
+    }
+
+    let samples = std::slice::from_raw_parts(samples, len).to_vec();
+    match analyze_samples_timeline(samples, sample_rate, channels, (*context).config.clone()) {
+        Ok(timeline) => {
+            *metrics = timeline.summary();
+            CowcowStatus::Ok
+        }
+        Err(e) => fail_with(e),
+    }
+}
+
+/// Release a context; null is ignored
+///
+/// # Safety
+///
+/// `context` must be null or a handle from [`cowcow_init`] that has not
+/// been freed, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_free(context: *mut CowcowContext) {
+    if !context.is_null() {
+        drop(Box::from_raw(context));
+    }
+}
+
+/// Opaque handle for analyzing a recording chunk by chunk over the C ABI
+///
+/// Created by [`cowcow_processor_new`] and released with
+/// [`cowcow_processor_free`]; a handle must not be used from two threads at
+/// once.
+pub struct CowcowProcessor {
+    processor: AudioProcessor,
+    totals: QcAccumulator,
+}
+
+/// Create a streaming processor for interleaved audio at `sample_rate` with
+/// `channels` channels, storing its handle in `processor`
+///
+/// # Safety
+///
+/// `processor` must be a valid pointer to writable storage for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_processor_new(
+    sample_rate: u32,
+    channels: u16,
+    processor: *mut *mut CowcowProcessor,
+) -> CowcowStatus {
+    if processor.is_null() {
+        return fail(CowcowStatus::NullPointer, "Processor argument is null");
+    }
+    match AudioProcessor::new(sample_rate, channels) {
+        Ok(inner) => {
+            *processor = Box::into_raw(Box::new(CowcowProcessor {
+                processor: inner,
+                totals: QcAccumulator::default(),
+            }));
+            CowcowStatus::Ok
+        }
+        Err(e) => fail_with(e),
+    }
+}
+
+/// Analyze the next `len` interleaved samples (in [-1.0, 1.0]) of a
+/// recording, writing the chunk's metrics to `metrics` for live feedback
+///
+/// `len` must be a whole number of frames, i.e. a multiple of the channel
+/// count.
+///
+/// # Safety
+///
+/// `processor` must be a live handle from [`cowcow_processor_new`],
+/// `samples` must point to `len` readable floats and `metrics` to writable
+/// [`QcMetrics`].
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_processor_process_chunk(
+    processor: *mut CowcowProcessor,
+    samples: *const f32,
+    len: usize,
+    metrics: *mut QcMetrics,
+) -> CowcowStatus {
+    if processor.is_null() || samples.is_null() || metrics.is_null() {
+        return fail(
+            CowcowStatus::NullPointer,
+            "Processor, samples or metrics argument is null",
+        );
+    }
+    let handle = &mut *processor;
+    let channels = handle.processor.channels() as usize;
+    if !len.is_multiple_of(channels) {
+        return fail(
+            CowcowStatus::InvalidArgument,
+            format!("Chunk of {len} samples is not a whole number of {channels}-channel frames"),
+        );
+    }
+
+    let chunk = handle
+        .processor
+        .process_chunk(std::slice::from_raw_parts(samples, len));
+    handle.totals.push(&chunk);
+    *metrics = chunk;
+    CowcowStatus::Ok
+}
+
+/// Write the metrics of the whole recording processed so far to `metrics`,
+/// and reset the processor for the next recording
+///
+/// # Safety
+///
+/// `processor` must be a live handle from [`cowcow_processor_new`] and
+/// `metrics` a valid pointer to writable [`QcMetrics`].
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_processor_finalize(
+    processor: *mut CowcowProcessor,
+    metrics: *mut QcMetrics,
+) -> CowcowStatus {
+    if processor.is_null() || metrics.is_null() {
+        return fail(
+            CowcowStatus::NullPointer,
+            "Processor or metrics argument is null",
+        );
+    }
+    let handle = &mut *processor;
+    *metrics = handle.totals.finish();
+    handle.totals = QcAccumulator::default();
+    match handle.processor.reset() {
+        Ok(()) => CowcowStatus::Ok,
+        Err(e) => fail_with(e),
+    }
+}
+
+/// Release a processor handle; null is ignored
+///
+/// # Safety
+///
+/// `processor` must be null or a handle from [`cowcow_processor_new`] that
+/// has not been freed, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_processor_free(processor: *mut CowcowProcessor) {
+    if !processor.is_null() {
+        drop(Box::from_raw(processor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyze_wav, cowcow_analyze_wav_v2};
+
+    #[test]
+    fn test_ffi_errors() {
+        let mut metrics = QcMetrics::default();
+        let missing = CString::new("/nonexistent/recording.wav").unwrap();
+        let status = unsafe { cowcow_analyze_wav_v2(missing.as_ptr(), &mut metrics) };
+        assert_eq!(status, CowcowStatus::Io);
+        let message = unsafe { CStr::from_ptr(cowcow_last_error_message()) };
+        assert!(!message.to_bytes().is_empty());
+
+        let status = unsafe { cowcow_analyze_wav_v2(std::ptr::null(), &mut metrics) };
+        assert_eq!(status, CowcowStatus::NullPointer);
+        let status = unsafe { cowcow_analyze_wav_v2(missing.as_ptr(), std::ptr::null_mut()) };
+        assert_eq!(status, CowcowStatus::NullPointer);
+
+        // The original entry point still reports failures as failing metrics
+        let legacy = unsafe { analyze_wav(missing.as_ptr()) };
+        assert_eq!(legacy.clipping_pct, 100.0);
+        assert_eq!(legacy.snr_db, 0.0);
+        // ...in the three-field layout callers were compiled against
+        assert_eq!(
+            std::mem::size_of_val(&legacy),
+            3 * std::mem::size_of::<f32>()
+        );
+
+        let format = anyhow::Error::from(AudioError::WavFormat(hound::Error::FormatError("bad")));
+        assert_eq!(CowcowStatus::from(&format), CowcowStatus::Format);
+    }
+
+    #[test]
+    fn test_streaming_processor() {
+        let mut processor = std::ptr::null_mut();
+        let status = unsafe { cowcow_processor_new(16000, 2, &mut processor) };
+        assert_eq!(status, CowcowStatus::Ok);
+
+        let tone: Vec<f32> = (0..3200)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * (i / 2) as f32 / 16000.0).sin())
+            .collect();
+        let mut chunk = QcMetrics::default();
+        for _ in 0..3 {
+            let status = unsafe {
+                cowcow_processor_process_chunk(processor, tone.as_ptr(), tone.len(), &mut chunk)
+            };
+            assert_eq!(status, CowcowStatus::Ok);
+            assert!((chunk.duration_secs - 0.1).abs() < 1e-3);
+        }
+
+        // Half a stereo frame is refused
+        let status =
+            unsafe { cowcow_processor_process_chunk(processor, tone.as_ptr(), 3, &mut chunk) };
+        assert_eq!(status, CowcowStatus::InvalidArgument);
+
+        let mut total = QcMetrics::default();
+        let status = unsafe { cowcow_processor_finalize(processor, &mut total) };
+        assert_eq!(status, CowcowStatus::Ok);
+        assert!((total.duration_secs - 0.3).abs() < 1e-3);
+        unsafe { cowcow_processor_free(processor) };
+
+        let status = unsafe { cowcow_processor_new(16000, 0, &mut processor) };
+        assert_eq!(status, CowcowStatus::InvalidArgument);
+    }
+
+    #[test]
+    fn test_c_api() {
+        let version = unsafe { CStr::from_ptr(cowcow_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+
+        let mut context = std::ptr::null_mut();
+        let status = unsafe { cowcow_init(COWCOW_API_VERSION + 1, &mut context) };
+        assert_eq!(status, CowcowStatus::InvalidArgument);
+        assert!(context.is_null());
+        let status = unsafe { cowcow_init(cowcow_api_version(), &mut context) };
+        assert_eq!(status, CowcowStatus::Ok);
+
+        // Half a second of 44.1 kHz stereo is resampled for analysis
+        let tone: Vec<f32> = (0..44100)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * (i / 2) as f32 / 44100.0).sin())
+            .collect();
+        let mut metrics = QcMetrics::default();
+        let status = unsafe {
+            cowcow_set_downmix(context, CowcowDownmix::Left);
+            cowcow_analyze_buffer(context, tone.as_ptr(), tone.len(), 44100, 2, &mut metrics)
+        };
+        assert_eq!(status, CowcowStatus::Ok);
+        assert!((metrics.duration_secs - 0.5).abs() < 0.01);
+
+        let status =
+            unsafe { cowcow_analyze_buffer(context, tone.as_ptr(), 3, 44100, 2, &mut metrics) };
+        assert_eq!(status, CowcowStatus::InvalidArgument);
+
+        let missing = CString::new("/nonexistent/recording.wav").unwrap();
+        let status = unsafe { cowcow_analyze_file(context, missing.as_ptr(), &mut metrics) };
+        assert_eq!(status, CowcowStatus::Io);
+        unsafe { cowcow_free(context) };
+    }
+}
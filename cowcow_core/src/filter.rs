@@ -0,0 +1,412 @@
+//! Optional clean-up of captured audio: a high-pass for wind and handling
+//! rumble, and a spectral noise gate for steady fan and hiss noise
+
+use std::f32::consts::PI;
+#[cfg(feature = "ffi")]
+use std::ffi::c_char;
+#[cfg(feature = "wav")]
+use std::path::Path;
+
+#[cfg(feature = "wav")]
+use anyhow::{Context, Result};
+
+use crate::dsp::{self, Biquad};
+#[cfg(feature = "ffi")]
+use crate::ffi::{self, CowcowStatus};
+
+/// High-pass cutoff when none is configured; below the lowest voice
+/// fundamentals
+pub const DEFAULT_HIGH_PASS_HZ: f32 = 80.0;
+
+/// How far above the noise estimate a frequency bin must rise to pass the gate
+pub const DEFAULT_GATE_THRESHOLD_DB: f32 = 6.0;
+
+/// Attenuation of gated bins; removing noise entirely sounds unnatural
+pub const DEFAULT_GATE_REDUCTION_DB: f32 = 12.0;
+
+/// Noise gate analysis frame; the gate delays audio by half a frame
+const GATE_FRAME_MS: u32 = 32;
+
+/// How fast the noise estimate may rise during continuous sound
+const NOISE_RISE_DB_PER_SEC: f32 = 3.0;
+
+/// Weight of the previous frame's gain, which softens "musical noise"
+const GAIN_SMOOTHING: f32 = 0.5;
+
+/// Which filters to apply
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FilterConfig {
+    /// High-pass cutoff in Hz (`None` disables the high-pass)
+    pub high_pass_hz: Option<f32>,
+    /// Spectral noise gate (`None` disables the gate)
+    pub noise_gate: Option<NoiseGate>,
+}
+
+impl FilterConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.high_pass_hz.is_some() || self.noise_gate.is_some()
+    }
+}
+
+/// Spectral noise gate settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseGate {
+    /// Level above the tracked noise, in dB, at which a bin opens
+    pub threshold_db: f32,
+    /// Attenuation of closed bins in dB
+    pub reduction_db: f32,
+}
+
+impl Default for NoiseGate {
+    fn default() -> Self {
+        Self {
+            threshold_db: DEFAULT_GATE_THRESHOLD_DB,
+            reduction_db: DEFAULT_GATE_REDUCTION_DB,
+        }
+    }
+}
+
+/// Streaming filter chain for interleaved audio: high-pass, then noise gate
+///
+/// Like [`crate::resample::Resampler`] it returns whatever output is ready;
+/// [`FilterChain::flush`] drains the gate's delay at the end of a stream so
+/// the output length matches the input. Each channel is filtered on its own.
+pub struct FilterChain {
+    channels: usize,
+    high_pass: Vec<Biquad>,
+    gates: Vec<SpectralGate>,
+    /// Frames of gate delay still to drop from the start of the output
+    skip_frames: usize,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl FilterChain {
+    pub fn new(sample_rate: u32, channels: u16, config: FilterConfig) -> Self {
+        let channels = channels.max(1) as usize;
+        let high_pass = config.high_pass_hz.map_or_else(Vec::new, |cutoff_hz| {
+            vec![Biquad::high_pass(sample_rate, cutoff_hz, Biquad::BUTTERWORTH_Q); channels]
+        });
+        let gates: Vec<SpectralGate> = config.noise_gate.map_or_else(Vec::new, |gate| {
+            (0..channels)
+                .map(|_| SpectralGate::new(sample_rate, gate))
+                .collect()
+        });
+        let skip_frames = gates.first().map_or(0, |gate| gate.hop);
+
+        Self {
+            channels,
+            high_pass,
+            gates,
+            skip_frames,
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    /// Filter a piece of interleaved input, returning interleaved output
+    pub fn process(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        self.input_frames += (interleaved.len() / self.channels) as u64;
+        self.run(interleaved)
+    }
+
+    /// Drain the noise gate's delayed audio at the end of a stream
+    pub fn flush(&mut self) -> Vec<f32> {
+        let Some(hop) = self.gates.first().map(|gate| gate.hop) else {
+            return Vec::new();
+        };
+        let missing = self.input_frames.saturating_sub(self.output_frames) as usize;
+
+        // Half-filled frames and the delay both come out within two hops
+        let mut output = self.run(&vec![0.0; 2 * hop * self.channels]);
+        output.truncate(missing * self.channels);
+        self.output_frames = self.input_frames;
+        output
+    }
+
+    fn run(&mut self, interleaved: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        let mut samples = interleaved[..interleaved.len() / channels * channels].to_vec();
+        if !self.high_pass.is_empty() {
+            for frame in samples.chunks_exact_mut(channels) {
+                for (x, filter) in frame.iter_mut().zip(&mut self.high_pass) {
+                    *x = filter.process(*x);
+                }
+            }
+        }
+        if self.gates.is_empty() {
+            self.output_frames += (samples.len() / channels) as u64;
+            return samples;
+        }
+
+        let gated: Vec<Vec<f32>> = self
+            .gates
+            .iter_mut()
+            .enumerate()
+            .map(|(channel, gate)| {
+                let input: Vec<f32> = samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(channels)
+                    .copied()
+                    .collect();
+                gate.process(&input)
+            })
+            .collect();
+
+        let frames = gated[0].len();
+        let skip = self.skip_frames.min(frames);
+        self.skip_frames -= skip;
+        let mut output = Vec::with_capacity((frames - skip) * channels);
+        for frame in skip..frames {
+            output.extend(gated.iter().map(|channel| channel[frame]));
+        }
+        self.output_frames += (frames - skip) as u64;
+        output
+    }
+}
+
+/// Single-channel STFT noise gate
+///
+/// Tracks a per-bin noise estimate that follows the quietest recent level
+/// (falling at once, rising slowly) and attenuates bins that do not rise
+/// [`NoiseGate::threshold_db`] above it. Frames overlap by half and use a
+/// square-root Hann window for both analysis and synthesis, so unattenuated
+/// audio is reconstructed exactly.
+struct SpectralGate {
+    hop: usize,
+    window: Vec<f32>,
+    /// The most recent frame of input
+    frame: Vec<f32>,
+    /// Input waiting for a full hop
+    queue: Vec<f32>,
+    /// Overlap-add output, the first hop of which is complete
+    overlap: Vec<f32>,
+    /// Noise power per bin, empty until the first frame
+    noise: Vec<f32>,
+    gains: Vec<f32>,
+    /// Per-frame growth factor of the noise estimate
+    rise: f32,
+    /// Power ratio over the noise at which a bin opens
+    threshold: f32,
+    /// Amplitude gain of closed bins
+    floor_gain: f32,
+}
+
+impl SpectralGate {
+    fn new(sample_rate: u32, gate: NoiseGate) -> Self {
+        let frame_len = (sample_rate * GATE_FRAME_MS / 1000)
+            .max(4)
+            .next_power_of_two() as usize;
+        let hop = frame_len / 2;
+        let window = (0..frame_len)
+            .map(|n| (0.5 - 0.5 * (2.0 * PI * n as f32 / frame_len as f32).cos()).sqrt())
+            .collect();
+        let hop_secs = hop as f32 / sample_rate as f32;
+
+        Self {
+            hop,
+            window,
+            frame: vec![0.0; frame_len],
+            queue: Vec::with_capacity(frame_len),
+            overlap: vec![0.0; frame_len],
+            noise: Vec::new(),
+            gains: vec![1.0; frame_len / 2 + 1],
+            rise: 10f32.powf(NOISE_RISE_DB_PER_SEC * hop_secs / 10.0),
+            threshold: 10f32.powf(gate.threshold_db / 10.0),
+            floor_gain: 10f32.powf(-gate.reduction_db.abs() / 20.0),
+        }
+    }
+
+    /// Gate a piece of input, returning one hop of output per full hop of input
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.queue.extend_from_slice(samples);
+        let mut output = Vec::with_capacity(self.queue.len() / self.hop * self.hop);
+        let mut start = 0;
+        while self.queue.len() - start >= self.hop {
+            let hop = self.hop;
+            self.frame.copy_within(hop.., 0);
+            let tail = self.frame.len() - hop;
+            self.frame[tail..].copy_from_slice(&self.queue[start..start + hop]);
+            start += hop;
+            self.gate_frame(&mut output);
+        }
+        self.queue.drain(..start);
+        output
+    }
+
+    fn gate_frame(&mut self, output: &mut Vec<f32>) {
+        let n = self.frame.len();
+        let mut re: Vec<f32> = self
+            .frame
+            .iter()
+            .zip(&self.window)
+            .map(|(&x, &w)| x * w)
+            .collect();
+        let mut im = vec![0.0f32; n];
+        dsp::fft(&mut re, &mut im);
+
+        let power: Vec<f32> = (0..=n / 2)
+            .map(|k| (re[k] * re[k] + im[k] * im[k]).max(1e-12))
+            .collect();
+        if self.noise.is_empty() {
+            self.noise = power.clone();
+        }
+
+        for k in 0..=n / 2 {
+            self.noise[k] = power[k].min(self.noise[k] * self.rise);
+            let target = if power[k] > self.noise[k] * self.threshold {
+                1.0
+            } else {
+                self.floor_gain
+            };
+            let gain = GAIN_SMOOTHING * self.gains[k] + (1.0 - GAIN_SMOOTHING) * target;
+            self.gains[k] = gain;
+
+            // Keep the spectrum conjugate-symmetric so the output stays real
+            re[k] *= gain;
+            im[k] *= gain;
+            if k > 0 && k < n / 2 {
+                re[n - k] *= gain;
+                im[n - k] *= gain;
+            }
+        }
+
+        dsp::ifft(&mut re, &mut im);
+        for ((acc, &x), &w) in self.overlap.iter_mut().zip(&re).zip(&self.window) {
+            *acc += x * w;
+        }
+        output.extend_from_slice(&self.overlap[..self.hop]);
+        self.overlap.copy_within(self.hop.., 0);
+        let tail = n - self.hop;
+        self.overlap[tail..].fill(0.0);
+    }
+}
+
+/// Filter a WAV file into another of the same sample format
+#[cfg(feature = "wav")]
+pub fn filter_wav_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    config: FilterConfig,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let reader = hound::WavReader::open(input)
+        .with_context(|| format!("Failed to open {}", input.display()))?;
+    let spec = reader.spec();
+    let samples = crate::decode::read_wav_samples(reader)?;
+
+    let mut chain = FilterChain::new(spec.sample_rate, spec.channels, config);
+    let mut filtered = chain.process(&samples);
+    filtered.extend(chain.flush());
+
+    let mut writer = hound::WavWriter::create(output, spec)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    for &sample in &filtered {
+        crate::wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Filter a WAV file of any sample format into another of the same format
+/// (unsafe C FFI)
+///
+/// A `high_pass_hz` of 0 or less disables the high-pass; a
+/// `gate_reduction_db` of 0 disables the noise gate. On failure the status
+/// says why and [`ffi::cowcow_last_error_message`] describes the error.
+///
+/// # Safety
+///
+/// `input` and `output` must be valid pointers to null-terminated UTF-8 C
+/// strings that stay valid for the duration of the call.
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_filter_wav(
+    input: *const c_char,
+    output: *const c_char,
+    high_pass_hz: f32,
+    gate_threshold_db: f32,
+    gate_reduction_db: f32,
+) -> CowcowStatus {
+    let (input, output) = match (ffi::path_arg(input), ffi::path_arg(output)) {
+        (Ok(input), Ok(output)) => (input, output),
+        (Err(status), _) | (_, Err(status)) => return status,
+    };
+    let config = FilterConfig {
+        high_pass_hz: (high_pass_hz > 0.0).then_some(high_pass_hz),
+        noise_gate: (gate_reduction_db != 0.0).then_some(NoiseGate {
+            threshold_db: gate_threshold_db,
+            reduction_db: gate_reduction_db,
+        }),
+    };
+
+    match filter_wav_file(&input, &output, config) {
+        Ok(()) => CowcowStatus::Ok,
+        Err(e) => ffi::fail_with(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_chain() {
+        let rate = 16000;
+        let tone = |freq: f32, amplitude: f32, len: usize| -> Vec<f32> {
+            (0..len)
+                .map(|i| amplitude * (2.0 * PI * freq * i as f32 / rate as f32).sin())
+                .collect()
+        };
+        let rms = |samples: &[f32]| {
+            (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+        };
+
+        // The high-pass removes wind-like rumble and keeps voice frequencies
+        let config = FilterConfig {
+            high_pass_hz: Some(DEFAULT_HIGH_PASS_HZ),
+            noise_gate: None,
+        };
+        let mut chain = FilterChain::new(rate, 1, config);
+        let rumble = chain.process(&tone(20.0, 0.5, 16000));
+        assert!(rms(&rumble[8000..]) < 0.05 * 0.5);
+        let mut chain = FilterChain::new(rate, 1, config);
+        let voice = chain.process(&tone(300.0, 0.5, 16000));
+        assert!(rms(&voice[8000..]) > 0.9 * rms(&tone(300.0, 0.5, 8000)));
+
+        // The gate passes a loud tone unchanged (and in step) but turns down
+        // steady hiss, and the output is as long as the input
+        let hiss: Vec<f32> = (0..32000u32)
+            .map(|i| 0.01 * ((i.wrapping_mul(2_654_435_761) >> 16) as f32 / 32768.0 - 1.0))
+            .collect();
+        let mut input = hiss.clone();
+        let loud = tone(1000.0, 0.5, 8000);
+        for (x, y) in input[16000..24000].iter_mut().zip(&loud) {
+            *x += y;
+        }
+
+        let config = FilterConfig {
+            high_pass_hz: None,
+            noise_gate: Some(NoiseGate::default()),
+        };
+        let mut chain = FilterChain::new(rate, 2, config);
+        let stereo: Vec<f32> = input.iter().flat_map(|&x| [x, x]).collect();
+        let mut output = chain.process(&stereo[..12345 * 2]);
+        output.extend(chain.process(&stereo[12345 * 2..]));
+        output.extend(chain.flush());
+        assert_eq!(output.len(), stereo.len());
+
+        let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+        let reduction = rms(&left[8000..14000]) / rms(&hiss[8000..14000]);
+        assert!(reduction < 0.5, "hiss reduced to {reduction}");
+        let kept = rms(&left[18000..22000]) / rms(&input[18000..22000]);
+        assert!((kept - 1.0).abs() < 0.05, "tone kept at {kept}");
+        let error = rms(&left[18000..22000]
+            .iter()
+            .zip(&input[18000..22000])
+            .map(|(a, b)| a - b)
+            .collect::<Vec<_>>());
+        assert!(error < 0.05, "tone misaligned by {error}");
+    }
+}
@@ -0,0 +1,228 @@
+//! Acoustic fingerprints for spotting duplicate recordings
+//!
+//! A chromaprint-style sub-fingerprint is computed for every hop of audio:
+//! 32 bits, each the sign of how the energy difference between two adjacent
+//! bands changed since the previous frame (Haitsma & Kalker). The bits depend
+//! on the spectral shape rather than the level, so re-encoded, resampled or
+//! slightly noisier copies of a recording keep nearly the same fingerprint.
+
+use std::f32::consts::PI;
+#[cfg(feature = "wav")]
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::dsp::power_spectrum;
+use crate::resample::Resampler;
+use crate::AudioError;
+
+/// Audio is converted to this rate before fingerprinting
+const FINGERPRINT_RATE: u32 = 8000;
+
+/// FFT frame length (128 ms)
+const FRAME_LEN: usize = 1024;
+
+/// Hop between sub-fingerprints (16 ms)
+const HOP: usize = 128;
+
+/// 33 bands give 32 adjacent band differences, one per bit
+const BANDS: usize = 33;
+
+const MIN_FREQ: f32 = 300.0;
+const MAX_FREQ: f32 = 2000.0;
+
+/// Largest misalignment tried when comparing (~2 s)
+const MAX_OFFSET: usize = 125;
+
+/// Sequence of 32-bit sub-fingerprints, one per 16 ms of audio
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fingerprint(pub Vec<u32>);
+
+impl Fingerprint {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Hex encoding, 8 characters per sub-fingerprint
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|code| format!("{code:08x}")).collect()
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if !hex.len().is_multiple_of(8) || !hex.is_ascii() {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(8)
+            .map(|i| u32::from_str_radix(&hex[i..i + 8], 16).ok())
+            .collect::<Option<Vec<_>>>()
+            .map(Self)
+    }
+
+    /// Fraction of matching bits at the best alignment, from 0.0 to 1.0
+    ///
+    /// Unrelated audio scores around 0.5; copies of the same recording score
+    /// close to 1.0. Alignments are tried up to [`MAX_OFFSET`] frames apart
+    /// and must overlap by at least half the shorter fingerprint.
+    pub fn similarity(&self, other: &Self) -> f32 {
+        let (a, b) = (&self.0, &other.0);
+        let min_overlap = (a.len().min(b.len()) / 2).max(1);
+
+        let mut best = 0.0f32;
+        for offset in -(MAX_OFFSET as isize)..=MAX_OFFSET as isize {
+            let (a_start, b_start) = if offset >= 0 {
+                (offset as usize, 0)
+            } else {
+                (0, offset.unsigned_abs())
+            };
+            if a_start >= a.len() || b_start >= b.len() {
+                continue;
+            }
+            let overlap = (a.len() - a_start).min(b.len() - b_start);
+            if overlap < min_overlap {
+                continue;
+            }
+
+            let errors: u32 = a[a_start..a_start + overlap]
+                .iter()
+                .zip(&b[b_start..])
+                .map(|(x, y)| (x ^ y).count_ones())
+                .sum();
+            best = best.max(1.0 - errors as f32 / (32 * overlap) as f32);
+        }
+        best
+    }
+}
+
+/// Fingerprint interleaved samples
+pub fn fingerprint(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<Fingerprint, AudioError> {
+    let channels = channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+
+    let mut resampler = Resampler::new(sample_rate, FINGERPRINT_RATE, 1)?;
+    let mut audio = resampler.process(&mono)?;
+    audio.extend(resampler.flush()?);
+
+    let window: Vec<f32> = (0..FRAME_LEN)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / FRAME_LEN as f32).cos())
+        .collect();
+    let edges = band_edges();
+
+    let mut codes = Vec::new();
+    let mut previous: Option<[f32; BANDS]> = None;
+    let mut frame = vec![0.0f32; FRAME_LEN];
+    for start in (0..audio.len().saturating_sub(FRAME_LEN - 1)).step_by(HOP) {
+        for ((dst, &src), &w) in frame.iter_mut().zip(&audio[start..]).zip(&window) {
+            *dst = src * w;
+        }
+        let spectrum = power_spectrum(&frame);
+
+        let mut energies = [0.0f32; BANDS];
+        for (band, energy) in energies.iter_mut().enumerate() {
+            *energy = spectrum[edges[band]..edges[band + 1]].iter().sum();
+        }
+
+        if let Some(prev) = previous {
+            let mut code = 0u32;
+            for bit in 0..BANDS - 1 {
+                let delta = (energies[bit] - energies[bit + 1]) - (prev[bit] - prev[bit + 1]);
+                if delta > 0.0 {
+                    code |= 1 << bit;
+                }
+            }
+            codes.push(code);
+        }
+        previous = Some(energies);
+    }
+
+    Ok(Fingerprint(codes))
+}
+
+/// Fingerprint a WAV file (or any other file [`crate::decode`] reads)
+#[cfg(feature = "wav")]
+pub fn fingerprint_wav_file<P: AsRef<Path>>(path: P) -> Result<Fingerprint> {
+    if !crate::decode::is_wav(path.as_ref()) {
+        let audio = crate::decode::decode_file(path)?;
+        return Ok(fingerprint(
+            &audio.samples,
+            audio.sample_rate,
+            audio.channels,
+        )?);
+    }
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = crate::decode::read_wav_samples(reader)?;
+    Ok(fingerprint(&samples, spec.sample_rate, spec.channels)?)
+}
+
+/// FFT bin boundaries of the log-spaced bands
+fn band_edges() -> [usize; BANDS + 1] {
+    let mut edges = [0usize; BANDS + 1];
+    let ratio = (MAX_FREQ / MIN_FREQ).powf(1.0 / BANDS as f32);
+    for (i, edge) in edges.iter_mut().enumerate() {
+        let freq = MIN_FREQ * ratio.powi(i as i32);
+        *edge = (freq * FRAME_LEN as f32 / FINGERPRINT_RATE as f32).round() as usize;
+    }
+    // Keep every band at least one bin wide
+    for i in 1..edges.len() {
+        edges[i] = edges[i].max(edges[i - 1] + 1);
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo-random value in [-1, 1)
+    fn noise(i: usize, seed: usize) -> f32 {
+        ((i * 7919 + seed * 104729) % 1000) as f32 / 500.0 - 1.0
+    }
+
+    /// Deterministic speech-like test signal: gliding harmonics with a
+    /// syllable-rate envelope over a room noise bed
+    fn signal(seed: f32, secs: f32, rate: u32) -> Vec<f32> {
+        (0..(secs * rate as f32) as usize)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                let f0 = 150.0 + 60.0 * (2.0 * PI * (0.7 + seed) * t).sin();
+                let envelope = 0.5 + 0.5 * (2.0 * PI * (3.0 + seed) * t).sin();
+                let voice: f32 = (1..8)
+                    .map(|h| (2.0 * PI * f0 * h as f32 * t + seed * h as f32).sin() / h as f32)
+                    .sum();
+                0.2 * envelope * voice + 0.01 * noise(i, 1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprint_similarity() {
+        let original = signal(0.0, 5.0, 16000);
+        let a = fingerprint(&original, 16000, 1).unwrap();
+        assert!(!a.is_empty());
+        assert_eq!(Fingerprint::from_hex(&a.to_hex()), Some(a.clone()));
+
+        // Same audio at another level, with a little noise and trimmed start
+        let copy: Vec<f32> = original[8000..]
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| 0.7 * s + 0.002 * noise(i, 2))
+            .collect();
+        let b = fingerprint(&copy, 16000, 1).unwrap();
+        assert!(a.similarity(&b) > 0.85, "copy: {}", a.similarity(&b));
+
+        let other = fingerprint(&signal(0.37, 5.0, 16000), 16000, 1).unwrap();
+        assert!(
+            a.similarity(&other) < 0.65,
+            "other: {}",
+            a.similarity(&other)
+        );
+    }
+}
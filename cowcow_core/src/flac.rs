@@ -0,0 +1,432 @@
+//! Lossless FLAC encoding of 16-bit recordings
+//!
+//! A small encoder in the spirit of `flac -1`: fixed-size blocks, the best
+//! of the fixed linear predictors (orders 0 to 4) per channel and a single
+//! Rice partition. Speech typically shrinks to half the size of the WAV.
+//! Decoding goes through [`crate::decode`].
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// File extension of FLAC recordings
+pub const FLAC_EXTENSION: &str = "flac";
+
+/// Samples per channel in each frame
+const BLOCK_SIZE: usize = 4096;
+
+const BITS_PER_SAMPLE: u32 = 16;
+
+/// Largest Rice parameter of the 4-bit residual coding method
+const MAX_RICE_PARAM: u32 = 14;
+
+/// Byte offset of the STREAMINFO block's minimum frame size field
+const STREAMINFO_FRAME_SIZE_OFFSET: u64 = 4 + 4 + 4;
+
+/// Whether a recording is stored as FLAC
+pub fn is_flac(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(FLAC_EXTENSION))
+}
+
+/// Streams interleaved 16-bit samples into a FLAC file
+///
+/// Mirrors `hound::WavWriter`: write samples one at a time, then call
+/// [`FlacWriter::finalize`] to flush the last block and fill in the stream
+/// length.
+pub struct FlacWriter<W: Write + Seek> {
+    out: W,
+    sample_rate: u32,
+    channels: u16,
+    /// Interleaved samples of the block being collected
+    block: Vec<i32>,
+    frame_number: u64,
+    total_frames: u64,
+    min_frame_bytes: u32,
+    max_frame_bytes: u32,
+}
+
+impl FlacWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), sample_rate, channels)
+    }
+}
+
+impl<W: Write + Seek> FlacWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        if sample_rate == 0 || sample_rate >= 1 << 20 {
+            return Err(invalid_input(format!(
+                "Sample rate {sample_rate} Hz cannot be stored in FLAC"
+            )));
+        }
+        if !(1..=8).contains(&channels) {
+            return Err(invalid_input(format!(
+                "FLAC supports 1 to 8 channels, not {channels}"
+            )));
+        }
+
+        out.write_all(b"fLaC")?;
+        // Last metadata block, type STREAMINFO, 34 bytes long
+        out.write_all(&[0x80, 0, 0, 34])?;
+        let mut info = BitWriter::default();
+        info.write(BLOCK_SIZE as u64, 16);
+        info.write(BLOCK_SIZE as u64, 16);
+        info.write(0, 24); // minimum frame size, filled in by finalize
+        info.write(0, 24); // maximum frame size
+        info.write(sample_rate as u64, 20);
+        info.write(channels as u64 - 1, 3);
+        info.write(BITS_PER_SAMPLE as u64 - 1, 5);
+        info.write(0, 36); // total samples per channel
+        out.write_all(&info.finish())?;
+        // MD5 of the audio; all zeros means not computed
+        out.write_all(&[0; 16])?;
+
+        Ok(Self {
+            out,
+            sample_rate,
+            channels,
+            block: Vec::with_capacity(BLOCK_SIZE * channels as usize),
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_bytes: u32::MAX,
+            max_frame_bytes: 0,
+        })
+    }
+
+    /// Write the next interleaved sample
+    pub fn write_sample(&mut self, sample: i16) -> io::Result<()> {
+        self.block.push(sample as i32);
+        if self.block.len() == BLOCK_SIZE * self.channels as usize {
+            self.write_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Flush the remaining samples and complete the stream header
+    pub fn finalize(mut self) -> io::Result<()> {
+        // Drop a trailing partial frame, as hound does
+        self.block
+            .truncate(self.block.len() / self.channels as usize * self.channels as usize);
+        if !self.block.is_empty() {
+            self.write_frame()?;
+        }
+
+        let mut info = BitWriter::default();
+        if self.total_frames > 0 {
+            info.write(self.min_frame_bytes as u64, 24);
+            info.write(self.max_frame_bytes as u64, 24);
+        } else {
+            info.write(0, 48);
+        }
+        info.write(self.sample_rate as u64, 20);
+        info.write(self.channels as u64 - 1, 3);
+        info.write(BITS_PER_SAMPLE as u64 - 1, 5);
+        info.write(self.total_frames, 36);
+        self.out
+            .seek(SeekFrom::Start(STREAMINFO_FRAME_SIZE_OFFSET))?;
+        self.out.write_all(&info.finish())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()
+    }
+
+    fn write_frame(&mut self) -> io::Result<()> {
+        let channels = self.channels as usize;
+        let block_len = self.block.len() / channels;
+
+        let mut frame = BitWriter::default();
+        frame.write(0b1111_1111_1111_1000, 16); // sync code, fixed block size
+        frame.write(0b0111, 4); // block size stored after the frame number
+        frame.write(0b0000, 4); // sample rate from STREAMINFO
+        frame.write(channels as u64 - 1, 4); // independent channels
+        frame.write(0b100, 3); // 16 bits per sample
+        frame.write(0, 1);
+        frame.write_utf8(self.frame_number);
+        frame.write(block_len as u64 - 1, 16);
+        let crc = crc8(&frame.bytes);
+        frame.write(crc as u64, 8);
+
+        let mut channel = Vec::with_capacity(block_len);
+        for index in 0..channels {
+            channel.clear();
+            channel.extend(self.block.iter().skip(index).step_by(channels));
+            write_subframe(&mut frame, &channel);
+        }
+
+        let mut bytes = frame.finish();
+        let crc = crc16(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        self.out.write_all(&bytes)?;
+
+        self.min_frame_bytes = self.min_frame_bytes.min(bytes.len() as u32);
+        self.max_frame_bytes = self.max_frame_bytes.max(bytes.len() as u32);
+        self.frame_number += 1;
+        self.total_frames += block_len as u64;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+/// Re-encode a 16-bit WAV file as FLAC
+pub fn encode_wav_file(wav_path: &Path, flac_path: &Path) -> Result<()> {
+    let reader = hound::WavReader::open(wav_path)
+        .with_context(|| format!("Failed to open {}", wav_path.display()))?;
+    let spec = reader.spec();
+    if spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+        anyhow::bail!(
+            "Only 16-bit WAV files can be encoded as FLAC: {}",
+            wav_path.display()
+        );
+    }
+
+    let mut writer = FlacWriter::create(flac_path, spec.sample_rate, spec.channels)
+        .with_context(|| format!("Failed to create {}", flac_path.display()))?;
+    for sample in reader.into_samples::<i16>() {
+        writer.write_sample(sample?)?;
+    }
+    writer
+        .finalize()
+        .with_context(|| format!("Failed to write {}", flac_path.display()))
+}
+
+/// Encode one channel of a block as the smallest of a constant, fixed
+/// predictor or verbatim subframe
+fn write_subframe(frame: &mut BitWriter, samples: &[i32]) {
+    if samples.iter().all(|&s| s == samples[0]) {
+        frame.write(0x00, 8); // constant subframe
+        frame.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    let best = (0..=4usize)
+        .filter(|&order| order < samples.len())
+        .map(|order| {
+            let residuals = fixed_residuals(samples, order);
+            let (param, bits) = best_rice_param(&residuals);
+            let total = order as u64 * BITS_PER_SAMPLE as u64 + 2 + 4 + 4 + bits;
+            (order, residuals, param, total)
+        })
+        .min_by_key(|(_, _, _, total)| *total);
+
+    match best {
+        Some((order, residuals, param, total)) if total < verbatim_bits => {
+            frame.write(0x10 | (order as u64) << 1, 8); // fixed subframe of `order`
+            for &warmup in &samples[..order] {
+                frame.write_signed(warmup, BITS_PER_SAMPLE);
+            }
+            frame.write(0b00, 2); // Rice coding with 4-bit parameters
+            frame.write(0, 4); // one partition
+            frame.write(param as u64, 4);
+            for &residual in &residuals {
+                frame.write_rice(residual, param);
+            }
+        }
+        _ => {
+            frame.write(0x02, 8); // verbatim subframe
+            for &sample in samples {
+                frame.write_signed(sample, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+/// Prediction errors of the fixed polynomial predictor of `order`
+fn fixed_residuals(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let s = |back: usize| samples[i - back];
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+/// Rice parameter giving the fewest bits for the residuals, with that count
+fn best_rice_param(residuals: &[i32]) -> (u32, u64) {
+    let folded: Vec<u64> = residuals.iter().map(|&r| fold(r) as u64).collect();
+    (0..=MAX_RICE_PARAM)
+        .map(|param| {
+            let bits = folded
+                .iter()
+                .map(|&u| (u >> param) + 1 + param as u64)
+                .sum();
+            (param, bits)
+        })
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap_or((0, 0))
+}
+
+/// Interleave signed values into unsigned ones: 0, -1, 1, -2, ...
+fn fold(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Most-significant-bit-first bit packing
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Append the low `bits` bits of `value` (at most 57 at a time)
+    fn write(&mut self, value: u64, bits: u32) {
+        if bits > 32 {
+            self.write(value >> 32, bits - 32);
+            self.write(value & 0xFFFF_FFFF, 32);
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1u64 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write(value as u32 as u64, bits);
+    }
+
+    fn write_rice(&mut self, value: i32, param: u32) {
+        let folded = fold(value);
+        let mut quotient = folded >> param;
+        while quotient >= 32 {
+            self.write(0, 32);
+            quotient -= 32;
+        }
+        self.write(1, quotient + 1);
+        self.write(folded as u64, param);
+    }
+
+    /// Frame number in FLAC's extended UTF-8 style coding
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        let len = match value {
+            0..0x800 => 2,
+            0x800..0x1_0000 => 3,
+            0x1_0000..0x20_0000 => 4,
+            0x20_0000..0x400_0000 => 5,
+            0x400_0000..0x8000_0000 => 6,
+            _ => 7,
+        };
+        let lead = (0xFF00u64 >> len) & 0xFF;
+        self.write(lead | (value >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+        }
+    }
+
+    /// The packed bytes, zero-padded to a whole byte
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+        self.bytes
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_file;
+
+    #[test]
+    fn test_flac_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cowcow-flac-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav_path = dir.join("speech.wav");
+        let flac_path = dir.join("speech.flac");
+
+        // A second of stereo: a tone, silence and a full-scale square wave,
+        // ending mid-block
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&wav_path, spec).unwrap();
+        let mut expected = Vec::new();
+        for i in 0..16000 {
+            let t = i as f32 / 16000.0;
+            let left = if i < 8000 {
+                (12000.0 * (2.0 * std::f32::consts::PI * 220.0 * t).sin()) as i16
+            } else {
+                0
+            };
+            let right = if (i / 40) % 2 == 0 {
+                i16::MAX
+            } else {
+                i16::MIN
+            };
+            for sample in [left, right] {
+                writer.write_sample(sample).unwrap();
+                expected.push(sample);
+            }
+        }
+        writer.finalize().unwrap();
+
+        encode_wav_file(&wav_path, &flac_path).unwrap();
+        assert!(is_flac(&flac_path));
+        assert!(
+            std::fs::metadata(&flac_path).unwrap().len()
+                < std::fs::metadata(&wav_path).unwrap().len() / 2
+        );
+
+        let decoded = decode_file(&flac_path).unwrap();
+        assert_eq!((decoded.sample_rate, decoded.channels), (16000, 2));
+        let samples: Vec<i16> = decoded
+            .samples
+            .iter()
+            .map(|&s| (s * 32768.0).round() as i16)
+            .collect();
+        assert_eq!(samples, expected);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,111 @@
+//! Detection of dropped buffers and discontinuity clicks
+
+/// Envelope level above which the signal counts as live when a run starts
+const LIVE_LEVEL: f32 = 0.01;
+
+/// Runs of held samples at or above this level are left to clipping detection
+const MAX_HELD_LEVEL: f32 = 0.1;
+
+/// Smallest sample-to-sample jump that can count as a click
+const MIN_CLICK_JUMP: f32 = 0.2;
+
+/// How many times the chunk's mean absolute slope a jump must exceed to count as a click
+const CLICK_SLOPE_RATIO: f32 = 6.0;
+
+/// Dropouts and clicks found in one chunk
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GlitchReport {
+    /// Runs of held samples (usually zeros) that cut into a live signal
+    pub dropouts: u32,
+    /// Samples whose jump from the previous sample is a discontinuity
+    pub clicks: usize,
+}
+
+/// Streaming detector for capture glitches
+///
+/// A dropout is a run of at least `min_run` identical samples entered from a
+/// live signal, which is what an underrun or a dropped buffer looks like once
+/// it has been zero-filled or held. Runs that continue across chunks are only
+/// counted once. Quiet passages that settle on digital silence are ignored.
+#[derive(Debug, Clone)]
+pub struct GlitchDetector {
+    min_run: usize,
+    envelope_decay: f32,
+    envelope: f32,
+    prev: Option<f32>,
+    run_len: usize,
+    run_entry_level: f32,
+    run_counted: bool,
+}
+
+impl GlitchDetector {
+    pub fn new(sample_rate: u32, min_run_ms: u32) -> Self {
+        // Envelope falls by ~60 dB over a millisecond so silence is reached quickly
+        let envelope_decay = 0.001f32.powf(1000.0 / sample_rate as f32);
+        Self {
+            min_run: (sample_rate * min_run_ms / 1000).max(2) as usize,
+            envelope_decay,
+            envelope: 0.0,
+            prev: None,
+            run_len: 0,
+            run_entry_level: 0.0,
+            run_counted: false,
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> GlitchReport {
+        let mut report = GlitchReport::default();
+
+        let mean_slope = {
+            let mut prev = self.prev;
+            let mut total = 0.0;
+            for &x in samples {
+                total += prev.map_or(0.0, |p| (x - p).abs());
+                prev = Some(x);
+            }
+            total / samples.len().max(1) as f32
+        };
+        let click_jump = MIN_CLICK_JUMP.max(mean_slope * CLICK_SLOPE_RATIO);
+
+        for &x in samples {
+            match self.prev {
+                Some(prev) if x == prev && x.abs() < MAX_HELD_LEVEL => {
+                    if self.run_len == 0 {
+                        // The first repeated sample is the second sample of the run
+                        self.run_len = 1;
+                        self.run_entry_level = self.envelope;
+                    }
+                    self.run_len += 1;
+                    if !self.run_counted
+                        && self.run_len >= self.min_run
+                        && self.run_entry_level >= LIVE_LEVEL
+                    {
+                        report.dropouts += 1;
+                        self.run_counted = true;
+                    }
+                }
+                Some(prev) => {
+                    self.run_len = 0;
+                    self.run_counted = false;
+                    if (x - prev).abs() >= click_jump {
+                        report.clicks += 1;
+                    }
+                }
+                None => {}
+            }
+
+            self.envelope = x.abs().max(self.envelope * self.envelope_decay);
+            self.prev = Some(x);
+        }
+
+        report
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.prev = None;
+        self.run_len = 0;
+        self.run_entry_level = 0.0;
+        self.run_counted = false;
+    }
+}
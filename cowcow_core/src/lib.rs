@@ -1,12 +1,77 @@
+//! Audio quality control for Cowcow
+//!
+//! Without features the crate holds only the metric types and the logic
+//! working on them ([`QcMetrics`], [`policy`], [`timeline`],
+//! [`prompt_analysis`], [`outliers`], [`silence`]), so a server that only
+//! reads stored metrics does not build an audio stack. The rest is opt-in:
+//!
+//! - `dsp`: filters, resampling, pitch, reverb and time stretching
+//! - `vad`: voice activity detection and the streaming [`AudioProcessor`]
+//! - `wav`: reading and writing audio files, and analyzing them
+//! - `ffi`: the C API declared in `include/cowcow.h`
+
+#[cfg(feature = "vad")]
+use std::borrow::Cow;
+#[cfg(feature = "ffi")]
 use std::ffi::c_char;
 
+#[cfg(feature = "vad")]
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+#[cfg(feature = "vad")]
 use tracing::error;
 
+pub mod asr;
+#[cfg(feature = "wav")]
+pub mod decode;
+#[cfg(feature = "dsp")]
+pub mod dsp;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "dsp")]
+pub mod filter;
+#[cfg(feature = "dsp")]
+pub mod fingerprint;
+#[cfg(feature = "wav")]
+pub mod flac;
+#[cfg(feature = "dsp")]
+pub mod glitch;
+#[cfg(feature = "dsp")]
+pub mod normalize;
+#[cfg(feature = "wav")]
+pub mod opus;
+pub mod outliers;
+#[cfg(feature = "dsp")]
+pub mod pitch;
+pub mod policy;
+pub mod prompt_analysis;
+#[cfg(feature = "dsp")]
+pub mod resample;
+#[cfg(feature = "dsp")]
+pub mod reverb;
+pub mod silence;
+#[cfg(feature = "dsp")]
+pub mod stretch;
+pub mod timeline;
+#[cfg(feature = "wav")]
+pub mod trim;
+#[cfg(feature = "vad")]
+pub mod vad;
+#[cfg(feature = "wav")]
+pub mod wav;
+pub mod word_timing;
+
+#[cfg(feature = "ffi")]
+use ffi::CowcowStatus;
+#[cfg(feature = "vad")]
+use timeline::QcTimeline;
+#[cfg(feature = "vad")]
+use vad::{VadBackend, VadMode, VoiceDetector};
+
 /// Quality control metrics for audio recordings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 #[repr(C)]
 pub struct QcMetrics {
     /// Signal-to-noise ratio in decibels
@@ -15,67 +80,576 @@ pub struct QcMetrics {
     pub clipping_pct: f32,
     /// Ratio of frames classified as speech by VAD
     pub vad_ratio: f32,
+    /// Mean sample value (DC offset) as a fraction of full scale
+    pub dc_offset: f32,
+    /// Energy below 50 Hz relative to total energy, in decibels
+    pub rumble_db: f32,
+    /// Mains hum (50 or 60 Hz and harmonics) relative to total energy, in decibels
+    pub hum_db: f32,
+    /// Number of dropped or held buffers (runs of repeated samples cutting into the signal)
+    pub dropout_count: u32,
+    /// Percentage of samples that are discontinuity clicks
+    pub glitch_pct: f32,
+    /// Length of the analyzed audio in seconds
+    pub duration_secs: f32,
+    /// Seconds of audio classified as speech by VAD
+    pub speech_secs: f32,
+    /// Silence before the first speech frame, in seconds
+    pub leading_silence_secs: f32,
+    /// Silence after the last speech frame, in seconds
+    pub trailing_silence_secs: f32,
+    /// Estimated reverberation time (RT60) in seconds, 0 when no usable
+    /// speech offset was found
+    pub reverb_rt60_secs: f32,
+    /// Lowest fundamental frequency (pitch) in Hz, 0 when nothing was voiced
+    pub f0_min_hz: f32,
+    /// Highest fundamental frequency in Hz, 0 when nothing was voiced
+    pub f0_max_hz: f32,
+    /// Mean fundamental frequency over voiced frames in Hz
+    pub f0_mean_hz: f32,
+    /// Percentage of frames with a detectable pitch; well below `vad_ratio`
+    /// suggests whispering
+    pub voiced_ratio: f32,
+}
+
+impl QcMetrics {
+    /// Combine per-chunk metrics into file-level metrics
+    ///
+    /// Level metrics are averaged, durations and counts are summed, and
+    /// leading/trailing silence extends across chunks without speech. The
+    /// reverberation estimate is averaged over chunks that produced one, and
+    /// pitch statistics over chunks with voiced frames.
+    pub fn average(chunks: &[QcMetrics]) -> QcMetrics {
+        chunks
+            .iter()
+            .fold(QcAccumulator::default(), |mut acc, chunk| {
+                acc.push(chunk);
+                acc
+            })
+            .finish()
+    }
+}
+
+/// Streaming form of [`QcMetrics::average`]
+///
+/// Keeps running totals instead of the chunks themselves, so file-level
+/// metrics of long recordings take constant memory.
+#[derive(Debug, Clone, Default)]
+pub struct QcAccumulator {
+    chunks: usize,
+    /// Running sums of averaged fields, and the summed fields themselves
+    totals: QcMetrics,
+    speech_seen: bool,
+    reverb_sum: f32,
+    reverb_count: usize,
+    f0_min_hz: Option<f32>,
+    f0_max_hz: Option<f32>,
+    f0_weighted_sum: f32,
+    f0_weight: f32,
+}
+
+impl QcAccumulator {
+    /// Add the metrics of the next chunk
+    pub fn push(&mut self, chunk: &QcMetrics) {
+        let totals = &mut self.totals;
+        self.chunks += 1;
+        totals.snr_db += chunk.snr_db;
+        totals.clipping_pct += chunk.clipping_pct;
+        totals.vad_ratio += chunk.vad_ratio;
+        totals.dc_offset += chunk.dc_offset;
+        totals.rumble_db += chunk.rumble_db;
+        totals.hum_db += chunk.hum_db;
+        totals.dropout_count += chunk.dropout_count;
+        totals.glitch_pct += chunk.glitch_pct;
+        totals.duration_secs += chunk.duration_secs;
+        totals.speech_secs += chunk.speech_secs;
+        totals.voiced_ratio += chunk.voiced_ratio;
+
+        // Leading silence grows until the first chunk with speech; trailing
+        // silence restarts at every chunk with speech
+        if !self.speech_seen {
+            totals.leading_silence_secs += chunk.leading_silence_secs;
+        }
+        if chunk.speech_secs > 0.0 {
+            self.speech_seen = true;
+            totals.trailing_silence_secs = chunk.trailing_silence_secs;
+        } else {
+            totals.trailing_silence_secs += chunk.trailing_silence_secs;
+        }
+
+        if chunk.reverb_rt60_secs > 0.0 {
+            self.reverb_sum += chunk.reverb_rt60_secs;
+            self.reverb_count += 1;
+        }
+
+        if chunk.voiced_ratio > 0.0 {
+            self.f0_min_hz = Some(
+                self.f0_min_hz
+                    .map_or(chunk.f0_min_hz, |f0| f0.min(chunk.f0_min_hz)),
+            );
+            self.f0_max_hz = Some(
+                self.f0_max_hz
+                    .map_or(chunk.f0_max_hz, |f0| f0.max(chunk.f0_max_hz)),
+            );
+            // Weighted by the voiced time in each chunk
+            let weight = chunk.voiced_ratio * chunk.duration_secs;
+            self.f0_weighted_sum += chunk.f0_mean_hz * weight;
+            self.f0_weight += weight;
+        }
+    }
+
+    /// Number of chunks added so far
+    pub fn len(&self) -> usize {
+        self.chunks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks == 0
+    }
+
+    /// File-level metrics of the chunks added so far
+    pub fn finish(&self) -> QcMetrics {
+        if self.chunks == 0 {
+            return QcMetrics::default();
+        }
+
+        let totals = &self.totals;
+        let n = self.chunks as f32;
+        QcMetrics {
+            snr_db: totals.snr_db / n,
+            clipping_pct: totals.clipping_pct / n,
+            vad_ratio: totals.vad_ratio / n,
+            dc_offset: totals.dc_offset / n,
+            rumble_db: totals.rumble_db / n,
+            hum_db: totals.hum_db / n,
+            dropout_count: totals.dropout_count,
+            glitch_pct: totals.glitch_pct / n,
+            duration_secs: totals.duration_secs,
+            speech_secs: totals.speech_secs,
+            leading_silence_secs: totals.leading_silence_secs,
+            trailing_silence_secs: totals.trailing_silence_secs,
+            reverb_rt60_secs: if self.reverb_count > 0 {
+                self.reverb_sum / self.reverb_count as f32
+            } else {
+                0.0
+            },
+            f0_min_hz: self.f0_min_hz.unwrap_or(0.0),
+            f0_max_hz: self.f0_max_hz.unwrap_or(0.0),
+            f0_mean_hz: if self.f0_weight > 0.0 {
+                self.f0_weighted_sum / self.f0_weight
+            } else {
+                0.0
+            },
+            voiced_ratio: totals.voiced_ratio / n,
+        }
+    }
+}
+
+/// Level statistics for a single input channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMetrics {
+    /// RMS level in dBFS
+    pub rms_db: f32,
+    /// Largest absolute sample value
+    pub peak: f32,
+    /// Percentage of samples at or above the clip threshold
+    pub clipping_pct: f32,
+    /// Mean sample value as a fraction of full scale
+    pub dc_offset: f32,
+}
+
+/// How interleaved multi-channel input is reduced to mono for analysis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownmixStrategy {
+    /// First channel only
+    Left,
+    /// Second channel only (first channel for mono input)
+    Right,
+    /// Mean of all channels
+    #[default]
+    Average,
+}
+
+/// Running per-channel sums since the last reset
+#[cfg(feature = "vad")]
+#[derive(Debug, Clone, Default)]
+struct ChannelAccumulator {
+    sum: f64,
+    sum_squares: f64,
+    peak: f32,
+    clipped: u64,
+    samples: u64,
+}
+
+#[cfg(feature = "vad")]
+impl ChannelAccumulator {
+    fn metrics(&self) -> ChannelMetrics {
+        if self.samples == 0 {
+            return ChannelMetrics::default();
+        }
+        let n = self.samples as f64;
+        ChannelMetrics {
+            rms_db: (20.0 * (self.sum_squares / n).sqrt().max(1e-10).log10()) as f32,
+            peak: self.peak,
+            clipping_pct: (self.clipped as f64 / n * 100.0) as f32,
+            dc_offset: (self.sum / n) as f32,
+        }
+    }
+}
+
+/// Speech frames found by VAD in one chunk
+#[cfg(feature = "vad")]
+#[derive(Debug, Default)]
+struct VadFrames {
+    speech: usize,
+    total: usize,
+    first_speech: Option<usize>,
+    last_speech: Option<usize>,
 }
 
+/// Upper edge of the band counted as low-frequency rumble
+pub const RUMBLE_CUTOFF_HZ: f32 = 50.0;
+
+/// Mains frequencies checked for hum
+pub const MAINS_FREQUENCIES_HZ: [f32; 2] = [50.0, 60.0];
+
+/// Number of harmonics (including the fundamental) summed into the hum level
+pub const HUM_HARMONICS: usize = 3;
+
 /// Audio processing errors
 #[derive(Debug, Error)]
 pub enum AudioError {
     #[error("Failed to open audio file: {0}")]
     FileOpen(#[from] std::io::Error),
+    #[cfg(feature = "wav")]
     #[error("Invalid WAV format: {0}")]
     WavFormat(#[from] hound::Error),
+    #[error("Failed to decode audio: {0}")]
+    Decode(String),
     #[error("VAD processing failed: {0}")]
     VadError(String),
+    #[error("Resampling failed: {0}")]
+    Resample(String),
+    #[error("Unsupported playback speed: {0} (expected 0.5 to 2.0)")]
+    Speed(f32),
+    #[error("Unsupported sample rate: {0} (resample to one of {SUPPORTED_SAMPLE_RATES:?} first)")]
+    SampleRate(u32),
+    #[error("At least one audio channel is required")]
+    NoChannels,
+}
+
+/// Sample rates the VAD and QC pipeline can run at directly; other rates
+/// must be converted with [`resample::Resampler`] first
+pub const SUPPORTED_SAMPLE_RATES: [u32; 4] = [8000, 16000, 32000, 48000];
+
+/// Rate used when a file's own rate is not directly supported
+pub const DEFAULT_ANALYSIS_RATE: u32 = 16000;
+
+/// Largest 16-bit sample after normalization to [-1.0, 1.0)
+pub const I16_FULL_SCALE: f32 = 32767.0 / 32768.0;
+
+/// How the SNR of a chunk is estimated
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnrEstimator {
+    /// Compare the chunk level against a fixed noise floor
+    FixedFloor { noise_floor_db: f32 },
+    /// Track the quietest 30ms frame seen since the last reset and use it as
+    /// the noise floor
+    Adaptive,
+}
+
+impl Default for SnrEstimator {
+    fn default() -> Self {
+        SnrEstimator::FixedFloor {
+            noise_floor_db: -60.0, // Typical noise floor in dB
+        }
+    }
+}
+
+/// Configuration for an [`AudioProcessor`]
+///
+/// Cheap to clone, so embedders can keep one around and build fresh
+/// processors from it.
+#[cfg(feature = "vad")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessorConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub vad_backend: VadBackend,
+    pub vad_mode: VadMode,
+    /// WebRTC VAD frame length in milliseconds (10, 20 or 30)
+    pub vad_frame_ms: u32,
+    pub snr_estimator: SnrEstimator,
+    /// Absolute level at or above which a sample counts as clipped
+    pub clip_threshold: f32,
+    /// Minimum run of identical samples at the chunk peak that counts as a
+    /// clipped plateau, catching clipping that was rescaled below full scale
+    pub clip_min_run: usize,
+    /// Minimum length in milliseconds of a run of held samples that counts
+    /// as a dropped buffer
+    pub dropout_min_ms: u32,
+    /// How interleaved multi-channel input is reduced to mono before QC
+    pub downmix: DownmixStrategy,
+}
+
+#[cfg(feature = "vad")]
+impl ProcessorConfig {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            vad_backend: VadBackend::WebRtc,
+            vad_mode: VadMode::default(),
+            vad_frame_ms: 30,
+            snr_estimator: SnrEstimator::default(),
+            clip_threshold: I16_FULL_SCALE,
+            clip_min_run: 3,
+            dropout_min_ms: 5,
+            downmix: DownmixStrategy::default(),
+        }
+    }
+}
+
+/// Builder for [`AudioProcessor`]
+#[cfg(feature = "vad")]
+#[derive(Debug, Clone)]
+pub struct AudioProcessorBuilder {
+    config: ProcessorConfig,
+}
+
+#[cfg(feature = "vad")]
+impl AudioProcessorBuilder {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            config: ProcessorConfig::new(sample_rate, channels),
+        }
+    }
+
+    pub fn vad_backend(mut self, backend: VadBackend) -> Self {
+        self.config.vad_backend = backend;
+        self
+    }
+
+    pub fn vad_mode(mut self, mode: VadMode) -> Self {
+        self.config.vad_mode = mode;
+        self
+    }
+
+    pub fn vad_frame_ms(mut self, frame_ms: u32) -> Self {
+        self.config.vad_frame_ms = frame_ms;
+        self
+    }
+
+    pub fn snr_estimator(mut self, estimator: SnrEstimator) -> Self {
+        self.config.snr_estimator = estimator;
+        self
+    }
+
+    pub fn clip_threshold(mut self, threshold: f32) -> Self {
+        self.config.clip_threshold = threshold;
+        self
+    }
+
+    pub fn clip_min_run(mut self, min_run: usize) -> Self {
+        self.config.clip_min_run = min_run;
+        self
+    }
+
+    pub fn dropout_min_ms(mut self, min_ms: u32) -> Self {
+        self.config.dropout_min_ms = min_ms;
+        self
+    }
+
+    pub fn downmix(mut self, strategy: DownmixStrategy) -> Self {
+        self.config.downmix = strategy;
+        self
+    }
+
+    /// Finished configuration, without building a processor
+    pub fn into_config(self) -> ProcessorConfig {
+        self.config
+    }
+
+    pub fn build(self) -> Result<AudioProcessor, AudioError> {
+        AudioProcessor::from_config(self.config)
+    }
 }
 
 /// Audio processor for real-time quality control
+#[cfg(feature = "vad")]
 pub struct AudioProcessor {
-    sample_rate: u32,
-    channels: u16,
-    vad: webrtc_vad::Vad,
+    config: ProcessorConfig,
+    vad: Box<dyn VoiceDetector>,
+    /// Lowest frame RMS seen since the last reset (adaptive SNR)
+    min_frame_rms: Option<f32>,
+    /// Low-pass filter isolating rumble energy
+    rumble_filter: dsp::Biquad,
+    /// Dropout and click detection, carried across chunk boundaries
+    glitch_detector: glitch::GlitchDetector,
+    /// Speech offset decays, carried across chunk boundaries
+    reverb_estimator: reverb::ReverbEstimator,
+    /// Pitch analysis windows, carried across chunk boundaries
+    pitch_tracker: pitch::PitchTracker,
+    /// Per-channel level statistics since the last reset
+    channel_stats: Vec<ChannelAccumulator>,
 }
 
+#[cfg(feature = "vad")]
 impl AudioProcessor {
-    /// Create a new audio processor
-    pub fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+    /// Create a new audio processor using the WebRTC VAD
+    pub fn new(sample_rate: u32, channels: u16) -> Result<Self, AudioError> {
+        Self::from_config(ProcessorConfig::new(sample_rate, channels))
+    }
+
+    /// Create a new audio processor with the given VAD backend
+    pub fn with_vad_backend(
+        sample_rate: u32,
+        channels: u16,
+        backend: &VadBackend,
+    ) -> Result<Self, AudioError> {
+        AudioProcessorBuilder::new(sample_rate, channels)
+            .vad_backend(backend.clone())
+            .build()
+    }
+
+    /// Start configuring a processor
+    pub fn builder(sample_rate: u32, channels: u16) -> AudioProcessorBuilder {
+        AudioProcessorBuilder::new(sample_rate, channels)
+    }
+
+    /// Create a processor from a complete configuration
+    pub fn from_config(config: ProcessorConfig) -> Result<Self, AudioError> {
         // Validate sample rate
-        match sample_rate {
-            8000 | 16000 | 32000 | 48000 => {}
-            _ => return Err(anyhow::anyhow!("Unsupported sample rate: {}", sample_rate)),
-        };
+        if !SUPPORTED_SAMPLE_RATES.contains(&config.sample_rate) {
+            return Err(AudioError::SampleRate(config.sample_rate));
+        }
 
-        // Validate channels - WebRTC VAD only supports mono audio
-        if channels != 1 {
-            return Err(anyhow::anyhow!(
-                "Only mono audio (1 channel) is supported, got {} channels",
-                channels
-            ));
+        // Multi-channel input is downmixed to mono before VAD and QC
+        if config.channels == 0 {
+            return Err(AudioError::NoChannels);
         }
 
-        let vad = webrtc_vad::Vad::new(sample_rate as i32)
-            .map_err(|_| anyhow::anyhow!("Failed to create VAD instance"))?;
+        let vad = Self::build_vad(&config)?;
+        let rumble_filter = dsp::Biquad::low_pass(
+            config.sample_rate,
+            RUMBLE_CUTOFF_HZ,
+            dsp::Biquad::BUTTERWORTH_Q,
+        );
+        let glitch_detector =
+            glitch::GlitchDetector::new(config.sample_rate, config.dropout_min_ms);
+        let reverb_estimator = reverb::ReverbEstimator::new(config.sample_rate);
+        let pitch_tracker = pitch::PitchTracker::new(config.sample_rate);
+        let channel_stats = vec![ChannelAccumulator::default(); config.channels as usize];
         Ok(Self {
-            sample_rate,
-            channels,
+            config,
             vad,
+            min_frame_rms: None,
+            rumble_filter,
+            glitch_detector,
+            reverb_estimator,
+            pitch_tracker,
+            channel_stats,
         })
     }
 
+    fn build_vad(config: &ProcessorConfig) -> Result<Box<dyn VoiceDetector>, AudioError> {
+        vad::build_detector(
+            &config.vad_backend,
+            config.sample_rate,
+            config.vad_mode,
+            config.vad_frame_ms,
+        )
+    }
+
+    /// Clear all state accumulated across chunks so the processor can be
+    /// reused for a new recording
+    pub fn reset(&mut self) -> Result<(), AudioError> {
+        self.vad = Self::build_vad(&self.config)?;
+        self.min_frame_rms = None;
+        self.rumble_filter.reset();
+        self.glitch_detector.reset();
+        self.reverb_estimator.reset();
+        self.pitch_tracker.reset();
+        self.channel_stats.fill(ChannelAccumulator::default());
+        Ok(())
+    }
+
+    /// Configuration this processor was built with
+    pub fn config(&self) -> &ProcessorConfig {
+        &self.config
+    }
+
     /// Get the number of channels this processor expects
     pub fn channels(&self) -> u16 {
-        self.channels
+        self.config.channels
     }
 
     /// Get the sample rate this processor expects
     pub fn sample_rate(&self) -> u32 {
-        self.sample_rate
+        self.config.sample_rate
+    }
+
+    /// Name of the VAD backend in use (may differ from the requested one after fallback)
+    pub fn vad_backend(&self) -> &'static str {
+        self.vad.name()
+    }
+
+    /// Level statistics for each input channel since the last reset
+    pub fn channel_metrics(&self) -> Vec<ChannelMetrics> {
+        self.channel_stats
+            .iter()
+            .map(ChannelAccumulator::metrics)
+            .collect()
+    }
+
+    /// Reduce interleaved samples to mono using the configured strategy
+    ///
+    /// A trailing partial frame is dropped.
+    pub fn downmix<'a>(&self, samples: &'a [f32]) -> Cow<'a, [f32]> {
+        let channels = self.config.channels as usize;
+        if channels == 1 {
+            return Cow::Borrowed(samples);
+        }
+
+        let frames = samples.chunks_exact(channels);
+        Cow::Owned(match self.config.downmix {
+            DownmixStrategy::Left => frames.map(|frame| frame[0]).collect(),
+            DownmixStrategy::Right => frames.map(|frame| frame[1]).collect(),
+            DownmixStrategy::Average => frames
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect(),
+        })
+    }
+
+    fn update_channel_stats(&mut self, samples: &[f32]) {
+        let channels = self.channel_stats.len();
+        let threshold = self.config.clip_threshold;
+
+        for frame in samples.chunks_exact(channels) {
+            for (stats, &x) in self.channel_stats.iter_mut().zip(frame) {
+                stats.sum += x as f64;
+                stats.sum_squares += (x * x) as f64;
+                stats.peak = stats.peak.max(x.abs());
+                stats.clipped += (x.abs() >= threshold) as u64;
+                stats.samples += 1;
+            }
+        }
     }
 
     /// Process a chunk of audio samples
     ///
-    /// Expects mono audio samples. For multi-channel audio, samples should be
-    /// converted to mono before calling this function.
+    /// Multi-channel input must be interleaved; it is downmixed to mono with
+    /// the configured [`DownmixStrategy`] before analysis, while per-channel
+    /// levels are tracked separately (see [`AudioProcessor::channel_metrics`]).
     pub fn process_chunk(&mut self, samples: &[f32]) -> QcMetrics {
+        self.update_channel_stats(samples);
+        let mono = self.downmix(samples);
+        self.analyze_mono(&mono)
+    }
+
+    fn analyze_mono(&mut self, samples: &[f32]) -> QcMetrics {
         // Calculate RMS
         let rms = self.calculate_rms(samples);
 
@@ -83,18 +657,123 @@ impl AudioProcessor {
         let clipping_pct = self.detect_clipping(samples);
 
         // Run VAD
-        let vad_ratio = self.run_vad(samples);
+        let frames = self.run_vad(samples);
+        let vad_ratio = if frames.total > 0 {
+            (frames.speech as f32 / frames.total as f32) * 100.0
+        } else {
+            0.0
+        };
+
+        // Speech and edge silence durations
+        let sample_rate = self.config.sample_rate as f32;
+        let frame_secs = self.vad.frame_size() as f32 / sample_rate;
+        let duration_secs = samples.len() as f32 / sample_rate;
+        let speech_secs = frames.speech as f32 * frame_secs;
+        let (leading_silence_secs, trailing_silence_secs) =
+            match (frames.first_speech, frames.last_speech) {
+                (Some(first), Some(last)) => (
+                    first as f32 * frame_secs,
+                    (duration_secs - (last + 1) as f32 * frame_secs).max(0.0),
+                ),
+                _ => (duration_secs, duration_secs),
+            };
 
         // Compute SNR (simplified)
-        let snr_db = self.estimate_snr(rms, clipping_pct);
+        let snr_db = self.estimate_snr(samples, rms, clipping_pct);
+
+        // Measure DC offset and low-frequency rumble
+        let dc_offset = dsp::mean(samples);
+        let rumble_db = self.measure_rumble(samples, dc_offset);
+
+        // Detect mains hum
+        let hum_db = self.measure_hum(samples, dc_offset);
+
+        // Detect dropped buffers and clicks
+        let glitches = self.glitch_detector.process(samples);
+        let glitch_pct = if samples.is_empty() {
+            0.0
+        } else {
+            (glitches.clicks as f32 / samples.len() as f32) * 100.0
+        };
+
+        // Estimate reverberation from speech offsets
+        let decays = self.reverb_estimator.process(samples);
+        let reverb_rt60_secs = if decays.is_empty() {
+            0.0
+        } else {
+            decays.iter().sum::<f32>() / decays.len() as f32
+        };
+
+        // Pitch statistics over voiced frames
+        let pitch = self.pitch_tracker.process(samples);
+        let voiced_ratio = if pitch.total > 0 {
+            (pitch.f0_hz.len() as f32 / pitch.total as f32) * 100.0
+        } else {
+            0.0
+        };
+        let f0_min_hz = pitch.f0_hz.iter().copied().reduce(f32::min).unwrap_or(0.0);
+        let f0_max_hz = pitch.f0_hz.iter().copied().reduce(f32::max).unwrap_or(0.0);
+        let f0_mean_hz = if pitch.f0_hz.is_empty() {
+            0.0
+        } else {
+            pitch.f0_hz.iter().sum::<f32>() / pitch.f0_hz.len() as f32
+        };
 
         QcMetrics {
             snr_db,
             clipping_pct,
             vad_ratio,
+            dc_offset,
+            rumble_db,
+            hum_db,
+            dropout_count: glitches.dropouts,
+            glitch_pct,
+            duration_secs,
+            speech_secs,
+            leading_silence_secs,
+            trailing_silence_secs,
+            reverb_rt60_secs,
+            f0_min_hz,
+            f0_max_hz,
+            f0_mean_hz,
+            voiced_ratio,
         }
     }
 
+    /// Hum level of the stronger mains family (50 or 60 Hz) relative to total energy
+    fn measure_hum(&self, samples: &[f32], dc_offset: f32) -> f32 {
+        let ac: Vec<f32> = samples.iter().map(|&x| x - dc_offset).collect();
+        let total_energy: f32 = ac.iter().map(|&x| x * x).sum();
+        let nyquist = self.config.sample_rate as f32 / 2.0;
+
+        MAINS_FREQUENCIES_HZ
+            .iter()
+            .map(|&mains| {
+                let hum_energy: f32 = (1..=HUM_HARMONICS)
+                    .map(|harmonic| mains * harmonic as f32)
+                    .filter(|&freq| freq < nyquist)
+                    .map(|freq| dsp::goertzel_energy(&ac, self.config.sample_rate, freq))
+                    .sum();
+                dsp::energy_ratio_db(hum_energy, total_energy)
+            })
+            .fold(f32::MIN, f32::max)
+    }
+
+    /// Energy below the rumble cutoff relative to total AC energy, in dB
+    fn measure_rumble(&mut self, samples: &[f32], dc_offset: f32) -> f32 {
+        let mut total_energy = 0.0;
+        let mut low_energy = 0.0;
+
+        for &sample in samples {
+            let ac = sample - dc_offset;
+            let low = self.rumble_filter.process(ac);
+            total_energy += ac * ac;
+            low_energy += low * low;
+        }
+
+        dsp::energy_ratio_db(low_energy, total_energy)
+    }
+
     /// Calculate RMS of audio samples
     fn calculate_rms(&self, samples: &[f32]) -> f32 {
         let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
@@ -102,32 +781,82 @@ impl AudioProcessor {
     }
 
     /// Detect percentage of clipped samples
+    ///
+    /// A sample is clipped when it reaches the near-full-scale threshold, or
+    /// when it belongs to a flat plateau of at least `clip_min_run` samples
+    /// sitting at the chunk's peak level.
     fn detect_clipping(&self, samples: &[f32]) -> f32 {
-        let clipped = samples.iter().filter(|&&x| x.abs() >= 1.0).count();
+        // Plateaus below this level are treated as quiet signal, not clipping
+        const MIN_PLATEAU_LEVEL: f32 = 0.1;
+        // How close to the peak a plateau sample must be
+        const PLATEAU_PEAK_FRACTION: f32 = 0.98;
+        // Maximum difference between neighbouring samples inside a plateau
+        const PLATEAU_TOLERANCE: f32 = 1e-4;
+
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let threshold = self.config.clip_threshold;
+        let min_run = self.config.clip_min_run.max(2);
+        let peak = samples.iter().fold(0.0f32, |acc, &x| acc.max(x.abs()));
+        let plateau_level = peak * PLATEAU_PEAK_FRACTION;
+        let detect_plateaus = peak >= MIN_PLATEAU_LEVEL;
+
+        let mut clipped = 0;
+        let mut run_len = 0;
+        let mut run_hard = 0;
+        let mut prev = 0.0f32;
+
+        for &x in samples {
+            let hard = x.abs() >= threshold;
+            let continues_run =
+                run_len > 0 && x.abs() >= plateau_level && (x - prev).abs() <= PLATEAU_TOLERANCE;
+
+            if continues_run {
+                run_len += 1;
+                run_hard += hard as usize;
+            } else {
+                clipped += Self::clipped_in_run(run_len, run_hard, min_run, detect_plateaus);
+                let starts_run = x.abs() >= plateau_level;
+                run_len = starts_run as usize;
+                run_hard = (starts_run && hard) as usize;
+                if !starts_run && hard {
+                    clipped += 1;
+                }
+            }
+            prev = x;
+        }
+        clipped += Self::clipped_in_run(run_len, run_hard, min_run, detect_plateaus);
+
         (clipped as f32 / samples.len() as f32) * 100.0
     }
 
-    /// Run Voice Activity Detection
-    fn run_vad(&mut self, samples: &[f32]) -> f32 {
-        // Convert f32 samples to i16 for VAD
-        let mut i16_samples = Vec::with_capacity(samples.len());
-        for &sample in samples {
-            i16_samples.push((sample * 32767.0) as i16);
+    /// Clipped samples within one run at the peak level
+    fn clipped_in_run(run_len: usize, run_hard: usize, min_run: usize, plateaus: bool) -> usize {
+        if plateaus && run_len >= min_run {
+            run_len
+        } else {
+            run_hard
         }
+    }
 
-        // Process in 30ms frames
-        let frame_size = (self.sample_rate as f32 * 0.03) as usize;
-        let mut speech_frames = 0;
-        let mut total_frames = 0;
+    /// Run Voice Activity Detection
+    fn run_vad(&mut self, samples: &[f32]) -> VadFrames {
+        // Process in backend-sized frames
+        let frame_size = self.vad.frame_size();
+        let mut frames = VadFrames::default();
 
-        for chunk in i16_samples.chunks(frame_size) {
+        for (index, chunk) in samples.chunks(frame_size).enumerate() {
             if chunk.len() == frame_size {
-                match self.vad.is_voice_segment(chunk) {
+                match self.vad.is_speech(chunk) {
                     Ok(is_speech) => {
                         if is_speech {
-                            speech_frames += 1;
+                            frames.speech += 1;
+                            frames.first_speech.get_or_insert(index);
+                            frames.last_speech = Some(index);
                         }
-                        total_frames += 1;
+                        frames.total += 1;
                     }
                     Err(_) => {
                         error!("VAD processing failed for frame");
@@ -136,18 +865,27 @@ impl AudioProcessor {
             }
         }
 
-        if total_frames > 0 {
-            (speech_frames as f32 / total_frames as f32) * 100.0
-        } else {
-            0.0
-        }
+        frames
     }
 
     /// Estimate SNR based on RMS and clipping
-    fn estimate_snr(&self, rms: f32, clipping_pct: f32) -> f32 {
+    fn estimate_snr(&mut self, samples: &[f32], rms: f32, clipping_pct: f32) -> f32 {
         // Simple SNR estimation based on RMS and clipping
         // This is a simplified model - real SNR calculation would be more complex
-        let noise_floor = -60.0; // Typical noise floor in dB
+        let noise_floor = match self.config.snr_estimator {
+            SnrEstimator::FixedFloor { noise_floor_db } => noise_floor_db,
+            SnrEstimator::Adaptive => {
+                let frame_size = (self.config.sample_rate as usize * 30 / 1000).max(1);
+                for frame in samples.chunks(frame_size) {
+                    let frame_rms = self.calculate_rms(frame).max(1e-6);
+                    self.min_frame_rms = Some(match self.min_frame_rms {
+                        Some(current) => current.min(frame_rms),
+                        None => frame_rms,
+                    });
+                }
+                20.0 * self.min_frame_rms.unwrap_or(1e-6).log10()
+            }
+        };
         let signal_level = 20.0 * rms.log10();
         let noise_level = noise_floor + (clipping_pct * 0.1);
         signal_level - noise_level
@@ -155,74 +893,230 @@ impl AudioProcessor {
 }
 
 /// Analyze a WAV file and return QC metrics (safe Rust API)
-pub fn analyze_wav_file<P: AsRef<std::path::Path>>(path: P) -> Result<QcMetrics> {
-    let path_str = path.as_ref().to_string_lossy();
-    analyze_wav_internal(&path_str)
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_wav_file<P: AsRef<std::path::Path>>(path: P) -> Result<QcMetrics, AudioError> {
+    analyze_wav_file_with_downmix(path, DownmixStrategy::default())
+}
+
+/// Analyze WAV data from any reader (a buffer, a network stream, an
+/// archive entry) and return QC metrics
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_reader<R: std::io::Read>(reader: R) -> Result<QcMetrics, AudioError> {
+    let config = ProcessorConfig::new(DEFAULT_ANALYSIS_RATE, 1);
+    Ok(analyze_reader_timeline(reader, config)?.summary())
+}
+
+/// Analyze a WAV file, reducing multi-channel audio with the given strategy
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_wav_file_with_downmix<P: AsRef<std::path::Path>>(
+    path: P,
+    downmix: DownmixStrategy,
+) -> Result<QcMetrics, AudioError> {
+    let config = ProcessorConfig {
+        downmix,
+        ..ProcessorConfig::new(DEFAULT_ANALYSIS_RATE, 1)
+    };
+    analyze_wav_file_with_config(path, config)
+}
+
+/// Analyze a WAV file with custom processor settings
+///
+/// The sample rate and channel count in `config` are replaced by the file's
+/// own (files at unsupported rates are resampled first).
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_wav_file_with_config<P: AsRef<std::path::Path>>(
+    path: P,
+    config: ProcessorConfig,
+) -> Result<QcMetrics, AudioError> {
+    Ok(analyze_wav_timeline(path, config)?.summary())
+}
+
+/// Analyze an audio file into per-frame metrics (see
+/// [`timeline::TIMELINE_FRAME_MS`])
+///
+/// WAV files of any sample format are read directly; other files (FLAC and
+/// Opus recordings, and the MP3, Ogg Vorbis or M4A files of imported
+/// corpora) are decoded with [`decode::decode_file`] and analyzed the same
+/// way.
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_wav_timeline<P: AsRef<std::path::Path>>(
+    path: P,
+    config: ProcessorConfig,
+) -> Result<QcTimeline, AudioError> {
+    let path = path.as_ref();
+    if !decode::is_wav(path) {
+        let audio = decode::decode_file(path).map_err(|e| {
+            let message = format!("{e:#}");
+            e.downcast::<std::io::Error>()
+                .map_or(AudioError::Decode(message), AudioError::FileOpen)
+        })?;
+        return analyze_samples_timeline(audio.samples, audio.sample_rate, audio.channels, config);
+    }
+    let file = std::fs::File::open(path)?;
+    analyze_reader_timeline(std::io::BufReader::new(file), config)
+}
+
+/// Analyze WAV data (integer or float samples) from a reader into per-frame
+/// metrics, with the same handling of `config` as
+/// [`analyze_wav_file_with_config`]
+#[cfg(all(feature = "vad", feature = "wav"))]
+pub fn analyze_reader_timeline<R: std::io::Read>(
+    reader: R,
+    config: ProcessorConfig,
+) -> Result<QcTimeline, AudioError> {
+    let reader = hound::WavReader::new(reader)?;
+    let spec = reader.spec();
+    let all_samples = decode::read_wav_samples(reader)?;
+
+    analyze_samples_timeline(all_samples, spec.sample_rate, spec.channels, config)
+}
+
+/// Analyze interleaved samples (in [-1.0, 1.0]) at `sample_rate` with
+/// `channels` channels into per-frame metrics, with the same handling of
+/// `config` as [`analyze_wav_file_with_config`]
+#[cfg(feature = "vad")]
+pub fn analyze_samples_timeline(
+    mut all_samples: Vec<f32>,
+    sample_rate: u32,
+    channels: u16,
+    config: ProcessorConfig,
+) -> Result<QcTimeline, AudioError> {
+    // Audio at other rates (e.g. 44.1 kHz) is converted before analysis
+    let analysis_rate = if SUPPORTED_SAMPLE_RATES.contains(&sample_rate) {
+        sample_rate
+    } else {
+        DEFAULT_ANALYSIS_RATE
+    };
+    let mut resampler = resample::Resampler::new(sample_rate, analysis_rate, channels)?;
+    let mut processor = AudioProcessor::from_config(ProcessorConfig {
+        sample_rate: analysis_rate,
+        channels,
+        ..config
+    })?;
+    if !resampler.is_passthrough() {
+        let mut resampled = resampler.process(&all_samples)?;
+        resampled.extend(resampler.flush()?);
+        all_samples = resampled;
+    }
+
+    // Process in timeline frames of whole interleaved samples
+    let chunk_size = QcTimeline::frame_len(analysis_rate, channels);
+    let mut timeline = QcTimeline::new();
+
+    for chunk in all_samples.chunks(chunk_size) {
+        timeline.push(processor.process_chunk(chunk));
+    }
+
+    Ok(timeline)
+}
+
+/// The three metrics [`analyze_wav`] has always returned
+///
+/// Frozen: callers compiled against the original header read exactly this
+/// layout, so new metrics go in [`QcMetrics`] and reach C through
+/// [`cowcow_analyze_wav_v2`] only.
+#[cfg(feature = "ffi")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct QcMetricsV1 {
+    /// Signal-to-noise ratio in decibels
+    pub snr_db: f32,
+    /// Percentage of samples that are clipped
+    pub clipping_pct: f32,
+    /// Ratio of frames classified as speech by VAD
+    pub vad_ratio: f32,
+}
+
+#[cfg(feature = "ffi")]
+impl From<&QcMetrics> for QcMetricsV1 {
+    fn from(metrics: &QcMetrics) -> Self {
+        Self {
+            snr_db: metrics.snr_db,
+            clipping_pct: metrics.clipping_pct,
+            vad_ratio: metrics.vad_ratio,
+        }
+    }
 }
 
 /// Analyze a WAV file and return QC metrics (unsafe C FFI)
 ///
+/// The original entry point, unchanged for existing mobile integrations: it
+/// returns the original three-field [`QcMetricsV1`], and errors are logged
+/// and reported as failing metrics. New C code should use
+/// [`cowcow_analyze_wav_v2`] or [`ffi::cowcow_analyze_file`], and Rust code
+/// the safe [`analyze_wav_file`] or [`analyze_reader`].
+///
 /// # Safety
 ///
 /// This function dereferences a raw pointer. The caller must ensure that:
 /// - `path` is a valid pointer to a null-terminated C string
 /// - The string pointed to by `path` is valid UTF-8 or UTF-8 compatible
 /// - The pointer remains valid for the duration of the function call
-///
-/// # Note
-///
-/// Consider using the safe `analyze_wav_file` function instead if calling from Rust.
+#[cfg(feature = "ffi")]
 #[no_mangle]
-pub unsafe extern "C" fn analyze_wav(path: *const c_char) -> QcMetrics {
+pub unsafe extern "C" fn analyze_wav(path: *const c_char) -> QcMetricsV1 {
+    if path.is_null() {
+        error!("Failed to analyze WAV file: path is null");
+        return failing_metrics();
+    }
     let path_str = std::ffi::CStr::from_ptr(path)
         .to_string_lossy()
         .into_owned();
 
-    match analyze_wav_internal(&path_str) {
-        Ok(metrics) => metrics,
+    match analyze_wav_timeline(&path_str, ProcessorConfig::new(DEFAULT_ANALYSIS_RATE, 1)) {
+        Ok(timeline) => QcMetricsV1::from(&timeline.summary()),
         Err(e) => {
             error!("Failed to analyze WAV file: {}", e);
-            QcMetrics {
-                snr_db: 0.0,
-                clipping_pct: 100.0,
-                vad_ratio: 0.0,
-            }
+            failing_metrics()
         }
     }
 }
 
-fn analyze_wav_internal(path: &str) -> Result<QcMetrics> {
-    let reader = hound::WavReader::open(path)?;
-    let spec = reader.spec();
-
-    let mut processor = AudioProcessor::new(spec.sample_rate, spec.channels)?;
-    let mut all_samples = Vec::new();
-
-    // Read all samples
-    for sample in reader.into_samples::<i16>() {
-        let sample = sample?;
-        all_samples.push(sample as f32 / 32768.0);
+/// What [`analyze_wav`] reports for a file it could not analyze
+#[cfg(feature = "ffi")]
+fn failing_metrics() -> QcMetricsV1 {
+    QcMetricsV1 {
+        snr_db: 0.0,
+        clipping_pct: 100.0,
+        vad_ratio: 0.0,
     }
+}
 
-    // Process in chunks
-    let chunk_size = (spec.sample_rate as f32 * 0.1) as usize; // 100ms chunks
-    let mut metrics = Vec::new();
-
-    for chunk in all_samples.chunks(chunk_size) {
-        metrics.push(processor.process_chunk(chunk));
+/// Analyze a WAV file into `metrics` (unsafe C FFI)
+///
+/// Returns [`CowcowStatus::Ok`] on success. On failure `metrics` is left
+/// untouched and [`ffi::cowcow_last_error_message`] describes the error.
+///
+/// # Safety
+///
+/// This function dereferences raw pointers. The caller must ensure that:
+/// - `path` is a valid pointer to a null-terminated UTF-8 C string
+/// - `metrics` is a valid pointer to writable [`QcMetrics`]
+/// - Both pointers remain valid for the duration of the function call
+#[cfg(feature = "ffi")]
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_analyze_wav_v2(
+    path: *const c_char,
+    metrics: *mut QcMetrics,
+) -> CowcowStatus {
+    if metrics.is_null() {
+        return ffi::fail(CowcowStatus::NullPointer, "Metrics argument is null");
     }
-
-    // Average the metrics
-    let avg_metrics = QcMetrics {
-        snr_db: metrics.iter().map(|m| m.snr_db).sum::<f32>() / metrics.len() as f32,
-        clipping_pct: metrics.iter().map(|m| m.clipping_pct).sum::<f32>() / metrics.len() as f32,
-        vad_ratio: metrics.iter().map(|m| m.vad_ratio).sum::<f32>() / metrics.len() as f32,
+    let path = match ffi::path_arg(path) {
+        Ok(path) => path,
+        Err(status) => return status,
     };
 
-    Ok(avg_metrics)
+    match analyze_wav_timeline(&path, ProcessorConfig::new(DEFAULT_ANALYSIS_RATE, 1)) {
+        Ok(timeline) => {
+            *metrics = timeline.summary();
+            CowcowStatus::Ok
+        }
+        Err(e) => ffi::fail_with(e),
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "vad"))]
 mod tests {
     use super::*;
 
@@ -234,8 +1128,8 @@ mod tests {
         assert_eq!(processor.sample_rate(), 16000);
         assert_eq!(processor.channels(), 1);
 
-        // Test that multi-channel fails
-        assert!(AudioProcessor::new(16000, 2).is_err());
+        // Test that a channel count of zero fails
+        assert!(AudioProcessor::new(16000, 0).is_err());
 
         // Generate a test signal (sine wave)
         let mut samples = Vec::new();
@@ -250,4 +1144,539 @@ mod tests {
         assert!(metrics.clipping_pct < 1.0);
         assert!(metrics.vad_ratio >= 0.0 && metrics.vad_ratio <= 100.0);
     }
+
+    #[test]
+    fn test_builder_and_reset() {
+        let mut processor = AudioProcessor::builder(16000, 1)
+            .vad_mode(VadMode::Aggressive)
+            .vad_frame_ms(20)
+            .snr_estimator(SnrEstimator::Adaptive)
+            .build()
+            .unwrap();
+        assert_eq!(processor.config().vad_frame_ms, 20);
+
+        let quiet = vec![0.001; 1600];
+        let loud: Vec<f32> = (0..1600)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                0.1 * std::f32::consts::SQRT_2 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            })
+            .collect();
+        processor.process_chunk(&quiet);
+        let with_floor = processor.process_chunk(&loud);
+        assert!((with_floor.snr_db - 40.0).abs() < 0.5);
+
+        // After reset the quiet floor is forgotten
+        processor.reset().unwrap();
+        let fresh = processor.process_chunk(&loud);
+        assert!(fresh.snr_db.abs() < 0.5);
+
+        // Unsupported frame lengths are rejected
+        assert!(AudioProcessor::builder(16000, 1)
+            .vad_frame_ms(25)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_clipping_detects_rescaled_plateaus() {
+        let processor = AudioProcessor::new(16000, 1).unwrap();
+
+        // Sine clipped at 0.8 then rescaled: flat tops never reach full scale
+        let samples: Vec<f32> = (0..1600)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                (1.6 * (2.0 * std::f32::consts::PI * 100.0 * t).sin()).clamp(-0.8, 0.8)
+            })
+            .collect();
+        assert!(processor.detect_clipping(&samples) > 10.0);
+
+        // A clean sine at the same peak is not clipped
+        let clean: Vec<f32> = (0..1600)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                0.8 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            })
+            .collect();
+        assert!(processor.detect_clipping(&clean) < 1.0);
+
+        // Samples at full scale are always counted
+        assert_eq!(processor.detect_clipping(&[1.0, 0.0, -1.0, 0.0]), 50.0);
+    }
+
+    #[test]
+    fn test_dc_offset_and_rumble() {
+        let mut processor = AudioProcessor::new(16000, 1).unwrap();
+        let tone = |freq: f32, amplitude: f32, offset: f32| -> Vec<f32> {
+            (0..16000)
+                .map(|i| {
+                    let t = i as f32 / 16000.0;
+                    offset + amplitude * (2.0 * std::f32::consts::PI * freq * t).sin()
+                })
+                .collect()
+        };
+
+        let speech_band = processor.process_chunk(&tone(440.0, 0.5, 0.0));
+        assert!(speech_band.dc_offset.abs() < 0.01);
+        assert!(speech_band.rumble_db < -20.0);
+
+        processor.reset().unwrap();
+        let rumbly = processor.process_chunk(&tone(20.0, 0.5, 0.2));
+        assert!((rumbly.dc_offset - 0.2).abs() < 0.01);
+        assert!(rumbly.rumble_db > -3.0);
+    }
+
+    #[test]
+    fn test_hum_detection() {
+        let mut processor = AudioProcessor::new(16000, 1).unwrap();
+        let tone = |freq: f32| -> Vec<f32> {
+            (0..1600)
+                .map(|i| {
+                    let t = i as f32 / 16000.0;
+                    0.3 * (2.0 * std::f32::consts::PI * freq * t).sin()
+                })
+                .collect()
+        };
+
+        let hum = processor.process_chunk(&tone(60.0));
+        assert!(hum.hum_db > -1.0);
+
+        let clean = processor.process_chunk(&tone(1000.0));
+        assert!(clean.hum_db < -30.0);
+    }
+
+    #[test]
+    fn test_stereo_downmix_and_channel_metrics() {
+        let tone: Vec<f32> = (0..1600)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            })
+            .collect();
+        // Left carries the tone, right is a dead channel
+        let interleaved: Vec<f32> = tone.iter().flat_map(|&x| [x, 0.0]).collect();
+
+        let mut left = AudioProcessor::builder(16000, 2)
+            .downmix(DownmixStrategy::Left)
+            .build()
+            .unwrap();
+        let mut right = AudioProcessor::builder(16000, 2)
+            .downmix(DownmixStrategy::Right)
+            .build()
+            .unwrap();
+        let mut average = AudioProcessor::new(16000, 2).unwrap();
+
+        assert_eq!(left.downmix(&interleaved).as_ref(), tone.as_slice());
+        let left_metrics = left.process_chunk(&interleaved);
+        let right_metrics = right.process_chunk(&interleaved);
+        let average_metrics = average.process_chunk(&interleaved);
+
+        assert!((left_metrics.duration_secs - 0.1).abs() < 1e-6);
+        assert!((left_metrics.snr_db - average_metrics.snr_db - 6.0).abs() < 0.1);
+        assert!(right_metrics.snr_db < left_metrics.snr_db);
+
+        let channels = average.channel_metrics();
+        assert_eq!(channels.len(), 2);
+        assert!((channels[0].peak - 0.5).abs() < 0.01);
+        assert_eq!(channels[1].peak, 0.0);
+        assert!(channels[1].rms_db < -100.0);
+    }
+
+    #[test]
+    fn test_edge_silence_spans_chunks() {
+        let chunk = |speech_secs: f32, leading: f32, trailing: f32| QcMetrics {
+            duration_secs: 1.0,
+            speech_secs,
+            leading_silence_secs: leading,
+            trailing_silence_secs: trailing,
+            ..Default::default()
+        };
+
+        let combined = QcMetrics::average(&[
+            chunk(0.0, 1.0, 1.0),
+            chunk(0.5, 0.3, 0.2),
+            chunk(0.8, 0.0, 0.1),
+            chunk(0.0, 1.0, 1.0),
+        ]);
+
+        assert_eq!(combined.duration_secs, 4.0);
+        assert!((combined.speech_secs - 1.3).abs() < 1e-6);
+        assert!((combined.leading_silence_secs - 1.3).abs() < 1e-6);
+        assert!((combined.trailing_silence_secs - 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_dropout_and_glitch_detection() {
+        let mut processor = AudioProcessor::new(16000, 1).unwrap();
+        let tone: Vec<f32> = (0..1600)
+            .map(|i| {
+                let t = i as f32 / 16000.0;
+                0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            })
+            .collect();
+
+        let clean = processor.process_chunk(&tone);
+        assert_eq!(clean.dropout_count, 0);
+        assert_eq!(clean.glitch_pct, 0.0);
+
+        // A zero-filled buffer in the middle of the tone
+        let mut dropped = tone.clone();
+        dropped[400..664].fill(0.0);
+        let metrics = processor.process_chunk(&dropped);
+        assert_eq!(metrics.dropout_count, 1);
+        assert!(metrics.glitch_pct > 0.0);
+
+        // A dropout spanning two chunks is counted once
+        processor.reset().unwrap();
+        let mut tail = tone.clone();
+        tail[1500..].fill(0.0);
+        let first = processor.process_chunk(&tail);
+        let second = processor.process_chunk(&vec![0.0; 1600]);
+        assert_eq!(first.dropout_count + second.dropout_count, 1);
+
+        // Silence that was never live is not a dropout
+        processor.reset().unwrap();
+        assert_eq!(processor.process_chunk(&vec![0.0; 1600]).dropout_count, 0);
+    }
+
+    #[test]
+    fn test_pitch_statistics() {
+        // 150 Hz then 250 Hz voice-like tones, followed by noise (unvoiced)
+        let tone = |f0: f32| {
+            (0..16000).map(move |i| {
+                let t = i as f32 / 16000.0;
+                (1..5)
+                    .map(|h| {
+                        0.2 / h as f32 * (2.0 * std::f32::consts::PI * f0 * h as f32 * t).sin()
+                    })
+                    .sum::<f32>()
+            })
+        };
+        let mut seed = 0x9e37_79b9u32;
+        let noise = std::iter::from_fn(move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            Some(0.2 * (seed as f32 / u32::MAX as f32 * 2.0 - 1.0))
+        });
+        let samples: Vec<f32> = tone(150.0)
+            .chain(tone(250.0))
+            .chain(noise.take(16000))
+            .collect();
+
+        let mut processor = AudioProcessor::new(16000, 1).unwrap();
+        let chunks: Vec<QcMetrics> = samples
+            .chunks(1600)
+            .map(|chunk| processor.process_chunk(chunk))
+            .collect();
+        let metrics = QcMetrics::average(&chunks);
+
+        assert!((metrics.f0_min_hz - 150.0).abs() < 5.0, "{metrics:?}");
+        assert!((metrics.f0_max_hz - 250.0).abs() < 5.0, "{metrics:?}");
+        assert!((metrics.f0_mean_hz - 200.0).abs() < 10.0, "{metrics:?}");
+        assert!((metrics.voiced_ratio - 66.7).abs() < 5.0, "{metrics:?}");
+    }
+
+    #[test]
+    fn test_reverb_estimate() {
+        // Deterministic white noise
+        let mut seed = 0x2545_f491u32;
+        let mut noise = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+        };
+
+        // Noise bursts whose tails decay by 60 dB over `rt60` seconds
+        let mut signal = |rt60: f32| -> Vec<f32> {
+            let mut samples: Vec<f32> = (0..4800).map(|_| 1e-4 * noise()).collect();
+            for _ in 0..3 {
+                samples.extend((0..3200).map(|_| 0.3 * noise()));
+                samples.extend((0..16000).map(|i| {
+                    let t = i as f32 / 16000.0;
+                    let gain = 10f32.powf(-3.0 * t / rt60);
+                    0.3 * gain * noise() + 1e-4 * noise()
+                }));
+            }
+            samples
+        };
+
+        let measure = |samples: Vec<f32>| {
+            let mut processor = AudioProcessor::new(16000, 1).unwrap();
+            let chunks: Vec<QcMetrics> = samples
+                .chunks(1600)
+                .map(|chunk| processor.process_chunk(chunk))
+                .collect();
+            QcMetrics::average(&chunks).reverb_rt60_secs
+        };
+
+        let reverberant = measure(signal(0.8));
+        assert!((reverberant - 0.8).abs() < 0.15, "rt60 {reverberant}");
+
+        let dry = measure(signal(0.05));
+        assert!(dry < 0.2, "rt60 {dry}");
+    }
+
+    #[test]
+    #[cfg(feature = "wav")]
+    fn test_timeline_frames() {
+        let path =
+            std::env::temp_dir().join(format!("cowcow-timeline-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        // Half a second of silence, then a clipped tone
+        for i in 0..12000 {
+            let sample = if i < 8000 {
+                0.0
+            } else {
+                (1.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 16000.0).sin())
+                    .clamp(-1.0, 1.0)
+            };
+            writer.write_sample((sample * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let timeline = analyze_wav_timeline(&path, ProcessorConfig::new(16000, 1)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(timeline.frames.len(), 8);
+        assert!((timeline.frames[7].start_secs - 0.7).abs() < 1e-4);
+        assert!((timeline.frames[7].metrics.duration_secs - 0.05).abs() < 1e-4);
+        assert!(timeline.frames[..5]
+            .iter()
+            .all(|frame| frame.metrics.clipping_pct == 0.0));
+        assert!(timeline.frames[5..]
+            .iter()
+            .all(|frame| frame.metrics.clipping_pct > 0.0));
+
+        let summary = timeline.summary();
+        assert!((summary.duration_secs - 0.75).abs() < 1e-4);
+
+        // Summary-only timelines drop frames but report the same summary
+        let mut streamed = QcTimeline::summary_only();
+        for frame in &timeline.frames {
+            streamed.push(frame.metrics.clone());
+        }
+        assert!(streamed.frames.is_empty());
+        assert_eq!(
+            streamed.last().unwrap().clipping_pct,
+            timeline.frames[7].metrics.clipping_pct
+        );
+        let streamed_summary = streamed.summary();
+        assert_eq!(streamed_summary.duration_secs, summary.duration_secs);
+        assert_eq!(streamed_summary.clipping_pct, summary.clipping_pct);
+        assert_eq!(streamed_summary.snr_db, summary.snr_db);
+
+        let json = serde_json::to_value(&timeline).unwrap();
+        assert!((json["frames"][5]["start_secs"].as_f64().unwrap() - 0.5).abs() < 1e-4);
+        assert!(json["frames"][5]["clipping_pct"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "wav")]
+    fn test_safe_analysis_api() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut wav = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut wav, spec).unwrap();
+        for i in 0..8000 {
+            let sample = 0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin();
+            writer.write_sample((sample * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        let bytes = wav.into_inner();
+
+        let path = std::env::temp_dir().join(format!("cowcow-safe-{}.wav", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &bytes).unwrap();
+        let from_file = analyze_wav_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let from_reader = analyze_reader(bytes.as_slice()).unwrap();
+        assert!((from_reader.duration_secs - 0.5).abs() < 1e-4);
+        assert_eq!(from_reader.snr_db, from_file.snr_db);
+        assert_eq!(from_reader.clipping_pct, 0.0);
+
+        // Errors are reported rather than turned into failing metrics
+        assert!(matches!(
+            analyze_wav_file(&path),
+            Err(AudioError::FileOpen(_))
+        ));
+        assert!(matches!(
+            analyze_reader(&b"not a wav file"[..]),
+            Err(AudioError::WavFormat(_))
+        ));
+
+        // 24-bit and float WAV files measure like the 16-bit original
+        for (bits_per_sample, sample_format) in [
+            (24, hound::SampleFormat::Int),
+            (32, hound::SampleFormat::Float),
+        ] {
+            let spec = hound::WavSpec {
+                bits_per_sample,
+                sample_format,
+                ..spec
+            };
+            let mut wav = std::io::Cursor::new(Vec::new());
+            let mut writer = hound::WavWriter::new(&mut wav, spec).unwrap();
+            for i in 0..8000 {
+                let sample = 0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin();
+                match sample_format {
+                    hound::SampleFormat::Int => {
+                        writer.write_sample((sample * 8_388_607.0) as i32).unwrap()
+                    }
+                    hound::SampleFormat::Float => writer.write_sample(sample).unwrap(),
+                }
+            }
+            writer.finalize().unwrap();
+            let metrics = analyze_reader(wav.into_inner().as_slice()).unwrap();
+            assert!((metrics.duration_secs - 0.5).abs() < 1e-4);
+            assert!((metrics.snr_db - from_file.snr_db).abs() < 0.5);
+        }
+        assert!(matches!(
+            AudioProcessor::new(44100, 1),
+            Err(AudioError::SampleRate(44100))
+        ));
+    }
+
+    #[test]
+    fn test_speech_spans() {
+        let mut timeline = QcTimeline::new();
+        for speech_secs in [0.0, 0.1, 0.05, 0.0, 0.0, 0.1, 0.0] {
+            timeline.push(QcMetrics {
+                duration_secs: 0.1,
+                speech_secs,
+                ..Default::default()
+            });
+        }
+
+        let spans = timeline.speech_spans();
+        assert_eq!(spans.len(), 2);
+        assert!((spans[0].0 - 0.1).abs() < 1e-6 && (spans[0].1 - 0.3).abs() < 1e-6);
+        assert!((spans[1].0 - 0.5).abs() < 1e-6 && (spans[1].1 - 0.6).abs() < 1e-6);
+        assert!(QcTimeline::summary_only().speech_spans().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "wav")]
+    fn test_trim_silence() {
+        let frame = |speech_secs: f32| QcMetrics {
+            duration_secs: 0.1,
+            speech_secs,
+            ..Default::default()
+        };
+        let mut timeline = QcTimeline::new();
+        for speech_secs in [0.0, 0.0, 0.0, 0.0, 0.05, 0.1, 0.0, 0.0, 0.0] {
+            timeline.push(frame(speech_secs));
+        }
+
+        let (start_secs, end_secs) = timeline.trim_silence(0.1).unwrap();
+        assert!((start_secs - 0.3).abs() < 1e-6);
+        assert!((end_secs - 0.2).abs() < 1e-6);
+        assert_eq!(timeline.frames.len(), 4);
+        assert_eq!(timeline.frames[0].start_secs, 0.0);
+        assert!((timeline.summary().duration_secs - 0.4).abs() < 1e-6);
+
+        let mut silent = QcTimeline::new();
+        silent.push(frame(0.0));
+        assert!(silent.trim_silence(0.1).is_none());
+
+        let path = std::env::temp_dir().join(format!("cowcow-trim-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..32000 {
+            writer.write_sample((i / 2) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        trim::trim_wav_file(&path, 0.25, 0.5).unwrap();
+        let samples: Vec<i16> = hound::WavReader::open(&path)
+            .unwrap()
+            .into_samples::<i16>()
+            .map(Result::unwrap)
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(samples.len(), 8000);
+        assert_eq!(samples[0], 4000);
+    }
+
+    #[test]
+    #[cfg(feature = "wav")]
+    fn test_split_long_take() {
+        let frame = |speech_secs: f32| QcMetrics {
+            duration_secs: 0.1,
+            speech_secs,
+            ..Default::default()
+        };
+        // 2.5s of speech with a pause at 0.8-1.2s and a short one at 1.9s
+        let mut timeline = QcTimeline::new();
+        for index in 0..25 {
+            let pause = (8..12).contains(&index) || index == 19;
+            timeline.push(frame(if pause { 0.0 } else { 0.1 }));
+        }
+
+        // Short enough, or summary-only: left whole
+        assert_eq!(timeline.split(3.0).len(), 1);
+        assert_eq!(QcTimeline::summary_only().split(1.0).len(), 1);
+
+        // Cut in the middle of each part's longest late pause, or at the
+        // limit when there is none
+        let parts = timeline.split(1.0);
+        let lengths: Vec<usize> = parts.iter().map(|part| part.frames.len()).collect();
+        assert_eq!(lengths, vec![9, 10, 6]);
+        assert_eq!(parts[1].frames[0].start_secs, 0.0);
+        let parts = timeline.split(0.5);
+        assert!(parts.iter().all(|part| part.frames.len() <= 5));
+        assert_eq!(
+            parts.iter().map(|part| part.frames.len()).sum::<usize>(),
+            25
+        );
+
+        let path = std::env::temp_dir().join(format!("cowcow-split-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..40000 {
+            writer.write_sample((i / 4) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let part_paths: Vec<_> = (0..3)
+            .map(|i| path.with_extension(format!("{i}.wav")))
+            .collect();
+        trim::split_wav_file(&path, &[1.0, 0.5, 9.0], &part_paths).unwrap();
+        let read = |path: &std::path::Path| -> Vec<i16> {
+            hound::WavReader::open(path)
+                .unwrap()
+                .into_samples::<i16>()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let parts: Vec<Vec<i16>> = part_paths.iter().map(|part| read(part)).collect();
+        for part in part_paths.iter().chain([&path]) {
+            std::fs::remove_file(part).unwrap();
+        }
+        assert_eq!(parts[0].len(), 16000);
+        assert_eq!(parts[1].len(), 8000);
+        assert_eq!(parts[2].len(), 16000);
+        assert_eq!(parts[1][0], 4000);
+    }
 }
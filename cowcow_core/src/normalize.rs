@@ -0,0 +1,281 @@
+//! Level normalization of saved recordings
+
+use std::fmt;
+#[cfg(feature = "wav")]
+use std::fs;
+#[cfg(feature = "wav")]
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "wav")]
+use anyhow::{Context, Result};
+
+use crate::dsp::Biquad;
+pub use crate::policy::LOUDNESS_METRIC;
+
+/// Highest sample peak loudness normalization may raise a recording to
+pub const PEAK_CEILING_DBFS: f32 = -1.0;
+
+/// Loudness measurement block (ITU-R BS.1770)
+const BLOCK_MS: u32 = 400;
+
+/// Step between measurement blocks (75% overlap)
+const BLOCK_STEP_MS: u32 = 100;
+
+/// Blocks quieter than this never count towards loudness
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+
+/// Blocks this far below the ungated loudness are dropped as pauses
+const RELATIVE_GATE_LU: f32 = -10.0;
+
+/// Level a recording is normalized to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormalizeTarget {
+    /// Integrated loudness in LUFS (e.g. `-23 LUFS`, EBU R 128)
+    Loudness(f32),
+    /// Sample peak in dBFS (e.g. `-1 dBFS`)
+    Peak(f32),
+}
+
+impl FromStr for NormalizeTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        let (number, unit) = lower
+            .find(|c: char| c.is_ascii_alphabetic())
+            .map_or((lower.as_str(), ""), |split| lower.split_at(split));
+        let level: f32 = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid level in normalize target: {s}"))?;
+        if level > 0.0 {
+            return Err(format!("Normalize target must be at most 0: {s}"));
+        }
+
+        match unit.trim() {
+            "lufs" => Ok(NormalizeTarget::Loudness(level)),
+            "dbfs" => Ok(NormalizeTarget::Peak(level)),
+            _ => Err(format!(
+                "Normalize target must end in LUFS or dBFS (e.g. \"-23 LUFS\"): {s}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NormalizeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizeTarget::Loudness(lufs) => write!(f, "{lufs} LUFS"),
+            NormalizeTarget::Peak(dbfs) => write!(f, "{dbfs} dBFS"),
+        }
+    }
+}
+
+/// Largest absolute sample value in dBFS (`-inf` for digital silence)
+pub fn peak_dbfs(samples: &[f32]) -> f32 {
+    let peak = samples.iter().fold(0.0f32, |peak, &x| peak.max(x.abs()));
+    20.0 * peak.log10()
+}
+
+/// Integrated loudness as stored under [`LOUDNESS_METRIC`]: silence counts as
+/// the absolute gate rather than having no value
+pub fn loudness_metric(samples: &[f32], sample_rate: u32, channels: u16) -> f32 {
+    integrated_loudness(samples, sample_rate, channels).unwrap_or(ABSOLUTE_GATE_LUFS)
+}
+
+/// Gated integrated loudness of interleaved audio in LUFS (ITU-R BS.1770)
+///
+/// Every channel is K-weighted and counts with unit weight. Returns `None`
+/// when no block rises above the absolute gate.
+pub fn integrated_loudness(samples: &[f32], sample_rate: u32, channels: u16) -> Option<f32> {
+    let channels = channels.max(1) as usize;
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return None;
+    }
+
+    // K-weighted squares, summed over channels
+    let mut weighted = vec![0.0f32; frames];
+    for channel in 0..channels {
+        let [mut shelf, mut high_pass] = k_weighting(sample_rate);
+        for (frame, sum) in weighted.iter_mut().enumerate() {
+            let y = high_pass.process(shelf.process(samples[frame * channels + channel]));
+            *sum += y * y;
+        }
+    }
+
+    // Short recordings are measured as a single block
+    let block = ((sample_rate * BLOCK_MS / 1000) as usize).min(frames);
+    let step = ((sample_rate * BLOCK_STEP_MS / 1000) as usize).max(1);
+    let powers: Vec<f32> = (0..=frames - block)
+        .step_by(step)
+        .map(|start| weighted[start..start + block].iter().sum::<f32>() / block as f32)
+        .filter(|&power| block_loudness(power) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if powers.is_empty() {
+        return None;
+    }
+
+    let mean = |powers: &mut dyn Iterator<Item = f32>| {
+        let (sum, count) = powers.fold((0.0, 0usize), |(sum, count), p| (sum + p, count + 1));
+        sum / count.max(1) as f32
+    };
+    let relative_gate = block_loudness(mean(&mut powers.iter().copied())) + RELATIVE_GATE_LU;
+    let gated = mean(
+        &mut powers
+            .iter()
+            .copied()
+            .filter(|&power| block_loudness(power) > relative_gate),
+    );
+    Some(block_loudness(gated))
+}
+
+/// BS.1770 K-weighting (head shelf, then high-pass) at any sample rate,
+/// matching the standard's 48 kHz coefficients
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (
+        1_681.974_450_955_533,
+        3.999_843_853_973_347,
+        0.707_175_236_955_419_6,
+    );
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::from_coefficients(
+        [
+            ((vh + vb * k / q + k * k) / a0) as f32,
+            (2.0 * (k * k - vh) / a0) as f32,
+            ((vh - vb * k / q + k * k) / a0) as f32,
+        ],
+        [
+            1.0,
+            (2.0 * (k * k - 1.0) / a0) as f32,
+            ((1.0 - k / q + k * k) / a0) as f32,
+        ],
+    );
+
+    let (f0, q) = (38.135_470_876_024_44, 0.500_327_037_323_877_3);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::from_coefficients(
+        [1.0, -2.0, 1.0],
+        [
+            1.0,
+            (2.0 * (k * k - 1.0) / a0) as f32,
+            ((1.0 - k / q + k * k) / a0) as f32,
+        ],
+    );
+
+    [shelf, high_pass]
+}
+
+fn block_loudness(power: f32) -> f32 {
+    -0.691 + 10.0 * (power + 1e-12).log10()
+}
+
+/// Gain in dB that brings interleaved audio to `target`; 0 for silence
+///
+/// Loudness targets are capped so the peak stays at or below
+/// [`PEAK_CEILING_DBFS`] rather than clipping.
+pub fn normalization_gain_db(
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+    target: NormalizeTarget,
+) -> f32 {
+    let peak = peak_dbfs(samples);
+    if !peak.is_finite() {
+        return 0.0;
+    }
+
+    match target {
+        NormalizeTarget::Peak(dbfs) => dbfs - peak,
+        NormalizeTarget::Loudness(lufs) => {
+            match integrated_loudness(samples, sample_rate, channels) {
+                Some(loudness) => (lufs - loudness).min(PEAK_CEILING_DBFS - peak),
+                None => 0.0,
+            }
+        }
+    }
+}
+
+/// Normalize a WAV file in place and return the applied gain in dB
+#[cfg(feature = "wav")]
+pub fn normalize_wav_file<P: AsRef<Path>>(path: P, target: NormalizeTarget) -> Result<f32> {
+    let path = path.as_ref();
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+    let mut samples = crate::decode::read_wav_samples(reader)?;
+
+    let gain_db = normalization_gain_db(&samples, spec.sample_rate, spec.channels, target);
+    if gain_db.abs() < 0.01 {
+        return Ok(0.0);
+    }
+
+    let gain = 10f32.powf(gain_db / 20.0);
+    samples.iter_mut().for_each(|x| *x *= gain);
+
+    // Write beside the original and swap, so a crash never leaves half a file
+    let tmp_path = path.with_extension("wav.tmp");
+    let mut writer = hound::WavWriter::create(&tmp_path, spec)?;
+    for &sample in &samples {
+        crate::wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    Ok(gain_db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(
+            "-23 LUFS".parse::<NormalizeTarget>(),
+            Ok(NormalizeTarget::Loudness(-23.0))
+        );
+        assert_eq!(
+            "-1dBFS".parse::<NormalizeTarget>(),
+            Ok(NormalizeTarget::Peak(-1.0))
+        );
+        assert!("-23".parse::<NormalizeTarget>().is_err());
+        assert!("6 dBFS".parse::<NormalizeTarget>().is_err());
+
+        // A full-scale 1 kHz sine reads -3.01 LUFS (BS.1770 reference)
+        let sine = |amplitude: f32| -> Vec<f32> {
+            (0..48000)
+                .map(|i| {
+                    amplitude * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin()
+                })
+                .collect()
+        };
+        let loudness = integrated_loudness(&sine(1.0), 48000, 1).unwrap();
+        assert!((loudness + 3.01).abs() < 0.1, "loudness {loudness}");
+
+        // Quiet speech-level audio is raised to the target
+        let quiet = sine(0.01);
+        let gain = normalization_gain_db(&quiet, 48000, 1, NormalizeTarget::Loudness(-23.0));
+        let loudness = integrated_loudness(&quiet, 48000, 1).unwrap();
+        assert!((loudness + gain + 23.0).abs() < 0.01);
+
+        // Loud targets stop at the peak ceiling
+        let gain = normalization_gain_db(&quiet, 48000, 1, NormalizeTarget::Loudness(0.0));
+        assert!((peak_dbfs(&quiet) + gain - PEAK_CEILING_DBFS).abs() < 0.01);
+
+        let gain = normalization_gain_db(&quiet, 48000, 1, NormalizeTarget::Peak(-6.0));
+        assert!((gain - 34.0).abs() < 0.01);
+
+        assert_eq!(
+            normalization_gain_db(&[0.0; 4800], 48000, 1, NormalizeTarget::Peak(-1.0)),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Lossy Opus-in-Ogg recordings for sites where upload bandwidth is scarce
+//!
+//! Speech at 24 kbit/s is about a tenth of the size of 16-bit WAV, which is
+//! plenty for ASR training but not for TTS. Encoding and decoding need the
+//! `opus` feature (libopus); without it both fail with an error, while the
+//! Ogg header handling below is always available.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::decode::DecodedAudio;
+
+/// File extension of Opus recordings
+pub const OPUS_EXTENSION: &str = "opus";
+
+/// Bitrate used unless configured otherwise, in bits per second
+pub const DEFAULT_OPUS_BITRATE: u32 = 24_000;
+
+/// Bitrates libopus accepts, in bits per second
+pub const OPUS_BITRATE_RANGE: std::ops::RangeInclusive<u32> = 6_000..=510_000;
+
+/// Sample rates Opus encodes natively; other rates are resampled to 48 kHz
+pub const OPUS_SAMPLE_RATES: [u32; 5] = [8_000, 12_000, 16_000, 24_000, 48_000];
+
+/// Rate Ogg granule positions and the pre-skip count at
+const GRANULE_RATE: u32 = 48_000;
+
+/// Whether a recording is stored as Opus
+pub fn is_opus(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(OPUS_EXTENSION))
+}
+
+/// Identification header of an Ogg Opus stream (RFC 7845, section 5.1),
+/// for mono and stereo streams (channel mapping family 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusHead {
+    pub channels: u8,
+    /// Decoder output to discard at the start, in 48 kHz samples
+    pub pre_skip: u16,
+    /// Rate of the audio before encoding, for decoding back to it
+    pub input_sample_rate: u32,
+}
+
+impl OpusHead {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(19);
+        bytes.extend_from_slice(b"OpusHead");
+        bytes.push(1); // version
+        bytes.push(self.channels);
+        bytes.extend_from_slice(&self.pre_skip.to_le_bytes());
+        bytes.extend_from_slice(&self.input_sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        bytes.push(0); // channel mapping family
+        bytes
+    }
+
+    pub fn parse(packet: &[u8]) -> Result<Self> {
+        if packet.len() < 19 || &packet[..8] != b"OpusHead" {
+            bail!("Not an Ogg Opus stream");
+        }
+        if packet[8] >> 4 != 0 {
+            bail!("Unsupported Ogg Opus version {}", packet[8]);
+        }
+        let channels = packet[9];
+        if packet[18] != 0 || !(1..=2).contains(&channels) {
+            bail!("Only mono and stereo Opus recordings are supported");
+        }
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([packet[10], packet[11]]),
+            input_sample_rate: u32::from_le_bytes([packet[12], packet[13], packet[14], packet[15]]),
+        })
+    }
+
+    /// Rate to run the codec at for this stream
+    pub fn codec_rate(&self) -> u32 {
+        codec_rate(self.input_sample_rate)
+    }
+}
+
+/// Comment header naming the encoder, with no user comments
+#[cfg(feature = "opus")]
+fn opus_tags() -> Vec<u8> {
+    let vendor = concat!("cowcow ", env!("CARGO_PKG_VERSION"));
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"OpusTags");
+    bytes.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(vendor.as_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes
+}
+
+fn codec_rate(sample_rate: u32) -> u32 {
+    if OPUS_SAMPLE_RATES.contains(&sample_rate) {
+        sample_rate
+    } else {
+        GRANULE_RATE
+    }
+}
+
+/// Re-encode a mono or stereo WAV file as Opus at `bitrate` bits per
+/// second
+#[cfg(feature = "opus")]
+pub fn encode_wav_file(wav_path: &Path, opus_path: &Path, bitrate: u32) -> Result<()> {
+    use anyhow::Context;
+    use ogg::{PacketWriteEndInfo, PacketWriter};
+    use std::io::{BufWriter, Write};
+
+    let reader = hound::WavReader::open(wav_path)
+        .with_context(|| format!("Failed to open {}", wav_path.display()))?;
+    let spec = reader.spec();
+    if !(1..=2).contains(&spec.channels) {
+        bail!(
+            "Opus recordings must be mono or stereo: {}",
+            wav_path.display()
+        );
+    }
+    if !OPUS_BITRATE_RANGE.contains(&bitrate) {
+        bail!("Opus bitrate must be between 6000 and 510000 bits/s, not {bitrate}");
+    }
+
+    let samples = crate::decode::read_wav_samples(reader).context("Failed to read recording")?;
+    let rate = codec_rate(spec.sample_rate);
+    let samples = if rate == spec.sample_rate {
+        samples
+    } else {
+        let mut resampler = crate::resample::Resampler::new(spec.sample_rate, rate, spec.channels)?;
+        let mut resampled = resampler.process(&samples)?;
+        resampled.extend(resampler.flush()?);
+        resampled
+    };
+
+    let mut encoder = codec::Encoder::new(rate, spec.channels)?;
+    encoder.set_bitrate(bitrate)?;
+
+    let scale = (GRANULE_RATE / rate) as u64;
+    let lookahead = encoder.lookahead()? as usize;
+    let pre_skip = lookahead as u64 * scale;
+    let head = OpusHead {
+        channels: spec.channels as u8,
+        pre_skip: pre_skip as u16,
+        input_sample_rate: spec.sample_rate,
+    };
+
+    let file = std::fs::File::create(opus_path)
+        .with_context(|| format!("Failed to create {}", opus_path.display()))?;
+    let mut writer = PacketWriter::new(BufWriter::new(file));
+    let serial = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(1, |elapsed| elapsed.subsec_nanos());
+    // Each header sits alone on its own page
+    writer.write_packet(
+        head.to_bytes().into(),
+        serial,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    writer.write_packet(opus_tags().into(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+    // 20 ms frames, continued with silence until the decoder, which lags
+    // the input by the lookahead, has put out the last sample; the padding
+    // is cut off again by the final granule position
+    let channels = spec.channels as usize;
+    let frame_len = (rate / 50) as usize * channels;
+    let total_frames = (samples.len() / channels) as u64;
+    let frame_count = (samples.len() + lookahead * channels)
+        .div_ceil(frame_len)
+        .max(1);
+    let mut pcm = vec![0i16; frame_len];
+    let mut packet = vec![0u8; 4000];
+    for index in 0..frame_count {
+        let chunk = samples.get(index * frame_len..).unwrap_or_default();
+        let chunk = &chunk[..chunk.len().min(frame_len)];
+        pcm.fill(0);
+        for (out, &sample) in pcm.iter_mut().zip(chunk) {
+            *out = (sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+        }
+        let len = encoder.encode(&pcm, &mut packet)?;
+
+        // Samples decoded up to this packet, pre-skip included; the last
+        // packet's marks where the recording ends
+        let last = index + 1 == frame_count;
+        let granule = if last {
+            pre_skip + total_frames * scale
+        } else {
+            ((index + 1) * frame_len / channels) as u64 * scale
+        };
+        let end = if last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer.write_packet(packet[..len].to_vec().into(), serial, end, granule)?;
+    }
+
+    writer
+        .into_inner()
+        .flush()
+        .with_context(|| format!("Failed to write {}", opus_path.display()))
+}
+
+/// Re-encode a mono or stereo WAV file as Opus at `bitrate` bits per
+/// second
+#[cfg(not(feature = "opus"))]
+pub fn encode_wav_file(_wav_path: &Path, _opus_path: &Path, _bitrate: u32) -> Result<()> {
+    bail!("cowcow_core was built without the `opus` feature")
+}
+
+/// Decode an Opus recording back to its original sample rate (or 48 kHz
+/// for rates Opus does not support)
+#[cfg(feature = "opus")]
+pub fn decode_file(path: &Path) -> Result<DecodedAudio> {
+    use anyhow::Context;
+    use ogg::PacketReader;
+
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = PacketReader::new(std::io::BufReader::new(file));
+    let read_error =
+        |e: ogg::OggReadError| anyhow::anyhow!("Failed to read {}: {e}", path.display());
+    let head = OpusHead::parse(&reader.read_packet_expected().map_err(read_error)?.data)?;
+    reader.read_packet_expected().map_err(read_error)?; // OpusTags
+
+    let rate = head.codec_rate();
+    let channels = head.channels as usize;
+    let mut decoder = codec::Decoder::new(rate, head.channels as u16)?;
+
+    // Room for the longest Opus packet, 120 ms
+    let mut pcm = vec![0i16; rate as usize * 120 / 1000 * channels];
+    let mut decoded = Vec::new();
+    let mut last_granule = 0;
+    while let Some(packet) = reader.read_packet().map_err(read_error)? {
+        let frames = decoder.decode(&packet.data, &mut pcm)?;
+        decoded.extend(pcm[..frames * channels].iter().map(|&s| s as f32 / 32768.0));
+        last_granule = packet.absgp_page();
+    }
+
+    // Drop the encoder delay and the padding of the last frame
+    let scale = (GRANULE_RATE / rate) as u64;
+    let skip = (head.pre_skip as u64 / scale) as usize * channels;
+    let frames = (last_granule.saturating_sub(head.pre_skip as u64) / scale) as usize;
+    let end = (skip + frames * channels).min(decoded.len());
+    let samples = decoded.get(skip..end).unwrap_or_default().to_vec();
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: rate,
+        channels: head.channels as u16,
+    })
+}
+
+/// Decode an Opus recording back to its original sample rate (or 48 kHz
+/// for rates Opus does not support)
+#[cfg(not(feature = "opus"))]
+pub fn decode_file(_path: &Path) -> Result<DecodedAudio> {
+    bail!("cowcow_core was built without the `opus` feature")
+}
+
+/// Safe ownership of libopus encoder and decoder states
+#[cfg(feature = "opus")]
+mod codec {
+    use anyhow::{bail, Result};
+    use audiopus_sys as sys;
+    use std::ffi::CStr;
+    use std::os::raw::c_int;
+
+    fn check(code: c_int, what: &str) -> Result<c_int> {
+        if code < 0 {
+            // SAFETY: opus_strerror returns a static string for any code
+            let message = unsafe { CStr::from_ptr(sys::opus_strerror(code)) };
+            bail!("Opus {what} error: {}", message.to_string_lossy());
+        }
+        Ok(code)
+    }
+
+    pub struct Encoder {
+        state: *mut sys::OpusEncoder,
+        channels: usize,
+    }
+
+    impl Encoder {
+        /// A speech encoder at `rate` Hz, which must be one Opus supports
+        pub fn new(rate: u32, channels: u16) -> Result<Self> {
+            let mut error = 0;
+            // SAFETY: libopus validates the rate and channel count and
+            // reports failures through `error`
+            let state = unsafe {
+                sys::opus_encoder_create(
+                    rate as i32,
+                    channels as c_int,
+                    sys::OPUS_APPLICATION_VOIP,
+                    &mut error,
+                )
+            };
+            check(error, "encoder")?;
+            if state.is_null() {
+                bail!("Opus encoder error: allocation failed");
+            }
+            Ok(Self {
+                state,
+                channels: channels as usize,
+            })
+        }
+
+        pub fn set_bitrate(&mut self, bits_per_sec: u32) -> Result<()> {
+            // SAFETY: the request takes one opus_int32 argument
+            let code = unsafe {
+                sys::opus_encoder_ctl(
+                    self.state,
+                    sys::OPUS_SET_BITRATE_REQUEST as c_int,
+                    bits_per_sec as i32,
+                )
+            };
+            check(code, "encoder").map(drop)
+        }
+
+        /// Samples per channel the decoder output lags behind the input
+        pub fn lookahead(&mut self) -> Result<u32> {
+            let mut lookahead: i32 = 0;
+            // SAFETY: the request writes one opus_int32 through the pointer
+            let code = unsafe {
+                sys::opus_encoder_ctl(
+                    self.state,
+                    sys::OPUS_GET_LOOKAHEAD_REQUEST as c_int,
+                    &mut lookahead as *mut i32,
+                )
+            };
+            check(code, "encoder")?;
+            Ok(lookahead.max(0) as u32)
+        }
+
+        /// Encode one frame of interleaved samples into `packet`; returns
+        /// the packet length
+        pub fn encode(&mut self, pcm: &[i16], packet: &mut [u8]) -> Result<usize> {
+            let frame_size = (pcm.len() / self.channels) as c_int;
+            // SAFETY: `pcm` holds `frame_size` frames and `packet` is
+            // writable for its whole length
+            let len = unsafe {
+                sys::opus_encode(
+                    self.state,
+                    pcm.as_ptr(),
+                    frame_size,
+                    packet.as_mut_ptr(),
+                    packet.len() as i32,
+                )
+            };
+            check(len, "encoder").map(|len| len as usize)
+        }
+    }
+
+    impl Drop for Encoder {
+        fn drop(&mut self) {
+            // SAFETY: the state came from opus_encoder_create
+            unsafe { sys::opus_encoder_destroy(self.state) }
+        }
+    }
+
+    pub struct Decoder {
+        state: *mut sys::OpusDecoder,
+        channels: usize,
+    }
+
+    impl Decoder {
+        pub fn new(rate: u32, channels: u16) -> Result<Self> {
+            let mut error = 0;
+            // SAFETY: as for the encoder
+            let state =
+                unsafe { sys::opus_decoder_create(rate as i32, channels as c_int, &mut error) };
+            check(error, "decoder")?;
+            if state.is_null() {
+                bail!("Opus decoder error: allocation failed");
+            }
+            Ok(Self {
+                state,
+                channels: channels as usize,
+            })
+        }
+
+        /// Decode one packet into `pcm`; returns the frames decoded
+        pub fn decode(&mut self, packet: &[u8], pcm: &mut [i16]) -> Result<usize> {
+            let frame_size = (pcm.len() / self.channels) as c_int;
+            // SAFETY: `packet` is readable and `pcm` writable for
+            // `frame_size` frames
+            let frames = unsafe {
+                sys::opus_decode(
+                    self.state,
+                    packet.as_ptr(),
+                    packet.len() as i32,
+                    pcm.as_mut_ptr(),
+                    frame_size,
+                    0,
+                )
+            };
+            check(frames, "decoder").map(|frames| frames as usize)
+        }
+    }
+
+    impl Drop for Decoder {
+        fn drop(&mut self) {
+            // SAFETY: the state came from opus_decoder_create
+            unsafe { sys::opus_decoder_destroy(self.state) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opus_head() {
+        let head = OpusHead {
+            channels: 1,
+            pre_skip: 312 * 3,
+            input_sample_rate: 16_000,
+        };
+        let bytes = head.to_bytes();
+        assert_eq!(bytes.len(), 19);
+        assert_eq!(OpusHead::parse(&bytes).unwrap(), head);
+        assert_eq!(head.codec_rate(), 16_000);
+
+        let resampled = OpusHead {
+            input_sample_rate: 44_100,
+            ..head
+        };
+        assert_eq!(resampled.codec_rate(), 48_000);
+        assert!(OpusHead::parse(b"OggS not a header").is_err());
+        assert!(is_opus(Path::new("take.OPUS")));
+        assert!(!is_opus(Path::new("take.ogg")));
+    }
+
+    #[cfg(feature = "opus")]
+    #[test]
+    fn test_opus_round_trip() {
+        let dir = std::env::temp_dir().join(format!("cowcow-opus-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav_path = dir.join("take.wav");
+        let opus_path = dir.join("take.opus");
+
+        // One second of a 220 Hz tone at 16 kHz
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let tone: Vec<f32> = (0..16_000)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 16_000.0).sin())
+            .collect();
+        let mut writer = hound::WavWriter::create(&wav_path, spec).unwrap();
+        for &sample in &tone {
+            writer.write_sample((sample * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        encode_wav_file(&wav_path, &opus_path, DEFAULT_OPUS_BITRATE).unwrap();
+        assert!(
+            std::fs::metadata(&opus_path).unwrap().len()
+                < std::fs::metadata(&wav_path).unwrap().len() / 4
+        );
+        let decoded = decode_file(&opus_path).unwrap();
+        assert_eq!(decoded.sample_rate, 16_000);
+        assert_eq!(decoded.channels, 1);
+        // The encoder delay and the last frame's padding are cut off again
+        assert_eq!(decoded.samples.len(), tone.len());
+
+        // Lossy, but close to the original; SILK's internal resampling may
+        // shift it by a fraction of a millisecond
+        let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let middle = 1_000..15_000;
+        let correlation = (-16..=16)
+            .map(|lag: isize| {
+                let shifted = &decoded.samples
+                    [(middle.start as isize + lag) as usize..(middle.end as isize + lag) as usize];
+                let original = &tone[middle.clone()];
+                let dot: f32 = original.iter().zip(shifted).map(|(a, b)| a * b).sum();
+                dot / (energy(original) * energy(shifted))
+            })
+            .fold(f32::MIN, f32::max);
+        assert!(correlation > 0.99, "correlation {correlation}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+//! Robust outlier detection over a collection of QC metric values
+//!
+//! Uses the modified z-score of Iglewicz and Hoaglin, built on the median
+//! and the median absolute deviation (MAD), so a handful of bad recordings
+//! cannot drag the baseline towards themselves the way they would a mean
+//! and standard deviation.
+
+/// Modified z-score above which a value is an outlier
+pub const OUTLIER_Z: f32 = 3.5;
+
+/// Fewest values needed before anything is called an outlier
+pub const MIN_SAMPLES: usize = 8;
+
+/// Scales the MAD to the standard deviation of normally distributed data
+const MAD_SCALE: f32 = 0.6745;
+
+/// Scales the mean absolute deviation likewise, used when the MAD is zero
+const MEAN_AD_SCALE: f32 = 0.7979;
+
+/// Median of the values (`None` when empty)
+pub fn median(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let mid = sorted.len() / 2;
+    Some(if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    })
+}
+
+/// Modified z-score of each value
+///
+/// When more than half the values are identical the MAD is zero, and the
+/// mean absolute deviation stands in; if that is zero too, every score is 0.
+pub fn modified_z_scores(values: &[f32]) -> Vec<f32> {
+    let Some(center) = median(values) else {
+        return Vec::new();
+    };
+    let deviations: Vec<f32> = values.iter().map(|x| (x - center).abs()).collect();
+    let mad = median(&deviations).unwrap_or(0.0);
+
+    let scale = if mad > f32::EPSILON {
+        MAD_SCALE / mad
+    } else {
+        let mean_ad = deviations.iter().sum::<f32>() / deviations.len() as f32;
+        if mean_ad <= f32::EPSILON {
+            return vec![0.0; values.len()];
+        }
+        MEAN_AD_SCALE / mean_ad
+    };
+    values.iter().map(|x| (x - center) * scale).collect()
+}
+
+/// Which values are outliers; none are until there are [`MIN_SAMPLES`]
+pub fn outliers(values: &[f32]) -> Vec<bool> {
+    if values.len() < MIN_SAMPLES {
+        return vec![false; values.len()];
+    }
+    modified_z_scores(values)
+        .into_iter()
+        .map(|z| z.abs() > OUTLIER_Z)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outliers() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), Some(2.5));
+        assert_eq!(median(&[]), None);
+
+        // One device's SNR sits around 25 dB; a 5 dB take stands out even
+        // though an absolute threshold of, say, 3 dB would pass it
+        let snr = [24.0, 25.5, 26.0, 25.0, 23.5, 24.5, 25.0, 26.5, 5.0];
+        let flagged = outliers(&snr);
+        assert_eq!(flagged.iter().filter(|&&f| f).count(), 1);
+        assert!(flagged[8]);
+
+        // Too few recordings to judge
+        assert!(outliers(&snr[4..]).iter().all(|&f| !f));
+
+        // Mostly identical values fall back to the mean absolute deviation
+        let clipping = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1];
+        assert!(outliers(&clipping)[8]);
+        assert!(outliers(&[0.0; 10]).iter().all(|&f| !f));
+    }
+}
@@ -0,0 +1,151 @@
+//! Fundamental frequency (F0) tracking for pitch statistics
+
+use crate::dsp::Biquad;
+
+/// Pitch is tracked on audio decimated to this rate
+const ANALYSIS_RATE: u32 = 8000;
+
+/// Anti-aliasing cutoff before decimation; F0 lies well below it
+const LOW_PASS_HZ: f32 = 1000.0;
+
+/// Analysis window, long enough for two periods of the lowest F0
+const WINDOW_MS: u32 = 40;
+
+/// Step between analysis windows
+const HOP_MS: u32 = 10;
+
+/// Lowest F0 searched for
+pub const MIN_F0_HZ: f32 = 60.0;
+
+/// Highest F0 searched for
+pub const MAX_F0_HZ: f32 = 500.0;
+
+/// Normalized difference below which a period candidate is accepted (YIN)
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// Windows quieter than this are unvoiced without further analysis
+const MIN_LEVEL_DB: f32 = -50.0;
+
+/// Pitch analysis of the windows completed within one call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PitchFrames {
+    /// Number of windows analyzed
+    pub total: usize,
+    /// F0 in Hz of each voiced window
+    pub f0_hz: Vec<f32>,
+}
+
+/// Streaming YIN pitch tracker
+///
+/// Input is low-passed and decimated to [`ANALYSIS_RATE`], then every
+/// [`HOP_MS`] a [`WINDOW_MS`] window is searched for the shortest period
+/// whose cumulative mean normalized difference dips below
+/// [`YIN_THRESHOLD`]. Windows without such a period (silence, noise,
+/// whispering) are unvoiced.
+#[derive(Debug, Clone)]
+pub struct PitchTracker {
+    decimation: usize,
+    phase: usize,
+    filters: [Biquad; 2],
+    window: usize,
+    hop: usize,
+    min_lag: usize,
+    max_lag: usize,
+    history: Vec<f32>,
+}
+
+impl PitchTracker {
+    pub fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate.max(ANALYSIS_RATE);
+        let low_pass = || Biquad::low_pass(rate, LOW_PASS_HZ, Biquad::BUTTERWORTH_Q);
+        Self {
+            decimation: (rate / ANALYSIS_RATE).max(1) as usize,
+            phase: 0,
+            filters: [low_pass(), low_pass()],
+            window: (ANALYSIS_RATE * WINDOW_MS / 1000) as usize,
+            hop: (ANALYSIS_RATE * HOP_MS / 1000) as usize,
+            min_lag: (ANALYSIS_RATE as f32 / MAX_F0_HZ).floor() as usize,
+            max_lag: (ANALYSIS_RATE as f32 / MIN_F0_HZ).ceil() as usize,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0;
+        self.filters.iter_mut().for_each(Biquad::reset);
+        self.history.clear();
+    }
+
+    /// Feed mono samples and return the windows completed within them
+    pub fn process(&mut self, samples: &[f32]) -> PitchFrames {
+        for &x in samples {
+            let y = self
+                .filters
+                .iter_mut()
+                .fold(x, |y, filter| filter.process(y));
+            if self.phase == 0 {
+                self.history.push(y);
+            }
+            self.phase = (self.phase + 1) % self.decimation;
+        }
+
+        let mut frames = PitchFrames::default();
+        let mut start = 0;
+        while start + self.window <= self.history.len() {
+            frames.total += 1;
+            if let Some(f0) = self.estimate(&self.history[start..start + self.window]) {
+                frames.f0_hz.push(f0);
+            }
+            start += self.hop;
+        }
+        self.history.drain(..start);
+        frames
+    }
+
+    /// F0 of one window, `None` when unvoiced
+    fn estimate(&self, window: &[f32]) -> Option<f32> {
+        let energy = window.iter().map(|&x| x * x).sum::<f32>() / window.len() as f32;
+        if 10.0 * (energy + 1e-10).log10() < MIN_LEVEL_DB {
+            return None;
+        }
+
+        // Difference function over the part of the window every lag can reach
+        let span = window.len() - self.max_lag;
+        let diff: Vec<f32> = (0..=self.max_lag)
+            .map(|lag| {
+                window[..span]
+                    .iter()
+                    .zip(&window[lag..lag + span])
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum()
+            })
+            .collect();
+
+        // Cumulative mean normalization
+        let mut cmnd = vec![1.0f32; diff.len()];
+        let mut running = 0.0;
+        for lag in 1..diff.len() {
+            running += diff[lag];
+            cmnd[lag] = if running > 0.0 {
+                diff[lag] * lag as f32 / running
+            } else {
+                1.0
+            };
+        }
+
+        let mut lag = (self.min_lag..self.max_lag).find(|&lag| cmnd[lag] < YIN_THRESHOLD)?;
+        while lag + 1 < self.max_lag && cmnd[lag + 1] < cmnd[lag] {
+            lag += 1;
+        }
+
+        // Parabolic interpolation around the dip
+        let (prev, here, next) = (cmnd[lag - 1], cmnd[lag], cmnd[lag + 1]);
+        let curvature = prev - 2.0 * here + next;
+        let offset = if curvature.abs() > 1e-6 {
+            (0.5 * (prev - next) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        Some(ANALYSIS_RATE as f32 / (lag as f32 + offset))
+    }
+}
@@ -0,0 +1,315 @@
+//! Named QC rules evaluated into a structured pass/fail report
+
+use serde::{Deserialize, Serialize};
+
+use crate::prompt_analysis::SPEAKING_RATE_METRIC;
+use crate::QcMetrics;
+
+/// Metric key of a recording's integrated loudness, stored with its QC
+/// metrics where QC needs it (see `normalize::loudness_metric`)
+pub const LOUDNESS_METRIC: &str = "loudness_lufs";
+
+/// Metric keys a rule can check: the `QcMetrics` fields, plus the loudness
+/// and speaking rate stored alongside them
+pub fn known_metrics() -> Vec<String> {
+    let mut metrics: Vec<String> = match serde_json::to_value(QcMetrics::default()) {
+        Ok(serde_json::Value::Object(fields)) => fields.into_iter().map(|(key, _)| key).collect(),
+        _ => Vec::new(),
+    };
+    metrics.extend([LOUDNESS_METRIC, SPEAKING_RATE_METRIC].map(String::from));
+    metrics
+}
+
+/// How a metric is compared against a rule's threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    /// The metric must be at least the threshold
+    Min,
+    /// The metric must be at most the threshold
+    Max,
+    /// The metric's magnitude must be at most the threshold
+    MaxAbs,
+}
+
+/// A single named threshold on one `QcMetrics` field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcRule {
+    pub name: String,
+    /// Field name as serialized in `QcMetrics` (e.g. `snr_db`)
+    pub metric: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+}
+
+impl QcRule {
+    pub fn new(name: &str, metric: &str, comparison: Comparison, threshold: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            metric: metric.to_string(),
+            comparison,
+            threshold,
+        }
+    }
+
+    pub fn min(name: &str, metric: &str, threshold: f64) -> Self {
+        Self::new(name, metric, Comparison::Min, threshold)
+    }
+
+    pub fn max(name: &str, metric: &str, threshold: f64) -> Self {
+        Self::new(name, metric, Comparison::Max, threshold)
+    }
+
+    pub fn max_abs(name: &str, metric: &str, threshold: f64) -> Self {
+        Self::new(name, metric, Comparison::MaxAbs, threshold)
+    }
+
+    fn passes(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Min => value >= self.threshold,
+            Comparison::Max => value <= self.threshold,
+            Comparison::MaxAbs => value.abs() <= self.threshold,
+        }
+    }
+}
+
+/// Outcome of one rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleStatus {
+    Pass,
+    Fail,
+    /// The metric was not recorded (e.g. a recording made before it existed)
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleResult {
+    pub rule: String,
+    pub metric: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    pub value: Option<f64>,
+    pub status: RuleStatus,
+}
+
+impl RuleResult {
+    /// Short human-readable explanation, e.g. `min_snr: snr_db 12.30 < 20`
+    pub fn describe(&self) -> String {
+        let op = match (self.status, self.comparison) {
+            (RuleStatus::Fail, Comparison::Min) => "<",
+            (RuleStatus::Fail, _) => ">",
+            (_, Comparison::Min) => ">=",
+            (_, _) => "<=",
+        };
+        match self.value {
+            Some(value) => format!(
+                "{}: {} {:.2} {} {}",
+                self.rule, self.metric, value, op, self.threshold
+            ),
+            None => format!("{}: {} not recorded", self.rule, self.metric),
+        }
+    }
+}
+
+/// Result of evaluating a [`QcPolicy`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QcReport {
+    pub passed: bool,
+    pub results: Vec<RuleResult>,
+}
+
+impl QcReport {
+    pub fn failures(&self) -> impl Iterator<Item = &RuleResult> {
+        self.results
+            .iter()
+            .filter(|result| result.status == RuleStatus::Fail)
+    }
+
+    /// Failed rules joined into one line, empty when the report passed
+    pub fn failure_summary(&self) -> String {
+        self.failures()
+            .map(RuleResult::describe)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Names accepted by [`QcPolicy::preset`]
+pub const PRESETS: [&str; 4] = ["asr-training", "tts", "lenient", "music"];
+
+/// Ordered set of named QC rules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QcPolicy {
+    pub rules: Vec<QcRule>,
+}
+
+impl QcPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A ready-made policy for a common dataset purpose
+    ///
+    /// - `asr-training`: speech recognition tolerates some background noise
+    ///   but not clipping, dropouts or empty takes
+    /// - `tts`: speech synthesis needs studio-like audio: high SNR, little
+    ///   reverb and hum, and no clipping or glitches
+    /// - `lenient`: only rejects recordings that are unusable
+    /// - `music`: singing and instruments, which VAD-based rules reject;
+    ///   only loudness and clipping are checked
+    pub fn preset(name: &str) -> Option<Self> {
+        let policy = match name {
+            "asr-training" => Self::new()
+                .with_rule(QcRule::min("min_snr", "snr_db", 15.0))
+                .with_rule(QcRule::max("max_clipping", "clipping_pct", 1.0))
+                .with_rule(QcRule::min("min_vad_ratio", "vad_ratio", 40.0))
+                .with_rule(QcRule::min("min_speech", "speech_secs", 1.0))
+                .with_rule(QcRule::max_abs("max_dc_offset", "dc_offset", 0.05))
+                .with_rule(QcRule::max("max_dropouts", "dropout_count", 0.0))
+                .with_rule(QcRule::max("max_glitch", "glitch_pct", 0.1)),
+            "tts" => Self::new()
+                .with_rule(QcRule::min("min_snr", "snr_db", 30.0))
+                .with_rule(QcRule::max("max_clipping", "clipping_pct", 0.1))
+                .with_rule(QcRule::min("min_vad_ratio", "vad_ratio", 60.0))
+                .with_rule(QcRule::min("min_speech", "speech_secs", 1.0))
+                .with_rule(QcRule::max_abs("max_dc_offset", "dc_offset", 0.01))
+                .with_rule(QcRule::max("max_rumble", "rumble_db", -30.0))
+                .with_rule(QcRule::max("max_hum", "hum_db", -40.0))
+                .with_rule(QcRule::max("max_dropouts", "dropout_count", 0.0))
+                .with_rule(QcRule::max("max_glitch", "glitch_pct", 0.01))
+                .with_rule(QcRule::max("max_reverb", "reverb_rt60_secs", 0.5)),
+            "lenient" => Self::new()
+                .with_rule(QcRule::min("min_snr", "snr_db", 5.0))
+                .with_rule(QcRule::max("max_clipping", "clipping_pct", 5.0))
+                .with_rule(QcRule::min("min_speech", "speech_secs", 0.5)),
+            "music" => Self::new()
+                .with_rule(QcRule::min("min_loudness", LOUDNESS_METRIC, -40.0))
+                .with_rule(QcRule::max("max_clipping", "clipping_pct", 1.0)),
+            _ => return None,
+        };
+        Some(policy)
+    }
+
+    /// Change the threshold of the rule called `name`; returns whether it
+    /// exists
+    pub fn set_threshold(&mut self, name: &str, threshold: f64) -> bool {
+        match self.rules.iter_mut().find(|rule| rule.name == name) {
+            Some(rule) => {
+                rule.threshold = threshold;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add a rule, replacing any existing rule with the same name
+    pub fn with_rule(mut self, rule: QcRule) -> Self {
+        self.set_rule(rule);
+        self
+    }
+
+    pub fn set_rule(&mut self, rule: QcRule) {
+        match self.rules.iter_mut().find(|r| r.name == rule.name) {
+            Some(existing) => *existing = rule,
+            None => self.rules.push(rule),
+        }
+    }
+
+    /// Remove a rule by name; returns whether it existed
+    pub fn remove_rule(&mut self, name: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|rule| rule.name != name);
+        self.rules.len() != before
+    }
+
+    pub fn evaluate(&self, metrics: &QcMetrics) -> QcReport {
+        self.evaluate_json(&serde_json::to_value(metrics).unwrap_or_default())
+    }
+
+    /// Evaluate stored metrics; rules on metrics the JSON lacks are skipped
+    /// rather than failed
+    pub fn evaluate_json(&self, metrics: &serde_json::Value) -> QcReport {
+        let results: Vec<RuleResult> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let value = metrics.get(&rule.metric).and_then(|v| v.as_f64());
+                let status = match value {
+                    Some(value) if rule.passes(value) => RuleStatus::Pass,
+                    Some(_) => RuleStatus::Fail,
+                    None => RuleStatus::Skipped,
+                };
+                RuleResult {
+                    rule: rule.name.clone(),
+                    metric: rule.metric.clone(),
+                    comparison: rule.comparison,
+                    threshold: rule.threshold,
+                    value,
+                    status,
+                }
+            })
+            .collect();
+
+        QcReport {
+            passed: results.iter().all(|r| r.status != RuleStatus::Fail),
+            results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_check_known_metrics() {
+        let known = known_metrics();
+        assert!(known.contains(&"snr_db".to_string()));
+        assert!(known.contains(&LOUDNESS_METRIC.to_string()));
+        for name in ["asr-training", "tts", "lenient", "music"] {
+            for rule in QcPolicy::preset(name).unwrap().rules {
+                assert!(known.contains(&rule.metric), "{name}: {}", rule.metric);
+            }
+        }
+    }
+
+    #[test]
+    fn test_policy_report() {
+        let policy = QcPolicy::new()
+            .with_rule(QcRule::min("min_snr", "snr_db", 20.0))
+            .with_rule(QcRule::max("max_clipping", "clipping_pct", 1.0))
+            .with_rule(QcRule::max_abs("max_dc_offset", "dc_offset", 0.05))
+            .with_rule(QcRule::min("min_snr", "snr_db", 10.0));
+        assert_eq!(policy.rules.len(), 3);
+
+        let metrics = QcMetrics {
+            snr_db: 15.0,
+            clipping_pct: 2.5,
+            dc_offset: -0.01,
+            ..Default::default()
+        };
+        let report = policy.evaluate(&metrics);
+        assert!(!report.passed);
+        let failed: Vec<&str> = report.failures().map(|r| r.rule.as_str()).collect();
+        assert_eq!(failed, vec!["max_clipping"]);
+        assert_eq!(
+            report.failure_summary(),
+            "max_clipping: clipping_pct 2.50 > 1"
+        );
+
+        // Metrics missing from older recordings are skipped, not failed
+        let report = policy.evaluate_json(&serde_json::json!({ "snr_db": 30.0 }));
+        assert!(report.passed);
+        assert_eq!(report.results[1].status, RuleStatus::Skipped);
+
+        for name in PRESETS {
+            assert!(QcPolicy::preset(name).is_some(), "{name}");
+        }
+        assert!(QcPolicy::preset("unknown").is_none());
+        let mut asr = QcPolicy::preset("asr-training").unwrap();
+        assert!(asr.set_threshold("min_snr", 25.0));
+        assert!(!asr.set_threshold("no_such_rule", 1.0));
+        assert!(!asr.evaluate(&metrics).passed);
+    }
+}
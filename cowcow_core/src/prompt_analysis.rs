@@ -0,0 +1,150 @@
+//! Checks of a recording against the prompt text it was read from
+
+use serde::{Deserialize, Serialize};
+
+use crate::QcMetrics;
+
+/// Name of the speaking rate metric added to serialized QC metrics
+pub const SPEAKING_RATE_METRIC: &str = "speaking_rate";
+
+/// Vowels of Latin, Greek and Cyrillic based orthographies (lowercase)
+const VOWELS: &str = "aeiouyàáâãäåāăąèéêëēĕėęěìíîïĩīĭįıòóôõöøōŏőùúûüũūŭůűųýÿɛɔəɨʉ\
+                      αεηιουωάέήίόύώϊϋ\
+                      аеёиоуыэюяіїє";
+
+/// Prompt-derived metrics for one recording
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PromptAnalysis {
+    /// Estimated syllables in the prompt
+    pub prompt_syllables: u32,
+    /// Prompt syllables per second of detected speech
+    pub speaking_rate: f32,
+}
+
+impl PromptAnalysis {
+    /// Speaking rate of a recording of `prompt`; `None` when the prompt has
+    /// no countable syllables or no speech was detected
+    pub fn new(prompt: &str, metrics: &QcMetrics) -> Option<Self> {
+        let syllables = count_syllables(prompt);
+        if syllables == 0 || metrics.speech_secs <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            prompt_syllables: syllables as u32,
+            speaking_rate: syllables as f32 / metrics.speech_secs,
+        })
+    }
+
+    /// Serialized QC metrics with the prompt metrics added, ready for
+    /// [`crate::policy::QcPolicy::evaluate_json`]
+    pub fn with_metrics(&self, metrics: &QcMetrics) -> serde_json::Value {
+        let mut value = serde_json::to_value(metrics).unwrap_or_default();
+        if let (Some(object), Ok(serde_json::Value::Object(extra))) =
+            (value.as_object_mut(), serde_json::to_value(self))
+        {
+            object.extend(extra);
+        }
+        value
+    }
+}
+
+/// Approximate syllable count of prompt text
+///
+/// Alphabetic scripts count one syllable per run of vowels, and at least one
+/// per word (covering syllabic consonants such as Swahili "m" in "mtu").
+/// Syllabaries and syllable-block scripts (Ethiopic, kana, Hangul, CJK,
+/// Cherokee, Canadian syllabics, Vai) count one per character. Other scripts
+/// fall back to one syllable per three letters.
+pub fn count_syllables(text: &str) -> usize {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(word_syllables)
+        .sum()
+}
+
+fn word_syllables(word: &str) -> usize {
+    let mut syllables = 0;
+    let mut vowel_run = false;
+    let mut other_letters = 0usize;
+    let mut has_letters = false;
+
+    for c in word.chars().flat_map(char::to_lowercase) {
+        if !c.is_alphabetic() {
+            vowel_run = false;
+            continue;
+        }
+        has_letters = true;
+
+        if is_syllabic(c) {
+            syllables += 1;
+            vowel_run = false;
+        } else if VOWELS.contains(c) {
+            if !vowel_run {
+                syllables += 1;
+            }
+            vowel_run = true;
+        } else {
+            vowel_run = false;
+            if !c.is_ascii() && !is_alphabetic_consonant(c) {
+                other_letters += 1;
+            }
+        }
+    }
+
+    syllables += other_letters.div_ceil(3);
+    if has_letters {
+        syllables.max(1)
+    } else {
+        0
+    }
+}
+
+/// Characters that each stand for a whole syllable
+fn is_syllabic(c: char) -> bool {
+    matches!(c as u32,
+        0x1200..=0x137F // Ethiopic
+        | 0x13A0..=0x13FF // Cherokee
+        | 0x1400..=0x167F // Unified Canadian Aboriginal Syllabics
+        | 0x3040..=0x30FF // Hiragana and Katakana
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xA500..=0xA63F // Vai
+        | 0xAC00..=0xD7AF // Hangul syllables
+    )
+}
+
+/// Consonants of the scripts whose vowels are listed in [`VOWELS`]
+fn is_alphabetic_consonant(c: char) -> bool {
+    matches!(c as u32,
+        0x00C0..=0x024F // Latin-1 Supplement and Latin Extended
+        | 0x0250..=0x02AF // IPA Extensions
+        | 0x0370..=0x03FF // Greek
+        | 0x0400..=0x04FF // Cyrillic
+        | 0x1E00..=0x1EFF // Latin Extended Additional
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speaking_rate() {
+        assert_eq!(count_syllables("Habari ya asubuhi"), 8);
+        assert_eq!(count_syllables("The quick brown fox."), 4);
+        assert_eq!(count_syllables("Mtu"), 1);
+        assert_eq!(count_syllables("ሰላም"), 3);
+        assert_eq!(count_syllables("  42 "), 0);
+
+        let metrics = QcMetrics {
+            speech_secs: 2.0,
+            ..Default::default()
+        };
+        let analysis = PromptAnalysis::new("Habari ya asubuhi", &metrics).unwrap();
+        assert_eq!(analysis.speaking_rate, 4.0);
+
+        let json = analysis.with_metrics(&metrics);
+        assert_eq!(json[SPEAKING_RATE_METRIC], 4.0);
+        assert_eq!(json["speech_secs"], 2.0);
+
+        assert!(PromptAnalysis::new("Habari", &QcMetrics::default()).is_none());
+    }
+}
@@ -0,0 +1,210 @@
+//! Sample rate conversion for devices and files at arbitrary rates
+
+use rubato::{
+    Resampler as _, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+use crate::AudioError;
+
+/// Input frames handed to the sinc resampler per call
+const CHUNK_FRAMES: usize = 1024;
+
+/// Streaming sample rate converter for interleaved audio
+///
+/// Accepts input in arbitrarily sized pieces and returns whatever output is
+/// ready; [`Resampler::flush`] drains the tail at the end of a stream so the
+/// output length matches the input duration.
+///
+/// Output is not delayed: rubato's `SincFixedIn` starts with its sinc window
+/// centred on the first input frame, so the filter delay reported by
+/// `output_delay()` is already taken out and trimming it again would shift
+/// takes early. The tests check events stay in place.
+pub struct Resampler {
+    /// `None` when input and output rates match
+    inner: Option<SincFixedIn<f32>>,
+    channels: usize,
+    ratio: f64,
+    /// De-interleaved input waiting for a full chunk
+    pending: Vec<Vec<f32>>,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32, channels: u16) -> Result<Self, AudioError> {
+        let channels = channels.max(1) as usize;
+        let ratio = to_rate as f64 / from_rate as f64;
+
+        let inner = if from_rate == to_rate {
+            None
+        } else {
+            let parameters = SincInterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.95,
+                oversampling_factor: 128,
+                interpolation: SincInterpolationType::Linear,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            Some(
+                SincFixedIn::new(ratio, 1.0, parameters, CHUNK_FRAMES, channels)
+                    .map_err(|e| AudioError::Resample(e.to_string()))?,
+            )
+        };
+
+        Ok(Self {
+            inner,
+            channels,
+            ratio,
+            pending: vec![Vec::with_capacity(CHUNK_FRAMES); channels],
+            input_frames: 0,
+            output_frames: 0,
+        })
+    }
+
+    /// Whether samples pass through unchanged
+    pub fn is_passthrough(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    /// Input frames taken in that have not come out yet; output lags the
+    /// input by this much
+    pub fn buffered_frames(&self) -> u64 {
+        let output_as_input = (self.output_frames as f64 / self.ratio).round() as u64;
+        self.input_frames.saturating_sub(output_as_input)
+    }
+
+    /// Convert a piece of interleaved input, returning interleaved output
+    pub fn process(&mut self, interleaved: &[f32]) -> Result<Vec<f32>, AudioError> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(interleaved.to_vec());
+        };
+
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (channel, &sample) in self.pending.iter_mut().zip(frame) {
+                channel.push(sample);
+            }
+        }
+        self.input_frames += (interleaved.len() / self.channels) as u64;
+
+        let mut output = Vec::new();
+        while self.pending[0].len() >= CHUNK_FRAMES {
+            let chunk: Vec<Vec<f32>> = self
+                .pending
+                .iter_mut()
+                .map(|channel| channel.drain(..CHUNK_FRAMES).collect())
+                .collect();
+            let resampled = inner
+                .process(&chunk, None)
+                .map_err(|e| AudioError::Resample(e.to_string()))?;
+            self.output_frames += interleave(&resampled, &mut output, None);
+        }
+        Ok(output)
+    }
+
+    /// Drain buffered input and the filter tail at the end of a stream
+    pub fn flush(&mut self) -> Result<Vec<f32>, AudioError> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let expected = (self.input_frames as f64 * self.ratio).round() as u64;
+        let mut output = Vec::new();
+        let mut pending = Some(std::mem::take(&mut self.pending));
+
+        // Feed silence until the filter tail has come out
+        while self.output_frames < expected {
+            let resampled = inner
+                .process_partial(pending.take().as_deref(), None)
+                .map_err(|e| AudioError::Resample(e.to_string()))?;
+            let remaining = (expected - self.output_frames) as usize;
+            self.output_frames += interleave(&resampled, &mut output, Some(remaining));
+        }
+
+        self.pending = vec![Vec::with_capacity(CHUNK_FRAMES); self.channels];
+        Ok(output)
+    }
+}
+
+/// Append up to `limit` frames of per-channel output as interleaved samples,
+/// returning the number of frames written
+fn interleave(resampled: &[Vec<f32>], output: &mut Vec<f32>, limit: Option<usize>) -> u64 {
+    let available = resampled.first().map_or(0, Vec::len);
+    let frames = limit.map_or(available, |limit| available.min(limit));
+
+    output.reserve(frames * resampled.len());
+    for index in 0..frames {
+        output.extend(resampled.iter().map(|channel| channel[index]));
+    }
+    frames as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_44100_to_16000() {
+        let tone = |rate: f32, i: usize| {
+            0.5 * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / rate).sin()
+        };
+        let input: Vec<f32> = (0..44100).map(|i| tone(44100.0, i)).collect();
+
+        let mut resampler = Resampler::new(44100, 16000, 1).unwrap();
+        let mut output = Vec::new();
+        for piece in input.chunks(441) {
+            output.extend(resampler.process(piece).unwrap());
+        }
+        output.extend(resampler.flush().unwrap());
+
+        assert_eq!(output.len(), 16000);
+
+        // The tone stays in phase with the input
+        let max_error = (1000..15000)
+            .map(|i| (output[i] - tone(16000.0, i)).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_error < 0.02, "max error {max_error}");
+    }
+
+    #[test]
+    fn test_resampling_keeps_events_in_place() {
+        for (from, to) in [
+            (44100, 16000),
+            (48000, 16000),
+            (22050, 16000),
+            (16000, 48000),
+        ] {
+            // A click half a second in, on the second channel only
+            let mut input = vec![0.0f32; from as usize * 2];
+            input[from as usize + 1] = 1.0;
+
+            let mut resampler = Resampler::new(from, to, 2).unwrap();
+            let mut output = Vec::new();
+            for piece in input.chunks(882) {
+                output.extend(resampler.process(piece).unwrap());
+                // A chunk waiting to be filled, plus half the filter
+                assert!(resampler.buffered_frames() <= CHUNK_FRAMES as u64 + 64);
+            }
+            output.extend(resampler.flush().unwrap());
+            assert_eq!(resampler.buffered_frames(), 0);
+            assert_eq!(output.len(), to as usize * 2);
+
+            let second: Vec<f32> = output.iter().skip(1).step_by(2).copied().collect();
+            let peak = (0..second.len())
+                .max_by(|&a, &b| second[a].total_cmp(&second[b]))
+                .unwrap();
+            let expected = to as usize / 2;
+            assert!(
+                peak.abs_diff(expected) <= 2,
+                "{from} -> {to} Hz: click at {peak}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_matching_rates_pass_through() {
+        let mut resampler = Resampler::new(16000, 16000, 2).unwrap();
+        assert!(resampler.is_passthrough());
+        assert_eq!(resampler.process(&[0.1, 0.2]).unwrap(), vec![0.1, 0.2]);
+        assert!(resampler.flush().unwrap().is_empty());
+    }
+}
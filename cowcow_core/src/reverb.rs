@@ -0,0 +1,150 @@
+//! Reverberation time estimate from the free decay at speech offsets
+
+/// Length of the energy envelope frames
+const FRAME_MS: u32 = 10;
+
+/// Energy may rise this far above the lowest point of a decay before the
+/// decay is considered over (absorbs frame-to-frame noise)
+const DECAY_TOLERANCE_DB: f32 = 3.0;
+
+/// Drop skipped at the start of a decay, where the direct sound still dominates
+const FIT_START_DB: f32 = 5.0;
+
+/// Range of the decay used for the slope fit (T20-style)
+const FIT_RANGE_DB: f32 = 20.0;
+
+/// Fewest envelope frames a usable decay must span within the fit range
+const MIN_FIT_FRAMES: usize = 3;
+
+/// The fit range must end this far above the noise floor
+const FLOOR_MARGIN_DB: f32 = 5.0;
+
+/// Decays are closed and evaluated after this long, even in unbroken silence
+const MAX_DECAY_SECS: f32 = 3.0;
+
+/// Decay in progress
+#[derive(Debug, Clone)]
+struct Decay {
+    peak_db: f32,
+    min_db: f32,
+    /// Energy of every frame since the peak, in dB
+    frames: Vec<f32>,
+}
+
+/// Streaming RT60 estimator
+///
+/// Speech offsets are followed by the room's free decay. Each time the short
+/// term energy falls by at least [`FIT_START_DB`] + [`FIT_RANGE_DB`] from a
+/// local peak, the decay rate over that range is fitted and extrapolated to a
+/// 60 dB decay. Offsets that end abruptly (dry rooms) or sink into the noise
+/// floor too early produce no estimate.
+#[derive(Debug, Clone)]
+pub struct ReverbEstimator {
+    sample_rate: u32,
+    frame_len: usize,
+    frame_secs: f32,
+    partial: Vec<f32>,
+    prev_db: Option<f32>,
+    floor_db: f32,
+    decay: Option<Decay>,
+}
+
+impl ReverbEstimator {
+    pub fn new(sample_rate: u32) -> Self {
+        let frame_len = (sample_rate * FRAME_MS / 1000).max(1) as usize;
+        Self {
+            sample_rate,
+            frame_len,
+            frame_secs: frame_len as f32 / sample_rate as f32,
+            partial: Vec::with_capacity(frame_len),
+            prev_db: None,
+            floor_db: f32::MAX,
+            decay: None,
+        }
+    }
+
+    /// Feed mono samples and return the RT60 estimates (in seconds) of the
+    /// decays that completed within them
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let mut estimates = Vec::new();
+
+        for &x in samples {
+            self.partial.push(x);
+            if self.partial.len() == self.frame_len {
+                let energy =
+                    self.partial.iter().map(|&s| s * s).sum::<f32>() / self.frame_len as f32;
+                self.partial.clear();
+                if let Some(rt60) = self.push_frame(10.0 * (energy + 1e-10).log10()) {
+                    estimates.push(rt60);
+                }
+            }
+        }
+
+        estimates
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate);
+    }
+
+    fn push_frame(&mut self, level_db: f32) -> Option<f32> {
+        self.floor_db = self.floor_db.min(level_db);
+        let prev_db = self.prev_db.replace(level_db);
+        let mut estimate = None;
+
+        let max_frames = (MAX_DECAY_SECS / self.frame_secs) as usize;
+        if let Some(decay) = self.decay.as_mut() {
+            if level_db > decay.min_db + DECAY_TOLERANCE_DB || decay.frames.len() >= max_frames {
+                let decay = self.decay.take()?;
+                estimate = self.fit(&decay);
+            } else {
+                decay.min_db = decay.min_db.min(level_db);
+                decay.frames.push(level_db);
+            }
+        }
+
+        if self.decay.is_none() {
+            if let Some(prev_db) = prev_db {
+                if level_db < prev_db {
+                    self.decay = Some(Decay {
+                        peak_db: prev_db,
+                        min_db: level_db,
+                        frames: vec![prev_db, level_db],
+                    });
+                }
+            }
+        }
+
+        estimate
+    }
+
+    /// Least-squares decay rate over the fit range, as seconds per 60 dB
+    fn fit(&self, decay: &Decay) -> Option<f32> {
+        let upper = decay.peak_db - FIT_START_DB;
+        let lower = upper - FIT_RANGE_DB;
+        if decay.min_db > lower || lower <= self.floor_db + FLOOR_MARGIN_DB {
+            return None;
+        }
+
+        // Frames from the first crossing of `upper` to the first crossing of `lower`
+        let start = decay.frames.iter().position(|&db| db <= upper)?;
+        let end = start + decay.frames[start..].iter().position(|&db| db <= lower)?;
+        let points = &decay.frames[start..=end];
+        if points.len() < MIN_FIT_FRAMES {
+            return None;
+        }
+
+        let n = points.len() as f32;
+        let mean_t = (n - 1.0) / 2.0;
+        let mean_db = points.iter().sum::<f32>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (i, &db) in points.iter().enumerate() {
+            let t = i as f32 - mean_t;
+            covariance += t * (db - mean_db);
+            variance += t * t;
+        }
+
+        let slope_db_per_sec = covariance / variance / self.frame_secs;
+        (slope_db_per_sec < 0.0).then(|| -60.0 / slope_db_per_sec)
+    }
+}
@@ -0,0 +1,182 @@
+//! Deciding when a recording has gone quiet, for auto-stop
+//!
+//! A fixed level threshold never fires in a noisy room and cuts quiet
+//! speakers off, so the gate here sits a margin above the noise floor
+//! instead. The floor starts from the session's measured baseline (room
+//! tone or calibration) when there is one and follows the room from there:
+//! it drops at once to any quieter chunk and creeps up slowly while the
+//! gate is closed, so a fan switching on is learned within seconds but
+//! speech, which holds the gate open, never is. The gate opens above one
+//! margin and closes below a lower one, so levels hovering near the
+//! threshold do not flap.
+
+/// The gate opens this far above the noise floor...
+pub const OPEN_MARGIN_DB: f32 = 9.0;
+
+/// ...and once open only closes below this
+pub const CLOSE_MARGIN_DB: f32 = 5.0;
+
+/// How fast the floor follows a room that got louder, while the gate is
+/// closed
+pub const FLOOR_RISE_DB_PER_SEC: f32 = 1.0;
+
+/// The gate never sits lower than this, so hiss over a digitally silent
+/// floor does not count as sound
+pub const MIN_GATE_DB: f32 = -65.0;
+
+/// Why the gate decided as it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateReason {
+    /// Level above the opening threshold
+    Loud,
+    /// Level between the thresholds while the gate was open
+    Holding,
+    /// Voice activity above the closing threshold
+    Voice,
+    /// Level below the thresholds
+    Quiet,
+}
+
+impl GateReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GateReason::Loud => "loud",
+            GateReason::Holding => "holding",
+            GateReason::Voice => "voice",
+            GateReason::Quiet => "quiet",
+        }
+    }
+}
+
+/// The gate's verdict on one chunk of audio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateDecision {
+    /// Whether the chunk counts as sound rather than silence
+    pub active: bool,
+    pub reason: GateReason,
+    /// Chunk level, in dBFS
+    pub level_db: f32,
+    /// Noise floor after this chunk, in dBFS
+    pub floor_db: f32,
+    /// Level the chunk had to exceed, in dBFS
+    pub threshold_db: f32,
+}
+
+/// Silence gate that adapts to the noise floor, with hysteresis
+#[derive(Debug, Clone)]
+pub struct SilenceGate {
+    floor_db: Option<f32>,
+    open: bool,
+}
+
+impl SilenceGate {
+    /// A gate starting from `baseline_floor_db` (dBFS), or from the first
+    /// chunk it sees when there is no baseline
+    pub fn new(baseline_floor_db: Option<f32>) -> Self {
+        Self {
+            floor_db: baseline_floor_db,
+            open: false,
+        }
+    }
+
+    /// Judge a chunk of `secs` seconds at `level_db` (dBFS); `voice` is
+    /// whether voice activity detection heard speech in it
+    pub fn update(&mut self, level_db: f32, voice: bool, secs: f64) -> GateDecision {
+        let floor_db = match self.floor_db {
+            Some(floor) if level_db < floor => level_db,
+            // Whatever opens or holds the gate is sound, not room
+            Some(floor) if self.open || level_db > floor + OPEN_MARGIN_DB => floor,
+            Some(floor) => (floor + FLOOR_RISE_DB_PER_SEC * secs as f32).min(level_db),
+            None => level_db,
+        };
+        self.floor_db = Some(floor_db);
+
+        let open_db = (floor_db + OPEN_MARGIN_DB).max(MIN_GATE_DB);
+        let close_db = (floor_db + CLOSE_MARGIN_DB).max(MIN_GATE_DB);
+        let (reason, threshold_db) = if level_db > open_db {
+            (GateReason::Loud, open_db)
+        } else if self.open && level_db > close_db {
+            (GateReason::Holding, close_db)
+        } else if voice && level_db > close_db {
+            (GateReason::Voice, close_db)
+        } else if self.open {
+            (GateReason::Quiet, close_db)
+        } else {
+            (GateReason::Quiet, open_db)
+        };
+        self.open = reason != GateReason::Quiet;
+
+        GateDecision {
+            active: self.open,
+            reason,
+            level_db,
+            floor_db,
+            threshold_db,
+        }
+    }
+
+    /// Noise floor followed so far, in dBFS
+    pub fn floor_db(&self) -> Option<f32> {
+        self.floor_db
+    }
+}
+
+/// RMS level of samples in dBFS
+pub fn level_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return -100.0;
+    }
+    let mean_square = samples.iter().map(|&x| x * x).sum::<f32>() / samples.len() as f32;
+    10.0 * mean_square.max(1e-10).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate_follows_noise_floor_with_hysteresis() {
+        // A noisy room at -40 dBFS: the old fixed threshold (about -46 dBFS)
+        // would call all of it sound
+        let mut gate = SilenceGate::new(Some(-40.0));
+        assert!(!gate.update(-39.0, false, 0.1).active);
+
+        // Speech opens the gate; a dip between the margins holds it open
+        let speech = gate.update(-25.0, false, 0.1);
+        assert!(speech.active);
+        assert_eq!(speech.reason, GateReason::Loud);
+        let dip = gate.update(-33.0, false, 0.1);
+        assert!(dip.active);
+        assert_eq!(dip.reason, GateReason::Holding);
+
+        // Back to the room level closes it, and the same dip from closed
+        // does not reopen it
+        assert!(!gate.update(-40.0, false, 0.1).active);
+        assert!(!gate.update(-33.0, false, 0.1).active);
+        // ...unless voice activity was heard in it
+        assert_eq!(gate.update(-33.0, true, 0.1).reason, GateReason::Voice);
+
+        // A quiet speaker in a quiet room still opens a gate learned from
+        // the first chunk
+        let mut gate = SilenceGate::new(None);
+        gate.update(-62.0, false, 0.1);
+        assert!(gate.update(-50.0, false, 0.1).active);
+
+        // A sustained tone well above the floor is sound, however long it
+        // lasts: the floor does not rise while the gate is open
+        let mut gate = SilenceGate::new(Some(-60.0));
+        for _ in 0..200 {
+            assert!(gate.update(-45.0, false, 0.1).active);
+        }
+        assert_eq!(gate.floor_db(), Some(-60.0));
+
+        // A room that gets louder below the opening margin is learned
+        let mut gate = SilenceGate::new(Some(-60.0));
+        for _ in 0..200 {
+            assert!(!gate.update(-53.0, false, 0.1).active);
+        }
+        assert!((gate.floor_db().unwrap() + 53.0).abs() < 1e-3);
+
+        assert!((level_db(&[0.5; 100]) + 6.02).abs() < 0.01);
+    }
+}
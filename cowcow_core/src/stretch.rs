@@ -0,0 +1,172 @@
+//! Pitch-preserving time stretching for playback (WSOLA)
+
+use std::f32::consts::PI;
+
+use crate::AudioError;
+
+/// Slowest supported playback speed
+pub const MIN_SPEED: f32 = 0.5;
+
+/// Fastest supported playback speed
+pub const MAX_SPEED: f32 = 2.0;
+
+/// Length of the overlap-added frames
+const FRAME_MS: u32 = 20;
+
+/// How far a frame may move from its nominal position to line up with the
+/// previous one; covers a full pitch period down to 100 Hz
+const TOLERANCE_MS: u32 = 5;
+
+/// Waveform-similarity overlap-add time stretcher
+///
+/// Audio is cut into Hann-windowed frames that are read at `speed` times the
+/// rate they are written. Each frame is shifted by up to [`TOLERANCE_MS`] so
+/// its waveform continues the previous frame's, which keeps the pitch
+/// unchanged and avoids phasing artifacts. Multi-channel audio is aligned on
+/// its mono mix so all channels stay in sync.
+#[derive(Debug, Clone)]
+pub struct TimeStretcher {
+    channels: usize,
+    speed: f32,
+    frame_len: usize,
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+}
+
+impl TimeStretcher {
+    pub fn new(sample_rate: u32, channels: u16, speed: f32) -> Result<Self, AudioError> {
+        if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+            return Err(AudioError::Speed(speed));
+        }
+
+        let frame_len = ((sample_rate * FRAME_MS / 1000) as usize / 2 * 2).max(2);
+        let window = (0..frame_len)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / frame_len as f32).cos())
+            .collect();
+
+        Ok(Self {
+            channels: channels.max(1) as usize,
+            speed,
+            frame_len,
+            hop: frame_len / 2,
+            tolerance: (sample_rate * TOLERANCE_MS / 1000) as usize,
+            window,
+        })
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Stretch interleaved samples; the result lasts `1 / speed` as long
+    pub fn process(&self, samples: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        let input_frames = samples.len() / channels;
+        if self.speed == 1.0 || input_frames < self.frame_len {
+            return samples[..input_frames * channels].to_vec();
+        }
+
+        let mono: Vec<f32> = samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        let output_frames = (input_frames as f64 / self.speed as f64) as usize;
+        let mut output = vec![0.0f32; (output_frames + self.frame_len) * channels];
+        let mut weights = vec![0.0f32; output_frames + self.frame_len];
+        let last_start = input_frames - self.frame_len;
+
+        let mut prev_start: Option<usize> = None;
+        let mut out_start = 0;
+        while out_start < output_frames {
+            let nominal = ((out_start as f64 * self.speed as f64) as usize).min(last_start);
+            let start = match prev_start {
+                Some(prev) if prev + self.hop <= last_start => {
+                    self.best_alignment(&mono, prev + self.hop, nominal, last_start)
+                }
+                _ => nominal,
+            };
+
+            for (n, &w) in self.window.iter().enumerate() {
+                let src = (start + n) * channels;
+                let dst = (out_start + n) * channels;
+                for c in 0..channels {
+                    output[dst + c] += w * samples[src + c];
+                }
+                weights[out_start + n] += w;
+            }
+
+            prev_start = Some(start);
+            out_start += self.hop;
+        }
+
+        output.truncate(output_frames * channels);
+        for (frame, &weight) in output.chunks_exact_mut(channels).zip(&weights) {
+            if weight > 1e-3 {
+                frame.iter_mut().for_each(|x| *x /= weight);
+            }
+        }
+        output
+    }
+
+    /// Frame start near `nominal` whose waveform best matches the natural
+    /// continuation of the previous frame (starting at `natural`)
+    fn best_alignment(
+        &self,
+        mono: &[f32],
+        natural: usize,
+        nominal: usize,
+        last_start: usize,
+    ) -> usize {
+        let target = &mono[natural..natural + self.frame_len];
+        let lo = nominal.saturating_sub(self.tolerance);
+        let hi = (nominal + self.tolerance).min(last_start);
+
+        let mut best = (nominal, f32::MIN);
+        for candidate in lo..=hi {
+            let similarity: f32 = mono[candidate..candidate + self.frame_len]
+                .iter()
+                .zip(target)
+                .step_by(2)
+                .map(|(a, b)| a * b)
+                .sum();
+            if similarity > best.1 {
+                best = (candidate, similarity);
+            }
+        }
+        best.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn test_stretch_preserves_pitch() {
+        let tone: Vec<f32> = (0..16000)
+            .map(|i| 0.5 * (2.0 * PI * 200.0 * i as f32 / 16000.0).sin())
+            .collect();
+
+        for speed in [0.5, 1.5, 2.0] {
+            let stretched = TimeStretcher::new(16000, 1, speed).unwrap().process(&tone);
+            let expected_len = (16000.0 / speed) as usize;
+            assert_eq!(stretched.len(), expected_len);
+
+            // Still ~400 zero crossings per second of output
+            let seconds = stretched.len() as f32 / 16000.0;
+            let rate = zero_crossings(&stretched) as f32 / seconds;
+            assert!((rate - 400.0).abs() < 20.0, "speed {speed}: {rate}");
+        }
+
+        assert!(TimeStretcher::new(16000, 1, 3.0).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Per-frame QC metrics for locating problems within a recording
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{QcAccumulator, QcMetrics};
+
+/// Length of one timeline frame
+pub const TIMELINE_FRAME_MS: u32 = 100;
+
+/// Extension of the timeline file saved next to a recording
+pub const TIMELINE_EXTENSION: &str = "qc.json";
+
+/// Metrics for one frame of a recording
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QcFrame {
+    /// Offset of the frame from the start of the recording, in seconds
+    pub start_secs: f32,
+    #[serde(flatten)]
+    pub metrics: QcMetrics,
+}
+
+/// QC metrics over time, one entry per [`TIMELINE_FRAME_MS`] of audio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QcTimeline {
+    /// Nominal frame length in seconds (the last frame may be shorter)
+    pub frame_secs: f32,
+    pub frames: Vec<QcFrame>,
+    /// Running summary kept instead of frames in summary-only mode
+    #[serde(skip)]
+    summary_only: Option<SummaryOnly>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SummaryOnly {
+    totals: QcAccumulator,
+    last: Option<QcMetrics>,
+}
+
+impl Default for QcTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QcTimeline {
+    pub fn new() -> Self {
+        Self {
+            frame_secs: TIMELINE_FRAME_MS as f32 / 1000.0,
+            frames: Vec::new(),
+            summary_only: None,
+        }
+    }
+
+    /// A timeline that keeps only the latest frame and a running summary,
+    /// for devices that cannot hold a long recording's frames in memory
+    pub fn summary_only() -> Self {
+        Self {
+            summary_only: Some(SummaryOnly::default()),
+            ..Self::new()
+        }
+    }
+
+    /// Use frames of a different nominal length
+    pub fn with_frame_secs(mut self, frame_secs: f32) -> Self {
+        self.frame_secs = frame_secs;
+        self
+    }
+
+    /// Whether frames are discarded as they are pushed
+    pub fn is_summary_only(&self) -> bool {
+        self.summary_only.is_some()
+    }
+
+    /// Number of interleaved samples in one frame
+    pub fn frame_len(sample_rate: u32, channels: u16) -> usize {
+        (sample_rate * TIMELINE_FRAME_MS / 1000) as usize * channels as usize
+    }
+
+    /// Append the metrics of the next frame
+    pub fn push(&mut self, metrics: QcMetrics) {
+        if let Some(summary) = &mut self.summary_only {
+            summary.totals.push(&metrics);
+            summary.last = Some(metrics);
+            return;
+        }
+
+        let start_secs = self
+            .frames
+            .last()
+            .map_or(0.0, |frame| frame.start_secs + frame.metrics.duration_secs);
+        self.frames.push(QcFrame {
+            start_secs,
+            metrics,
+        });
+    }
+
+    /// Metrics of the most recent frame
+    pub fn last(&self) -> Option<&QcMetrics> {
+        match &self.summary_only {
+            Some(summary) => summary.last.as_ref(),
+            None => self.frames.last().map(|frame| &frame.metrics),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match &self.summary_only {
+            Some(summary) => summary.totals.is_empty(),
+            None => self.frames.is_empty(),
+        }
+    }
+
+    /// File-level metrics, as reported by [`QcMetrics::average`]
+    pub fn summary(&self) -> QcMetrics {
+        if let Some(summary) = &self.summary_only {
+            return summary.totals.finish();
+        }
+
+        let mut totals = QcAccumulator::default();
+        for frame in &self.frames {
+            totals.push(&frame.metrics);
+        }
+        totals.finish()
+    }
+
+    /// Drop silent frames at both ends, keeping `padding_secs` of silence
+    /// next to the speech; returns the seconds removed from the start and end
+    ///
+    /// Timelines without speech, and summary-only timelines, are left alone.
+    pub fn trim_silence(&mut self, padding_secs: f32) -> Option<(f32, f32)> {
+        if self.summary_only.is_some() {
+            return None;
+        }
+
+        let has_speech = |frame: &QcFrame| frame.metrics.speech_secs > 0.0;
+        let first = self.frames.iter().position(has_speech)?;
+        let last = self.frames.iter().rposition(has_speech)?;
+        let padding = (padding_secs / self.frame_secs).round() as usize;
+        let keep_from = first.saturating_sub(padding);
+        let keep_to = (last + 1 + padding).min(self.frames.len());
+
+        let seconds = |frames: &[QcFrame]| -> f32 {
+            frames.iter().map(|frame| frame.metrics.duration_secs).sum()
+        };
+        let start_secs = seconds(&self.frames[..keep_from]);
+        let end_secs = seconds(&self.frames[keep_to..]);
+
+        self.frames.truncate(keep_to);
+        self.frames.drain(..keep_from);
+        for frame in &mut self.frames {
+            frame.start_secs -= start_secs;
+        }
+        Some((start_secs, end_secs))
+    }
+
+    /// Runs of frames with speech, as (start, end) seconds from the start of
+    /// the recording; empty for summary-only timelines
+    pub fn speech_spans(&self) -> Vec<(f32, f32)> {
+        let mut spans: Vec<(f32, f32)> = Vec::new();
+        let mut previous_speech = false;
+        for frame in &self.frames {
+            let speech = frame.metrics.speech_secs > 0.0;
+            let end_secs = frame.start_secs + frame.metrics.duration_secs;
+            match spans.last_mut() {
+                Some(span) if speech && previous_speech => span.1 = end_secs,
+                _ if speech => spans.push((frame.start_secs, end_secs)),
+                _ => {}
+            }
+            previous_speech = speech;
+        }
+        spans
+    }
+
+    /// Cut the timeline into parts of at most `max_secs`, each ending in the
+    /// middle of the longest pause in its second half (or at `max_secs`
+    /// when it has none), with frame offsets relative to each part's start
+    ///
+    /// Timelines no longer than `max_secs`, and summary-only timelines, are
+    /// returned whole.
+    pub fn split(&self, max_secs: f32) -> Vec<QcTimeline> {
+        if self.summary_only.is_some() || max_secs <= 0.0 {
+            return vec![self.clone()];
+        }
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        loop {
+            // Frames that fit in a part starting at `start`
+            let mut secs = 0.0;
+            let mut end = start;
+            while end < self.frames.len()
+                && secs + self.frames[end].metrics.duration_secs <= max_secs + 1e-4
+            {
+                secs += self.frames[end].metrics.duration_secs;
+                end += 1;
+            }
+            if end == self.frames.len() {
+                parts.push(self.part(start, end));
+                return parts;
+            }
+            // A frame longer than a part still makes progress
+            let end = end.max(start + 1);
+
+            let middle = start + (end - start) / 2;
+            let mut longest = None;
+            let mut run_start = None;
+            for index in middle..=end {
+                let silent = index < end && self.frames[index].metrics.speech_secs <= 0.0;
+                match (silent, run_start) {
+                    (true, None) => run_start = Some(index),
+                    (false, Some(from)) => {
+                        if longest.is_none_or(|(a, b)| index - from > b - a) {
+                            longest = Some((from, index));
+                        }
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+            let cut = longest
+                .map_or(end, |(from, to)| (from + to) / 2)
+                .max(start + 1);
+            parts.push(self.part(start, cut));
+            start = cut;
+        }
+    }
+
+    /// Frames `from..to` as a timeline of their own
+    fn part(&self, from: usize, to: usize) -> QcTimeline {
+        let offset = self.frames.get(from).map_or(0.0, |frame| frame.start_secs);
+        QcTimeline {
+            frame_secs: self.frame_secs,
+            frames: self.frames[from..to]
+                .iter()
+                .map(|frame| QcFrame {
+                    start_secs: frame.start_secs - offset,
+                    metrics: frame.metrics.clone(),
+                })
+                .collect(),
+            summary_only: None,
+        }
+    }
+
+    /// Where the timeline of a recording is stored (`<id>.qc.json`)
+    pub fn sidecar_path(wav_path: &Path) -> PathBuf {
+        wav_path.with_extension(TIMELINE_EXTENSION)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).context("Failed to serialize QC timeline")?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write QC timeline: {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path)
+            .with_context(|| format!("Failed to read QC timeline: {}", path.display()))?;
+        serde_json::from_slice(&json)
+            .with_context(|| format!("Failed to parse QC timeline: {}", path.display()))
+    }
+}
@@ -0,0 +1,74 @@
+//! Removal of silence at the edges of a recording
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Silence kept around the speech when trimming, so word onsets and decays
+/// are not clipped
+pub const TRIM_PADDING_SECS: f32 = 0.3;
+
+/// Cut `start_secs` from the start and `end_secs` from the end of a WAV
+/// file, in place
+pub fn trim_wav_file<P: AsRef<Path>>(path: P, start_secs: f32, end_secs: f32) -> Result<()> {
+    let path = path.as_ref();
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+    let samples = crate::decode::read_wav_samples(reader)?;
+
+    let sample_index = |secs: f32| {
+        (secs.max(0.0) * spec.sample_rate as f32).round() as usize * spec.channels as usize
+    };
+    let start = sample_index(start_secs).min(samples.len());
+    let end = samples
+        .len()
+        .saturating_sub(sample_index(end_secs))
+        .max(start);
+
+    // Write beside the original and swap, so a crash never leaves half a file
+    let tmp_path = path.with_extension("wav.tmp");
+    let mut writer = hound::WavWriter::create(&tmp_path, spec)?;
+    for &sample in &samples[start..end] {
+        crate::wav::write_sample(&mut writer, sample)?;
+    }
+    writer.finalize()?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Write consecutive parts of a WAV file to `part_paths`, each part but the
+/// last `part_secs` long; the original is left as it is
+pub fn split_wav_file<P: AsRef<Path>>(
+    path: P,
+    part_secs: &[f32],
+    part_paths: &[PathBuf],
+) -> Result<()> {
+    let path = path.as_ref();
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let spec = reader.spec();
+    let samples = crate::decode::read_wav_samples(reader)?;
+
+    let mut start = 0;
+    let mut offset_secs = 0.0;
+    for (index, part_path) in part_paths.iter().enumerate() {
+        let end = match part_secs.get(index) {
+            Some(secs) if index + 1 < part_paths.len() => {
+                offset_secs += secs;
+                ((offset_secs * spec.sample_rate as f32).round() as usize * spec.channels as usize)
+                    .clamp(start, samples.len())
+            }
+            _ => samples.len(),
+        };
+        let mut writer = hound::WavWriter::create(part_path, spec)
+            .with_context(|| format!("Failed to write {}", part_path.display()))?;
+        for &sample in &samples[start..end] {
+            crate::wav::write_sample(&mut writer, sample)?;
+        }
+        writer.finalize()?;
+        start = end;
+    }
+    Ok(())
+}
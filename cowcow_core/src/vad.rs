@@ -0,0 +1,266 @@
+//! Pluggable voice activity detection backends
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::AudioError;
+
+/// Frame-level voice activity detector
+///
+/// Implementations consume fixed-size frames of mono `f32` samples in the
+/// range [-1.0, 1.0] and classify each one as speech or non-speech.
+pub trait VoiceDetector: Send {
+    /// Backend name, used in logs and diagnostics
+    fn name(&self) -> &'static str;
+
+    /// Number of samples per frame expected by `is_speech`
+    fn frame_size(&self) -> usize;
+
+    /// Classify a single frame of exactly `frame_size()` samples
+    fn is_speech(&mut self, frame: &[f32]) -> Result<bool, AudioError>;
+}
+
+/// Aggressiveness of the WebRTC detector, from most to least permissive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VadMode {
+    #[default]
+    Quality,
+    LowBitrate,
+    Aggressive,
+    VeryAggressive,
+}
+
+impl From<VadMode> for webrtc_vad::VadMode {
+    fn from(mode: VadMode) -> Self {
+        match mode {
+            VadMode::Quality => webrtc_vad::VadMode::Quality,
+            VadMode::LowBitrate => webrtc_vad::VadMode::LowBitrate,
+            VadMode::Aggressive => webrtc_vad::VadMode::Aggressive,
+            VadMode::VeryAggressive => webrtc_vad::VadMode::VeryAggressive,
+        }
+    }
+}
+
+/// VAD backend selection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum VadBackend {
+    /// WebRTC GMM-based detector (always available)
+    WebRtc,
+    /// Silero neural detector loaded from an ONNX model file
+    Silero { model_path: PathBuf },
+}
+
+/// Build a detector for the requested backend
+///
+/// `mode` and `frame_ms` only apply to the WebRTC detector. Falls back to
+/// WebRTC when the Silero model cannot be loaded (missing file, unsupported
+/// sample rate, or built without the `silero` feature).
+pub fn build_detector(
+    backend: &VadBackend,
+    sample_rate: u32,
+    mode: VadMode,
+    frame_ms: u32,
+) -> Result<Box<dyn VoiceDetector>, AudioError> {
+    match backend {
+        VadBackend::WebRtc => Ok(Box::new(WebRtcDetector::new(sample_rate, mode, frame_ms)?)),
+        VadBackend::Silero { model_path } => match load_silero(model_path, sample_rate) {
+            Ok(detector) => Ok(detector),
+            Err(e) => {
+                warn!("Silero VAD unavailable ({}), falling back to webrtc", e);
+                Ok(Box::new(WebRtcDetector::new(sample_rate, mode, frame_ms)?))
+            }
+        },
+    }
+}
+
+#[cfg(feature = "silero")]
+fn load_silero(
+    model_path: &std::path::Path,
+    sample_rate: u32,
+) -> Result<Box<dyn VoiceDetector>, AudioError> {
+    Ok(Box::new(SileroDetector::new(model_path, sample_rate)?))
+}
+
+#[cfg(not(feature = "silero"))]
+fn load_silero(
+    _model_path: &std::path::Path,
+    _sample_rate: u32,
+) -> Result<Box<dyn VoiceDetector>, AudioError> {
+    Err(AudioError::VadError(
+        "cowcow_core was built without the `silero` feature".to_string(),
+    ))
+}
+
+/// WebRTC VAD operating on 10, 20 or 30ms frames
+pub struct WebRtcDetector {
+    vad: FvadHandle,
+    frame_size: usize,
+    buffer: Vec<i16>,
+}
+
+impl WebRtcDetector {
+    pub fn new(sample_rate: u32, mode: VadMode, frame_ms: u32) -> Result<Self, AudioError> {
+        if !matches!(frame_ms, 10 | 20 | 30) {
+            return Err(AudioError::VadError(format!(
+                "WebRTC VAD frames must be 10, 20 or 30ms, got {frame_ms}ms"
+            )));
+        }
+
+        let mut vad = webrtc_vad::Vad::new(sample_rate as i32)
+            .map_err(|_| AudioError::VadError("Failed to create VAD instance".to_string()))?;
+        vad.fvad_set_mode(mode.into())
+            .map_err(|_| AudioError::VadError("Failed to set VAD mode".to_string()))?;
+        let frame_size = (sample_rate * frame_ms / 1000) as usize;
+
+        Ok(Self {
+            vad: FvadHandle(vad),
+            frame_size,
+            buffer: Vec::with_capacity(frame_size),
+        })
+    }
+}
+
+/// Owned libfvad instance that can move between threads
+struct FvadHandle(webrtc_vad::Vad);
+
+// SAFETY: `webrtc_vad::Vad` is `!Send` only because it holds a raw
+// `*mut Fvad`. libfvad keeps no thread-local or global state, the pointer
+// is owned exclusively by this handle, and every call goes through
+// `&mut self`, so the state is only ever used by one thread at a time.
+unsafe impl Send for FvadHandle {}
+
+impl VoiceDetector for WebRtcDetector {
+    fn name(&self) -> &'static str {
+        "webrtc"
+    }
+
+    fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> Result<bool, AudioError> {
+        // Convert f32 samples to i16 for VAD
+        self.buffer.clear();
+        self.buffer
+            .extend(frame.iter().map(|&sample| (sample * 32767.0) as i16));
+
+        self.vad
+            .0
+            .is_voice_segment(&self.buffer)
+            .map_err(|_| AudioError::VadError("VAD processing failed for frame".to_string()))
+    }
+}
+
+/// Silero VAD v5 running through ONNX Runtime
+#[cfg(feature = "silero")]
+pub struct SileroDetector {
+    session: ort::session::Session,
+    sample_rate: u32,
+    frame_size: usize,
+    context: Vec<f32>,
+    state: Vec<f32>,
+    threshold: f32,
+}
+
+#[cfg(feature = "silero")]
+impl SileroDetector {
+    /// Speech probability above which a frame counts as speech
+    pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+    pub fn new(model_path: &std::path::Path, sample_rate: u32) -> Result<Self, AudioError> {
+        // The model only ships with 8 kHz and 16 kHz support
+        let (frame_size, context_size) = match sample_rate {
+            8000 => (256, 32),
+            16000 => (512, 64),
+            _ => {
+                return Err(AudioError::VadError(format!(
+                    "Silero VAD does not support {sample_rate} Hz"
+                )))
+            }
+        };
+
+        if !model_path.exists() {
+            return Err(AudioError::VadError(format!(
+                "Silero model not found: {}",
+                model_path.display()
+            )));
+        }
+
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| AudioError::VadError(format!("Failed to load Silero model: {e}")))?;
+
+        Ok(Self {
+            session,
+            sample_rate,
+            frame_size,
+            context: vec![0.0; context_size],
+            state: vec![0.0; 2 * 128],
+            threshold: Self::DEFAULT_THRESHOLD,
+        })
+    }
+
+    fn speech_probability(&mut self, frame: &[f32]) -> Result<f32, ort::Error> {
+        use ort::value::Tensor;
+
+        let mut input = Vec::with_capacity(self.context.len() + frame.len());
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(frame);
+        let input_len = input.len();
+
+        let outputs = self.session.run(ort::inputs![
+            "input" => Tensor::from_array(([1usize, input_len], input))?,
+            "state" => Tensor::from_array(([2usize, 1, 128], self.state.clone()))?,
+            "sr" => Tensor::from_array(((), vec![self.sample_rate as i64]))?,
+        ])?;
+
+        let (_, probability) = outputs["output"].try_extract_tensor::<f32>()?;
+        let (_, state) = outputs["stateN"].try_extract_tensor::<f32>()?;
+        let probability = probability.first().copied().unwrap_or(0.0);
+        self.state.copy_from_slice(state);
+
+        let context_size = self.context.len();
+        self.context
+            .copy_from_slice(&frame[frame.len() - context_size..]);
+
+        Ok(probability)
+    }
+}
+
+#[cfg(feature = "silero")]
+impl VoiceDetector for SileroDetector {
+    fn name(&self) -> &'static str {
+        "silero"
+    }
+
+    fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    fn is_speech(&mut self, frame: &[f32]) -> Result<bool, AudioError> {
+        let probability = self
+            .speech_probability(frame)
+            .map_err(|e| AudioError::VadError(format!("Silero inference failed: {e}")))?;
+        Ok(probability >= self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silero_falls_back_to_webrtc() {
+        let backend = VadBackend::Silero {
+            model_path: PathBuf::from("/nonexistent/silero_vad.onnx"),
+        };
+        let detector = build_detector(&backend, 16000, VadMode::default(), 30).unwrap();
+
+        assert_eq!(detector.name(), "webrtc");
+        assert_eq!(detector.frame_size(), 480);
+    }
+}
@@ -0,0 +1,125 @@
+//! Sample formats recordings are written in
+
+use std::fmt;
+use std::io::{Seek, Write};
+use std::str::FromStr;
+
+/// Sample format of WAV recordings: 16-bit is the default, 24-bit and 32-bit
+/// float are for projects that require higher-resolution masters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl BitDepth {
+    pub const ALL: [BitDepth; 3] = [BitDepth::Int16, BitDepth::Int24, BitDepth::Float32];
+
+    /// WAV spec of a recording at this depth
+    pub fn spec(self, sample_rate: u32, channels: u16) -> hound::WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            BitDepth::Int16 => (16, hound::SampleFormat::Int),
+            BitDepth::Int24 => (24, hound::SampleFormat::Int),
+            BitDepth::Float32 => (32, hound::SampleFormat::Float),
+        };
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BitDepth::Int16 => "16",
+            BitDepth::Int24 => "24",
+            BitDepth::Float32 => "32f",
+        }
+    }
+}
+
+impl fmt::Display for BitDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BitDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "16" => Ok(BitDepth::Int16),
+            "24" => Ok(BitDepth::Int24),
+            "32f" | "f32" | "float" => Ok(BitDepth::Float32),
+            _ => Err(format!("Unknown bit depth: {s} (use 16, 24 or 32f)")),
+        }
+    }
+}
+
+/// Write one sample in [-1.0, 1.0] in the writer's own sample format
+///
+/// Integer formats round and clip; float keeps overs as they are, so a hot
+/// take can still be turned down afterwards.
+pub fn write_sample<W: Write + Seek>(
+    writer: &mut hound::WavWriter<W>,
+    sample: f32,
+) -> hound::Result<()> {
+    let spec = writer.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => writer.write_sample(sample),
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+            let value = (sample as f64 * scale).round().clamp(-scale, scale - 1.0);
+            if spec.bits_per_sample <= 16 {
+                writer.write_sample(value as i16)
+            } else {
+                writer.write_sample(value as i32)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_bit_depths_round_trip() {
+        let samples = [0.0f32, 0.25, -0.5, 0.999, -1.0, 1.5];
+        for depth in BitDepth::ALL {
+            assert_eq!(depth.as_str().parse::<BitDepth>().unwrap(), depth);
+
+            let mut buffer = Cursor::new(Vec::new());
+            let mut writer = hound::WavWriter::new(&mut buffer, depth.spec(48000, 1)).unwrap();
+            for &sample in &samples {
+                write_sample(&mut writer, sample).unwrap();
+            }
+            writer.finalize().unwrap();
+
+            buffer.set_position(0);
+            let reader = hound::WavReader::new(buffer).unwrap();
+            assert_eq!(reader.spec(), depth.spec(48000, 1));
+            let read = crate::decode::read_wav_samples(reader).unwrap();
+            let tolerance = match depth {
+                BitDepth::Int16 => 1.0 / 32768.0,
+                BitDepth::Int24 => 1.0 / 8_388_608.0,
+                BitDepth::Float32 => 0.0,
+            };
+            for (&written, &read) in samples[..5].iter().zip(&read) {
+                assert!(
+                    (written - read).abs() <= tolerance,
+                    "{depth}: {written} vs {read}"
+                );
+            }
+            // Overs clip in integer formats only
+            let over = read[5];
+            assert_eq!(over > 1.0, depth == BitDepth::Float32);
+        }
+        assert!("8".parse::<BitDepth>().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Word timings of a recording against its transcript
+//!
+//! The timings come from a whisper model's token timestamps
+//! ([`crate::asr::WhisperModel::align_words`], behind the `whisper`
+//! feature): the model transcribes the recording prompted with the
+//! transcript, one word per segment, and each transcript word takes the
+//! times of the word heard in its place. This module holds the stored form
+//! and that matching, which need no model, so alignments made on one machine
+//! can be exported from any build.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::asr::normalized_word;
+
+/// Name stored with timings taken from whisper's token timestamps
+pub const WHISPER_ALIGNER: &str = "whisper-token-timestamps";
+
+/// One transcript word and when it was spoken, in seconds from the start of
+/// the audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start_secs: f64,
+    pub end_secs: f64,
+}
+
+/// Word timestamps of one recording
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WordAlignment {
+    /// Aligner that produced the timings
+    pub aligner: String,
+    pub words: Vec<WordTiming>,
+}
+
+impl WordAlignment {
+    /// CTM lines (`<utterance> 1 <start> <duration> <word>`) for the
+    /// recording's audio exported as `utterance`
+    pub fn to_ctm(&self, utterance: &str) -> String {
+        let mut ctm = String::new();
+        for word in &self.words {
+            let _ = writeln!(
+                ctm,
+                "{} 1 {:.3} {:.3} {}",
+                utterance,
+                word.start_secs,
+                word.end_secs - word.start_secs,
+                word.word
+            );
+        }
+        ctm
+    }
+}
+
+/// Time the words of `transcript` by the words a model heard
+///
+/// Transcript words are matched to `heard` in order by edit distance,
+/// ignoring case and punctuation; a matched or substituted word takes the
+/// heard word's times. Words the model skipped share the span of the timed
+/// word before them (after them, at the start), so every word falls inside
+/// audio the model heard speech in.
+pub fn match_transcript(transcript: &str, heard: &[WordTiming]) -> Result<Vec<WordTiming>> {
+    let words: Vec<(&str, String)> = transcript
+        .split_whitespace()
+        .map(|word| (word, normalized_word(word)))
+        .filter(|(_, normalized)| !normalized.is_empty())
+        .collect();
+    if words.is_empty() {
+        anyhow::bail!("The transcript has no words");
+    }
+    if heard.is_empty() {
+        anyhow::bail!("No speech was heard to align the transcript to");
+    }
+    let heard_words: Vec<String> = heard.iter().map(|w| normalized_word(&w.word)).collect();
+
+    // Edit distance over words, keeping the whole table to trace back
+    let (n, m) = (words.len(), heard_words.len());
+    let mut cost = vec![vec![0usize; m + 1]; n + 1];
+    cost[0] = (0..=m).collect();
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let substitution =
+                cost[i - 1][j - 1] + usize::from(words[i - 1].1 != heard_words[j - 1]);
+            cost[i][j] = substitution.min(cost[i - 1][j] + 1).min(cost[i][j - 1] + 1);
+        }
+    }
+
+    // The heard word standing in for each transcript word, if any
+    let mut matched: Vec<Option<usize>> = vec![None; n];
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 {
+        let substitution = cost[i - 1][j - 1] + usize::from(words[i - 1].1 != heard_words[j - 1]);
+        if cost[i][j] == substitution {
+            matched[i - 1] = Some(j - 1);
+            i -= 1;
+            j -= 1;
+        } else if cost[i][j] == cost[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+
+    // Group each run of skipped words with the timed word it follows (or,
+    // for a leading run, precedes) and split that word's span between them
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut leading = Vec::new();
+    for (index, heard_index) in matched.into_iter().enumerate() {
+        if let Some(heard_index) = heard_index {
+            let mut members = std::mem::take(&mut leading);
+            members.push(index);
+            groups.push((heard_index, members));
+        } else if let Some((_, members)) = groups.last_mut() {
+            members.push(index);
+        } else {
+            leading.push(index);
+        }
+    }
+    if groups.is_empty() {
+        // Nothing matched at all: the transcript spans what was heard
+        let span = WordTiming {
+            word: String::new(),
+            start_secs: heard[0].start_secs,
+            end_secs: heard[m - 1].end_secs,
+        };
+        return Ok(split_span(&span, &leading, &words));
+    }
+
+    let mut timings = Vec::with_capacity(n);
+    for (heard_index, members) in groups {
+        timings.extend(split_span(&heard[heard_index], &members, &words));
+    }
+    Ok(timings)
+}
+
+/// `span` divided evenly between the transcript words at `members`
+fn split_span(span: &WordTiming, members: &[usize], words: &[(&str, String)]) -> Vec<WordTiming> {
+    let length = (span.end_secs - span.start_secs) / members.len() as f64;
+    members
+        .iter()
+        .enumerate()
+        .map(|(k, &index)| WordTiming {
+            word: words[index].0.to_string(),
+            start_secs: span.start_secs + k as f64 * length,
+            end_secs: span.start_secs + (k + 1) as f64 * length,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heard(words: &[(&str, f64, f64)]) -> Vec<WordTiming> {
+        words
+            .iter()
+            .map(|&(word, start_secs, end_secs)| WordTiming {
+                word: word.to_string(),
+                start_secs,
+                end_secs,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_transcript_words_take_the_heard_times() {
+        let heard = heard(&[
+            ("Habari", 0.25, 0.7),
+            ("za", 0.7, 0.9),
+            ("asubuhi.", 1.5, 2.0),
+        ]);
+        // "ya" was heard as "za"; "njema" was not heard at all
+        let words = match_transcript("habari ya asubuhi njema", &heard).unwrap();
+        let spans: Vec<(&str, f64, f64)> = words
+            .iter()
+            .map(|w| (w.word.as_str(), w.start_secs, w.end_secs))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("habari", 0.25, 0.7),
+                ("ya", 0.7, 0.9),
+                ("asubuhi", 1.5, 1.75),
+                ("njema", 1.75, 2.0),
+            ]
+        );
+
+        let alignment = WordAlignment {
+            aligner: WHISPER_ALIGNER.to_string(),
+            words,
+        };
+        let ctm = alignment.to_ctm("sw_take");
+        assert_eq!(ctm.lines().count(), 4);
+        assert!(ctm.starts_with("sw_take 1 0.250 0.450 habari"));
+    }
+
+    #[test]
+    fn test_skipped_leading_words_share_the_first_heard_word() {
+        let heard = heard(&[("leo", 1.0, 1.6)]);
+        let words = match_transcript("habari ya leo", &heard).unwrap();
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0].start_secs, 1.0);
+        assert!((words[1].start_secs - 1.2).abs() < 1e-9);
+        assert_eq!(words[2].word, "leo");
+        assert!((words[2].end_secs - 1.6).abs() < 1e-9);
+
+        assert!(match_transcript("habari", &[]).is_err());
+        assert!(match_transcript("…", &heard).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! UniFFI bindings for the Kotlin and Swift companion apps
+//!
+//! Wraps the QC API of `cowcow_core` (the streaming [`AudioProcessor`], its
+//! [`QcMetrics`] and the file analysis functions) so the bindings can be
+//! generated instead of hand-writing JNI and C shims:
+//!
+//! ```sh
+//! cargo build -p cowcow_ffi --release
+//! cargo run -p cowcow_ffi --features bindgen --bin uniffi-bindgen -- \
+//!     generate --library target/release/libcowcow_ffi.so --language kotlin --out-dir out
+//! ```
+
+use std::io::Cursor;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use cowcow_core::{AudioError, DownmixStrategy, QcAccumulator};
+use thiserror::Error;
+
+uniffi::setup_scaffolding!();
+
+/// Quality control metrics for audio recordings (see
+/// `cowcow_core::QcMetrics` for the meaning of each field)
+#[derive(Debug, Clone, Default, PartialEq, uniffi::Record)]
+pub struct QcMetrics {
+    pub snr_db: f32,
+    pub clipping_pct: f32,
+    pub vad_ratio: f32,
+    pub dc_offset: f32,
+    pub rumble_db: f32,
+    pub hum_db: f32,
+    pub dropout_count: u32,
+    pub glitch_pct: f32,
+    pub duration_secs: f32,
+    pub speech_secs: f32,
+    pub leading_silence_secs: f32,
+    pub trailing_silence_secs: f32,
+    pub reverb_rt60_secs: f32,
+    pub f0_min_hz: f32,
+    pub f0_max_hz: f32,
+    pub f0_mean_hz: f32,
+    pub voiced_ratio: f32,
+}
+
+impl From<cowcow_core::QcMetrics> for QcMetrics {
+    fn from(metrics: cowcow_core::QcMetrics) -> Self {
+        Self {
+            snr_db: metrics.snr_db,
+            clipping_pct: metrics.clipping_pct,
+            vad_ratio: metrics.vad_ratio,
+            dc_offset: metrics.dc_offset,
+            rumble_db: metrics.rumble_db,
+            hum_db: metrics.hum_db,
+            dropout_count: metrics.dropout_count,
+            glitch_pct: metrics.glitch_pct,
+            duration_secs: metrics.duration_secs,
+            speech_secs: metrics.speech_secs,
+            leading_silence_secs: metrics.leading_silence_secs,
+            trailing_silence_secs: metrics.trailing_silence_secs,
+            reverb_rt60_secs: metrics.reverb_rt60_secs,
+            f0_min_hz: metrics.f0_min_hz,
+            f0_max_hz: metrics.f0_max_hz,
+            f0_mean_hz: metrics.f0_mean_hz,
+            voiced_ratio: metrics.voiced_ratio,
+        }
+    }
+}
+
+/// How multi-channel audio is reduced to the mono signal QC runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum Downmix {
+    Left,
+    Right,
+    Average,
+}
+
+impl From<Downmix> for DownmixStrategy {
+    fn from(downmix: Downmix) -> Self {
+        match downmix {
+            Downmix::Left => DownmixStrategy::Left,
+            Downmix::Right => DownmixStrategy::Right,
+            Downmix::Average => DownmixStrategy::Average,
+        }
+    }
+}
+
+/// Errors raised to Kotlin and Swift as exceptions
+#[derive(Debug, Error, uniffi::Error)]
+pub enum CowcowError {
+    /// A file could not be opened or read
+    #[error("{message}")]
+    Io { message: String },
+    /// The audio is not in a format the library can read
+    #[error("{message}")]
+    Format { message: String },
+    /// An argument (sample rate, channel count, ...) is not supported
+    #[error("{message}")]
+    InvalidArgument { message: String },
+    /// Anything else, such as a VAD failure
+    #[error("{message}")]
+    Internal { message: String },
+}
+
+impl From<AudioError> for CowcowError {
+    fn from(error: AudioError) -> Self {
+        let message = error.to_string();
+        match error {
+            AudioError::FileOpen(_) | AudioError::WavFormat(hound::Error::IoError(_)) => {
+                CowcowError::Io { message }
+            }
+            AudioError::WavFormat(_) | AudioError::Decode(_) => CowcowError::Format { message },
+            AudioError::Speed(_) | AudioError::SampleRate(_) | AudioError::NoChannels => {
+                CowcowError::InvalidArgument { message }
+            }
+            AudioError::VadError(_) | AudioError::Resample(_) => CowcowError::Internal { message },
+        }
+    }
+}
+
+/// Streaming QC for a recording in progress
+///
+/// Feed captured buffers to [`AudioProcessor::process_chunk`] for live
+/// metrics, then call [`AudioProcessor::finalize`] for the recording's
+/// metrics; the processor is then ready for the next recording. Safe to
+/// share between threads.
+#[derive(uniffi::Object)]
+pub struct AudioProcessor {
+    state: Mutex<ProcessorState>,
+}
+
+struct ProcessorState {
+    processor: cowcow_core::AudioProcessor,
+    totals: QcAccumulator,
+}
+
+#[uniffi::export]
+impl AudioProcessor {
+    /// Processor for interleaved audio at `sample_rate` with `channels`
+    /// channels
+    #[uniffi::constructor]
+    pub fn new(sample_rate: u32, channels: u16) -> Result<Arc<Self>, CowcowError> {
+        Ok(Arc::new(Self {
+            state: Mutex::new(ProcessorState {
+                processor: cowcow_core::AudioProcessor::new(sample_rate, channels)?,
+                totals: QcAccumulator::default(),
+            }),
+        }))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.lock().processor.sample_rate()
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.lock().processor.channels()
+    }
+
+    /// Analyze the next interleaved samples (in [-1.0, 1.0]), which must be
+    /// a whole number of frames, returning the chunk's metrics
+    pub fn process_chunk(&self, samples: Vec<f32>) -> Result<QcMetrics, CowcowError> {
+        let mut state = self.lock();
+        let channels = state.processor.channels() as usize;
+        if !samples.len().is_multiple_of(channels) {
+            return Err(CowcowError::InvalidArgument {
+                message: format!(
+                    "Chunk of {} samples is not a whole number of {channels}-channel frames",
+                    samples.len()
+                ),
+            });
+        }
+
+        let metrics = state.processor.process_chunk(&samples);
+        state.totals.push(&metrics);
+        Ok(metrics.into())
+    }
+
+    /// Metrics of the whole recording processed so far; resets the
+    /// processor for the next recording
+    pub fn finalize(&self) -> Result<QcMetrics, CowcowError> {
+        let mut state = self.lock();
+        let metrics = std::mem::take(&mut state.totals).finish();
+        state.processor.reset()?;
+        Ok(metrics.into())
+    }
+}
+
+impl AudioProcessor {
+    fn lock(&self) -> MutexGuard<'_, ProcessorState> {
+        // A panic mid-chunk leaves the state usable; recover it
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Analyze a WAV file
+#[uniffi::export]
+pub fn analyze_wav_file(path: String) -> Result<QcMetrics, CowcowError> {
+    Ok(cowcow_core::analyze_wav_file(path)?.into())
+}
+
+/// Analyze a WAV file, reducing multi-channel audio as given
+#[uniffi::export]
+pub fn analyze_wav_file_with_downmix(
+    path: String,
+    downmix: Downmix,
+) -> Result<QcMetrics, CowcowError> {
+    Ok(cowcow_core::analyze_wav_file_with_downmix(path, downmix.into())?.into())
+}
+
+/// Analyze WAV data held in memory
+#[uniffi::export]
+pub fn analyze_wav_bytes(data: Vec<u8>) -> Result<QcMetrics, CowcowError> {
+    Ok(cowcow_core::analyze_reader(Cursor::new(data))?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_api() {
+        let processor = AudioProcessor::new(16000, 1).unwrap();
+        let tone: Vec<f32> = (0..1600)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin())
+            .collect();
+        let chunk = processor.process_chunk(tone.clone()).unwrap();
+        processor.process_chunk(tone).unwrap();
+        assert!((chunk.duration_secs - 0.1).abs() < 1e-3);
+        assert!((processor.finalize().unwrap().duration_secs - 0.2).abs() < 1e-3);
+
+        assert!(matches!(
+            AudioProcessor::new(16000, 0),
+            Err(CowcowError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            analyze_wav_file("/nonexistent/recording.wav".to_string()),
+            Err(CowcowError::Io { .. })
+        ));
+        assert!(matches!(
+            analyze_wav_bytes(b"not a wav file".to_vec()),
+            Err(CowcowError::Format { .. })
+        ));
+    }
+}
@@ -0,0 +1,775 @@
+//! In-memory stand-in for the Cowcow server
+//!
+//! Serves the endpoints the CLI talks to (registration and login, whole,
+//! resumable and test uploads, the recording list, token balance and history,
+//! campaigns, telemetry and the health check) from state kept in memory, so the CLI can be tested end to end without the Python
+//! server, its database or the network. Uploads earn tokens by the same rule
+//! as the real server, and need the same `upload` scope.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::body::Bytes;
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Form, Json, Router};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// Tokens every accepted upload earns before quality bonuses
+pub const TOKENS_PER_MINUTE: i32 = 10;
+
+/// What a token may be used for, from least to most privileged; each scope
+/// includes the ones before it
+const SCOPES: [&str; 3] = ["record", "upload", "admin"];
+
+/// Scopes an account may be granted; stub accounts are never admins
+const ACCOUNT_SCOPES: [&str; 2] = ["record", "upload"];
+
+/// A recording received by the stub
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadedRecording {
+    pub recording_id: String,
+    /// Account that uploaded it
+    pub username: String,
+    pub lang: String,
+    pub qc_metrics: serde_json::Value,
+    pub file_path: String,
+    pub campaign_id: Option<String>,
+    pub transcript: Option<String>,
+    /// Name and size of the uploaded audio
+    pub file_name: Option<String>,
+    pub audio_bytes: usize,
+    /// Chunks the audio came in; 0 for a whole upload
+    pub chunks: usize,
+    pub tokens_awarded: i32,
+}
+
+/// Recording details sent when a resumable upload starts
+#[derive(Debug, Clone, Deserialize)]
+struct ResumableStart {
+    recording_id: String,
+    lang: String,
+    qc_metrics: String,
+    file_path: String,
+    campaign_id: Option<String>,
+    transcript: Option<String>,
+    file_name: Option<String>,
+    size: usize,
+}
+
+/// A resumable upload in progress
+#[derive(Debug)]
+struct ResumableUpload {
+    username: String,
+    start: ResumableStart,
+    data: Vec<u8>,
+    chunks: usize,
+}
+
+/// One entry of an account's token history
+#[derive(Debug, Clone, Serialize)]
+struct Transaction {
+    id: String,
+    transaction_type: String,
+    amount: i32,
+    balance: i32,
+    date: DateTime<Utc>,
+    notes: String,
+    recording_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct Account {
+    id: u64,
+    email: String,
+    password: String,
+    api_key: String,
+    transactions: Vec<Transaction>,
+}
+
+impl Account {
+    fn balance(&self) -> i32 {
+        self.transactions.iter().map(|t| t.amount).sum()
+    }
+}
+
+/// An access token issued at login
+#[derive(Debug)]
+struct Session {
+    username: String,
+    scopes: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct StubState {
+    accounts: HashMap<String, Account>,
+    /// Access tokens issued at login, by token
+    sessions: HashMap<String, Session>,
+    uploads: Vec<UploadedRecording>,
+    /// Test uploads received, which are neither kept nor rewarded
+    test_uploads: Vec<UploadedRecording>,
+    /// Resumable uploads in progress, by upload ID
+    resumable: HashMap<String, ResumableUpload>,
+    /// Behave like a server without resumable uploads
+    whole_uploads_only: bool,
+    /// Telemetry events received, as sent
+    telemetry: Vec<serde_json::Value>,
+}
+
+impl StubState {
+    fn add_account(&mut self, username: &str, email: &str, password: &str) -> &Account {
+        let id = self.accounts.len() as u64 + 1;
+        self.accounts
+            .entry(username.to_string())
+            .or_insert(Account {
+                id,
+                email: email.to_string(),
+                password: password.to_string(),
+                api_key: Uuid::new_v4().simple().to_string(),
+                transactions: Vec::new(),
+            })
+    }
+
+    /// Account named by the bearer token or API key of a request
+    fn authenticate(&self, headers: &HeaderMap) -> Option<String> {
+        self.credentials(headers)
+            .map(|(username, _)| username.to_string())
+    }
+
+    /// Account and scopes of the bearer token or API key of a request; an
+    /// API key grants every scope its account may have
+    fn credentials(&self, headers: &HeaderMap) -> Option<(&str, Vec<&str>)> {
+        let bearer = headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| self.sessions.get(token))
+            .map(|session| {
+                let scopes = session.scopes.iter().map(String::as_str).collect();
+                (session.username.as_str(), scopes)
+            });
+        let api_key = headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|key| {
+                self.accounts
+                    .iter()
+                    .find(|(_, account)| account.api_key == key)
+                    .map(|(username, _)| (username.as_str(), ACCOUNT_SCOPES.to_vec()))
+            });
+        bearer.or(api_key)
+    }
+
+    /// Account of a request whose credentials grant `scope`
+    fn authorize(&self, headers: &HeaderMap, scope: &'static str) -> Result<String, Denied> {
+        let (username, granted) = self.credentials(headers).ok_or(Denied::Unauthenticated)?;
+        if !has_scope(&granted, scope) {
+            return Err(Denied::MissingScope(scope));
+        }
+        Ok(username.to_string())
+    }
+}
+
+/// Why a request was refused
+enum Denied {
+    Unauthenticated,
+    MissingScope(&'static str),
+}
+
+impl IntoResponse for Denied {
+    fn into_response(self) -> Response {
+        match self {
+            Denied::Unauthenticated => unauthorized(),
+            Denied::MissingScope(scope) => error(
+                StatusCode::FORBIDDEN,
+                &format!("This token lacks the {scope} scope"),
+            ),
+        }
+    }
+}
+
+/// Whether `granted` covers `required`, as the server decides it
+fn has_scope(granted: &[&str], required: &str) -> bool {
+    let rank = |scope: &str| SCOPES.iter().position(|&known| known == scope);
+    let required = rank(required);
+    granted.iter().any(|&scope| rank(scope) >= required)
+}
+
+type Shared = Arc<Mutex<StubState>>;
+
+fn lock(state: &Shared) -> MutexGuard<'_, StubState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Error body in the server's (FastAPI) shape
+fn error(status: StatusCode, detail: &str) -> Response {
+    (status, Json(json!({ "detail": detail }))).into_response()
+}
+
+fn unauthorized() -> Response {
+    error(StatusCode::UNAUTHORIZED, "Could not validate credentials")
+}
+
+/// A running stub server, shut down when dropped
+pub struct StubServer {
+    addr: SocketAddr,
+    state: Shared,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl StubServer {
+    /// Start on a free local port
+    pub async fn start() -> io::Result<Self> {
+        Self::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+
+    /// Start on `addr`
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Shared::default();
+        let (shutdown, stopped) = oneshot::channel();
+        let app = router(state.clone());
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = stopped.await;
+                })
+                .await;
+        });
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL, for `api.endpoint`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Register an account without going through `/auth/users`
+    pub fn add_user(&self, username: &str, password: &str) {
+        lock(&self.state).add_account(username, &format!("{username}@example.com"), password);
+    }
+
+    /// API key of an account, for clients that skip logging in
+    pub fn api_key(&self, username: &str) -> Option<String> {
+        lock(&self.state)
+            .accounts
+            .get(username)
+            .map(|account| account.api_key.clone())
+    }
+
+    /// Recordings uploaded so far, oldest first
+    pub fn uploads(&self) -> Vec<UploadedRecording> {
+        lock(&self.state).uploads.clone()
+    }
+
+    /// Test uploads received so far, oldest first
+    pub fn test_uploads(&self) -> Vec<UploadedRecording> {
+        lock(&self.state).test_uploads.clone()
+    }
+
+    /// Telemetry events received so far
+    pub fn telemetry_events(&self) -> Vec<serde_json::Value> {
+        lock(&self.state).telemetry.clone()
+    }
+
+    /// Answer the resumable upload endpoints with 404, as older servers do
+    pub fn disable_resumable_uploads(&self) {
+        lock(&self.state).whole_uploads_only = true;
+    }
+
+    /// Token balance of an account, 0 for unknown accounts
+    pub fn balance(&self, username: &str) -> i32 {
+        lock(&self.state)
+            .accounts
+            .get(username)
+            .map_or(0, Account::balance)
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn router(state: Shared) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/auth/users", post(register))
+        .route("/auth/token", post(login))
+        .route("/tokens/balance", get(token_balance))
+        .route("/tokens/history", get(token_history))
+        .route("/campaigns", get(campaigns))
+        .route("/recordings", get(recordings))
+        .route("/telemetry", post(telemetry))
+        .route("/recordings/upload", post(upload))
+        .route("/recordings/upload/test", post(test_upload))
+        .route("/recordings/uploads", post(start_resumable))
+        .route("/recordings/uploads/:upload_id", put(upload_chunk))
+        .route(
+            "/recordings/uploads/:upload_id/complete",
+            post(complete_resumable),
+        )
+        .with_state(state)
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "healthy", "timestamp": Utc::now() }))
+}
+
+#[derive(Deserialize)]
+struct RegisterRequest {
+    username: String,
+    email: String,
+    password: String,
+}
+
+async fn register(State(state): State<Shared>, Json(request): Json<RegisterRequest>) -> Response {
+    let mut state = lock(&state);
+    if state.accounts.contains_key(&request.username) {
+        return error(StatusCode::BAD_REQUEST, "Username already registered");
+    }
+    if state.accounts.values().any(|a| a.email == request.email) {
+        return error(StatusCode::BAD_REQUEST, "Email already registered");
+    }
+    let account = state.add_account(&request.username, &request.email, &request.password);
+    Json(json!({
+        "id": account.id,
+        "username": request.username,
+        "email": account.email,
+        "api_key": account.api_key,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+    scope: Option<String>,
+}
+
+async fn login(State(state): State<Shared>, Form(form): Form<LoginForm>) -> Response {
+    let mut state = lock(&state);
+    let api_key = match state.accounts.get(&form.username) {
+        Some(account) if account.password == form.password => account.api_key.clone(),
+        _ => return error(StatusCode::UNAUTHORIZED, "Incorrect username or password"),
+    };
+    // As the server: the requested scopes the account may have, or all of
+    // them when none were requested
+    let requested: Vec<&str> = form
+        .scope
+        .as_deref()
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+    let unknown: Vec<&str> = requested
+        .iter()
+        .copied()
+        .filter(|scope| !SCOPES.contains(scope))
+        .collect();
+    if !unknown.is_empty() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            &format!("Unknown scope: {}", unknown.join(" ")),
+        );
+    }
+    let scopes: Vec<String> = if requested.is_empty() {
+        ACCOUNT_SCOPES.map(str::to_string).to_vec()
+    } else {
+        requested
+            .into_iter()
+            .filter(|scope| ACCOUNT_SCOPES.contains(scope))
+            .map(str::to_string)
+            .collect()
+    };
+    if scopes.is_empty() {
+        return error(
+            StatusCode::FORBIDDEN,
+            "None of the requested scopes can be granted",
+        );
+    }
+    // Nor is the account's API key handed to a restricted login
+    let api_key = (scopes.len() == ACCOUNT_SCOPES.len()).then_some(api_key);
+    let access_token = Uuid::new_v4().simple().to_string();
+    let scope = scopes.join(" ");
+    state.sessions.insert(
+        access_token.clone(),
+        Session {
+            username: form.username,
+            scopes,
+        },
+    );
+    Json(json!({
+        "access_token": access_token,
+        "token_type": "bearer",
+        "api_key": api_key,
+        "scope": scope,
+    }))
+    .into_response()
+}
+
+async fn token_balance(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let state = lock(&state);
+    let Some(username) = state.authenticate(&headers) else {
+        return unauthorized();
+    };
+    let transactions = &state.accounts[&username].transactions;
+    let earned: i32 = transactions.iter().map(|t| t.amount.max(0)).sum();
+    let spent: i32 = transactions.iter().map(|t| (-t.amount).max(0)).sum();
+    Json(json!({
+        "balance": earned - spent,
+        "total_earned": earned,
+        "total_spent": spent,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_history_days")]
+    days: i64,
+}
+
+fn default_history_days() -> i64 {
+    30
+}
+
+async fn token_history(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let state = lock(&state);
+    let Some(username) = state.authenticate(&headers) else {
+        return unauthorized();
+    };
+    let since = Utc::now() - Duration::days(query.days);
+    let history: Vec<&Transaction> = state.accounts[&username]
+        .transactions
+        .iter()
+        .rev()
+        .filter(|t| t.date >= since)
+        .collect();
+    Json(history).into_response()
+}
+
+/// Anonymous events, accepted without credentials
+async fn telemetry(
+    State(state): State<Shared>,
+    Json(events): Json<Vec<serde_json::Value>>,
+) -> Response {
+    let received = events.len();
+    lock(&state).telemetry.extend(events);
+    (StatusCode::ACCEPTED, Json(json!({ "received": received }))).into_response()
+}
+
+async fn recordings(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let state = lock(&state);
+    let Some(username) = state.authenticate(&headers) else {
+        return unauthorized();
+    };
+    let recordings: Vec<serde_json::Value> = state
+        .uploads
+        .iter()
+        .rev()
+        .filter(|upload| upload.username == username)
+        .map(|upload| {
+            json!({
+                "id": upload.recording_id,
+                "lang": upload.lang,
+                "status": "completed",
+            })
+        })
+        .collect();
+    Json(recordings).into_response()
+}
+
+async fn campaigns(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    match lock(&state).authenticate(&headers) {
+        Some(_) => Json(Vec::<serde_json::Value>::new()).into_response(),
+        None => unauthorized(),
+    }
+}
+
+async fn upload(State(state): State<Shared>, headers: HeaderMap, multipart: Multipart) -> Response {
+    match received_upload(&state, &headers, multipart).await {
+        Ok(recording) => accept(&mut lock(&state), recording),
+        Err(response) => response,
+    }
+}
+
+/// Take an upload as `/recordings/upload` would, without keeping it or
+/// awarding tokens
+async fn test_upload(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Response {
+    let recording = match received_upload(&state, &headers, multipart).await {
+        Ok(recording) => recording,
+        Err(response) => return response,
+    };
+    if recording.audio_bytes == 0 {
+        return error(StatusCode::BAD_REQUEST, "No audio received");
+    }
+    let response = Json(json!({
+        "status": "success",
+        "recording_id": recording.recording_id,
+        "tokens_awarded": 0,
+        "bytes_received": recording.audio_bytes,
+        "message": "Test upload received; nothing was stored",
+    }));
+    lock(&state).test_uploads.push(recording);
+    response.into_response()
+}
+
+/// The recording in a whole upload's form, from an account with the
+/// `upload` scope
+async fn received_upload(
+    state: &Shared,
+    headers: &HeaderMap,
+    multipart: Multipart,
+) -> Result<UploadedRecording, Response> {
+    let username = lock(state)
+        .authorize(headers, "upload")
+        .map_err(IntoResponse::into_response)?;
+    let fields = read_form(multipart)
+        .await
+        .map_err(|detail| error(StatusCode::BAD_REQUEST, &detail))?;
+    let text = |name: &str| {
+        fields
+            .get(name)
+            .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+    };
+    let (Some(recording_id), Some(lang), Some(qc_metrics), Some(file_path)) = (
+        text("recording_id"),
+        text("lang"),
+        text("qc_metrics"),
+        text("file_path"),
+    ) else {
+        return Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Missing form field",
+        ));
+    };
+    let qc_metrics: serde_json::Value = serde_json::from_str(&qc_metrics)
+        .map_err(|e| error(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    let (file_name, audio_bytes) = fields
+        .get("file")
+        .map_or((None, 0), |(name, data)| (name.clone(), data.len()));
+
+    Ok(UploadedRecording {
+        recording_id,
+        username,
+        lang,
+        qc_metrics,
+        file_path,
+        campaign_id: text("campaign_id"),
+        transcript: text("transcript"),
+        file_name,
+        audio_bytes,
+        chunks: 0,
+        tokens_awarded: 0,
+    })
+}
+
+/// Award tokens for a received recording and keep it
+fn accept(state: &mut StubState, mut recording: UploadedRecording) -> Response {
+    let tokens_awarded = tokens_for(&recording.qc_metrics);
+    recording.tokens_awarded = tokens_awarded;
+    let account = state
+        .accounts
+        .get_mut(&recording.username)
+        .expect("authenticated account exists");
+    let balance = account.balance() + tokens_awarded;
+    account.transactions.push(Transaction {
+        id: Uuid::new_v4().to_string(),
+        transaction_type: "recording".to_string(),
+        amount: tokens_awarded,
+        balance,
+        date: Utc::now(),
+        notes: format!("Recording upload: {}", recording.lang),
+        recording_id: Some(recording.recording_id.clone()),
+    });
+    let recording_id = recording.recording_id.clone();
+    state.uploads.push(recording);
+
+    Json(json!({
+        "status": "success",
+        "recording_id": recording_id,
+        "tokens_awarded": tokens_awarded,
+        "message": format!("Recording uploaded successfully! Earned {tokens_awarded} tokens."),
+    }))
+    .into_response()
+}
+
+/// Open a resumable upload, or report how far the open one for the same
+/// recording got
+async fn start_resumable(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Json(start): Json<ResumableStart>,
+) -> Response {
+    let mut state = lock(&state);
+    if state.whole_uploads_only {
+        return error(StatusCode::NOT_FOUND, "Not Found");
+    }
+    let username = match state.authorize(&headers, "upload") {
+        Ok(username) => username,
+        Err(denied) => return denied.into_response(),
+    };
+    let open = state.resumable.iter().find(|(_, upload)| {
+        upload.username == username && upload.start.recording_id == start.recording_id
+    });
+    if let Some((upload_id, upload)) = open {
+        return Json(json!({ "upload_id": upload_id, "offset": upload.data.len() }))
+            .into_response();
+    }
+    let upload_id = Uuid::new_v4().to_string();
+    state.resumable.insert(
+        upload_id.clone(),
+        ResumableUpload {
+            username,
+            start,
+            data: Vec::new(),
+            chunks: 0,
+        },
+    );
+    Json(json!({ "upload_id": upload_id, "offset": 0 })).into_response()
+}
+
+/// Append a chunk sent at the offset the upload has reached
+async fn upload_chunk(
+    State(state): State<Shared>,
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut state = lock(&state);
+    let username = match state.authorize(&headers, "upload") {
+        Ok(username) => username,
+        Err(denied) => return denied.into_response(),
+    };
+    let Some(upload) = state
+        .resumable
+        .get_mut(&upload_id)
+        .filter(|upload| upload.username == username)
+    else {
+        return error(StatusCode::NOT_FOUND, "Upload not found");
+    };
+    let offset = headers
+        .get("upload-offset")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if offset != Some(upload.data.len()) {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "offset": upload.data.len() })),
+        )
+            .into_response();
+    }
+    if upload.data.len() + body.len() > upload.start.size {
+        return error(StatusCode::BAD_REQUEST, "Chunk runs past the declared size");
+    }
+    upload.data.extend_from_slice(&body);
+    upload.chunks += 1;
+    Json(json!({ "offset": upload.data.len() })).into_response()
+}
+
+/// Take a fully received resumable upload as a recording
+async fn complete_resumable(
+    State(state): State<Shared>,
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let mut state = lock(&state);
+    let username = match state.authorize(&headers, "upload") {
+        Ok(username) => username,
+        Err(denied) => return denied.into_response(),
+    };
+    match state.resumable.get(&upload_id) {
+        Some(upload) if upload.username == username => {
+            if upload.data.len() < upload.start.size {
+                return error(StatusCode::CONFLICT, "Upload is incomplete");
+            }
+        }
+        _ => return error(StatusCode::NOT_FOUND, "Upload not found"),
+    }
+    let upload = state.resumable.remove(&upload_id).expect("upload checked");
+    let qc_metrics: serde_json::Value = match serde_json::from_str(&upload.start.qc_metrics) {
+        Ok(metrics) => metrics,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let start = upload.start;
+    accept(
+        &mut state,
+        UploadedRecording {
+            recording_id: start.recording_id,
+            username,
+            lang: start.lang,
+            qc_metrics,
+            file_path: start.file_path,
+            campaign_id: start.campaign_id,
+            transcript: start.transcript,
+            file_name: start.file_name,
+            audio_bytes: upload.data.len(),
+            chunks: upload.chunks,
+            tokens_awarded: 0,
+        },
+    )
+}
+
+/// Fields of a multipart form by name, with the file name of file parts
+async fn read_form(
+    mut multipart: Multipart,
+) -> Result<HashMap<String, (Option<String>, Vec<u8>)>, String> {
+    let mut fields = HashMap::new();
+    while let Some(field) = multipart.next_field().await.map_err(|e| e.to_string())? {
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field.file_name().map(str::to_string);
+        let data = field.bytes().await.map_err(|e| e.to_string())?;
+        fields.insert(name, (file_name, data.to_vec()));
+    }
+    Ok(fields)
+}
+
+/// Base reward plus the server's bonuses for SNR above 20 dB, clipping
+/// below 1% and a speech ratio above 0.3
+fn tokens_for(metrics: &serde_json::Value) -> i32 {
+    let metric =
+        |key: &str, missing: f64| metrics.get(key).and_then(|v| v.as_f64()).unwrap_or(missing);
+    let mut tokens = TOKENS_PER_MINUTE;
+    if metric("snr_db", 0.0) > 20.0 {
+        tokens += 2;
+    }
+    if metric("clipping_pct", 100.0) < 1.0 {
+        tokens += 1;
+    }
+    if metric("vad_ratio", 0.0) > 0.3 {
+        tokens += 1;
+    }
+    tokens
+}
@@ -0,0 +1,28 @@
+//! Run the stub server for demos: `cowcow_server_stub [ADDR] [USER PASSWORD]`
+//!
+//! Listens on 127.0.0.1:8000 (the CLI's default endpoint) unless given
+//! another address, optionally with an account already registered.
+
+use std::net::SocketAddr;
+
+use cowcow_server_stub::StubServer;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let addr: SocketAddr = match args.first() {
+        Some(addr) => addr.parse().map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{addr}: {e}"))
+        })?,
+        None => SocketAddr::from(([127, 0, 0, 1], 8000)),
+    };
+
+    let server = StubServer::bind(addr).await?;
+    if let [_, username, password, ..] = args.as_slice() {
+        server.add_user(username, password);
+        println!("Registered {username}");
+    }
+    println!("Stub server listening on {}; Ctrl-C to stop", server.url());
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! C API for the Android (JNI) and iOS (Swift) wrappers
+//!
+//! Sessions are opaque handles guarded by a mutex, so the audio callback
+//! thread may push samples while the UI thread pauses or stops the session.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::sync::Mutex;
+
+use cowcow_core::QcMetrics;
+use tracing::error;
+
+use crate::{RecordingSession, SessionObserver, SessionOptions, SessionState};
+
+/// Called with the metrics of each analyzed chunk
+pub type MetricsCallback = extern "C" fn(user_data: *mut c_void, metrics: *const QcMetrics);
+
+/// Called whenever the session changes state
+pub type StateCallback = extern "C" fn(user_data: *mut c_void, state: SessionState);
+
+/// Opaque session handle
+pub struct CowcowSession(Mutex<RecordingSession>);
+
+struct CallbackObserver {
+    on_metrics: Option<MetricsCallback>,
+    on_state: Option<StateCallback>,
+    user_data: *mut c_void,
+}
+
+// The caller owns `user_data` and guarantees it may be used from the thread
+// driving the session, as documented on `cowcow_session_start`.
+unsafe impl Send for CallbackObserver {}
+
+impl SessionObserver for CallbackObserver {
+    fn on_metrics(&mut self, metrics: &QcMetrics) {
+        if let Some(callback) = self.on_metrics {
+            callback(self.user_data, metrics);
+        }
+    }
+
+    fn on_state_changed(&mut self, state: SessionState) {
+        if let Some(callback) = self.on_state {
+            callback(self.user_data, state);
+        }
+    }
+}
+
+/// Start a session writing to `output_dir`
+///
+/// Returns null on failure. Callbacks are invoked with `user_data` on
+/// whichever thread calls into the session.
+///
+/// # Safety
+///
+/// `output_dir` must be a valid NUL-terminated string and `user_data` must
+/// stay valid until the session is freed.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_start(
+    output_dir: *const c_char,
+    sample_rate: u32,
+    channels: u16,
+    on_metrics: Option<MetricsCallback>,
+    on_state: Option<StateCallback>,
+    user_data: *mut c_void,
+) -> *mut CowcowSession {
+    if output_dir.is_null() {
+        return std::ptr::null_mut();
+    }
+    let output_dir = CStr::from_ptr(output_dir).to_string_lossy().into_owned();
+
+    let observer = CallbackObserver {
+        on_metrics,
+        on_state,
+        user_data,
+    };
+    match RecordingSession::start(
+        SessionOptions::new(output_dir, sample_rate, channels),
+        Some(Box::new(observer)),
+    ) {
+        Ok(session) => Box::into_raw(Box::new(CowcowSession(Mutex::new(session)))),
+        Err(e) => {
+            error!("Failed to start recording session: {}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Push `len` interleaved samples; returns 0 on success and -1 on failure
+///
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start` and `samples` must point
+/// to at least `len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_push(
+    session: *mut CowcowSession,
+    samples: *const f32,
+    len: usize,
+) -> i32 {
+    if samples.is_null() && len > 0 {
+        return -1;
+    }
+    let samples = if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(samples, len)
+    };
+    with_session(session, |s| s.push_samples(samples))
+}
+
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start`.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_pause(session: *mut CowcowSession) -> i32 {
+    with_session(session, RecordingSession::pause)
+}
+
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start`.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_resume(session: *mut CowcowSession) -> i32 {
+    with_session(session, RecordingSession::resume)
+}
+
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start`.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_state(session: *mut CowcowSession) -> SessionState {
+    match session.as_ref() {
+        Some(session) => lock(session).state(),
+        None => SessionState::Stopped,
+    }
+}
+
+/// Finalize the recording
+///
+/// Returns the path of the WAV file (free it with `cowcow_string_free`) and
+/// writes the file-level metrics to `out_metrics` when it is not null.
+/// Returns null on failure.
+///
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start` and `out_metrics` must be
+/// null or point to writable memory for one `QcMetrics`.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_stop(
+    session: *mut CowcowSession,
+    out_metrics: *mut QcMetrics,
+) -> *mut c_char {
+    let Some(session) = session.as_ref() else {
+        return std::ptr::null_mut();
+    };
+
+    match lock(session).stop() {
+        Ok(finished) => {
+            if !out_metrics.is_null() {
+                out_metrics.write(finished.metrics);
+            }
+            CString::new(finished.wav_path.to_string_lossy().into_owned())
+                .map(CString::into_raw)
+                .unwrap_or(std::ptr::null_mut())
+        }
+        Err(e) => {
+            error!("Failed to stop recording session: {}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Discard the recording; returns 0 on success and -1 on failure
+///
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start`.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_abort(session: *mut CowcowSession) -> i32 {
+    with_session(session, RecordingSession::abort)
+}
+
+/// Release a session handle
+///
+/// A session freed without being stopped keeps its `.wav.partial` file so
+/// the app can recover it later.
+///
+/// # Safety
+///
+/// `session` must come from `cowcow_session_start` and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_session_free(session: *mut CowcowSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+///
+/// `value` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cowcow_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+fn lock(session: &CowcowSession) -> std::sync::MutexGuard<'_, RecordingSession> {
+    // A panic inside a callback must not brick the session for other threads
+    session
+        .0
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+unsafe fn with_session<F>(session: *mut CowcowSession, action: F) -> i32
+where
+    F: FnOnce(&mut RecordingSession) -> Result<(), crate::SessionError>,
+{
+    let Some(session) = session.as_ref() else {
+        return -1;
+    };
+
+    match action(&mut lock(session)) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("Recording session error: {}", e);
+            -1
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Long-lived recording sessions for the Cowcow companion apps
+//!
+//! The platform owns the microphone (an Android foreground service or an iOS
+//! audio session) and pushes captured buffers into a [`RecordingSession`],
+//! which runs QC, writes the WAV file and reports progress through a
+//! [`SessionObserver`]. The C API in [`ffi`] wraps the same state machine.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use cowcow_core::timeline::{QcTimeline, TIMELINE_FRAME_MS};
+use cowcow_core::{AudioError, AudioProcessor, ChannelMetrics, ProcessorConfig, QcMetrics};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub mod ffi;
+
+/// Default length of audio analyzed per metrics update
+pub const DEFAULT_CHUNK_MS: u32 = TIMELINE_FRAME_MS;
+
+/// Extension used while a recording is still being written
+pub const PARTIAL_EXTENSION: &str = "wav.partial";
+
+/// Lifecycle of a recording session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(C)]
+pub enum SessionState {
+    Recording,
+    Paused,
+    Stopped,
+}
+
+/// Receives progress from a running session
+///
+/// Callbacks run on the thread that drives the session (the one calling
+/// `push_samples`, `pause`, `resume` or `stop`), so implementations should
+/// hand work off to the UI thread rather than block.
+pub trait SessionObserver: Send {
+    /// QC metrics for the most recently analyzed chunk
+    fn on_metrics(&mut self, _metrics: &QcMetrics) {}
+
+    /// The session moved to a new state
+    fn on_state_changed(&mut self, _state: SessionState) {}
+}
+
+/// Recording session errors
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("Cannot {action} a session that is {state:?}")]
+    InvalidState {
+        action: &'static str,
+        state: SessionState,
+    },
+    #[error("Failed to create audio processor: {0}")]
+    Processor(String),
+    #[error(transparent)]
+    Audio(#[from] AudioError),
+    #[error("Failed to write recording: {0}")]
+    Wav(#[from] hound::Error),
+    #[error("Failed to access recording file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Settings for a new recording session
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    /// Directory the finished WAV file is written to
+    pub output_dir: PathBuf,
+    /// Recording id, generated when not provided
+    pub recording_id: Option<String>,
+    /// QC pipeline settings, including the capture rate and channel count
+    pub processor: ProcessorConfig,
+    /// Milliseconds of audio per metrics update
+    pub chunk_ms: u32,
+}
+
+impl SessionOptions {
+    pub fn new<P: Into<PathBuf>>(output_dir: P, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            recording_id: None,
+            processor: ProcessorConfig::new(sample_rate, channels),
+            chunk_ms: DEFAULT_CHUNK_MS,
+        }
+    }
+}
+
+/// A recording that was stopped and written to disk
+#[derive(Debug, Clone)]
+pub struct FinishedRecording {
+    pub id: String,
+    pub wav_path: PathBuf,
+    pub metrics: QcMetrics,
+    pub channel_metrics: Vec<ChannelMetrics>,
+}
+
+/// Push-based recording session: start, pause/resume, stop
+///
+/// Audio is written to `<id>.wav.partial` and renamed to `<id>.wav` on
+/// [`stop`](Self::stop), so a session killed by the OS leaves a valid but
+/// clearly unfinished file behind instead of a truncated recording. The
+/// per-chunk metrics are saved next to it as a QC timeline (`<id>.qc.json`).
+pub struct RecordingSession {
+    id: String,
+    state: SessionState,
+    processor: AudioProcessor,
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+    partial_path: PathBuf,
+    wav_path: PathBuf,
+    pending: Vec<f32>,
+    chunk_len: usize,
+    timeline: QcTimeline,
+    samples_written: u64,
+    observer: Option<Box<dyn SessionObserver>>,
+}
+
+impl RecordingSession {
+    /// Open the output file and begin recording
+    pub fn start(
+        options: SessionOptions,
+        observer: Option<Box<dyn SessionObserver>>,
+    ) -> Result<Self, SessionError> {
+        let processor = AudioProcessor::from_config(options.processor)
+            .map_err(|e| SessionError::Processor(e.to_string()))?;
+        let id = options
+            .recording_id
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        fs::create_dir_all(&options.output_dir)?;
+        let wav_path = options.output_dir.join(format!("{id}.wav"));
+        let partial_path = options.output_dir.join(format!("{id}.{PARTIAL_EXTENSION}"));
+
+        let spec = hound::WavSpec {
+            channels: processor.channels(),
+            sample_rate: processor.sample_rate(),
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(&partial_path, spec)?;
+
+        let frames_per_chunk = (processor.sample_rate() * options.chunk_ms.max(1) / 1000).max(1);
+        let chunk_len = frames_per_chunk as usize * processor.channels() as usize;
+
+        info!("Started recording session {}", id);
+        let mut session = Self {
+            id,
+            state: SessionState::Recording,
+            processor,
+            writer: Some(writer),
+            partial_path,
+            wav_path,
+            pending: Vec::with_capacity(chunk_len),
+            chunk_len,
+            timeline: QcTimeline::new().with_frame_secs(options.chunk_ms.max(1) as f32 / 1000.0),
+            samples_written: 0,
+            observer,
+        };
+        session.notify_state();
+        Ok(session)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// Seconds of audio written so far, excluding paused time
+    pub fn elapsed_secs(&self) -> f64 {
+        let samples_per_second =
+            self.processor.sample_rate() as f64 * self.processor.channels() as f64;
+        self.samples_written as f64 / samples_per_second
+    }
+
+    /// Metrics for everything analyzed so far
+    pub fn metrics(&self) -> QcMetrics {
+        self.timeline.summary()
+    }
+
+    /// Feed interleaved `f32` samples captured by the platform
+    ///
+    /// Samples pushed while paused are discarded, so audio callbacks may keep
+    /// firing during a pause.
+    pub fn push_samples(&mut self, samples: &[f32]) -> Result<(), SessionError> {
+        match self.state {
+            SessionState::Recording => {}
+            SessionState::Paused => return Ok(()),
+            SessionState::Stopped => {
+                return Err(SessionError::InvalidState {
+                    action: "push samples to",
+                    state: self.state,
+                })
+            }
+        }
+
+        self.pending.extend_from_slice(samples);
+        while self.pending.len() >= self.chunk_len {
+            let chunk: Vec<f32> = self.pending.drain(..self.chunk_len).collect();
+            self.write_chunk(&chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<(), SessionError> {
+        self.transition("pause", SessionState::Recording, SessionState::Paused)
+    }
+
+    pub fn resume(&mut self) -> Result<(), SessionError> {
+        self.transition("resume", SessionState::Paused, SessionState::Recording)
+    }
+
+    /// Flush buffered audio, finalize the WAV file and return its metrics
+    pub fn stop(&mut self) -> Result<FinishedRecording, SessionError> {
+        if self.state == SessionState::Stopped {
+            return Err(SessionError::InvalidState {
+                action: "stop",
+                state: self.state,
+            });
+        }
+
+        // Keep whole frames only; a trailing partial frame cannot be written
+        let channels = self.processor.channels() as usize;
+        let tail_len = self.pending.len() - self.pending.len() % channels;
+        let tail: Vec<f32> = self.pending.drain(..).take(tail_len).collect();
+        if !tail.is_empty() {
+            self.write_chunk(&tail)?;
+        }
+
+        if let Some(writer) = self.writer.take() {
+            writer.finalize()?;
+        }
+        fs::rename(&self.partial_path, &self.wav_path)?;
+        if let Err(e) = self
+            .timeline
+            .save(&QcTimeline::sidecar_path(&self.wav_path))
+        {
+            warn!("Failed to save QC timeline: {}", e);
+        }
+
+        self.state = SessionState::Stopped;
+        self.notify_state();
+        info!(
+            "Stopped recording session {} ({:.1}s)",
+            self.id,
+            self.elapsed_secs()
+        );
+
+        Ok(FinishedRecording {
+            id: self.id.clone(),
+            wav_path: self.wav_path.clone(),
+            metrics: self.metrics(),
+            channel_metrics: self.processor.channel_metrics(),
+        })
+    }
+
+    /// Stop without keeping the recording
+    pub fn abort(&mut self) -> Result<(), SessionError> {
+        if self.state == SessionState::Stopped {
+            return Err(SessionError::InvalidState {
+                action: "abort",
+                state: self.state,
+            });
+        }
+
+        drop(self.writer.take());
+        remove_partial(&self.partial_path);
+        self.pending.clear();
+        self.state = SessionState::Stopped;
+        self.notify_state();
+        Ok(())
+    }
+
+    fn transition(
+        &mut self,
+        action: &'static str,
+        from: SessionState,
+        to: SessionState,
+    ) -> Result<(), SessionError> {
+        if self.state != from {
+            return Err(SessionError::InvalidState {
+                action,
+                state: self.state,
+            });
+        }
+        self.state = to;
+        self.notify_state();
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: &[f32]) -> Result<(), SessionError> {
+        let metrics = self.processor.process_chunk(chunk);
+
+        if let Some(writer) = self.writer.as_mut() {
+            for &sample in chunk {
+                writer.write_sample((sample.clamp(-1.0, 1.0) * 32767.0) as i16)?;
+            }
+        }
+        self.samples_written += chunk.len() as u64;
+
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_metrics(&metrics);
+        }
+        self.timeline.push(metrics);
+        Ok(())
+    }
+
+    fn notify_state(&mut self) {
+        let state = self.state;
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_state_changed(state);
+        }
+    }
+}
+
+fn remove_partial(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        warn!("Failed to remove {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Events {
+        metrics: usize,
+        states: Vec<SessionState>,
+    }
+
+    struct Recorder(Arc<Mutex<Events>>);
+
+    impl SessionObserver for Recorder {
+        fn on_metrics(&mut self, _metrics: &QcMetrics) {
+            self.0.lock().unwrap().metrics += 1;
+        }
+
+        fn on_state_changed(&mut self, state: SessionState) {
+            self.0.lock().unwrap().states.push(state);
+        }
+    }
+
+    fn tone(seconds: f32) -> Vec<f32> {
+        (0..(16000.0 * seconds) as usize)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let output_dir = std::env::temp_dir().join(format!("cowcow-session-{}", Uuid::new_v4()));
+        let events = Arc::new(Mutex::new(Events::default()));
+        let mut session = RecordingSession::start(
+            SessionOptions::new(&output_dir, 16000, 1),
+            Some(Box::new(Recorder(events.clone()))),
+        )
+        .unwrap();
+
+        session.push_samples(&tone(1.0)).unwrap();
+        session.pause().unwrap();
+        assert!(session.pause().is_err());
+        session.push_samples(&tone(1.0)).unwrap();
+        session.resume().unwrap();
+        session.push_samples(&tone(0.55)).unwrap();
+
+        let finished = session.stop().unwrap();
+        assert!(finished.wav_path.exists());
+        assert!(QcTimeline::sidecar_path(&finished.wav_path).exists());
+        assert!(!output_dir
+            .join(format!("{}.{PARTIAL_EXTENSION}", finished.id))
+            .exists());
+        assert!((finished.metrics.duration_secs - 1.55).abs() < 0.01);
+        assert!(session.push_samples(&tone(0.1)).is_err());
+
+        let reader = hound::WavReader::open(&finished.wav_path).unwrap();
+        assert_eq!(reader.duration(), 24800);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.metrics, 16);
+        assert_eq!(
+            events.states,
+            vec![
+                SessionState::Recording,
+                SessionState::Paused,
+                SessionState::Recording,
+                SessionState::Stopped
+            ]
+        );
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -116,7 +116,7 @@ cargo clean
 
 # Build core library
 info "Building cowcow_core..."
-cargo build --release -p cowcow_core || error "Failed to build cowcow_core"
+cargo build --release -p cowcow_core --features ffi || error "Failed to build cowcow_core"
 success "cowcow_core built successfully"
 
 # Build CLI
@@ -177,7 +177,7 @@ fi
 
 # Test core library
 info "Testing core library..."
-if cargo test -p cowcow_core --release > /dev/null 2>&1; then
+if cargo test -p cowcow_core --features ffi --release > /dev/null 2>&1; then
     success "Core library tests passed"
 else
     warning "Core library tests failed or not found"
@@ -5,10 +5,12 @@ from fastapi import APIRouter, Depends, HTTPException, status
 from fastapi.security import OAuth2PasswordBearer, OAuth2PasswordRequestForm
 from pydantic import BaseModel, EmailStr
 from sqlalchemy.orm import Session
+import hashlib
 import secrets
+import uuid
 
 from database import get_db
-from models import User
+from models import Enrollment, User
 
 router = APIRouter()
 oauth2_scheme = OAuth2PasswordBearer(tokenUrl="token")
@@ -19,10 +21,21 @@ ALGORITHM = "HS256"
 ACCESS_TOKEN_EXPIRE_MINUTES = 30
 API_KEY_LENGTH = 32
 
+# What a token may be used for, from least to most privileged
+SCOPES = ["record", "upload", "admin"]
+
+# Scopes a paired device may be given; administration stays with people
+ENROLLMENT_SCOPES = ["record", "upload"]
+ENROLLMENT_TOKEN_LENGTH = 32
+MAX_ENROLLMENT_EXPIRY_SECS = 7 * 24 * 60 * 60
+
 class Token(BaseModel):
     access_token: str
     token_type: str
-    api_key: str
+    # Only for logins granted everything the account may do: the key
+    # carries no scope of its own
+    api_key: Optional[str] = None
+    scope: str
 
 class UserCreate(BaseModel):
     username: str
@@ -36,6 +49,92 @@ class UserResponse(BaseModel):
     role: str
     api_key: str
 
+class EnrollmentCreate(BaseModel):
+    scope: str
+    campaign_id: Optional[str] = None
+    expires_in_secs: int = 15 * 60
+    label: Optional[str] = None
+
+class EnrollmentResponse(BaseModel):
+    id: str
+    token: Optional[str] = None
+    scope: str
+    campaign_id: Optional[str] = None
+    label: Optional[str] = None
+    expires_at: str
+    redeemed_at: Optional[str] = None
+    device_name: Optional[str] = None
+    revoked_at: Optional[str] = None
+
+class EnrollmentRedeem(BaseModel):
+    token: str
+    device_name: Optional[str] = None
+
+class DeviceToken(BaseModel):
+    access_token: str
+    token_type: str
+    scope: str
+    campaign_id: Optional[str] = None
+
+def utc_timestamp(moment: Optional[datetime]) -> Optional[str]:
+    """RFC 3339 timestamp of a naive UTC datetime."""
+    return moment.strftime("%Y-%m-%dT%H:%M:%SZ") if moment else None
+
+def hash_enrollment_token(token: str) -> str:
+    return hashlib.sha256(token.encode()).hexdigest()
+
+def enrollment_response(enrollment: Enrollment, token: Optional[str] = None) -> EnrollmentResponse:
+    return EnrollmentResponse(
+        id=enrollment.id,
+        token=token,
+        scope=enrollment.scope,
+        campaign_id=enrollment.campaign_id,
+        label=enrollment.label,
+        expires_at=utc_timestamp(enrollment.expires_at),
+        redeemed_at=utc_timestamp(enrollment.redeemed_at),
+        device_name=enrollment.device_name,
+        revoked_at=utc_timestamp(enrollment.revoked_at),
+    )
+
+def enrollment_revoked(db: Session, payload: dict) -> bool:
+    """Whether a token was issued to a paired device since revoked."""
+    enrollment_id = payload.get("enrollment")
+    if enrollment_id is None:
+        return False
+    enrollment = db.query(Enrollment).filter(Enrollment.id == enrollment_id).first()
+    return enrollment is None or enrollment.revoked_at is not None
+
+def grant_scopes(role: str, requested: list[str]) -> list[str]:
+    """Scopes granted to a user of `role`: the requested ones it may have,
+    or all of them when none were requested."""
+    allowed = SCOPES if role == "admin" else [s for s in SCOPES if s != "admin"]
+    if not requested:
+        return allowed
+    unknown = [s for s in requested if s not in SCOPES]
+    if unknown:
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail=f"Unknown scope: {' '.join(unknown)}",
+        )
+    return [s for s in requested if s in allowed]
+
+def token_scopes(payload: dict) -> list[str]:
+    """Scopes a decoded access token was issued with."""
+    return payload.get("scope", "").split()
+
+def has_scope(granted: list[str], required: str) -> bool:
+    """Whether `granted` covers `required`; each scope includes the ones
+    listed before it in SCOPES."""
+    rank = SCOPES.index(required)
+    return any(s in SCOPES and SCOPES.index(s) >= rank for s in granted)
+
+def insufficient_scope(scope: str) -> HTTPException:
+    return HTTPException(
+        status_code=status.HTTP_403_FORBIDDEN,
+        detail=f"This token lacks the {scope} scope",
+        headers={"WWW-Authenticate": f'Bearer scope="{scope}"'},
+    )
+
 def create_access_token(data: dict, expires_delta: Optional[timedelta] = None):
     to_encode = data.copy()
     if expires_delta:
@@ -46,10 +145,11 @@ def create_access_token(data: dict, expires_delta: Optional[timedelta] = None):
     encoded_jwt = jwt.encode(to_encode, SECRET_KEY, algorithm=ALGORITHM)
     return encoded_jwt
 
-async def get_current_user(
+async def get_token_payload(
     token: str = Depends(oauth2_scheme),
     db: Session = Depends(get_db)
-) -> User:
+) -> dict:
+    """Claims of the caller's access token, once it is known to be valid."""
     credentials_exception = HTTPException(
         status_code=status.HTTP_401_UNAUTHORIZED,
         detail="Could not validate credentials",
@@ -57,15 +157,25 @@ async def get_current_user(
     )
     try:
         payload = jwt.decode(token, SECRET_KEY, algorithms=[ALGORITHM])
-        username: str = payload.get("sub")
-        if username is None:
+        if payload.get("sub") is None:
             raise credentials_exception
     except jwt.JWTError:
         raise credentials_exception
-    
-    user = db.query(User).filter(User.username == username).first()
-    if user is None:
+    if enrollment_revoked(db, payload):
         raise credentials_exception
+    return payload
+
+async def get_current_user(
+    payload: dict = Depends(get_token_payload),
+    db: Session = Depends(get_db)
+) -> User:
+    user = db.query(User).filter(User.username == payload["sub"]).first()
+    if user is None:
+        raise HTTPException(
+            status_code=status.HTTP_401_UNAUTHORIZED,
+            detail="Could not validate credentials",
+            headers={"WWW-Authenticate": "Bearer"},
+        )
     return user
 
 @router.post("/token", response_model=Token)
@@ -90,14 +200,26 @@ async def login_for_access_token(
         user.api_key = secrets.token_hex(API_KEY_LENGTH)
         db.commit()
 
+    scopes = grant_scopes(user.role, form_data.scopes)
+    if not scopes:
+        raise HTTPException(
+            status_code=status.HTTP_403_FORBIDDEN,
+            detail="None of the requested scopes can be granted",
+        )
+
     access_token_expires = timedelta(minutes=ACCESS_TOKEN_EXPIRE_MINUTES)
     access_token = create_access_token(
-        data={"sub": user.username}, expires_delta=access_token_expires
+        data={"sub": user.username, "scope": " ".join(scopes)},
+        expires_delta=access_token_expires,
     )
+    # The API key stands for the whole account, so a restricted login must
+    # not hand it out alongside its restricted token
+    unrestricted = set(scopes) == set(grant_scopes(user.role, []))
     return {
         "access_token": access_token,
         "token_type": "bearer",
-        "api_key": user.api_key
+        "api_key": user.api_key if unrestricted else None,
+        "scope": " ".join(scopes),
     }
 
 @router.post("/users", response_model=UserResponse)
@@ -137,4 +259,124 @@ async def regenerate_api_key(current_user: User = Depends(get_current_user), db:
     current_user.api_key = secrets.token_hex(API_KEY_LENGTH)
     db.commit()
     db.refresh(current_user)
-    return current_user 
\ No newline at end of file
+    return current_user 
+
+@router.post("/enrollments", response_model=EnrollmentResponse)
+async def create_enrollment(
+    request: EnrollmentCreate,
+    current_user: User = Depends(get_current_user),
+    payload: dict = Depends(get_token_payload),
+    db: Session = Depends(get_db)
+):
+    """Issue a single-use token a companion device redeems for credentials
+    of its own, limited to the requested scope."""
+    # Paired devices cannot pair further devices, whatever their scope
+    if payload.get("enrollment") is not None:
+        raise HTTPException(
+            status_code=status.HTTP_403_FORBIDDEN,
+            detail="Paired devices cannot pair other devices",
+        )
+    if request.scope not in ENROLLMENT_SCOPES:
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail=f"Devices can only be paired with scope {' or '.join(ENROLLMENT_SCOPES)}",
+        )
+    if request.scope not in grant_scopes(current_user.role, [request.scope]):
+        raise HTTPException(
+            status_code=status.HTTP_403_FORBIDDEN,
+            detail=f"Your account cannot grant the {request.scope} scope",
+        )
+    # A token can only hand on what it was itself granted
+    if not has_scope(token_scopes(payload), request.scope):
+        raise insufficient_scope(request.scope)
+    if not 0 < request.expires_in_secs <= MAX_ENROLLMENT_EXPIRY_SECS:
+        raise HTTPException(
+            status_code=status.HTTP_400_BAD_REQUEST,
+            detail=f"expires_in_secs must be between 1 and {MAX_ENROLLMENT_EXPIRY_SECS}",
+        )
+
+    token = secrets.token_urlsafe(ENROLLMENT_TOKEN_LENGTH)
+    enrollment = Enrollment(
+        id=str(uuid.uuid4()),
+        user_id=current_user.id,
+        token_hash=hash_enrollment_token(token),
+        scope=request.scope,
+        campaign_id=request.campaign_id,
+        label=request.label,
+        expires_at=datetime.utcnow() + timedelta(seconds=request.expires_in_secs),
+    )
+    db.add(enrollment)
+    db.commit()
+    db.refresh(enrollment)
+    # The token is only ever shown here; the server keeps its hash
+    return enrollment_response(enrollment, token)
+
+@router.get("/enrollments", response_model=list[EnrollmentResponse])
+async def list_enrollments(
+    current_user: User = Depends(get_current_user),
+    db: Session = Depends(get_db)
+):
+    enrollments = (
+        db.query(Enrollment)
+        .filter(Enrollment.user_id == current_user.id)
+        .order_by(Enrollment.created_at.desc())
+        .all()
+    )
+    return [enrollment_response(enrollment) for enrollment in enrollments]
+
+@router.delete("/enrollments/{enrollment_id}", status_code=status.HTTP_204_NO_CONTENT)
+async def revoke_enrollment(
+    enrollment_id: str,
+    current_user: User = Depends(get_current_user),
+    db: Session = Depends(get_db)
+):
+    """Revoke an enrollment; the credentials of the device that redeemed it
+    stop working at once."""
+    enrollment = (
+        db.query(Enrollment)
+        .filter(Enrollment.id == enrollment_id, Enrollment.user_id == current_user.id)
+        .first()
+    )
+    if enrollment is None:
+        raise HTTPException(status_code=status.HTTP_404_NOT_FOUND, detail="Enrollment not found")
+    if enrollment.revoked_at is None:
+        enrollment.revoked_at = datetime.utcnow()
+        db.commit()
+
+@router.post("/enrollments/redeem", response_model=DeviceToken)
+async def redeem_enrollment(request: EnrollmentRedeem, db: Session = Depends(get_db)):
+    """Exchange a pairing token for the device's own access token."""
+    enrollment = (
+        db.query(Enrollment)
+        .filter(Enrollment.token_hash == hash_enrollment_token(request.token))
+        .first()
+    )
+    if (
+        enrollment is None
+        or enrollment.revoked_at is not None
+        or enrollment.redeemed_at is not None
+        or enrollment.expires_at <= datetime.utcnow()
+    ):
+        raise HTTPException(
+            status_code=status.HTTP_401_UNAUTHORIZED,
+            detail="Pairing code is invalid, expired or already used",
+        )
+
+    enrollment.redeemed_at = datetime.utcnow()
+    enrollment.device_name = request.device_name
+    db.commit()
+
+    access_token = create_access_token(
+        data={
+            "sub": enrollment.user.username,
+            "scope": enrollment.scope,
+            "enrollment": enrollment.id,
+        },
+        expires_delta=timedelta(minutes=ACCESS_TOKEN_EXPIRE_MINUTES),
+    )
+    return {
+        "access_token": access_token,
+        "token_type": "bearer",
+        "scope": enrollment.scope,
+        "campaign_id": enrollment.campaign_id,
+    }
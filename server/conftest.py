@@ -0,0 +1,28 @@
+"""Shared setup of the server tests: the app on a scratch database and
+upload directory, with one user, `amina`."""
+import os
+import tempfile
+
+# The app opens its database and upload directory on import
+_data_dir = tempfile.mkdtemp()
+os.environ["DATABASE_URL"] = f"sqlite:///{os.path.join(_data_dir, 'cowcow.db')}"
+os.environ["UPLOAD_DIR"] = os.path.join(_data_dir, "uploads")
+
+from fastapi.testclient import TestClient  # noqa: E402
+
+import main  # noqa: E402
+
+client = TestClient(main.app)
+
+client.post(
+    "/auth/users",
+    json={"username": "amina", "email": "amina@example.com", "password": "secret"},
+)
+
+def login(scope: str) -> dict:
+    response = client.post(
+        "/auth/token",
+        data={"username": "amina", "password": "secret", "scope": scope},
+    )
+    assert response.status_code == 200, response.text
+    return response.json()
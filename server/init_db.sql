@@ -35,6 +35,23 @@ CREATE TABLE IF NOT EXISTS recordings (
     CONSTRAINT recordings_status_check CHECK (status IN ('pending', 'uploading', 'completed', 'failed'))
 );
 
+-- Create campaigns table for time-boxed collection drives
+CREATE TABLE IF NOT EXISTS campaigns (
+    id VARCHAR(36) PRIMARY KEY DEFAULT uuid_generate_v4()::text,
+    name VARCHAR(100) NOT NULL,
+    lang VARCHAR(10) NOT NULL,
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ends_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    target_hours REAL NOT NULL,
+    token_multiplier REAL NOT NULL DEFAULT 1.0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT campaigns_dates_check CHECK (ends_at > starts_at)
+);
+
+ALTER TABLE recordings ADD COLUMN IF NOT EXISTS transcript TEXT;
+
+ALTER TABLE recordings ADD COLUMN IF NOT EXISTS campaign_id VARCHAR(36) REFERENCES campaigns(id) ON DELETE SET NULL;
+
 -- Create tokens table for reward system
 CREATE TABLE IF NOT EXISTS tokens (
     id VARCHAR(36) PRIMARY KEY DEFAULT uuid_generate_v4()::text,
@@ -69,6 +86,52 @@ CREATE TABLE IF NOT EXISTS sessions (
     user_agent TEXT
 );
 
+-- Create enrollments table for pairing companion devices
+CREATE TABLE IF NOT EXISTS enrollments (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    scope VARCHAR(20) NOT NULL,
+    campaign_id VARCHAR(36) REFERENCES campaigns(id) ON DELETE SET NULL,
+    label VARCHAR(100),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    redeemed_at TIMESTAMP WITH TIME ZONE,
+    device_name VARCHAR(100),
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    CONSTRAINT enrollments_scope_check CHECK (scope IN ('record', 'upload'))
+);
+
+-- Create upload_sessions table for resumable uploads in progress
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    recording_id VARCHAR(36) NOT NULL,
+    lang VARCHAR(10) NOT NULL,
+    qc_metrics TEXT NOT NULL,
+    file_path VARCHAR(255) NOT NULL,
+    campaign_id VARCHAR(36),
+    transcript TEXT,
+    file_name VARCHAR(255) NOT NULL,
+    size INTEGER NOT NULL,
+    received INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create telemetry_events table for anonymous, opt-in CLI usage events
+CREATE TABLE IF NOT EXISTS telemetry_events (
+    id SERIAL PRIMARY KEY,
+    install_id VARCHAR(36) NOT NULL,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+    version VARCHAR(20) NOT NULL,
+    command VARCHAR(100) NOT NULL,
+    success BOOLEAN NOT NULL,
+    error_class VARCHAR(20),
+    duration_ms INTEGER NOT NULL,
+    device JSONB NOT NULL,
+    received_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Create quality_thresholds table for configurable QC settings
 CREATE TABLE IF NOT EXISTS quality_thresholds (
     id SERIAL PRIMARY KEY,
@@ -91,9 +154,12 @@ CREATE INDEX IF NOT EXISTS idx_tokens_user_id ON tokens(user_id);
 CREATE INDEX IF NOT EXISTS idx_tokens_type ON tokens(type);
 CREATE INDEX IF NOT EXISTS idx_tokens_created_at ON tokens(created_at);
 CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_enrollments_user_id ON enrollments(user_id);
 CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
 CREATE INDEX IF NOT EXISTS idx_upload_queue_priority ON upload_queue(priority);
 CREATE INDEX IF NOT EXISTS idx_upload_queue_created_at ON upload_queue(created_at);
+CREATE INDEX IF NOT EXISTS idx_recordings_campaign_id ON recordings(campaign_id);
+CREATE INDEX IF NOT EXISTS idx_campaigns_ends_at ON campaigns(ends_at);
 
 -- Create a GIN index for JSONB QC metrics for efficient querying
 CREATE INDEX IF NOT EXISTS idx_recordings_qc_metrics ON recordings USING GIN (qc_metrics);
@@ -6,6 +6,7 @@ from typing import Optional
 import boto3
 from fastapi import FastAPI, HTTPException, Depends, status, Request, Form, File, UploadFile
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 from fastapi.security import OAuth2PasswordBearer
 from grpclib.server import Server
 from jose import JWTError, jwt
@@ -28,7 +29,7 @@ from cowcow_pb2 import (
 from cowcow_grpc import UploadServiceBase, RewardServiceBase
 import auth
 import database
-from models import User, Recording, Token, UploadQueue
+from models import User, Recording, Token, UploadQueue, Campaign, UploadSession, TelemetryEvent
 from database import get_db
 from sqlalchemy.orm import Session
 
@@ -114,17 +115,20 @@ async def get_current_user(token: str = Depends(oauth2_scheme)):
     
     # Get user from database
     db = next(get_db())
+    if auth.enrollment_revoked(db, payload):
+        raise credentials_exception
     user = db.query(User).filter(User.username == username).first()
     if user is None:
         raise credentials_exception
     return user
 
-async def get_current_user_by_api_key_or_token(
-    api_key: Optional[str] = None,
-    token: Optional[str] = None,
-    db: Session = Depends(get_db)
-):
-    """Get current user by API key or Bearer token"""
+def authenticate(
+    api_key: Optional[str], token: Optional[str], db: Session
+) -> tuple[User, list[str]]:
+    """User behind an API key or Bearer token, with the scopes it grants.
+
+    An API key stands for the account itself and grants everything the
+    account's role allows; a token grants the scopes it was issued with."""
     credentials_exception = HTTPException(
         status_code=401,
         detail="Could not validate credentials",
@@ -135,40 +139,59 @@ async def get_current_user_by_api_key_or_token(
     if api_key:
         user = db.query(User).filter(User.api_key == api_key).first()
         if user:
-            return user
+            return user, auth.grant_scopes(user.role, [])
     
-    # Try Bearer token
+    # Try Bearer token, as issued by the auth router's login and pairing
     if token:
         try:
-            payload = jwt.decode(
-                token, settings.jwt_secret, algorithms=[settings.jwt_algorithm]
-            )
+            payload = jwt.decode(token, auth.SECRET_KEY, algorithms=[auth.ALGORITHM])
             username: str = payload.get("sub")
-            if username:
+            if username and not auth.enrollment_revoked(db, payload):
                 user = db.query(User).filter(User.username == username).first()
                 if user:
-                    return user
+                    return user, auth.token_scopes(payload)
         except JWTError:
             pass
     
     raise credentials_exception
 
-async def get_current_user_multi_auth(
-    request: Request,
+async def get_current_user_by_api_key_or_token(
+    api_key: Optional[str] = None,
+    token: Optional[str] = None,
     db: Session = Depends(get_db)
 ):
-    """Get current user by API key or Bearer token from request"""
-    # Try to get API key from X-API-Key header
+    """Get current user by API key or Bearer token"""
+    user, _ = authenticate(api_key, token, db)
+    return user
+
+def request_credentials(request: Request) -> tuple[Optional[str], Optional[str]]:
+    """API key (X-API-Key header) and Bearer token sent with a request"""
     api_key = request.headers.get("X-API-Key")
-    
-    # Try to get Bearer token from Authorization header
     auth_header = request.headers.get("Authorization")
     token = None
     if auth_header and auth_header.startswith("Bearer "):
         token = auth_header.split(" ")[1]
-    
+    return api_key, token
+
+async def get_current_user_multi_auth(
+    request: Request,
+    db: Session = Depends(get_db)
+):
+    """Get current user by API key or Bearer token from request"""
+    api_key, token = request_credentials(request)
     return await get_current_user_by_api_key_or_token(api_key, token, db)
 
+def require_scope(scope: str):
+    """Dependency authenticating like get_current_user_multi_auth, refusing
+    callers whose credentials do not grant `scope`."""
+    async def dependency(request: Request, db: Session = Depends(get_db)) -> User:
+        api_key, token = request_credentials(request)
+        user, scopes = authenticate(api_key, token, db)
+        if not auth.has_scope(scopes, scope):
+            raise auth.insufficient_scope(scope)
+        return user
+    return dependency
+
 # gRPC service implementation
 class UploadServiceImpl(UploadServiceBase):
     async def UploadChunk(self, stream):
@@ -283,67 +306,309 @@ async def upload_recording(
     lang: str = Form(...),
     qc_metrics: str = Form(...),
     file_path: str = Form(...),
-    current_user: User = Depends(get_current_user_multi_auth),
+    campaign_id: Optional[str] = Form(None),
+    transcript: Optional[str] = Form(None),
+    current_user: User = Depends(require_scope("upload")),
     db: Session = Depends(get_db)
 ):
     """Upload a recording and award tokens based on quality."""
     try:
-        # Parse QC metrics
-        metrics = json.loads(qc_metrics)
-        
-        # Save recording to database
-        recording = Recording(
-            id=recording_id,
-            user_id=current_user.id,
-            lang=lang,
-            qc_metrics=qc_metrics,
-            file_path=file_path,
-            status="completed"
+        result = stage_recording(
+            db, current_user, recording_id, lang, qc_metrics, file_path, campaign_id, transcript
         )
-        db.add(recording)
-        
-        # Calculate token reward based on QC metrics
-        base_tokens = TOKENS_PER_MINUTE  # Base reward
-        
-        # Bonus for high quality
-        snr_db = metrics.get("snr_db", 0)
-        clipping_pct = metrics.get("clipping_pct", 100)
-        vad_ratio = metrics.get("vad_ratio", 0)
-        
-        bonus_tokens = 0
-        if snr_db > 20:  # High SNR bonus
-            bonus_tokens += 2
-        if clipping_pct < 1:  # Low clipping bonus
-            bonus_tokens += 1
-        if vad_ratio > 0.3:  # Good voice activity bonus
-            bonus_tokens += 1
-        
-        total_tokens = base_tokens + bonus_tokens
-        
-        # Award tokens
-        token_record = Token(
+        db.commit()
+        return result
+    except Exception as e:
+        db.rollback()
+        raise HTTPException(status_code=400, detail=str(e))
+
+@app.post("/recordings/upload/test")
+async def test_upload(
+    recording_id: str = Form(...),
+    lang: str = Form(...),
+    qc_metrics: str = Form(...),
+    file_path: str = Form(...),
+    file: UploadFile = File(...),
+    current_user: User = Depends(require_scope("upload")),
+):
+    """Take an upload as /recordings/upload would, then discard it: nothing
+    is stored and no tokens are awarded. `cowcow init` uses it to check a
+    new device can upload."""
+    try:
+        json.loads(qc_metrics)
+    except ValueError as e:
+        raise HTTPException(status_code=400, detail=f"Invalid qc_metrics: {e}")
+    received = len(await file.read())
+    if received == 0:
+        raise HTTPException(status_code=400, detail="No audio received")
+    return {
+        "status": "success",
+        "recording_id": recording_id,
+        "tokens_awarded": 0,
+        "bytes_received": received,
+        "message": "Test upload received; nothing was stored",
+    }
+
+def stage_recording(
+    db: Session,
+    current_user: User,
+    recording_id: str,
+    lang: str,
+    qc_metrics: str,
+    file_path: str,
+    campaign_id: Optional[str],
+    transcript: Optional[str],
+):
+    """Add a received recording and its token award to the session, leaving
+    the caller to commit them."""
+    # Parse QC metrics
+    metrics = json.loads(qc_metrics)
+
+    # Campaign bonuses only apply to running campaigns in the same language
+    campaign = None
+    if campaign_id:
+        now = datetime.utcnow()
+        campaign = db.query(Campaign).filter(
+            Campaign.id == campaign_id,
+            Campaign.lang == lang,
+            Campaign.starts_at <= now,
+            Campaign.ends_at > now,
+        ).first()
+    
+    # Save recording to database
+    recording = Recording(
+        id=recording_id,
+        user_id=current_user.id,
+        lang=lang,
+        qc_metrics=qc_metrics,
+        file_path=file_path,
+        transcript=transcript,
+        status="completed",
+        campaign_id=campaign.id if campaign else None
+    )
+    db.add(recording)
+    
+    # Calculate token reward based on QC metrics
+    base_tokens = TOKENS_PER_MINUTE  # Base reward
+    
+    # Bonus for high quality
+    snr_db = metrics.get("snr_db", 0)
+    clipping_pct = metrics.get("clipping_pct", 100)
+    vad_ratio = metrics.get("vad_ratio", 0)
+    
+    bonus_tokens = 0
+    if snr_db > 20:  # High SNR bonus
+        bonus_tokens += 2
+    if clipping_pct < 1:  # Low clipping bonus
+        bonus_tokens += 1
+    if vad_ratio > 0.3:  # Good voice activity bonus
+        bonus_tokens += 1
+    
+    total_tokens = base_tokens + bonus_tokens
+    if campaign:
+        total_tokens = round(total_tokens * campaign.token_multiplier)
+    
+    # Award tokens
+    token_record = Token(
+        id=str(uuid.uuid4()),
+        user_id=current_user.id,
+        amount=total_tokens,
+        type="recording",
+        description=f"Recording upload: {lang} (SNR: {snr_db:.1f}dB, Clipping: {clipping_pct:.1f}%)",
+        recording_id=recording_id
+    )
+    db.add(token_record)
+    
+    return upload_result(recording_id, total_tokens)
+
+def upload_result(recording_id: str, total_tokens: int) -> dict:
+    return {
+        "status": "success",
+        "recording_id": recording_id,
+        "tokens_awarded": total_tokens,
+        "message": f"Recording uploaded successfully! Earned {total_tokens} tokens."
+    }
+
+class ResumableStart(BaseModel):
+    recording_id: str
+    lang: str
+    qc_metrics: str
+    file_path: str
+    campaign_id: Optional[str] = None
+    transcript: Optional[str] = None
+    file_name: str
+    content_type: Optional[str] = None
+    size: int
+
+def part_path(upload_id: str) -> str:
+    return os.path.join(UPLOAD_DIR, f"{upload_id}.part")
+
+def require_uuid(recording_id: str) -> str:
+    """Recording IDs are UUIDs; anything else could name a path."""
+    try:
+        uuid.UUID(recording_id)
+    except ValueError:
+        raise HTTPException(status_code=400, detail="Recording ID must be a UUID")
+    return recording_id
+
+def stored_upload_path(session: UploadSession) -> str:
+    """Where a completed upload's file is kept, refusing any that would land
+    outside UPLOAD_DIR."""
+    require_uuid(session.recording_id)
+    upload_dir = os.path.realpath(UPLOAD_DIR)
+    target = os.path.realpath(
+        os.path.join(upload_dir, f"{session.recording_id}-{session.file_name}")
+    )
+    if os.path.dirname(target) != upload_dir:
+        raise HTTPException(status_code=400, detail="Invalid file name")
+    return target
+
+def find_upload_session(db: Session, upload_id: str, user: User) -> UploadSession:
+    session = db.query(UploadSession).filter(
+        UploadSession.id == upload_id,
+        UploadSession.user_id == user.id,
+    ).first()
+    if session is None:
+        raise HTTPException(status_code=404, detail="Upload not found")
+    return session
+
+@app.post("/recordings/uploads")
+async def start_resumable_upload(
+    start: ResumableStart,
+    current_user: User = Depends(require_scope("upload")),
+    db: Session = Depends(get_db)
+):
+    """Open a resumable upload, or report how far the open one for the
+    same recording got."""
+    if start.size < 0:
+        raise HTTPException(status_code=400, detail="Size must not be negative")
+    require_uuid(start.recording_id)
+    session = db.query(UploadSession).filter(
+        UploadSession.user_id == current_user.id,
+        UploadSession.recording_id == start.recording_id,
+    ).first()
+    # A retry of an upload that was stored but whose response was lost has
+    # nothing left to send: completing it returns the stored result
+    stored = db.query(Recording).filter(
+        Recording.id == start.recording_id,
+        Recording.user_id == current_user.id,
+    ).first()
+    if session is None:
+        session = UploadSession(
             id=str(uuid.uuid4()),
             user_id=current_user.id,
-            amount=total_tokens,
-            type="recording",
-            description=f"Recording upload: {lang} (SNR: {snr_db:.1f}dB, Clipping: {clipping_pct:.1f}%)",
-            recording_id=recording_id
+            recording_id=start.recording_id,
+            lang=start.lang,
+            qc_metrics=start.qc_metrics,
+            file_path=start.file_path,
+            campaign_id=start.campaign_id,
+            transcript=start.transcript,
+            file_name=os.path.basename(start.file_name),
+            size=start.size,
+            received=start.size if stored else 0,
         )
-        db.add(token_record)
-        
+        db.add(session)
         db.commit()
-        
-        return {
-            "status": "success",
-            "recording_id": recording_id,
-            "tokens_awarded": total_tokens,
-            "message": f"Recording uploaded successfully! Earned {total_tokens} tokens."
-        }
-        
+        if not stored:
+            open(part_path(session.id), "wb").close()
+    return {"upload_id": session.id, "offset": session.received}
+
+@app.put("/recordings/uploads/{upload_id}")
+async def upload_chunk(
+    upload_id: str,
+    request: Request,
+    current_user: User = Depends(require_scope("upload")),
+    db: Session = Depends(get_db)
+):
+    """Append a chunk sent at the offset the upload has reached; a chunk
+    sent at any other offset gets 409 with the offset expected."""
+    session = find_upload_session(db, upload_id, current_user)
+    try:
+        offset = int(request.headers.get("Upload-Offset", ""))
+    except ValueError:
+        raise HTTPException(status_code=400, detail="Missing or invalid Upload-Offset")
+    if offset != session.received:
+        return JSONResponse(status_code=409, content={"offset": session.received})
+
+    chunk = await request.body()
+    if session.received + len(chunk) > session.size:
+        raise HTTPException(status_code=400, detail="Chunk runs past the declared size")
+    with open(part_path(upload_id), "r+b") as part:
+        # Drop whatever a failed write left past the recorded offset
+        part.truncate(session.received)
+        part.seek(session.received)
+        part.write(chunk)
+    session.received += len(chunk)
+    db.commit()
+    return {"offset": session.received}
+
+@app.post("/recordings/uploads/{upload_id}/complete")
+async def complete_resumable_upload(
+    upload_id: str,
+    current_user: User = Depends(require_scope("upload")),
+    db: Session = Depends(get_db)
+):
+    """Take a fully received resumable upload as a recording.
+
+    The file is moved into place before the recording and its tokens are
+    committed, and moved back if the commit fails, so a failure leaves the
+    upload open to complete again. Completing an upload whose recording is
+    already stored returns the same result again.
+    """
+    session = find_upload_session(db, upload_id, current_user)
+    if session.received < session.size:
+        raise HTTPException(status_code=409, detail="Upload is incomplete")
+    target = stored_upload_path(session)
+
+    existing = db.query(Recording).filter(Recording.id == session.recording_id).first()
+    if existing is not None:
+        if existing.user_id != current_user.id:
+            raise HTTPException(status_code=400, detail="Recording ID already in use")
+        # Stored by an earlier attempt whose response was lost
+        if os.path.exists(part_path(upload_id)):
+            os.remove(part_path(upload_id))
+        db.delete(session)
+        db.commit()
+        return stored_result(db, existing)
+
+    try:
+        result = stage_recording(
+            db,
+            current_user,
+            session.recording_id,
+            session.lang,
+            session.qc_metrics,
+            session.file_path,
+            session.campaign_id,
+            session.transcript,
+        )
+        db.delete(session)
+        db.flush()
     except Exception as e:
         db.rollback()
         raise HTTPException(status_code=400, detail=str(e))
 
+    try:
+        os.replace(part_path(upload_id), target)
+    except OSError:
+        db.rollback()
+        raise HTTPException(status_code=500, detail="Failed to store the upload")
+    try:
+        db.commit()
+    except Exception:
+        db.rollback()
+        os.replace(target, part_path(upload_id))
+        raise
+    return result
+
+def stored_result(db: Session, recording: Recording) -> dict:
+    """What completing the upload of an already stored recording returned."""
+    awarded = db.query(Token).filter(
+        Token.recording_id == recording.id,
+        Token.type == "recording",
+    ).first()
+    return upload_result(recording.id, awarded.amount if awarded else 0)
+
 @app.get("/recordings")
 async def list_recordings(
     current_user: User = Depends(get_current_user_multi_auth),
@@ -400,7 +665,8 @@ async def get_token_history(
             "amount": token.amount,
             "balance": 0,  # Will be calculated below
             "date": token.created_at.isoformat(),
-            "notes": token.description or f"{token.type} transaction"
+            "notes": token.description or f"{token.type} transaction",
+            "recording_id": token.recording_id,
         })
     
     # Calculate running balance for each transaction
@@ -414,6 +680,82 @@ async def get_token_history(
     
     return transactions
 
+@app.get("/campaigns")
+async def list_campaigns(
+    current_user: User = Depends(get_current_user_multi_auth),
+    db: Session = Depends(get_db)
+):
+    """List running and upcoming campaigns with the hours collected so far."""
+    now = datetime.utcnow()
+    campaigns = db.query(Campaign).filter(
+        Campaign.ends_at > now
+    ).order_by(Campaign.ends_at).all()
+
+    # Progress of all the campaigns in one query; durations live in the QC
+    # metrics JSON, so they are summed here
+    collected_secs = {campaign.id: 0.0 for campaign in campaigns}
+    if campaigns:
+        rows = db.query(Recording.campaign_id, Recording.qc_metrics).filter(
+            Recording.campaign_id.in_(list(collected_secs))
+        ).all()
+        for campaign_id, qc_metrics in rows:
+            collected_secs[campaign_id] += json.loads(qc_metrics).get("duration_secs", 0)
+
+    result = []
+    for campaign in campaigns:
+        result.append({
+            "id": campaign.id,
+            "name": campaign.name,
+            "lang": campaign.lang,
+            "starts_at": campaign.starts_at.strftime("%Y-%m-%dT%H:%M:%SZ"),
+            "ends_at": campaign.ends_at.strftime("%Y-%m-%dT%H:%M:%SZ"),
+            "target_hours": campaign.target_hours,
+            "collected_hours": collected_secs[campaign.id] / 3600,
+            "token_multiplier": campaign.token_multiplier,
+        })
+
+    return result
+
+class TelemetryDevice(BaseModel):
+    os: str
+    arch: str
+    cpus: int
+    sample_rate: int
+    channels: int
+
+class TelemetryEventIn(BaseModel):
+    install_id: str
+    timestamp: int
+    version: str
+    command: str
+    success: bool
+    error_class: Optional[str] = None
+    duration_ms: int
+    device: TelemetryDevice
+
+@app.post("/telemetry", status_code=202)
+async def collect_telemetry(
+    events: list[TelemetryEventIn],
+    db: Session = Depends(get_db)
+):
+    """Store anonymous usage events from CLIs that opted in; no
+    credentials are sent with them."""
+    if len(events) > 10000:
+        raise HTTPException(status_code=413, detail="Too many events in one batch")
+    for event in events:
+        db.add(TelemetryEvent(
+            install_id=event.install_id[:36],
+            timestamp=datetime.utcfromtimestamp(event.timestamp),
+            version=event.version[:20],
+            command=event.command[:100],
+            success=event.success,
+            error_class=event.error_class[:20] if event.error_class else None,
+            duration_ms=event.duration_ms,
+            device=event.device.json(),
+        ))
+    db.commit()
+    return {"received": len(events)}
+
 @app.get("/health")
 async def health_check():
     """Health check endpoint."""
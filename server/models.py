@@ -1,6 +1,6 @@
 from datetime import datetime
 from typing import Optional
-from sqlalchemy import Column, Integer, String, DateTime, Boolean, ForeignKey, Text
+from sqlalchemy import Column, Integer, String, DateTime, Boolean, ForeignKey, Text, Float
 from sqlalchemy.ext.declarative import declarative_base
 from sqlalchemy.orm import relationship
 import bcrypt
@@ -39,14 +39,28 @@ class Recording(Base):
     user_id = Column(Integer, ForeignKey('users.id'), nullable=False)
     lang = Column(String(10), nullable=False)
     prompt = Column(Text)
+    transcript = Column(Text)  # what was actually said, when it differs from the prompt
     qc_metrics = Column(Text, nullable=False)
     file_path = Column(String(255), nullable=False)
     created_at = Column(DateTime, default=datetime.utcnow)
     uploaded_at = Column(DateTime)
     status = Column(String(20), default='pending')  # pending, processing, completed, failed
+    campaign_id = Column(String(36), ForeignKey('campaigns.id'))
 
     user = relationship("User", back_populates="recordings")
 
+class Campaign(Base):
+    __tablename__ = 'campaigns'
+
+    id = Column(String(36), primary_key=True)
+    name = Column(String(100), nullable=False)
+    lang = Column(String(10), nullable=False)
+    starts_at = Column(DateTime, nullable=False)
+    ends_at = Column(DateTime, nullable=False)
+    target_hours = Column(Float, nullable=False)
+    token_multiplier = Column(Float, nullable=False, default=1.0)
+    created_at = Column(DateTime, default=datetime.utcnow)
+
 class Token(Base):
     __tablename__ = 'tokens'
 
@@ -61,6 +75,55 @@ class Token(Base):
     user = relationship("User", back_populates="tokens")
     recording = relationship("Recording")
 
+class Enrollment(Base):
+    __tablename__ = 'enrollments'
+
+    id = Column(String(36), primary_key=True)
+    user_id = Column(Integer, ForeignKey('users.id'), nullable=False)
+    token_hash = Column(String(64), unique=True, nullable=False)  # sha256 of the single-use token
+    scope = Column(String(20), nullable=False)  # record, upload
+    campaign_id = Column(String(36), ForeignKey('campaigns.id'))
+    label = Column(String(100))
+    created_at = Column(DateTime, default=datetime.utcnow)
+    expires_at = Column(DateTime, nullable=False)
+    redeemed_at = Column(DateTime)
+    device_name = Column(String(100))
+    revoked_at = Column(DateTime)
+
+    user = relationship("User")
+
+class UploadSession(Base):
+    __tablename__ = 'upload_sessions'
+
+    id = Column(String(36), primary_key=True)
+    user_id = Column(Integer, ForeignKey('users.id'), nullable=False)
+    recording_id = Column(String(36), nullable=False)
+    lang = Column(String(10), nullable=False)
+    qc_metrics = Column(Text, nullable=False)
+    file_path = Column(String(255), nullable=False)
+    campaign_id = Column(String(36))
+    transcript = Column(Text)
+    file_name = Column(String(255), nullable=False)
+    size = Column(Integer, nullable=False)  # bytes the client will send
+    received = Column(Integer, nullable=False, default=0)  # bytes stored in UPLOAD_DIR/<id>.part
+    created_at = Column(DateTime, default=datetime.utcnow)
+
+    user = relationship("User")
+
+class TelemetryEvent(Base):
+    __tablename__ = 'telemetry_events'
+
+    id = Column(Integer, primary_key=True, autoincrement=True)
+    install_id = Column(String(36), nullable=False)  # random per opt-in, not tied to a user
+    timestamp = Column(DateTime, nullable=False)
+    version = Column(String(20), nullable=False)
+    command = Column(String(100), nullable=False)
+    success = Column(Boolean, nullable=False)
+    error_class = Column(String(20))
+    duration_ms = Column(Integer, nullable=False)
+    device = Column(Text, nullable=False)  # JSON: os, arch, cpus, sample_rate, channels
+    received_at = Column(DateTime, default=datetime.utcnow)
+
 class UploadQueue(Base):
     __tablename__ = 'upload_queue'
 
@@ -0,0 +1,59 @@
+"""Scope enforcement on the upload endpoints.
+
+Run from this directory with `python -m pytest`.
+"""
+import json
+
+from conftest import client, login
+
+def upload(recording_id: str, headers: dict):
+    return client.post(
+        "/recordings/upload",
+        data={
+            "recording_id": recording_id,
+            "lang": "sw",
+            "qc_metrics": json.dumps({"snr_db": 30.0}),
+            "file_path": f"{recording_id}.wav",
+        },
+        headers=headers,
+    )
+
+def test_record_scoped_token_cannot_upload():
+    login_response = login("record")
+    assert login_response["scope"] == "record"
+    # Nor does it come with the account's unrestricted API key
+    assert login_response["api_key"] is None
+    headers = {"Authorization": f"Bearer {login_response['access_token']}"}
+
+    response = upload("rec-record", headers)
+    assert response.status_code == 403
+    assert "upload scope" in response.json()["detail"]
+
+    response = client.post(
+        "/recordings/uploads",
+        json={
+            "recording_id": "rec-record",
+            "lang": "sw",
+            "qc_metrics": "{}",
+            "file_path": "rec-record.wav",
+            "file_name": "rec-record.wav",
+            "size": 4,
+        },
+        headers=headers,
+    )
+    assert response.status_code == 403
+
+def test_upload_scoped_token_can_upload():
+    login_response = login("upload")
+    headers = {"Authorization": f"Bearer {login_response['access_token']}"}
+
+    assert upload("rec-upload", headers).status_code == 200
+
+def test_record_scoped_token_cannot_pair_an_uploader():
+    login_response = login("record")
+    response = client.post(
+        "/auth/enrollments",
+        json={"scope": "upload"},
+        headers={"Authorization": f"Bearer {login_response['access_token']}"},
+    )
+    assert response.status_code == 403
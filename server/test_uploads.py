@@ -0,0 +1,116 @@
+"""Resumable uploads: where their files land and completing them again;
+and test uploads, which keep nothing.
+
+Run from this directory with `python -m pytest`.
+"""
+import json
+import os
+import uuid
+
+import database
+import main
+from conftest import client, login
+from models import Recording
+
+AUDIO = b"RIFF" + bytes(60)
+
+def headers() -> dict:
+    return {"Authorization": f"Bearer {login('upload')['access_token']}"}
+
+def start(recording_id: str, file_name: str = "take.wav"):
+    return client.post(
+        "/recordings/uploads",
+        json={
+            "recording_id": recording_id,
+            "lang": "sw",
+            "qc_metrics": json.dumps({"snr_db": 30.0}),
+            "file_path": file_name,
+            "file_name": file_name,
+            "size": len(AUDIO),
+        },
+        headers=headers(),
+    )
+
+def send(upload_id: str, offset: int = 0):
+    response = client.put(
+        f"/recordings/uploads/{upload_id}",
+        content=AUDIO[offset:],
+        headers={**headers(), "Upload-Offset": str(offset)},
+    )
+    assert response.status_code == 200, response.text
+
+def complete(upload_id: str):
+    return client.post(f"/recordings/uploads/{upload_id}/complete", headers=headers())
+
+def balance() -> int:
+    return client.get("/tokens/balance", headers=headers()).json()["balance"]
+
+def test_recording_ids_that_are_not_uuids_are_refused():
+    response = start("../../escaped")
+    assert response.status_code == 400
+    assert "UUID" in response.json()["detail"]
+
+def test_completed_upload_lands_in_the_upload_dir():
+    recording_id = str(uuid.uuid4())
+    upload_id = start(recording_id, "../take.wav").json()["upload_id"]
+    send(upload_id)
+    assert complete(upload_id).status_code == 200
+    stored = os.path.join(main.UPLOAD_DIR, f"{recording_id}-take.wav")
+    with open(stored, "rb") as f:
+        assert f.read() == AUDIO
+
+def test_failed_move_awards_nothing_and_can_be_completed_again(monkeypatch):
+    recording_id = str(uuid.uuid4())
+    upload_id = start(recording_id).json()["upload_id"]
+    send(upload_id)
+    before = balance()
+
+    def fail(src, dst):
+        raise OSError("disk full")
+
+    with monkeypatch.context() as patched:
+        patched.setattr(main.os, "replace", fail)
+        assert complete(upload_id).status_code == 500
+    assert balance() == before
+
+    response = complete(upload_id)
+    assert response.status_code == 200, response.text
+    assert balance() == before + response.json()["tokens_awarded"]
+
+def test_uploading_a_stored_recording_again_returns_the_stored_result():
+    recording_id = str(uuid.uuid4())
+    upload_id = start(recording_id).json()["upload_id"]
+    send(upload_id)
+    first = complete(upload_id).json()
+    after_first = balance()
+
+    # The response was lost: the client starts over
+    retry = start(recording_id).json()
+    assert retry["offset"] == len(AUDIO)
+    second = complete(retry["upload_id"])
+    assert second.status_code == 200, second.text
+    assert second.json()["tokens_awarded"] == first["tokens_awarded"]
+    assert balance() == after_first
+
+def test_test_upload_keeps_nothing():
+    recording_id = str(uuid.uuid4())
+    before = balance()
+    response = client.post(
+        "/recordings/upload/test",
+        data={
+            "recording_id": recording_id,
+            "lang": "sw",
+            "qc_metrics": json.dumps({"snr_db": 30.0}),
+            "file_path": "init-test.wav",
+        },
+        files={"file": ("init-test.wav", AUDIO, "audio/wav")},
+        headers=headers(),
+    )
+    assert response.status_code == 200, response.text
+    assert response.json()["bytes_received"] == len(AUDIO)
+    assert balance() == before
+    db = database.SessionLocal()
+    try:
+        assert db.query(Recording).filter(Recording.id == recording_id).first() is None
+    finally:
+        db.close()